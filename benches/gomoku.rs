@@ -0,0 +1,47 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pytorch::{alpha_zero::Game, tictactoe::BoardState};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+// A position from the middle of a random game
+fn midgame(plies: usize) -> BoardState {
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut board = BoardState::new();
+    for _ in 0..plies {
+        let moves = board.get_state().get_moves().unwrap();
+        let next = board.make_move(moves.choose(&mut rng).unwrap());
+        if next.get_state().get_moves().is_none() {
+            continue;
+        }
+        board = next;
+    }
+    board
+}
+
+fn win_detection(c: &mut Criterion) {
+    let board = midgame(60);
+    let moves = board.get_state().get_moves().unwrap();
+    let m = moves[moves.len() / 2];
+
+    c.bench_function("make_move", |b| {
+        b.iter(|| black_box(&board).make_move(black_box(&m)))
+    });
+    c.bench_function("is_win after make_move", |b| {
+        let next = board.make_move(&m);
+        b.iter(|| black_box(&next).is_win())
+    });
+    c.bench_function("scan_win", |b| b.iter(|| black_box(&board).scan_win()));
+    c.bench_function("get_state", |b| b.iter(|| black_box(&board).get_state()));
+    c.bench_function("random game", |b| {
+        let mut rng = StdRng::seed_from_u64(11);
+        b.iter(|| {
+            let mut board = BoardState::new();
+            while let Some(moves) = board.get_state().get_moves() {
+                board = board.make_move(moves.choose(&mut rng).unwrap());
+            }
+            board
+        })
+    });
+}
+
+criterion_group!(benches, win_detection);
+criterion_main!(benches);
@@ -1,23 +1,139 @@
+mod action_encoding;
+#[cfg(feature = "torch")]
+mod adam;
+mod agent;
+#[cfg(feature = "torch")]
 mod alpha_zero_adapter;
+#[cfg(feature = "torch")]
 mod alpha_zero_net;
+#[cfg(feature = "torch")]
+mod amp;
+mod analysis;
+mod arena;
+mod baseline;
 mod battle;
+#[cfg(feature = "torch")]
+mod checkpoint;
+#[cfg(feature = "torch")]
+mod data_loader;
+mod data_report;
+mod device;
+mod early_stopping;
+mod evaluator;
+#[cfg(feature = "torch")]
 mod executor_scope;
 mod game;
+mod gating;
 mod generate_game;
+mod gtp;
+#[cfg(feature = "torch")]
+mod head_to_head;
+mod heuristic;
+mod inference_backend;
+mod interactive;
+#[cfg(feature = "torch")]
 mod l2_norm;
+#[cfg(feature = "torch")]
+mod loss;
+mod lr_schedule;
+mod match_stats;
 mod mcts;
+#[cfg(feature = "torch")]
+mod model_export;
+#[cfg(feature = "torch")]
 mod network_batched_executor;
+mod opening_book;
+mod perfect_play;
+mod portable_game;
+mod position_suite;
+mod profile;
+mod rating;
+mod replay_buffer;
+#[cfg(feature = "torch")]
+mod resnet;
+mod seed;
+mod sgf;
+mod significance;
+mod sprt;
+#[cfg(feature = "torch")]
+mod symmetry;
+mod time_control;
 mod timer;
+#[cfg(feature = "torch")]
+mod trainer;
+mod uci;
 mod util;
+mod value_plot;
+mod visualizer;
+#[cfg(feature = "torch")]
+mod weight_import;
 
+pub use action_encoding::*;
+#[cfg(feature = "torch")]
+pub use adam::*;
+pub use agent::*;
+#[cfg(feature = "torch")]
 pub use alpha_zero_adapter::*;
+#[cfg(feature = "torch")]
 pub use alpha_zero_net::*;
+#[cfg(feature = "torch")]
+pub use amp::*;
+pub use analysis::*;
+pub use arena::*;
+pub use baseline::*;
 pub use battle::*;
+#[cfg(feature = "torch")]
+pub use checkpoint::*;
+#[cfg(feature = "torch")]
+pub use data_loader::*;
+pub use data_report::*;
+pub use device::*;
+pub use early_stopping::*;
+pub use evaluator::*;
+#[cfg(feature = "torch")]
 pub use executor_scope::*;
 pub use game::*;
+pub use gating::*;
 pub use generate_game::*;
+pub use gtp::*;
+#[cfg(feature = "torch")]
+pub use head_to_head::*;
+pub use heuristic::*;
+pub use inference_backend::*;
+pub use interactive::*;
+#[cfg(feature = "torch")]
 pub use l2_norm::*;
+#[cfg(feature = "torch")]
+pub use loss::*;
+pub use lr_schedule::*;
+pub use match_stats::*;
 pub use mcts::*;
+#[cfg(feature = "torch")]
+pub use model_export::*;
+#[cfg(feature = "torch")]
 pub use network_batched_executor::*;
+pub use opening_book::*;
+pub use perfect_play::*;
+pub use portable_game::*;
+pub use position_suite::*;
+pub use profile::*;
+pub use rating::*;
+pub use replay_buffer::*;
+#[cfg(feature = "torch")]
+pub use resnet::*;
+pub use seed::*;
+pub use sgf::*;
+pub use significance::*;
+pub use sprt::*;
+#[cfg(feature = "torch")]
+pub use symmetry::*;
+pub use time_control::*;
 pub use timer::*;
+#[cfg(feature = "torch")]
+pub use trainer::*;
+pub use uci::*;
 pub use util::*;
+pub use value_plot::*;
+pub use visualizer::*;
+#[cfg(feature = "torch")]
+pub use weight_import::*;
@@ -0,0 +1,52 @@
+#[cfg(feature = "torch")]
+use tch::Tensor;
+
+use super::Game;
+
+// Maps moves onto a fixed-size flat action space, which is what the policy head predicts.
+// Adapters implementing it get `decode_policy`/`encode_policy` for free and can forward
+// `get_estimated_policy`/`convert_policy_to_nn` to them.
+pub trait ActionEncoding<TGame: Game> {
+    fn action_space_size() -> usize;
+    fn move_to_index(m: &TGame::Move) -> usize;
+    fn index_to_move(index: usize) -> TGame::Move;
+
+    // Shape of a single policy tensor as produced by the network
+    fn policy_shape() -> Vec<i64> {
+        vec![Self::action_space_size() as i64]
+    }
+
+    // Converts the network's log-policy into probabilities of the given moves, normalized to 1
+    #[cfg(feature = "torch")]
+    fn decode_policy(policy: &Tensor, moves: &[TGame::Move]) -> Vec<f32> {
+        Self::decode_log_policy(&<Vec<f32>>::try_from(policy.view([-1])).unwrap(), moves)
+    }
+
+    // `decode_policy` of a flat log-policy, as nets run outside of tch give it
+    fn decode_log_policy(policy: &[f32], moves: &[TGame::Move]) -> Vec<f32> {
+        assert_eq!(policy.len(), Self::action_space_size());
+
+        let mut res = moves
+            .iter()
+            .map(|m| policy[Self::move_to_index(m)].exp())
+            .collect::<Vec<_>>();
+
+        let sum = res.iter().sum::<f32>();
+        if sum > 0. {
+            for x in &mut res {
+                *x /= sum;
+            }
+        }
+        res
+    }
+
+    // Scatters per-move probabilities into a full action-space tensor
+    #[cfg(feature = "torch")]
+    fn encode_policy(policy: &[f32], moves: &[TGame::Move]) -> Tensor {
+        let mut res = vec![0f32; Self::action_space_size()];
+        for (m, &pol) in moves.iter().zip(policy) {
+            res[Self::move_to_index(m)] = pol;
+        }
+        Tensor::from_slice(&res).view(Self::policy_shape().as_slice())
+    }
+}
@@ -0,0 +1,198 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Context;
+use tch::{nn, Kind, Tensor};
+
+const BETA1: f64 = 0.9;
+const BETA2: f64 = 0.999;
+const EPSILON: f64 = 1e-8;
+
+// A trainable variable with its moment estimates
+struct Moments {
+    name: String,
+    variable: Tensor,
+    // Moving averages of the gradient and of its square
+    first: Tensor,
+    second: Tensor,
+}
+
+// Adam like `nn::Adam::default()`, but with its state in tensors of its own, which tch's
+// optimizers don't expose. Saved with the checkpoints, so a resumed run keeps its estimates
+// instead of starting over with a burst of large steps.
+pub struct Adam {
+    // By name, for the saved state to match up
+    moments: Vec<Moments>,
+    steps: i32,
+    lr: f64,
+}
+
+impl Adam {
+    // Variables created under `vs` later on are picked up by the next step
+    pub fn new(lr: f64) -> Self {
+        Self {
+            moments: vec![],
+            steps: 0,
+            lr,
+        }
+    }
+
+    pub fn set_lr(&mut self, lr: f64) {
+        self.lr = lr;
+    }
+
+    fn track(&mut self, vs: &nn::VarStore) {
+        if self.moments.len() == vs.trainable_variables().len() {
+            return;
+        }
+        let mut variables = vs.variables();
+        variables.retain(|name, variable| {
+            variable.requires_grad() && self.moments.iter().all(|m| m.name != *name)
+        });
+        for (name, variable) in variables {
+            self.moments.push(Moments {
+                name,
+                first: variable.zeros_like(),
+                second: variable.zeros_like(),
+                variable,
+            });
+        }
+        self.moments.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    pub fn zero_grad(&self, vs: &nn::VarStore) {
+        for mut variable in vs.trainable_variables() {
+            variable.zero_grad();
+        }
+    }
+
+    // Scales the gradients down to a global norm of at most `max_norm`
+    pub fn clip_grad_norm(&self, vs: &nn::VarStore, max_norm: f64) {
+        let grads = vs
+            .trainable_variables()
+            .iter()
+            .map(Tensor::grad)
+            .filter(Tensor::defined)
+            .collect::<Vec<_>>();
+        tch::no_grad(|| {
+            let norm = grads
+                .iter()
+                .map(|grad| f64::try_from(grad.square().sum(Kind::Float)).unwrap())
+                .sum::<f64>()
+                .sqrt();
+            let scale = max_norm / (norm + 1e-6);
+            if scale < 1.0 {
+                for mut grad in grads {
+                    grad *= scale;
+                }
+            }
+        });
+    }
+
+    pub fn step(&mut self, vs: &nn::VarStore) {
+        self.track(vs);
+        self.steps += 1;
+        let first_correction = 1.0 - BETA1.powi(self.steps);
+        let second_correction = 1.0 - BETA2.powi(self.steps);
+        tch::no_grad(|| {
+            for moments in &mut self.moments {
+                let grad = moments.variable.grad();
+                if !grad.defined() {
+                    continue;
+                }
+                moments.first *= BETA1;
+                moments.first += &grad * (1.0 - BETA1);
+                moments.second *= BETA2;
+                moments.second += grad.square() * (1.0 - BETA2);
+                let denominator = (&moments.second / second_correction).sqrt() + EPSILON;
+                moments.variable -= &moments.first / first_correction / denominator * self.lr;
+            }
+        });
+    }
+
+    pub fn backward_step(&mut self, vs: &nn::VarStore, loss: &Tensor) {
+        self.zero_grad(vs);
+        loss.backward();
+        self.step(vs);
+    }
+
+    pub fn backward_step_clip_norm(&mut self, vs: &nn::VarStore, loss: &Tensor, max_norm: f64) {
+        self.zero_grad(vs);
+        loss.backward();
+        self.clip_grad_norm(vs, max_norm);
+        self.step(vs);
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let steps = Tensor::from_slice(&[self.steps]);
+        let mut tensors = vec![("steps".to_owned(), &steps)];
+        for moments in &self.moments {
+            tensors.push((format!("first.{}", moments.name), &moments.first));
+            tensors.push((format!("second.{}", moments.name), &moments.second));
+        }
+        Tensor::write_safetensors(&tensors, path)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    // Into the optimizer of the same variables
+    pub fn load(&mut self, vs: &nn::VarStore, path: &Path) -> anyhow::Result<()> {
+        self.track(vs);
+        let mut tensors = Tensor::read_safetensors(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        let mut take = |name: String| {
+            tensors
+                .remove(&name)
+                .with_context(|| format!("{} has no {name}", path.display()))
+        };
+        let steps = i32::try_from(&take("steps".to_owned())?)?;
+        for moments in &mut self.moments {
+            let first = take(format!("first.{}", moments.name))?;
+            let second = take(format!("second.{}", moments.name))?;
+            tch::no_grad(|| {
+                moments.first.copy_(&first);
+                moments.second.copy_(&second);
+            });
+        }
+        self.steps = steps;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tch::{nn, Device, Kind, Tensor};
+
+    use super::Adam;
+
+    #[test]
+    fn state_survives_saving() {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let w = vs.root().zeros("w", &[2]);
+        tch::no_grad(|| {
+            w.shallow_clone()
+                .copy_(&Tensor::from_slice(&[1.0f32, -2.0]))
+        });
+        let loss = || (&w * &w).sum(Kind::Float);
+
+        // The first step moves every weight by the learning rate, against its gradient
+        let mut adam = Adam::new(0.1);
+        adam.backward_step(&vs, &loss());
+        let after = Vec::<f32>::try_from(&w).unwrap();
+        assert!((after[0] - 0.9).abs() < 1e-5 && (after[1] + 1.9).abs() < 1e-5);
+
+        let path = std::env::temp_dir().join(format!("adam-{}.safetensors", std::process::id()));
+        adam.save(&path).unwrap();
+        let mut resumed = Adam::new(0.1);
+        resumed.load(&vs, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Both take the same second step from the same weights
+        let snapshot = w.copy();
+        adam.backward_step(&vs, &loss());
+        let expected = Vec::<f32>::try_from(&w).unwrap();
+        tch::no_grad(|| w.shallow_clone().copy_(&snapshot));
+        resumed.backward_step(&vs, &loss());
+        assert_eq!(Vec::<f32>::try_from(&w).unwrap(), expected);
+    }
+}
@@ -0,0 +1,172 @@
+use std::{future::Future, time::Instant};
+
+use rand::{seq::SliceRandom, thread_rng, Rng};
+use serde::Serialize;
+
+use super::{
+    argmax, sample_policy, Clock, Evaluator, Game, MonteCarloTree, Perspective, TerminationState,
+    TimeControl,
+};
+
+// A player choosing moves, so evaluation code can mix and match opponents
+pub trait Agent<TGame: Game> {
+    // Index of the chosen move in `state.get_state()`'s move list. `state` must not be terminal.
+    fn select_move(&mut self, state: &TGame) -> impl Future<Output = usize>;
+
+    // Of the last `select_move`, for agents that search
+    fn last_search(&self) -> Option<&SearchInfo> {
+        None
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SearchInfo {
+    // Of the player to move
+    pub root_value: f32,
+    // Share of the root's visits per move
+    pub visits: Vec<f32>,
+}
+
+fn count_moves<TGame: Game>(state: &TGame) -> usize {
+    state
+        .get_state()
+        .get_moves()
+        .expect("Asked to move in a terminal state")
+        .len()
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct RandomAgent;
+
+impl<TGame: Game> Agent<TGame> for RandomAgent {
+    async fn select_move(&mut self, state: &TGame) -> usize {
+        thread_rng().gen_range(0..count_moves(state))
+    }
+}
+
+// Plays the move with the highest prior, without any search
+pub struct RawPolicyAgent<TEval> {
+    evaluator: TEval,
+}
+
+impl<TEval> RawPolicyAgent<TEval> {
+    pub fn new(evaluator: TEval) -> Self {
+        Self { evaluator }
+    }
+}
+
+impl<TGame: Game, TEval: Evaluator<TGame>> Agent<TGame> for RawPolicyAgent<TEval> {
+    async fn select_move(&mut self, state: &TGame) -> usize {
+        let moves = state
+            .get_state()
+            .get_moves()
+            .expect("Asked to move in a terminal state");
+        let (_, policy) = self.evaluator.evaluate(state, &moves).await;
+        argmax(&policy)
+    }
+}
+
+// Looks a single move ahead, playing the one leading to the best position for it. Terminal
+// positions are valued by their result, the others by the heuristic, or as draws without one.
+// Ties are broken at random, so a game without a heuristic is played randomly unless there is
+// a win.
+pub struct GreedyAgent<TGame> {
+    heuristic: Option<fn(&TGame) -> f32>,
+}
+
+impl<TGame> GreedyAgent<TGame> {
+    // The heuristic is the value of the player to move, like `HeuristicEval::eval`
+    pub fn new(heuristic: Option<fn(&TGame) -> f32>) -> Self {
+        Self { heuristic }
+    }
+}
+
+impl<TGame: Game> Agent<TGame> for GreedyAgent<TGame> {
+    async fn select_move(&mut self, state: &TGame) -> usize {
+        let moves = state
+            .get_state()
+            .get_moves()
+            .expect("Asked to move in a terminal state");
+        let values = moves
+            .iter()
+            .map(|m| {
+                let next = state.make_move(m);
+                let value = match next.get_state() {
+                    TerminationState::Terminal(value) => value,
+                    TerminationState::Moves(_) => self.heuristic.map_or(0.5, |eval| eval(&next)),
+                };
+                Perspective::after_move(m).convert(value)
+            })
+            .collect::<Vec<_>>();
+
+        let best = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let best_moves = (0..moves.len())
+            .filter(|&i| values[i] >= best - 1e-6)
+            .collect::<Vec<_>>();
+        *best_moves.choose(&mut thread_rng()).unwrap()
+    }
+}
+
+// Runs a fresh search from every position it is asked about. Temperature 0 plays
+// the most visited move, otherwise the visit distribution is sampled.
+pub struct MctsAgent<TEval> {
+    evaluator: Option<TEval>,
+    samples: usize,
+    c_puct: f32,
+    temp: f32,
+    // One per game, fresh agents being made for every game
+    clock: Option<Clock>,
+    last_search: Option<SearchInfo>,
+}
+
+impl<TEval> MctsAgent<TEval> {
+    pub fn new(evaluator: TEval, samples: usize, c_puct: f32, temp: f32) -> Self {
+        Self {
+            evaluator: Some(evaluator),
+            samples,
+            c_puct,
+            temp,
+            clock: None,
+            last_search: None,
+        }
+    }
+
+    // Searching until the clock's budget for the move runs out, `samples` still being the most
+    pub fn with_time_control(mut self, time_control: Option<TimeControl>) -> Self {
+        self.clock = time_control.map(TimeControl::clock);
+        self
+    }
+}
+
+impl<TGame: Game + Clone, TEval: Evaluator<TGame>> Agent<TGame> for MctsAgent<TEval> {
+    async fn select_move(&mut self, state: &TGame) -> usize {
+        let mut tree = MonteCarloTree::new(state.clone(), self.evaluator.take().unwrap());
+        match &mut self.clock {
+            Some(clock) => {
+                let started = Instant::now();
+                tree.do_simulations_until(started + clock.budget(), self.samples, self.c_puct)
+                    .await;
+                clock.spend(started.elapsed());
+            }
+            None => tree.do_simulations(self.samples, self.c_puct).await,
+        }
+        let policy = tree.get_policy();
+        let root_value = tree.get_root_value();
+        self.evaluator = Some(tree.into_evaluator());
+
+        let r#move = if self.temp == 0.0 {
+            argmax(&policy)
+        } else {
+            sample_policy(&policy, self.temp, &mut thread_rng())
+        };
+        self.last_search = Some(SearchInfo {
+            root_value,
+            visits: policy,
+        });
+        r#move
+    }
+
+    fn last_search(&self) -> Option<&SearchInfo> {
+        self.last_search.as_ref()
+    }
+}
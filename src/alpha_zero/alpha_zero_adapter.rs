@@ -1,14 +1,72 @@
-use tch::Tensor;
+use tch::{Device, Kind, Tensor};
 
-use super::{AlphaZeroNet, Game};
+use super::{AlphaZeroNet, Game, SymmetryTransform};
 
 pub trait AlphaZeroAdapter<TGame: Game, Net: AlphaZeroNet> {
+    // Symmetries of the game, identity first. Used for training augmentation
+    // and to randomize network evaluations during search.
+    fn symmetries() -> Vec<SymmetryTransform> {
+        vec![SymmetryTransform::identity()]
+    }
+
     fn reflect_and_augment(state: &Tensor, policy: &Tensor) -> Vec<(Tensor, Tensor)> {
-        vec![(state.copy(), policy.copy())]
+        Self::symmetries()
+            .iter()
+            .map(|s| (s.transform_state(state), s.transform_policy(policy)))
+            .collect()
+    }
+
+    // Who the cells belong to at `final_state`, the end of the game, for the player to move
+    // in `state`: 1 for theirs, -1 for the opponent's and 0 for neither, row by row as in an
+    // input plane. Targets of the net's ownership head, `None` for games without them.
+    fn ownership_target(_state: &TGame, _final_state: &TGame) -> Option<Vec<f32>> {
+        None
+    }
+
+    // Names of the input planes in order, for describing exported nets. Empty if unnamed.
+    fn input_planes() -> Vec<String> {
+        vec![]
     }
 
+    // Single-item conversions produce CPU tensors of any kind, the caller
+    // (executor or trainer) is responsible for the final kind and device.
     fn convert_game_to_nn_input(state: &TGame) -> Tensor;
     fn get_estimated_policy(policy: &Tensor, moves: &[TGame::Move]) -> Vec<f32>;
 
     fn convert_policy_to_nn(policy: &[f32], moves: &[TGame::Move]) -> Tensor;
+
+    // Batched versions of the conversions above, returning tensors with the requested
+    // kind and device. Override these if the game can build the whole batch without
+    // going through per-item tensors.
+    fn convert_games_to_nn_input(states: &[TGame], (kind, device): (Kind, Device)) -> Tensor {
+        let inputs = states
+            .iter()
+            .map(Self::convert_game_to_nn_input)
+            .collect::<Vec<_>>();
+        Tensor::stack(&inputs, 0).to_kind(kind).to(device)
+    }
+
+    fn convert_policies_to_nn(
+        policies: &[Vec<f32>],
+        moves: &[Vec<TGame::Move>],
+        (kind, device): (Kind, Device),
+    ) -> Tensor {
+        assert_eq!(policies.len(), moves.len());
+        let policies = policies
+            .iter()
+            .zip(moves)
+            .map(|(policy, moves)| Self::convert_policy_to_nn(policy, moves))
+            .collect::<Vec<_>>();
+        Tensor::stack(&policies, 0).to_kind(kind).to(device)
+    }
+
+    // Boolean tensor with the shape of the policies, set for every action of `moves`. See
+    // `AlphaZeroNet::forward_masked_t`.
+    fn convert_moves_to_legal_mask(moves: &[Vec<TGame::Move>], device: Device) -> Tensor {
+        let ones = moves
+            .iter()
+            .map(|moves| vec![1.0; moves.len()])
+            .collect::<Vec<_>>();
+        Self::convert_policies_to_nn(&ones, moves, (Kind::Float, device)).gt(0.0)
+    }
 }
@@ -1,5 +1,108 @@
-use tch::Tensor;
+use std::sync::Arc;
+
+use tch::{nn, Kind, Tensor};
+
+// Log-probability of the actions masked out by `mask_log_policy`. Finite, so that the zero
+// targets of illegal actions don't turn the policy loss into NaN, but low enough that
+// nothing of the policy is left on them.
+const MASKED_LOG_PROBABILITY: f64 = -1e9;
+
+// Heads of the net for a batch of positions. The auxiliary ones are `None` for nets
+// without them, and only used for training.
+pub struct NetOutput {
+    // Probabilities of winning for the players to move
+    pub value: Tensor,
+    // Log-probabilities of the actions, in the adapter's policy shape
+    pub policy: Tensor,
+    // Of every cell ending up the player to move's (1) or the opponent's (-1), in the shape
+    // of an input plane. See `AlphaZeroAdapter::ownership_target`.
+    pub ownership: Option<Tensor>,
+    // Log-probabilities of the policy flattened by `soft_policy_target`, which tells the
+    // tower more about the moves the search hardly visits
+    pub soft_policy: Option<Tensor>,
+}
+
+impl NetOutput {
+    pub fn new(value: Tensor, policy: Tensor) -> Self {
+        Self {
+            value,
+            policy,
+            ownership: None,
+            soft_policy: None,
+        }
+    }
+
+    pub fn to_kind(self, kind: Kind) -> Self {
+        Self {
+            value: self.value.to_kind(kind),
+            policy: self.policy.to_kind(kind),
+            ownership: self.ownership.map(|t| t.to_kind(kind)),
+            soft_policy: self.soft_policy.map(|t| t.to_kind(kind)),
+        }
+    }
+
+    // Renormalizes both policies over the legal actions, see `mask_log_policy`
+    pub fn masked(self, legal: &Tensor) -> Self {
+        Self {
+            policy: mask_log_policy(&self.policy, legal),
+            soft_policy: self.soft_policy.map(|t| mask_log_policy(&t, legal)),
+            ..self
+        }
+    }
+}
+
+// Creates a net's variables under the path, shared by everything that needs copies of the net
+pub type BuildNet<TNet> = Arc<dyn Fn(&nn::Path) -> TNet + Send + Sync>;
 
 pub trait AlphaZeroNet {
-    fn forward_t(&self, xs: &Tensor, is_training: bool) -> (Tensor, Tensor);
+    fn forward_t(&self, xs: &Tensor, is_training: bool) -> NetOutput;
+
+    // Like `forward_t`, with the policies only over the actions set in `legal`, a boolean
+    // tensor of the policies' shape. Nets can override it to mask before their softmax,
+    // which gives the same result.
+    fn forward_masked_t(&self, xs: &Tensor, legal: &Tensor, is_training: bool) -> NetOutput {
+        self.forward_t(xs, is_training).masked(legal)
+    }
+}
+
+// Renormalizes a batch of log-policies over the actions set in `legal`, so that no
+// probability is left on illegal actions. The log-softmax of log-probabilities is the
+// log of their normalized probabilities, so this is what the net's log-softmax would have
+// given over the legal actions only.
+pub fn mask_log_policy(log_policies: &Tensor, legal: &Tensor) -> Tensor {
+    let shape = log_policies.size();
+    log_policies
+        .masked_fill(&legal.logical_not(), MASKED_LOG_PROBABILITY)
+        .flatten(1, -1)
+        .log_softmax(1, None)
+        .view(shape.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use tch::Tensor;
+
+    use super::mask_log_policy;
+
+    #[test]
+    fn masked_policies_sum_to_one_over_legal_actions() {
+        let log_policies =
+            Tensor::from_slice2(&[[0.1f32, 0.2, 0.3, 0.4], [0.25, 0.25, 0.25, 0.25]])
+                .log()
+                .view([2, 2, 2]);
+        let legal = Tensor::from_slice2(&[[true, false, true, false], [true, true, true, false]])
+            .view([2, 2, 2]);
+
+        let masked = mask_log_policy(&log_policies, &legal);
+        assert_eq!(masked.size(), [2, 2, 2]);
+        let probabilities = Vec::<f32>::try_from(masked.exp().flatten(0, -1)).unwrap();
+        let expected = [0.25, 0.0, 0.75, 0.0, 1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0, 0.0];
+        for (p, e) in probabilities.iter().zip(expected) {
+            assert!((p - e).abs() < 1e-6, "{probabilities:?} isn't {expected:?}");
+        }
+        // Zero targets on the masked actions keep the cross-entropy finite
+        let target = Tensor::from_slice(&[0.5f32, 0.0, 0.5, 0.0]);
+        let cross_entropy = -(target * masked.get(0).flatten(0, -1)).sum(None);
+        assert!(f64::try_from(cross_entropy).unwrap().is_finite());
+    }
 }
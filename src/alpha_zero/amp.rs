@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use tch::Tensor;
+
+// Precision of the training forward pass as configs write it, `off`, `fp16` or `bf16`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AmpMode {
+    #[default]
+    Off,
+    Fp16,
+    // Not supported yet, tch's autocast only running in fp16
+    Bf16,
+}
+
+// Mixed precision: the forward pass runs under CUDA autocast in fp16 while the weights and
+// the optimizer stay in fp32. The loss is scaled up so small fp16 gradients don't flush
+// to zero.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct AmpConfig {
+    pub init_scale: f64,
+    // Applied after `growth_interval` steps in a row without overflow
+    pub growth_factor: f64,
+    // Applied after every overflow
+    pub backoff_factor: f64,
+    pub growth_interval: usize,
+}
+
+impl Default for AmpConfig {
+    fn default() -> Self {
+        Self {
+            init_scale: 65536.0,
+            growth_factor: 2.0,
+            backoff_factor: 0.5,
+            growth_interval: 2000,
+        }
+    }
+}
+
+// Dynamic loss scaling, adjusting the scale to the largest one that doesn't overflow
+pub struct GradScaler {
+    config: AmpConfig,
+    scale: f64,
+    good_steps: usize,
+}
+
+impl GradScaler {
+    pub fn new(config: AmpConfig) -> Self {
+        Self {
+            config,
+            scale: config.init_scale,
+            good_steps: 0,
+        }
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    // Backpropagates the scaled loss and unscales the gradients of `variables`. Returns
+    // whether they are all finite, the optimizer step has to be skipped otherwise.
+    pub fn backward(&mut self, loss: &Tensor, variables: &[Tensor]) -> bool {
+        (loss * self.scale).backward();
+        let inverse = 1.0 / self.scale;
+        let finite = tch::no_grad(|| {
+            let mut finite = true;
+            for variable in variables {
+                let mut grad = variable.grad();
+                if grad.defined() {
+                    let _ = grad.g_mul_scalar_(inverse);
+                    finite &= bool::try_from(grad.isfinite().all()).unwrap();
+                }
+            }
+            finite
+        });
+        self.update(finite);
+        finite
+    }
+
+    fn update(&mut self, finite: bool) {
+        if finite {
+            self.good_steps += 1;
+            if self.good_steps == self.config.growth_interval {
+                self.scale *= self.config.growth_factor;
+                self.good_steps = 0;
+            }
+        } else {
+            self.scale *= self.config.backoff_factor;
+            self.good_steps = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AmpConfig, GradScaler};
+
+    #[test]
+    fn scale_backs_off_and_grows() {
+        let mut scaler = GradScaler::new(AmpConfig {
+            init_scale: 1024.0,
+            growth_interval: 3,
+            ..Default::default()
+        });
+        scaler.update(false);
+        scaler.update(false);
+        assert_eq!(scaler.scale(), 256.0);
+        scaler.update(true);
+        scaler.update(true);
+        assert_eq!(scaler.scale(), 256.0);
+        // Overflow restarts the interval
+        scaler.update(false);
+        for _ in 0..3 {
+            scaler.update(true);
+        }
+        assert_eq!(scaler.scale(), 256.0);
+        for _ in 0..3 {
+            scaler.update(true);
+        }
+        assert_eq!(scaler.scale(), 512.0);
+    }
+}
@@ -0,0 +1,456 @@
+use std::fmt::Write;
+
+use anyhow::Context;
+
+use super::{Evaluator, Game, MonteCarloTree, Notation, Perspective, TerminationState};
+
+// What a search of the position thought of a move of a game
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveReport<TMove> {
+    pub r#move: TMove,
+    // Of the mover, before the move
+    pub value: f32,
+    // How much of `value` the move gave away: the mover's value before the move minus the one
+    // after it, by the search of the next position or the result. Negative when the move
+    // turned out better than the search expected.
+    pub swing: f32,
+    // Shares of the search's visits and the evaluator's prior of the move
+    pub visits: f32,
+    pub prior: f32,
+    // The most visited of the other moves, with its share of the visits. `None` if the move
+    // was the only one.
+    pub alternative: Option<(TMove, f32)>,
+}
+
+impl<TMove> MoveReport<TMove> {
+    // A move giving away at least `threshold` when the search preferred another
+    pub fn is_blunder(&self, threshold: f32) -> bool {
+        self.swing >= threshold
+            && self
+                .alternative
+                .as_ref()
+                .is_some_and(|(_, visits)| *visits > self.visits)
+    }
+}
+
+struct Search {
+    value: f32,
+    visits: Vec<f32>,
+    priors: Vec<f32>,
+    q: Vec<Option<f32>>,
+}
+
+// The evaluator is passed along, so that nets keep their executor handle
+async fn search<TGame: Game + Clone, TEval: Evaluator<TGame>>(
+    state: &TGame,
+    evaluator: TEval,
+    simulations: usize,
+    c_puct: f32,
+) -> (Search, TEval) {
+    let mut tree = MonteCarloTree::new(state.clone(), evaluator);
+    // The root is only evaluated by the first simulation
+    tree.do_simulations(simulations.max(1), c_puct).await;
+    let search = Search {
+        value: tree.get_root_value(),
+        visits: tree.get_policy(),
+        priors: tree.get_priors(),
+        q: tree.get_q_values(),
+    };
+    (search, tree.into_evaluator())
+}
+
+// What the evaluator and the search made of a single position, for spotting where they
+// disagree. Everything but the value is over the position's moves.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchHeatmaps {
+    // Of the player to move, by the search
+    pub value: f32,
+    pub priors: Vec<f32>,
+    // Shares of the visits
+    pub visits: Vec<f32>,
+    // Mean value of every move for the player to move, `None` for moves never visited
+    pub q: Vec<Option<f32>>,
+}
+
+impl SearchHeatmaps {
+    // Named and scaled to [0, 1] for drawing: the priors and the visits relative to their
+    // largest, so that they stay visible on large boards, and Q as it is, with the moves never
+    // visited at 0
+    pub fn maps(&self) -> [(&'static str, Vec<f32>); 3] {
+        let relative = |map: &[f32]| {
+            let max = map.iter().copied().fold(f32::MIN_POSITIVE, f32::max);
+            map.iter().map(|p| p / max).collect()
+        };
+        [
+            ("Prior", relative(&self.priors)),
+            ("Visits", relative(&self.visits)),
+            ("Q", self.q.iter().map(|q| q.unwrap_or(0.0)).collect()),
+        ]
+    }
+}
+
+pub async fn search_heatmaps<TGame, TEval>(
+    state: &TGame,
+    evaluator: TEval,
+    simulations: usize,
+    c_puct: f32,
+) -> SearchHeatmaps
+where
+    TGame: Game + Clone,
+    TEval: Evaluator<TGame>,
+{
+    let (search, _) = search(state, evaluator, simulations, c_puct).await;
+    SearchHeatmaps {
+        value: search.value,
+        priors: search.priors,
+        visits: search.visits,
+        q: search.q,
+    }
+}
+
+// A position of a line an engine played, with its search's shares of the visits and the most
+// visited move, which it played
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinePosition<TGame: Game> {
+    pub state: TGame,
+    pub visits: Vec<f32>,
+    pub r#move: TGame::Move,
+}
+
+// Where two engines playing the same position stop agreeing on the most visited move, like
+// consecutive generations after an opening
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineDiff<TGame: Game> {
+    // Played by both before they diverged
+    pub agreed: Vec<TGame::Move>,
+    // Each engine's line from the position they disagree on, empty if the game ended first
+    pub first: Vec<LinePosition<TGame>>,
+    pub second: Vec<LinePosition<TGame>>,
+}
+
+async fn line_position<TGame, TEval>(
+    state: &TGame,
+    mut moves: Vec<TGame::Move>,
+    evaluator: TEval,
+    simulations: usize,
+    c_puct: f32,
+) -> (LinePosition<TGame>, TEval)
+where
+    TGame: Game + Clone,
+    TEval: Evaluator<TGame>,
+{
+    let (search, evaluator) = search(state, evaluator, simulations, c_puct).await;
+    // The first of equally visited moves, for engines without noise to play the same line
+    let best = (0..moves.len())
+        .rev()
+        .max_by(|&a, &b| search.visits[a].total_cmp(&search.visits[b]))
+        .unwrap();
+    let position = LinePosition {
+        state: state.clone(),
+        visits: search.visits,
+        r#move: moves.swap_remove(best),
+    };
+    (position, evaluator)
+}
+
+// Up to `plies` positions of the engine's line, `position` included
+async fn continue_line<TGame, TEval>(
+    position: LinePosition<TGame>,
+    evaluator: TEval,
+    simulations: usize,
+    c_puct: f32,
+    plies: usize,
+) -> Vec<LinePosition<TGame>>
+where
+    TGame: Game + Clone,
+    TEval: Evaluator<TGame>,
+{
+    let mut evaluator = evaluator;
+    let mut state = position.state.make_move(&position.r#move);
+    let mut line = vec![position];
+    while line.len() < plies {
+        let Some(moves) = state.get_state().get_moves() else {
+            break;
+        };
+        let (next, returned) = line_position(&state, moves, evaluator, simulations, c_puct).await;
+        evaluator = returned;
+        state = state.make_move(&next.r#move);
+        line.push(next);
+    }
+    line
+}
+
+// Both engines play their most visited moves from `start` with `simulations` each, until they
+// disagree and then for `plies` positions each
+pub async fn diverging_lines<TGame, TFirst, TSecond>(
+    start: &TGame,
+    first: TFirst,
+    second: TSecond,
+    simulations: usize,
+    c_puct: f32,
+    plies: usize,
+) -> LineDiff<TGame>
+where
+    TGame: Game + Clone,
+    TGame::Move: Clone + PartialEq,
+    TFirst: Evaluator<TGame>,
+    TSecond: Evaluator<TGame>,
+{
+    let (mut first, mut second) = (first, second);
+    let mut state = start.clone();
+    let mut agreed = vec![];
+    while let Some(moves) = state.get_state().get_moves() {
+        let (first_position, returned) =
+            line_position(&state, moves.clone(), first, simulations, c_puct).await;
+        first = returned;
+        let (second_position, returned) =
+            line_position(&state, moves, second, simulations, c_puct).await;
+        second = returned;
+        if first_position.r#move != second_position.r#move {
+            return LineDiff {
+                agreed,
+                first: continue_line(first_position, first, simulations, c_puct, plies).await,
+                second: continue_line(second_position, second, simulations, c_puct, plies).await,
+            };
+        }
+        state = state.make_move(&first_position.r#move);
+        agreed.push(first_position.r#move);
+    }
+    LineDiff {
+        agreed,
+        first: vec![],
+        second: vec![],
+    }
+}
+
+// The moves of a whitespace separated list, like the movetext of `PortableGame` without
+// values or a result, played from `start` to check them
+pub fn parse_move_list<TGame>(
+    start: &TGame,
+    notation: Notation<TGame>,
+    text: &str,
+) -> anyhow::Result<Vec<TGame::Move>>
+where
+    TGame: Game + Clone,
+{
+    let mut state = start.clone();
+    let mut moves = vec![];
+    for token in text.split_whitespace() {
+        let r#move = (notation.parse)(&state, token)?;
+        anyhow::ensure!(
+            state.get_state().get_moves().is_some() && state.is_legal(&r#move),
+            "Illegal move {token}"
+        );
+        state = state.make_move(&r#move);
+        moves.push(r#move);
+    }
+    Ok(moves)
+}
+
+// Searches every position of the game from `start`, the one after the last move included
+// unless the game ended, with `simulations` each. The reports are in the order of `moves`.
+pub async fn analyze_game<TGame, TEval>(
+    start: &TGame,
+    moves: &[TGame::Move],
+    evaluator: TEval,
+    simulations: usize,
+    c_puct: f32,
+) -> anyhow::Result<Vec<MoveReport<TGame::Move>>>
+where
+    TGame: Game + Clone,
+    TGame::Move: Clone + PartialEq,
+    TEval: Evaluator<TGame>,
+{
+    let mut evaluator = evaluator;
+    let mut state = start.clone();
+    let mut searches = vec![];
+    for (ply, r#move) in moves.iter().enumerate() {
+        let legal = state
+            .get_state()
+            .get_moves()
+            .with_context(|| format!("Move {} after the game ended", ply + 1))?;
+        let played = legal
+            .iter()
+            .position(|m| m == r#move)
+            .with_context(|| format!("Move {} is illegal", ply + 1))?;
+        let (result, returned) = search(&state, evaluator, simulations, c_puct).await;
+        evaluator = returned;
+        searches.push((legal, played, result));
+        state = state.make_move(r#move);
+    }
+    // Of the player to move after the last move
+    let mut next_value = match state.get_state() {
+        TerminationState::Terminal(value) => value,
+        TerminationState::Moves(_) => search(&state, evaluator, simulations, c_puct).await.0.value,
+    };
+
+    let mut reports = vec![];
+    for (r#move, (mut legal, played, search)) in moves.iter().zip(searches).rev() {
+        let after = Perspective::after_move(r#move).convert(next_value);
+        let alternative = (0..legal.len())
+            .filter(|&i| i != played)
+            .max_by(|&a, &b| search.visits[a].total_cmp(&search.visits[b]))
+            .map(|i| (legal.swap_remove(i), search.visits[i]));
+        reports.push(MoveReport {
+            r#move: r#move.clone(),
+            value: search.value,
+            swing: search.value - after,
+            visits: search.visits[played],
+            prior: search.priors[played],
+            alternative,
+        });
+        next_value = search.value;
+    }
+    reports.reverse();
+    Ok(reports)
+}
+
+// A line per move with the reports' numbers, blunders by `blunder_threshold` being marked `??`
+pub fn format_reports<TGame: Game + Clone>(
+    start: &TGame,
+    notation: Notation<TGame>,
+    reports: &[MoveReport<TGame::Move>],
+    blunder_threshold: f32,
+) -> String {
+    let mut text = String::new();
+    let mut state = start.clone();
+    for (ply, report) in reports.iter().enumerate() {
+        let alternative = match &report.alternative {
+            Some((m, visits)) => format!("{} {:.0}%", (notation.format)(&state, m), 100.0 * visits),
+            None => "none".to_owned(),
+        };
+        let mark = match report.is_blunder(blunder_threshold) {
+            true => " ??",
+            false => "",
+        };
+        let _ = writeln!(
+            text,
+            "{:>3}. {}{mark}: value {:.3}, swing {:+.3}, visits {:.0}%, prior {:.0}%, \
+             alternative {alternative}",
+            ply + 1,
+            (notation.format)(&state, &report.r#move),
+            report.value,
+            report.swing,
+            100.0 * report.visits,
+            100.0 * report.prior,
+        );
+        state = state.make_move(&report.r#move);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{Evaluator, Game, Notation, UniformEvaluator},
+        tictactoe3::{TicTacToe3, TicTacToe3Move},
+    };
+
+    use super::{analyze_game, diverging_lines, format_reports, parse_move_list, search_heatmaps};
+
+    // Nearly all of the prior on the first legal cell in its order, without an opinion of the
+    // value
+    struct Preferring([usize; 9]);
+
+    impl Evaluator<TicTacToe3> for Preferring {
+        async fn evaluate(&mut self, _: &TicTacToe3, moves: &[TicTacToe3Move]) -> (f32, Vec<f32>) {
+            let preferred = self
+                .0
+                .iter()
+                .find_map(|&cell| moves.iter().position(|m| m.0 == cell))
+                .unwrap();
+            let rest = 0.01 / moves.len() as f32;
+            let priors = (0..moves.len())
+                .map(|i| if i == preferred { 0.99 } else { rest })
+                .collect();
+            (0.5, priors)
+        }
+    }
+
+    #[tokio::test]
+    async fn missed_win() {
+        // X misses the top row by playing 8, then O completes the middle row
+        let start = TicTacToe3::new();
+        let moves = parse_move_list(&start, Notation::of(), "0 3 1 4 8 5").unwrap();
+        let reports = analyze_game(&start, &moves, UniformEvaluator, 2000, 1.0)
+            .await
+            .unwrap();
+        assert_eq!(reports.len(), 6);
+
+        let missed = &reports[4];
+        assert!(missed.value > 0.8, "{missed:?}");
+        assert!(missed.swing > 0.7, "{missed:?}");
+        assert_eq!(missed.prior, 1.0 / 5.0);
+        assert!(missed.is_blunder(0.5));
+        let (alternative, visits) = missed.alternative.unwrap();
+        assert_eq!(alternative, TicTacToe3Move(2));
+        assert!(visits > 0.5, "{missed:?}");
+
+        // The winning move loses nothing and is what the search plays
+        let won = &reports[5];
+        assert!(won.swing.abs() < 0.1, "{won:?}");
+        assert!(!won.is_blunder(0.5));
+
+        let text = format_reports(&start, Notation::of(), &reports, 0.5);
+        assert_eq!(text.lines().count(), 6);
+        assert!(text.lines().nth(4).unwrap().starts_with("  5. 8 ??: "));
+        assert!(text.contains("alternative 2 "));
+
+        assert!(parse_move_list(&start, Notation::of(), "0 0").is_err());
+        assert!(parse_move_list(&start, Notation::of(), "0 3 1 4 2 5").is_err());
+    }
+
+    #[tokio::test]
+    async fn heatmaps_of_a_won_position() {
+        // X wins at 2, which the search finds and values at 1
+        let start = TicTacToe3::new();
+        let moves = parse_move_list(&start, Notation::of(), "0 3 1 4").unwrap();
+        let state = moves.iter().fold(start, |state, m| state.make_move(m));
+        let heatmaps = search_heatmaps(&state, UniformEvaluator, 500, 1.0).await;
+        assert_eq!(heatmaps.priors, [0.2; 5]);
+        assert_eq!(heatmaps.q.len(), 5);
+        assert_eq!(heatmaps.q[0], Some(1.0));
+        assert!(heatmaps.visits[0] > 0.5, "{heatmaps:?}");
+
+        let [(prior, priors), (_, visits), (_, q)] = heatmaps.maps();
+        assert_eq!(prior, "Prior");
+        assert_eq!(priors, [1.0; 5]);
+        assert_eq!(visits[0], 1.0);
+        assert_eq!(q[0], 1.0);
+    }
+
+    #[tokio::test]
+    async fn lines_from_the_divergence() {
+        let first = Preferring([4, 0, 1, 2, 3, 5, 6, 7, 8]);
+        let second = Preferring([4, 0, 2, 1, 3, 5, 6, 7, 8]);
+        let diff = diverging_lines(&TicTacToe3::new(), first, second, 20, 1.0, 3).await;
+        assert_eq!(diff.agreed, [TicTacToe3Move(4), TicTacToe3Move(0)]);
+        assert_eq!(diff.first.len(), 3);
+        assert_eq!(diff.second.len(), 3);
+        assert_eq!(diff.first[0].state, diff.second[0].state);
+        assert_eq!(diff.first[0].r#move, TicTacToe3Move(1));
+        assert_eq!(diff.second[0].r#move, TicTacToe3Move(2));
+        assert!(diff.first[0].visits[0] > 0.5, "{diff:?}");
+        assert_eq!(
+            diff.first[1].state,
+            diff.first[0].state.make_move(&TicTacToe3Move(1))
+        );
+
+        // The same engine never diverges from itself
+        let same = diverging_lines(
+            &TicTacToe3::new(),
+            UniformEvaluator,
+            UniformEvaluator,
+            20,
+            1.0,
+            3,
+        )
+        .await;
+        assert!(same.first.is_empty() && same.second.is_empty());
+        let end = same
+            .agreed
+            .iter()
+            .fold(TicTacToe3::new(), |state, m| state.make_move(m));
+        assert!(end.get_state().get_moves().is_none());
+    }
+}
@@ -0,0 +1,785 @@
+use std::{fmt::Write, ops::Add, time::Instant};
+#[cfg(feature = "torch")]
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[cfg(feature = "torch")]
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+#[cfg(feature = "torch")]
+use tch::{nn, Device, Kind};
+#[cfg(feature = "torch")]
+use tracing::info;
+
+#[cfg(feature = "torch")]
+use super::{
+    do_battle, AlphaZeroAdapter, AlphaZeroNet, Baseline, BattlePlayer, BuildNet, CheckpointManager,
+    ContenderAgent, ExecutorScope, MctsAgent, NetworkEvaluator,
+};
+use super::{
+    Agent, Game, MatchStats, MoveParameters, OpeningBook, SearchInfo, Significance, SprtConfig,
+    SprtDecision, TerminationState, TimeControl,
+};
+
+// Some randomness in every match, so that the games don't all repeat each other
+pub const MATCH_TEMPERATURE: f32 = 0.2;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct MatchConfig {
+    pub games: usize,
+    // Per move, for both players. With a time control, the most per move.
+    pub simulations: usize,
+    pub c_puct: f32,
+    // Games played at the same time
+    pub parallelism: usize,
+    // Stops the match early once it decides, `games` being the most played
+    pub sprt: Option<SprtConfig>,
+    // For the nets, baselines searching a fixed number of simulations
+    pub time_control: Option<TimeControl>,
+}
+
+impl MatchConfig {
+    fn decided(&self, result: &MatchResult) -> bool {
+        self.sprt
+            .is_some_and(|sprt| sprt.decide(result) != SprtDecision::Continue)
+    }
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            simulations: 32,
+            c_puct: 1.0 / 32.0,
+            parallelism: 64,
+            sprt: None,
+            time_control: None,
+        }
+    }
+}
+
+// From the first player's point of view
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MatchResult {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl MatchResult {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    // Wins plus half the draws over the games played, 0.5 if there were none
+    pub fn score(&self) -> f64 {
+        match self.games() {
+            0 => 0.5,
+            games => (self.wins as f64 + self.draws as f64 / 2.0) / games as f64,
+        }
+    }
+
+    // Interval of the score `z` standard deviations wide on either side, from the variance of
+    // the outcomes of the games played
+    pub fn confidence_interval(&self, z: f64) -> (f64, f64) {
+        let games = self.games() as f64;
+        if games == 0.0 {
+            return (0.0, 1.0);
+        }
+        let score = self.score();
+        let variance = (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games;
+        let margin = z * (variance / games).sqrt();
+        ((score - margin).max(0.0), (score + margin).min(1.0))
+    }
+
+    // Outcome in [0, 1] for the first player
+    pub fn record(&mut self, value: f32) {
+        match value.partial_cmp(&0.5).unwrap() {
+            std::cmp::Ordering::Greater => self.wins += 1,
+            std::cmp::Ordering::Equal => self.draws += 1,
+            std::cmp::Ordering::Less => self.losses += 1,
+        }
+    }
+
+    // The same games from the opponent's point of view
+    pub fn reversed(&self) -> Self {
+        Self {
+            wins: self.losses,
+            draws: self.draws,
+            losses: self.wins,
+        }
+    }
+}
+
+impl Add for MatchResult {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            wins: self.wins + other.wins,
+            draws: self.draws + other.draws,
+            losses: self.losses + other.losses,
+        }
+    }
+}
+
+// The position a game starts from, with the index of the opening played to reach it and
+// whether that opening handed the move to the second player
+fn starting_position<TGame: Game + Clone>(
+    start: &TGame,
+    openings: Option<&OpeningBook<TGame>>,
+    rng: &mut impl Rng,
+) -> (TGame, Option<usize>, bool) {
+    match openings {
+        Some(book) => {
+            let index = book.sample_index(rng);
+            (
+                book.play(index, start),
+                Some(index),
+                book.switches_player(index),
+            )
+        }
+        None => (start.clone(), None, false),
+    }
+}
+
+// Plays `config.games` between the two nets, each evaluated by an executor of its own.
+// `net` makes the game's first move in every other game, counting the opening's moves. With
+// an SPRT, the games still in progress when it decides are abandoned.
+#[cfg(feature = "torch")]
+pub async fn play_match<TGame, TNet, TAdapter>(
+    start: &TGame,
+    openings: Option<&OpeningBook<TGame>>,
+    config: &MatchConfig,
+    net: TNet,
+    opponent: TNet,
+    device: Device,
+) -> MatchStats
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let options = (Kind::Float, device);
+    let batch_acc_time = Duration::from_millis(10);
+    let mut games = ExecutorScope::new(
+        net,
+        config.parallelism,
+        config.parallelism,
+        batch_acc_time,
+        options,
+    );
+    // Only serves the opponent's evaluations, the games run on `games`
+    let mut opponent = ExecutorScope::<(), _>::new(
+        opponent,
+        config.parallelism,
+        config.parallelism,
+        batch_acc_time,
+        options,
+    );
+
+    let mut rng = StdRng::from_entropy();
+    for game in 0..config.games {
+        let net_first = game % 2 == 0;
+        let (start, opening, switched) = starting_position(start, openings, &mut rng);
+        // Whether `net` moves first from the opening's position
+        let net_to_move = net_first != switched;
+        let opponent = opponent.handle();
+        let config = *config;
+        games.spawn(move |net| async move {
+            let (first, second) = match net_to_move {
+                true => (net, opponent),
+                false => (opponent, net),
+            };
+            let player = |net| {
+                BattlePlayer::new(
+                    NetworkEvaluator::<TGame, TNet, TAdapter>::new(net),
+                    config.simulations,
+                    config.c_puct,
+                    |_| MATCH_TEMPERATURE,
+                )
+                .with_time_control(config.time_control)
+            };
+            let history = do_battle(start, None, player(first), player(second)).await;
+            // Value for the player who moved first
+            let value = history.first().map_or(0.5, |(_, _, value, _)| *value);
+            let value = match net_to_move {
+                true => value,
+                false => 1.0 - value,
+            };
+            (value, net_first, opening)
+        });
+    }
+
+    let mut stats = MatchStats::default();
+    while let Some((value, net_first, opening)) = games.next().await {
+        opponent.set_external_tasks(games.len()).await;
+        stats.record(value, net_first, opening);
+        if config.decided(&stats.total()) {
+            games.abort();
+            break;
+        }
+    }
+    games.join().await;
+    opponent.join().await;
+    stats
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MoveLog {
+    pub ply: usize,
+    // Whether `agent` rather than `opponent` moved
+    pub agent: bool,
+    // Index in the position's move list
+    #[serde(rename = "move")]
+    pub r#move: usize,
+    pub search: Option<SearchInfo>,
+    pub seconds: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GameLog {
+    pub game: usize,
+    // Whether `agent` made the game's first move, counting the opening's moves
+    pub agent_first: bool,
+    // Index in the opening book
+    pub opening: Option<usize>,
+    // For `agent`
+    pub value: f32,
+    pub moves: Vec<MoveLog>,
+}
+
+async fn play_game<TGame: Game>(
+    game: usize,
+    (start, opening, switched): (TGame, Option<usize>, bool),
+    agent: &mut impl Agent<TGame>,
+    opponent: &mut impl Agent<TGame>,
+    agent_first: bool,
+) -> GameLog {
+    let mut state = start;
+    let mut agent_to_move = agent_first != switched;
+    let mut moves_played = vec![];
+    let value = loop {
+        let moves = match state.get_state() {
+            TerminationState::Terminal(value) => {
+                break if agent_to_move { value } else { 1.0 - value };
+            }
+            TerminationState::Moves(moves) => moves,
+        };
+        let started = Instant::now();
+        let (r#move, search) = match agent_to_move {
+            true => (agent.select_move(&state).await, agent.last_search()),
+            false => (opponent.select_move(&state).await, opponent.last_search()),
+        };
+        moves_played.push(MoveLog {
+            ply: moves_played.len(),
+            agent: agent_to_move,
+            r#move,
+            search: search.cloned(),
+            seconds: started.elapsed().as_secs_f64(),
+        });
+        agent_to_move ^= moves[r#move].is_player_switch();
+        state = state.make_move(&moves[r#move]);
+    };
+    GameLog {
+        game,
+        agent_first,
+        opening,
+        value,
+        moves: moves_played,
+    }
+}
+
+// Like `play_match` for any agents, each game getting fresh ones. The games run concurrently
+// on the current task, nets being evaluated by executors the caller keeps.
+pub async fn play_agents<TGame, A1, A2>(
+    start: &TGame,
+    openings: Option<&OpeningBook<TGame>>,
+    config: &MatchConfig,
+    agent: impl FnMut() -> A1,
+    opponent: impl FnMut() -> A2,
+) -> MatchStats
+where
+    TGame: Game + Clone,
+    A1: Agent<TGame>,
+    A2: Agent<TGame>,
+{
+    play_logged_agents(start, openings, config, agent, opponent)
+        .await
+        .0
+}
+
+// `play_agents` keeping a record of every move, the games in the order they finished
+pub async fn play_logged_agents<TGame, A1, A2>(
+    start: &TGame,
+    openings: Option<&OpeningBook<TGame>>,
+    config: &MatchConfig,
+    mut agent: impl FnMut() -> A1,
+    mut opponent: impl FnMut() -> A2,
+) -> (MatchStats, Vec<GameLog>)
+where
+    TGame: Game + Clone,
+    A1: Agent<TGame>,
+    A2: Agent<TGame>,
+{
+    let mut rng = StdRng::from_entropy();
+    let games = (0..config.games)
+        .map(|game| {
+            let start = starting_position(start, openings, &mut rng);
+            let (mut agent, mut opponent) = (agent(), opponent());
+            async move { play_game(game, start, &mut agent, &mut opponent, game % 2 == 0).await }
+        })
+        .collect::<Vec<_>>();
+    let mut games = futures::stream::iter(games).buffer_unordered(config.parallelism);
+
+    let mut stats = MatchStats::default();
+    let mut records = vec![];
+    while let Some(record) = games.next().await {
+        stats.record(record.value, record.agent_first, record.opening);
+        records.push(record);
+        if config.decided(&stats.total()) {
+            break;
+        }
+    }
+    (stats, records)
+}
+
+// Results of every participant against every other one
+pub struct CrossTable {
+    pub names: Vec<String>,
+    // `results[i][j]` from the point of view of `i`
+    pub results: Vec<Vec<MatchResult>>,
+    // Over all of each participant's games, for the matches set with their stats
+    pub stats: Vec<MatchStats>,
+    // Every game counted, as `(i, j, log)` with `i` the log's agent, in the order they finished
+    pub games: Vec<(usize, usize, GameLog)>,
+}
+
+impl CrossTable {
+    pub fn new(names: Vec<String>) -> Self {
+        let n = names.len();
+        Self {
+            names,
+            results: vec![vec![MatchResult::default(); n]; n],
+            stats: vec![MatchStats::default(); n],
+            games: vec![],
+        }
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, result: MatchResult) {
+        self.results[i][j] = result;
+        self.results[j][i] = result.reversed();
+    }
+
+    // `set` keeping the breakdown by color and opening too
+    pub fn set_stats(&mut self, i: usize, j: usize, stats: &MatchStats) {
+        self.set(i, j, stats.total());
+        self.stats[i].merge(stats);
+        self.stats[j].merge(&stats.reversed());
+    }
+
+    // Over all of the participant's games
+    pub fn total(&self, i: usize) -> MatchResult {
+        self.results[i]
+            .iter()
+            .fold(MatchResult::default(), |total, &result| total + result)
+    }
+
+    // Markdown, with wins-draws-losses of the row against the column and how significant each
+    // participant's score is
+    pub fn to_markdown(&self) -> String {
+        let mut table = String::from("| |");
+        for name in &self.names {
+            let _ = write!(table, " {name} |");
+        }
+        let _ = write!(table, " {} |\n|---|", Significance::HEADERS);
+        table += &"---|".repeat(self.names.len() + 4);
+        table.push('\n');
+        for (i, name) in self.names.iter().enumerate() {
+            let _ = write!(table, "| {name} |");
+            for (j, result) in self.results[i].iter().enumerate() {
+                match i == j {
+                    true => table += " |",
+                    false => {
+                        let _ = write!(
+                            table,
+                            " {}-{}-{} |",
+                            result.wins, result.draws, result.losses
+                        );
+                    }
+                }
+            }
+            let significance = Significance::of(&self.total(i));
+            let _ = writeln!(table, " {} |", significance.to_cells());
+        }
+        table
+    }
+
+    // Markdown, with every participant's wins-draws-losses and score by color, and the first
+    // player's score over all games
+    pub fn colors_to_markdown(&self) -> String {
+        let mut table = String::from("| | as first | as second |\n|---|---|---|\n");
+        let mut first_player = MatchResult::default();
+        for (name, stats) in self.names.iter().zip(&self.stats) {
+            let _ = write!(table, "| {name} |");
+            for result in [stats.as_first, stats.as_second] {
+                let _ = write!(
+                    table,
+                    " {}-{}-{} ({}) |",
+                    result.wins,
+                    result.draws,
+                    result.losses,
+                    Significance::of(&result)
+                );
+            }
+            table.push('\n');
+            first_player = first_player + stats.as_first;
+        }
+        let _ = writeln!(
+            table,
+            "\nThe first player scored {} over {} games",
+            Significance::of(&first_player),
+            first_player.games()
+        );
+        table
+    }
+}
+
+// The EMA weights where there are some
+#[cfg(feature = "torch")]
+fn checkpoint_weights(checkpoints: &CheckpointManager, generation: usize) -> PathBuf {
+    let ema = checkpoints.ema_weights_file(generation);
+    match ema.exists() {
+        true => ema,
+        false => checkpoints.weights_file(generation),
+    }
+}
+
+#[cfg(feature = "torch")]
+enum Participant {
+    // Kept as weights, every tournament building its own net
+    Net(nn::VarStore),
+    Baseline(Baseline),
+}
+
+// Nets and baselines taking part in a tournament
+#[cfg(feature = "torch")]
+pub struct Arena<TNet> {
+    build_net: BuildNet<TNet>,
+    device: Device,
+    participants: Vec<(String, Participant)>,
+}
+
+#[cfg(feature = "torch")]
+impl<TNet: AlphaZeroNet + Send + 'static> Arena<TNet> {
+    pub fn new(build_net: BuildNet<TNet>, device: Device) -> Self {
+        Self {
+            build_net,
+            device,
+            participants: vec![],
+        }
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, weights: &Path) -> anyhow::Result<()> {
+        let mut vs = nn::VarStore::new(self.device);
+        (self.build_net)(&vs.root());
+        vs.load(weights)?;
+        self.participants.push((name.into(), Participant::Net(vs)));
+        Ok(())
+    }
+
+    // Named after the baseline
+    pub fn add_baseline(&mut self, baseline: Baseline) {
+        self.participants
+            .push((baseline.to_string(), Participant::Baseline(baseline)));
+    }
+
+    // Every complete checkpoint, with the EMA weights where there are some
+    pub fn add_checkpoints(&mut self, checkpoints: &CheckpointManager) -> anyhow::Result<()> {
+        for metadata in checkpoints.list()? {
+            let generation = metadata.generation;
+            self.add(
+                format!("gen{generation:02}"),
+                &checkpoint_weights(checkpoints, generation),
+            )?;
+        }
+        Ok(())
+    }
+
+    // The last complete checkpoint, false if there is none
+    pub fn add_latest(
+        &mut self,
+        name: impl Into<String>,
+        checkpoints: &CheckpointManager,
+    ) -> anyhow::Result<bool> {
+        let Some(metadata) = checkpoints.latest()? else {
+            return Ok(false);
+        };
+        self.add(name, &checkpoint_weights(checkpoints, metadata.generation))?;
+        Ok(true)
+    }
+
+    pub fn len(&self) -> usize {
+        self.participants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.participants.is_empty()
+    }
+
+    fn net(&self, weights: &nn::VarStore) -> anyhow::Result<TNet> {
+        let mut vs = nn::VarStore::new(self.device);
+        let net = (self.build_net)(&vs.root());
+        vs.copy(weights)?;
+        Ok(net)
+    }
+
+    // Plays a match between every pair of participants, all of them at once. Each net is
+    // evaluated by one executor for the whole tournament, whose batches shrink as the games it
+    // plays in finish. The games run on the current task, `config.parallelism` at a time, the
+    // pairs taking turns to start one. `heuristic` is for the greedy baseline.
+    pub async fn round_robin<TGame, TAdapter>(
+        &self,
+        start: &TGame,
+        openings: Option<&OpeningBook<TGame>>,
+        heuristic: Option<fn(&TGame) -> f32>,
+        config: &MatchConfig,
+    ) -> anyhow::Result<CrossTable>
+    where
+        TGame: Game + Clone + Send + Sync + 'static,
+        TGame::Move: Send + Sync,
+        TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+    {
+        let mut executors = self
+            .participants
+            .iter()
+            .map(|(_, participant)| match participant {
+                Participant::Net(weights) => Ok(Some(ExecutorScope::<(), _>::new(
+                    self.net(weights)?,
+                    config.parallelism,
+                    config.parallelism,
+                    Duration::from_millis(10),
+                    (Kind::Float, self.device),
+                ))),
+                Participant::Baseline(_) => Ok(None),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let handles = executors
+            .iter()
+            .map(|executor| executor.as_ref().map(ExecutorScope::handle))
+            .collect::<Vec<_>>();
+        let agent = |i: usize| match (&self.participants[i].1, &handles[i]) {
+            (_, Some(handle)) => ContenderAgent::Net(Box::new(
+                MctsAgent::new(
+                    NetworkEvaluator::<TGame, TNet, TAdapter>::new(handle.clone()),
+                    config.simulations,
+                    config.c_puct,
+                    MATCH_TEMPERATURE,
+                )
+                .with_time_control(config.time_control),
+            )),
+            (Participant::Baseline(baseline), None) => {
+                ContenderAgent::Baseline(baseline.agent(heuristic, MATCH_TEMPERATURE))
+            }
+            (Participant::Net(_), None) => unreachable!("Nets always get an executor"),
+        };
+
+        let pairs = (0..self.len())
+            .flat_map(|i| (i + 1..self.len()).map(move |j| (i, j)))
+            .collect::<Vec<_>>();
+        let mut pending =
+            (0..config.games).flat_map(|game| (0..pairs.len()).map(move |pair| (pair, game)));
+        let mut stats = vec![MatchStats::default(); pairs.len()];
+        // With an SPRT, a pair's games still in progress when it decides are ignored
+        let mut decided = vec![false; pairs.len()];
+        // Games in progress by participant
+        let mut playing = vec![0; self.len()];
+        let mut logs = vec![];
+        let mut rng = StdRng::from_entropy();
+        let mut games = FuturesUnordered::new();
+        loop {
+            while games.len() < config.parallelism {
+                let Some((pair, game)) = pending.find(|&(pair, _)| !decided[pair]) else {
+                    break;
+                };
+                let (i, j) = pairs[pair];
+                playing[i] += 1;
+                playing[j] += 1;
+                let start = starting_position(start, openings, &mut rng);
+                let (mut agent, mut opponent) = (agent(i), agent(j));
+                games.push(async move {
+                    let log = play_game(game, start, &mut agent, &mut opponent, game % 2 == 0);
+                    (pair, log.await)
+                });
+            }
+            for (executor, &playing) in executors.iter_mut().zip(&playing) {
+                if let Some(executor) = executor {
+                    executor.set_external_tasks(playing).await;
+                }
+            }
+
+            let Some((pair, log)) = games.next().await else {
+                break;
+            };
+            let (i, j) = pairs[pair];
+            playing[i] -= 1;
+            playing[j] -= 1;
+            if !decided[pair] {
+                stats[pair].record(log.value, log.agent_first, log.opening);
+                decided[pair] = config.decided(&stats[pair].total());
+                logs.push((i, j, log));
+            }
+        }
+        drop(handles);
+        for executor in executors.into_iter().flatten() {
+            executor.join().await;
+        }
+
+        let mut table = CrossTable::new(
+            self.participants
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect(),
+        );
+        for (&(i, j), stats) in pairs.iter().zip(&stats) {
+            let total = stats.total();
+            info!(
+                "{} vs {}: {}-{}-{}",
+                table.names[i], table.names[j], total.wins, total.draws, total.losses
+            );
+            table.set_stats(i, j, stats);
+        }
+        table.games = logs;
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "torch")]
+    use std::sync::Arc;
+
+    #[cfg(feature = "torch")]
+    use tch::Device;
+
+    #[cfg(feature = "torch")]
+    use crate::{
+        alpha_zero::Baseline,
+        tictactoe3::{TicTacToe3, TicTacToe3AlphaZeroAdapter, TicTacToe3Net},
+    };
+    use crate::{
+        alpha_zero::{GreedyAgent, RandomAgent},
+        combinatorial::Nim,
+    };
+
+    #[cfg(feature = "torch")]
+    use super::Arena;
+    use super::{play_agents, CrossTable, MatchConfig, MatchResult};
+
+    #[test]
+    fn scores_count_draws_as_half() {
+        let mut result = MatchResult::default();
+        assert_eq!(result.score(), 0.5);
+        for value in [1.0, 0.5, 0.0, 1.0] {
+            result.record(value);
+        }
+        assert_eq!(
+            result,
+            MatchResult {
+                wins: 2,
+                draws: 1,
+                losses: 1
+            }
+        );
+        assert_eq!(result.score(), 0.625);
+    }
+
+    #[test]
+    fn cross_table_is_symmetric() {
+        let mut table = CrossTable::new(vec!["a".to_owned(), "b".to_owned(), "c".to_owned()]);
+        let result = |wins, draws, losses| MatchResult {
+            wins,
+            draws,
+            losses,
+        };
+        table.set(0, 1, result(3, 1, 0));
+        table.set(1, 2, result(2, 0, 2));
+        assert_eq!(table.results[1][0], result(0, 1, 3));
+        assert_eq!(table.total(1), result(2, 1, 5));
+        assert_eq!(
+            table.to_markdown(),
+            "| | a | b | c | score | 95% wilson | 95% exact | LOS |\n\
+             |---|---|---|---|---|---|---|---|\n\
+             | a | | 3-1-0 | 0-0-0 | 0.875 | 0.396-0.987 | 0.284-1.000 | 96% |\n\
+             | b | 0-1-3 | | 2-0-2 | 0.312 | 0.102-0.644 | 0.056-0.705 | 13% |\n\
+             | c | 0-0-0 | 2-0-2 | | 0.500 | 0.150-0.850 | 0.068-0.932 | 50% |\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn greedy_agent_wins_when_moving_first() {
+        // A win for the first player, which only a perfect heuristic finds
+        let start = Nim::new(vec![1, 2]);
+        let heuristic: fn(&Nim) -> f32 = Nim::value;
+        let config = MatchConfig {
+            games: 20,
+            parallelism: 4,
+            ..Default::default()
+        };
+        let stats = play_agents(
+            &start,
+            None,
+            &config,
+            || GreedyAgent::new(Some(heuristic)),
+            || RandomAgent,
+        )
+        .await;
+
+        let result = stats.total();
+        assert_eq!(result.games(), 20);
+        assert!(result.wins >= 10, "{result:?}");
+        // Every game where it moves first
+        assert_eq!(stats.as_first.wins, 10, "{stats:?}");
+    }
+
+    #[cfg(feature = "torch")]
+    #[tokio::test]
+    async fn round_robin_plays_every_pair() {
+        let mut arena = Arena::new(Arc::new(TicTacToe3Net::new), Device::Cpu);
+        for baseline in [
+            Baseline::Random,
+            Baseline::UniformMcts(16),
+            Baseline::UniformMcts(64),
+        ] {
+            arena.add_baseline(baseline);
+        }
+        let config = MatchConfig {
+            games: 6,
+            parallelism: 4,
+            ..Default::default()
+        };
+        let table = arena
+            .round_robin::<_, TicTacToe3AlphaZeroAdapter>(&TicTacToe3::new(), None, None, &config)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            table.names,
+            ["random", "uniform-mcts:16", "uniform-mcts:64"]
+        );
+        for i in 0..3 {
+            assert_eq!(table.total(i).games(), 12);
+            // Every participant moved first in half of its games
+            assert_eq!(table.stats[i].as_first.games(), 6);
+        }
+        assert_eq!(table.results[2][1], table.results[1][2].reversed());
+    }
+}
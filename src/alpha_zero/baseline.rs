@@ -0,0 +1,136 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::Context;
+
+use super::{
+    Agent, Game, GreedyAgent, MctsAgent, RandomAgent, RolloutEvaluator, SearchInfo,
+    UniformEvaluator,
+};
+
+// Opponents that need no training, to measure progress before two nets are worth comparing.
+// Written as `random`, `greedy`, `uniform-mcts:<simulations>` and `rollout-mcts:<simulations>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Baseline {
+    Random,
+    // See `GreedyAgent`
+    Greedy,
+    // Searches with uniform priors and every leaf valued as a draw
+    UniformMcts(usize),
+    // Searches with uniform priors and leaves valued by random playouts
+    RolloutMcts(usize),
+}
+
+// Without a network's priors the search needs to explore much more than the nets do
+const BASELINE_C_PUCT: f32 = 1.0;
+
+impl Baseline {
+    // Plays with the same temperature as the nets it is compared to
+    pub fn agent<TGame>(
+        self,
+        heuristic: Option<fn(&TGame) -> f32>,
+        temp: f32,
+    ) -> BaselineAgent<TGame> {
+        match self {
+            Baseline::Random => BaselineAgent::Random(RandomAgent),
+            Baseline::Greedy => BaselineAgent::Greedy(GreedyAgent::new(heuristic)),
+            Baseline::UniformMcts(simulations) => BaselineAgent::UniformMcts(MctsAgent::new(
+                UniformEvaluator,
+                simulations,
+                BASELINE_C_PUCT,
+                temp,
+            )),
+            Baseline::RolloutMcts(simulations) => {
+                BaselineAgent::RolloutMcts(Box::new(MctsAgent::new(
+                    RolloutEvaluator::default(),
+                    simulations,
+                    BASELINE_C_PUCT,
+                    temp,
+                )))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Baseline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Baseline::Random => write!(f, "random"),
+            Baseline::Greedy => write!(f, "greedy"),
+            Baseline::UniformMcts(simulations) => write!(f, "uniform-mcts:{simulations}"),
+            Baseline::RolloutMcts(simulations) => write!(f, "rollout-mcts:{simulations}"),
+        }
+    }
+}
+
+impl FromStr for Baseline {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let simulations = |value: &str| -> anyhow::Result<usize> {
+            let simulations = value
+                .parse()
+                .with_context(|| format!("Invalid simulations {value} of baseline {s}"))?;
+            anyhow::ensure!(
+                simulations > 0,
+                "Baseline {s} needs at least one simulation"
+            );
+            Ok(simulations)
+        };
+        Ok(match s.split_once(':') {
+            None if s == "random" => Baseline::Random,
+            None if s == "greedy" => Baseline::Greedy,
+            Some(("uniform-mcts", value)) => Baseline::UniformMcts(simulations(value)?),
+            Some(("rollout-mcts", value)) => Baseline::RolloutMcts(simulations(value)?),
+            _ => anyhow::bail!(
+                "Unknown baseline {s}, expected random, greedy, uniform-mcts:<simulations> or \
+                 rollout-mcts:<simulations>"
+            ),
+        })
+    }
+}
+
+pub enum BaselineAgent<TGame> {
+    Random(RandomAgent),
+    Greedy(GreedyAgent<TGame>),
+    UniformMcts(MctsAgent<UniformEvaluator>),
+    // Boxed for the random generator's state
+    RolloutMcts(Box<MctsAgent<RolloutEvaluator>>),
+}
+
+impl<TGame: Game + Clone> Agent<TGame> for BaselineAgent<TGame> {
+    async fn select_move(&mut self, state: &TGame) -> usize {
+        match self {
+            BaselineAgent::Random(agent) => agent.select_move(state).await,
+            BaselineAgent::Greedy(agent) => agent.select_move(state).await,
+            BaselineAgent::UniformMcts(agent) => agent.select_move(state).await,
+            BaselineAgent::RolloutMcts(agent) => agent.select_move(state).await,
+        }
+    }
+
+    fn last_search(&self) -> Option<&SearchInfo> {
+        match self {
+            BaselineAgent::Random(_) | BaselineAgent::Greedy(_) => None,
+            BaselineAgent::UniformMcts(agent) => Agent::<TGame>::last_search(agent),
+            BaselineAgent::RolloutMcts(agent) => Agent::<TGame>::last_search(agent.as_ref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Baseline;
+
+    #[test]
+    fn names_round_trip() {
+        for baseline in [
+            Baseline::Random,
+            Baseline::Greedy,
+            Baseline::UniformMcts(64),
+            Baseline::RolloutMcts(256),
+        ] {
+            assert_eq!(baseline.to_string().parse::<Baseline>().unwrap(), baseline);
+        }
+        assert!("rollout-mcts:0".parse::<Baseline>().is_err());
+        assert!("minimax".parse::<Baseline>().is_err());
+    }
+}
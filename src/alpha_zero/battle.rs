@@ -1,53 +1,92 @@
-use rand::{thread_rng, Rng};
+use std::time::Instant;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use super::{
-    sample_policy, AlphaZeroAdapter, AlphaZeroNet, Game, MonteCarloTree, MoveParameters,
-    NetworkBatchedExecutorHandle, TerminationState,
+    argmax, sample_policy, Clock, Evaluator, Game, MonteCarloTree, MoveParameters, OpeningBook,
+    Perspective, TerminationState, TimeControl,
 };
 
-async fn make_move<
-    TNet1: AlphaZeroNet,
-    TNet2: AlphaZeroNet,
-    TGame: Game + Clone,
-    TAdapter1: AlphaZeroAdapter<TGame, TNet1>,
-    TAdapter2: AlphaZeroAdapter<TGame, TNet2>,
-    R: Rng,
->(
-    samples: usize,
+// One side of a battle. `temperature` gets the game's turn; at 0 the most visited move is
+// played, otherwise the visit distribution is sampled.
+pub struct BattlePlayer<TEval, F> {
+    pub evaluator: TEval,
+    // The most per move, also with a time control
+    pub simulations: usize,
+    pub c_puct: f32,
+    pub temperature: F,
+    // Searching until the clock's budget for the move runs out
+    pub time_control: Option<TimeControl>,
+}
+
+impl<TEval, F: FnMut(usize) -> f32> BattlePlayer<TEval, F> {
+    pub fn new(evaluator: TEval, simulations: usize, c_puct: f32, temperature: F) -> Self {
+        Self {
+            evaluator,
+            simulations,
+            c_puct,
+            temperature,
+            time_control: None,
+        }
+    }
+
+    pub fn with_time_control(mut self, time_control: Option<TimeControl>) -> Self {
+        self.time_control = time_control;
+        self
+    }
+}
+
+// Searches from the mover's tree only, the other one just follows the move
+async fn make_move<TGame: Game + Clone, TEval: Evaluator<TGame>, R: Rng>(
+    simulations: usize,
     c_puct: f32,
     temp: f32,
-    tree1: &mut MonteCarloTree<TGame, TNet1, TAdapter1>,
-    tree2: &mut MonteCarloTree<TGame, TNet2, TAdapter2>,
+    clock: Option<&mut Clock>,
+    mover: &mut MonteCarloTree<TGame, TEval>,
     rng: &mut R,
 ) -> (usize, Vec<f32>) {
-    tree1.do_simulations(samples, c_puct).await;
-    tree2.do_simulations(2, c_puct).await;
-    let policy = tree1.get_policy();
-    let r#move = sample_policy(&policy, temp, rng);
-
-    tree1.do_move(r#move);
-    tree2.do_move(r#move);
-
+    match clock {
+        Some(clock) => {
+            let started = Instant::now();
+            mover
+                .do_simulations_until(started + clock.budget(), simulations, c_puct)
+                .await;
+            clock.spend(started.elapsed());
+        }
+        None => mover.do_simulations(simulations, c_puct).await,
+    }
+    let policy = mover.get_policy();
+    let r#move = match temp == 0.0 {
+        true => argmax(&policy),
+        false => sample_policy(&policy, temp, rng),
+    };
     (r#move, policy)
 }
 
+// Plays a game between the two players, `player1` moving first. Each position comes with the
+// mover's policy, its final value for the mover and whether `player1` was to move.
 pub async fn do_battle<
-    TNet1: AlphaZeroNet,
-    TNet2: AlphaZeroNet,
     TGame: Game + Clone,
-    TAdapter1: AlphaZeroAdapter<TGame, TNet1>,
-    TAdapter2: AlphaZeroAdapter<TGame, TNet2>,
-    F: FnMut(usize) -> f32,
+    TEval1: Evaluator<TGame>,
+    TEval2: Evaluator<TGame>,
+    F1: FnMut(usize) -> f32,
+    F2: FnMut(usize) -> f32,
 >(
     start: TGame,
-    samples: usize,
-    c_puct: f32,
-    mut temp: F,
-    executor1: NetworkBatchedExecutorHandle<TNet1>,
-    executor2: NetworkBatchedExecutorHandle<TNet2>,
+    opening: Option<OpeningBook<TGame>>,
+    mut player1: BattlePlayer<TEval1, F1>,
+    mut player2: BattlePlayer<TEval2, F2>,
 ) -> Vec<(TGame, Vec<f32>, f32, bool)> {
-    let mut tree1 = MonteCarloTree::<TGame, TNet1, TAdapter1>::new(start.clone(), executor1);
-    let mut tree2 = MonteCarloTree::<TGame, TNet2, TAdapter2>::new(start.clone(), executor2);
+    // Not `thread_rng()`, which would keep the game's future from being `Send`
+    let mut rng = StdRng::from_entropy();
+    let start = match opening {
+        Some(book) => book.sample(&start, &mut rng),
+        None => start,
+    };
+    let mut tree1 = MonteCarloTree::new(start.clone(), player1.evaluator);
+    let mut tree2 = MonteCarloTree::new(start.clone(), player2.evaluator);
+    let mut clock1 = player1.time_control.map(TimeControl::clock);
+    let mut clock2 = player2.time_control.map(TimeControl::clock);
     let mut turn = 0;
     let mut first = true;
 
@@ -60,28 +99,31 @@ pub async fn do_battle<
             TerminationState::Terminal(v) => break v,
             TerminationState::Moves(moves) => moves,
         };
-        let temp = temp(turn);
         let (r#move, policy) = if first {
+            let temp = (player1.temperature)(turn);
             make_move(
-                samples,
-                c_puct,
+                player1.simulations,
+                player1.c_puct,
                 temp,
+                clock1.as_mut(),
                 &mut tree1,
-                &mut tree2,
-                &mut thread_rng(),
+                &mut rng,
             )
             .await
         } else {
+            let temp = (player2.temperature)(turn);
             make_move(
-                samples,
-                c_puct,
+                player2.simulations,
+                player2.c_puct,
                 temp,
+                clock2.as_mut(),
                 &mut tree2,
-                &mut tree1,
-                &mut thread_rng(),
+                &mut rng,
             )
             .await
         };
+        tree1.do_move(r#move);
+        tree2.do_move(r#move);
 
         let new_state = state.make_move(&moves[r#move]);
         history.push((state, policy, 0.0, first));
@@ -92,7 +134,12 @@ pub async fn do_battle<
     };
 
     for h in &mut history {
-        h.2 = if h.3 == first { score } else { 1.0 - score };
+        h.2 = if h.3 == first {
+            Perspective::Same
+        } else {
+            Perspective::Opponent
+        }
+        .convert(score);
     }
 
     history
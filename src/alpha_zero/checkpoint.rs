@@ -0,0 +1,315 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tch::nn;
+
+use super::{Adam, Seed, SeedStream};
+
+// Stored as a JSON sidecar next to the weights. The sidecar is written last, so a
+// checkpoint without one is incomplete and ignored.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointMetadata {
+    pub generation: usize,
+    // Optimizer steps taken since the start of the run
+    pub steps: usize,
+    pub lr: f64,
+    pub seed: Option<Seed>,
+    // Of the training config, to notice resuming with different settings
+    pub config_hash: u64,
+    // Of the experiment's `Config`, missing from checkpoints of runs started without one
+    #[serde(default)]
+    pub experiment_hash: Option<u64>,
+    pub elo: Option<f64>,
+}
+
+// Keeps the last `keep_last` checkpoints of a run plus the one with the highest Elo
+pub struct CheckpointManager {
+    dir: PathBuf,
+    keep_last: usize,
+}
+
+impl CheckpointManager {
+    pub fn new(dir: impl AsRef<Path>, keep_last: usize) -> Self {
+        assert!(
+            keep_last > 0,
+            "At least the latest checkpoint has to be kept"
+        );
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            keep_last,
+        }
+    }
+
+    pub fn weights_file(&self, generation: usize) -> PathBuf {
+        self.dir.join(format!("{generation:02}.safetensors"))
+    }
+
+    pub fn ema_weights_file(&self, generation: usize) -> PathBuf {
+        self.dir.join(format!("{generation:02}.ema.safetensors"))
+    }
+
+    // Missing from checkpoints of runs from before it was saved
+    pub fn optimizer_file(&self, generation: usize) -> PathBuf {
+        self.dir
+            .join(format!("{generation:02}.optimizer.safetensors"))
+    }
+
+    fn metadata_file(&self, generation: usize) -> PathBuf {
+        self.dir.join(format!("{generation:02}.json"))
+    }
+
+    // Copy of the checkpoint picked by `keep_as_best`, unaffected by retention
+    pub fn best_dir(&self) -> PathBuf {
+        self.dir.join("best")
+    }
+
+    // Complete checkpoints, oldest first
+    pub fn list(&self) -> anyhow::Result<Vec<CheckpointMetadata>> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut checkpoints = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                let metadata = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                checkpoints.push(
+                    serde_json::from_str::<CheckpointMetadata>(&metadata)
+                        .with_context(|| format!("Malformed metadata in {}", path.display()))?,
+                );
+            }
+        }
+        checkpoints.sort_by_key(|metadata| metadata.generation);
+        Ok(checkpoints)
+    }
+
+    pub fn latest(&self) -> anyhow::Result<Option<CheckpointMetadata>> {
+        Ok(self.list()?.pop())
+    }
+
+    pub fn best(&self) -> anyhow::Result<Option<CheckpointMetadata>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(|metadata| metadata.elo.is_some())
+            .max_by(|a, b| a.elo.unwrap().total_cmp(&b.elo.unwrap())))
+    }
+
+    // Every file is written under a temporary name and renamed, so a crash never leaves
+    // a truncated checkpoint behind
+    pub fn save(
+        &self,
+        metadata: &CheckpointMetadata,
+        weights: &nn::VarStore,
+        ema_weights: Option<&nn::VarStore>,
+        optimizer: Option<&Adam>,
+    ) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let generation = metadata.generation;
+        atomic_write(&self.weights_file(generation), |tmp| Ok(weights.save(tmp)?))?;
+        if let Some(ema_weights) = ema_weights {
+            atomic_write(&self.ema_weights_file(generation), |tmp| {
+                Ok(ema_weights.save(tmp)?)
+            })?;
+        }
+        if let Some(optimizer) = optimizer {
+            atomic_write(&self.optimizer_file(generation), |tmp| optimizer.save(tmp))?;
+        }
+        self.write_metadata(metadata)?;
+        self.apply_retention()
+    }
+
+    fn write_metadata(&self, metadata: &CheckpointMetadata) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(metadata)?;
+        atomic_write(&self.metadata_file(metadata.generation), |tmp| {
+            Ok(fs::write(tmp, &json)?)
+        })
+    }
+
+    // Typically once the generation was rated in the arena
+    pub fn set_elo(&self, generation: usize, elo: f64) -> anyhow::Result<()> {
+        let mut metadata = self
+            .list()?
+            .into_iter()
+            .find(|metadata| metadata.generation == generation)
+            .with_context(|| format!("No checkpoint for generation {generation}"))?;
+        metadata.elo = Some(elo);
+        self.write_metadata(&metadata)?;
+        self.apply_retention()
+    }
+
+    // Copies the checkpoint of `generation` into `best_dir()`, replacing the previous one
+    pub fn keep_as_best(&self, generation: usize) -> anyhow::Result<()> {
+        let best_dir = self.best_dir();
+        fs::create_dir_all(&best_dir)
+            .with_context(|| format!("Failed to create {}", best_dir.display()))?;
+        let ema_file = best_dir.join("ema.safetensors");
+        if ema_file.exists() {
+            fs::remove_file(&ema_file)
+                .with_context(|| format!("Failed to remove {}", ema_file.display()))?;
+        }
+        for (from, to) in [
+            (
+                self.weights_file(generation),
+                best_dir.join("weights.safetensors"),
+            ),
+            (self.ema_weights_file(generation), ema_file),
+            (
+                self.metadata_file(generation),
+                best_dir.join("metadata.json"),
+            ),
+        ] {
+            if from.exists() {
+                atomic_write(&to, |tmp| Ok(fs::copy(&from, tmp).map(|_| ())?))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Loads the latest checkpoint into `weights` and, if given, `ema_weights`. EMA weights
+    // start from the raw ones if the checkpoint has none.
+    pub fn resume(
+        &self,
+        weights: &mut nn::VarStore,
+        ema_weights: Option<&mut nn::VarStore>,
+    ) -> anyhow::Result<Option<CheckpointMetadata>> {
+        let latest = self.latest()?;
+        if let Some(metadata) = &latest {
+            weights.load(self.weights_file(metadata.generation))?;
+        }
+        if let Some(ema_weights) = ema_weights {
+            match latest
+                .as_ref()
+                .map(|metadata| self.ema_weights_file(metadata.generation))
+                .filter(|file| file.exists())
+            {
+                Some(file) => ema_weights.load(file)?,
+                None => ema_weights.copy(weights)?,
+            }
+        }
+        Ok(latest)
+    }
+
+    fn apply_retention(&self) -> anyhow::Result<()> {
+        let checkpoints = self.list()?;
+        let best = self.best()?.map(|metadata| metadata.generation);
+        let obsolete = checkpoints.len().saturating_sub(self.keep_last);
+        for metadata in &checkpoints[..obsolete] {
+            let generation = metadata.generation;
+            if Some(generation) == best {
+                continue;
+            }
+            // Metadata first, so an interrupted removal leaves an ignored checkpoint
+            for file in [
+                self.metadata_file(generation),
+                self.weights_file(generation),
+                self.ema_weights_file(generation),
+                self.optimizer_file(generation),
+            ] {
+                if file.exists() {
+                    fs::remove_file(&file)
+                        .with_context(|| format!("Failed to remove {}", file.display()))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn atomic_write(
+    path: &Path,
+    write: impl FnOnce(&Path) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    // Before the extension, which tch tells the format of weights by
+    let tmp = match path.extension() {
+        Some(extension) => path.with_extension(format!("tmp.{}", extension.to_string_lossy())),
+        None => path.with_extension("tmp"),
+    };
+    write(&tmp).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to move {}", path.display()))?;
+    Ok(())
+}
+
+// Stays the same across builds, unlike `DefaultHasher`
+pub fn config_hash(config: &impl std::fmt::Debug) -> u64 {
+    format!("{config:?}").as_str().key()
+}
+
+#[cfg(test)]
+mod tests {
+    use tch::{nn, Device, Tensor};
+
+    use crate::alpha_zero::Seed;
+
+    use super::{config_hash, CheckpointManager, CheckpointMetadata};
+
+    fn metadata(generation: usize) -> CheckpointMetadata {
+        CheckpointMetadata {
+            generation,
+            steps: generation * 10,
+            lr: 1e-3,
+            seed: Some(Seed(7)),
+            config_hash: config_hash(&"config"),
+            experiment_hash: Some(config_hash(&"experiment")),
+            elo: None,
+        }
+    }
+
+    #[test]
+    fn retention_and_resume() {
+        let dir = std::env::temp_dir().join(format!("checkpoints-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let manager = CheckpointManager::new(&dir, 2);
+
+        let vs = nn::VarStore::new(Device::Cpu);
+        let _w = vs.root().ones("w", &[3]);
+        for generation in 0..3 {
+            manager
+                .save(&metadata(generation), &vs, None, None)
+                .unwrap();
+        }
+        manager.set_elo(1, 250.0).unwrap();
+        manager.set_elo(2, 100.0).unwrap();
+        for generation in 3..5 {
+            manager
+                .save(&metadata(generation), &vs, None, None)
+                .unwrap();
+        }
+
+        // Last two, plus generation 1 with the best Elo
+        let kept = manager.list().unwrap();
+        assert_eq!(
+            kept.iter().map(|m| m.generation).collect::<Vec<_>>(),
+            [1, 3, 4]
+        );
+        assert!(!manager.weights_file(2).exists());
+        assert_eq!(manager.best().unwrap().unwrap().elo, Some(250.0));
+        manager.keep_as_best(3).unwrap();
+        assert!(manager.best_dir().join("weights.safetensors").exists());
+        assert_eq!(manager.list().unwrap().len(), 3);
+        assert!(std::fs::read_dir(&dir).unwrap().all(|entry| !entry
+            .unwrap()
+            .path()
+            .to_string_lossy()
+            .contains(".tmp")));
+        // Written as safetensors, whatever the temporary file was named
+        assert!(Tensor::read_safetensors(manager.weights_file(4)).is_ok());
+
+        let mut restored = nn::VarStore::new(Device::Cpu);
+        let r = restored.root().zeros("w", &[3]);
+        let mut ema = nn::VarStore::new(Device::Cpu);
+        let e = ema.root().zeros("w", &[3]);
+        let latest = manager.resume(&mut restored, Some(&mut ema)).unwrap();
+        assert_eq!(latest, Some(metadata(4)));
+        assert_eq!(Vec::<f32>::try_from(&r).unwrap(), [1.0; 3]);
+        assert_eq!(Vec::<f32>::try_from(&e).unwrap(), [1.0; 3]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,307 @@
+use std::sync::{Arc, RwLock};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tch::{Device, Kind, Tensor};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use super::{AlphaZeroAdapter, AlphaZeroNet, Game, ReplayBuffer, SampleId, Seed, SelfPlaySample};
+
+#[derive(Clone, Copy, Debug)]
+pub struct DataLoaderConfig {
+    pub batch_size: usize,
+    pub batches: usize,
+    // Minibatches assembled ahead of the training loop
+    pub prefetch: usize,
+    // See `ReplayBuffer::sample_prioritized`
+    pub alpha: f32,
+    pub beta: f32,
+    // Applies a random symmetry of the game to every position
+    pub augment: bool,
+    pub options: (Kind, Device),
+    // Of the sampling and the augmentation, random if `None`
+    pub seed: Option<Seed>,
+    pub weighting: SampleWeighting,
+    // Current one, the age of the samples is relative to it
+    pub generation: usize,
+}
+
+// Multiplies the importance-sampling weights. The defaults weight every sample equally.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SampleWeighting {
+    // Applied once per generation of age, 1 disables it
+    pub recency_decay: f32,
+    // Of positions from drawn games, which say little about who is winning
+    pub draw_weight: f32,
+}
+
+impl Default for SampleWeighting {
+    fn default() -> Self {
+        Self {
+            recency_decay: 1.0,
+            draw_weight: 1.0,
+        }
+    }
+}
+
+impl SampleWeighting {
+    pub fn weight<TGame>(&self, sample: &SelfPlaySample<TGame>, generation: usize) -> f32 {
+        let age = generation.saturating_sub(sample.generation);
+        let recency = self.recency_decay.powi(age as i32);
+        // Values are 0.5 for both players exactly when the game was drawn
+        if sample.value == 0.5 {
+            recency * self.draw_weight
+        } else {
+            recency
+        }
+    }
+}
+
+pub struct Minibatch {
+    // Sample behind every row, for the priority updates
+    pub ids: Vec<SampleId>,
+    pub states: Tensor,
+    pub policies: Tensor,
+    // Of the legal actions, see `AlphaZeroAdapter::convert_moves_to_legal_mask`
+    pub legal: Tensor,
+    pub values: Tensor,
+    // Targets of the ownership head in the shape of the input planes, zeros for the samples
+    // without one. `None` if no sample of the batch has one.
+    pub ownership: Option<Tensor>,
+    // 1 for the samples with an ownership target, 0 for the others
+    pub has_ownership: Tensor,
+    // Importance-sampling weights
+    pub weights: Tensor,
+}
+
+// Assembles minibatches from the replay buffer on a blocking task, so the device doesn't
+// wait for the CPU between training steps
+pub struct DataLoader {
+    batches: mpsc::Receiver<Minibatch>,
+    task: JoinHandle<()>,
+}
+
+impl DataLoader {
+    pub fn spawn<TGame, TNet, TAdapter>(
+        buffer: Arc<RwLock<ReplayBuffer<TGame>>>,
+        config: DataLoaderConfig,
+    ) -> Self
+    where
+        TGame: Game + Clone + Send + Sync + 'static,
+        TNet: AlphaZeroNet + 'static,
+        TAdapter: AlphaZeroAdapter<TGame, TNet> + 'static,
+    {
+        let (tx, rx) = mpsc::channel(config.prefetch.max(1));
+        let task = tokio::task::spawn_blocking(move || {
+            let mut rng = config.seed.map_or_else(StdRng::from_entropy, Seed::rng);
+            for _ in 0..config.batches {
+                let Some(batch) = assemble::<TGame, TNet, TAdapter>(&buffer, &config, &mut rng)
+                else {
+                    break;
+                };
+                if tx.blocking_send(batch).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { batches: rx, task }
+    }
+
+    // `None` once all the batches were consumed, or if the buffer is empty
+    pub async fn next(&mut self) -> Option<Minibatch> {
+        self.batches.recv().await
+    }
+
+    // Stops early if not all the batches were consumed
+    pub async fn join(self) {
+        drop(self.batches);
+        self.task.await.unwrap();
+    }
+}
+
+fn assemble<TGame, TNet, TAdapter>(
+    buffer: &RwLock<ReplayBuffer<TGame>>,
+    config: &DataLoaderConfig,
+    rng: &mut StdRng,
+) -> Option<Minibatch>
+where
+    TGame: Game + Clone,
+    TNet: AlphaZeroNet,
+    TAdapter: AlphaZeroAdapter<TGame, TNet>,
+{
+    // Only hold the lock while copying the samples out
+    let (mut ids, weights, states, policies, values, ownership) = {
+        let buffer = buffer.read().unwrap();
+        let sampled = buffer.sample_prioritized(config.batch_size, config.alpha, config.beta, rng);
+        if sampled.is_empty() {
+            return None;
+        }
+        let mut columns = (vec![], vec![], vec![], vec![], vec![], vec![]);
+        for s in sampled {
+            columns.0.push(s.id);
+            columns
+                .1
+                .push(s.weight * config.weighting.weight(s.sample, config.generation));
+            columns.2.push(s.sample.state.clone());
+            columns.3.push(s.sample.policy.clone());
+            columns.4.push(s.sample.value);
+            columns.5.push(s.sample.ownership.clone());
+        }
+        columns
+    };
+
+    let cpu = (Kind::Float, Device::Cpu);
+    let moves = states
+        .iter()
+        .map(|state| state.get_state().get_moves().unwrap())
+        .collect::<Vec<_>>();
+    let mut states = TAdapter::convert_games_to_nn_input(&states, cpu);
+    let mut has_ownership = Tensor::from_slice(
+        &ownership
+            .iter()
+            .map(|o| o.is_some() as u8 as f32)
+            .collect::<Vec<_>>(),
+    );
+    let mut ownership = ownership.iter().any(Option::is_some).then(|| {
+        let &[batch, _, height, width] = states.size().as_slice() else {
+            panic!("Inputs aren't batches of planes");
+        };
+        let cells = (height * width) as usize;
+        let targets = ownership
+            .iter()
+            .flat_map(|o| match o {
+                Some(o) => o.clone(),
+                None => vec![0.0; cells],
+            })
+            .collect::<Vec<_>>();
+        Tensor::from_slice(&targets).view([batch, height, width])
+    });
+    let mut policies = TAdapter::convert_policies_to_nn(&policies, &moves, cpu);
+    let mut legal = TAdapter::convert_moves_to_legal_mask(&moves, Device::Cpu);
+    let mut values = Tensor::from_slice(&values);
+    let mut weights = Tensor::from_slice(&weights);
+
+    if config.augment {
+        // Rows are regrouped by the symmetry applied to them
+        let symmetries = TAdapter::symmetries();
+        let choices = (0..ids.len())
+            .map(|_| rng.gen_range(0..symmetries.len()))
+            .collect::<Vec<_>>();
+        let mut order = vec![];
+        let (mut new_states, mut new_policies, mut new_legal) = (vec![], vec![], vec![]);
+        let mut new_ownership = vec![];
+        for (k, symmetry) in symmetries.iter().enumerate() {
+            let rows = (0..ids.len() as i64)
+                .filter(|&i| choices[i as usize] == k)
+                .collect::<Vec<_>>();
+            if rows.is_empty() {
+                continue;
+            }
+            let rows_index = Tensor::from_slice(&rows);
+            new_states.push(symmetry.transform_state(&states.index_select(0, &rows_index)));
+            new_policies.push(symmetry.transform_policy(&policies.index_select(0, &rows_index)));
+            new_legal.push(symmetry.transform_policy(&legal.index_select(0, &rows_index)));
+            // Cells turn like the input planes
+            if let Some(ownership) = &ownership {
+                new_ownership
+                    .push(symmetry.transform_state(&ownership.index_select(0, &rows_index)));
+            }
+            order.extend(rows);
+        }
+        let order_index = Tensor::from_slice(&order);
+        states = Tensor::concat(&new_states, 0);
+        policies = Tensor::concat(&new_policies, 0);
+        legal = Tensor::concat(&new_legal, 0);
+        ownership = ownership.map(|_| Tensor::concat(&new_ownership, 0));
+        has_ownership = has_ownership.index_select(0, &order_index);
+        values = values.index_select(0, &order_index);
+        weights = weights.index_select(0, &order_index);
+        ids = order.into_iter().map(|i| ids[i as usize]).collect();
+    }
+
+    let (kind, device) = config.options;
+    let to_device = |t: Tensor| {
+        let t = t.to_kind(kind);
+        if device.is_cuda() {
+            // Copies from page-locked memory don't block the host
+            t.pin_memory(device).to_device_(device, kind, true, false)
+        } else {
+            t.to(device)
+        }
+    };
+    Some(Minibatch {
+        ids,
+        states: to_device(states),
+        policies: to_device(policies),
+        legal: legal.to(device),
+        values: to_device(values),
+        ownership: ownership.map(to_device),
+        has_ownership: to_device(has_ownership),
+        weights: to_device(weights),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use tch::{Device, Kind};
+
+    use crate::{
+        alpha_zero::{ReplayBuffer, SelfPlaySample},
+        tictactoe3::{TicTacToe3, TicTacToe3AlphaZeroAdapter, TicTacToe3Net},
+    };
+
+    use super::{DataLoader, DataLoaderConfig, SampleWeighting};
+
+    #[tokio::test]
+    async fn yields_requested_batches() {
+        let mut buffer = ReplayBuffer::new(16);
+        buffer.push(SelfPlaySample::uniform(TicTacToe3::new(), 9));
+
+        let mut loader = DataLoader::spawn::<_, TicTacToe3Net, TicTacToe3AlphaZeroAdapter>(
+            Arc::new(RwLock::new(buffer)),
+            DataLoaderConfig {
+                batch_size: 5,
+                batches: 3,
+                prefetch: 1,
+                alpha: 0.6,
+                beta: 0.4,
+                augment: true,
+                options: (Kind::Float, Device::Cpu),
+                seed: None,
+                weighting: SampleWeighting::default(),
+                generation: 0,
+            },
+        );
+        let mut batches = 0;
+        while let Some(batch) = loader.next().await {
+            assert_eq!(batch.ids.len(), 5);
+            assert_eq!(batch.states.size()[0], 5);
+            assert_eq!(batch.policies.size()[0], 5);
+            assert_eq!(batch.legal.size(), batch.policies.size());
+            assert_eq!(batch.weights.size(), [5]);
+            batches += 1;
+        }
+        loader.join().await;
+        assert_eq!(batches, 3);
+    }
+
+    #[test]
+    fn weights_decay_with_age_and_draws() {
+        let mut sample = SelfPlaySample {
+            generation: 3,
+            ..SelfPlaySample::uniform(TicTacToe3::new(), 9)
+        };
+        let weighting = SampleWeighting {
+            recency_decay: 0.5,
+            draw_weight: 0.2,
+        };
+        assert_eq!(weighting.weight(&sample, 3), 0.2);
+        assert_eq!(weighting.weight(&sample, 5), 0.05);
+        sample.value = 1.0;
+        assert_eq!(weighting.weight(&sample, 4), 0.5);
+        assert_eq!(SampleWeighting::default().weight(&sample, 10), 1.0);
+    }
+}
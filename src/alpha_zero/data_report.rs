@@ -0,0 +1,262 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashSet},
+    fmt::Write,
+    hash::{Hash, Hasher},
+};
+
+use super::{Game, Notation, SelfPlaySample};
+use crate::metrics::MetricsSink;
+
+// Width of the buckets of the policy entropy histogram, in nats, the last one being open
+const ENTROPY_BUCKET: f64 = 0.5;
+const ENTROPY_BUCKETS: usize = 10;
+
+// For `GameSpec::hash` and deduplicating replay buffers
+pub fn hash_position<TGame: Hash>(state: &TGame) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    state.hash(&mut hasher);
+    hasher.finish()
+}
+
+// What the training data looks like, for catching pathologies like identical games or
+// collapsed policies early
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DataReport {
+    pub positions: usize,
+    // Games started in the data, a game evicted in part not counting
+    pub games: usize,
+    // Games by their number of positions
+    pub game_lengths: BTreeMap<usize, usize>,
+    // Of the games, by the first player's outcome
+    pub first_player_wins: usize,
+    pub draws: usize,
+    pub first_player_losses: usize,
+    // Positions by the entropy of their policy, see `ENTROPY_BUCKET`
+    pub entropy_histogram: [usize; ENTROPY_BUCKETS],
+    pub mean_entropy: f64,
+    // Positions whose state came up earlier in the data, `None` for games without a hash
+    pub duplicates: Option<usize>,
+    // Games by their first move, written with the notation if there is one and as its index
+    // among the start's moves otherwise
+    pub first_moves: BTreeMap<String, usize>,
+}
+
+impl DataReport {
+    // Of positions in the order they were played, like a replay buffer's. Games are told
+    // apart by their first position, so buffers merging duplicates undercount their length.
+    pub fn new<'a, TGame: Game + 'a>(
+        samples: impl IntoIterator<Item = &'a SelfPlaySample<TGame>>,
+        hash: Option<fn(&TGame) -> u64>,
+        notation: Option<Notation<TGame>>,
+    ) -> Self {
+        let mut report = Self {
+            duplicates: hash.map(|_| 0),
+            ..Default::default()
+        };
+        let mut seen = HashSet::new();
+        let mut entropy = 0.0;
+        // Positions of the game being read so far
+        let mut length = None;
+        for sample in samples {
+            report.positions += 1;
+            let sample_entropy = sample
+                .policy
+                .iter()
+                .filter(|&&p| p > 0.0)
+                .map(|&p| -(p as f64) * (p as f64).ln())
+                .sum::<f64>();
+            entropy += sample_entropy;
+            let bucket = ((sample_entropy / ENTROPY_BUCKET) as usize).min(ENTROPY_BUCKETS - 1);
+            report.entropy_histogram[bucket] += 1;
+            if let (Some(hash), Some(duplicates)) = (hash, &mut report.duplicates) {
+                if !seen.insert(hash(&sample.state)) {
+                    *duplicates += 1;
+                }
+            }
+
+            if sample.move_number == 0 {
+                report.end_game(length.take());
+                report.games += 1;
+                match sample.player.convert(sample.value).partial_cmp(&0.5) {
+                    Some(std::cmp::Ordering::Greater) => report.first_player_wins += 1,
+                    Some(std::cmp::Ordering::Less) => report.first_player_losses += 1,
+                    _ => report.draws += 1,
+                }
+                let first_move = match (notation, sample.state.get_state().get_moves()) {
+                    (Some(notation), Some(moves)) => {
+                        (notation.format)(&sample.state, &moves[sample.played])
+                    }
+                    _ => format!("#{}", sample.played),
+                };
+                *report.first_moves.entry(first_move).or_default() += 1;
+                length = Some(0);
+            }
+            if let Some(length) = &mut length {
+                *length += 1;
+            }
+        }
+        report.end_game(length);
+        report.mean_entropy = entropy / report.positions.max(1) as f64;
+        report
+    }
+
+    fn end_game(&mut self, length: Option<usize>) {
+        if let Some(length) = length {
+            *self.game_lengths.entry(length).or_default() += 1;
+        }
+    }
+
+    pub fn mean_game_length(&self) -> f64 {
+        let positions = self
+            .game_lengths
+            .iter()
+            .map(|(length, games)| length * games)
+            .sum::<usize>();
+        positions as f64 / self.games.max(1) as f64
+    }
+
+    pub fn duplicate_rate(&self) -> Option<f64> {
+        self.duplicates
+            .map(|duplicates| duplicates as f64 / self.positions.max(1) as f64)
+    }
+
+    // Share of the games opened with the most common first move
+    pub fn top_first_move_rate(&self) -> f64 {
+        let top = self.first_moves.values().copied().max().unwrap_or(0);
+        top as f64 / self.games.max(1) as f64
+    }
+
+    pub fn report(&self, metrics: &mut impl MetricsSink, generation: usize) -> std::io::Result<()> {
+        let games = self.games.max(1) as f64;
+        for (tag, value) in [
+            ("data/positions", self.positions as f64),
+            ("data/games", self.games as f64),
+            ("data/mean_game_length", self.mean_game_length()),
+            (
+                "data/first_player_wins",
+                self.first_player_wins as f64 / games,
+            ),
+            ("data/draws", self.draws as f64 / games),
+            ("data/mean_policy_entropy", self.mean_entropy),
+            ("data/top_first_move", self.top_first_move_rate()),
+        ] {
+            metrics.scalar(tag, generation, value)?;
+        }
+        if let Some(rate) = self.duplicate_rate() {
+            metrics.scalar("data/duplicate_rate", generation, rate)?;
+        }
+        Ok(())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let games = self.games.max(1) as f64;
+        let percent = |count: usize, total: f64| 100.0 * count as f64 / total;
+        let mut text = format!(
+            "{} positions, {} games of {:.1} positions on average\n\n",
+            self.positions,
+            self.games,
+            self.mean_game_length()
+        );
+        let _ = writeln!(
+            text,
+            "First player: {:.1}% won, {:.1}% drawn, {:.1}% lost\n",
+            percent(self.first_player_wins, games),
+            percent(self.draws, games),
+            percent(self.first_player_losses, games)
+        );
+        if let Some(rate) = self.duplicate_rate() {
+            let _ = writeln!(text, "Duplicate positions: {:.1}%\n", 100.0 * rate);
+        }
+
+        text += "| game length | games |\n|---|---|\n";
+        for (length, count) in &self.game_lengths {
+            let _ = writeln!(text, "| {length} | {count} |");
+        }
+        let _ = write!(
+            text,
+            "\nMean policy entropy {:.3} nats\n\n| entropy | positions |\n|---|---|\n",
+            self.mean_entropy
+        );
+        for (i, count) in self.entropy_histogram.iter().enumerate() {
+            let from = i as f64 * ENTROPY_BUCKET;
+            let range = match i + 1 < ENTROPY_BUCKETS {
+                true => format!("{from:.1} to {:.1}", from + ENTROPY_BUCKET),
+                false => format!("{from:.1} and more"),
+            };
+            let _ = writeln!(text, "| {range} | {count} |");
+        }
+        text += "\n| first move | games | share |\n|---|---|---|\n";
+        let mut first_moves = self.first_moves.iter().collect::<Vec<_>>();
+        first_moves.sort_by(|(a, x), (b, y)| y.cmp(x).then(a.cmp(b)));
+        for (r#move, count) in first_moves {
+            let _ = writeln!(
+                text,
+                "| {move} | {count} | {:.1}% |",
+                percent(*count, games)
+            );
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        alpha_zero::{uniform_game, Notation, SelfPlaySample},
+        tictactoe3::{TicTacToe3, TicTacToe3Move},
+    };
+
+    use super::{hash_position, DataReport};
+
+    // Uniform policies, the first player's outcome being `first_player`
+    fn game(moves: &[usize], first_player: f32) -> Vec<SelfPlaySample<TicTacToe3>> {
+        let moves = moves.iter().map(|&m| TicTacToe3Move(m)).collect::<Vec<_>>();
+        uniform_game(TicTacToe3::new(), &moves)
+            .into_iter()
+            .map(|sample| SelfPlaySample {
+                value: sample.player.convert(first_player),
+                ..sample
+            })
+            .collect()
+    }
+
+    #[test]
+    fn statistics_of_games() {
+        // The tail of an evicted game, then two games opening in the center and one in a
+        // corner
+        let games = [
+            game(&[0, 3, 1, 4, 2], 1.0)[3..].to_vec(),
+            game(&[4, 0, 8, 2, 1, 7, 6, 3, 5], 0.5),
+            game(&[4, 0, 8, 2, 1, 7, 6, 3, 5], 0.5),
+            game(&[0, 3, 1, 4, 2], 1.0),
+        ];
+        let report = DataReport::new(
+            games.iter().flatten(),
+            Some(hash_position),
+            Some(Notation::of()),
+        );
+        assert_eq!(report.positions, 2 + 9 + 9 + 5);
+        assert_eq!(report.games, 3);
+        assert_eq!(report.game_lengths, BTreeMap::from([(5, 1), (9, 2)]));
+        assert_eq!((report.first_player_wins, report.draws), (1, 2));
+        // The repeated game, the empty board and the position after 0 and 3 of the tail
+        assert_eq!(report.duplicates, Some(9 + 1 + 2));
+        assert_eq!(report.first_moves["4"], 2);
+        assert_eq!(report.top_first_move_rate(), 2.0 / 3.0);
+        // Only the draws' last moves have no choice, and the first two moves of the whole
+        // games have over 2 nats
+        assert_eq!(report.entropy_histogram[0], 2);
+        assert_eq!(report.entropy_histogram[4], 3 * 2);
+
+        let text = report.to_markdown();
+        assert!(text.starts_with("25 positions, 3 games of 7.7 positions on average"));
+        assert!(text.contains("| 4 | 2 | 66.7% |\n| 0 | 1 | 33.3% |"));
+        assert!(text.contains("| 4.5 and more | 0 |"));
+
+        let unhashed = DataReport::new(games.iter().flatten(), None, None);
+        assert_eq!(unhashed.duplicate_rate(), None);
+        assert_eq!(unhashed.first_moves["#4"], 2);
+    }
+}
@@ -0,0 +1,123 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "torch")]
+use tch::{Cuda, Device};
+
+// Where the nets run, written as `cpu`, `cuda:<index>`, `mps` or `auto`. `cuda` alone is the
+// first GPU.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum DeviceSetting {
+    // The first GPU, Apple's GPU or else the CPU, whichever is there
+    #[default]
+    Auto,
+    Cpu,
+    Cuda(usize),
+    Mps,
+}
+
+impl fmt::Display for DeviceSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceSetting::Auto => write!(f, "auto"),
+            DeviceSetting::Cpu => write!(f, "cpu"),
+            DeviceSetting::Cuda(index) => write!(f, "cuda:{index}"),
+            DeviceSetting::Mps => write!(f, "mps"),
+        }
+    }
+}
+
+impl FromStr for DeviceSetting {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(match s.split_once(':') {
+            None if s == "auto" => DeviceSetting::Auto,
+            None if s == "cpu" => DeviceSetting::Cpu,
+            None if s == "cuda" => DeviceSetting::Cuda(0),
+            None if s == "mps" => DeviceSetting::Mps,
+            Some(("cuda", index)) => DeviceSetting::Cuda(
+                index
+                    .parse()
+                    .with_context(|| format!("Invalid GPU index {index} of device {s}"))?,
+            ),
+            _ => anyhow::bail!("Unknown device {s}, expected cpu, cuda:<index>, mps or auto"),
+        })
+    }
+}
+
+impl TryFrom<String> for DeviceSetting {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> anyhow::Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<DeviceSetting> for String {
+    fn from(setting: DeviceSetting) -> Self {
+        setting.to_string()
+    }
+}
+
+#[cfg(feature = "torch")]
+impl DeviceSetting {
+    // The device, if this build of libtorch and the machine have it
+    pub fn resolve(self) -> anyhow::Result<Device> {
+        let gpus = match Cuda::is_available() {
+            true => Cuda::device_count().max(0) as usize,
+            false => 0,
+        };
+        match self {
+            DeviceSetting::Auto if gpus > 0 => Ok(Device::Cuda(0)),
+            DeviceSetting::Auto if tch::utils::has_mps() => Ok(Device::Mps),
+            DeviceSetting::Auto | DeviceSetting::Cpu => Ok(Device::Cpu),
+            DeviceSetting::Cuda(index) if index < gpus => Ok(Device::Cuda(index)),
+            DeviceSetting::Cuda(_) if gpus == 0 => anyhow::bail!(
+                "Device {self} is not available: libtorch was built without CUDA or found no \
+                 GPU, use cpu or auto"
+            ),
+            DeviceSetting::Cuda(_) => anyhow::bail!(
+                "Device {self} is not available: found {gpus} GPUs, the last being cuda:{}",
+                gpus - 1
+            ),
+            DeviceSetting::Mps if tch::utils::has_mps() => Ok(Device::Mps),
+            DeviceSetting::Mps => anyhow::bail!(
+                "Device mps is not available: it needs Apple silicon and a libtorch built with \
+                 MPS, use cpu or auto"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeviceSetting;
+
+    #[test]
+    fn written_settings() {
+        for (text, setting) in [
+            ("auto", DeviceSetting::Auto),
+            ("cpu", DeviceSetting::Cpu),
+            ("cuda:1", DeviceSetting::Cuda(1)),
+            ("mps", DeviceSetting::Mps),
+        ] {
+            assert_eq!(text.parse::<DeviceSetting>().unwrap(), setting);
+            assert_eq!(setting.to_string(), text);
+        }
+        assert_eq!(
+            "cuda".parse::<DeviceSetting>().unwrap(),
+            DeviceSetting::Cuda(0)
+        );
+        for invalid in ["gpu", "cuda:x", "cpu:0", ""] {
+            assert!(invalid.parse::<DeviceSetting>().is_err(), "{invalid}");
+        }
+        assert_eq!(
+            serde_json::from_str::<DeviceSetting>("\"cuda:2\"").unwrap(),
+            DeviceSetting::Cuda(2)
+        );
+        assert!(serde_json::from_str::<DeviceSetting>("\"tpu\"").is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopMetric {
+    // Rating of the generation's checkpoint, higher is better. Generations that weren't
+    // rated are skipped.
+    Elo,
+    // Value plus policy loss of the generation, lower is better
+    Loss,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EarlyStoppingConfig {
+    pub metric: StopMetric,
+    // Generations without improvement before training stops
+    pub patience: usize,
+    // Smaller changes of the metric don't count as an improvement
+    #[serde(default)]
+    pub min_delta: f64,
+}
+
+// Ends training once the metric plateaus
+pub struct EarlyStopping {
+    config: EarlyStoppingConfig,
+    // Generation and value
+    best: Option<(usize, f64)>,
+    stale: usize,
+}
+
+pub enum Plateau {
+    Improved,
+    Stale,
+    Stop,
+}
+
+impl EarlyStopping {
+    pub fn new(config: EarlyStoppingConfig) -> Self {
+        Self {
+            config,
+            best: None,
+            stale: 0,
+        }
+    }
+
+    pub fn metric(&self) -> StopMetric {
+        self.config.metric
+    }
+
+    pub fn best_generation(&self) -> Option<usize> {
+        self.best.map(|(generation, _)| generation)
+    }
+
+    pub fn observe(&mut self, generation: usize, value: f64) -> Plateau {
+        let improvement = match self.config.metric {
+            StopMetric::Elo => 1.0,
+            StopMetric::Loss => -1.0,
+        } * (value - self.best.map_or(f64::NAN, |(_, best)| best));
+        // The first value, with `improvement` being NaN, is always the best so far
+        if self.best.is_none() || improvement > self.config.min_delta {
+            self.best = Some((generation, value));
+            self.stale = 0;
+            return Plateau::Improved;
+        }
+        self.stale += 1;
+        if self.stale >= self.config.patience {
+            Plateau::Stop
+        } else {
+            Plateau::Stale
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EarlyStopping, EarlyStoppingConfig, Plateau, StopMetric};
+
+    #[test]
+    fn stops_after_patience_runs_out() {
+        let mut stopping = EarlyStopping::new(EarlyStoppingConfig {
+            metric: StopMetric::Loss,
+            patience: 2,
+            min_delta: 0.01,
+        });
+        assert!(matches!(stopping.observe(0, 1.0), Plateau::Improved));
+        assert!(matches!(stopping.observe(1, 0.9), Plateau::Improved));
+        assert!(matches!(stopping.observe(2, 0.895), Plateau::Stale));
+        assert!(matches!(stopping.observe(3, 0.8), Plateau::Improved));
+        assert!(matches!(stopping.observe(4, 0.85), Plateau::Stale));
+        assert!(matches!(stopping.observe(5, 0.81), Plateau::Stop));
+        assert_eq!(stopping.best_generation(), Some(3));
+
+        let mut stopping = EarlyStopping::new(EarlyStoppingConfig {
+            metric: StopMetric::Elo,
+            patience: 1,
+            min_delta: 0.0,
+        });
+        assert!(matches!(stopping.observe(0, 100.0), Plateau::Improved));
+        assert!(matches!(stopping.observe(1, 150.0), Plateau::Improved));
+        assert!(matches!(stopping.observe(2, 120.0), Plateau::Stop));
+    }
+}
@@ -0,0 +1,85 @@
+use std::future::Future;
+#[cfg(feature = "torch")]
+use std::{marker::PhantomData, sync::Arc, time::Instant};
+
+#[cfg(feature = "torch")]
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use super::Game;
+#[cfg(feature = "torch")]
+use super::{
+    AlphaZeroAdapter, AlphaZeroNet, NetworkBatchedExecutorHandle, Seed, SelfPlayProfile, Stage,
+    SymmetryTransform,
+};
+
+// Source of leaf evaluations for the search
+pub trait Evaluator<TGame: Game> {
+    // Value of a non-terminal state and prior probabilities of its moves
+    fn evaluate(
+        &mut self,
+        state: &TGame,
+        moves: &[TGame::Move],
+    ) -> impl Future<Output = (f32, Vec<f32>)>;
+}
+
+#[cfg(feature = "torch")]
+pub struct NetworkEvaluator<TGame, TNet: AlphaZeroNet, TAdapter> {
+    executor: NetworkBatchedExecutorHandle<TNet>,
+    symmetries: Vec<SymmetryTransform>,
+    rng: StdRng,
+    profile: Option<Arc<SelfPlayProfile>>,
+    _p: PhantomData<(TGame, TAdapter)>,
+}
+
+#[cfg(feature = "torch")]
+impl<TGame: Game, TNet: AlphaZeroNet, TAdapter: AlphaZeroAdapter<TGame, TNet>>
+    NetworkEvaluator<TGame, TNet, TAdapter>
+{
+    pub fn new(executor: NetworkBatchedExecutorHandle<TNet>) -> Self {
+        Self::with_rng(executor, StdRng::from_entropy())
+    }
+
+    // Symmetries are drawn from the seed, for reproducible self-play
+    pub fn with_seed(executor: NetworkBatchedExecutorHandle<TNet>, seed: Seed) -> Self {
+        Self::with_rng(executor, seed.rng())
+    }
+
+    fn with_rng(executor: NetworkBatchedExecutorHandle<TNet>, rng: StdRng) -> Self {
+        Self {
+            executor,
+            symmetries: TAdapter::symmetries(),
+            rng,
+            profile: None,
+            _p: PhantomData,
+        }
+    }
+
+    // Records the time of the conversions and of the waiting for the executor
+    pub fn with_profile(mut self, profile: Arc<SelfPlayProfile>) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+}
+
+#[cfg(feature = "torch")]
+impl<TGame: Game, TNet: AlphaZeroNet, TAdapter: AlphaZeroAdapter<TGame, TNet>> Evaluator<TGame>
+    for NetworkEvaluator<TGame, TNet, TAdapter>
+{
+    async fn evaluate(&mut self, state: &TGame, moves: &[TGame::Move]) -> (f32, Vec<f32>) {
+        let started = self.profile.as_ref().map(|_| Instant::now());
+        // Evaluate under a random symmetry to average out the net's orientation bias
+        let symmetry = self.symmetries.choose(&mut self.rng).unwrap();
+        let input = symmetry.transform_state(&TAdapter::convert_game_to_nn_input(state));
+        let sent = started.map(|_| Instant::now());
+        let (value, policy) = self.executor.execute(input).await;
+        let waiting = sent.map(|sent| sent.elapsed());
+        let value = f32::try_from(value).unwrap();
+        let policy = TAdapter::get_estimated_policy(&symmetry.inverse_policy(&policy), moves);
+        if let (Some(profile), Some(started), Some(waiting)) = (&self.profile, started, waiting) {
+            profile.add_evaluation(waiting);
+            // Both ways
+            profile.add(Stage::Conversion, started.elapsed() - waiting);
+        }
+        (value, policy)
+    }
+}
@@ -6,8 +6,11 @@ use tokio::{
     sync::{mpsc::Sender, Semaphore},
     task::JoinHandle,
 };
+use tracing::debug;
 
-use super::{AlphaZeroNet, BatcherCommand, NetworkBatchedExecutor, NetworkBatchedExecutorHandle};
+use super::{
+    AlphaZeroNet, BatchStats, BatcherCommand, NetworkBatchedExecutor, NetworkBatchedExecutorHandle,
+};
 
 struct BatchSizeManager {
     current_batch_size: usize,
@@ -57,13 +60,17 @@ impl BatchSizeManager {
 }
 
 pub struct ExecutorScope<T, TNet: AlphaZeroNet> {
-    results: FuturesUnordered<JoinHandle<T>>,
+    // `None` for tasks that never started, see `stop_admitting`
+    results: FuturesUnordered<JoinHandle<Option<T>>>,
     parallelism: Arc<Semaphore>,
     parallelism_tokens: usize,
+    // Tasks of other scopes evaluating through `handle()`
+    external_tasks: usize,
     batch_size_manager: BatchSizeManager,
-    executor_cmd: Sender<BatcherCommand>,
+    executor_cmd: Sender<BatcherCommand<TNet>>,
     executor_handle: NetworkBatchedExecutorHandle<TNet>,
     executor: JoinHandle<TNet>,
+    batch_stats: Arc<BatchStats>,
 }
 
 impl<T, TNet: AlphaZeroNet + Send + 'static> ExecutorScope<T, TNet> {
@@ -77,20 +84,26 @@ impl<T, TNet: AlphaZeroNet + Send + 'static> ExecutorScope<T, TNet> {
         let executor = NetworkBatchedExecutor::new(nn);
         let handle = executor.mint_handle();
         let (cmd_tx, cmd_rx) = tokio::sync::mpsc::channel(1);
-        let executor = tokio::spawn(async move {
-            executor
-                .serve(batch_size, batch_acc_time, cmd_rx, options)
-                .await
+        let batch_stats = Arc::new(BatchStats::default());
+        let executor = tokio::spawn({
+            let batch_stats = batch_stats.clone();
+            async move {
+                executor
+                    .serve(batch_size, batch_acc_time, cmd_rx, options, batch_stats)
+                    .await
+            }
         });
 
         Self {
             results: FuturesUnordered::new(),
             parallelism: Arc::new(Semaphore::new(parallelism)),
             parallelism_tokens: parallelism,
+            external_tasks: 0,
             batch_size_manager: BatchSizeManager::new(batch_size, (5, 6)),
             executor_cmd: cmd_tx,
             executor_handle: handle,
             executor,
+            batch_stats,
         }
     }
 
@@ -107,12 +120,17 @@ impl<T, TNet: AlphaZeroNet + Send + 'static> ExecutorScope<T, TNet> {
             let f = f(self.executor_handle.clone());
             let par = self.parallelism.clone();
             tokio::spawn(async move {
-                let _perm = par.acquire().await.unwrap();
-                f.await
+                let _perm = par.acquire().await.ok()?;
+                Some(f.await)
             })
         });
     }
 
+    // Tasks waiting for their turn are dropped, the running ones still finish
+    pub fn stop_admitting(&self) {
+        self.parallelism.close();
+    }
+
     pub async fn increase_parallelism(&mut self, delta: usize) {
         self.parallelism_tokens += delta;
         self.parallelism.add_permits(delta);
@@ -121,6 +139,11 @@ impl<T, TNet: AlphaZeroNet + Send + 'static> ExecutorScope<T, TNet> {
 
     pub async fn set_batch_size(&mut self, batch_size: usize) {
         if let Some(v) = self.batch_size_manager.change_max_batch_size(batch_size) {
+            debug!(
+                max_batch_size = batch_size,
+                batch_size = v,
+                "Capping the batch size"
+            );
             self.executor_cmd
                 .send(BatcherCommand::SetBatchSize(v))
                 .await
@@ -129,9 +152,36 @@ impl<T, TNet: AlphaZeroNet + Send + 'static> ExecutorScope<T, TNet> {
         self.on_tasks_count_change().await;
     }
 
+    // For evaluations outside of the scope's tasks, e.g. by games spawned on another scope
+    pub fn handle(&self) -> NetworkBatchedExecutorHandle<TNet> {
+        self.executor_handle.clone()
+    }
+
+    pub fn batch_stats(&self) -> Arc<BatchStats> {
+        self.batch_stats.clone()
+    }
+
+    // Games in progress continue with the new network
+    pub async fn swap_net(&mut self, nn: TNet) {
+        self.executor_cmd
+            .send(BatcherCommand::SwapNet(nn))
+            .await
+            .unwrap();
+    }
+
+    // Counts tasks running elsewhere into the batch size, e.g. for a scope that only serves
+    // evaluations
+    pub async fn set_external_tasks(&mut self, tasks: usize) {
+        if tasks != self.external_tasks {
+            self.external_tasks = tasks;
+            self.on_tasks_count_change().await;
+        }
+    }
+
     pub async fn on_tasks_count_change(&mut self) {
-        let tasks = self.len().min(self.parallelism_tokens);
+        let tasks = (self.len() + self.external_tasks).min(self.parallelism_tokens);
         if let Some(batch) = self.batch_size_manager.on_task_count_change(tasks) {
+            debug!(tasks, batch_size = batch, "Following the tasks in flight");
             self.executor_cmd
                 .send(BatcherCommand::SetBatchSize(batch))
                 .await
@@ -140,9 +190,23 @@ impl<T, TNet: AlphaZeroNet + Send + 'static> ExecutorScope<T, TNet> {
     }
 
     pub async fn next(&mut self) -> Option<T> {
-        let res = self.results.next().await.map(Result::unwrap);
-        self.on_tasks_count_change().await;
-        res
+        loop {
+            let res = self.results.next().await.map(Result::unwrap);
+            self.on_tasks_count_change().await;
+            match res {
+                Some(None) => continue,
+                Some(Some(res)) => return Some(res),
+                None => return None,
+            }
+        }
+    }
+
+    // Cancels the tasks that haven't finished
+    pub fn abort(&mut self) {
+        for task in self.results.iter() {
+            task.abort();
+        }
+        self.results.clear();
     }
 
     pub async fn join(self) -> TNet {
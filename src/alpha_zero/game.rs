@@ -1,7 +1,46 @@
+use std::{error::Error, fmt::Display};
+
+// Value convention used throughout the crate: a value is the expected score of the player
+// to move in the state it belongs to, in [0, 1] (1 - win, 0.5 - draw, 0 - loss).
+// Terminal states, network value estimates, tree statistics and training targets all follow it,
+// and `Perspective` is the only place where values are converted between the two players.
 pub trait MoveParameters {
     fn is_player_switch(&self) -> bool;
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Perspective {
+    Same,
+    Opponent,
+}
+
+impl Perspective {
+    // Relation between the player to move before and after `m`
+    pub fn after_move(m: &impl MoveParameters) -> Self {
+        if m.is_player_switch() {
+            Perspective::Opponent
+        } else {
+            Perspective::Same
+        }
+    }
+
+    // Perspective relation of two consecutive relations
+    pub fn then(self, other: Perspective) -> Self {
+        if self == other {
+            Perspective::Same
+        } else {
+            Perspective::Opponent
+        }
+    }
+
+    pub fn convert(self, value: f32) -> f32 {
+        match self {
+            Perspective::Same => value,
+            Perspective::Opponent => 1.0 - value,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TerminationState<Move> {
     Terminal(f32),
@@ -24,10 +63,85 @@ impl<Move> TerminationState<Move> {
     }
 }
 
-pub trait Game {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IllegalMove;
+
+impl Display for IllegalMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "illegal move")
+    }
+}
+
+impl Error for IllegalMove {}
+
+pub trait Game: Sized {
     type Move: MoveParameters;
 
     fn get_state(&self) -> TerminationState<Self::Move>;
     // Should "switch" player if the move does so
     fn make_move(&self, m: &Self::Move) -> Self;
+
+    fn is_legal(&self, m: &Self::Move) -> bool;
+
+    // Same as make_move, but for moves coming from untrusted sources (humans, other engines)
+    fn try_make_move(&self, m: &Self::Move) -> Result<Self, IllegalMove> {
+        if self.is_legal(m) {
+            Ok(self.make_move(m))
+        } else {
+            Err(IllegalMove)
+        }
+    }
+}
+
+// Games that can take a move back, used by interactive tools to step backwards
+pub trait ReversibleGame: Game {
+    // `m` must be the last move that led to this state
+    fn undo_move(&self, m: &Self::Move) -> Self;
+}
+
+// Current position plus the moves leading to it, with undo/redo support.
+// Only the current state is stored, earlier ones are recovered through `undo_move`.
+pub struct MoveHistory<TGame: ReversibleGame> {
+    state: TGame,
+    moves: Vec<TGame::Move>,
+    undone: Vec<TGame::Move>,
+}
+
+impl<TGame: ReversibleGame> MoveHistory<TGame> {
+    pub fn new(start: TGame) -> Self {
+        Self {
+            state: start,
+            moves: vec![],
+            undone: vec![],
+        }
+    }
+
+    pub fn state(&self) -> &TGame {
+        &self.state
+    }
+
+    pub fn moves(&self) -> &[TGame::Move] {
+        &self.moves
+    }
+
+    pub fn play(&mut self, m: TGame::Move) -> Result<(), IllegalMove> {
+        self.state = self.state.try_make_move(&m)?;
+        self.moves.push(m);
+        self.undone.clear();
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Option<&TGame::Move> {
+        let m = self.moves.pop()?;
+        self.state = self.state.undo_move(&m);
+        self.undone.push(m);
+        self.undone.last()
+    }
+
+    pub fn redo(&mut self) -> Option<&TGame::Move> {
+        let m = self.undone.pop()?;
+        self.state = self.state.make_move(&m);
+        self.moves.push(m);
+        self.moves.last()
+    }
 }
@@ -0,0 +1,36 @@
+use serde::Serialize;
+
+use super::{MatchConfig, MatchResult, SprtConfig, SprtDecision};
+
+// A new generation only replaces the one self-play uses if it scores at least `threshold`
+// against it, counting draws as half a win. With an SPRT in `games`, the match usually ends
+// sooner and its decision is taken instead.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct GatingConfig {
+    pub games: MatchConfig,
+    pub threshold: f64,
+}
+
+impl GatingConfig {
+    pub fn passes(&self, result: &MatchResult) -> bool {
+        match self.games.sprt.map(|sprt| sprt.decide(result)) {
+            Some(SprtDecision::Accept) => true,
+            Some(SprtDecision::Reject) => false,
+            // Out of games before the test decided
+            Some(SprtDecision::Continue) | None => result.score() >= self.threshold,
+        }
+    }
+}
+
+impl Default for GatingConfig {
+    fn default() -> Self {
+        Self {
+            games: MatchConfig {
+                games: 400,
+                sprt: Some(SprtConfig::default()),
+                ..Default::default()
+            },
+            threshold: 0.55,
+        }
+    }
+}
@@ -1,9 +1,108 @@
-use rand::thread_rng;
+#[cfg(feature = "torch")]
+use std::sync::Arc;
 
-use crate::alpha_zero::{AlphaZeroAdapter, AlphaZeroNet, Game, MonteCarloTree, MoveParameters};
+#[cfg(any(test, feature = "torch"))]
+use crate::alpha_zero::Game;
+use crate::alpha_zero::Perspective;
+#[cfg(feature = "torch")]
+use crate::alpha_zero::{AlphaZeroAdapter, AlphaZeroNet, MonteCarloTree, NetworkEvaluator};
 
-use super::{sample_policy, NetworkBatchedExecutorHandle, TerminationState};
+#[cfg(feature = "torch")]
+use super::{
+    sample_policy, NetworkBatchedExecutorHandle, OpeningBook, Seed, SelfPlayProfile,
+    TerminationState,
+};
 
+#[derive(Clone, Debug)]
+pub struct SelfPlaySample<TGame> {
+    pub state: TGame,
+    // MCTS visit distribution over the state's moves
+    pub policy: Vec<f32>,
+    // Final outcome for the player to move
+    pub value: f32,
+    // Of the cells at the end of the game, see `AlphaZeroAdapter::ownership_target`. Not
+    // kept by the data store.
+    pub ownership: Option<Vec<f32>>,
+    pub move_number: usize,
+    // Player to move, relative to the one who moved first
+    pub player: Perspective,
+    // Search's value estimate of the state
+    pub root_q: f32,
+    // Visits of the root when the move was chosen
+    pub simulations: usize,
+    // Index of the move played among the state's moves
+    pub played: usize,
+    // Of the learner when the game reached it, 0 until then
+    pub generation: usize,
+}
+
+// Fixtures of tests, which overwrite the fields they check
+#[cfg(test)]
+impl<TGame> SelfPlaySample<TGame> {
+    // A uniform policy over `moves` moves, the first of them played after a single visit,
+    // and a drawn game
+    pub(crate) fn uniform(state: TGame, moves: usize) -> Self {
+        Self {
+            state,
+            policy: vec![1.0 / moves as f32; moves],
+            value: 0.5,
+            ownership: None,
+            move_number: 0,
+            player: Perspective::Same,
+            root_q: 0.5,
+            simulations: 1,
+            played: 0,
+            generation: 0,
+        }
+    }
+}
+
+// Uniform samples of the game playing `moves` from `start`
+#[cfg(test)]
+pub(crate) fn uniform_game<TGame>(start: TGame, moves: &[TGame::Move]) -> Vec<SelfPlaySample<TGame>>
+where
+    TGame: Game,
+    TGame::Move: PartialEq,
+{
+    let mut state = start;
+    let mut player = Perspective::Same;
+    let mut samples = vec![];
+    for m in moves {
+        let legal = state.get_state().get_moves().unwrap();
+        let next = state.make_move(m);
+        samples.push(SelfPlaySample {
+            move_number: samples.len(),
+            player,
+            played: legal.iter().position(|l| l == m).unwrap(),
+            ..SelfPlaySample::uniform(state, legal.len())
+        });
+        player = player.then(Perspective::after_move(m));
+        state = next;
+    }
+    samples
+}
+
+// Sets the ownership targets of a whole game's samples, in order, if the adapter has them
+#[cfg(feature = "torch")]
+pub fn set_ownership_targets<TGame, TNet, TAdapter>(game: &mut [SelfPlaySample<TGame>])
+where
+    TGame: Game,
+    TNet: AlphaZeroNet,
+    TAdapter: AlphaZeroAdapter<TGame, TNet>,
+{
+    let Some(last) = game.last() else {
+        return;
+    };
+    let moves = last.state.get_state().get_moves().unwrap();
+    let final_state = last.state.make_move(&moves[last.played]);
+    for sample in game {
+        sample.ownership = TAdapter::ownership_target(&sample.state, &final_state);
+    }
+}
+
+// Records where the time goes into `profile` if given
+#[cfg(feature = "torch")]
+#[allow(clippy::too_many_arguments)]
 pub async fn generate_self_played_game<
     TGame: Game + Clone,
     TNet: AlphaZeroNet,
@@ -11,12 +110,28 @@ pub async fn generate_self_played_game<
     F: FnMut(usize) -> f32,
 >(
     start: TGame,
+    opening: Option<OpeningBook<TGame>>,
     samples: usize,
     c_puct: f32,
     mut temp: F,
+    seed: Seed,
     executor: NetworkBatchedExecutorHandle<TNet>,
-) -> Vec<(TGame, Vec<f32>, f32)> {
-    let mut tree = MonteCarloTree::<TGame, TNet, TAdapter>::new(start.clone(), executor);
+    profile: Option<Arc<SelfPlayProfile>>,
+) -> Vec<SelfPlaySample<TGame>> {
+    let mut rng = seed.derive("moves").rng();
+    let start = match opening {
+        Some(book) => book.sample(&start, &mut rng),
+        None => start,
+    };
+    let evaluator =
+        NetworkEvaluator::<TGame, TNet, TAdapter>::with_seed(executor, seed.derive("symmetries"));
+    let mut tree = match &profile {
+        Some(profile) => {
+            MonteCarloTree::new(start.clone(), evaluator.with_profile(profile.clone()))
+                .with_profile(profile.clone())
+        }
+        None => MonteCarloTree::new(start.clone(), evaluator),
+    };
     // let mut tree = tree.try_lock().unwrap();
     let mut turn = 0;
 
@@ -24,6 +139,8 @@ pub async fn generate_self_played_game<
 
     let mut history = vec![];
 
+    let mut player = Perspective::Same;
+
     let mut value = loop {
         let moves = match state.get_state() {
             TerminationState::Moves(moves) => moves,
@@ -31,25 +148,45 @@ pub async fn generate_self_played_game<
         };
         tree.do_simulations(samples, c_puct).await;
         let policy = tree.get_policy();
+        let root_q = tree.get_root_value();
+        let simulations = tree.get_root_visits();
 
-        let r#move = sample_policy(&policy, temp(turn), &mut thread_rng());
+        let r#move = sample_policy(&policy, temp(turn), &mut rng);
 
         // println!("policy: {policy:?}, move: {move}");
 
         let new_state = state.make_move(&moves[r#move]);
         tree.do_move(r#move);
 
-        history.push((state, policy, moves[r#move].is_player_switch()));
+        let perspective = Perspective::after_move(&moves[r#move]);
+        history.push((
+            perspective,
+            SelfPlaySample {
+                state,
+                policy,
+                value: 0.0,
+                ownership: None,
+                move_number: turn,
+                player,
+                root_q,
+                simulations,
+                played: r#move,
+                generation: 0,
+            },
+        ));
         state = new_state;
+        player = player.then(perspective);
         turn += 1;
     };
 
+    if let Some(profile) = &profile {
+        profile.add_game(history.len());
+    }
     let mut result = Vec::with_capacity(history.len());
-    while let Some((state, policy, switch)) = history.pop() {
-        if switch {
-            value = 1.0 - value;
-        }
-        result.push((state, policy, value));
+    while let Some((perspective, mut sample)) = history.pop() {
+        value = perspective.convert(value);
+        sample.value = value;
+        result.push(sample);
     }
     result.reverse();
     result
@@ -0,0 +1,378 @@
+use std::{
+    io::{BufRead, Write},
+    time::Duration,
+};
+
+use anyhow::Context;
+
+use super::{Agent, Game, IllegalMove, Perspective, SgfGame, TerminationState, TimeControl};
+
+// As `(row, column)` from the top left corner, `None` for a pass
+type Point = Option<(usize, usize)>;
+
+// `SgfGame`'s board as plain functions, for engines speaking the Go Text Protocol
+pub struct GtpBoard<TGame: Game> {
+    pub board_size: fn(&TGame) -> usize,
+    pub move_point: fn(&TGame, &TGame::Move) -> Point,
+    pub point_move: fn(&TGame, Point) -> anyhow::Result<TGame::Move>,
+}
+
+impl<TGame: Game> Clone for GtpBoard<TGame> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<TGame: Game> Copy for GtpBoard<TGame> {}
+
+impl<TGame: SgfGame> GtpBoard<TGame> {
+    pub fn of() -> Self {
+        Self {
+            board_size: TGame::board_size,
+            move_point: TGame::move_point,
+            point_move: TGame::point_move,
+        }
+    }
+}
+
+// Of the columns, I being left out
+const COLUMNS: &[u8] = b"ABCDEFGHJKLMNOPQRSTUVWXYZ";
+
+const COMMANDS: [&str; 13] = [
+    "boardsize",
+    "clear_board",
+    "genmove",
+    "known_command",
+    "komi",
+    "list_commands",
+    "name",
+    "play",
+    "protocol_version",
+    "quit",
+    "showboard",
+    "time_settings",
+    "version",
+];
+
+// A GTP engine playing the moves of agents made by `new_agent` for the time control, a fresh
+// one per game. Black is the first player. The board size is the game's, `boardsize` only
+// accepting it, and moves are only taken from the player to move.
+pub struct GtpEngine<TGame: Game, TAgent, F> {
+    start: TGame,
+    board: GtpBoard<TGame>,
+    text: Option<fn(&TGame) -> String>,
+    new_agent: F,
+    time_control: Option<TimeControl>,
+    agent: TAgent,
+    state: TGame,
+    black_to_move: bool,
+}
+
+impl<TGame, TAgent, F> GtpEngine<TGame, TAgent, F>
+where
+    TGame: Game + Clone,
+    TGame::Move: PartialEq,
+    TAgent: Agent<TGame>,
+    F: FnMut(Option<TimeControl>) -> TAgent,
+{
+    pub fn new(
+        start: TGame,
+        board: GtpBoard<TGame>,
+        text: Option<fn(&TGame) -> String>,
+        time_control: Option<TimeControl>,
+        mut new_agent: F,
+    ) -> Self {
+        Self {
+            state: start.clone(),
+            start,
+            board,
+            text,
+            agent: new_agent(time_control),
+            new_agent,
+            time_control,
+            black_to_move: true,
+        }
+    }
+
+    fn clear_board(&mut self) {
+        self.state = self.start.clone();
+        self.black_to_move = true;
+        self.agent = (self.new_agent)(self.time_control);
+    }
+
+    fn check_to_move(&self, color: &str) -> anyhow::Result<()> {
+        let black = match color.to_ascii_lowercase().as_str() {
+            "b" | "black" => true,
+            "w" | "white" => false,
+            _ => anyhow::bail!("syntax error"),
+        };
+        anyhow::ensure!(black == self.black_to_move, "illegal move");
+        Ok(())
+    }
+
+    fn vertex(&self, point: Point) -> String {
+        let size = (self.board.board_size)(&self.state);
+        match point {
+            Some((row, column)) => format!("{}{}", COLUMNS[column] as char, size - row),
+            None => "pass".to_owned(),
+        }
+    }
+
+    fn parse_vertex(&self, text: &str) -> anyhow::Result<Point> {
+        let text = text.to_ascii_uppercase();
+        if text == "PASS" {
+            return Ok(None);
+        }
+        let size = (self.board.board_size)(&self.state);
+        let parse = || -> Option<(usize, usize)> {
+            let letter = *text.as_bytes().first()?;
+            let column = COLUMNS.iter().position(|&c| c == letter)?;
+            let row = text[1..].parse::<usize>().ok()?;
+            (column < size && (1..=size).contains(&row)).then_some((size - row, column))
+        };
+        parse().context("syntax error").map(Some)
+    }
+
+    // Leaves the game as it was if the move is illegal
+    fn make_move(&mut self, m: &TGame::Move) -> Result<(), IllegalMove> {
+        self.state = self.state.try_make_move(m)?;
+        if Perspective::after_move(m) == Perspective::Opponent {
+            self.black_to_move = !self.black_to_move;
+        }
+        Ok(())
+    }
+
+    // The response to a command, errors being GTP's messages
+    async fn execute(&mut self, command: &str, args: &[&str]) -> anyhow::Result<String> {
+        let response = match (command, args) {
+            ("protocol_version", []) => "2".to_owned(),
+            ("name", []) => "alpha-zero".to_owned(),
+            ("version", []) => env!("CARGO_PKG_VERSION").to_owned(),
+            ("known_command", [name]) => COMMANDS.contains(name).to_string(),
+            ("list_commands", []) => COMMANDS.join("\n"),
+            ("quit", []) => String::new(),
+            ("boardsize", [size]) => {
+                let size = size.parse::<usize>().context("syntax error")?;
+                anyhow::ensure!(
+                    size == (self.board.board_size)(&self.start),
+                    "unacceptable size"
+                );
+                self.clear_board();
+                String::new()
+            }
+            ("clear_board", []) => {
+                self.clear_board();
+                String::new()
+            }
+            // Left to the game's rules
+            ("komi", [komi]) => {
+                komi.parse::<f32>().context("syntax error")?;
+                String::new()
+            }
+            ("play", [color, vertex]) => {
+                self.check_to_move(color)?;
+                let point = self.parse_vertex(vertex)?;
+                let m = (self.board.point_move)(&self.state, point).context("illegal move")?;
+                self.make_move(&m).context("illegal move")?;
+                String::new()
+            }
+            ("genmove", [color]) => {
+                self.check_to_move(color)?;
+                let TerminationState::Moves(moves) = self.state.get_state() else {
+                    anyhow::bail!("game is over");
+                };
+                let m = &moves[self.agent.select_move(&self.state).await];
+                let vertex = self.vertex((self.board.move_point)(&self.state, m));
+                self.make_move(m)?;
+                vertex
+            }
+            // Byo-yomi periods are approximated by an increment of their time per stone
+            ("time_settings", [main, period, stones]) => {
+                let seconds = |text: &str| -> anyhow::Result<Duration> {
+                    let seconds = text.parse::<u64>().context("syntax error")?;
+                    Ok(Duration::from_secs(seconds))
+                };
+                let (main, period) = (seconds(main)?, seconds(period)?);
+                let stones = stones.parse::<u32>().context("syntax error")?;
+                self.time_control = match (period.is_zero(), stones) {
+                    (true, _) if main.is_zero() => None,
+                    (false, 0) => None,
+                    (true, _) => Some(TimeControl::Fischer {
+                        base: main,
+                        increment: Duration::ZERO,
+                    }),
+                    (false, stones) => Some(TimeControl::Fischer {
+                        base: main,
+                        increment: period / stones,
+                    }),
+                };
+                self.agent = (self.new_agent)(self.time_control);
+                String::new()
+            }
+            ("showboard", []) => {
+                let text = self.text.context("The game has no text board")?;
+                format!("\n{}", text(&self.state))
+            }
+            _ if COMMANDS.contains(&command) => anyhow::bail!("syntax error"),
+            _ => anyhow::bail!("unknown command"),
+        };
+        Ok(response)
+    }
+
+    // Answers the commands of `input` until it closes or says `quit`
+    pub async fn serve(
+        &mut self,
+        mut input: impl BufRead,
+        mut output: impl Write,
+    ) -> anyhow::Result<()> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if input
+                .read_line(&mut line)
+                .context("Failed to read a command")?
+                == 0
+            {
+                return Ok(());
+            }
+            let text = line.split('#').next().unwrap();
+            let mut words = text.split_whitespace().peekable();
+            let id = words
+                .next_if(|word| word.parse::<u32>().is_ok())
+                .unwrap_or("");
+            let Some(command) = words.next() else {
+                continue;
+            };
+            let args = words.collect::<Vec<_>>();
+            match self.execute(command, &args).await {
+                Ok(response) if response.is_empty() => write!(output, "={id}\n\n")?,
+                Ok(response) => write!(output, "={id} {response}\n\n")?,
+                Err(e) => write!(output, "?{id} {e}\n\n")?,
+            }
+            output.flush()?;
+            if command == "quit" {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        alpha_zero::{Agent, Game, TerminationState, TimeControl},
+        go::{GoConfig, GoState},
+        tictactoe::GomokuBoard,
+    };
+
+    use super::{GtpBoard, GtpEngine};
+
+    // Takes the first free point, remembering the time control it was made for
+    struct FirstPoint(Option<TimeControl>);
+
+    impl Agent<GomokuBoard<15, 5>> for FirstPoint {
+        async fn select_move(&mut self, _state: &GomokuBoard<15, 5>) -> usize {
+            0
+        }
+    }
+
+    // Passes, the last of Go's moves
+    struct Pass;
+
+    impl Agent<GoState> for Pass {
+        async fn select_move(&mut self, state: &GoState) -> usize {
+            state.get_state().get_moves().unwrap().len() - 1
+        }
+    }
+
+    #[tokio::test]
+    async fn session_with_a_gui() {
+        let mut engine = GtpEngine::new(
+            GomokuBoard::<15, 5>::new(),
+            GtpBoard::of(),
+            None,
+            None,
+            FirstPoint,
+        );
+        let input = "1 protocol_version\n\
+                     boardsize 19\n\
+                     boardsize 15\n\
+                     time_settings 300 0 0\n\
+                     play B H8 # tengen\n\
+                     \n\
+                     2 genmove w\n\
+                     play w J9\n\
+                     play b A15\n\
+                     play b Z1\n\
+                     genmove b\n\
+                     frob\n\
+                     quit\n\
+                     name\n";
+        let mut output = vec![];
+        engine.serve(Cursor::new(input), &mut output).await.unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "=1 2\n\n\
+             ? unacceptable size\n\n\
+             =\n\n\
+             =\n\n\
+             =\n\n\
+             =2 A15\n\n\
+             ? illegal move\n\n\
+             ? illegal move\n\n\
+             ? syntax error\n\n\
+             = B15\n\n\
+             ? unknown command\n\n\
+             =\n\n"
+        );
+        assert_eq!(
+            engine.agent.0,
+            Some(TimeControl::Fischer {
+                base: std::time::Duration::from_secs(300),
+                increment: std::time::Duration::ZERO
+            })
+        );
+        assert_eq!(
+            engine.state.get_state().get_moves().unwrap().len(),
+            15 * 15 - 3
+        );
+    }
+
+    #[tokio::test]
+    async fn go_session() {
+        let mut engine = GtpEngine::new(
+            GoState::new(GoConfig::default()),
+            GtpBoard::of(),
+            None,
+            None,
+            |_| Pass,
+        );
+        let input = "boardsize 9\n\
+                     komi 7.5\n\
+                     play b E5\n\
+                     play w E5\n\
+                     play w J9\n\
+                     genmove b\n\
+                     play w pass\n\
+                     genmove b\n";
+        let mut output = vec![];
+        engine.serve(Cursor::new(input), &mut output).await.unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "=\n\n\
+             =\n\n\
+             =\n\n\
+             ? illegal move\n\n\
+             =\n\n\
+             = pass\n\n\
+             =\n\n\
+             ? game is over\n\n"
+        );
+        // Each side has a stone and no territory, so komi decides
+        assert_eq!(engine.state.get(4, 4), crate::tictactoe::CellState::X);
+        assert_eq!(engine.state.get(0, 8), crate::tictactoe::CellState::O);
+        assert_eq!(engine.state.get_state(), TerminationState::Terminal(0.0));
+    }
+}
@@ -0,0 +1,185 @@
+use std::{fmt, path::PathBuf, str::FromStr, time::Duration};
+
+use anyhow::Context;
+use tch::{nn, Device, Kind};
+
+use super::{
+    play_logged_agents, Agent, AlphaZeroAdapter, AlphaZeroNet, Baseline, BaselineAgent,
+    ExecutorScope, Game, GameLog, MatchConfig, MatchResult, MatchStats, MctsAgent,
+    NetworkEvaluator, OpeningBook, SearchInfo, Significance, MATCH_TEMPERATURE,
+};
+
+// One side of a head-to-head match: a baseline by name, or a file of weights
+#[derive(Clone, Debug, PartialEq)]
+pub enum Contender {
+    Weights(PathBuf),
+    Baseline(Baseline),
+}
+
+impl FromStr for Contender {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        if let Ok(baseline) = s.parse() {
+            return Ok(Contender::Baseline(baseline));
+        }
+        let path = PathBuf::from(s);
+        anyhow::ensure!(
+            path.is_file(),
+            "{s} is neither a baseline nor a file of weights"
+        );
+        Ok(Contender::Weights(path))
+    }
+}
+
+impl fmt::Display for Contender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Contender::Weights(path) => write!(f, "{}", path.display()),
+            Contender::Baseline(baseline) => write!(f, "{baseline}"),
+        }
+    }
+}
+
+pub enum ContenderAgent<TGame, TNet: AlphaZeroNet, TAdapter> {
+    // Boxed for the evaluator's random generator
+    Net(Box<MctsAgent<NetworkEvaluator<TGame, TNet, TAdapter>>>),
+    Baseline(BaselineAgent<TGame>),
+}
+
+impl<TGame, TNet, TAdapter> Agent<TGame> for ContenderAgent<TGame, TNet, TAdapter>
+where
+    TGame: Game + Clone,
+    TNet: AlphaZeroNet,
+    TAdapter: AlphaZeroAdapter<TGame, TNet>,
+{
+    async fn select_move(&mut self, state: &TGame) -> usize {
+        match self {
+            ContenderAgent::Net(agent) => agent.select_move(state).await,
+            ContenderAgent::Baseline(agent) => agent.select_move(state).await,
+        }
+    }
+
+    fn last_search(&self) -> Option<&SearchInfo> {
+        match self {
+            ContenderAgent::Net(agent) => Agent::<TGame>::last_search(agent.as_ref()),
+            ContenderAgent::Baseline(agent) => agent.last_search(),
+        }
+    }
+}
+
+// Plays `config.games` between the two contenders, the first one moving first in every other
+// game. Nets search with the config's settings, each on an executor of its own. Results are
+// from the first contender's point of view.
+#[allow(clippy::too_many_arguments)]
+pub async fn play_head_to_head<TGame, TNet, TAdapter>(
+    build_net: &dyn Fn(&nn::Path) -> TNet,
+    device: Device,
+    start: &TGame,
+    openings: Option<&OpeningBook<TGame>>,
+    heuristic: Option<fn(&TGame) -> f32>,
+    config: &MatchConfig,
+    contender: &Contender,
+    opponent: &Contender,
+) -> anyhow::Result<(MatchStats, Vec<GameLog>)>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    // Only serve the evaluations, the games run on this task
+    let executor = |contender: &Contender| -> anyhow::Result<_> {
+        let Contender::Weights(path) = contender else {
+            return Ok(None);
+        };
+        let mut vs = nn::VarStore::new(device);
+        let net = build_net(&vs.root());
+        vs.load(path)
+            .with_context(|| format!("Failed to load {}", path.display()))?;
+        Ok(Some(ExecutorScope::<(), _>::new(
+            net,
+            config.parallelism,
+            config.parallelism,
+            Duration::from_millis(10),
+            (Kind::Float, device),
+        )))
+    };
+    let executors = [executor(contender)?, executor(opponent)?];
+    let agent = |i: usize| {
+        let contender = [contender, opponent][i];
+        let executor = &executors[i];
+        move || match (contender, executor) {
+            (_, Some(executor)) => ContenderAgent::Net(Box::new(
+                MctsAgent::new(
+                    NetworkEvaluator::<TGame, TNet, TAdapter>::new(executor.handle()),
+                    config.simulations,
+                    config.c_puct,
+                    MATCH_TEMPERATURE,
+                )
+                .with_time_control(config.time_control),
+            )),
+            (Contender::Baseline(baseline), None) => {
+                ContenderAgent::Baseline(baseline.agent(heuristic, MATCH_TEMPERATURE))
+            }
+            (Contender::Weights(_), None) => unreachable!("Weights always get an executor"),
+        }
+    };
+
+    let result = play_logged_agents(start, openings, config, agent(0), agent(1)).await;
+    for executor in executors.into_iter().flatten() {
+        executor.join().await;
+    }
+    Ok(result)
+}
+
+// Markdown summary from the first contender's point of view, with a 95% interval of the score
+// and the Elo difference it corresponds to, then the score's Wilson and exact intervals and the
+// likelihood that the contender is the stronger
+pub fn match_summary(contender: &Contender, opponent: &Contender, result: &MatchResult) -> String {
+    let elo = |score: f64| 400.0 * (score / (1.0 - score)).log10();
+    let (lo, hi) = result.confidence_interval(1.96);
+    let significance = Significance::of(result);
+    let (wilson_lo, wilson_hi) = significance.wilson;
+    let (exact_lo, exact_hi) = significance.clopper_pearson;
+    format!(
+        "{contender} against {opponent}\n\n\
+         | games | wins | draws | losses | score | 95% interval | elo | 95% interval \
+         | 95% wilson | 95% exact | LOS |\n\
+         |---|---|---|---|---|---|---|---|---|---|---|\n\
+         | {} | {} | {} | {} | {:.3} | {lo:.3} to {hi:.3} | {:.0} | {:.0} to {:.0} \
+         | {wilson_lo:.3} to {wilson_hi:.3} | {exact_lo:.3} to {exact_hi:.3} | {:.0}% |\n",
+        result.games(),
+        result.wins,
+        result.draws,
+        result.losses,
+        result.score(),
+        elo(result.score()),
+        elo(lo),
+        elo(hi),
+        100.0 * significance.likelihood_of_superiority,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alpha_zero::{Baseline, MatchResult};
+
+    use super::{match_summary, Contender};
+
+    #[test]
+    fn contenders_and_summary() {
+        let greedy = "greedy".parse::<Contender>().unwrap();
+        assert_eq!(greedy, Contender::Baseline(Baseline::Greedy));
+        assert!("no/such/weights.safetensors".parse::<Contender>().is_err());
+
+        let result = MatchResult {
+            wins: 30,
+            draws: 40,
+            losses: 30,
+        };
+        let summary = match_summary(&greedy, &Contender::Baseline(Baseline::Random), &result);
+        assert!(summary.starts_with("greedy against random\n"));
+        assert!(summary.contains("| 100 | 30 | 40 | 30 | 0.500 | 0.424 to 0.576 | 0 | -53 to 53 |"));
+    }
+}
@@ -0,0 +1,78 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use super::{Evaluator, Game, Perspective, TerminationState};
+
+// Hand-written evaluation of a game, usable in place of a network for baseline agents
+pub trait HeuristicEval: Game {
+    // Estimated score of the player to move, see `Perspective` for the convention
+    fn eval(&self) -> f32;
+
+    fn policy_prior(&self, moves: &[Self::Move]) -> Vec<f32> {
+        vec![1.0 / moves.len() as f32; moves.len()]
+    }
+}
+
+// Plugs `HeuristicEval` into the search
+#[derive(Clone, Copy, Default)]
+pub struct HeuristicEvaluator;
+
+impl<TGame: HeuristicEval> Evaluator<TGame> for HeuristicEvaluator {
+    async fn evaluate(&mut self, state: &TGame, moves: &[TGame::Move]) -> (f32, Vec<f32>) {
+        (state.eval(), state.policy_prior(moves))
+    }
+}
+
+// Stand-in for an untrained network: every position is a draw and every move equally likely
+#[derive(Clone, Copy, Default)]
+pub struct UniformEvaluator;
+
+impl<TGame: Game> Evaluator<TGame> for UniformEvaluator {
+    async fn evaluate(&mut self, _: &TGame, moves: &[TGame::Move]) -> (f32, Vec<f32>) {
+        (0.5, vec![1.0 / moves.len() as f32; moves.len()])
+    }
+}
+
+// Classic MCTS leaf evaluation: the result of one game of random moves, with every move equally
+// likely. Slow on games that take long to finish.
+pub struct RolloutEvaluator {
+    rng: StdRng,
+}
+
+impl Default for RolloutEvaluator {
+    fn default() -> Self {
+        Self {
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl<TGame: Game> Evaluator<TGame> for RolloutEvaluator {
+    async fn evaluate(&mut self, state: &TGame, moves: &[TGame::Move]) -> (f32, Vec<f32>) {
+        let prior = vec![1.0 / moves.len() as f32; moves.len()];
+        let r#move = moves.choose(&mut self.rng).unwrap();
+        // Of the player to move in `state` relative to the one in the original state
+        let mut perspective = Perspective::after_move(r#move);
+        let mut state = state.make_move(r#move);
+        let value = loop {
+            let moves = match state.get_state() {
+                TerminationState::Terminal(value) => break perspective.convert(value),
+                TerminationState::Moves(moves) => moves,
+            };
+            let r#move = moves.choose(&mut self.rng).unwrap();
+            perspective = perspective.then(Perspective::after_move(r#move));
+            state = state.make_move(r#move);
+        };
+        (value, prior)
+    }
+}
+
+// Moves sorted by decreasing heuristic prior, e.g. to seed a root or for a 1-ply greedy player
+pub fn order_moves<TGame: HeuristicEval>(
+    state: &TGame,
+    moves: Vec<TGame::Move>,
+) -> Vec<TGame::Move> {
+    let prior = state.policy_prior(&moves);
+    let mut moves = moves.into_iter().zip(prior).collect::<Vec<_>>();
+    moves.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    moves.into_iter().map(|(m, _)| m).collect()
+}
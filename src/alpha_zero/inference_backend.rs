@@ -0,0 +1,90 @@
+use std::{future::Future, marker::PhantomData};
+
+use super::{ActionEncoding, Evaluator, Game};
+
+// Runs a net outside of tch, like ONNX Runtime on one written by `export-model`. Inputs and
+// outputs are those of a single position, flattened.
+pub trait InferenceBackend {
+    // The value for the player to move, see `OutputDescription`, and the log-policy over the
+    // whole action space
+    fn infer(&mut self, input: Vec<f32>) -> impl Future<Output = (f32, Vec<f32>)>;
+}
+
+// The net's input as a flat list of planes, like `AlphaZeroAdapter::convert_game_to_nn_input`
+// without the tensor
+pub trait InputEncoding<TGame: Game> {
+    fn input_shape() -> Vec<usize>;
+
+    fn encode_input(state: &TGame) -> Vec<f32>;
+}
+
+// Plugs a backend into the search, with the adapter's encodings. Positions are evaluated as
+// they are, without the random symmetries of `NetworkEvaluator`.
+pub struct BackendEvaluator<TGame, TBackend, TAdapter> {
+    backend: TBackend,
+    _p: PhantomData<(TGame, TAdapter)>,
+}
+
+impl<TGame, TBackend, TAdapter> BackendEvaluator<TGame, TBackend, TAdapter> {
+    pub fn new(backend: TBackend) -> Self {
+        Self {
+            backend,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<TGame, TBackend, TAdapter> Evaluator<TGame> for BackendEvaluator<TGame, TBackend, TAdapter>
+where
+    TGame: Game,
+    TBackend: InferenceBackend,
+    TAdapter: InputEncoding<TGame> + ActionEncoding<TGame>,
+{
+    async fn evaluate(&mut self, state: &TGame, moves: &[TGame::Move]) -> (f32, Vec<f32>) {
+        let (value, policy) = self.backend.infer(TAdapter::encode_input(state)).await;
+        (value, TAdapter::decode_log_policy(&policy, moves))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{argmax, Game, MonteCarloTree},
+        tictactoe::{GomokuBoard, TicTacToeAlphaZeroAdapter, TicTacToeMove},
+    };
+
+    use super::{BackendEvaluator, InferenceBackend, InputEncoding};
+
+    type Adapter = TicTacToeAlphaZeroAdapter;
+
+    // All of its policy on the center, whatever the position
+    struct Center;
+
+    impl InferenceBackend for Center {
+        async fn infer(&mut self, input: Vec<f32>) -> (f32, Vec<f32>) {
+            assert_eq!(input.len(), 2 * 7 * 7);
+            let mut policy = vec![f32::NEG_INFINITY; 7 * 7];
+            policy[3 * 7 + 3] = 0.0;
+            (0.5, policy)
+        }
+    }
+
+    #[tokio::test]
+    async fn search_follows_the_backend() {
+        let state = GomokuBoard::<7, 5>::new().make_move(&TicTacToeMove(0, 0));
+        let input = <Adapter as InputEncoding<GomokuBoard<7, 5>>>::encode_input(&state);
+        assert_eq!(
+            <Adapter as InputEncoding<GomokuBoard<7, 5>>>::input_shape(),
+            [2, 7, 7]
+        );
+        // The opponent's stone, on the second plane
+        assert_eq!(input[7 * 7], 1.0);
+        assert_eq!(input.iter().sum::<f32>(), 1.0);
+
+        let evaluator = BackendEvaluator::<_, _, Adapter>::new(Center);
+        let mut tree = MonteCarloTree::new(state.clone(), evaluator);
+        tree.do_simulations(16, 1.0).await;
+        let moves = state.get_state().get_moves().unwrap();
+        assert_eq!(moves[argmax(&tree.get_policy())], TicTacToeMove(3, 3));
+    }
+}
@@ -0,0 +1,201 @@
+use std::io::{BufRead, Write};
+
+use anyhow::Context;
+
+use super::{Agent, Game, Notation, Perspective, SearchInfo, TerminationState};
+
+// Candidates shown after every engine move
+const CANDIDATES: usize = 3;
+
+// Most visited moves of a search first, as `(index in the move list, share of the visits)`
+pub fn top_candidates(search: &SearchInfo, count: usize) -> Vec<(usize, f32)> {
+    let mut candidates = search
+        .visits
+        .iter()
+        .copied()
+        .enumerate()
+        .collect::<Vec<_>>();
+    candidates.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    candidates.truncate(count);
+    candidates
+}
+
+// A game between a human typing moves into `input` and `engine`, the board being written to
+// `output` before every move with `show`. After each of its moves, the engine's value of the
+// position and its top candidates are shown if it searched. Returns the human's score, or
+// `None` if they quit, by typing `quit` or closing the input.
+pub async fn play_in_terminal<TGame: Game + Clone>(
+    start: &TGame,
+    notation: Notation<TGame>,
+    show: Option<fn(&TGame) -> String>,
+    engine: &mut impl Agent<TGame>,
+    human_first: bool,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> anyhow::Result<Option<f32>>
+where
+    TGame::Move: PartialEq,
+{
+    let mut state = start.clone();
+    // Relation of the player to move to the human
+    let mut player = match human_first {
+        true => Perspective::Same,
+        false => Perspective::Opponent,
+    };
+    let mut line = String::new();
+    let value = loop {
+        if let Some(show) = show {
+            writeln!(output, "{}", show(&state))?;
+        }
+        let moves = match state.get_state() {
+            TerminationState::Moves(moves) => moves,
+            TerminationState::Terminal(value) => break player.convert(value),
+        };
+        let index = match player {
+            Perspective::Same => loop {
+                write!(
+                    output,
+                    "Your move, like {}: ",
+                    (notation.format)(&state, &moves[0])
+                )?;
+                output.flush()?;
+                line.clear();
+                if input
+                    .read_line(&mut line)
+                    .context("Failed to read a move")?
+                    == 0
+                {
+                    return Ok(None);
+                }
+                let text = line.trim();
+                if text == "quit" {
+                    return Ok(None);
+                }
+                let index = (notation.parse)(&state, text).and_then(|m| {
+                    moves
+                        .iter()
+                        .position(|legal| *legal == m)
+                        .with_context(|| format!("{text} isn't legal here"))
+                });
+                match index {
+                    Ok(index) => break index,
+                    Err(e) => writeln!(output, "{e:#}")?,
+                }
+            },
+            Perspective::Opponent => {
+                let index = engine.select_move(&state).await;
+                writeln!(
+                    output,
+                    "Engine plays {}",
+                    (notation.format)(&state, &moves[index])
+                )?;
+                if let Some(search) = engine.last_search() {
+                    let candidates = top_candidates(search, CANDIDATES)
+                        .into_iter()
+                        .map(|(i, share)| {
+                            format!(
+                                "{} {:.0}%",
+                                (notation.format)(&state, &moves[i]),
+                                100.0 * share
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    writeln!(
+                        output,
+                        "Engine's value {:.3}, candidates: {}",
+                        search.root_value,
+                        candidates.join(", ")
+                    )?;
+                }
+                index
+            }
+        };
+        player = player.then(Perspective::after_move(&moves[index]));
+        state = state.make_move(&moves[index]);
+    };
+    let result = match value.partial_cmp(&0.5).unwrap() {
+        std::cmp::Ordering::Greater => "You win",
+        std::cmp::Ordering::Equal => "Draw",
+        std::cmp::Ordering::Less => "The engine wins",
+    };
+    writeln!(output, "{result}")?;
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        alpha_zero::{Agent, Game, Notation, SearchInfo},
+        tictactoe3::TicTacToe3,
+    };
+
+    use super::{play_in_terminal, top_candidates};
+
+    // Takes the lowest free cell, claiming to have searched it
+    struct LowestCell(Option<SearchInfo>);
+
+    impl Agent<TicTacToe3> for LowestCell {
+        async fn select_move(&mut self, state: &TicTacToe3) -> usize {
+            let moves = state.get_state().get_moves().unwrap().len();
+            let mut visits = vec![0.0; moves];
+            visits[0] = 0.75;
+            visits[moves - 1] = 0.25;
+            self.0 = Some(SearchInfo {
+                root_value: 0.25,
+                visits,
+            });
+            0
+        }
+
+        fn last_search(&self) -> Option<&SearchInfo> {
+            self.0.as_ref()
+        }
+    }
+
+    async fn play(input: &str, human_first: bool) -> (Option<f32>, String) {
+        let mut output = vec![];
+        let score = play_in_terminal(
+            &TicTacToe3::new(),
+            Notation::of(),
+            None,
+            &mut LowestCell(None),
+            human_first,
+            Cursor::new(input),
+            &mut output,
+        )
+        .await
+        .unwrap();
+        (score, String::from_utf8(output).unwrap())
+    }
+
+    #[tokio::test]
+    async fn human_against_engine() {
+        // The engine takes 0 and 1 while the human gets the diagonal through 2
+        let (score, output) = play("4\n9\nfour\n4\n2\n6\n", true).await;
+        assert_eq!(score, Some(1.0));
+        assert!(output.contains("Expected a cell from 0 to 8, got 9"));
+        assert!(output.contains("4 isn't legal here"));
+        assert!(output.contains("Engine plays 0\nEngine's value 0.250, candidates: 0 75%, 8 25%"));
+        assert!(output.ends_with("You win\n"));
+
+        // Moving second, the engine gets the top row
+        let (score, output) = play("4\n5\n", false).await;
+        assert_eq!(score, Some(0.0));
+        assert!(output.ends_with("The engine wins\n"));
+
+        assert_eq!(play("4\nquit\n", true).await.0, None);
+        assert_eq!(play("4\n", true).await.0, None);
+    }
+
+    #[test]
+    fn candidates_by_visits() {
+        let search = SearchInfo {
+            root_value: 0.5,
+            visits: vec![0.1, 0.5, 0.0, 0.4],
+        };
+        assert_eq!(top_candidates(&search, 2), [(1, 0.5), (3, 0.4)]);
+        assert_eq!(top_candidates(&search, 10).len(), 4);
+    }
+}
@@ -1,6 +1,6 @@
 use tch::{
-    nn::{Conv, ConvTransposeND, Linear},
-    Tensor,
+    nn::{Conv, ConvTransposeND, Linear, VarStore},
+    Kind, Tensor,
 };
 
 pub trait L2Norm {
@@ -36,3 +36,38 @@ impl<ND> L2Norm for ConvTransposeND<ND> {
         self.ws.l2()
     }
 }
+
+// Every trainable weight matrix and kernel, so networks don't have to sum over their
+// layers. Biases and normalization parameters, the 1-dimensional variables, are left out.
+impl L2Norm for VarStore {
+    fn l2(&self) -> Tensor {
+        self.trainable_variables()
+            .iter()
+            .filter(|variable| variable.dim() > 1)
+            .map(L2Norm::l2)
+            .fold(
+                Tensor::zeros([], (Kind::Float, self.device())),
+                |acc, l2| acc + l2,
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tch::{nn, Device};
+
+    use super::L2Norm;
+
+    #[test]
+    fn var_store_skips_biases() {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let mut linear = nn::linear(vs.root(), 2, 3, Default::default());
+        let _norm = nn::batch_norm1d(vs.root(), 3, Default::default());
+        let _ws = vs.root().ones("ws", &[2, 2]);
+        let _frozen = vs.root().ones_no_train("frozen", &[2, 2]);
+        assert_eq!(vs.trainable_variables().len(), 5);
+
+        tch::no_grad(|| linear.ws.fill_(1.0));
+        assert_eq!(f64::try_from(vs.l2()).unwrap(), 10.0);
+    }
+}
@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use tch::{Kind, Tensor};
+
+use super::NetOutput;
+
+// Of the visit distributions, for the targets of the soft policy head
+const SOFT_POLICY_EXPONENT: f64 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LossConfig {
+    pub value_weight: f64,
+    pub policy_weight: f64,
+    // Rewards high entropy of the predicted policy, 0 disables it
+    pub entropy_bonus: f64,
+    // Of the auxiliary heads, only applied to nets that have them. The ownership loss is a
+    // mean over the cells.
+    pub ownership_weight: f64,
+    pub soft_policy_weight: f64,
+}
+
+impl Default for LossConfig {
+    fn default() -> Self {
+        Self {
+            value_weight: 1.0,
+            policy_weight: 1.0,
+            entropy_bonus: 0.0,
+            ownership_weight: 0.5,
+            // The soft policy's gradients are small, being spread over many moves
+            soft_policy_weight: 8.0,
+        }
+    }
+}
+
+// Scalars are means over the batch, without the weights of the config applied
+pub struct AlphaZeroLoss {
+    // What gets minimized, weighted by the config and the per-sample weights
+    pub total: Tensor,
+    pub value: Tensor,
+    pub policy: Tensor,
+    pub entropy: Tensor,
+    // KL divergence of the predicted policy from the target one, the part of the policy
+    // loss the net can still reduce
+    pub kl: Tensor,
+    // Weighted loss of every sample, detached from the graph
+    pub per_sample: Tensor,
+}
+
+// Value MSE plus cross-entropy between the target policies and the predicted log-probabilities,
+// e.g. the log_softmax output of the net. Policies can have any shape after the batch dimension.
+pub fn alpha_zero_loss(
+    values: &Tensor,
+    target_values: &Tensor,
+    log_policies: &Tensor,
+    target_policies: &Tensor,
+    sample_weights: Option<&Tensor>,
+    config: &LossConfig,
+) -> AlphaZeroLoss {
+    let value = (values - target_values).square();
+    let policy = -(target_policies * log_policies)
+        .flatten(1, -1)
+        .sum_dim_intlist(1, false, None);
+    // Zero-probability moves contribute nothing instead of 0 * -inf
+    let entropy = -(log_policies.exp() * log_policies)
+        .nan_to_num(0.0, None, None)
+        .flatten(1, -1)
+        .sum_dim_intlist(1, false, None);
+    let target_entropy = -(target_policies * target_policies.log())
+        .nan_to_num(0.0, None, None)
+        .flatten(1, -1)
+        .sum_dim_intlist(1, false, None);
+
+    let per_sample = &value * config.value_weight + &policy * config.policy_weight
+        - &entropy * config.entropy_bonus;
+    let total = match sample_weights {
+        Some(weights) => (weights * &per_sample).mean(None),
+        None => per_sample.mean(None),
+    };
+
+    AlphaZeroLoss {
+        total,
+        value: value.mean(None),
+        policy: policy.mean(None),
+        entropy: entropy.mean(None),
+        kl: (&policy - target_entropy).mean(None),
+        per_sample: per_sample.detach(),
+    }
+}
+
+// Of the auxiliary heads the net has and the samples have targets for, the rest being `None`
+pub struct AuxiliaryLoss {
+    // Weighted by the config and the per-sample weights, zero without any auxiliary head
+    pub total: Tensor,
+    // Means over the samples with targets
+    pub ownership: Option<Tensor>,
+    pub soft_policy: Option<Tensor>,
+}
+
+// The visit distributions raised to `SOFT_POLICY_EXPONENT` and normalized again, which
+// brings out the moves besides the best one
+pub fn soft_policy_target(policies: &Tensor) -> Tensor {
+    let soft = policies.pow_tensor_scalar(SOFT_POLICY_EXPONENT);
+    let sums = soft
+        .flatten(1, -1)
+        .sum_dim_intlist(1, false, None)
+        .clamp_min(1e-12);
+    let mut shape = vec![1; soft.dim()];
+    shape[0] = -1;
+    &soft / sums.view(shape.as_slice())
+}
+
+// Losses of the heads of `output` besides the value and the policy. `ownership` holds the
+// targets and which samples have them, see `Minibatch`.
+pub fn auxiliary_loss(
+    output: &NetOutput,
+    target_policies: &Tensor,
+    ownership: Option<(&Tensor, &Tensor)>,
+    sample_weights: Option<&Tensor>,
+    config: &LossConfig,
+) -> AuxiliaryLoss {
+    let weighted_mean = |per_sample: &Tensor| match sample_weights {
+        Some(weights) => (weights * per_sample).mean(None),
+        None => per_sample.mean(None),
+    };
+    let mut total = Tensor::zeros([], (Kind::Float, target_policies.device()));
+
+    let soft_policy = output.soft_policy.as_ref().map(|log_policies| {
+        let per_sample = -(soft_policy_target(target_policies) * log_policies)
+            .flatten(1, -1)
+            .sum_dim_intlist(1, false, None);
+        total += weighted_mean(&per_sample) * config.soft_policy_weight;
+        per_sample.mean(None)
+    });
+
+    let ownership = match (&output.ownership, ownership) {
+        (Some(predicted), Some((targets, has_targets))) => {
+            let per_sample = (predicted - targets)
+                .square()
+                .flatten(1, -1)
+                .mean_dim(1, false, None)
+                * has_targets;
+            total += weighted_mean(&per_sample) * config.ownership_weight;
+            Some(per_sample.sum(None) / has_targets.sum(None).clamp_min(1.0))
+        }
+        _ => None,
+    };
+
+    AuxiliaryLoss {
+        total,
+        ownership,
+        soft_policy,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tch::{Device, Kind, Tensor};
+
+    use crate::alpha_zero::NetOutput;
+
+    use super::{alpha_zero_loss, auxiliary_loss, soft_policy_target, LossConfig};
+
+    fn assert_close(t: &Tensor, expected: f64) {
+        let v = f64::try_from(t).unwrap();
+        assert!((v - expected).abs() < 1e-4, "{v} != {expected}");
+    }
+
+    #[test]
+    fn hand_computed_losses() {
+        let values = Tensor::from_slice(&[0.5f32, 0.0]);
+        let target_values = Tensor::from_slice(&[1.0f32, 0.0]);
+        let log_policies = Tensor::from_slice2(&[[0.5f32, 0.5], [0.25, 0.75]]).log();
+        let target_policies = Tensor::from_slice2(&[[1.0f32, 0.0], [0.5, 0.5]]);
+
+        let loss = alpha_zero_loss(
+            &values,
+            &target_values,
+            &log_policies,
+            &target_policies,
+            None,
+            &LossConfig::default(),
+        );
+        // (0.5^2 + 0) / 2
+        assert_close(&loss.value, 0.125);
+        // (ln 2 + (ln 4 + ln 4/3) / 2) / 2
+        assert_close(&loss.policy, 0.765_05);
+        // (ln 2 + (ln 4 / 4 + 3/4 ln 4/3)) / 2
+        assert_close(&loss.entropy, 0.627_68);
+        // (ln 2 + ln 4/3 / 2) / 2
+        assert_close(&loss.kl, 0.418_49);
+        assert_close(&loss.total, 0.890_05);
+
+        let weights = Tensor::from_slice(&[2.0f32, 0.0]);
+        let loss = alpha_zero_loss(
+            &values,
+            &target_values,
+            &log_policies,
+            &target_policies,
+            Some(&weights),
+            &LossConfig {
+                value_weight: 2.0,
+                policy_weight: 1.0,
+                entropy_bonus: 0.5,
+                ..Default::default()
+            },
+        );
+        // Only the first sample: 2 * (2 * 0.25 + ln 2 - ln 2 / 2) / 2
+        assert_close(&loss.total, 0.846_57);
+        assert_eq!(loss.per_sample.size(), [2]);
+    }
+    #[test]
+    fn auxiliary_losses() {
+        let target_policies = Tensor::from_slice2(&[[1.0f32 / 16.0, 15.0 / 16.0], [1.0, 0.0]]);
+        let soft =
+            Vec::<f32>::try_from(soft_policy_target(&target_policies).flatten(0, -1)).unwrap();
+        for (p, expected) in soft.iter().zip([0.336_93, 0.663_07, 1.0, 0.0]) {
+            assert!((p - expected).abs() < 1e-4, "{soft:?}");
+        }
+
+        let mut output = NetOutput::new(
+            Tensor::from_slice(&[0.5f32, 0.5]),
+            Tensor::from_slice2(&[[0.5f32, 0.5], [0.5, 0.5]]).log(),
+        );
+        output.ownership = Some(Tensor::zeros([2, 2, 2], (Kind::Float, Device::Cpu)));
+        let ownership = Tensor::ones([2, 2, 2], (Kind::Float, Device::Cpu));
+        let has_ownership = Tensor::from_slice(&[1.0f32, 0.0]);
+        let config = LossConfig::default();
+
+        // No soft policy head, and the second sample has no ownership target
+        let loss = auxiliary_loss(
+            &output,
+            &target_policies,
+            Some((&ownership, &has_ownership)),
+            None,
+            &config,
+        );
+        assert!(loss.soft_policy.is_none());
+        assert_close(loss.ownership.as_ref().unwrap(), 1.0);
+        assert_close(&loss.total, 0.5 * 0.5);
+
+        // Without targets, only the soft policy counts
+        output.soft_policy = Some(output.policy.shallow_clone());
+        let loss = auxiliary_loss(&output, &target_policies, None, None, &config);
+        assert!(loss.ownership.is_none());
+        assert_close(loss.soft_policy.as_ref().unwrap(), std::f64::consts::LN_2);
+        assert_close(&loss.total, 8.0 * std::f64::consts::LN_2);
+    }
+}
@@ -0,0 +1,97 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+// Written in configs as a table with a `kind` of `constant`, `step` or `cosine`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LrDecay {
+    Constant,
+    // Multiplies the rate by `factor` every `every` generations
+    Step { every: usize, factor: f64 },
+    // Anneals down to `min_lr` over `generations`, staying there afterwards
+    Cosine { generations: usize, min_lr: f64 },
+}
+
+// Learning rate as a function of the generation, set on the optimizer before each one
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct LrSchedule {
+    pub base_lr: f64,
+    // Generations over which the rate grows linearly up to the decayed one
+    pub warmup: usize,
+    pub decay: LrDecay,
+}
+
+impl LrSchedule {
+    pub fn constant(lr: f64) -> Self {
+        Self {
+            base_lr: lr,
+            warmup: 0,
+            decay: LrDecay::Constant,
+        }
+    }
+
+    pub fn lr(&self, generation: usize) -> f64 {
+        let decayed = match self.decay {
+            LrDecay::Constant => self.base_lr,
+            LrDecay::Step { every, factor } => {
+                self.base_lr * factor.powi((generation / every.max(1)) as i32)
+            }
+            LrDecay::Cosine {
+                generations,
+                min_lr,
+            } => {
+                let progress = generation.min(generations) as f64 / generations.max(1) as f64;
+                min_lr + (self.base_lr - min_lr) * (1.0 + (PI * progress).cos()) / 2.0
+            }
+        };
+        if generation < self.warmup {
+            decayed * (generation + 1) as f64 / (self.warmup + 1) as f64
+        } else {
+            decayed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LrDecay, LrSchedule};
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-12, "{a} != {b}");
+    }
+
+    #[test]
+    fn schedules() {
+        let step = LrSchedule {
+            base_lr: 1.0,
+            warmup: 0,
+            decay: LrDecay::Step {
+                every: 10,
+                factor: 0.1,
+            },
+        };
+        assert_close(step.lr(9), 1.0);
+        assert_close(step.lr(10), 0.1);
+        assert_close(step.lr(25), 0.01);
+
+        let cosine = LrSchedule {
+            base_lr: 1.0,
+            warmup: 3,
+            decay: LrDecay::Cosine {
+                generations: 100,
+                min_lr: 0.2,
+            },
+        };
+        assert_close(cosine.lr(0), 0.25);
+        assert_close(
+            cosine.lr(2),
+            0.75 * (0.2 + 0.8 * (1.0 + (0.02 * std::f64::consts::PI).cos()) / 2.0),
+        );
+        assert_close(cosine.lr(50), 0.6);
+        assert_close(cosine.lr(100), 0.2);
+        assert_close(cosine.lr(1000), 0.2);
+
+        assert_close(LrSchedule::constant(1e-4).lr(77), 1e-4);
+    }
+}
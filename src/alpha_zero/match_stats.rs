@@ -0,0 +1,144 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use serde::Serialize;
+
+use super::{MatchResult, Significance};
+
+// Results broken down by color and by opening. When the first player wins most games, as in
+// gomoku, the totals mostly tell how often each side got to move first.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MatchStats {
+    // From the player's point of view, in the games where it made the game's first move,
+    // counting the opening's moves, and in the others
+    pub as_first: MatchResult,
+    pub as_second: MatchResult,
+    // From the first player's point of view, whoever it was, by index in the opening book
+    pub openings: BTreeMap<usize, MatchResult>,
+}
+
+impl MatchStats {
+    // `value` in [0, 1] for the player
+    pub fn record(&mut self, value: f32, first: bool, opening: Option<usize>) {
+        let first_value = match first {
+            true => {
+                self.as_first.record(value);
+                value
+            }
+            false => {
+                self.as_second.record(value);
+                1.0 - value
+            }
+        };
+        if let Some(opening) = opening {
+            self.openings
+                .entry(opening)
+                .or_default()
+                .record(first_value);
+        }
+    }
+
+    pub fn total(&self) -> MatchResult {
+        self.as_first + self.as_second
+    }
+
+    // Every game from the first player's point of view
+    pub fn first_player(&self) -> MatchResult {
+        self.as_first + self.as_second.reversed()
+    }
+
+    // The same games from the opponent's point of view
+    pub fn reversed(&self) -> Self {
+        Self {
+            as_first: self.as_second.reversed(),
+            as_second: self.as_first.reversed(),
+            openings: self.openings.clone(),
+        }
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        self.as_first = self.as_first + other.as_first;
+        self.as_second = self.as_second + other.as_second;
+        for (&opening, &result) in &other.openings {
+            let merged = self.openings.entry(opening).or_default();
+            *merged = *merged + result;
+        }
+    }
+
+    // Markdown, with the first player's score by opening when games started from one
+    pub fn to_markdown(&self) -> String {
+        let row = |name: &str, result: &MatchResult| {
+            format!(
+                "| {name} | {} | {}-{}-{} | {} |\n",
+                result.games(),
+                result.wins,
+                result.draws,
+                result.losses,
+                Significance::of(result).to_cells()
+            )
+        };
+        let mut table = format!(
+            "| | games | wins-draws-losses | {} |\n|---|---|---|---|---|---|---|\n",
+            Significance::HEADERS
+        );
+        table += &row("as first", &self.as_first);
+        table += &row("as second", &self.as_second);
+        table += &row("first player", &self.first_player());
+        if !self.openings.is_empty() {
+            let _ = write!(
+                table,
+                "\n| opening | games | first player's {} |\n|---|---|---|---|---|---|\n",
+                Significance::HEADERS
+            );
+            for (opening, result) in &self.openings {
+                let _ = writeln!(
+                    table,
+                    "| {opening} | {} | {} |",
+                    result.games(),
+                    Significance::of(result).to_cells()
+                );
+            }
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alpha_zero::MatchResult;
+
+    use super::MatchStats;
+
+    #[test]
+    fn first_player_and_openings() {
+        let mut stats = MatchStats::default();
+        // The first player never loses
+        stats.record(1.0, true, Some(3));
+        stats.record(0.0, false, Some(3));
+        stats.record(0.5, true, Some(0));
+        stats.record(0.0, false, Some(0));
+
+        let result = |wins, draws, losses| MatchResult {
+            wins,
+            draws,
+            losses,
+        };
+        assert_eq!(stats.total(), result(1, 1, 2));
+        assert_eq!(stats.as_second, result(0, 0, 2));
+        assert_eq!(stats.first_player(), result(3, 1, 0));
+        assert_eq!(stats.openings[&3], result(2, 0, 0));
+        assert_eq!(stats.openings[&0], result(1, 1, 0));
+
+        let reversed = stats.reversed();
+        assert_eq!(reversed.as_first, result(2, 0, 0));
+        assert_eq!(reversed.first_player(), stats.first_player());
+        assert_eq!(reversed.openings, stats.openings);
+
+        let mut merged = stats.clone();
+        merged.merge(&reversed);
+        assert_eq!(merged.total(), result(3, 2, 3));
+        assert_eq!(merged.openings[&3], result(4, 0, 0));
+        assert!(merged
+            .to_markdown()
+            .contains("| first player | 8 | 6-2-0 | 0.875 | 0.529-0.978 | 0.473-0.997 | 99% |\n"));
+    }
+}
@@ -1,10 +1,13 @@
-use std::{marker::PhantomData, sync::OnceLock};
+use std::{
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
 
 use atomic_refcell::AtomicRefCell;
 
 use crate::alpha_zero::TerminationState;
 
-use super::{AlphaZeroAdapter, AlphaZeroNet, Game, MoveParameters, NetworkBatchedExecutorHandle};
+use super::{Evaluator, Game, Perspective, SelfPlayProfile, Stage};
 
 #[derive(Clone, Copy, Debug)]
 struct MoveDynamicInfo {
@@ -15,7 +18,7 @@ struct MoveDynamicInfo {
 #[derive(Clone, Copy, Debug)]
 struct MoveStaticInfo {
     priority: f32,
-    player_switch: bool,
+    perspective: Perspective,
 }
 
 struct NodeState<T> {
@@ -74,6 +77,27 @@ impl<T> NodeState<T> {
             .1
     }
 
+    fn get_visits(&self) -> usize {
+        self.children
+            .iter()
+            .map(|(_, _, d)| d.borrow().descends)
+            .sum()
+    }
+
+    // Average score of the explored moves, or the node's own estimate if none are
+    fn get_value(&self) -> f32 {
+        let (total_score, descends) = self
+            .children
+            .iter()
+            .map(|(_, _, d)| *d.borrow())
+            .fold((0.0, 0), |(s, n), d| (s + d.total_score, n + d.descends));
+        if descends == 0 {
+            self.value
+        } else {
+            total_score / descends as f32
+        }
+    }
+
     fn get_policy(&self) -> Vec<f32> {
         // println!("{:?}", self.children);
         let iter = self.children.iter().map(|(_, _, d)| d.borrow().descends);
@@ -83,31 +107,43 @@ impl<T> NodeState<T> {
     }
 }
 
-pub struct MonteCarloTree<TGame: Game, TNet: AlphaZeroNet, TAdapter: AlphaZeroAdapter<TGame, TNet>>
-{
+pub struct MonteCarloTree<TGame: Game, TEval: Evaluator<TGame>> {
     root: MonteCarloNode<TGame>,
-    executor: NetworkBatchedExecutorHandle<TNet>,
-    _p: PhantomData<TAdapter>,
+    evaluator: TEval,
+    profile: Option<Arc<SelfPlayProfile>>,
 }
 
-impl<TGame: Game, TNet: AlphaZeroNet, TAdapter: AlphaZeroAdapter<TGame, TNet>>
-    MonteCarloTree<TGame, TNet, TAdapter>
-{
-    pub fn new(state: TGame, executor: NetworkBatchedExecutorHandle<TNet>) -> Self {
+impl<TGame: Game, TEval: Evaluator<TGame>> MonteCarloTree<TGame, TEval> {
+    pub fn new(state: TGame, evaluator: TEval) -> Self {
         let root = MonteCarloNode::new(state);
         Self {
             root,
-            executor,
-            _p: PhantomData,
+            evaluator,
+            profile: None,
         }
     }
 
+    // Records the time of the selection and the expansion of the simulations
+    pub fn with_profile(mut self, profile: Arc<SelfPlayProfile>) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
     async fn create_node_state(
-        executor: &mut NetworkBatchedExecutorHandle<TNet>,
+        evaluator: &mut TEval,
         state: &TGame,
+        profile: Option<&SelfPlayProfile>,
     ) -> NodeState<TGame> {
+        let started = profile.map(|_| Instant::now());
+        // Without the evaluation, which the evaluator records itself
+        let record = |evaluating: Duration| {
+            if let (Some(profile), Some(started)) = (profile, started) {
+                profile.add(Stage::Expansion, started.elapsed() - evaluating);
+            }
+        };
         let moves = match state.get_state() {
             TerminationState::Terminal(val) => {
+                record(Duration::ZERO);
                 return NodeState {
                     value: val,
                     is_terminal: true,
@@ -117,11 +153,9 @@ impl<TGame: Game, TNet: AlphaZeroNet, TAdapter: AlphaZeroAdapter<TGame, TNet>>
             TerminationState::Moves(moves) => moves,
         };
         // println!("Found target state in {:?}", Instant::now() - start);
-        let (value, policy) = executor
-            .execute(TAdapter::convert_game_to_nn_input(state))
-            .await;
-        let value = f32::try_from(value).unwrap();
-        let policy = TAdapter::get_estimated_policy(&policy, &moves);
+        let evaluating = started.map(|_| Instant::now());
+        let (value, policy) = evaluator.evaluate(state, &moves).await;
+        let evaluating = evaluating.map_or(Duration::ZERO, |evaluating| evaluating.elapsed());
 
         let node_state = NodeState {
             value,
@@ -135,7 +169,7 @@ impl<TGame: Game, TNet: AlphaZeroNet, TAdapter: AlphaZeroAdapter<TGame, TNet>>
                         MonteCarloNode::new(state.make_move(r#move)),
                         MoveStaticInfo {
                             priority: policy,
-                            player_switch: r#move.is_player_switch(),
+                            perspective: Perspective::after_move(r#move),
                         },
                         AtomicRefCell::new(MoveDynamicInfo {
                             total_score: 0.0,
@@ -145,12 +179,33 @@ impl<TGame: Game, TNet: AlphaZeroNet, TAdapter: AlphaZeroAdapter<TGame, TNet>>
                 })
                 .collect(),
         };
+        record(evaluating);
         node_state
     }
 
     pub async fn do_simulations(&mut self, samples: usize, cpuct: f32) {
+        self.do_simulations_while(cpuct, |done| done < samples)
+            .await
+    }
+
+    // Simulates until `deadline`, at most `samples` times. The first two simulations always
+    // run, so that the root has a move visited to make a policy of.
+    pub async fn do_simulations_until(&mut self, deadline: Instant, samples: usize, cpuct: f32) {
+        self.do_simulations_while(cpuct, |done| {
+            done < samples && (done < 2 || Instant::now() < deadline)
+        })
+        .await
+    }
+
+    // `more` gets the number of simulations done so far
+    pub async fn do_simulations_while(&mut self, cpuct: f32, mut more: impl FnMut(usize) -> bool) {
         let mut state_stack = vec![];
-        for _ in 0..samples {
+        let mut done = 0;
+        while more(done) {
+            done += 1;
+            let started = self.profile.is_some().then(Instant::now);
+            // Creating the node, recorded by `create_node_state`
+            let mut creating = Duration::ZERO;
             let mut cur = &self.root;
             // let start = Instant::now();
             let mut value = loop {
@@ -158,7 +213,14 @@ impl<TGame: Game, TNet: AlphaZeroNet, TAdapter: AlphaZeroAdapter<TGame, TNet>>
                     if let Some(r) = cur.node_state.get() {
                         break 'cl (r, false);
                     }
-                    let state = Self::create_node_state(&mut self.executor, &cur.game_state).await;
+                    let creation = started.map(|_| Instant::now());
+                    let state = Self::create_node_state(
+                        &mut self.evaluator,
+                        &cur.game_state,
+                        self.profile.as_deref(),
+                    )
+                    .await;
+                    creating = creation.map_or(Duration::ZERO, |creation| creation.elapsed());
                     cur.node_state.set(state).map_err(|_| ()).unwrap();
                     (cur.node_state.get().unwrap(), true)
                 };
@@ -175,14 +237,16 @@ impl<TGame: Game, TNet: AlphaZeroNet, TAdapter: AlphaZeroAdapter<TGame, TNet>>
             while let Some((state, r#move)) = state_stack.pop() {
                 let child = &state.children[r#move];
 
-                if child.1.player_switch {
-                    value *= -1.0;
-                }
+                // Scores on an edge are from the point of view of the player making the move
+                value = child.1.perspective.convert(value);
 
                 let mut dyn_info = child.2.borrow_mut();
                 dyn_info.total_score += value;
                 dyn_info.descends += 1;
             }
+            if let (Some(profile), Some(started)) = (&self.profile, started) {
+                profile.add(Stage::Selection, started.elapsed().saturating_sub(creating));
+            }
         }
     }
 
@@ -190,15 +254,249 @@ impl<TGame: Game, TNet: AlphaZeroNet, TAdapter: AlphaZeroAdapter<TGame, TNet>>
         self.root.node_state.get().unwrap().get_policy()
     }
 
-    pub fn do_move(&mut self, move_id: usize) {
-        let root = self
-            .root
+    // Of the root's moves, as the evaluator gave them
+    pub fn get_priors(&self) -> Vec<f32> {
+        self.root
             .node_state
-            .get_mut()
+            .get()
             .unwrap()
             .children
-            .swap_remove(move_id)
-            .0;
-        self.root = root;
+            .iter()
+            .map(|(_, info, _)| info.priority)
+            .collect()
+    }
+
+    // Mean value of each of the root's moves for the player to move, `None` for moves that
+    // weren't visited
+    pub fn get_q_values(&self) -> Vec<Option<f32>> {
+        self.root
+            .node_state
+            .get()
+            .unwrap()
+            .children
+            .iter()
+            .map(|(_, _, d)| {
+                let d = d.borrow();
+                (d.descends > 0).then(|| d.total_score / d.descends as f32)
+            })
+            .collect()
+    }
+
+    pub fn get_root_value(&self) -> f32 {
+        self.root.node_state.get().unwrap().get_value()
+    }
+
+    pub fn get_root_visits(&self) -> usize {
+        self.root.node_state.get().unwrap().get_visits()
+    }
+
+    pub fn into_evaluator(self) -> TEval {
+        self.evaluator
+    }
+
+    // Keeps the subtree of the move. A root that was never searched is just replaced.
+    pub fn do_move(&mut self, move_id: usize) {
+        self.root = match self.root.node_state.take() {
+            Some(mut node_state) => node_state.children.swap_remove(move_id).0,
+            None => {
+                let moves = self
+                    .root
+                    .game_state
+                    .get_state()
+                    .get_moves()
+                    .expect("Asked to move in a terminal state");
+                MonteCarloNode::new(self.root.game_state.make_move(&moves[move_id]))
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    #[cfg(feature = "torch")]
+    use tch::{Device, Kind, Tensor};
+
+    use crate::alpha_zero::{
+        do_battle, BattlePlayer, Game, HeuristicEval, HeuristicEvaluator, MoveParameters,
+        Perspective, TerminationState,
+    };
+    #[cfg(feature = "torch")]
+    use crate::alpha_zero::{
+        AlphaZeroAdapter, AlphaZeroNet, ExecutorScope, NetOutput, NetworkEvaluator,
+    };
+
+    use super::MonteCarloTree;
+
+    // From the start the player either passes the turn (`Switch`) or moves again (`Double`).
+    // Both lead to a terminal state lost by the player to move, so only `Switch` wins.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum DoubleMoveGame {
+        Start,
+        AfterSwitch,
+        AfterDouble,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum DoubleMove {
+        Switch,
+        Double,
+    }
+
+    impl MoveParameters for DoubleMove {
+        fn is_player_switch(&self) -> bool {
+            *self == DoubleMove::Switch
+        }
+    }
+
+    impl Game for DoubleMoveGame {
+        type Move = DoubleMove;
+
+        fn get_state(&self) -> TerminationState<Self::Move> {
+            match self {
+                DoubleMoveGame::Start => {
+                    TerminationState::Moves(vec![DoubleMove::Double, DoubleMove::Switch])
+                }
+                _ => TerminationState::Terminal(0.0),
+            }
+        }
+
+        fn make_move(&self, m: &Self::Move) -> Self {
+            match m {
+                DoubleMove::Switch => DoubleMoveGame::AfterSwitch,
+                DoubleMove::Double => DoubleMoveGame::AfterDouble,
+            }
+        }
+
+        fn is_legal(&self, _: &Self::Move) -> bool {
+            *self == DoubleMoveGame::Start
+        }
+    }
+
+    #[cfg(feature = "torch")]
+    struct UniformNet;
+
+    #[cfg(feature = "torch")]
+    impl AlphaZeroNet for UniformNet {
+        fn forward_t(&self, xs: &Tensor, _is_training: bool) -> NetOutput {
+            let batch = xs.size()[0];
+            let options = (Kind::Float, Device::Cpu);
+            NetOutput::new(
+                Tensor::full([batch], 0.5, options),
+                Tensor::full([batch, 2], -f64::ln(2.0), options),
+            )
+        }
+    }
+
+    #[cfg(feature = "torch")]
+    struct UniformAdapter;
+
+    #[cfg(feature = "torch")]
+    impl AlphaZeroAdapter<DoubleMoveGame, UniformNet> for UniformAdapter {
+        fn convert_game_to_nn_input(_: &DoubleMoveGame) -> Tensor {
+            Tensor::zeros([1], (Kind::Float, Device::Cpu))
+        }
+
+        fn get_estimated_policy(_: &Tensor, moves: &[DoubleMove]) -> Vec<f32> {
+            vec![1.0 / moves.len() as f32; moves.len()]
+        }
+
+        fn convert_policy_to_nn(policy: &[f32], _: &[DoubleMove]) -> Tensor {
+            Tensor::from_slice(policy)
+        }
+    }
+
+    #[test]
+    fn perspective_conversions() {
+        assert_eq!(
+            Perspective::after_move(&DoubleMove::Double),
+            Perspective::Same
+        );
+        assert_eq!(
+            Perspective::after_move(&DoubleMove::Switch),
+            Perspective::Opponent
+        );
+        assert_eq!(Perspective::Same.convert(0.25), 0.25);
+        assert_eq!(Perspective::Opponent.convert(0.25), 0.75);
+        assert_eq!(
+            Perspective::Opponent.then(Perspective::Opponent),
+            Perspective::Same
+        );
+        assert_eq!(
+            Perspective::Same.then(Perspective::Opponent),
+            Perspective::Opponent
+        );
+    }
+
+    #[cfg(feature = "torch")]
+    #[tokio::test]
+    async fn backprop_respects_non_switching_moves() {
+        let mut scope = ExecutorScope::new(
+            UniformNet,
+            1,
+            1,
+            Duration::from_millis(1),
+            (Kind::Float, Device::Cpu),
+        );
+        scope.spawn(|handle| async move {
+            let mut tree = MonteCarloTree::new(
+                DoubleMoveGame::Start,
+                NetworkEvaluator::<_, _, UniformAdapter>::new(handle),
+            );
+            tree.do_simulations(64, 1.0).await;
+            tree.get_policy()
+        });
+        let policy = scope.next().await.unwrap();
+        scope.join().await;
+
+        // [Double, Switch]
+        assert!(policy[1] > 0.8, "policy: {policy:?}");
+    }
+
+    impl HeuristicEval for DoubleMoveGame {
+        fn eval(&self) -> f32 {
+            0.5
+        }
+    }
+
+    #[tokio::test]
+    async fn heuristic_search_without_network() {
+        let mut tree = MonteCarloTree::new(DoubleMoveGame::Start, HeuristicEvaluator);
+        tree.do_simulations(64, 1.0).await;
+        let policy = tree.get_policy();
+
+        assert!(policy[1] > 0.8, "policy: {policy:?}");
+    }
+
+    #[tokio::test]
+    async fn searches_stop_at_the_deadline() {
+        // Past deadlines still expand the root and visit a move
+        let mut tree = MonteCarloTree::new(DoubleMoveGame::Start, HeuristicEvaluator);
+        tree.do_simulations_until(Instant::now(), 64, 1.0).await;
+        assert_eq!(tree.get_root_visits(), 1);
+
+        let mut tree = MonteCarloTree::new(DoubleMoveGame::Start, HeuristicEvaluator);
+        let deadline = Instant::now() + Duration::from_secs(60);
+        tree.do_simulations_until(deadline, 64, 1.0).await;
+        assert_eq!(tree.get_root_visits(), 63);
+    }
+
+    #[tokio::test]
+    async fn battle_moves_by_the_movers_search() {
+        // The second player never searches, its tree only follows the moves
+        let history = do_battle(
+            DoubleMoveGame::Start,
+            None,
+            BattlePlayer::new(HeuristicEvaluator, 64, 1.0, |_| 0.0),
+            BattlePlayer::new(HeuristicEvaluator, 1, 1.0, |_| 0.0),
+        )
+        .await;
+
+        assert_eq!(history.len(), 1);
+        let (state, policy, value, first) = &history[0];
+        assert_eq!(*state, DoubleMoveGame::Start);
+        assert!(policy[1] > 0.8, "policy: {policy:?}");
+        assert_eq!((*value, *first), (1.0, true));
     }
 }
@@ -0,0 +1,163 @@
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Serialize;
+use tch::{CModule, Device, Kind, Tensor};
+
+use super::{AlphaZeroAdapter, AlphaZeroNet, Game, Notation};
+
+// What an exported net takes and gives, written next to it as JSON for serving it elsewhere
+#[derive(Debug, Serialize)]
+pub struct ModelDescription {
+    pub game: String,
+    // The net was traced at, which is the only one some runtimes accept
+    pub batch_size: usize,
+    pub input: InputDescription,
+    pub value: OutputDescription,
+    pub policy: OutputDescription,
+    // Transforms of the board the net should be invariant under, identity first
+    pub symmetries: usize,
+    // The start position's moves, as an example of the action encoding
+    pub start_moves: Vec<ActionDescription>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InputDescription {
+    pub shape: Vec<i64>,
+    pub kind: String,
+    // By the adapter, empty if it doesn't name them
+    pub planes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutputDescription {
+    pub shape: Vec<i64>,
+    pub meaning: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActionDescription {
+    // Into the policy flattened past the batch dimension
+    pub index: usize,
+    // In the game's notation, if it has one
+    pub notation: Option<String>,
+}
+
+// Batch of `batch_size` copies of the position, as the net takes it
+pub fn example_input<TGame, TNet, TAdapter>(state: &TGame, batch_size: usize) -> Tensor
+where
+    TGame: Game + Clone,
+    TNet: AlphaZeroNet,
+    TAdapter: AlphaZeroAdapter<TGame, TNet>,
+{
+    TAdapter::convert_games_to_nn_input(
+        &vec![state.clone(); batch_size],
+        (Kind::Float, Device::Cpu),
+    )
+}
+
+pub fn describe_model<TGame, TNet, TAdapter>(
+    game: &str,
+    net: &TNet,
+    start: &TGame,
+    batch_size: usize,
+    notation: Option<Notation<TGame>>,
+) -> anyhow::Result<ModelDescription>
+where
+    TGame: Game + Clone,
+    TNet: AlphaZeroNet,
+    TAdapter: AlphaZeroAdapter<TGame, TNet>,
+{
+    let input = example_input::<TGame, TNet, TAdapter>(start, batch_size);
+    let output = tch::no_grad(|| net.forward_t(&input, false));
+    let moves = start
+        .get_state()
+        .get_moves()
+        .context("The start position is over")?;
+    let start_moves = moves
+        .iter()
+        .map(|m| ActionDescription {
+            index: TAdapter::convert_policy_to_nn(&[1.0], std::slice::from_ref(m))
+                .view([-1])
+                .argmax(0, false)
+                .int64_value(&[]) as usize,
+            notation: notation.map(|notation| (notation.format)(start, m)),
+        })
+        .collect();
+    Ok(ModelDescription {
+        game: game.to_owned(),
+        batch_size,
+        input: InputDescription {
+            shape: input.size(),
+            kind: "float32".to_owned(),
+            planes: TAdapter::input_planes(),
+        },
+        value: OutputDescription {
+            shape: output.value.size(),
+            meaning: "probability that the player to move wins, draws counting half",
+        },
+        policy: OutputDescription {
+            shape: output.policy.size(),
+            meaning: "log-probabilities of the actions, legal or not",
+        },
+        symmetries: TAdapter::symmetries().len(),
+        start_moves,
+    })
+}
+
+// Traces the net's evaluation of `input` into a TorchScript module returning the value and
+// the log-policy. libtorch can't write ONNX, but `torch.onnx.export` converts the module.
+pub fn export_torchscript<TNet: AlphaZeroNet>(
+    net: &TNet,
+    input: &Tensor,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let module = tch::no_grad(|| {
+        CModule::create_by_tracing(
+            "AlphaZeroNet",
+            "forward",
+            std::slice::from_ref(input),
+            &mut |inputs| {
+                let output = net.forward_t(&inputs[0], false);
+                vec![output.value, output.policy]
+            },
+        )
+    })
+    .context("Failed to trace the net")?;
+    module
+        .save(path)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use tch::{nn, Device};
+
+    use crate::tictactoe3::{TicTacToe3, TicTacToe3AlphaZeroAdapter, TicTacToe3Net};
+
+    use super::describe_model;
+
+    #[test]
+    fn describes_shapes_and_actions() {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let net = TicTacToe3Net::new(&vs.root());
+        let description = describe_model::<_, _, TicTacToe3AlphaZeroAdapter>(
+            "tictactoe3",
+            &net,
+            &TicTacToe3::new(),
+            4,
+            None,
+        )
+        .unwrap();
+        assert_eq!(description.input.shape, [4, 2, 3, 3]);
+        assert_eq!(description.input.planes.len(), 2);
+        assert_eq!(description.policy.shape, [4, 3, 3]);
+        assert_eq!(description.symmetries, 8);
+        let indices = description
+            .start_moves
+            .iter()
+            .map(|action| action.index)
+            .collect::<Vec<_>>();
+        assert_eq!(indices, (0..9).collect::<Vec<_>>());
+    }
+}
@@ -1,4 +1,11 @@
-use std::{marker::PhantomData, time::Duration};
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use futures::{stream::FuturesUnordered, StreamExt};
 use tch::{Device, Kind, Tensor};
@@ -6,10 +13,11 @@ use tch::{Device, Kind, Tensor};
 use tokio::sync::mpsc::{
     channel, unbounded_channel, Receiver, Sender, UnboundedReceiver, UnboundedSender,
 };
+use tracing::{debug, trace};
 
 use crate::alpha_zero::Timer;
 
-use super::AlphaZeroNet;
+use super::{AlphaZeroNet, NetOutput};
 
 pub struct NetworkBatchedExecutor<Net: AlphaZeroNet> {
     receiver: UnboundedReceiver<(Tensor, Sender<(Tensor, Tensor)>)>,
@@ -45,8 +53,51 @@ impl<Net: AlphaZeroNet> NetworkBatchedExecutorHandle<Net> {
     }
 }
 
-pub enum BatcherCommand {
+// How full the evaluated batches were, shared with whoever reports it
+#[derive(Default)]
+pub struct BatchStats {
+    batches: AtomicUsize,
+    positions: AtomicUsize,
+    capacity: AtomicUsize,
+    // Nanoseconds of the forward passes, and of them times the positions of their batches
+    forward: AtomicU64,
+    forward_latency: AtomicU64,
+}
+
+impl BatchStats {
+    fn record(&self, positions: usize, max_batch: usize, forward: Duration) {
+        self.batches.fetch_add(1, Ordering::Relaxed);
+        self.positions.fetch_add(positions, Ordering::Relaxed);
+        self.capacity.fetch_add(max_batch, Ordering::Relaxed);
+        let forward = forward.as_nanos() as u64;
+        self.forward.fetch_add(forward, Ordering::Relaxed);
+        self.forward_latency
+            .fetch_add(forward * positions as u64, Ordering::Relaxed);
+    }
+
+    // Time the net spent evaluating since the executor started, and that summed over the
+    // positions, each waiting for its whole batch
+    pub fn forward_times(&self) -> (Duration, Duration) {
+        (
+            Duration::from_nanos(self.forward.load(Ordering::Relaxed)),
+            Duration::from_nanos(self.forward_latency.load(Ordering::Relaxed)),
+        )
+    }
+
+    // Evaluated positions over the maximal batch sizes since the last call, along with
+    // the number of batches. `None` if there were none.
+    pub fn take_fill_rate(&self) -> Option<(f64, usize)> {
+        let batches = self.batches.swap(0, Ordering::Relaxed);
+        let positions = self.positions.swap(0, Ordering::Relaxed);
+        let capacity = self.capacity.swap(0, Ordering::Relaxed);
+        (batches > 0).then(|| (positions as f64 / capacity as f64, batches))
+    }
+}
+
+pub enum BatcherCommand<Net> {
     SetBatchSize(usize),
+    // Evaluations from the next batch on use the new network
+    SwapNet(Net),
 }
 
 impl<Net: AlphaZeroNet> NetworkBatchedExecutor<Net> {
@@ -73,14 +124,15 @@ impl<Net: AlphaZeroNet> NetworkBatchedExecutor<Net> {
         self,
         mut max_batch: usize,
         batch_acc_time: Duration,
-        mut command_receiver: Receiver<BatcherCommand>,
+        mut command_receiver: Receiver<BatcherCommand<Net>>,
         (kind, device): (Kind, Device),
+        stats: Arc<BatchStats>,
     ) -> Net {
         const MAX_PAR_RESPS: usize = 1;
 
         let NetworkBatchedExecutor {
             mut receiver,
-            nn,
+            mut nn,
             sender,
         } = self;
         drop(sender);
@@ -122,9 +174,13 @@ impl<Net: AlphaZeroNet> NetworkBatchedExecutor<Net> {
                         };
                         match cmd {
                             BatcherCommand::SetBatchSize(s) => {
-                                println!("Changing batch size to {s}");
+                                debug!(batch_size = s, "Changing the batch size");
                                 max_batch = s;
                             },
+                            BatcherCommand::SwapNet(new_nn) => {
+                                debug!("Swapping the network");
+                                nn = new_nn;
+                            },
                         }
                     }
                 }
@@ -135,7 +191,7 @@ impl<Net: AlphaZeroNet> NetworkBatchedExecutor<Net> {
             }
 
             if buf.len() != max_batch {
-                println!("Batch of size {} (max_batch = {max_batch})", buf.len());
+                trace!(size = buf.len(), max_batch, "Partial batch");
             }
             if buf.is_empty() {
                 acc_time *= 2;
@@ -152,26 +208,33 @@ impl<Net: AlphaZeroNet> NetworkBatchedExecutor<Net> {
 
             let timer = Timer::new();
             let input = Tensor::stack(&inputs, 0).totype(kind).to(device);
-            timer.print_if_greater(Duration::from_secs(1), "Input construction took {t}");
-            let (values, policies) = nn.forward_t(&input, false);
-            timer.print_if_greater(Duration::from_secs(1), "Input evaluation took {t}");
+            timer.warn_if_greater(Duration::from_secs(1), "Input construction");
+            let NetOutput {
+                value: values,
+                policy: policies,
+                ..
+            } = nn.forward_t(&input, false);
+            timer.warn_if_greater(Duration::from_secs(1), "Input evaluation");
             let values = values.to(Device::Cpu);
             let policies = policies.to(Device::Cpu);
-            timer.print_if_greater(Duration::from_secs(1), "CPU conversion took {t}");
+            timer.warn_if_greater(Duration::from_secs(1), "CPU conversion");
+            let forward = timer.passed();
             response_tasks.push(tokio::spawn(async move {
                 for (i, resp) in responses.iter().enumerate() {
                     let value = values.get(i as i64);
                     let policy = policies.get(i as i64);
-                    resp.send((value, policy)).await.unwrap();
+                    // The task may have been aborted while waiting
+                    let _ = resp.send((value, policy)).await;
                 }
-                timer.print_if_greater(Duration::from_secs(1), "Reply took {t}");
+                timer.warn_if_greater(Duration::from_secs(1), "Reply");
             }));
 
             invocations += 1;
             total_tensors += inputs.len();
+            stats.record(inputs.len(), max_batch, forward);
 
             if invocations % 1000 == 0 {
-                println!("Invocations: {invocations}, total tensors: {total_tensors}");
+                debug!(invocations, total_tensors, "Evaluated batches");
             }
 
             inputs.clear();
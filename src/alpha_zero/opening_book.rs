@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+
+use super::{Game, MoveParameters};
+
+// Weighted set of move sequences to start games from. Cheap to clone, so every
+// spawned game can hold its own copy.
+pub struct OpeningBook<TGame: Game> {
+    openings: Arc<Vec<Vec<TGame::Move>>>,
+    weights: Arc<WeightedIndex<f32>>,
+}
+
+impl<TGame: Game> Clone for OpeningBook<TGame> {
+    fn clone(&self) -> Self {
+        Self {
+            openings: self.openings.clone(),
+            weights: self.weights.clone(),
+        }
+    }
+}
+
+impl<TGame: Game> OpeningBook<TGame> {
+    pub fn new(openings: Vec<(Vec<TGame::Move>, f32)>) -> Self {
+        let (openings, weights): (Vec<_>, Vec<_>) = openings.into_iter().unzip();
+        Self {
+            openings: Arc::new(openings),
+            weights: Arc::new(WeightedIndex::new(weights).expect("Invalid opening weights")),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.openings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.openings.is_empty()
+    }
+
+    pub fn sample_moves<R: Rng>(&self, rng: &mut R) -> &[TGame::Move] {
+        &self.openings[self.sample_index(rng)]
+    }
+
+    // Openings are identified by their index, e.g. to tell which one a game started from
+    pub fn sample_index<R: Rng>(&self, rng: &mut R) -> usize {
+        self.weights.sample(rng)
+    }
+
+    // Plays a sampled opening from `start`
+    pub fn sample<R: Rng>(&self, start: &TGame, rng: &mut R) -> TGame
+    where
+        TGame: Clone,
+    {
+        self.play(self.sample_index(rng), start)
+    }
+
+    pub fn moves(&self, index: usize) -> &[TGame::Move] {
+        &self.openings[index]
+    }
+
+    // Whether the opening hands the move to the player who didn't make the game's first move
+    pub fn switches_player(&self, index: usize) -> bool {
+        self.openings[index]
+            .iter()
+            .filter(|m| m.is_player_switch())
+            .count()
+            % 2
+            == 1
+    }
+
+    // Plays the opening with the given index from `start`
+    pub fn play(&self, index: usize, start: &TGame) -> TGame
+    where
+        TGame: Clone,
+    {
+        self.openings[index].iter().fold(start.clone(), |state, m| {
+            state
+                .try_make_move(m)
+                .expect("Opening contains an illegal move")
+        })
+    }
+}
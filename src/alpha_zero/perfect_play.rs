@@ -0,0 +1,164 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    hash::Hash,
+};
+
+use futures::StreamExt;
+use serde::Serialize;
+
+use super::{Agent, Game, Perspective};
+
+// Perfect-play oracle of a solved game
+pub trait Solver<TGame: Game> {
+    // Game-theoretic value for the player to move
+    fn value(&mut self, state: &TGame) -> f32;
+}
+
+// Positions an agent is tested on, with the oracle judging its moves
+pub struct PerfectPlay<TGame> {
+    pub positions: Vec<TGame>,
+    pub solver: Box<dyn Solver<TGame> + Send>,
+}
+
+impl<TGame: Game> PerfectPlay<TGame> {
+    pub fn new(positions: Vec<TGame>, solver: impl Solver<TGame> + Send + 'static) -> Self {
+        Self {
+            positions,
+            solver: Box::new(solver),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PerfectPlayReport {
+    pub positions: usize,
+    // Moves worse than the position's game-theoretic value
+    pub blunders: usize,
+    // Blunders throwing away a won position
+    pub lost_wins: usize,
+}
+
+impl PerfectPlayReport {
+    pub fn blunder_rate(&self) -> f64 {
+        self.blunders as f64 / self.positions.max(1) as f64
+    }
+}
+
+// Distinct non-terminal positions reachable from `start`, at most `limit` of them, closest
+// to the start first
+pub fn reachable_positions<TGame>(start: &TGame, limit: usize) -> Vec<TGame>
+where
+    TGame: Game + Clone + Hash + Eq,
+{
+    let mut seen = HashSet::from([start.clone()]);
+    let mut queue = VecDeque::from([start.clone()]);
+    let mut positions = vec![];
+    while let Some(state) = queue.pop_front() {
+        let Some(moves) = state.get_state().get_moves() else {
+            continue;
+        };
+        if positions.len() == limit {
+            break;
+        }
+        for m in &moves {
+            let next = state.make_move(m);
+            if seen.insert(next.clone()) {
+                queue.push_back(next);
+            }
+        }
+        positions.push(state);
+    }
+    positions
+}
+
+// `moves[i]` being the index of the move chosen in `positions[i]`
+pub fn judge_moves<TGame: Game>(
+    solver: &mut (impl Solver<TGame> + ?Sized),
+    positions: &[TGame],
+    moves: &[usize],
+) -> PerfectPlayReport {
+    let mut report = PerfectPlayReport::default();
+    for (state, &chosen) in positions.iter().zip(moves) {
+        let best = solver.value(state);
+        let r#move = &state
+            .get_state()
+            .get_moves()
+            .expect("Judged a move in a terminal state")[chosen];
+        let value = Perspective::after_move(r#move).convert(solver.value(&state.make_move(r#move)));
+
+        report.positions += 1;
+        if value < best {
+            report.blunders += 1;
+            report.lost_wins += (best == 1.0) as usize;
+        }
+    }
+    report
+}
+
+// Asks a fresh agent for a move in every position, `parallelism` positions at a time, and
+// compares the moves to perfect play
+pub async fn perfect_play_eval<TGame, A>(
+    positions: &[TGame],
+    parallelism: usize,
+    mut make_agent: impl FnMut() -> A,
+    solver: &mut (impl Solver<TGame> + ?Sized),
+) -> PerfectPlayReport
+where
+    TGame: Game,
+    A: Agent<TGame>,
+{
+    let searches = positions
+        .iter()
+        .map(|state| {
+            let mut agent = make_agent();
+            async move { agent.select_move(state).await }
+        })
+        .collect::<Vec<_>>();
+    // In the order of the positions
+    let moves = futures::stream::iter(searches)
+        .buffered(parallelism)
+        .collect::<Vec<_>>()
+        .await;
+    judge_moves(solver, positions, &moves)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{GreedyAgent, RandomAgent},
+        combinatorial::{Nim, NimSolver},
+        tictactoe3::{TicTacToe3, TicTacToe3Solver},
+    };
+
+    use super::{perfect_play_eval, reachable_positions};
+
+    #[test]
+    fn tictactoe_positions() {
+        // Every position of the game, but the 958 finished ones
+        let positions = reachable_positions(&TicTacToe3::new(), usize::MAX);
+        assert_eq!(positions.len(), 5478 - 958);
+        assert_eq!(positions[0], TicTacToe3::new());
+        assert_eq!(reachable_positions(&TicTacToe3::new(), 10).len(), 10);
+    }
+
+    #[tokio::test]
+    async fn perfect_heuristic_never_blunders() {
+        let positions = reachable_positions(&Nim::new(vec![2, 3, 4]), usize::MAX);
+        let heuristic: fn(&Nim) -> f32 = Nim::value;
+        let report = perfect_play_eval(
+            &positions,
+            8,
+            || GreedyAgent::new(Some(heuristic)),
+            &mut NimSolver,
+        )
+        .await;
+        assert_eq!(report.positions, positions.len());
+        assert_eq!(report.blunders, 0);
+
+        let positions = reachable_positions(&TicTacToe3::new(), usize::MAX);
+        let report =
+            perfect_play_eval(&positions, 8, || RandomAgent, &mut TicTacToe3Solver::new()).await;
+        assert!(report.blunder_rate() > 0.1, "{report:?}");
+        assert!(report.lost_wins <= report.blunders);
+    }
+}
@@ -0,0 +1,383 @@
+use std::fmt::Write;
+
+use anyhow::Context;
+
+use super::{
+    Game, GameLog, MoveNotation, MoveParameters, OpeningBook, Perspective, SelfPlaySample,
+};
+
+// `MoveNotation` as plain functions, for code that is generic over games which may have none
+pub struct Notation<TGame: Game> {
+    pub parse: fn(&TGame, &str) -> anyhow::Result<TGame::Move>,
+    pub format: fn(&TGame, &TGame::Move) -> String,
+}
+
+// Not derived, which would require the game to be `Copy` too
+impl<TGame: Game> Clone for Notation<TGame> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<TGame: Game> Copy for Notation<TGame> {}
+
+impl<TGame: MoveNotation> Notation<TGame> {
+    pub fn of() -> Self {
+        Self {
+            parse: TGame::parse_move,
+            format: TGame::format_move,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortableMove<TMove> {
+    pub r#move: TMove,
+    // Search's value for the mover, for players that search
+    pub value: Option<f32>,
+}
+
+// A finished game, written in a PGN-like format: tags like `[First "gen03"]`, one per line,
+// then the moves separated by whitespace, each optionally followed by its value as `{0.512}`,
+// and the result for the first player, `1-0`, `0-1` or `1/2-1/2`. Games are separated by a
+// blank line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortableGame<TMove> {
+    // The first player made the game's first move, counting the opening's
+    pub first: String,
+    pub second: String,
+    // Index in the opening book and the number of its moves, which come first in `moves`
+    pub opening: Option<(usize, usize)>,
+    pub moves: Vec<PortableMove<TMove>>,
+    // For the first player: 1, 0.5 or 0
+    pub result: f32,
+}
+
+fn result_token(result: f32) -> &'static str {
+    match result.partial_cmp(&0.5).unwrap() {
+        std::cmp::Ordering::Greater => "1-0",
+        std::cmp::Ordering::Equal => "1/2-1/2",
+        std::cmp::Ordering::Less => "0-1",
+    }
+}
+
+fn parse_result(token: &str) -> Option<f32> {
+    match token {
+        "1-0" => Some(1.0),
+        "1/2-1/2" => Some(0.5),
+        "0-1" => Some(0.0),
+        _ => None,
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// `[<key> "<value>"]`
+fn parse_tag(line: &str) -> anyhow::Result<(String, String)> {
+    let tag = line
+        .strip_prefix('[')
+        .and_then(|tag| tag.strip_suffix(']'))
+        .with_context(|| format!("Invalid tag {line}"))?;
+    let (key, value) = tag
+        .split_once(' ')
+        .with_context(|| format!("Invalid tag {line}"))?;
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .with_context(|| format!("Tag {key} isn't quoted"))?;
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.push(chars.next().context("Dangling escape")?),
+            c => unescaped.push(c),
+        }
+    }
+    Ok((key.to_owned(), unescaped))
+}
+
+impl<TMove: Clone> PortableGame<TMove> {
+    // `agent` and `opponent` are the names of the log's players
+    pub fn from_log<TGame: Game<Move = TMove> + Clone>(
+        start: &TGame,
+        openings: Option<&OpeningBook<TGame>>,
+        log: &GameLog,
+        agent: &str,
+        opponent: &str,
+    ) -> Self {
+        let mut state = start.clone();
+        let mut moves = vec![];
+        if let (Some(book), Some(index)) = (openings, log.opening) {
+            moves.extend(book.moves(index).iter().map(|m| PortableMove {
+                r#move: m.clone(),
+                value: None,
+            }));
+            state = book.play(index, start);
+        }
+        let opening = log.opening.map(|index| (index, moves.len()));
+        for played in &log.moves {
+            let r#move = state
+                .get_state()
+                .get_moves()
+                .expect("Logged a move in a terminal state")
+                .swap_remove(played.r#move);
+            state = state.make_move(&r#move);
+            moves.push(PortableMove {
+                r#move,
+                value: played.search.as_ref().map(|search| search.root_value),
+            });
+        }
+
+        let (first, second, result) = match log.agent_first {
+            true => (agent, opponent, log.value),
+            false => (opponent, agent, 1.0 - log.value),
+        };
+        Self {
+            first: first.to_owned(),
+            second: second.to_owned(),
+            opening,
+            moves,
+            result,
+        }
+    }
+
+    pub fn to_text<TGame: Game<Move = TMove> + Clone>(
+        &self,
+        start: &TGame,
+        notation: Notation<TGame>,
+    ) -> String {
+        let result = result_token(self.result);
+        let mut text = String::new();
+        let _ = writeln!(text, "[First \"{}\"]", escape(&self.first));
+        let _ = writeln!(text, "[Second \"{}\"]", escape(&self.second));
+        if let Some((index, moves)) = self.opening {
+            let _ = writeln!(text, "[Opening \"{index}\"]\n[OpeningMoves \"{moves}\"]");
+        }
+        let _ = writeln!(text, "[Result \"{result}\"]\n");
+
+        // Lines of at most 80 characters unless a single token is longer
+        let mut line = String::new();
+        let mut push = |token: &str| {
+            if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+                text += &line;
+                text.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line += token;
+        };
+        let mut state = start.clone();
+        for PortableMove { r#move, value } in &self.moves {
+            push(&(notation.format)(&state, r#move));
+            if let Some(value) = value {
+                push(&format!("{{{value:.3}}}"));
+            }
+            state = state.make_move(r#move);
+        }
+        push(result);
+        text += &line;
+        text.push('\n');
+        text
+    }
+
+    // Every game of the text, each played out from `start` to check its moves
+    pub fn parse_all<TGame: Game<Move = TMove> + Clone>(
+        start: &TGame,
+        notation: Notation<TGame>,
+        text: &str,
+    ) -> anyhow::Result<Vec<Self>> {
+        let mut games = vec![];
+        let mut tags = vec![];
+        let mut movetext = String::new();
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('[') {
+                // A tag after the moves starts the next game
+                if !movetext.is_empty() {
+                    let game = Self::parse_game(start, notation, &tags, &movetext)
+                        .with_context(|| format!("Invalid game {}", games.len() + 1))?;
+                    games.push(game);
+                    tags.clear();
+                    movetext.clear();
+                }
+                tags.push(parse_tag(line)?);
+            } else if !line.is_empty() {
+                movetext += line;
+                movetext.push(' ');
+            }
+        }
+        if !tags.is_empty() || !movetext.is_empty() {
+            let game = Self::parse_game(start, notation, &tags, &movetext)
+                .with_context(|| format!("Invalid game {}", games.len() + 1))?;
+            games.push(game);
+        }
+        Ok(games)
+    }
+
+    fn parse_game<TGame: Game<Move = TMove> + Clone>(
+        start: &TGame,
+        notation: Notation<TGame>,
+        tags: &[(String, String)],
+        movetext: &str,
+    ) -> anyhow::Result<Self> {
+        let tag = |key: &str| {
+            tags.iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value.as_str())
+                .with_context(|| format!("Missing the {key} tag"))
+        };
+        let result = tag("Result")?;
+        let result = parse_result(result).with_context(|| format!("Invalid result {result}"))?;
+        let opening = match tag("Opening") {
+            Ok(index) => Some((index.parse()?, tag("OpeningMoves")?.parse()?)),
+            Err(_) => None,
+        };
+
+        let mut state = start.clone();
+        let mut moves: Vec<PortableMove<TMove>> = vec![];
+        let mut tokens = movetext.split_whitespace();
+        let ended = loop {
+            let Some(token) = tokens.next() else {
+                break None;
+            };
+            if let Some(value) = token.strip_prefix('{').and_then(|v| v.strip_suffix('}')) {
+                let last = moves
+                    .last_mut()
+                    .filter(|last| last.value.is_none())
+                    .with_context(|| format!("Value {token} doesn't follow a move"))?;
+                last.value = Some(value.parse().context("Invalid value")?);
+            } else if let Some(result) = parse_result(token) {
+                break Some(result);
+            } else {
+                let r#move = (notation.parse)(&state, token)?;
+                anyhow::ensure!(
+                    state.get_state().get_moves().is_some() && state.is_legal(&r#move),
+                    "Illegal move {token}"
+                );
+                state = state.make_move(&r#move);
+                moves.push(PortableMove {
+                    r#move,
+                    value: None,
+                });
+            }
+        };
+        anyhow::ensure!(ended == Some(result), "The moves don't end with the result");
+        anyhow::ensure!(tokens.next().is_none(), "Moves after the result");
+
+        Ok(Self {
+            first: tag("First")?.to_owned(),
+            second: tag("Second")?.to_owned(),
+            opening,
+            moves,
+            result,
+        })
+    }
+
+    // The positions before every move, with the move played as the policy, for renderers
+    pub fn samples<TGame: Game<Move = TMove> + Clone>(
+        &self,
+        start: &TGame,
+    ) -> Vec<SelfPlaySample<TGame>>
+    where
+        TMove: MoveParameters + PartialEq,
+    {
+        let mut state = start.clone();
+        let mut player = Perspective::Same;
+        let mut samples = vec![];
+        for (move_number, PortableMove { r#move, value }) in self.moves.iter().enumerate() {
+            let moves = state
+                .get_state()
+                .get_moves()
+                .expect("A move after the game ended");
+            let played = moves
+                .iter()
+                .position(|m| m == r#move)
+                .expect("An illegal move");
+            let mut policy = vec![0.0; moves.len()];
+            policy[played] = 1.0;
+            let next = state.make_move(r#move);
+            samples.push(SelfPlaySample {
+                state,
+                policy,
+                value: player.convert(self.result),
+                ownership: None,
+                move_number,
+                player,
+                root_q: value.unwrap_or(0.5),
+                simulations: 0,
+                played,
+                generation: 0,
+            });
+            player = player.then(Perspective::after_move(r#move));
+            state = next;
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{GameLog, MoveLog, Notation, OpeningBook, SearchInfo},
+        tictactoe3::{TicTacToe3, TicTacToe3Move},
+    };
+
+    use super::{PortableGame, PortableMove};
+
+    #[test]
+    fn round_trip() {
+        let start = TicTacToe3::new();
+        let openings = OpeningBook::new(vec![(vec![TicTacToe3Move(4)], 1.0)]);
+        // The opening's move is the first player's, so the agent has the second player's
+        let log = |r#move, agent| MoveLog {
+            ply: 0,
+            agent,
+            r#move,
+            search: agent.then(|| SearchInfo {
+                root_value: 0.75,
+                visits: vec![],
+            }),
+            seconds: 0.0,
+        };
+        let log = GameLog {
+            game: 0,
+            agent_first: false,
+            opening: Some(0),
+            value: 1.0,
+            // Always the first empty cell
+            moves: vec![log(0, true), log(0, false), log(0, true), log(0, false)],
+        };
+        let game = PortableGame::from_log(&start, Some(&openings), &log, "gen01", "random");
+        assert_eq!(game.first, "random");
+        assert_eq!(game.second, "gen01");
+        assert_eq!(game.result, 0.0);
+        assert_eq!(game.opening, Some((0, 1)));
+        assert_eq!(
+            game.moves[1],
+            PortableMove {
+                r#move: TicTacToe3Move(0),
+                value: Some(0.75)
+            }
+        );
+
+        let notation = Notation::of();
+        let text = game.to_text(&start, notation);
+        assert_eq!(
+            text,
+            "[First \"random\"]\n[Second \"gen01\"]\n[Opening \"0\"]\n[OpeningMoves \"1\"]\n\
+             [Result \"0-1\"]\n\n4 0 {0.750} 1 2 {0.750} 3 0-1\n"
+        );
+        let games = PortableGame::parse_all(&start, notation, &format!("{text}\n{text}")).unwrap();
+        assert_eq!(games, [game.clone(), game.clone()]);
+        assert_eq!(game.samples(&start).len(), 5);
+
+        let parse = |text: &str| PortableGame::parse_all(&start, notation, text);
+        assert!(parse(&text.replace("0-1\n", "")).is_err());
+        assert!(parse(&text.replace(" 3 ", " 4 ")).is_err());
+        assert!(parse(&text.replace("4 0", "4 {0.5} 0")).is_ok());
+        assert!(parse(&text.replace("4 0", "{0.5} 4 0")).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Context;
+use futures::StreamExt;
+use serde::Serialize;
+
+use super::{Agent, Game};
+
+// Moves written as text, for files of positions and games. `self` is the position the move is
+// made in.
+pub trait MoveNotation: Game {
+    fn parse_move(&self, text: &str) -> anyhow::Result<Self::Move>;
+
+    // Without whitespace, so that moves can be separated by it
+    fn format_move(&self, m: &Self::Move) -> String;
+}
+
+pub struct TestPosition<TGame> {
+    pub tag: String,
+    pub state: TGame,
+    // Indices in the position's move list
+    pub best_moves: Vec<usize>,
+}
+
+// Positions with known best moves, one per line as `<tag>: <moves> -> <best moves>`, the
+// moves leading to the position being played from the start. Empty lines and lines starting
+// with `#` are skipped.
+pub struct PositionSuite<TGame> {
+    pub positions: Vec<TestPosition<TGame>>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TagScore {
+    pub solved: usize,
+    pub positions: usize,
+}
+
+impl TagScore {
+    pub fn fraction(&self) -> f64 {
+        self.solved as f64 / self.positions.max(1) as f64
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SuiteReport {
+    pub tags: BTreeMap<String, TagScore>,
+}
+
+impl SuiteReport {
+    pub fn total(&self) -> TagScore {
+        self.tags
+            .values()
+            .fold(TagScore::default(), |total, score| TagScore {
+                solved: total.solved + score.solved,
+                positions: total.positions + score.positions,
+            })
+    }
+}
+
+impl<TGame> PositionSuite<TGame>
+where
+    TGame: MoveNotation + Clone,
+    TGame::Move: PartialEq,
+{
+    pub fn parse(start: &TGame, text: &str) -> anyhow::Result<Self> {
+        let mut positions = vec![];
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let position = Self::parse_position(start, line)
+                .with_context(|| format!("Invalid position on line {}", number + 1))?;
+            positions.push(position);
+        }
+        Ok(Self { positions })
+    }
+
+    pub fn load(start: &TGame, path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(start, &text).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn parse_position(start: &TGame, line: &str) -> anyhow::Result<TestPosition<TGame>> {
+        let (tag, line) = line.split_once(':').context("Missing the tag")?;
+        let (moves, best) = line.split_once("->").context("Missing the best moves")?;
+
+        let mut state = start.clone();
+        for text in moves.split_whitespace() {
+            let r#move = state.parse_move(text)?;
+            anyhow::ensure!(
+                state.get_state().get_moves().is_some() && state.is_legal(&r#move),
+                "Illegal move {text}"
+            );
+            state = state.make_move(&r#move);
+        }
+
+        let moves = state
+            .get_state()
+            .get_moves()
+            .context("The game is over in the position")?;
+        let best_moves = best
+            .split_whitespace()
+            .map(|text| {
+                let r#move = state.parse_move(text)?;
+                moves
+                    .iter()
+                    .position(|m| *m == r#move)
+                    .with_context(|| format!("Illegal best move {text}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        anyhow::ensure!(!best_moves.is_empty(), "No best moves are given");
+
+        Ok(TestPosition {
+            tag: tag.trim().to_owned(),
+            state,
+            best_moves,
+        })
+    }
+}
+
+impl<TGame: Game> PositionSuite<TGame> {
+    // Asks a fresh agent for a move in every position, `parallelism` positions at a time
+    pub async fn run<A: Agent<TGame>>(
+        &self,
+        parallelism: usize,
+        mut make_agent: impl FnMut() -> A,
+    ) -> SuiteReport {
+        let searches = self
+            .positions
+            .iter()
+            .map(|position| {
+                let mut agent = make_agent();
+                async move { agent.select_move(&position.state).await }
+            })
+            .collect::<Vec<_>>();
+        let moves = futures::stream::iter(searches)
+            .buffered(parallelism)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut report = SuiteReport::default();
+        for (position, chosen) in self.positions.iter().zip(moves) {
+            let score = report.tags.entry(position.tag.clone()).or_default();
+            score.positions += 1;
+            score.solved += position.best_moves.contains(&chosen) as usize;
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{GreedyAgent, HeuristicEval},
+        tictactoe::{gomoku_tactics, GomokuBoard},
+        tictactoe3::TicTacToe3,
+    };
+
+    use super::PositionSuite;
+
+    #[test]
+    fn parse_errors() {
+        let parse = |text| PositionSuite::parse(&TicTacToe3::new(), text);
+        let suite = parse("# X to move\n\nwin: 0 3 1 4 -> 2\n").unwrap();
+        assert_eq!(suite.positions.len(), 1);
+        assert_eq!(suite.positions[0].tag, "win");
+        // Cell 2 is the first empty one
+        assert_eq!(suite.positions[0].best_moves, [0]);
+
+        assert!(parse("win: 0 3 1 4 2 -> 5").is_err());
+        assert!(parse("win: 0 0 -> 1").is_err());
+        assert!(parse("win: 0 3 -> 9").is_err());
+        assert!(parse("0 3 -> 1").is_err());
+    }
+
+    #[tokio::test]
+    async fn greedy_agent_finds_the_wins() {
+        let suite = gomoku_tactics::<15>();
+        let heuristic: fn(&GomokuBoard<15, 5>) -> f32 = HeuristicEval::eval;
+        let report = suite.run(4, || GreedyAgent::new(Some(heuristic))).await;
+
+        let wins = report.tags["win"];
+        assert!(wins.positions > 0);
+        assert_eq!(wins.solved, wins.positions);
+        assert_eq!(report.total().positions, suite.positions.len());
+    }
+}
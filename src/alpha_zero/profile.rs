@@ -0,0 +1,205 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+// Where self-play spends its time, summed over the games sharing it. Searches and evaluators
+// given one record into it, see `MonteCarloTree::with_profile`.
+#[derive(Default)]
+pub struct SelfPlayProfile {
+    // Nanoseconds per stage
+    selection: AtomicU64,
+    expansion: AtomicU64,
+    conversion: AtomicU64,
+    // From sending a position to the executor until its evaluation came back
+    waiting: AtomicU64,
+    evaluations: AtomicUsize,
+    games: AtomicUsize,
+    moves: AtomicUsize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    // Walking down the tree and backing up the values
+    Selection,
+    // The game's `get_state` and `make_move`
+    Expansion,
+    // The adapter's conversions and the symmetries, both ways
+    Conversion,
+    // Waiting for the executor to fill a batch and get to it
+    Queueing,
+    // The net's forward pass, as each position of a batch waits for all of it
+    Forward,
+}
+
+impl Stage {
+    const ALL: [Stage; 5] = [
+        Stage::Selection,
+        Stage::Expansion,
+        Stage::Conversion,
+        Stage::Queueing,
+        Stage::Forward,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Stage::Selection => "tree selection",
+            Stage::Expansion => "expansion (get_state, make_move)",
+            Stage::Conversion => "adapter conversion",
+            Stage::Queueing => "executor queueing",
+            Stage::Forward => "NN forward",
+        }
+    }
+
+    // What to make faster when the stage takes most of the time
+    fn advice(self) -> &'static str {
+        match self {
+            Stage::Selection => "the search, or play fewer simulations",
+            Stage::Expansion => "the game's move generation",
+            Stage::Conversion => "the adapter's conversion to tensors",
+            Stage::Queueing => {
+                "the batching: raise the parallelism or lower the batch size and wait time"
+            }
+            Stage::Forward => "the net, or its device",
+        }
+    }
+}
+
+impl SelfPlayProfile {
+    // Of the stages measured by the games, see `add_evaluation` for the others
+    pub fn add(&self, stage: Stage, time: Duration) {
+        let counter = match stage {
+            Stage::Selection => &self.selection,
+            Stage::Expansion => &self.expansion,
+            Stage::Conversion => &self.conversion,
+            Stage::Queueing | Stage::Forward => unreachable!("{stage:?} is split from waiting"),
+        };
+        counter.fetch_add(time.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    // Of a position evaluated after `waiting` for the executor
+    pub fn add_evaluation(&self, waiting: Duration) {
+        self.waiting
+            .fetch_add(waiting.as_nanos() as u64, Ordering::Relaxed);
+        self.evaluations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_game(&self, moves: usize) {
+        self.games.fetch_add(1, Ordering::Relaxed);
+        self.moves.fetch_add(moves, Ordering::Relaxed);
+    }
+
+    // Splits the waiting with the executor's summed forward latency, see
+    // `BatchStats::forward_times`
+    pub fn report(&self, wall: Duration, busy: Duration, forward: Duration) -> ProfileReport {
+        let nanos = |counter: &AtomicU64| Duration::from_nanos(counter.load(Ordering::Relaxed));
+        let waiting = nanos(&self.waiting);
+        ProfileReport {
+            games: self.games.load(Ordering::Relaxed),
+            moves: self.moves.load(Ordering::Relaxed),
+            evaluations: self.evaluations.load(Ordering::Relaxed),
+            wall,
+            busy,
+            stages: [
+                nanos(&self.selection),
+                nanos(&self.expansion),
+                nanos(&self.conversion),
+                waiting.saturating_sub(forward),
+                forward.min(waiting),
+            ],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ProfileReport {
+    pub games: usize,
+    pub moves: usize,
+    pub evaluations: usize,
+    pub wall: Duration,
+    // Time the net was evaluating
+    pub busy: Duration,
+    // Summed over the games, in the order of `Stage::ALL`
+    stages: [Duration; 5],
+}
+
+impl ProfileReport {
+    pub fn time(&self, stage: Stage) -> Duration {
+        self.stages[Stage::ALL.iter().position(|&s| s == stage).unwrap()]
+    }
+
+    pub fn bottleneck(&self) -> Stage {
+        *Stage::ALL
+            .iter()
+            .max_by_key(|&&stage| self.time(stage))
+            .unwrap()
+    }
+}
+
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Played {} games of {} moves and {} evaluations in {:.1}s",
+            self.games,
+            self.moves,
+            self.evaluations,
+            self.wall.as_secs_f64()
+        )?;
+        writeln!(f)?;
+        writeln!(f, "| stage | per game | share |")?;
+        writeln!(f, "|---|---|---|")?;
+        let total = self.stages.iter().sum::<Duration>().as_secs_f64().max(1e-9);
+        let games = self.games.max(1) as u32;
+        for stage in Stage::ALL {
+            let time = self.time(stage);
+            writeln!(
+                f,
+                "| {} | {:.1?} | {:.1}% |",
+                stage.name(),
+                time / games,
+                100.0 * time.as_secs_f64() / total
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            "Games overlap, so their times add up to more than the run's. The net was busy {:.0}% \
+             of it.",
+            100.0 * self.busy.as_secs_f64() / self.wall.as_secs_f64().max(1e-9)
+        )?;
+        write!(f, "Most time goes to {}", self.bottleneck().advice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{SelfPlayProfile, Stage};
+
+    #[test]
+    fn waiting_splits_into_queueing_and_forward() {
+        let profile = SelfPlayProfile::default();
+        let ms = Duration::from_millis;
+        profile.add(Stage::Selection, ms(35));
+        profile.add(Stage::Expansion, ms(10));
+        profile.add(Stage::Conversion, ms(5));
+        for _ in 0..4 {
+            profile.add_evaluation(ms(50));
+        }
+        profile.add_game(9);
+        profile.add_game(7);
+
+        // Positions waited 200ms, 80ms of which their batches were evaluated
+        let report = profile.report(ms(150), ms(40), ms(80));
+        assert_eq!((report.games, report.moves, report.evaluations), (2, 16, 4));
+        assert_eq!(report.time(Stage::Queueing), ms(120));
+        assert_eq!(report.time(Stage::Forward), ms(80));
+        assert_eq!(report.bottleneck(), Stage::Queueing);
+        assert!(report
+            .to_string()
+            .contains("| executor queueing | 60.0ms | 48.0% |"));
+    }
+}
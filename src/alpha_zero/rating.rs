@@ -0,0 +1,155 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use super::{CrossTable, MatchResult};
+
+// Elo difference implied by a match, from the first player's point of view. Half a game
+// is added to each side, so that a clean sweep doesn't give an infinite difference.
+pub fn elo_difference(result: &MatchResult) -> f64 {
+    let score =
+        (result.wins as f64 + result.draws as f64 / 2.0 + 0.5) / (result.games() + 1) as f64;
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+// Maximum-likelihood Bradley-Terry ratings on the Elo scale, with the first participant at 0.
+// Only meaningful for participants connected to it by games. Draws count as half a win.
+// Like BayesElo's prior, `prior` virtual draws are added to every pair that played, which
+// keeps the ratings of unbeaten participants finite.
+pub fn bradley_terry(table: &CrossTable, prior: f64) -> Vec<f64> {
+    let n = table.names.len();
+    let played = |i: usize, j: usize| i != j && table.results[i][j].games() > 0;
+    let score = |i: usize, j: usize| {
+        let result = &table.results[i][j];
+        result.wins as f64 + result.draws as f64 / 2.0 + prior / 2.0
+    };
+    let games = |i: usize, j: usize| table.results[i][j].games() as f64 + prior;
+
+    let mut gamma = vec![1.0f64; n];
+    // Minorization-maximization, which converges monotonically
+    for _ in 0..10_000 {
+        let mut next = gamma.clone();
+        for (i, next) in next.iter_mut().enumerate() {
+            let (mut wins, mut weight) = (0.0, 0.0);
+            for j in (0..n).filter(|&j| played(i, j)) {
+                wins += score(i, j);
+                weight += games(i, j) / (gamma[i] + gamma[j]);
+            }
+            if weight > 0.0 {
+                *next = wins / weight;
+            }
+        }
+        let change = gamma
+            .iter()
+            .zip(&next)
+            .map(|(a, b)| (a.ln() - b.ln()).abs())
+            .fold(0.0, f64::max);
+        gamma = next;
+        if change < 1e-10 {
+            break;
+        }
+    }
+    gamma
+        .iter()
+        .map(|g| 400.0 * (g / gamma[0]).log10())
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RatingEntry {
+    // Generation of the run when the rating was computed
+    pub step: usize,
+    pub elo: f64,
+    // Behind the rating
+    pub games: usize,
+}
+
+// Every rating computed for every checkpoint, stored as JSON in the run directory
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RatingHistory {
+    pub ratings: BTreeMap<String, Vec<RatingEntry>>,
+}
+
+impl RatingHistory {
+    // Empty if the file doesn't exist yet
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("Malformed ratings in {}", path.display()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn record(&mut self, name: impl Into<String>, entry: RatingEntry) {
+        self.ratings.entry(name.into()).or_default().push(entry);
+    }
+
+    pub fn latest(&self, name: &str) -> Option<f64> {
+        self.ratings.get(name)?.last().map(|entry| entry.elo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alpha_zero::{CrossTable, MatchResult};
+
+    use super::{bradley_terry, elo_difference, RatingEntry, RatingHistory};
+
+    fn result(wins: usize, draws: usize, losses: usize) -> MatchResult {
+        MatchResult {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    #[test]
+    fn ratings_match_the_scores() {
+        assert_eq!(elo_difference(&result(1, 1, 1)), 0.0);
+        // 3 out of 4 points, 3.5 out of 5 with the extra half games
+        assert!((elo_difference(&result(2, 2, 0)) - 147.19).abs() < 0.01);
+
+        let mut table = CrossTable::new(vec!["a".into(), "b".into(), "c".into()]);
+        table.set(0, 1, result(1, 0, 3));
+        let elo = bradley_terry(&table, 0.0);
+        assert!((elo[1] - 190.85).abs() < 0.01);
+
+        // Beating everyone is still a finite rating with a prior
+        table.set(1, 2, result(2, 0, 2));
+        table.set(2, 0, result(4, 0, 0));
+        let elo = bradley_terry(&table, 2.0);
+        assert!(elo.iter().all(|e| e.is_finite()));
+        assert!(elo[2] > elo[1] && elo[1] > elo[0]);
+    }
+
+    #[test]
+    fn history_round_trips() {
+        let path = std::env::temp_dir().join(format!("ratings-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut history = RatingHistory::load(&path).unwrap();
+        let entry = |step, elo| RatingEntry {
+            step,
+            elo,
+            games: 10,
+        };
+        history.record("gen01", entry(1, 50.0));
+        history.record("gen01", entry(4, 80.0));
+        history.save(&path).unwrap();
+
+        let loaded = RatingHistory::load(&path).unwrap();
+        assert_eq!(loaded, history);
+        assert_eq!(loaded.latest("gen01"), Some(80.0));
+        assert_eq!(loaded.latest("gen02"), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
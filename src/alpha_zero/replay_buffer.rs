@@ -0,0 +1,301 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    seq::index,
+    Rng,
+};
+
+use super::{hash_position, SelfPlaySample};
+
+struct Dedup<TGame> {
+    hash: fn(&TGame) -> u64,
+    eq: fn(&TGame, &TGame) -> bool,
+    // State hash to the absolute position of its sample
+    index: HashMap<u64, u64>,
+}
+
+struct Entry<TGame> {
+    sample: SelfPlaySample<TGame>,
+    // Number of positions merged into the sample
+    merged: usize,
+    priority: f32,
+}
+
+// Stays valid until the sample is evicted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SampleId(u64);
+
+pub struct PrioritizedSample<'a, TGame> {
+    pub id: SampleId,
+    pub sample: &'a SelfPlaySample<TGame>,
+    // Importance-sampling weight correcting for the non-uniform sampling, at most 1
+    pub weight: f32,
+}
+
+// Sliding window over the most recent self-play positions. Old positions are evicted
+// first once there are more than `capacity` of them.
+pub struct ReplayBuffer<TGame> {
+    capacity: usize,
+    samples: VecDeque<Entry<TGame>>,
+    evicted: u64,
+    dedup: Option<Dedup<TGame>>,
+    // New samples get the highest priority seen so far, so each is trained on at least once
+    max_priority: f32,
+}
+
+impl<TGame> ReplayBuffer<TGame> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "Replay buffer must hold at least one position"
+        );
+        Self {
+            capacity,
+            samples: VecDeque::with_capacity(capacity),
+            evicted: 0,
+            dedup: None,
+            max_priority: 1.0,
+        }
+    }
+
+    // Positions seen again are merged into the stored sample, averaging the policy and
+    // the value, instead of taking another slot
+    pub fn with_dedup(capacity: usize) -> Self
+    where
+        TGame: Hash + Eq,
+    {
+        Self {
+            dedup: Some(Dedup {
+                hash: hash_position,
+                eq: |a, b| a == b,
+                index: HashMap::new(),
+            }),
+            ..Self::new(capacity)
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn push(&mut self, sample: SelfPlaySample<TGame>) {
+        if let Some(dedup) = &mut self.dedup {
+            let hash = (dedup.hash)(&sample.state);
+            let position = self.evicted + self.samples.len() as u64;
+            match dedup.index.get(&hash).copied() {
+                Some(existing) => {
+                    let entry = &mut self.samples[(existing - self.evicted) as usize];
+                    // On a hash collision the newer position simply isn't indexed
+                    if (dedup.eq)(&entry.sample.state, &sample.state) {
+                        entry.merge(&sample);
+                        return;
+                    }
+                }
+                None => {
+                    dedup.index.insert(hash, position);
+                }
+            }
+        }
+
+        self.samples.push_back(Entry {
+            sample,
+            merged: 1,
+            priority: self.max_priority,
+        });
+        while self.samples.len() > self.capacity {
+            let Entry { sample, .. } = self.samples.pop_front().unwrap();
+            if let Some(dedup) = &mut self.dedup {
+                let hash = (dedup.hash)(&sample.state);
+                if dedup.index.get(&hash) == Some(&self.evicted) {
+                    dedup.index.remove(&hash);
+                }
+            }
+            self.evicted += 1;
+        }
+    }
+
+    pub fn extend(&mut self, samples: impl IntoIterator<Item = SelfPlaySample<TGame>>) {
+        for sample in samples {
+            self.push(sample);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SelfPlaySample<TGame>> {
+        self.samples.iter().map(|entry| &entry.sample)
+    }
+
+    // Uniformly chosen distinct positions, all of them if there are fewer than `amount`
+    pub fn sample<R: Rng>(&self, amount: usize, rng: &mut R) -> Vec<&SelfPlaySample<TGame>> {
+        index::sample(rng, self.len(), amount.min(self.len()))
+            .into_iter()
+            .map(|i| &self.samples[i].sample)
+            .collect()
+    }
+
+    // Samples with replacement, proportionally to `priority^alpha`. `beta` controls how
+    // much of the resulting bias the weights correct, 1 corrects it fully.
+    pub fn sample_prioritized<R: Rng>(
+        &self,
+        amount: usize,
+        alpha: f32,
+        beta: f32,
+        rng: &mut R,
+    ) -> Vec<PrioritizedSample<'_, TGame>> {
+        if self.is_empty() {
+            return vec![];
+        }
+        let scaled = self
+            .samples
+            .iter()
+            .map(|entry| entry.priority.powf(alpha))
+            .collect::<Vec<_>>();
+        let total: f32 = scaled.iter().sum();
+        let distribution = WeightedIndex::new(&scaled).expect("Invalid priorities");
+
+        let n = self.len() as f32;
+        let weight = |i: usize| (n * scaled[i] / total).powf(-beta);
+        // Normalized by the largest possible weight, the one of the lowest priority sample
+        let max_weight = weight(
+            (0..self.len())
+                .min_by(|&a, &b| scaled[a].total_cmp(&scaled[b]))
+                .unwrap(),
+        );
+
+        (0..amount)
+            .map(|_| {
+                let i = distribution.sample(rng);
+                PrioritizedSample {
+                    id: SampleId(self.evicted + i as u64),
+                    sample: &self.samples[i].sample,
+                    weight: weight(i) / max_weight,
+                }
+            })
+            .collect()
+    }
+
+    // Typically the sample's latest loss. Samples evicted in the meantime are skipped.
+    pub fn update_priorities(&mut self, priorities: impl IntoIterator<Item = (SampleId, f32)>) {
+        for (SampleId(id), priority) in priorities {
+            assert!(
+                priority > 0.0,
+                "Priorities must be positive, got {priority}"
+            );
+            if let Some(entry) = id
+                .checked_sub(self.evicted)
+                .and_then(|i| self.samples.get_mut(i as usize))
+            {
+                entry.priority = priority;
+                self.max_priority = self.max_priority.max(priority);
+            }
+        }
+    }
+}
+
+impl<TGame> Entry<TGame> {
+    fn merge(&mut self, new: &SelfPlaySample<TGame>) {
+        let weight = 1.0 / (self.merged + 1) as f32;
+        for (p, q) in self.sample.policy.iter_mut().zip(&new.policy) {
+            *p += (q - *p) * weight;
+        }
+        self.sample.value += (new.value - self.sample.value) * weight;
+        self.merged += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::thread_rng;
+
+    use crate::alpha_zero::SelfPlaySample;
+
+    use super::{ReplayBuffer, SampleId};
+
+    fn sample(state: u32, value: f32) -> SelfPlaySample<u32> {
+        SelfPlaySample {
+            policy: vec![value, 1.0 - value],
+            value,
+            ..SelfPlaySample::uniform(state, 2)
+        }
+    }
+
+    #[test]
+    fn fifo_eviction_and_sampling() {
+        let mut buffer = ReplayBuffer::new(3);
+        buffer.extend((0..5).map(|i| sample(i, 0.0)));
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(
+            buffer.iter().map(|s| s.state).collect::<Vec<_>>(),
+            [2, 3, 4]
+        );
+
+        let mut drawn = buffer
+            .sample(10, &mut thread_rng())
+            .into_iter()
+            .map(|s| s.state)
+            .collect::<Vec<_>>();
+        drawn.sort();
+        assert_eq!(drawn, [2, 3, 4]);
+        assert_eq!(buffer.sample(2, &mut thread_rng()).len(), 2);
+    }
+
+    #[test]
+    fn dedup_merges_positions() {
+        let mut buffer = ReplayBuffer::with_dedup(2);
+        buffer.push(sample(7, 1.0));
+        buffer.push(sample(7, 0.0));
+        buffer.push(sample(7, 0.0));
+        assert_eq!(buffer.len(), 1);
+        let merged = buffer.iter().next().unwrap();
+        assert!((merged.value - 1.0 / 3.0).abs() < 1e-6);
+        assert!((merged.policy[1] - 2.0 / 3.0).abs() < 1e-6);
+
+        // Once evicted, the position is stored anew
+        buffer.push(sample(8, 0.0));
+        buffer.push(sample(9, 0.0));
+        buffer.push(sample(7, 0.0));
+        assert_eq!(buffer.iter().map(|s| s.state).collect::<Vec<_>>(), [9, 7]);
+        assert_eq!(buffer.iter().last().unwrap().value, 0.0);
+    }
+
+    #[test]
+    fn prioritized_sampling() {
+        let mut buffer = ReplayBuffer::new(3);
+        buffer.extend((0..4).map(|i| sample(i, 0.0)));
+        // Ids of the kept samples are 1, 2 and 3, the first one is already evicted
+        buffer.update_priorities([(SampleId(0), 100.0), (SampleId(1), 9.0), (SampleId(3), 0.5)]);
+
+        let drawn = buffer.sample_prioritized(4000, 1.0, 1.0, &mut thread_rng());
+        let hits = |state| drawn.iter().filter(|s| s.sample.state == state).count();
+        // Priorities are 9, 1 and 0.5
+        assert!(
+            hits(1) > 3000 && hits(3) < 300,
+            "{:?}",
+            (hits(1), hits(2), hits(3))
+        );
+
+        for s in &drawn {
+            let expected = match s.sample.state {
+                1 => 0.5 / 9.0,
+                2 => 0.5,
+                _ => 1.0,
+            };
+            assert!((s.weight - expected).abs() < 1e-5);
+        }
+
+        // Without any correction, all weights are equal
+        let drawn = buffer.sample_prioritized(10, 1.0, 0.0, &mut thread_rng());
+        assert!(drawn.iter().all(|s| s.weight == 1.0));
+    }
+}
@@ -0,0 +1,307 @@
+use tch::{
+    nn::{self, BatchNorm, Conv2D, ConvConfig, Linear, Module, ModuleT},
+    Tensor,
+};
+
+use super::{AlphaZeroNet, NetOutput};
+
+#[derive(Debug, Clone)]
+pub struct ResNetConfig {
+    pub input_planes: i64,
+    // Side of the square board, every layer of the tower keeps it
+    pub board_size: i64,
+    // Shape of a single policy as the adapter expects it, see `ActionEncoding::policy_shape`
+    pub policy_shape: Vec<i64>,
+    pub blocks: usize,
+    pub channels: i64,
+    pub value_hidden: i64,
+    // Reduction ratio of squeeze-and-excitation in every block, which rescales the channels
+    // by what the whole board holds. `None` disables it.
+    pub squeeze_excitation: Option<i64>,
+    // KataGo's global pooling: the means and maxima of the tower's channels bias the policy
+    // planes and feed the value head, so that both see beyond the convolutions' reach
+    pub global_pooling: bool,
+    // Auxiliary heads, see `NetOutput`. They are only trained, so they cost nothing in
+    // search, and the ownership one only helps games whose adapter has targets for it.
+    pub ownership_head: bool,
+    pub soft_policy_head: bool,
+}
+
+impl ResNetConfig {
+    // A tower of the default size
+    pub fn new(input_planes: i64, board_size: i64, policy_shape: Vec<i64>) -> Self {
+        Self {
+            input_planes,
+            board_size,
+            policy_shape,
+            blocks: 6,
+            channels: 64,
+            value_hidden: 128,
+            squeeze_excitation: None,
+            global_pooling: false,
+            ownership_head: false,
+            soft_policy_head: false,
+        }
+    }
+
+    pub fn with_tower(mut self, blocks: usize, channels: i64) -> Self {
+        self.blocks = blocks;
+        self.channels = channels;
+        self
+    }
+
+    pub fn with_value_hidden(mut self, value_hidden: i64) -> Self {
+        self.value_hidden = value_hidden;
+        self
+    }
+
+    pub fn with_squeeze_excitation(mut self, reduction: i64) -> Self {
+        self.squeeze_excitation = Some(reduction);
+        self
+    }
+
+    pub fn with_global_pooling(mut self) -> Self {
+        self.global_pooling = true;
+        self
+    }
+
+    pub fn with_auxiliary_heads(mut self, ownership: bool, soft_policy: bool) -> Self {
+        self.ownership_head = ownership;
+        self.soft_policy_head = soft_policy;
+        self
+    }
+
+    pub fn action_size(&self) -> i64 {
+        self.policy_shape.iter().product()
+    }
+}
+
+fn conv3x3(path: nn::Path, c_in: i64, c_out: i64) -> Conv2D {
+    nn::conv2d(
+        path,
+        c_in,
+        c_out,
+        3,
+        ConvConfig {
+            padding: 1,
+            bias: false,
+            ..Default::default()
+        },
+    )
+}
+
+// Means and maxima of every channel over the board, 2C features
+fn global_pool(xs: &Tensor) -> Tensor {
+    Tensor::concat(
+        &[
+            xs.mean_dim([2, 3].as_slice(), false, None),
+            xs.amax([2, 3], false),
+        ],
+        1,
+    )
+}
+
+struct SqueezeExcitation {
+    fc_squeeze: Linear,
+    fc_excite: Linear,
+}
+
+impl SqueezeExcitation {
+    fn new(path: nn::Path, channels: i64, reduction: i64) -> Self {
+        let squeezed = (channels / reduction).max(1);
+        Self {
+            fc_squeeze: nn::linear(&path / "fc_squeeze", channels, squeezed, Default::default()),
+            fc_excite: nn::linear(&path / "fc_excite", squeezed, channels, Default::default()),
+        }
+    }
+
+    fn forward(&self, xs: &Tensor) -> Tensor {
+        let size = xs.size();
+        let scale = self
+            .fc_squeeze
+            .forward(&xs.mean_dim([2, 3].as_slice(), false, None))
+            .relu();
+        let scale = self.fc_excite.forward(&scale).sigmoid();
+        xs * scale.view([size[0], size[1], 1, 1])
+    }
+}
+
+struct ResidualBlock {
+    conv1: Conv2D,
+    bn1: BatchNorm,
+    conv2: Conv2D,
+    bn2: BatchNorm,
+    se: Option<SqueezeExcitation>,
+}
+
+impl ResidualBlock {
+    fn new(path: nn::Path, channels: i64, squeeze_excitation: Option<i64>) -> Self {
+        Self {
+            conv1: conv3x3(&path / "conv1", channels, channels),
+            bn1: nn::batch_norm2d(&path / "bn1", channels, Default::default()),
+            conv2: conv3x3(&path / "conv2", channels, channels),
+            bn2: nn::batch_norm2d(&path / "bn2", channels, Default::default()),
+            se: squeeze_excitation
+                .map(|reduction| SqueezeExcitation::new(&path / "se", channels, reduction)),
+        }
+    }
+
+    fn forward_t(&self, xs: &Tensor, is_training: bool) -> Tensor {
+        let ys = self.conv1.forward_t(xs, is_training);
+        let ys = self.bn1.forward_t(&ys, is_training).relu();
+        let ys = self.conv2.forward_t(&ys, is_training);
+        let ys = self.bn2.forward_t(&ys, is_training);
+        let ys = match &self.se {
+            Some(se) => se.forward(&ys),
+            None => ys,
+        };
+        (ys + xs).relu()
+    }
+}
+
+// The AlphaGo Zero architecture for any game on a square board: a residual tower, a policy
+// head with two planes and a fully connected layer onto the action space, and a value head
+// with one plane. Values are probabilities of winning for the player to move. See
+// `ResNetConfig` for the options beyond AlphaGo Zero.
+pub struct ResNetAlphaZero {
+    conv_input: Conv2D,
+    bn_input: BatchNorm,
+    blocks: Vec<ResidualBlock>,
+
+    conv_policy: Conv2D,
+    // Of the pooled tower onto a bias of every policy plane
+    fc_policy_pooled: Option<Linear>,
+    bn_policy: BatchNorm,
+    fc_policy: Linear,
+    fc_soft_policy: Option<Linear>,
+
+    conv_value: Conv2D,
+    bn_value: BatchNorm,
+    fc_value_1: Linear,
+    fc_value_2: Linear,
+
+    conv_ownership: Option<Conv2D>,
+
+    policy_shape: Vec<i64>,
+}
+
+impl ResNetAlphaZero {
+    pub fn new(path: &nn::Path, config: &ResNetConfig) -> Self {
+        let channels = config.channels;
+        let area = config.board_size * config.board_size;
+        // Features the value head gets besides its plane
+        let pooled = match config.global_pooling {
+            true => 2 * channels,
+            false => 0,
+        };
+        Self {
+            conv_input: conv3x3(path / "conv_input", config.input_planes, channels),
+            bn_input: nn::batch_norm2d(path / "bn_input", channels, Default::default()),
+            blocks: (0..config.blocks)
+                .map(|i| {
+                    ResidualBlock::new(path / "blocks" / i, channels, config.squeeze_excitation)
+                })
+                .collect(),
+
+            conv_policy: nn::conv2d(path / "conv_policy", channels, 2, 1, Default::default()),
+            fc_policy_pooled: config.global_pooling.then(|| {
+                nn::linear(
+                    path / "fc_policy_pooled",
+                    2 * channels,
+                    2,
+                    Default::default(),
+                )
+            }),
+            bn_policy: nn::batch_norm2d(path / "bn_policy", 2, Default::default()),
+            fc_policy: nn::linear(
+                path / "fc_policy",
+                2 * area,
+                config.action_size(),
+                Default::default(),
+            ),
+            fc_soft_policy: config.soft_policy_head.then(|| {
+                nn::linear(
+                    path / "fc_soft_policy",
+                    2 * area,
+                    config.action_size(),
+                    Default::default(),
+                )
+            }),
+
+            conv_value: nn::conv2d(path / "conv_value", channels, 1, 1, Default::default()),
+            bn_value: nn::batch_norm2d(path / "bn_value", 1, Default::default()),
+            fc_value_1: nn::linear(
+                path / "fc_value_1",
+                area + pooled,
+                config.value_hidden,
+                Default::default(),
+            ),
+            fc_value_2: nn::linear(
+                path / "fc_value_2",
+                config.value_hidden,
+                1,
+                Default::default(),
+            ),
+
+            conv_ownership: config
+                .ownership_head
+                .then(|| nn::conv2d(path / "conv_ownership", channels, 1, 1, Default::default())),
+
+            policy_shape: config.policy_shape.clone(),
+        }
+    }
+}
+
+impl AlphaZeroNet for ResNetAlphaZero {
+    fn forward_t(&self, xs: &Tensor, is_training: bool) -> NetOutput {
+        let batch = xs.size()[0];
+
+        let mid = self.conv_input.forward_t(xs, is_training);
+        let mut mid = self.bn_input.forward_t(&mid, is_training).relu();
+        for block in &self.blocks {
+            mid = block.forward_t(&mid, is_training);
+        }
+
+        let pooled = self.fc_policy_pooled.is_some().then(|| global_pool(&mid));
+
+        let policy = self.conv_policy.forward_t(&mid, is_training);
+        let policy = match (&self.fc_policy_pooled, &pooled) {
+            (Some(fc), Some(pooled)) => policy + fc.forward(pooled).view([batch, 2, 1, 1]),
+            _ => policy,
+        };
+        let policy = self.bn_policy.forward_t(&policy, is_training).relu();
+        let policy = policy.view([batch, -1]);
+        let policy_shape = [&[batch], self.policy_shape.as_slice()].concat();
+        let soft_policy = self.fc_soft_policy.as_ref().map(|fc| {
+            fc.forward(&policy)
+                .log_softmax(1, None)
+                .view(policy_shape.as_slice())
+        });
+        let policy = self
+            .fc_policy
+            .forward(&policy)
+            .log_softmax(1, None)
+            .view(policy_shape.as_slice());
+
+        let val = self.conv_value.forward_t(&mid, is_training);
+        let val = self.bn_value.forward_t(&val, is_training).relu();
+        let val = val.view([batch, -1]);
+        let val = match &pooled {
+            Some(pooled) => Tensor::concat(&[&val, pooled], 1),
+            None => val,
+        };
+        let val = self.fc_value_1.forward(&val).relu();
+        let val = self.fc_value_2.forward(&val).view([batch]).sigmoid();
+
+        let ownership = self
+            .conv_ownership
+            .as_ref()
+            .map(|conv| conv.forward_t(&mid, is_training).squeeze_dim(1).tanh());
+
+        NetOutput {
+            ownership,
+            soft_policy,
+            ..NetOutput::new(val, policy)
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::{fmt::Write, iter::Peekable, str::Chars};
+
+use anyhow::Context;
+
+use super::{Game, MoveParameters, Perspective, PortableGame, PortableMove};
+
+// Games on a square board that SGF viewers can show. Black is the first player.
+pub trait SgfGame: Game {
+    // The `GM` property, 1 for Go and 4 for gomoku
+    const GAME_TYPE: u32;
+
+    fn board_size(&self) -> usize;
+
+    // As `(row, column)` from the top left corner, `None` for a pass
+    fn move_point(&self, m: &Self::Move) -> Option<(usize, usize)>;
+
+    fn point_move(&self, point: Option<(usize, usize)>) -> anyhow::Result<Self::Move>;
+
+    // Written to the root node besides the game type and the board size, like Go's komi
+    fn root_properties(&self) -> Vec<(&'static str, String)> {
+        vec![]
+    }
+}
+
+type Games<TMove> = Vec<PortableGame<TMove>>;
+
+// `SgfGame` as plain functions, like `Notation`
+pub struct SgfFormat<TGame: Game> {
+    pub read: fn(&TGame, &str) -> anyhow::Result<Games<TGame::Move>>,
+    pub write: fn(&TGame, &PortableGame<TGame::Move>) -> String,
+}
+
+impl<TGame: Game> Clone for SgfFormat<TGame> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<TGame: Game> Copy for SgfFormat<TGame> {}
+
+impl<TGame> SgfFormat<TGame>
+where
+    TGame: SgfGame + Clone,
+    TGame::Move: Clone + MoveParameters,
+{
+    pub fn of() -> Self {
+        Self {
+            read: read_sgf,
+            write: write_sgf,
+        }
+    }
+}
+
+fn point_text((row, column): (usize, usize)) -> String {
+    [column, row]
+        .map(|x| (b'a' + x as u8) as char)
+        .iter()
+        .collect()
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+// A game tree with a single line of moves. Values of the players' moves and the opening are
+// left out, viewers having no use for them.
+pub fn write_sgf<TGame>(start: &TGame, game: &PortableGame<TGame::Move>) -> String
+where
+    TGame: SgfGame + Clone,
+    TGame::Move: MoveParameters,
+{
+    let result = match game.result.partial_cmp(&0.5).unwrap() {
+        std::cmp::Ordering::Greater => "B+",
+        std::cmp::Ordering::Equal => "0",
+        std::cmp::Ordering::Less => "W+",
+    };
+    let mut text = format!(
+        "(;FF[4]GM[{}]SZ[{}]PB[{}]PW[{}]RE[{result}]",
+        TGame::GAME_TYPE,
+        start.board_size(),
+        escape(&game.first),
+        escape(&game.second)
+    );
+    for (key, value) in start.root_properties() {
+        let _ = write!(text, "{key}[{}]", escape(&value));
+    }
+    text.push('\n');
+
+    let mut state = start.clone();
+    let mut player = Perspective::Same;
+    for PortableMove { r#move, .. } in &game.moves {
+        let color = match player {
+            Perspective::Same => 'B',
+            Perspective::Opponent => 'W',
+        };
+        let point = state.move_point(r#move).map(point_text).unwrap_or_default();
+        let _ = writeln!(text, ";{color}[{point}]");
+        player = player.then(Perspective::after_move(r#move));
+        state = state.make_move(r#move);
+    }
+    text.push_str(")\n");
+    text
+}
+
+// Every game of a collection, each played out from `start` to check its moves. Only the main
+// line of a game tree is read, and setup stones aren't supported.
+pub fn read_sgf<TGame>(start: &TGame, text: &str) -> anyhow::Result<Vec<PortableGame<TGame::Move>>>
+where
+    TGame: SgfGame + Clone,
+    TGame::Move: MoveParameters,
+{
+    let mut chars = text.chars().peekable();
+    let mut games = vec![];
+    while skip_whitespace(&mut chars).is_some() {
+        let game = parse_tree(&mut chars)
+            .and_then(|nodes| to_game(start, &nodes))
+            .with_context(|| format!("Invalid game {}", games.len() + 1))?;
+        games.push(game);
+    }
+    Ok(games)
+}
+
+// Properties of a node, each with its values
+type Node = Vec<(String, Vec<String>)>;
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) -> Option<char> {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    chars.peek().copied()
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> anyhow::Result<()> {
+    match skip_whitespace(chars) {
+        Some(c) if c == expected => {
+            chars.next();
+            Ok(())
+        }
+        Some(c) => anyhow::bail!("Expected {expected}, got {c}"),
+        None => anyhow::bail!("Expected {expected}, got the end"),
+    }
+}
+
+// The main line of the tree, its first variation being followed at every branch
+fn parse_tree(chars: &mut Peekable<Chars>) -> anyhow::Result<Vec<Node>> {
+    expect(chars, '(')?;
+    let mut nodes = vec![];
+    while skip_whitespace(chars) == Some(';') {
+        chars.next();
+        nodes.push(parse_node(chars)?);
+    }
+    let mut first = true;
+    while skip_whitespace(chars) == Some('(') {
+        let variation = parse_tree(chars)?;
+        if first {
+            nodes.extend(variation);
+            first = false;
+        }
+    }
+    expect(chars, ')')?;
+    Ok(nodes)
+}
+
+fn parse_node(chars: &mut Peekable<Chars>) -> anyhow::Result<Node> {
+    let mut properties = vec![];
+    while skip_whitespace(chars).is_some_and(|c| c.is_ascii_alphabetic()) {
+        // Lowercase letters of old files' identifiers are ignored
+        let key = std::iter::from_fn(|| chars.next_if(char::is_ascii_alphabetic))
+            .filter(char::is_ascii_uppercase)
+            .collect::<String>();
+        let mut values = vec![];
+        while skip_whitespace(chars) == Some('[') {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next().context("Unterminated value")? {
+                    ']' => break,
+                    '\\' => value.push(chars.next().context("Unterminated value")?),
+                    c => value.push(c),
+                }
+            }
+            values.push(value);
+        }
+        anyhow::ensure!(!values.is_empty(), "Property {key} without a value");
+        properties.push((key, values));
+    }
+    Ok(properties)
+}
+
+fn to_game<TGame>(start: &TGame, nodes: &[Node]) -> anyhow::Result<PortableGame<TGame::Move>>
+where
+    TGame: SgfGame + Clone,
+    TGame::Move: MoveParameters,
+{
+    let root = nodes.first().context("A game without nodes")?;
+    let property = |key: &str| {
+        root.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, values)| values[0].trim())
+    };
+    if let Some(game_type) = property("GM") {
+        anyhow::ensure!(
+            game_type == TGame::GAME_TYPE.to_string(),
+            "Expected game type {}, got {game_type}",
+            TGame::GAME_TYPE
+        );
+    }
+    let size = start.board_size();
+    if let Some(board) = property("SZ") {
+        anyhow::ensure!(
+            board == size.to_string(),
+            "Expected a board of size {size}, got {board}"
+        );
+    }
+    let result = property("RE").context("Missing the result")?;
+    let result = match result.chars().next() {
+        Some('B') => 1.0,
+        Some('W') => 0.0,
+        _ if ["0", "Draw", "Jigo"].contains(&result) => 0.5,
+        _ => anyhow::bail!("Unsupported result {result}"),
+    };
+
+    let mut state = start.clone();
+    let mut player = Perspective::Same;
+    let mut moves = vec![];
+    for node in nodes {
+        for (key, values) in node {
+            let expected = match player {
+                Perspective::Same => "B",
+                Perspective::Opponent => "W",
+            };
+            match key.as_str() {
+                "AB" | "AW" | "AE" => anyhow::bail!("Setup stones aren't supported"),
+                "B" | "W" => anyhow::ensure!(
+                    key == expected,
+                    "Move {} should be {expected}'s",
+                    moves.len() + 1
+                ),
+                _ => continue,
+            }
+            let r#move = state.point_move(parse_point(&values[0], size)?)?;
+            anyhow::ensure!(
+                state.get_state().get_moves().is_some() && state.is_legal(&r#move),
+                "Illegal move {}[{}]",
+                key,
+                values[0]
+            );
+            player = player.then(Perspective::after_move(&r#move));
+            state = state.make_move(&r#move);
+            moves.push(PortableMove {
+                r#move,
+                value: None,
+            });
+        }
+    }
+
+    let name = |key: &str, default: &str| property(key).unwrap_or(default).to_owned();
+    Ok(PortableGame {
+        first: name("PB", "Black"),
+        second: name("PW", "White"),
+        opening: None,
+        moves,
+        result,
+    })
+}
+
+// `tt` is a pass too on boards of at most 19 lines
+fn parse_point(text: &str, size: usize) -> anyhow::Result<Option<(usize, usize)>> {
+    if text.is_empty() || (size <= 19 && text == "tt") {
+        return Ok(None);
+    }
+    let coordinates = text
+        .chars()
+        .map(|c| match c {
+            'a'..='z' => Some(c as usize - 'a' as usize),
+            'A'..='Z' => Some(c as usize - 'A' as usize + 26),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    match coordinates.as_deref() {
+        Some(&[column, row]) if column < size && row < size => Ok(Some((row, column))),
+        _ => anyhow::bail!("Invalid point {text}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{PortableGame, PortableMove},
+        go::{GoConfig, GoMove, GoState},
+        tictactoe::{BoardState, TicTacToeMove},
+    };
+
+    use super::{read_sgf, write_sgf};
+
+    #[test]
+    fn gomoku_round_trip() {
+        let start = BoardState::new();
+        let moves = [
+            (7, 7),
+            (8, 8),
+            (7, 8),
+            (0, 18),
+            (7, 6),
+            (1, 18),
+            (7, 5),
+            (2, 18),
+            (7, 9),
+        ];
+        let game = PortableGame {
+            first: "gen03".to_owned(),
+            second: "Mc]Gee".to_owned(),
+            opening: None,
+            moves: moves
+                .iter()
+                .map(|&(row, column)| PortableMove {
+                    r#move: TicTacToeMove(row, column),
+                    value: None,
+                })
+                .collect(),
+            result: 1.0,
+        };
+        let text = write_sgf(&start, &game);
+        assert!(text.starts_with("(;FF[4]GM[4]SZ[19]PB[gen03]PW[Mc\\]Gee]RE[B+]\n;B[hh]\n;W[ii]\n"));
+        assert_eq!(read_sgf(&start, &text.repeat(2)).unwrap().len(), 2);
+        assert_eq!(read_sgf(&start, &text).unwrap(), [game]);
+
+        // Other properties and variations are skipped, lowercase identifiers are read
+        let other =
+            "(;GaMe[4]SZ[19]RE[W+R]C[A comment [sic\\]];B[hh]C[Center](;W[ii];B[aa])(;W[aa]))";
+        let read = read_sgf(&start, other).unwrap();
+        assert_eq!(read[0].result, 0.0);
+        assert_eq!(read[0].first, "Black");
+        assert_eq!(
+            read[0].moves.iter().map(|m| m.r#move).collect::<Vec<_>>(),
+            [
+                TicTacToeMove(7, 7),
+                TicTacToeMove(8, 8),
+                TicTacToeMove(0, 0)
+            ]
+        );
+
+        for invalid in [
+            "(;GM[1]SZ[19]RE[B+];B[hh])",
+            "(;GM[4]SZ[15]RE[B+];B[hh])",
+            "(;GM[4]SZ[19];B[hh])",
+            "(;GM[4]SZ[19]RE[B+];W[hh])",
+            "(;GM[4]SZ[19]RE[B+];B[hh];W[hh])",
+            "(;GM[4]SZ[19]RE[B+]AB[aa];B[hh])",
+            "(;GM[4]SZ[19]RE[B+];B[hh]",
+            "(;GM[4]SZ[19]RE[B+];B[])",
+        ] {
+            assert!(read_sgf(&start, invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn go_passes_and_komi() {
+        let start = GoState::new(GoConfig::default());
+        let game = PortableGame {
+            first: "Black".to_owned(),
+            second: "White".to_owned(),
+            opening: None,
+            moves: [GoMove::Place(40), GoMove::Pass, GoMove::Pass]
+                .into_iter()
+                .map(|r#move| PortableMove {
+                    r#move,
+                    value: None,
+                })
+                .collect(),
+            result: 1.0,
+        };
+        let text = write_sgf(&start, &game);
+        assert_eq!(
+            text,
+            "(;FF[4]GM[1]SZ[9]PB[Black]PW[White]RE[B+]KM[7.5]\n;B[ee]\n;W[]\n;B[]\n)\n"
+        );
+        assert_eq!(read_sgf(&start, &text).unwrap(), vec![game.clone()]);
+        let passes = "(;GM[1]SZ[9]RE[B+];B[ee];W[tt];B[tt])";
+        assert_eq!(read_sgf(&start, passes).unwrap(), [game]);
+    }
+}
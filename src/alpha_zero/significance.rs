@@ -0,0 +1,261 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use super::MatchResult;
+
+// Two-sided 95%
+const Z: f64 = 1.959963984540054;
+const CONFIDENCE: f64 = 0.95;
+
+// How far a score can be trusted, so that a 12-8 isn't taken for a stronger player. The
+// intervals treat the score as a proportion of wins, a draw being half of one.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Significance {
+    pub score: f64,
+    // 95% intervals of the score
+    pub wilson: (f64, f64),
+    pub clopper_pearson: (f64, f64),
+    // Probability that the player is the stronger one, from the decisive games
+    pub likelihood_of_superiority: f64,
+}
+
+impl Significance {
+    // Column headers of `to_cells`
+    pub const HEADERS: &'static str = "score | 95% wilson | 95% exact | LOS";
+
+    pub fn of(result: &MatchResult) -> Self {
+        let successes = result.wins as f64 + result.draws as f64 / 2.0;
+        let games = result.games() as f64;
+        Self {
+            score: result.score(),
+            wilson: wilson_interval(successes, games, Z),
+            clopper_pearson: clopper_pearson_interval(successes, games, CONFIDENCE),
+            likelihood_of_superiority: likelihood_of_superiority(result),
+        }
+    }
+
+    // Markdown cells without the outer pipes, under `HEADERS`
+    pub fn to_cells(&self) -> String {
+        let (lo, hi) = self.wilson;
+        let (exact_lo, exact_hi) = self.clopper_pearson;
+        format!(
+            "{:.3} | {lo:.3}-{hi:.3} | {exact_lo:.3}-{exact_hi:.3} | {:.0}%",
+            self.score,
+            100.0 * self.likelihood_of_superiority
+        )
+    }
+}
+
+// `0.550 (0.450-0.646, exact 0.444-0.652, LOS 84%)`
+impl fmt::Display for Significance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (lo, hi) = self.wilson;
+        let (exact_lo, exact_hi) = self.clopper_pearson;
+        write!(
+            f,
+            "{:.3} ({lo:.3}-{hi:.3}, exact {exact_lo:.3}-{exact_hi:.3}, LOS {:.0}%)",
+            self.score,
+            100.0 * self.likelihood_of_superiority
+        )
+    }
+}
+
+// Wilson score interval, `z` standard deviations wide. Unlike the normal approximation it
+// stays inside [0, 1] and doesn't collapse to a point after a clean sweep.
+pub fn wilson_interval(successes: f64, trials: f64, z: f64) -> (f64, f64) {
+    if trials == 0.0 {
+        return (0.0, 1.0);
+    }
+    let p = successes / trials;
+    let z2 = z * z;
+    let denominator = 1.0 + z2 / trials;
+    let center = (p + z2 / (2.0 * trials)) / denominator;
+    let margin = z * (p * (1.0 - p) / trials + z2 / (4.0 * trials * trials)).sqrt() / denominator;
+    ((center - margin).max(0.0), (center + margin).min(1.0))
+}
+
+// Clopper-Pearson interval, from the beta distribution's quantiles. Conservative: it covers
+// the true proportion at least `confidence` of the time.
+pub fn clopper_pearson_interval(successes: f64, trials: f64, confidence: f64) -> (f64, f64) {
+    let alpha = 1.0 - confidence;
+    let failures = trials - successes;
+    let lo = match successes > 0.0 {
+        true => beta_quantile(alpha / 2.0, successes, failures + 1.0),
+        false => 0.0,
+    };
+    let hi = match failures > 0.0 {
+        true => beta_quantile(1.0 - alpha / 2.0, successes + 1.0, failures),
+        false => 1.0,
+    };
+    (lo, hi)
+}
+
+// Probability that the first player is stronger, with draws telling nothing either way
+pub fn likelihood_of_superiority(result: &MatchResult) -> f64 {
+    let decisive = (result.wins + result.losses) as f64;
+    if decisive == 0.0 {
+        return 0.5;
+    }
+    let x = (result.wins as f64 - result.losses as f64) / decisive.sqrt();
+    0.5 * erfc(-x / std::f64::consts::SQRT_2)
+}
+
+// Complementary error function, with a relative error below 1.2e-7 (Numerical Recipes)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let polynomial = [
+        -1.26551223,
+        1.00002368,
+        0.37409196,
+        0.09678418,
+        -0.18628806,
+        0.27886807,
+        -1.13520398,
+        1.48851587,
+        -0.82215223,
+        0.17087277,
+    ]
+    .iter()
+    .rev()
+    .fold(0.0, |sum, coefficient| coefficient + t * sum);
+    let value = t * (-z * z + polynomial).exp();
+    match x >= 0.0 {
+        true => value,
+        false => 2.0 - value,
+    }
+}
+
+// Lanczos approximation of ln Γ(x) for x > 0
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .fold(1.000000000190015, |sum, (i, c)| {
+            sum + c / (x + 1.0 + i as f64)
+        });
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+// Continued fraction of the incomplete beta function, by the modified Lentz method
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let (qab, qap, qam) = (a + b, a + 1.0, a - 1.0);
+    let mut c = 1.0;
+    let mut d = 1.0 - qab * x / qap;
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..300 {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+        let mut step = |numerator: f64| {
+            d = 1.0 + numerator * d;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = 1.0 + numerator / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            c * d
+        };
+        h *= step(m * (b - m) * x / ((qam + m2) * (a + m2)));
+        let delta = step(-(a + m) * (qab + m) * x / ((a + m2) * (qap + m2)));
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    h
+}
+
+// Regularized incomplete beta function I_x(a, b), the beta distribution's CDF
+fn beta_cdf(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // The continued fraction converges quickly on this side of the mean
+    match x < (a + 1.0) / (a + b + 2.0) {
+        true => front * beta_continued_fraction(x, a, b) / a,
+        false => 1.0 - front * beta_continued_fraction(1.0 - x, b, a) / b,
+    }
+}
+
+// By bisection, the CDF being increasing
+fn beta_quantile(p: f64, a: f64, b: f64) -> f64 {
+    let (mut lo, mut hi) = (0.0, 1.0);
+    for _ in 0..60 {
+        let mid = (lo + hi) / 2.0;
+        match beta_cdf(mid, a, b) < p {
+            true => lo = mid,
+            false => hi = mid,
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alpha_zero::MatchResult;
+
+    use super::{clopper_pearson_interval, wilson_interval, Significance, Z};
+
+    fn assert_close((lo, hi): (f64, f64), (expected_lo, expected_hi): (f64, f64)) {
+        assert!((lo - expected_lo).abs() < 1e-4, "{lo} isn't {expected_lo}");
+        assert!((hi - expected_hi).abs() < 1e-4, "{hi} isn't {expected_hi}");
+    }
+
+    #[test]
+    fn intervals() {
+        assert_close(wilson_interval(8.0, 10.0, Z), (0.4902, 0.9433));
+        assert_close(wilson_interval(0.0, 10.0, Z), (0.0, 0.2775));
+        assert_close(clopper_pearson_interval(8.0, 10.0, 0.95), (0.4439, 0.9748));
+        assert_close(clopper_pearson_interval(5.0, 10.0, 0.95), (0.1871, 0.8129));
+        assert_close(clopper_pearson_interval(0.0, 10.0, 0.95), (0.0, 0.3085));
+        assert_eq!(wilson_interval(0.0, 0.0, Z), (0.0, 1.0));
+        assert_eq!(clopper_pearson_interval(0.0, 0.0, 0.95), (0.0, 1.0));
+    }
+
+    #[test]
+    fn likelihood_of_superiority() {
+        let result = |wins, draws, losses| MatchResult {
+            wins,
+            draws,
+            losses,
+        };
+        let los = |r| Significance::of(&r).likelihood_of_superiority;
+        assert_eq!(los(result(0, 10, 0)), 0.5);
+        assert!((los(result(10, 5, 0)) - 0.99922).abs() < 1e-5);
+        assert!((los(result(30, 0, 20)) - 0.92135).abs() < 1e-5);
+        assert!((los(result(20, 0, 30)) - 0.07865).abs() < 1e-5);
+
+        // A 12-8 is far from settled
+        let significance = Significance::of(&result(12, 0, 8));
+        assert!(significance.wilson.0 < 0.5);
+        assert!(significance.clopper_pearson.0 < significance.wilson.0);
+        assert_eq!(
+            significance.to_string(),
+            "0.600 (0.387-0.781, exact 0.361-0.809, LOS 81%)"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use serde::Serialize;
+
+use super::MatchResult;
+
+// Sequential probability ratio test of "the first player is `elo0` stronger" against
+// "`elo1` stronger", wrongly accepting the latter with probability `alpha` and wrongly
+// rejecting it with probability `beta`
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct SprtConfig {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for SprtConfig {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            // About the 0.55 score the gating asks for
+            elo1: 35.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SprtDecision {
+    // The first player is `elo1` stronger
+    Accept,
+    // The first player is at most `elo0` stronger
+    Reject,
+    Continue,
+}
+
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl SprtConfig {
+    // Log-likelihood ratio of the two hypotheses, approximating the distribution of the score
+    // by a normal one. Half a win and half a loss are added, so that the first few games
+    // don't have a zero variance.
+    pub fn llr(&self, result: &MatchResult) -> f64 {
+        let wins = result.wins as f64 + 0.5;
+        let draws = result.draws as f64;
+        let losses = result.losses as f64 + 0.5;
+        let games = wins + draws + losses;
+        let score = (wins + draws / 2.0) / games;
+        let variance =
+            (wins * (1.0 - score).powi(2) + draws * (0.5 - score).powi(2) + losses * score.powi(2))
+                / games;
+
+        let (s0, s1) = (expected_score(self.elo0), expected_score(self.elo1));
+        games * (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * variance)
+    }
+
+    // The test stops once the ratio leaves these
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    pub fn decide(&self, result: &MatchResult) -> SprtDecision {
+        let llr = self.llr(result);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtDecision::Accept
+        } else if llr <= lower {
+            SprtDecision::Reject
+        } else {
+            SprtDecision::Continue
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alpha_zero::MatchResult;
+
+    use super::{SprtConfig, SprtDecision};
+
+    #[test]
+    fn stops_once_the_results_are_clear() {
+        let sprt = SprtConfig::default();
+        let result = |wins, draws, losses| MatchResult {
+            wins,
+            draws,
+            losses,
+        };
+
+        assert_eq!(sprt.decide(&result(1, 0, 0)), SprtDecision::Continue);
+        assert_eq!(sprt.decide(&result(6, 2, 4)), SprtDecision::Continue);
+        assert_eq!(sprt.decide(&result(40, 10, 10)), SprtDecision::Accept);
+        assert_eq!(sprt.decide(&result(10, 10, 30)), SprtDecision::Reject);
+        // An even match is closer to `elo0`
+        assert!(sprt.llr(&result(50, 0, 50)) < 0.0);
+    }
+}
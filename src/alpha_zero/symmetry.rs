@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use tch::Tensor;
+
+type TensorTransform = Arc<dyn Fn(&Tensor) -> Tensor + Send + Sync>;
+
+// A symmetry of the game: transforms NN inputs and policies consistently.
+// `inverse_policy` maps a policy predicted for the transformed state back to the original one.
+#[derive(Clone)]
+pub struct SymmetryTransform {
+    state: TensorTransform,
+    policy: TensorTransform,
+    inverse_policy: TensorTransform,
+}
+
+impl SymmetryTransform {
+    pub fn new(
+        state: impl Fn(&Tensor) -> Tensor + Send + Sync + 'static,
+        policy: impl Fn(&Tensor) -> Tensor + Send + Sync + 'static,
+        inverse_policy: impl Fn(&Tensor) -> Tensor + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            state: Arc::new(state),
+            policy: Arc::new(policy),
+            inverse_policy: Arc::new(inverse_policy),
+        }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(Tensor::copy, Tensor::copy, Tensor::copy)
+    }
+
+    // Optional reflection followed by `rotations` counter-clockwise quarter turns.
+    // Operates on the last two dimensions, so works for both single and batched tensors.
+    pub fn dihedral(reflect: bool, rotations: i64) -> Self {
+        let forward = move |inp: &Tensor| -> Tensor {
+            if reflect { inp.flip([-2]) } else { inp.copy() }.rot90(rotations, [-2, -1])
+        };
+        let inverse = move |inp: &Tensor| -> Tensor {
+            let inp = inp.rot90((4 - rotations) % 4, [-2, -1]);
+            if reflect {
+                inp.flip([-2])
+            } else {
+                inp
+            }
+        };
+        Self::new(forward, forward, inverse)
+    }
+
+    // All 8 symmetries of a square board, identity first
+    pub fn dihedral_group() -> Vec<Self> {
+        [false, true]
+            .into_iter()
+            .flat_map(|reflect| (0..4).map(move |rots| Self::dihedral(reflect, rots)))
+            .collect()
+    }
+
+    pub fn transform_state(&self, state: &Tensor) -> Tensor {
+        (self.state)(state)
+    }
+
+    pub fn transform_policy(&self, policy: &Tensor) -> Tensor {
+        (self.policy)(policy)
+    }
+
+    pub fn inverse_policy(&self, policy: &Tensor) -> Tensor {
+        (self.inverse_policy)(policy)
+    }
+}
@@ -0,0 +1,133 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use anyhow::Context;
+use serde::Serialize;
+
+// How long a player may think. Written as `<seconds>` per move or `<seconds>+<seconds>` for a
+// clock with an increment.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum TimeControl {
+    PerMove(Duration),
+    // Fischer clock: `base` for the game, `increment` added after every move
+    Fischer { base: Duration, increment: Duration },
+}
+
+// Moves a clock is expected to last for, to spread the remaining time over
+const MOVES_TO_GO: u32 = 30;
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeControl::PerMove(time) => write!(f, "{}", time.as_secs_f64()),
+            TimeControl::Fischer { base, increment } => {
+                write!(f, "{}+{}", base.as_secs_f64(), increment.as_secs_f64())
+            }
+        }
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let seconds = |value: &str| -> anyhow::Result<Duration> {
+            let seconds = value
+                .parse::<f64>()
+                .with_context(|| format!("Invalid seconds {value} of time control {s}"))?;
+            Duration::try_from_secs_f64(seconds)
+                .with_context(|| format!("Invalid seconds {value} of time control {s}"))
+        };
+        let control = match s.split_once('+') {
+            None => TimeControl::PerMove(seconds(s)?),
+            Some((base, increment)) => TimeControl::Fischer {
+                base: seconds(base)?,
+                increment: seconds(increment)?,
+            },
+        };
+        anyhow::ensure!(
+            control.clock().budget() > Duration::ZERO,
+            "Time control {s} leaves no time to think"
+        );
+        Ok(control)
+    }
+}
+
+impl TimeControl {
+    // A fresh clock for a game
+    pub fn clock(self) -> Clock {
+        let remaining = match self {
+            TimeControl::PerMove(_) => Duration::ZERO,
+            TimeControl::Fischer { base, .. } => base,
+        };
+        Clock {
+            control: self,
+            remaining,
+        }
+    }
+}
+
+// A player's time left in a game. Searches stop when their budget runs out, so a clock never
+// runs out by more than a simulation and nobody loses on time.
+#[derive(Clone, Copy, Debug)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: Duration,
+}
+
+impl Clock {
+    // Time to spend on the next move
+    pub fn budget(&self) -> Duration {
+        match self.control {
+            TimeControl::PerMove(time) => time,
+            TimeControl::Fischer { increment, .. } => self.remaining / MOVES_TO_GO + increment,
+        }
+    }
+
+    // Charges a move that took `elapsed`
+    pub fn spend(&mut self, elapsed: Duration) {
+        if let TimeControl::Fischer { increment, .. } = self.control {
+            self.remaining = (self.remaining + increment).saturating_sub(elapsed);
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.remaining
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::TimeControl;
+
+    #[test]
+    fn clocks() {
+        let control = "60+0.5".parse::<TimeControl>().unwrap();
+        assert_eq!(
+            control,
+            TimeControl::Fischer {
+                base: Duration::from_secs(60),
+                increment: Duration::from_millis(500)
+            }
+        );
+        assert_eq!(control.to_string(), "60+0.5");
+        let mut clock = control.clock();
+        assert_eq!(clock.budget(), Duration::from_millis(2500));
+        clock.spend(Duration::from_millis(10_500));
+        assert_eq!(clock.remaining(), Duration::from_secs(50));
+        clock.spend(Duration::from_secs(100));
+        assert_eq!(clock.remaining(), Duration::ZERO);
+        // Only the increment is left
+        assert_eq!(clock.budget(), Duration::from_millis(500));
+
+        let mut clock = "0.1".parse::<TimeControl>().unwrap().clock();
+        clock.spend(Duration::from_secs(1));
+        assert_eq!(clock.budget(), Duration::from_millis(100));
+
+        assert!("0".parse::<TimeControl>().is_err());
+        assert!("0+0".parse::<TimeControl>().is_err());
+        assert!("-1".parse::<TimeControl>().is_err());
+        assert!("fast".parse::<TimeControl>().is_err());
+    }
+}
@@ -1,5 +1,7 @@
 use std::time::{Duration, Instant};
 
+use tracing::warn;
+
 pub struct Timer {
     start: Instant,
 }
@@ -15,14 +17,12 @@ impl Timer {
         Instant::now() - self.start
     }
 
-    pub fn print_if_greater(&self, threshold: Duration, msg: &str) {
+    // Of a step that took at least `threshold`
+    pub fn warn_if_greater(&self, threshold: Duration, step: &str) {
         let passed = self.passed();
         if passed < threshold {
             return;
         }
-        println!(
-            "{}",
-            msg.to_string().replace("{t}", &format!("{:?}", passed))
-        );
+        warn!(?passed, "{step} was slow");
     }
 }
@@ -0,0 +1,604 @@
+use std::{
+    marker::PhantomData,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Context;
+use serde::Serialize;
+use tch::{nn, Device, Kind, Tensor};
+use tracing::{info, instrument, warn};
+
+use crate::metrics::MetricsSink;
+
+use super::{
+    alpha_zero_loss, auxiliary_loss, config_hash, Adam, AlphaZeroAdapter, AlphaZeroNet, AmpConfig,
+    CheckpointManager, CheckpointMetadata, DataLoader, DataLoaderConfig, EarlyStopping,
+    EarlyStoppingConfig, Game, GatingConfig, GradScaler, L2Norm, LossConfig, LrSchedule, Plateau,
+    ReplayBuffer, SampleWeighting, Seed, SelfPlaySample, StopMetric,
+};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TrainConfig {
+    pub lr_schedule: LrSchedule,
+    pub batch_size: usize,
+    // Passes over the augmented positions added by the latest generation
+    pub epochs_per_generation: usize,
+    // Replaces the passes with a number of steps, for quick runs
+    pub steps_per_generation: Option<usize>,
+    pub loss: LossConfig,
+    // Renormalizes the predicted policies over the legal moves before the loss, instead of
+    // training the net to put no probability on illegal ones
+    pub mask_illegal_moves: bool,
+    // Coefficient of the squared L2 norm of the weights added to the loss, see the `L2Norm`
+    // impl of `VarStore`
+    pub weight_decay: f64,
+    // Gradients are rescaled so that their global norm is at most this
+    pub grad_clip_norm: Option<f64>,
+    // Decay of the exponential moving average of the weights updated after every step,
+    // `None` disables it
+    pub ema_decay: Option<f64>,
+    // Mixed-precision training, only on CUDA devices. Falls back to fp32 elsewhere.
+    pub amp: Option<AmpConfig>,
+    // Prioritized replay exponents, see `ReplayBuffer::sample_prioritized`
+    pub priority_alpha: f32,
+    pub priority_beta: f32,
+    // Down-weights old and drawn positions
+    pub sample_weighting: SampleWeighting,
+    pub prefetch: usize,
+    // Share of the self-play games held out of the replay buffer to measure overfitting on
+    pub validation_fraction: f64,
+    // Root of every random choice of the run, random if `None`. Recorded in the checkpoints,
+    // so a run can be replayed by passing the seed it used.
+    pub seed: Option<Seed>,
+    pub checkpoint_dir: PathBuf,
+    // Older checkpoints are removed, except for the one with the best Elo
+    pub keep_checkpoints: usize,
+    // Training runs forever if `None`
+    pub early_stopping: Option<EarlyStoppingConfig>,
+    // Applied by the training loop before self-play picks up a new generation. Every
+    // generation is used if `None`.
+    pub gating: Option<GatingConfig>,
+    // Of the experiment the run belongs to, recorded in the checkpoints
+    pub experiment_hash: Option<u64>,
+}
+
+impl Default for TrainConfig {
+    fn default() -> Self {
+        Self {
+            lr_schedule: LrSchedule::constant(1e-4),
+            batch_size: 1024,
+            epochs_per_generation: 1,
+            steps_per_generation: None,
+            loss: LossConfig::default(),
+            mask_illegal_moves: true,
+            weight_decay: 1e-4,
+            grad_clip_norm: Some(10.0),
+            ema_decay: None,
+            amp: None,
+            priority_alpha: 0.6,
+            priority_beta: 0.4,
+            sample_weighting: SampleWeighting::default(),
+            prefetch: 4,
+            validation_fraction: 0.0,
+            seed: None,
+            checkpoint_dir: PathBuf::from("checkpoints"),
+            keep_checkpoints: 5,
+            early_stopping: None,
+            gating: None,
+            experiment_hash: None,
+        }
+    }
+}
+
+// Losses are averaged over the steps taken in a generation
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TrainStats {
+    pub steps: usize,
+    pub positions: usize,
+    pub value_loss: f64,
+    pub policy_loss: f64,
+    // Of the predicted policies
+    pub entropy: f64,
+    // Of the predicted policies from the MCTS ones
+    pub policy_kl: f64,
+    // Of the auxiliary heads, averaged over the steps that had them. `None` if the net has
+    // no such head or the game no targets for it.
+    pub ownership_loss: Option<f64>,
+    pub soft_policy_loss: Option<f64>,
+    // Squared L2 norm of the weights after the last step
+    pub l2: f64,
+    pub lr: f64,
+    // Steps not taken because the loss wasn't finite
+    pub skipped_steps: usize,
+    // Mixed-precision steps not taken because the gradients overflowed
+    pub overflow_steps: usize,
+    // At the end of the generation, 1 without mixed precision
+    pub loss_scale: f64,
+    // On the held-out positions after the last step, `None` if there were none
+    pub validation: Option<ValidationStats>,
+}
+
+// Unlike the training losses, these are unweighted
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ValidationStats {
+    pub positions: usize,
+    pub value_loss: f64,
+    pub policy_loss: f64,
+    pub policy_kl: f64,
+}
+
+impl TrainStats {
+    pub fn report(&self, metrics: &mut impl MetricsSink, generation: usize) -> std::io::Result<()> {
+        for (tag, value) in [
+            ("train/value_loss", self.value_loss),
+            ("train/policy_loss", self.policy_loss),
+            ("train/entropy", self.entropy),
+            ("train/policy_kl", self.policy_kl),
+            ("train/l2", self.l2),
+            ("train/lr", self.lr),
+            ("train/steps", self.steps as f64),
+            ("train/positions", self.positions as f64),
+            ("train/skipped_steps", self.skipped_steps as f64),
+            ("train/overflow_steps", self.overflow_steps as f64),
+            ("train/loss_scale", self.loss_scale),
+        ] {
+            metrics.scalar(tag, generation, value)?;
+        }
+        for (tag, value) in [
+            ("train/ownership_loss", self.ownership_loss),
+            ("train/soft_policy_loss", self.soft_policy_loss),
+        ] {
+            if let Some(value) = value {
+                metrics.scalar(tag, generation, value)?;
+            }
+        }
+        if let Some(validation) = &self.validation {
+            for (tag, value) in [
+                ("validation/value_loss", validation.value_loss),
+                ("validation/policy_loss", validation.policy_loss),
+                ("validation/policy_kl", validation.policy_kl),
+                ("validation/positions", validation.positions as f64),
+            ] {
+                metrics.scalar(tag, generation, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+type TypeMarker<TNet, TAdapter, TGame> = PhantomData<fn() -> (TNet, TAdapter, TGame)>;
+
+// Owns the weights of the network and the optimizer. The network itself is built from
+// `root()` by the caller, since it also has to be moved into the self-play executor.
+// With EMA enabled, a second copy of the network is built from `ema_root()`, meant for
+// self-play and evaluation, while training continues on the raw weights.
+pub struct Trainer<TNet, TAdapter, TGame> {
+    vs: nn::VarStore,
+    ema_vs: Option<nn::VarStore>,
+    opt: Adam,
+    // Set when mixed precision is enabled and supported by the device
+    scaler: Option<GradScaler>,
+    config: TrainConfig,
+    checkpoints: CheckpointManager,
+    // Starts over when the run is resumed
+    early_stopping: Option<EarlyStopping>,
+    // Optimizer steps over the whole run, restored from the checkpoint
+    steps: usize,
+    seed: Seed,
+    _types: TypeMarker<TNet, TAdapter, TGame>,
+}
+
+impl<TNet, TAdapter, TGame> Trainer<TNet, TAdapter, TGame>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TNet: AlphaZeroNet + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + 'static,
+{
+    // Variables have to be created under `vs` before the first training step. Seeds tch,
+    // so networks built afterwards are initialized reproducibly.
+    pub fn new(vs: nn::VarStore, config: TrainConfig) -> anyhow::Result<Self> {
+        let seed = config.seed.unwrap_or_else(Seed::random);
+        seed.seed_tch();
+        let opt = Adam::new(config.lr_schedule.lr(0));
+        let ema_vs = config.ema_decay.map(|_| nn::VarStore::new(vs.device()));
+        let checkpoints = CheckpointManager::new(&config.checkpoint_dir, config.keep_checkpoints);
+        let scaler = match config.amp {
+            Some(amp) if vs.device().is_cuda() => Some(GradScaler::new(amp)),
+            Some(_) => {
+                warn!(
+                    "Mixed precision is not supported on {:?}, training in fp32",
+                    vs.device()
+                );
+                None
+            }
+            None => None,
+        };
+        let early_stopping = config.early_stopping.map(EarlyStopping::new);
+        Ok(Self {
+            vs,
+            ema_vs,
+            opt,
+            scaler,
+            config,
+            checkpoints,
+            early_stopping,
+            steps: 0,
+            seed,
+            _types: PhantomData,
+        })
+    }
+
+    pub fn root(&self) -> nn::Path<'_> {
+        self.vs.root()
+    }
+
+    // `None` unless `ema_decay` is set
+    pub fn ema_root(&self) -> Option<nn::Path<'_>> {
+        self.ema_vs.as_ref().map(nn::VarStore::root)
+    }
+
+    // Weights meant for self-play: the EMA ones if enabled, the raw ones otherwise
+    pub fn self_play_weights(&self) -> &nn::VarStore {
+        self.ema_vs.as_ref().unwrap_or(&self.vs)
+    }
+
+    // Continues with the seed of the checkpoint if the config doesn't set one
+    pub fn seed(&self) -> Seed {
+        self.seed
+    }
+
+    pub fn device(&self) -> Device {
+        self.vs.device()
+    }
+
+    pub fn config(&self) -> &TrainConfig {
+        &self.config
+    }
+
+    pub fn checkpoints(&self) -> &CheckpointManager {
+        &self.checkpoints
+    }
+
+    // Loads the latest checkpoint, if any. Returns the generation to continue from.
+    // The EMA network has to be built already, it starts from the raw weights if the
+    // checkpoint has no EMA ones.
+    pub fn restore(&mut self) -> anyhow::Result<usize> {
+        let Some(metadata) = self
+            .checkpoints
+            .resume(&mut self.vs, self.ema_vs.as_mut())
+            .context("Failed to restore the latest checkpoint")?
+        else {
+            return Ok(0);
+        };
+        info!(
+            generation = metadata.generation,
+            "Restoring from the checkpoint"
+        );
+        let optimizer = self.checkpoints.optimizer_file(metadata.generation);
+        match optimizer.exists() {
+            true => self.opt.load(&self.vs, &optimizer)?,
+            false => warn!("Checkpoint has no optimizer state, starting it over"),
+        }
+        if metadata.config_hash != config_hash(&self.config) {
+            warn!("Checkpoint was trained with a different config");
+        }
+        self.steps = metadata.steps;
+        if let (None, Some(seed)) = (self.config.seed, metadata.seed) {
+            self.seed = seed;
+            seed.seed_tch();
+        }
+        Ok(metadata.generation + 1)
+    }
+
+    pub fn save_checkpoint(&self, generation: usize) -> anyhow::Result<()> {
+        let metadata = CheckpointMetadata {
+            generation,
+            steps: self.steps,
+            lr: self.config.lr_schedule.lr(generation),
+            seed: Some(self.seed),
+            config_hash: config_hash(&self.config),
+            experiment_hash: self.config.experiment_hash,
+            elo: None,
+        };
+        self.checkpoints
+            .save(&metadata, &self.vs, self.ema_vs.as_ref(), Some(&self.opt))
+    }
+
+    // Feeds the metric of the generation to the stopping rule, called after its checkpoint
+    // was saved. Whenever the metric improves, the checkpoint is kept as the best one.
+    // Returns whether training should stop.
+    pub fn should_stop(&mut self, generation: usize, stats: &TrainStats) -> anyhow::Result<bool> {
+        let Some(stopping) = &mut self.early_stopping else {
+            return Ok(false);
+        };
+        let value = match (stopping.metric(), stats.validation) {
+            (StopMetric::Loss, Some(validation)) => {
+                Some(validation.value_loss + validation.policy_loss)
+            }
+            (StopMetric::Loss, None) if stats.steps > 0 => {
+                Some(stats.value_loss + stats.policy_loss)
+            }
+            (StopMetric::Loss, None) => None,
+            (StopMetric::Elo, _) => self
+                .checkpoints
+                .list()?
+                .into_iter()
+                .find(|metadata| metadata.generation == generation)
+                .and_then(|metadata| metadata.elo),
+        };
+        let Some(value) = value else {
+            return Ok(false);
+        };
+        match stopping.observe(generation, value) {
+            Plateau::Improved => {
+                self.checkpoints.keep_as_best(generation)?;
+                Ok(false)
+            }
+            Plateau::Stale => Ok(false),
+            Plateau::Stop => {
+                info!(
+                    "{:?} didn't improve since generation {}, stopping",
+                    stopping.metric(),
+                    stopping.best_generation().unwrap()
+                );
+                Ok(true)
+            }
+        }
+    }
+
+    fn update_ema(&self) {
+        let (Some(ema_vs), Some(decay)) = (&self.ema_vs, self.config.ema_decay) else {
+            return;
+        };
+        let raw = self.vs.variables();
+        tch::no_grad(|| {
+            for (name, mut ema) in ema_vs.variables() {
+                let raw = &raw[&name];
+                ema.copy_(&(&ema * decay + raw * (1.0 - decay)));
+            }
+        });
+    }
+
+    // Trains `net` on minibatches sampled from `buffer`, after `new_positions` were added.
+    // Afterwards, the losses are measured on the `validation` positions, which were kept
+    // out of the buffer.
+    #[instrument(skip_all, fields(generation))]
+    pub async fn train_generation(
+        &mut self,
+        net: &TNet,
+        buffer: &Arc<RwLock<ReplayBuffer<TGame>>>,
+        new_positions: usize,
+        validation: &[SelfPlaySample<TGame>],
+        generation: usize,
+    ) -> TrainStats {
+        let config = &self.config;
+        let lr = config.lr_schedule.lr(generation);
+        self.opt.set_lr(lr);
+        // Dropout and the like draw the same numbers whether or not the run was resumed
+        self.seed.derive("train").derive(generation).seed_tch();
+        let augmented = new_positions * TAdapter::symmetries().len();
+        let mut loader = DataLoader::spawn::<TGame, TNet, TAdapter>(
+            buffer.clone(),
+            DataLoaderConfig {
+                batch_size: config.batch_size,
+                batches: config.steps_per_generation.unwrap_or(
+                    (augmented * config.epochs_per_generation).div_ceil(config.batch_size),
+                ),
+                prefetch: config.prefetch,
+                alpha: config.priority_alpha,
+                beta: config.priority_beta,
+                augment: true,
+                options: (Kind::Float, self.vs.device()),
+                seed: Some(self.seed.derive("replay").derive(generation)),
+                weighting: config.sample_weighting,
+                generation,
+            },
+        );
+
+        let mut stats = TrainStats {
+            lr,
+            ..Default::default()
+        };
+        let amp = self.scaler.is_some();
+        let (mut ownership_steps, mut soft_policy_steps) = (0, 0);
+        while let Some(batch) = loader.next().await {
+            // The loss is computed in fp32 even if the forward pass isn't
+            let output =
+                tch::autocast(amp, || net.forward_t(&batch.states, true)).to_kind(Kind::Float);
+            let output = match config.mask_illegal_moves {
+                true => output.masked(&batch.legal),
+                false => output,
+            };
+            let loss = alpha_zero_loss(
+                &output.value,
+                &batch.values,
+                &output.policy,
+                &batch.policies,
+                Some(&batch.weights),
+                &config.loss,
+            );
+            let auxiliary = auxiliary_loss(
+                &output,
+                &batch.policies,
+                batch
+                    .ownership
+                    .as_ref()
+                    .map(|ownership| (ownership, &batch.has_ownership)),
+                Some(&batch.weights),
+                &config.loss,
+            );
+
+            let l2 = self.vs.l2();
+            let total = &loss.total + &auxiliary.total + &l2 * config.weight_decay;
+            let losses = Vec::<f32>::try_from(loss.per_sample.to(Device::Cpu)).unwrap();
+
+            // A single bad step would poison the weights and everything trained afterwards
+            let total_value = f64::try_from(&total).unwrap();
+            if !total_value.is_finite() {
+                let rows = losses
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, l)| !l.is_finite())
+                    .map(|(row, _)| (row, batch.ids[row]))
+                    .collect::<Vec<_>>();
+                warn!(
+                    "Skipping step {} with loss {total_value}, non-finite rows: {rows:?}",
+                    stats.steps + stats.skipped_steps
+                );
+                stats.skipped_steps += 1;
+                continue;
+            }
+
+            buffer.write().unwrap().update_priorities(
+                batch
+                    .ids
+                    .into_iter()
+                    .zip(losses)
+                    .map(|(id, loss)| (id, loss.max(0.0) + 1e-3)),
+            );
+
+            if let Some(scaler) = &mut self.scaler {
+                self.opt.zero_grad(&self.vs);
+                if !scaler.backward(&total, &self.vs.trainable_variables()) {
+                    stats.overflow_steps += 1;
+                    continue;
+                }
+                if let Some(max_norm) = config.grad_clip_norm {
+                    self.opt.clip_grad_norm(&self.vs, max_norm);
+                }
+                self.opt.step(&self.vs);
+            } else {
+                match config.grad_clip_norm {
+                    Some(max_norm) => self.opt.backward_step_clip_norm(&self.vs, &total, max_norm),
+                    None => self.opt.backward_step(&self.vs, &total),
+                }
+            }
+            self.update_ema();
+
+            self.steps += 1;
+            stats.steps += 1;
+            stats.positions += output.value.size()[0] as usize;
+            stats.value_loss += f64::try_from(loss.value).unwrap();
+            stats.policy_loss += f64::try_from(loss.policy).unwrap();
+            stats.entropy += f64::try_from(loss.entropy).unwrap();
+            stats.policy_kl += f64::try_from(loss.kl).unwrap();
+            stats.l2 = f64::try_from(l2).unwrap();
+            for (sum, steps, loss) in [
+                (
+                    &mut stats.ownership_loss,
+                    &mut ownership_steps,
+                    auxiliary.ownership,
+                ),
+                (
+                    &mut stats.soft_policy_loss,
+                    &mut soft_policy_steps,
+                    auxiliary.soft_policy,
+                ),
+            ] {
+                if let Some(loss) = loss {
+                    *sum.get_or_insert(0.0) += f64::try_from(loss).unwrap();
+                    *steps += 1;
+                }
+            }
+        }
+        stats.loss_scale = self.scaler.as_ref().map_or(1.0, GradScaler::scale);
+        if stats.steps > 0 {
+            let steps = stats.steps as f64;
+            stats.value_loss /= steps;
+            stats.policy_loss /= steps;
+            stats.entropy /= steps;
+            stats.policy_kl /= steps;
+        }
+        for (loss, steps) in [
+            (&mut stats.ownership_loss, ownership_steps),
+            (&mut stats.soft_policy_loss, soft_policy_steps),
+        ] {
+            if let Some(loss) = loss {
+                *loss /= steps as f64;
+            }
+        }
+        loader.join().await;
+        stats.validation = self.validate(net, validation);
+        stats
+    }
+
+    fn validate(&self, net: &TNet, samples: &[SelfPlaySample<TGame>]) -> Option<ValidationStats> {
+        if samples.is_empty() {
+            return None;
+        }
+        let options = (Kind::Float, self.vs.device());
+        let mut stats = ValidationStats {
+            positions: samples.len(),
+            ..Default::default()
+        };
+        tch::no_grad(|| {
+            for chunk in samples.chunks(self.config.batch_size) {
+                let states = chunk.iter().map(|s| s.state.clone()).collect::<Vec<_>>();
+                let moves = states
+                    .iter()
+                    .map(|state| state.get_state().get_moves().unwrap())
+                    .collect::<Vec<_>>();
+                let policies = chunk.iter().map(|s| s.policy.clone()).collect::<Vec<_>>();
+                let values = chunk.iter().map(|s| s.value).collect::<Vec<_>>();
+
+                let inputs = TAdapter::convert_games_to_nn_input(&states, options);
+                let output = match self.config.mask_illegal_moves {
+                    true => net.forward_masked_t(
+                        &inputs,
+                        &TAdapter::convert_moves_to_legal_mask(&moves, options.1),
+                        false,
+                    ),
+                    false => net.forward_t(&inputs, false),
+                };
+                let loss = alpha_zero_loss(
+                    &output.value,
+                    &Tensor::from_slice(&values).to_device(options.1),
+                    &output.policy,
+                    &TAdapter::convert_policies_to_nn(&policies, &moves, options),
+                    None,
+                    &self.config.loss,
+                );
+                let share = chunk.len() as f64 / samples.len() as f64;
+                stats.value_loss += share * f64::try_from(loss.value).unwrap();
+                stats.policy_loss += share * f64::try_from(loss.policy).unwrap();
+                stats.policy_kl += share * f64::try_from(loss.kl).unwrap();
+            }
+        });
+        Some(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tch::{nn, Device, Kind, Tensor};
+
+    use crate::tictactoe3::{TicTacToe3, TicTacToe3AlphaZeroAdapter, TicTacToe3Net};
+
+    use super::{TrainConfig, Trainer};
+
+    #[test]
+    fn ema_follows_raw_weights() {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let mut raw = vs.root().zeros("w", &[2]);
+        let mut trainer = Trainer::<TicTacToe3Net, TicTacToe3AlphaZeroAdapter, TicTacToe3>::new(
+            vs,
+            TrainConfig {
+                ema_decay: Some(0.75),
+                checkpoint_dir: PathBuf::from("nonexistent-checkpoints"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let ema = trainer.ema_root().unwrap().ones("w", &[2]);
+        assert_eq!(trainer.restore().unwrap(), 0);
+        assert_eq!(Vec::<f32>::try_from(&ema).unwrap(), [0.0, 0.0]);
+
+        tch::no_grad(|| raw.copy_(&Tensor::ones([2], (Kind::Float, Device::Cpu))));
+        trainer.update_ema();
+        trainer.update_ema();
+        assert_eq!(Vec::<f32>::try_from(&ema).unwrap(), [0.4375, 0.4375]);
+    }
+}
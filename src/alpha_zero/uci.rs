@@ -0,0 +1,476 @@
+use std::{
+    collections::HashSet,
+    io::{BufRead, Write},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use super::{argmax, Evaluator, Game, MonteCarloTree, MoveNotation, TerminationState, TimeControl};
+
+// Games engines speak UCI for, with moves in its notation
+pub trait UciGame: MoveNotation {
+    fn from_fen(fen: &str) -> anyhow::Result<Self>;
+
+    fn white_to_move(&self) -> bool;
+}
+
+// `UciGame` as plain functions, like `Notation`
+pub struct UciFormat<TGame: Game> {
+    pub from_fen: fn(&str) -> anyhow::Result<TGame>,
+    pub white_to_move: fn(&TGame) -> bool,
+    pub parse_move: fn(&TGame, &str) -> anyhow::Result<TGame::Move>,
+    pub format_move: fn(&TGame, &TGame::Move) -> String,
+}
+
+impl<TGame: Game> Clone for UciFormat<TGame> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<TGame: Game> Copy for UciFormat<TGame> {}
+
+impl<TGame: UciGame> UciFormat<TGame> {
+    pub fn of() -> Self {
+        Self {
+            from_fen: TGame::from_fen,
+            white_to_move: TGame::white_to_move,
+            parse_move: TGame::parse_move,
+            format_move: TGame::format_move,
+        }
+    }
+}
+
+// Kept back from the clock for the move to reach the GUI in time
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+// The parameters of `go`, which end a `searchmoves` list
+const GO_PARAMETERS: [&str; 12] = [
+    "searchmoves",
+    "ponder",
+    "infinite",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+];
+
+// Limits of a `go` command
+#[derive(Debug, Default, PartialEq)]
+struct SearchLimits {
+    move_time: Option<Duration>,
+    nodes: Option<usize>,
+    // Of the side to move, with its increment
+    clock: Option<(Duration, Duration)>,
+    moves_to_go: Option<u32>,
+    // Until `stop`
+    infinite: bool,
+}
+
+impl SearchLimits {
+    fn parse(args: &[&str], white_to_move: bool) -> anyhow::Result<Self> {
+        let mut limits = Self::default();
+        let (mut time, mut increment) = (None, Duration::ZERO);
+        let mut args = args.iter().peekable();
+        while let Some(&name) = args.next() {
+            match name {
+                "infinite" => {
+                    limits.infinite = true;
+                    continue;
+                }
+                // Pondering searches like any other `go`
+                "ponder" => continue,
+                // Every move stays a candidate
+                "searchmoves" => {
+                    while args.next_if(|arg| !GO_PARAMETERS.contains(arg)).is_some() {}
+                    continue;
+                }
+                _ => {}
+            }
+            let value = args
+                .next()
+                .with_context(|| format!("No value for {name}"))?;
+            let value = value
+                .parse::<u64>()
+                .with_context(|| format!("Invalid {name} {value}"))?;
+            let ms = Duration::from_millis(value);
+            match (name, white_to_move) {
+                ("movetime", _) => limits.move_time = Some(ms),
+                ("nodes", _) => limits.nodes = Some(value as usize),
+                ("movestogo", _) => limits.moves_to_go = Some(value as u32),
+                ("wtime", true) | ("btime", false) => time = Some(ms),
+                ("winc", true) | ("binc", false) => increment = ms,
+                // The opponent's clock, and depths the search has no use for
+                _ => {}
+            }
+        }
+        limits.clock = time.map(|time| (time, increment));
+        Ok(limits)
+    }
+
+    // Time to search for, `None` without a limit
+    fn budget(&self) -> Option<Duration> {
+        let from_clock = self.clock.map(|(time, increment)| {
+            let budget = match self.moves_to_go {
+                Some(moves) => time / moves.max(1) + increment,
+                None => TimeControl::Fischer {
+                    base: time,
+                    increment,
+                }
+                .clock()
+                .budget(),
+            };
+            budget.min(time.saturating_sub(MOVE_OVERHEAD))
+        });
+        match (self.move_time, from_clock) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+// A UCI engine searching with the evaluator, `simulations` per move unless `go` limits the
+// nodes or the time. Every `go` searches a fresh tree and a `stop` ends the search early.
+pub struct UciEngine<TGame: Game, TEval> {
+    start: TGame,
+    format: UciFormat<TGame>,
+    evaluator: Option<TEval>,
+    simulations: usize,
+    c_puct: f32,
+    state: TGame,
+}
+
+impl<TGame, TEval> UciEngine<TGame, TEval>
+where
+    TGame: Game + Clone,
+    TGame::Move: PartialEq,
+    TEval: Evaluator<TGame>,
+{
+    pub fn new(
+        start: TGame,
+        format: UciFormat<TGame>,
+        evaluator: TEval,
+        simulations: usize,
+        c_puct: f32,
+    ) -> Self {
+        Self {
+            state: start.clone(),
+            start,
+            format,
+            evaluator: Some(evaluator),
+            simulations,
+            c_puct,
+        }
+    }
+
+    // `startpos` or `fen <fen>`, optionally followed by `moves` and the moves from there
+    fn set_position(&mut self, args: &[&str]) -> anyhow::Result<()> {
+        let moves_at = args
+            .iter()
+            .position(|&arg| arg == "moves")
+            .unwrap_or(args.len());
+        let mut state = match &args[..moves_at] {
+            ["startpos"] => self.start.clone(),
+            ["fen", fen @ ..] => (self.format.from_fen)(&fen.join(" "))?,
+            _ => anyhow::bail!("Expected startpos or fen"),
+        };
+        for &text in args.iter().skip(moves_at + 1) {
+            let m = (self.format.parse_move)(&state, text)?;
+            state = state
+                .try_make_move(&m)
+                .with_context(|| format!("{text} isn't legal"))?;
+        }
+        self.state = state;
+        Ok(())
+    }
+
+    // The lines answering a `go`, searching until the limits or `stopped` say so
+    async fn go(&mut self, limits: &SearchLimits, stopped: impl Fn() -> bool) -> Vec<String> {
+        let TerminationState::Moves(moves) = self.state.get_state() else {
+            // UCI's null move
+            return vec!["bestmove 0000".to_owned()];
+        };
+        let started = Instant::now();
+        let budget = limits.budget();
+        let samples = match (limits.nodes, limits.infinite || budget.is_some()) {
+            (Some(nodes), _) => nodes,
+            (None, true) => usize::MAX,
+            (None, false) => self.simulations,
+        };
+        let mut tree = MonteCarloTree::new(self.state.clone(), self.evaluator.take().unwrap());
+        let mut nodes = 0;
+        // The first two simulations always run, so that the root has a move visited
+        tree.do_simulations_while(self.c_puct, |done| {
+            nodes = done;
+            let late = budget.is_some_and(|budget| started.elapsed() >= budget);
+            done < samples && (done < 2 || !(late || stopped()))
+        })
+        .await;
+        let best = &moves[argmax(&tree.get_policy())];
+        let value = tree.get_root_value().clamp(0.001, 0.999);
+        self.evaluator = Some(tree.into_evaluator());
+        let best = (self.format.format_move)(&self.state, best);
+        // Pawns as the logistic of the win probability, like conventional engines' scores
+        let centipawns = (400.0 * (value / (1.0 - value)).log10()).round();
+        vec![
+            format!(
+                "info depth 1 nodes {nodes} time {} score cp {centipawns} pv {best}",
+                started.elapsed().as_millis()
+            ),
+            format!("bestmove {best}"),
+        ]
+    }
+
+    // Answers the commands of `input` until it closes or says `quit`. Commands are read on a
+    // thread of their own, for a `stop` to reach a running search and for `isready` to be
+    // answered during one.
+    pub async fn serve(
+        &mut self,
+        input: impl BufRead + Send + 'static,
+        output: impl Write + Send + 'static,
+    ) -> anyhow::Result<()> {
+        let (sender, mut commands) = mpsc::unbounded_channel();
+        let output = Arc::new(Mutex::new(output));
+        // Numbers of the `go`s a `stop` or a `quit` came right after, counting from 1
+        let stopped = Arc::new(Mutex::new(HashSet::new()));
+        // How many `go`s have been answered
+        let answered = Arc::new(AtomicUsize::new(0));
+        let reader_stopped = stopped.clone();
+        let reader_answered = answered.clone();
+        let reader_output = output.clone();
+        std::thread::spawn(move || {
+            let mut gos = 0;
+            for line in input.lines() {
+                let Ok(line) = line else { break };
+                match line.split_whitespace().next() {
+                    Some("go") => gos += 1,
+                    Some("stop" | "quit") => {
+                        reader_stopped.lock().unwrap().insert(gos);
+                    }
+                    // Behind a search the command would wait for its end
+                    Some("isready") if reader_answered.load(Ordering::SeqCst) < gos => {
+                        let mut output = reader_output.lock().unwrap();
+                        if writeln!(output, "readyok")
+                            .and_then(|()| output.flush())
+                            .is_err()
+                        {
+                            break;
+                        }
+                        continue;
+                    }
+                    _ => {}
+                }
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut gos = 0;
+        while let Some(line) = commands.recv().await {
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let Some((&command, args)) = words.split_first() else {
+                continue;
+            };
+            let lines = match command {
+                "uci" => vec![
+                    format!("id name alpha-zero {}", env!("CARGO_PKG_VERSION")),
+                    "id author alpha-zero contributors".to_owned(),
+                    "uciok".to_owned(),
+                ],
+                "isready" => vec!["readyok".to_owned()],
+                "position" => {
+                    if let Err(e) = self.set_position(args) {
+                        warn!("Ignoring position {}: {e:#}", args.join(" "));
+                    }
+                    vec![]
+                }
+                "go" => {
+                    gos += 1;
+                    let go = gos;
+                    // The GUI waits for a `bestmove` whatever it sent
+                    let limits =
+                        SearchLimits::parse(args, (self.format.white_to_move)(&self.state))
+                            .unwrap_or_else(|e| {
+                                warn!(
+                                    "Searching with default limits for go {}: {e:#}",
+                                    args.join(" ")
+                                );
+                                SearchLimits::default()
+                            });
+                    self.go(&limits, || stopped.lock().unwrap().contains(&go))
+                        .await
+                }
+                "quit" => return Ok(()),
+                // Without options or state between games, `stop` only ending searches
+                "stop" | "ucinewgame" | "setoption" | "debug" | "ponderhit" => vec![],
+                _ => {
+                    warn!("Unknown command {command}");
+                    vec![]
+                }
+            };
+            let mut output = output.lock().unwrap();
+            for line in lines {
+                writeln!(output, "{line}")?;
+            }
+            output.flush()?;
+            if command == "go" {
+                answered.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Cursor, Write},
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use crate::{alpha_zero::UniformEvaluator, chess::ChessGame};
+
+    use super::{SearchLimits, UciEngine, UciFormat};
+
+    // The engine's output, shared with its reader thread and read back by the test
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn limits_of_go() {
+        let limits =
+            SearchLimits::parse(&["wtime", "60000", "btime", "1000", "winc", "500"], true).unwrap();
+        assert_eq!(
+            limits.clock,
+            Some((Duration::from_secs(60), Duration::from_millis(500)))
+        );
+        assert_eq!(limits.budget(), Some(Duration::from_millis(2500)));
+        // Never more than the clock has left
+        let limits = SearchLimits::parse(&["btime", "100", "binc", "1000"], false).unwrap();
+        assert_eq!(limits.budget(), Some(Duration::from_millis(50)));
+        let limits = SearchLimits::parse(&["movetime", "300", "nodes", "64"], true).unwrap();
+        assert_eq!(limits.budget(), Some(Duration::from_millis(300)));
+        assert_eq!(limits.nodes, Some(64));
+        assert!(SearchLimits::parse(&["movetime"], true).is_err());
+        // Flags without values and the moves to search among
+        let limits = SearchLimits::parse(
+            &[
+                "searchmoves",
+                "e2e4",
+                "d2d4",
+                "ponder",
+                "infinite",
+                "nodes",
+                "8",
+            ],
+            true,
+        )
+        .unwrap();
+        assert!(limits.infinite);
+        assert_eq!(limits.nodes, Some(8));
+    }
+
+    #[tokio::test]
+    async fn finds_mate_and_stops() {
+        let mut engine = UciEngine::new(
+            ChessGame::default(),
+            UciFormat::of(),
+            UniformEvaluator,
+            16,
+            1.5,
+        );
+        // Back rank mate, then an infinite search ended by a stop
+        let input = "uci\n\
+                     isready\n\
+                     position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\n\
+                     go nodes 800\n\
+                     position startpos moves e2e4 e7e5\n\
+                     go infinite\n\
+                     stop\n\
+                     quit\n";
+        let output = Output::default();
+        engine
+            .serve(Cursor::new(input), output.clone())
+            .await
+            .unwrap();
+        let output = output.text();
+        let bestmoves = output
+            .lines()
+            .filter_map(|line| line.strip_prefix("bestmove "))
+            .collect::<Vec<_>>();
+        assert!(output.contains("uciok\nreadyok\n"), "{output}");
+        assert_eq!(bestmoves.len(), 2, "{output}");
+        assert_eq!(bestmoves[0], "a1a8", "{output}");
+
+        // The pawn has moved on already
+        let error = engine
+            .set_position(&["startpos", "moves", "e2e4", "e7e5", "e2e4"])
+            .unwrap_err();
+        assert!(
+            format!("{error:#}").contains("e2e4 isn't legal"),
+            "{error:#}"
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_during_a_search() {
+        let mut engine = UciEngine::new(
+            ChessGame::default(),
+            UciFormat::of(),
+            UniformEvaluator,
+            16,
+            1.5,
+        );
+        // Ready while searching, and a move for a `go` it can't read
+        let input = "go infinite\n\
+                     isready\n\
+                     stop\n\
+                     go movetime soon\n\
+                     quit\n";
+        let output = Output::default();
+        engine
+            .serve(Cursor::new(input), output.clone())
+            .await
+            .unwrap();
+        let output = output.text();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "readyok", "{output}");
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.starts_with("bestmove "))
+                .count(),
+            2,
+            "{output}"
+        );
+    }
+}
@@ -17,3 +17,12 @@ pub fn sample_policy<R: Rng>(policy: &[f32], temp: f32, rng: &mut R) -> usize {
 
     WeightedIndex::new(policy).unwrap().sample(rng)
 }
+
+pub fn argmax(values: &[f32]) -> usize {
+    values
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .unwrap()
+        .0
+}
@@ -0,0 +1,170 @@
+use image::{Rgb, RgbImage};
+
+use super::SelfPlaySample;
+
+// Of every game's chart, in pixels
+const PANEL_WIDTH: u32 = 240;
+const PANEL_HEIGHT: u32 = 100;
+const PANEL_GAP: u32 = 8;
+const PANELS_PER_ROW: u32 = 4;
+
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+const FRAME: Rgb<u8> = Rgb([160, 160, 160]);
+const VALUE: Rgb<u8> = Rgb([30, 90, 200]);
+const WON: Rgb<u8> = Rgb([0, 160, 0]);
+const LOST: Rgb<u8> = Rgb([210, 0, 0]);
+const DRAWN: Rgb<u8> = Rgb([120, 120, 120]);
+
+// The search's root value through each game, in blue, against the game's outcome, green for
+// a win, red for a loss and gray for a draw, both for the first player. A chart per game, from
+// 1 at the top to 0 at the bottom with a dotted line at 0.5, four to a row. Shows whether the
+// value tracks the outcome or flips around.
+pub fn plot_value_trajectories<TGame>(games: &[impl AsRef<[SelfPlaySample<TGame>]>]) -> RgbImage {
+    let count = games.len() as u32;
+    let (columns, rows) = (
+        count.clamp(1, PANELS_PER_ROW),
+        count.div_ceil(PANELS_PER_ROW),
+    );
+    let mut image = RgbImage::from_pixel(
+        columns * (PANEL_WIDTH + PANEL_GAP) - PANEL_GAP,
+        (rows * (PANEL_HEIGHT + PANEL_GAP)).max(PANEL_GAP + 1) - PANEL_GAP,
+        BACKGROUND,
+    );
+    for (i, game) in games.iter().enumerate() {
+        let (left, top) = (
+            i as u32 % PANELS_PER_ROW * (PANEL_WIDTH + PANEL_GAP),
+            i as u32 / PANELS_PER_ROW * (PANEL_HEIGHT + PANEL_GAP),
+        );
+        draw_panel(&mut image, left, top, game.as_ref());
+    }
+    image
+}
+
+fn draw_panel<TGame>(image: &mut RgbImage, left: u32, top: u32, game: &[SelfPlaySample<TGame>]) {
+    let (right, bottom) = (left + PANEL_WIDTH - 1, top + PANEL_HEIGHT - 1);
+    let y = |value: f32| {
+        let value = value.clamp(0.0, 1.0);
+        (top as f32 + (1.0 - value) * (PANEL_HEIGHT - 1) as f32).round() as u32
+    };
+    for x in left..=right {
+        image.put_pixel(x, top, FRAME);
+        image.put_pixel(x, bottom, FRAME);
+        if x % 4 == 0 {
+            image.put_pixel(x, y(0.5), FRAME);
+        }
+    }
+    for y in top..=bottom {
+        image.put_pixel(left, y, FRAME);
+        image.put_pixel(right, y, FRAME);
+    }
+    let Some(first) = game.first() else {
+        return;
+    };
+
+    let outcome = first.player.convert(first.value);
+    let color = match outcome.partial_cmp(&0.5).unwrap() {
+        std::cmp::Ordering::Greater => WON,
+        std::cmp::Ordering::Equal => DRAWN,
+        std::cmp::Ordering::Less => LOST,
+    };
+    for x in left + 1..right {
+        image.put_pixel(x, y(outcome), color);
+    }
+    let x = |i: usize| left + (i as u32 * (PANEL_WIDTH - 1)) / (game.len() as u32 - 1).max(1);
+    let points = game
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| (x(i), y(sample.player.convert(sample.root_q))))
+        .collect::<Vec<_>>();
+    for pair in points.windows(2) {
+        draw_line(image, pair[0], pair[1], VALUE);
+    }
+    if let [(x, y)] = points[..] {
+        image.put_pixel(x, y, VALUE);
+    }
+}
+
+// Bresenham's, both ends included
+fn draw_line(image: &mut RgbImage, from: (u32, u32), to: (u32, u32), color: Rgb<u8>) {
+    let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+    let (x1, y1) = (to.0 as i64, to.1 as i64);
+    let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+    let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+    let mut error = dx + dy;
+    loop {
+        image.put_pixel(x as u32, y as u32, color);
+        if (x, y) == (x1, y1) {
+            break;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += sx;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{Perspective, SelfPlaySample},
+        tictactoe3::TicTacToe3,
+    };
+
+    use super::{plot_value_trajectories, PANEL_GAP, PANEL_HEIGHT, PANEL_WIDTH, VALUE, WON};
+
+    // Won by the first player, whose value the search puts at `first_player`
+    fn game(first_player: &[f32]) -> Vec<SelfPlaySample<TicTacToe3>> {
+        let mut player = Perspective::Same;
+        first_player
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| {
+                let sample = SelfPlaySample {
+                    value: player.convert(1.0),
+                    move_number: i,
+                    player,
+                    root_q: player.convert(value),
+                    ..SelfPlaySample::uniform(TicTacToe3::new(), 0)
+                };
+                player = player.then(Perspective::Opponent);
+                sample
+            })
+            .collect()
+    }
+
+    #[test]
+    fn charts_of_games() {
+        let games = [
+            game(&[0.0, 0.0, 1.0]),
+            game(&[0.5]),
+            game(&[]),
+            game(&[]),
+            game(&[]),
+        ];
+        let image = plot_value_trajectories(&games);
+        assert_eq!(
+            image.dimensions(),
+            (
+                4 * PANEL_WIDTH + 3 * PANEL_GAP,
+                2 * PANEL_HEIGHT + PANEL_GAP
+            )
+        );
+        // The first game's value, flat at 0 for the first player and then rising to 1, where
+        // the win's line runs
+        assert_eq!(image.get_pixel(10, PANEL_HEIGHT - 1).0, VALUE.0);
+        assert_eq!(image.get_pixel(PANEL_WIDTH - 1, 0).0, VALUE.0);
+        assert_eq!(image.get_pixel(10, 0).0, WON.0);
+        // The second's single position
+        let second = PANEL_WIDTH + PANEL_GAP;
+        assert_eq!(image.get_pixel(second, PANEL_HEIGHT / 2).0, VALUE.0);
+        assert_eq!(
+            plot_value_trajectories::<TicTacToe3>(&[] as &[Vec<_>]).height(),
+            1
+        );
+    }
+}
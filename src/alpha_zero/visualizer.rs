@@ -0,0 +1,215 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use anyhow::Context;
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    imageops, Frame, Rgb, RgbImage,
+};
+
+use super::{Game, LineDiff, LinePosition, SearchHeatmaps, SelfPlaySample};
+
+// Between the positions of `GameVisualizer::render_history`'s default strip
+const STRIP_GAP: u32 = 5;
+
+// Pictures of a game's positions, for the images the training loop writes and for replays.
+// Only single positions are required, the other renderings defaulting to them or to nothing.
+pub trait GameVisualizer<TGame: Game> {
+    // With the search's policy over the position's moves, if there is one
+    fn render_position(&self, state: &TGame, policy: Option<&[f32]>) -> RgbImage;
+
+    // The positions before every move, side by side
+    fn render_history(&self, history: &[SelfPlaySample<TGame>]) -> RgbImage {
+        strip(
+            history
+                .iter()
+                .map(|sample| self.render_position(&sample.state, Some(&sample.policy))),
+        )
+    }
+
+    // The game move by move, see `save_animation`
+    fn animate(&self, _history: &[SelfPlaySample<TGame>]) -> Option<Vec<Frame>> {
+        None
+    }
+
+    // The position before move `position` as an SVG document, or after the last one for
+    // `history.len()`
+    fn render_svg(&self, _history: &[SelfPlaySample<TGame>], _position: usize) -> Option<String> {
+        None
+    }
+
+    // The position once per named map, each over the position's moves and in [0, 1], as an
+    // SVG document
+    fn render_maps_svg(&self, _state: &TGame, _maps: &[(&str, Vec<f32>)]) -> Option<String> {
+        None
+    }
+}
+
+// Side by side, top aligned
+fn strip(images: impl IntoIterator<Item = RgbImage>) -> RgbImage {
+    let images = images.into_iter().collect::<Vec<_>>();
+    let width = images
+        .iter()
+        .map(|image| image.width() + STRIP_GAP)
+        .sum::<u32>();
+    let height = images.iter().map(RgbImage::height).max().unwrap_or(0);
+    let mut strip = RgbImage::from_pixel(width.saturating_sub(STRIP_GAP), height, Rgb([255; 3]));
+    let mut x = 0;
+    for image in &images {
+        imageops::replace(&mut strip, image, x, 0);
+        x += image.width() as i64 + STRIP_GAP as i64;
+    }
+    strip
+}
+
+// One above the other, left aligned
+fn stack(images: impl IntoIterator<Item = RgbImage>) -> RgbImage {
+    let images = images.into_iter().collect::<Vec<_>>();
+    let width = images.iter().map(RgbImage::width).max().unwrap_or(0);
+    let height = images
+        .iter()
+        .map(|image| image.height() + STRIP_GAP)
+        .sum::<u32>();
+    let mut stack = RgbImage::from_pixel(width, height.saturating_sub(STRIP_GAP), Rgb([255; 3]));
+    let mut y = 0;
+    for image in &images {
+        imageops::replace(&mut stack, image, 0, y);
+        y += image.height() as i64 + STRIP_GAP as i64;
+    }
+    stack
+}
+
+// The engines' lines from where they diverged, the first's above the second's with every
+// position showing its engine's visits, and below them the diverging position with how much
+// the visits of every move changed between the engines, relative to the largest change. `None`
+// if the engines never diverged.
+pub fn render_line_diff<TGame: Game>(
+    visualizer: &(impl GameVisualizer<TGame> + ?Sized),
+    diff: &LineDiff<TGame>,
+) -> Option<RgbImage> {
+    let (first, second) = (diff.first.first()?, diff.second.first()?);
+    let changes = first
+        .visits
+        .iter()
+        .zip(&second.visits)
+        .map(|(a, b)| (b - a).abs())
+        .collect::<Vec<_>>();
+    let largest = changes.iter().copied().fold(f32::MIN_POSITIVE, f32::max);
+    let changes = changes.iter().map(|c| c / largest).collect::<Vec<_>>();
+    let line =
+        |line: &[LinePosition<TGame>]| {
+            strip(line.iter().map(|position| {
+                visualizer.render_position(&position.state, Some(&position.visits))
+            }))
+        };
+    Some(stack([
+        line(&diff.first),
+        line(&diff.second),
+        visualizer.render_position(&first.state, Some(&changes)),
+    ]))
+}
+
+// The position rendered with each of `SearchHeatmaps::maps` as its policy, side by side, and
+// the same as an SVG document if the visualizer draws those
+pub fn render_heatmaps<TGame: Game>(
+    visualizer: &(impl GameVisualizer<TGame> + ?Sized),
+    state: &TGame,
+    heatmaps: &SearchHeatmaps,
+) -> (RgbImage, Option<String>) {
+    let maps = heatmaps.maps();
+    let image = strip(
+        maps.iter()
+            .map(|(_, map)| visualizer.render_position(state, Some(map))),
+    );
+    (image, visualizer.render_maps_svg(state, &maps))
+}
+
+// As a GIF that loops forever. APNG isn't written by the `image` crate.
+pub fn save_animation(frames: Vec<Frame>, path: &Path) -> anyhow::Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut encoder = GifEncoder::new(BufWriter::new(file));
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.encode_frames(frames)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use crate::{
+        alpha_zero::{LineDiff, LinePosition, SearchHeatmaps, SelfPlaySample},
+        tictactoe3::{TicTacToe3, TicTacToe3Move},
+    };
+
+    use super::{render_heatmaps, render_line_diff, GameVisualizer};
+
+    // A pixel per cell, lit for the cells with a policy
+    struct Cells;
+
+    impl GameVisualizer<TicTacToe3> for Cells {
+        fn render_position(&self, _: &TicTacToe3, policy: Option<&[f32]>) -> RgbImage {
+            let lit = policy.map_or(0, |policy| policy.len() as u32);
+            RgbImage::from_fn(3, 3, |x, y| match y * 3 + x < lit {
+                true => Rgb([0, 255, 0]),
+                false => Rgb([0, 0, 0]),
+            })
+        }
+    }
+
+    #[test]
+    fn strip_of_positions() {
+        let sample = |policy: usize| SelfPlaySample::uniform(TicTacToe3::new(), policy);
+        let strip = Cells.render_history(&[sample(9), sample(1)]);
+        assert_eq!(strip.dimensions(), (3 + 5 + 3, 3));
+        assert_eq!(strip.get_pixel(2, 2).0, [0, 255, 0]);
+        assert_eq!(strip.get_pixel(3, 0).0, [255; 3]);
+        assert_eq!(strip.get_pixel(8, 0).0, [0, 255, 0]);
+        assert_eq!(strip.get_pixel(9, 0).0, [0, 0, 0]);
+        assert!(Cells.animate(&[]).is_none());
+    }
+
+    #[test]
+    fn heatmaps_side_by_side() {
+        let heatmaps = SearchHeatmaps {
+            value: 0.5,
+            priors: vec![0.5; 2],
+            visits: vec![1.0; 4],
+            q: vec![Some(0.5), None, Some(1.0)],
+        };
+        let (image, svg) = render_heatmaps(&Cells, &TicTacToe3::new(), &heatmaps);
+        assert_eq!(image.dimensions(), (3 * 3 + 2 * 5, 3));
+        // Lit by the lengths of the maps
+        assert_eq!(image.get_pixel(1, 0).0, [0, 255, 0]);
+        assert_eq!(image.get_pixel(2, 0).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(9, 1).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(16, 0).0, [0, 255, 0]);
+        assert!(svg.is_none());
+    }
+
+    #[test]
+    fn lines_above_the_change() {
+        let position = |visits: Vec<f32>, r#move: usize| LinePosition {
+            state: TicTacToe3::new(),
+            visits,
+            r#move: TicTacToe3Move(r#move),
+        };
+        let mut diff = LineDiff {
+            agreed: vec![],
+            first: vec![position(vec![1.0, 0.0], 0), position(vec![1.0; 5], 4)],
+            second: vec![position(vec![0.5, 0.5, 0.0], 1)],
+        };
+        let image = render_line_diff(&Cells, &diff).unwrap();
+        assert_eq!(image.dimensions(), (3 + 5 + 3, 3 * 3 + 2 * 5));
+        // The second line below the first, then the change over the moves of both
+        assert_eq!(image.get_pixel(4, 0).0, [255; 3]);
+        assert_eq!(image.get_pixel(2, 8).0, [0, 255, 0]);
+        assert_eq!(image.get_pixel(0, 9).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(1, 16).0, [0, 255, 0]);
+        assert_eq!(image.get_pixel(2, 16).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(4, 16).0, [255; 3]);
+
+        diff.first.clear();
+        assert!(render_line_diff(&Cells, &diff).is_none());
+    }
+}
@@ -0,0 +1,159 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Context;
+use tch::{nn, Tensor};
+
+// From the names of weights trained elsewhere, like a PyTorch `state_dict`, to the names of
+// the variables of the crate's nets. Read from a TOML table of `"theirs" = "ours"` entries,
+// where a trailing `*` on both sides renames a prefix and an empty name drops the weight,
+// like `"backbone.*" = "tower.*"` or `"bn.num_batches_tracked" = ""`. Names without a
+// matching entry are kept.
+#[derive(Debug, Default)]
+pub struct NameMapping {
+    exact: HashMap<String, String>,
+    // Longest first, for the most specific to win
+    prefixes: Vec<(String, String)>,
+}
+
+impl NameMapping {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let table: HashMap<String, String> = toml::from_str(text)?;
+        let mut mapping = Self::default();
+        for (theirs, ours) in table {
+            match (theirs.strip_suffix('*'), ours.strip_suffix('*')) {
+                (Some(theirs), Some(ours)) => mapping.prefixes.push((theirs.into(), ours.into())),
+                (None, _) if !ours.contains('*') => {
+                    mapping.exact.insert(theirs, ours);
+                }
+                _ => anyhow::bail!("{theirs} = {ours}: a prefix has to map to a prefix"),
+            }
+        }
+        mapping
+            .prefixes
+            .sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+        Ok(mapping)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid name mapping {}", path.display()))
+    }
+
+    // `None` for a dropped weight
+    pub fn rename(&self, name: &str) -> Option<String> {
+        let renamed = match self.exact.get(name) {
+            Some(ours) => ours.clone(),
+            None => self
+                .prefixes
+                .iter()
+                .find_map(|(theirs, ours)| Some(format!("{ours}{}", name.strip_prefix(theirs)?)))
+                .unwrap_or_else(|| name.to_owned()),
+        };
+        (!renamed.is_empty()).then_some(renamed)
+    }
+}
+
+// Of weights read from a file but not imported, by their names in it
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub dropped: Vec<String>,
+    // Named after no variable of the net
+    pub unused: Vec<String>,
+}
+
+// Tensors of a safetensors or npz file, by name
+fn read_tensors(path: &Path) -> anyhow::Result<Vec<(String, Tensor)>> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match extension {
+        "safetensors" => Tensor::read_safetensors(path),
+        "npz" => Tensor::read_npz(path),
+        _ => anyhow::bail!(
+            "Unknown format of {}, expected .safetensors or .npz. Write a state_dict with \
+             safetensors.torch.save_file(model.state_dict(), path)",
+            path.display()
+        ),
+    }
+    .with_context(|| format!("Failed to read {}", path.display()))
+}
+
+// Copies the weights of the file into the variables of `vs` they are mapped to, converting
+// their kind. Every variable has to get one of its shape, checked before any is copied.
+pub fn import_weights(
+    vs: &nn::VarStore,
+    path: &Path,
+    mapping: &NameMapping,
+) -> anyhow::Result<ImportReport> {
+    let mut variables = vs.variables();
+    let mut report = ImportReport::default();
+    let mut copies = vec![];
+    let mut errors = vec![];
+    for (name, tensor) in read_tensors(path)? {
+        let Some(ours) = mapping.rename(&name) else {
+            report.dropped.push(name);
+            continue;
+        };
+        match variables.remove(&ours) {
+            Some(variable) if variable.size() == tensor.size() => copies.push((variable, tensor)),
+            Some(variable) => errors.push(format!(
+                "{name} has shape {:?} but {ours} has {:?}",
+                tensor.size(),
+                variable.size()
+            )),
+            None => report.unused.push(name),
+        }
+    }
+    let mut missing = variables.into_keys().collect::<Vec<_>>();
+    missing.sort_unstable();
+    errors.extend(missing.iter().map(|name| format!("Nothing maps to {name}")));
+    anyhow::ensure!(
+        errors.is_empty(),
+        "{} doesn't fit the net:\n{}",
+        path.display(),
+        errors.join("\n")
+    );
+    report.imported = copies.len();
+    tch::no_grad(|| {
+        for (mut variable, tensor) in copies {
+            variable.copy_(&tensor);
+        }
+    });
+    report.dropped.sort_unstable();
+    report.unused.sort_unstable();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NameMapping;
+
+    #[test]
+    fn mapping_renames_prefixes_and_drops() {
+        let mapping = NameMapping::parse(
+            r#"
+            "backbone.*" = "tower.*"
+            "backbone.stem.*" = "stem.*"
+            "value_head.fc.weight" = "fc_value.weight"
+            "bn.num_batches_tracked" = ""
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            mapping.rename("backbone.0.conv.weight").as_deref(),
+            Some("tower.0.conv.weight")
+        );
+        assert_eq!(
+            mapping.rename("backbone.stem.bias").as_deref(),
+            Some("stem.bias")
+        );
+        assert_eq!(
+            mapping.rename("value_head.fc.weight").as_deref(),
+            Some("fc_value.weight")
+        );
+        assert_eq!(mapping.rename("fc1.bias").as_deref(), Some("fc1.bias"));
+        assert_eq!(mapping.rename("bn.num_batches_tracked"), None);
+
+        assert!(NameMapping::parse(r#""backbone.*" = "tower""#).is_err());
+    }
+}
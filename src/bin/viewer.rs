@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use pytorch::{
+    alpha_zero::{AlphaZeroAdapter, AlphaZeroNet, Game},
+    registry::{GameRegistry, GameSpec, GameVisitor},
+    selfplay::DataStore,
+    viewer::{portable_games, shard_games, Viewer, ViewerGame},
+};
+
+// The games of a file written by a match or an arena, or of an SGF collection
+struct LoadGames {
+    file: PathBuf,
+}
+
+impl GameVisitor for LoadGames {
+    type Output = anyhow::Result<Vec<ViewerGame>>;
+
+    fn visit<TGame, TNet, TAdapter>(self, spec: GameSpec<TGame, TNet, TAdapter>) -> Self::Output
+    where
+        TGame: Game + Clone + Send + Sync + 'static,
+        TGame::Move: Clone + PartialEq + Send + Sync,
+        TNet: AlphaZeroNet + Send + 'static,
+        TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+    {
+        let notation = spec
+            .notation
+            .context("The game has no notation to show moves in")?;
+        let games = spec.read_games(&self.file)?;
+        Ok(portable_games(&spec.start, notation, spec.text, &games))
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let registry = GameRegistry::with_builtin_games();
+    let mut args = std::env::args().skip(1).peekable();
+    // The game is only needed to read games files
+    let game = match args.peek() {
+        Some(arg) if registry.contains(arg) => args.next().unwrap(),
+        _ => "gomoku".to_owned(),
+    };
+    let (mut file, mut shards, mut shard) = (None, None, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--shards" | "--shard" => {
+                let value = args
+                    .next()
+                    .with_context(|| format!("Missing value of {arg}"))?;
+                match arg.as_str() {
+                    "--shards" => shards = Some(PathBuf::from(value)),
+                    _ => shard = Some(value.parse::<usize>()?),
+                }
+            }
+            _ if file.is_none() && !arg.starts_with("--") => file = Some(PathBuf::from(arg)),
+            _ => anyhow::bail!(
+                "Usage: viewer [game] <games file> | viewer --shards <data store dir> \
+                 [--shard <index, the last by default>]"
+            ),
+        }
+    }
+
+    let games = match (file, shards) {
+        (Some(file), None) => registry.visit(&game, LoadGames { file }).unwrap()?,
+        (None, Some(dir)) => {
+            // Opening would create it
+            anyhow::ensure!(dir.is_dir(), "No data store at {}", dir.display());
+            let store = DataStore::open(&dir, 1)?;
+            let shard = match shard {
+                Some(shard) => shard,
+                None => store
+                    .shards()
+                    .checked_sub(1)
+                    .with_context(|| format!("{} has no shards", dir.display()))?,
+            };
+            shard_games(&store.load_shard(shard)?)?
+        }
+        _ => anyhow::bail!("Expected one of a games file and --shards"),
+    };
+    anyhow::ensure!(!games.is_empty(), "No games to show");
+
+    let mut terminal = ratatui::init();
+    let result = Viewer::new(games).run(&mut terminal);
+    ratatui::restore();
+    Ok(result?)
+}
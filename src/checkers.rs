@@ -0,0 +1,7 @@
+#[cfg(feature = "torch")]
+mod alpha_zero_adapter;
+mod board;
+
+#[cfg(feature = "torch")]
+pub use alpha_zero_adapter::*;
+pub use board::*;
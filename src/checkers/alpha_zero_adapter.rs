@@ -0,0 +1,75 @@
+use tch::Tensor;
+
+use crate::alpha_zero::{ActionEncoding, AlphaZeroAdapter, AlphaZeroNet};
+
+use super::{CheckersBoard, CheckersMove, Piece, CHECKERS_DIRECTIONS};
+
+// Own men, own kings, opponent's men, opponent's kings and the piece in the middle of a
+// multi-jump, if any
+pub const CHECKERS_INPUT_PLANES: i64 = 5;
+
+// Policy planes are direction * 2 + (1 for jumps), indexed by the starting square.
+// Only dark squares are ever used, and the board has no symmetries that keep them dark.
+pub struct CheckersAlphaZeroAdapter;
+
+impl ActionEncoding<CheckersBoard> for CheckersAlphaZeroAdapter {
+    fn action_space_size() -> usize {
+        8 * 64
+    }
+
+    fn move_to_index(m: &CheckersMove) -> usize {
+        let distance = if m.is_jump() { 2 } else { 1 };
+        let dr = ((m.to / 8) as i32 - (m.from / 8) as i32) / distance;
+        let dc = ((m.to % 8) as i32 - (m.from % 8) as i32) / distance;
+        let direction = CHECKERS_DIRECTIONS
+            .iter()
+            .position(|&d| d == (dr, dc))
+            .unwrap();
+        (direction * 2 + distance as usize - 1) * 64 + m.from
+    }
+
+    // Whether the move ends the turn depends on the position, look it up in the legal moves
+    fn index_to_move(index: usize) -> CheckersMove {
+        let (plane, from) = (index / 64, index % 64);
+        let (dr, dc) = CHECKERS_DIRECTIONS[plane / 2];
+        let distance = (plane % 2 + 1) as i32;
+        let to = ((from / 8) as i32 + dr * distance) * 8 + (from % 8) as i32 + dc * distance;
+        CheckersMove {
+            from,
+            to: to as usize,
+            player_switch: true,
+        }
+    }
+
+    fn policy_shape() -> Vec<i64> {
+        vec![8, 8, 8]
+    }
+}
+
+impl<Net: AlphaZeroNet> AlphaZeroAdapter<CheckersBoard, Net> for CheckersAlphaZeroAdapter {
+    fn convert_game_to_nn_input(state: &CheckersBoard) -> Tensor {
+        let mut fld = [0f32; CHECKERS_INPUT_PLANES as usize * 64];
+        for sq in 0..64 {
+            let plane = match state.get(sq / 8, sq % 8) {
+                Some(Piece::OwnMan) => 0,
+                Some(Piece::OwnKing) => 1,
+                Some(Piece::OtherMan) => 2,
+                Some(Piece::OtherKing) => 3,
+                None => continue,
+            };
+            fld[plane * 64 + sq] = 1.;
+        }
+        if let Some(sq) = state.jumping() {
+            fld[4 * 64 + sq] = 1.;
+        }
+        Tensor::from_slice(&fld).view([CHECKERS_INPUT_PLANES, 8, 8])
+    }
+
+    fn get_estimated_policy(policy: &Tensor, moves: &[CheckersMove]) -> Vec<f32> {
+        Self::decode_policy(policy, moves)
+    }
+
+    fn convert_policy_to_nn(policy: &[f32], moves: &[CheckersMove]) -> Tensor {
+        Self::encode_policy(policy, moves)
+    }
+}
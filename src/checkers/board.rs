@@ -0,0 +1,336 @@
+use crate::alpha_zero::{Game, HeuristicEval, MoveParameters, TerminationState};
+
+// Plies without captures or man moves after which the game is a draw
+pub const CHECKERS_QUIET_LIMIT: usize = 80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Piece {
+    OwnMan,
+    OwnKing,
+    OtherMan,
+    OtherKing,
+}
+
+impl Piece {
+    fn is_own(self) -> bool {
+        matches!(self, Piece::OwnMan | Piece::OwnKing)
+    }
+
+    fn swap_sides(self) -> Self {
+        match self {
+            Piece::OwnMan => Piece::OtherMan,
+            Piece::OwnKing => Piece::OtherKing,
+            Piece::OtherMan => Piece::OwnMan,
+            Piece::OtherKing => Piece::OwnKing,
+        }
+    }
+}
+
+// Diagonal steps as (rows, columns), men may only use the first two
+pub const CHECKERS_DIRECTIONS: [(i32, i32); 4] = [(1, -1), (1, 1), (-1, -1), (-1, 1)];
+
+// Squares are `row * 8 + column`. A jump that can be continued doesn't switch the player:
+// the rest of a multi-jump is a sequence of further moves by the same player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CheckersMove {
+    pub from: usize,
+    pub to: usize,
+    pub player_switch: bool,
+}
+
+impl CheckersMove {
+    pub fn is_jump(&self) -> bool {
+        (self.from / 8).abs_diff(self.to / 8) == 2
+    }
+}
+
+impl MoveParameters for CheckersMove {
+    fn is_player_switch(&self) -> bool {
+        self.player_switch
+    }
+}
+
+// English draughts from the point of view of the player to move, whose men move towards
+// row 7. The board is rotated by 180° whenever the turn passes.
+#[derive(Clone, Hash, PartialEq, Eq)]
+pub struct CheckersBoard {
+    cells: [Option<Piece>; 64],
+    // Piece in the middle of a multi-jump, the only one allowed to move
+    jumping: Option<usize>,
+    quiet_plies: usize,
+}
+
+impl Default for CheckersBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CheckersBoard {
+    pub fn new() -> Self {
+        let mut cells = [None; 64];
+        for (sq, cell) in cells.iter_mut().enumerate() {
+            let (row, column) = (sq / 8, sq % 8);
+            if (row + column) % 2 == 1 {
+                if row < 3 {
+                    *cell = Some(Piece::OwnMan);
+                } else if row > 4 {
+                    *cell = Some(Piece::OtherMan);
+                }
+            }
+        }
+        Self::from_cells(cells)
+    }
+
+    pub fn from_cells(cells: [Option<Piece>; 64]) -> Self {
+        Self {
+            cells,
+            jumping: None,
+            quiet_plies: 0,
+        }
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> Option<Piece> {
+        self.cells[row * 8 + column]
+    }
+
+    pub fn jumping(&self) -> Option<usize> {
+        self.jumping
+    }
+
+    fn step(sq: usize, (dr, dc): (i32, i32), k: i32) -> Option<usize> {
+        let (row, column) = ((sq / 8) as i32 + dr * k, (sq % 8) as i32 + dc * k);
+        ((0..8).contains(&row) && (0..8).contains(&column)).then(|| (row * 8 + column) as usize)
+    }
+
+    fn directions(piece: Piece) -> &'static [(i32, i32)] {
+        match piece {
+            Piece::OwnKing => &CHECKERS_DIRECTIONS,
+            _ => &CHECKERS_DIRECTIONS[..2],
+        }
+    }
+
+    // Target squares of `sq`'s jumps and simple moves
+    fn targets(&self, sq: usize, jumps: bool) -> impl Iterator<Item = usize> + '_ {
+        let piece = self.cells[sq].filter(|p| p.is_own());
+        piece
+            .into_iter()
+            .flat_map(|p| Self::directions(p).iter())
+            .filter_map(move |&dir| {
+                if jumps {
+                    let over = Self::step(sq, dir, 1)?;
+                    let to = Self::step(sq, dir, 2)?;
+                    (self.cells[over].is_some_and(|p| !p.is_own()) && self.cells[to].is_none())
+                        .then_some(to)
+                } else {
+                    let to = Self::step(sq, dir, 1)?;
+                    self.cells[to].is_none().then_some(to)
+                }
+            })
+    }
+
+    // Moves the piece without switching sides, returns whether the turn is over
+    fn apply(&mut self, from: usize, to: usize) -> bool {
+        let mut piece = self.cells[from].take().unwrap();
+        let jump = (from / 8).abs_diff(to / 8) == 2;
+        if jump {
+            self.cells[(from + to) / 2] = None;
+        }
+        let crowned = piece == Piece::OwnMan && to / 8 == 7;
+        if crowned {
+            piece = Piece::OwnKing;
+        }
+        self.cells[to] = Some(piece);
+
+        if jump || piece == Piece::OwnMan || crowned {
+            self.quiet_plies = 0;
+        } else {
+            self.quiet_plies += 1;
+        }
+        // Crowning ends the move even if the new king could jump on
+        !jump || crowned || self.targets(to, true).next().is_none()
+    }
+
+    fn swap_sides(&self) -> Self {
+        let mut cells = [None; 64];
+        for (sq, cell) in self.cells.iter().enumerate() {
+            cells[63 - sq] = cell.map(Piece::swap_sides);
+        }
+        Self {
+            cells,
+            jumping: None,
+            quiet_plies: self.quiet_plies,
+        }
+    }
+
+    fn moves(&self, jumps: bool) -> Vec<CheckersMove> {
+        let squares = match self.jumping {
+            Some(sq) => sq..sq + 1,
+            None => 0..64,
+        };
+        squares
+            .flat_map(|from| self.targets(from, jumps).map(move |to| (from, to)))
+            .map(|(from, to)| CheckersMove {
+                from,
+                to,
+                player_switch: self.clone().apply(from, to),
+            })
+            .collect()
+    }
+}
+
+impl Game for CheckersBoard {
+    type Move = CheckersMove;
+
+    fn get_state(&self) -> TerminationState<Self::Move> {
+        if self.quiet_plies >= CHECKERS_QUIET_LIMIT {
+            return TerminationState::Terminal(0.5);
+        }
+        // Captures are mandatory
+        let mut moves = self.moves(true);
+        if moves.is_empty() && self.jumping.is_none() {
+            moves = self.moves(false);
+        }
+        if moves.is_empty() {
+            TerminationState::Terminal(0.0)
+        } else {
+            TerminationState::Moves(moves)
+        }
+    }
+
+    fn make_move(&self, m: &Self::Move) -> Self {
+        let mut res = self.clone();
+        let finished = res.apply(m.from, m.to);
+        assert_eq!(finished, m.player_switch);
+        if finished {
+            res.swap_sides()
+        } else {
+            res.jumping = Some(m.to);
+            res
+        }
+    }
+
+    fn is_legal(&self, m: &Self::Move) -> bool {
+        match self.get_state() {
+            TerminationState::Moves(moves) => moves.contains(m),
+            TerminationState::Terminal(_) => false,
+        }
+    }
+}
+
+impl HeuristicEval for CheckersBoard {
+    // Material balance, kings count as 1.5 men
+    fn eval(&self) -> f32 {
+        let balance = self
+            .cells
+            .iter()
+            .flatten()
+            .map(|p| match p {
+                Piece::OwnMan => 1.0,
+                Piece::OwnKing => 1.5,
+                Piece::OtherMan => -1.0,
+                Piece::OtherKing => -1.5,
+            })
+            .sum::<f32>();
+        (0.5 + 0.05 * balance).clamp(0.05, 0.95)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alpha_zero::{Game, HeuristicEvaluator, MonteCarloTree, TerminationState};
+
+    use super::{CheckersBoard, CheckersMove, Piece};
+
+    fn board(pieces: &[(usize, usize, Piece)]) -> CheckersBoard {
+        let mut cells = [None; 64];
+        for &(row, column, piece) in pieces {
+            cells[row * 8 + column] = Some(piece);
+        }
+        CheckersBoard::from_cells(cells)
+    }
+
+    #[test]
+    fn checkers_opening_moves() {
+        let moves = CheckersBoard::new().get_state().get_moves().unwrap();
+        assert_eq!(moves.len(), 7);
+        assert!(moves.iter().all(|m| m.player_switch && !m.is_jump()));
+    }
+
+    #[test]
+    fn checkers_multi_jump() {
+        let start = board(&[
+            (2, 1, Piece::OwnMan),
+            (3, 2, Piece::OtherMan),
+            (5, 4, Piece::OtherMan),
+            (7, 0, Piece::OtherMan),
+        ]);
+        // The capture is forced and can be continued
+        let moves = start.get_state().get_moves().unwrap();
+        assert_eq!(
+            moves,
+            [CheckersMove {
+                from: 17,
+                to: 35,
+                player_switch: false
+            }]
+        );
+
+        let mid = start.make_move(&moves[0]);
+        assert_eq!(mid.jumping(), Some(35));
+        let moves = mid.get_state().get_moves().unwrap();
+        assert_eq!(
+            moves,
+            [CheckersMove {
+                from: 35,
+                to: 53,
+                player_switch: true
+            }]
+        );
+
+        // Seen from the other side now, which has a single man left
+        let end = mid.make_move(&moves[0]);
+        assert_eq!(end.get(0, 7), Some(Piece::OwnMan));
+        assert_eq!(end.get(1, 2), Some(Piece::OtherMan));
+    }
+
+    #[test]
+    fn checkers_crowning_ends_the_move() {
+        let start = board(&[
+            (5, 2, Piece::OwnMan),
+            (6, 3, Piece::OtherMan),
+            (6, 5, Piece::OtherMan),
+        ]);
+        let moves = start.get_state().get_moves().unwrap();
+        assert_eq!(moves.len(), 1);
+        assert!(moves[0].player_switch);
+        assert_eq!(start.make_move(&moves[0]).get(0, 3), Some(Piece::OtherKing));
+    }
+
+    #[tokio::test]
+    async fn search_through_multi_jumps() {
+        // Both jumps capture everything the opponent has, so the root is a win even though
+        // the first jump doesn't switch the player
+        let start = board(&[
+            (2, 1, Piece::OwnMan),
+            (3, 2, Piece::OtherMan),
+            (5, 4, Piece::OtherMan),
+        ]);
+        let mut tree = MonteCarloTree::new(start.clone(), HeuristicEvaluator);
+        tree.do_simulations(64, 1.0).await;
+        assert!(tree.get_root_value() > 0.95, "{}", tree.get_root_value());
+
+        let end = start
+            .make_move(&CheckersMove {
+                from: 17,
+                to: 35,
+                player_switch: false,
+            })
+            .make_move(&CheckersMove {
+                from: 35,
+                to: 53,
+                player_switch: true,
+            });
+        assert_eq!(end.get_state(), TerminationState::Terminal(0.0));
+    }
+}
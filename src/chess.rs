@@ -0,0 +1,11 @@
+#[cfg(feature = "torch")]
+mod alpha_zero_adapter;
+mod board;
+#[cfg(feature = "torch")]
+mod nn;
+
+#[cfg(feature = "torch")]
+pub use alpha_zero_adapter::*;
+pub use board::*;
+#[cfg(feature = "torch")]
+pub use nn::*;
@@ -0,0 +1,185 @@
+use shakmaty::{uci::UciMove, Color, EnPassantMode, Position, Role, Square};
+use tch::Tensor;
+
+use crate::alpha_zero::{ActionEncoding, AlphaZeroAdapter};
+
+use super::{ChessGame, ChessMove, ChessNet};
+
+// Own pieces, opponent pieces, castling rights (own then opponent's, king side first),
+// en passant target, 50-move counter and repetition, all from the side to move
+pub const CHESS_INPUT_PLANES: i64 = 6 + 6 + 4 + 1 + 1 + 1;
+
+// Move planes of the AlphaZero encoding. A move is the plane of its shape times the
+// square it starts from: 56 queen-like moves (8 directions, up to 7 squares), 8 knight
+// jumps and 9 underpromotions (3 pieces, 3 directions). Queen promotions use the queen planes.
+const QUEEN_DIRECTIONS: [(i32, i32); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+const KNIGHT_JUMPS: [(i32, i32); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const UNDERPROMOTIONS: [Role; 3] = [Role::Knight, Role::Bishop, Role::Rook];
+const MOVE_PLANES: usize = 73;
+
+pub struct ChessAlphaZeroAdapter;
+
+impl ActionEncoding<ChessGame> for ChessAlphaZeroAdapter {
+    fn action_space_size() -> usize {
+        MOVE_PLANES * 64
+    }
+
+    fn move_to_index(m: &ChessMove) -> usize {
+        let UciMove::Normal {
+            from,
+            to,
+            promotion,
+        } = m.0
+        else {
+            panic!("Unsupported move {:?}", m.0);
+        };
+        let df = to.file().to_u32() as i32 - from.file().to_u32() as i32;
+        let dr = to.rank().to_u32() as i32 - from.rank().to_u32() as i32;
+
+        let plane = match promotion {
+            Some(role) if role != Role::Queen => {
+                let piece = UNDERPROMOTIONS.iter().position(|&r| r == role).unwrap();
+                56 + 8 + piece * 3 + (df + 1) as usize
+            }
+            _ => match KNIGHT_JUMPS.iter().position(|&d| d == (df, dr)) {
+                Some(jump) => 56 + jump,
+                None => {
+                    let direction = QUEEN_DIRECTIONS
+                        .iter()
+                        .position(|&d| d == (df.signum(), dr.signum()))
+                        .unwrap();
+                    let distance = df.abs().max(dr.abs()) as usize;
+                    direction * 7 + distance - 1
+                }
+            },
+        };
+        plane * 64 + from.to_usize()
+    }
+
+    fn index_to_move(index: usize) -> ChessMove {
+        let (plane, from) = (index / 64, Square::new((index % 64) as u32));
+        let ((df, dr), promotion) = if plane < 56 {
+            let (dx, dy) = QUEEN_DIRECTIONS[plane / 7];
+            let distance = (plane % 7 + 1) as i32;
+            ((dx * distance, dy * distance), None)
+        } else if plane < 64 {
+            (KNIGHT_JUMPS[plane - 56], None)
+        } else {
+            let (piece, direction) = ((plane - 64) / 3, (plane - 64) % 3);
+            ((direction as i32 - 1, 1), Some(UNDERPROMOTIONS[piece]))
+        };
+        let to = match (from.file().offset(df), from.rank().offset(dr)) {
+            (Some(file), Some(rank)) => Square::from_coords(file, rank),
+            _ => panic!("Move index {index} points outside the board"),
+        };
+        ChessMove(UciMove::Normal {
+            from,
+            to,
+            promotion,
+        })
+    }
+
+    fn policy_shape() -> Vec<i64> {
+        vec![MOVE_PLANES as i64, 8, 8]
+    }
+}
+
+impl AlphaZeroAdapter<ChessGame, ChessNet> for ChessAlphaZeroAdapter {
+    fn convert_game_to_nn_input(state: &ChessGame) -> Tensor {
+        let position = state.position();
+        let us = position.turn();
+        let relative = |sq: Square| {
+            if us == Color::White {
+                sq
+            } else {
+                sq.flip_vertical()
+            }
+        };
+
+        let mut fld = vec![0f32; CHESS_INPUT_PLANES as usize * 64];
+        let mut fill = |plane: usize, value: f32| fld[plane * 64..(plane + 1) * 64].fill(value);
+        let castles = position.castles();
+        for (i, color) in [us, !us].into_iter().enumerate() {
+            if castles.has(color, shakmaty::CastlingSide::KingSide) {
+                fill(12 + 2 * i, 1.);
+            }
+            if castles.has(color, shakmaty::CastlingSide::QueenSide) {
+                fill(13 + 2 * i, 1.);
+            }
+        }
+        fill(17, position.halfmoves() as f32 / 100.);
+        if state.repetitions() > 1 {
+            fill(18, 1.);
+        }
+
+        for (sq, piece) in position.board().clone() {
+            let plane =
+                piece.role as usize - Role::Pawn as usize + if piece.color == us { 0 } else { 6 };
+            fld[plane * 64 + relative(sq).to_usize()] = 1.;
+        }
+        if let Some(sq) = position.ep_square(EnPassantMode::Legal) {
+            fld[16 * 64 + relative(sq).to_usize()] = 1.;
+        }
+
+        Tensor::from_slice(&fld).view([CHESS_INPUT_PLANES, 8, 8])
+    }
+
+    fn get_estimated_policy(policy: &Tensor, moves: &[ChessMove]) -> Vec<f32> {
+        Self::decode_policy(policy, moves)
+    }
+
+    fn convert_policy_to_nn(policy: &[f32], moves: &[ChessMove]) -> Tensor {
+        Self::encode_policy(policy, moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::alpha_zero::{ActionEncoding, Game};
+
+    use super::{ChessAlphaZeroAdapter, ChessGame};
+
+    #[test]
+    fn action_encoding_round_trip() {
+        // Castling both ways, en passant, promotions and underpromotions with captures
+        for fen in [
+            "r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/1PPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/Pp2P3/2N2Q2/1PPBBPpP/R3K2R b KQkq a3 0 1",
+        ] {
+            let game = ChessGame::from_fen(fen).unwrap();
+            let moves = game.get_state().get_moves().unwrap();
+            let indices = moves
+                .iter()
+                .map(ChessAlphaZeroAdapter::move_to_index)
+                .collect::<HashSet<_>>();
+            assert_eq!(indices.len(), moves.len());
+
+            for m in &moves {
+                let index = ChessAlphaZeroAdapter::move_to_index(m);
+                assert!(index < ChessAlphaZeroAdapter::action_space_size());
+                let decoded = ChessAlphaZeroAdapter::index_to_move(index);
+                assert!(game.is_legal(&decoded), "{m:?} -> {decoded:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use shakmaty::{
+    fen::Fen, uci::UciMove, zobrist::Zobrist64, CastlingMode, Chess, EnPassantMode, Move, Position,
+    Role,
+};
+
+use crate::alpha_zero::{Game, MoveNotation, MoveParameters, TerminationState, UciGame};
+
+// Games are adjudicated as draws after this many plies, so self-play always terminates
+pub const DEFAULT_MAX_PLIES: usize = 512;
+
+// Move in UCI form (castling as the king's two-square step), seen from the side to move:
+// black's moves are mirrored vertically, so both players push their pawns "up" the board.
+// This keeps the action encoding independent of the color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChessMove(pub UciMove);
+
+impl MoveParameters for ChessMove {
+    fn is_player_switch(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Clone)]
+pub struct ChessGame {
+    position: Chess,
+    // Hashes of the positions since the last capture or pawn move, current one last
+    history: Vec<Zobrist64>,
+    ply: usize,
+    max_plies: usize,
+}
+
+impl Default for ChessGame {
+    fn default() -> Self {
+        Self::new(Chess::default())
+    }
+}
+
+impl ChessGame {
+    pub fn new(position: Chess) -> Self {
+        Self {
+            history: vec![position.zobrist_hash(EnPassantMode::Legal)],
+            position,
+            ply: 0,
+            max_plies: DEFAULT_MAX_PLIES,
+        }
+    }
+
+    pub fn from_fen(fen: &str) -> anyhow::Result<Self> {
+        let fen: Fen = fen.parse()?;
+        Ok(Self::new(fen.into_position(CastlingMode::Standard)?))
+    }
+
+    pub fn with_max_plies(self, max_plies: usize) -> Self {
+        Self { max_plies, ..self }
+    }
+
+    pub fn position(&self) -> &Chess {
+        &self.position
+    }
+
+    // Of the position alone, whatever led to it
+    pub fn position_hash(&self) -> u64 {
+        self.history.last().unwrap().0
+    }
+
+    // How many times the current position has occurred, including now
+    pub fn repetitions(&self) -> usize {
+        let current = self.history.last().unwrap();
+        self.history.iter().filter(|&h| h == current).count()
+    }
+
+    pub fn to_uci(&self, m: &ChessMove) -> UciMove {
+        if self.position.turn().is_white() {
+            m.0
+        } else {
+            m.0.to_mirrored()
+        }
+    }
+
+    pub fn from_uci(&self, m: UciMove) -> ChessMove {
+        if self.position.turn().is_white() {
+            ChessMove(m)
+        } else {
+            ChessMove(m.to_mirrored())
+        }
+    }
+
+    fn relative(&self, m: Move) -> ChessMove {
+        self.from_uci(UciMove::from_standard(m))
+    }
+
+    // A move to the last rank without a piece (which is how the action encoding stores
+    // queen promotions) promotes to a queen
+    fn resolve(&self, m: &ChessMove) -> Option<Move> {
+        let uci = self.to_uci(m);
+        uci.to_move(&self.position).ok().or_else(|| match uci {
+            UciMove::Normal {
+                from,
+                to,
+                promotion: None,
+            } => UciMove::Normal {
+                from,
+                to,
+                promotion: Some(Role::Queen),
+            }
+            .to_move(&self.position)
+            .ok(),
+            _ => None,
+        })
+    }
+}
+
+impl Game for ChessGame {
+    type Move = ChessMove;
+
+    fn get_state(&self) -> TerminationState<Self::Move> {
+        let moves = self.position.legal_moves();
+        if moves.is_empty() {
+            // Checkmate or stalemate
+            return TerminationState::Terminal(if self.position.is_check() { 0.0 } else { 0.5 });
+        }
+        // Threefold repetition and the 50-move rule are applied automatically
+        if self.position.is_insufficient_material()
+            || self.position.halfmoves() >= 100
+            || self.repetitions() >= 3
+            || self.ply >= self.max_plies
+        {
+            return TerminationState::Terminal(0.5);
+        }
+        TerminationState::Moves(moves.into_iter().map(|m| self.relative(m)).collect())
+    }
+
+    fn make_move(&self, m: &Self::Move) -> Self {
+        let m = self
+            .resolve(m)
+            .unwrap_or_else(|| panic!("Illegal move {}", self.to_uci(m)));
+        let irreversible = m.is_zeroing();
+
+        let mut position = self.position.clone();
+        position.play_unchecked(m);
+        let hash = position.zobrist_hash(EnPassantMode::Legal);
+        let history = if irreversible {
+            vec![hash]
+        } else {
+            let mut history = self.history.clone();
+            history.push(hash);
+            history
+        };
+
+        Self {
+            position,
+            history,
+            ply: self.ply + 1,
+            max_plies: self.max_plies,
+        }
+    }
+
+    fn is_legal(&self, m: &Self::Move) -> bool {
+        self.resolve(m).is_some() && self.get_state().get_terminal().is_none()
+    }
+}
+
+// UCI's long algebraic notation, like e2e4, e1g1 for castling and e7e8q
+impl MoveNotation for ChessGame {
+    fn parse_move(&self, text: &str) -> anyhow::Result<Self::Move> {
+        let m = text
+            .parse::<UciMove>()
+            .map_err(|e| anyhow::anyhow!("Invalid move {text}: {e}"))?;
+        Ok(self.from_uci(m))
+    }
+
+    fn format_move(&self, m: &Self::Move) -> String {
+        self.to_uci(m).to_string()
+    }
+}
+
+impl UciGame for ChessGame {
+    fn from_fen(fen: &str) -> anyhow::Result<Self> {
+        ChessGame::from_fen(fen)
+    }
+
+    fn white_to_move(&self) -> bool {
+        self.position.turn().is_white()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shakmaty::{Color, Piece, Position, Role, Square};
+
+    use crate::alpha_zero::{Game, TerminationState};
+
+    use super::ChessGame;
+
+    fn play(game: &ChessGame, moves: &[&str]) -> ChessGame {
+        moves.iter().fold(game.clone(), |game, m| {
+            let m = game.from_uci(m.parse().unwrap());
+            assert!(game.is_legal(&m), "{m:?}");
+            game.make_move(&m)
+        })
+    }
+
+    #[test]
+    fn chess_start_and_mate() {
+        let game = ChessGame::default();
+        assert_eq!(game.get_state().get_moves().unwrap().len(), 20);
+
+        // Fool's mate, the side to move is mated
+        let game = play(&game, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert_eq!(game.get_state(), TerminationState::Terminal(0.0));
+    }
+
+    #[test]
+    fn chess_draws() {
+        let knights = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let game = play(&ChessGame::default(), &knights);
+        assert_eq!(game.repetitions(), 2);
+        assert!(game.get_state().get_terminal().is_none());
+        let game = play(&game, &knights);
+        assert_eq!(game.get_state(), TerminationState::Terminal(0.5));
+
+        let game = ChessGame::default().with_max_plies(2);
+        let game = play(&game, &["e2e4", "e7e5"]);
+        assert_eq!(game.get_state(), TerminationState::Terminal(0.5));
+    }
+
+    #[test]
+    fn chess_promotion_defaults_to_queen() {
+        let game = ChessGame::from_fen("8/P6k/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let game = play(&game, &["a7a8"]);
+        assert_eq!(
+            game.position().board().piece_at(Square::A8),
+            Some(Piece {
+                color: Color::White,
+                role: Role::Queen
+            })
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use tch::{
+    nn::{self, BatchNorm, Conv2D, ConvConfig, Linear, Module, ModuleT},
+    Tensor,
+};
+
+use crate::alpha_zero::{AlphaZeroNet, NetOutput};
+
+use super::CHESS_INPUT_PLANES;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChessNetConfig {
+    pub blocks: usize,
+    pub channels: i64,
+    pub value_hidden: i64,
+}
+
+impl Default for ChessNetConfig {
+    fn default() -> Self {
+        Self {
+            blocks: 10,
+            channels: 128,
+            value_hidden: 256,
+        }
+    }
+}
+
+fn conv3x3(path: nn::Path, c_in: i64, c_out: i64) -> Conv2D {
+    nn::conv2d(
+        path,
+        c_in,
+        c_out,
+        3,
+        ConvConfig {
+            padding: 1,
+            bias: false,
+            ..Default::default()
+        },
+    )
+}
+
+struct ResidualBlock {
+    conv1: Conv2D,
+    bn1: BatchNorm,
+    conv2: Conv2D,
+    bn2: BatchNorm,
+}
+
+impl ResidualBlock {
+    fn new(path: nn::Path, channels: i64) -> Self {
+        Self {
+            conv1: conv3x3(&path / "conv1", channels, channels),
+            bn1: nn::batch_norm2d(&path / "bn1", channels, Default::default()),
+            conv2: conv3x3(&path / "conv2", channels, channels),
+            bn2: nn::batch_norm2d(&path / "bn2", channels, Default::default()),
+        }
+    }
+
+    fn forward_t(&self, xs: &Tensor, is_training: bool) -> Tensor {
+        let ys = self.conv1.forward_t(xs, is_training);
+        let ys = self.bn1.forward_t(&ys, is_training).relu();
+        let ys = self.conv2.forward_t(&ys, is_training);
+        let ys = self.bn2.forward_t(&ys, is_training);
+        (ys + xs).relu()
+    }
+}
+
+// AlphaZero's chess architecture with a configurable tower. The policy head is
+// convolutional and predicts the 73 move planes directly.
+pub struct ChessNet {
+    conv_input: Conv2D,
+    bn_input: BatchNorm,
+    blocks: Vec<ResidualBlock>,
+
+    conv_policy: Conv2D,
+    bn_policy: BatchNorm,
+    conv_policy_out: Conv2D,
+
+    conv_value: Conv2D,
+    bn_value: BatchNorm,
+    fc_value_1: Linear,
+    fc_value_2: Linear,
+}
+
+impl ChessNet {
+    pub fn new(path: &nn::Path, config: ChessNetConfig) -> Self {
+        let channels = config.channels;
+        Self {
+            conv_input: conv3x3(path / "conv_input", CHESS_INPUT_PLANES, channels),
+            bn_input: nn::batch_norm2d(path / "bn_input", channels, Default::default()),
+            blocks: (0..config.blocks)
+                .map(|i| ResidualBlock::new(path / "blocks" / i, channels))
+                .collect(),
+
+            conv_policy: conv3x3(path / "conv_policy", channels, channels),
+            bn_policy: nn::batch_norm2d(path / "bn_policy", channels, Default::default()),
+            conv_policy_out: nn::conv2d(
+                path / "conv_policy_out",
+                channels,
+                73,
+                1,
+                Default::default(),
+            ),
+
+            conv_value: nn::conv2d(path / "conv_value", channels, 1, 1, Default::default()),
+            bn_value: nn::batch_norm2d(path / "bn_value", 1, Default::default()),
+            fc_value_1: nn::linear(
+                path / "fc_value_1",
+                64,
+                config.value_hidden,
+                Default::default(),
+            ),
+            fc_value_2: nn::linear(
+                path / "fc_value_2",
+                config.value_hidden,
+                1,
+                Default::default(),
+            ),
+        }
+    }
+}
+
+impl AlphaZeroNet for ChessNet {
+    fn forward_t(&self, xs: &Tensor, is_training: bool) -> NetOutput {
+        let batch = xs.size()[0];
+
+        let mid = self.conv_input.forward_t(xs, is_training);
+        let mut mid = self.bn_input.forward_t(&mid, is_training).relu();
+        for block in &self.blocks {
+            mid = block.forward_t(&mid, is_training);
+        }
+
+        let policy = self.conv_policy.forward_t(&mid, is_training);
+        let policy = self.bn_policy.forward_t(&policy, is_training).relu();
+        let policy = self
+            .conv_policy_out
+            .forward_t(&policy, is_training)
+            .view([batch, -1])
+            .log_softmax(1, None)
+            .view([batch, 73, 8, 8]);
+
+        let val = self.conv_value.forward_t(&mid, is_training);
+        let val = self.bn_value.forward_t(&val, is_training).relu();
+        let val = self.fc_value_1.forward(&val.view([batch, -1])).relu();
+        let val = self.fc_value_2.forward(&val).view([batch]).sigmoid();
+
+        NetOutput::new(val, policy)
+    }
+}
@@ -0,0 +1,542 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::{
+    alpha_zero::{Baseline, Contender, DeviceSetting, TimeControl},
+    config::Config,
+    logging::LogFormat,
+    run::RunContext,
+    sweep::SweepSpace,
+};
+
+#[derive(Debug, Parser)]
+#[command(about = "Trains AlphaZero nets by self-play and puts them to use")]
+pub struct Cli {
+    #[command(flatten)]
+    pub overrides: Overrides,
+    /// Writes the logs as JSON lines, their verbosity per module coming from RUST_LOG
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub log_format: LogFormat,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+// Taken by every command, before or after its name
+#[derive(Debug, Default, Args)]
+pub struct Overrides {
+    /// TOML, YAML or JSON file of the settings, the flags below overriding it
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Game to play, by its name in the registry
+    #[arg(long, global = true)]
+    pub game: Option<String>,
+    /// Device the nets run on: cpu, cuda:<index>, mps or auto
+    #[arg(long, global = true)]
+    pub device: Option<DeviceSetting>,
+    /// Self-play games the learner waits for before training a generation
+    #[arg(long, global = true)]
+    pub games_per_generation: Option<usize>,
+    /// Learning rate of training
+    #[arg(long, global = true)]
+    pub lr: Option<f64>,
+    /// Positions per training step
+    #[arg(long, global = true)]
+    pub batch_size: Option<usize>,
+    /// Search simulations per move, in self-play, matches and analyses
+    #[arg(long, global = true)]
+    pub simulations: Option<usize>,
+    /// Exploration constant of the search
+    #[arg(long, global = true)]
+    pub c_puct: Option<f32>,
+    /// Games of a match, or of every pair of an arena
+    #[arg(long, global = true)]
+    pub games: Option<usize>,
+}
+
+impl Overrides {
+    // The config file's settings, or the defaults without one, with the flags given, validated
+    pub fn config(&self) -> anyhow::Result<Config> {
+        let config = match &self.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        self.apply(config)
+    }
+
+    // The settings the run in `dir` started with, with the flags given, validated
+    pub fn resumed(&self, dir: &Path) -> anyhow::Result<Config> {
+        anyhow::ensure!(
+            self.config.is_none(),
+            "A resumed run keeps its settings, --config doesn't apply"
+        );
+        // Opening would create it
+        anyhow::ensure!(dir.is_dir(), "No run directory at {}", dir.display());
+        let settings = RunContext::open(dir)?.experiment();
+        anyhow::ensure!(
+            settings.exists(),
+            "No settings to resume with at {}, continue the run with train --run instead",
+            settings.display()
+        );
+        let config = Config::load(&settings)?;
+        if let Some(game) = &self.game {
+            anyhow::ensure!(
+                *game == config.game,
+                "The run plays {}, it can't resume with {game}",
+                config.game
+            );
+        }
+        self.apply(config)
+    }
+
+    fn apply(&self, mut config: Config) -> anyhow::Result<Config> {
+        if let Some(game) = &self.game {
+            config.game = game.clone();
+        }
+        let Self {
+            device,
+            games_per_generation,
+            lr,
+            batch_size,
+            simulations,
+            c_puct,
+            games,
+            ..
+        } = *self;
+        config.device = device.unwrap_or(config.device);
+        let schedule = &mut config.schedule;
+        schedule.games_per_generation =
+            games_per_generation.unwrap_or(schedule.games_per_generation);
+        config.trainer.lr = lr.unwrap_or(config.trainer.lr);
+        config.trainer.batch_size = batch_size.unwrap_or(config.trainer.batch_size);
+        config.search.simulations = simulations.unwrap_or(config.search.simulations);
+        config.search.c_puct = c_puct.unwrap_or(config.search.c_puct);
+        config.evaluation.games = games.unwrap_or(config.evaluation.games);
+        config.validate().context("Invalid settings")?;
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Trains a net by self-play, forever
+    Train {
+        /// Run directory to continue, a new one below runs/ by default
+        #[arg(long)]
+        run: Option<PathBuf>,
+        /// Address to accept self-play workers on
+        #[arg(long)]
+        listen: Option<String>,
+        /// Address to serve a dashboard of the run on
+        #[arg(long)]
+        dashboard: Option<String>,
+        /// Trains a generation of a few tiny games on the CPU, going through every stage in under
+        /// a minute, to check the settings and the code before a long run
+        #[arg(long, conflicts_with = "run")]
+        smoke: bool,
+    },
+    /// Continues a crashed or stopped run with its settings, weights, optimizer, replay buffer
+    /// and ratings
+    Resume {
+        /// Run directory, like runs/20240501-120000-gomoku
+        run: PathBuf,
+        /// Address to accept self-play workers on
+        #[arg(long)]
+        listen: Option<String>,
+        /// Address to serve a dashboard of the run on
+        #[arg(long)]
+        dashboard: Option<String>,
+    },
+    /// Plays self-play games for a learner accepting workers
+    Selfplay {
+        /// Address of the learner
+        learner: String,
+    },
+    /// Plays a generation of self-play games and reports where their time goes
+    Profile {
+        /// Weights of the net, randomly initialized by default
+        #[arg(long = "with")]
+        weights: Option<PathBuf>,
+    },
+    /// Trains a shortened run per trial of a hyperparameter space and compares them
+    Sweep {
+        /// Values or ranges per hyperparameter, like "lr=1e-4..1e-2 simulations=32,64", a range being
+        /// sampled log-uniformly
+        space: SweepSpace,
+        /// Random trials, a grid of the values by default
+        #[arg(long)]
+        trials: Option<usize>,
+        /// Generations trained per trial
+        #[arg(long, default_value_t = 3)]
+        generations: usize,
+        /// Trials trained at the same time
+        #[arg(long, default_value_t = 1)]
+        jobs: usize,
+    },
+    /// Plays two nets or baselines against each other, or a round-robin of checkpoints
+    Eval {
+        /// Weights or baseline, like random, greedy, uniform-mcts:N or rollout-mcts:N
+        #[arg(requires = "against", required_unless_present = "arena")]
+        contender: Option<Contender>,
+        /// Weights or baseline
+        #[arg(long)]
+        against: Option<Contender>,
+        /// Directory of checkpoints to play a round-robin between
+        #[arg(long, conflicts_with = "contender")]
+        arena: Option<PathBuf>,
+        /// Baselines joining the round-robin
+        #[arg(long, value_delimiter = ',', requires = "arena")]
+        baselines: Vec<Baseline>,
+        /// Seconds per move, or seconds+increment
+        #[arg(long)]
+        time_control: Option<TimeControl>,
+    },
+    /// A game against a net or a baseline in the terminal
+    Play {
+        /// Weights or baseline
+        engine: Contender,
+        #[arg(long, value_enum, default_value_t = Side::First)]
+        human: Side,
+        /// Seconds per move, or seconds+increment
+        #[arg(long)]
+        time_control: Option<TimeControl>,
+    },
+    /// Speaks GTP on stdin and stdout, for GUIs and referees of board games like gomoku
+    Gtp {
+        /// Weights of the net
+        #[arg(long = "with")]
+        weights: PathBuf,
+        /// Seconds per move, or seconds+increment, until the controller sends time_settings
+        #[arg(long)]
+        time_control: Option<TimeControl>,
+    },
+    /// Speaks UCI on stdin and stdout, for chess GUIs and tournament managers like cutechess
+    Uci {
+        /// Weights of the net
+        #[arg(long = "with")]
+        weights: PathBuf,
+    },
+    /// Plays browsers over WebSockets, serving a page to play gomoku in
+    PlayServer {
+        /// Weights of the net
+        #[arg(long = "with")]
+        weights: PathBuf,
+        /// Address to serve on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+    },
+    /// Plays the matches posted over HTTP one at a time, answering with JSON reports, for
+    /// sharing the machine's GPU
+    EvalServer {
+        /// Directory of the weights matches can be posted with, named relative to it
+        #[arg(long)]
+        models: PathBuf,
+        /// Address to serve on
+        #[arg(long, default_value = "127.0.0.1:8090")]
+        listen: String,
+        /// Most games a match can ask for
+        #[arg(long)]
+        max_games: Option<usize>,
+        /// Most simulations per move a match can ask for
+        #[arg(long)]
+        max_simulations: Option<usize>,
+    },
+    /// Reports what a net's search thinks of every move of a games or moves file
+    Analyze {
+        /// Games written by a match or an arena, SGF, or a whitespace separated move list
+        file: PathBuf,
+        /// Weights of the net
+        #[arg(long = "with")]
+        weights: PathBuf,
+        /// Renders the prior, visits and Q of the position after the moves instead
+        #[arg(long)]
+        heatmaps: bool,
+        /// Renders where the lines of the net and these weights diverge after the moves instead
+        #[arg(long, conflicts_with = "heatmaps")]
+        versus: Option<PathBuf>,
+    },
+    /// Converts games, metrics or nets to files for other tools
+    Export {
+        #[arg(value_enum)]
+        format: ExportFormat,
+        /// Games file for sgf and images, run directory for charts, parquet and arrow, weights
+        /// for torchscript
+        path: PathBuf,
+        /// Positions per batch the net is traced with, which TorchScript keeps fixed
+        #[arg(long, default_value_t = 1)]
+        trace_batch: usize,
+    },
+    /// Converts weights trained elsewhere, like a PyTorch state_dict, into the game's net
+    Import {
+        /// Safetensors or npz file of the weights
+        file: PathBuf,
+        /// TOML table of "their.name" = "our.name", a trailing * renaming prefixes and an
+        /// empty name dropping the weight
+        #[arg(long)]
+        mapping: Option<PathBuf>,
+        /// Where to write the weights of the net
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Side {
+    First,
+    Second,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    // Of the games of a match or an arena
+    Sgf,
+    // Pictures and animations of the games of a match or an arena
+    Images,
+    // Of a run's metrics
+    Charts,
+    // A traced net with a JSON description of its inputs and outputs, for serving it from
+    // Python or other engines. `torch.onnx.export` converts it to ONNX.
+    Torchscript,
+    // A run's self-play samples, a row per position, for pandas, polars or other frameworks
+    Parquet,
+    // The same as an Arrow IPC file
+    Arrow,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use clap::Parser;
+
+    use crate::{
+        alpha_zero::{Baseline, Contender, DeviceSetting},
+        config::Config,
+        logging::LogFormat,
+    };
+
+    use super::{Cli, Command, ExportFormat};
+
+    #[test]
+    fn subcommands_with_overrides() {
+        let cli = Cli::try_parse_from([
+            "alpha-zero",
+            "--game",
+            "tictactoe",
+            "eval",
+            "--arena",
+            "checkpoints",
+            "--baselines",
+            "random,greedy",
+            "--simulations",
+            "64",
+            "--device",
+            "cuda:1",
+        ])
+        .unwrap();
+        let Command::Eval {
+            contender,
+            arena,
+            baselines,
+            ..
+        } = cli.command
+        else {
+            panic!("Expected an eval");
+        };
+        assert_eq!(contender, None);
+        assert_eq!(arena, Some(PathBuf::from("checkpoints")));
+        assert_eq!(baselines, [Baseline::Random, Baseline::Greedy]);
+        let config = cli.overrides.config().unwrap();
+        assert_eq!(
+            (config.game.as_str(), config.search.simulations),
+            ("tictactoe", 64)
+        );
+        assert_eq!(config.trainer.lr, 1e-4);
+        assert_eq!(config.device, DeviceSetting::Cuda(1));
+        assert_eq!(cli.log_format, LogFormat::Text);
+
+        // Checked like config files
+        let cli = Cli::try_parse_from(["alpha-zero", "train", "--simulations", "0"]).unwrap();
+        assert!(cli.overrides.config().is_err());
+        let cli = Cli::try_parse_from(["alpha-zero", "train", "--smoke", "--log-format", "json"])
+            .unwrap();
+        assert!(matches!(cli.command, Command::Train { smoke: true, .. }));
+        assert_eq!(cli.log_format, LogFormat::Json);
+        assert!(
+            Cli::try_parse_from(["alpha-zero", "train", "--smoke", "--run", "runs/x"]).is_err()
+        );
+
+        // With the run's settings
+        let dir = std::env::temp_dir().join(format!("resume-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let started = Config {
+            game: "gomoku".to_owned(),
+            ..Default::default()
+        };
+        started.save(&dir.join("experiment.toml")).unwrap();
+        let resume = |args: &[&str]| {
+            let cli = Cli::try_parse_from(
+                ["alpha-zero", "resume", dir.to_str().unwrap()]
+                    .into_iter()
+                    .chain(args.iter().copied()),
+            )
+            .unwrap();
+            assert!(matches!(cli.command, Command::Resume { .. }));
+            cli.overrides.resumed(&dir)
+        };
+        let config = resume(&["--lr", "0.01"]).unwrap();
+        assert_eq!((config.game.as_str(), config.trainer.lr), ("gomoku", 0.01));
+        assert!(resume(&["--game", "tictactoe"]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(resume(&[]).is_err());
+
+        let cli =
+            Cli::try_parse_from(["alpha-zero", "eval", "random", "--against", "greedy"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Eval {
+                contender: Some(Contender::Baseline(Baseline::Random)),
+                ..
+            }
+        ));
+        let cli =
+            Cli::try_parse_from(["alpha-zero", "profile", "--games-per-generation", "8"]).unwrap();
+        assert!(matches!(cli.command, Command::Profile { weights: None }));
+        assert_eq!(
+            cli.overrides
+                .config()
+                .unwrap()
+                .schedule
+                .games_per_generation,
+            8
+        );
+        let cli = Cli::try_parse_from(["alpha-zero", "export", "charts", "runs/x"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Export {
+                format: ExportFormat::Charts,
+                trace_batch: 1,
+                ..
+            }
+        ));
+        let cli = Cli::try_parse_from([
+            "alpha-zero",
+            "export",
+            "torchscript",
+            "runs/x/checkpoints/07.safetensors",
+            "--trace-batch",
+            "16",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Export {
+                format: ExportFormat::Torchscript,
+                trace_batch: 16,
+                ..
+            }
+        ));
+        let cli = Cli::try_parse_from(["alpha-zero", "export", "parquet", "runs/x"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Export {
+                format: ExportFormat::Parquet,
+                ..
+            }
+        ));
+        let cli = Cli::try_parse_from([
+            "alpha-zero",
+            "import",
+            "model.safetensors",
+            "--mapping",
+            "names.toml",
+            "-o",
+            "imported.safetensors",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Import {
+                mapping: Some(_),
+                ..
+            }
+        ));
+        let cli = Cli::try_parse_from([
+            "alpha-zero",
+            "gtp",
+            "--game",
+            "gomoku15",
+            "--with",
+            "07.safetensors",
+            "--time-control",
+            "5",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Gtp {
+                time_control: Some(_),
+                ..
+            }
+        ));
+        let cli = Cli::try_parse_from([
+            "alpha-zero",
+            "uci",
+            "--game",
+            "chess",
+            "--with",
+            "07.safetensors",
+        ])
+        .unwrap();
+        assert!(matches!(cli.command, Command::Uci { .. }));
+        let cli = Cli::try_parse_from([
+            "alpha-zero",
+            "play-server",
+            "--game",
+            "gomoku15",
+            "--with",
+            "07.safetensors",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::PlayServer { ref listen, .. } if listen == "127.0.0.1:8080"
+        ));
+        let cli = Cli::try_parse_from([
+            "alpha-zero",
+            "eval-server",
+            "--models",
+            "runs",
+            "--listen",
+            "0.0.0.0:9000",
+            "--max-games",
+            "400",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::EvalServer {
+                ref listen,
+                max_games: Some(400),
+                max_simulations: None,
+                ..
+            } if listen == "0.0.0.0:9000"
+        ));
+        assert!(Cli::try_parse_from(["alpha-zero", "eval-server"]).is_err());
+
+        // A match needs an opponent, and analyses are of one kind
+        assert!(Cli::try_parse_from(["alpha-zero", "eval", "random"]).is_err());
+        assert!(Cli::try_parse_from([
+            "alpha-zero",
+            "analyze",
+            "moves.txt",
+            "--with",
+            "a.safetensors",
+            "--heatmaps",
+            "--versus",
+            "b.safetensors",
+        ])
+        .is_err());
+    }
+}
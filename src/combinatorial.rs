@@ -0,0 +1,66 @@
+mod chomp;
+mod nim;
+
+pub use chomp::*;
+pub use nim::*;
+
+// Tiny solved games: MCTS without any network has to find their optimal moves
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use proptest::{collection::vec, prelude::*};
+
+    use crate::alpha_zero::{argmax, Game, MonteCarloTree, UniformEvaluator};
+
+    use super::{Chomp, ChompSolver, Nim};
+
+    const SIMULATIONS: usize = 10_000;
+
+    fn search<TGame: Game>(state: TGame) -> (usize, f32) {
+        let mut tree = MonteCarloTree::new(state, UniformEvaluator);
+        block_on(tree.do_simulations(SIMULATIONS, 1.0));
+        (argmax(&tree.get_policy()), tree.get_root_value())
+    }
+
+    #[test]
+    fn solved_values() {
+        assert_eq!(Nim::new(vec![1, 2, 3]).value(), 0.0);
+        assert_eq!(Nim::new(vec![1, 2, 4]).value(), 1.0);
+
+        let mut solver = ChompSolver::new();
+        assert_eq!(solver.value(&Chomp::new(1, 1)), 0.0);
+        for (rows, columns) in [(1, 2), (2, 2), (3, 4), (4, 5)] {
+            assert_eq!(solver.value(&Chomp::new(rows, columns)), 1.0);
+        }
+        // Square bars are won by leaving an L with equal arms
+        assert_eq!(
+            solver.optimal_moves(&Chomp::new(3, 3)),
+            [super::ChompMove { row: 1, column: 1 }]
+        );
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(24))]
+
+        #[test]
+        fn mcts_finds_winning_nim_moves(heaps in vec(0..5usize, 2..=3)) {
+            let state = Nim::new(heaps);
+            prop_assume!(state.value() == 1.0);
+
+            let (best, value) = search(state.clone());
+            let moves = state.get_state().get_moves().unwrap();
+            prop_assert!(state.is_winning_move(&moves[best]), "{:?}", moves[best]);
+            prop_assert!(value > 0.5);
+        }
+
+        #[test]
+        fn mcts_finds_winning_chomp_moves(rows in 1..4usize, columns in 2..5usize) {
+            let state = Chomp::new(rows, columns);
+            let mut solver = ChompSolver::new();
+
+            let (best, _) = search(state.clone());
+            let moves = state.get_state().get_moves().unwrap();
+            prop_assert!(solver.optimal_moves(&state).contains(&moves[best]), "{:?}", moves[best]);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use crate::alpha_zero::{Game, MoveParameters, Perspective, Solver, TerminationState};
+
+// Chomp on a rectangular bar whose top-left square is poisoned. Taking a square removes
+// everything below and to the right of it; whoever is left with the poisoned square loses.
+// Rows are stored by length, which never increases going down.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Chomp {
+    rows: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChompMove {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl MoveParameters for ChompMove {
+    fn is_player_switch(&self) -> bool {
+        true
+    }
+}
+
+impl Chomp {
+    pub fn new(rows: usize, columns: usize) -> Self {
+        Self {
+            rows: vec![columns; rows],
+        }
+    }
+
+    pub fn rows(&self) -> &[usize] {
+        &self.rows
+    }
+}
+
+impl Game for Chomp {
+    type Move = ChompMove;
+
+    fn get_state(&self) -> TerminationState<Self::Move> {
+        let moves = self
+            .rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row, &len)| (0..len).map(move |column| ChompMove { row, column }))
+            .filter(|&m| m != ChompMove { row: 0, column: 0 })
+            .collect::<Vec<_>>();
+        if moves.is_empty() {
+            TerminationState::Terminal(0.0)
+        } else {
+            TerminationState::Moves(moves)
+        }
+    }
+
+    fn make_move(&self, m: &Self::Move) -> Self {
+        let mut rows = self.rows.clone();
+        for len in &mut rows[m.row..] {
+            *len = (*len).min(m.column);
+        }
+        while rows.last() == Some(&0) {
+            rows.pop();
+        }
+        Self { rows }
+    }
+
+    fn is_legal(&self, m: &Self::Move) -> bool {
+        (m.row, m.column) != (0, 0) && self.rows.get(m.row).is_some_and(|&len| m.column < len)
+    }
+}
+
+// Exhaustive solver. Any bar larger than the poisoned square is a first-player win by
+// strategy stealing, but the winning moves themselves have no closed form.
+#[derive(Default)]
+pub struct ChompSolver {
+    cache: HashMap<Chomp, f32>,
+}
+
+impl ChompSolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn value(&mut self, state: &Chomp) -> f32 {
+        if let Some(&v) = self.cache.get(state) {
+            return v;
+        }
+        let value = match state.get_state() {
+            TerminationState::Terminal(v) => v,
+            TerminationState::Moves(moves) => moves
+                .iter()
+                .map(|m| Perspective::after_move(m).convert(self.value(&state.make_move(m))))
+                .fold(0.0, f32::max),
+        };
+        self.cache.insert(state.clone(), value);
+        value
+    }
+
+    pub fn optimal_moves(&mut self, state: &Chomp) -> Vec<ChompMove> {
+        let best = self.value(state);
+        let moves = state.get_state().get_moves().unwrap_or_default();
+        moves
+            .into_iter()
+            .filter(|m| Perspective::after_move(m).convert(self.value(&state.make_move(m))) == best)
+            .collect()
+    }
+}
+
+impl Solver<Chomp> for ChompSolver {
+    fn value(&mut self, state: &Chomp) -> f32 {
+        ChompSolver::value(self, state)
+    }
+}
@@ -0,0 +1,84 @@
+use crate::alpha_zero::{Game, MoveParameters, Solver, TerminationState};
+
+// Normal-play Nim: take any number of objects from one heap, whoever takes the last one wins
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Nim {
+    heaps: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NimMove {
+    pub heap: usize,
+    pub take: usize,
+}
+
+impl MoveParameters for NimMove {
+    fn is_player_switch(&self) -> bool {
+        true
+    }
+}
+
+impl Nim {
+    pub fn new(heaps: Vec<usize>) -> Self {
+        Self { heaps }
+    }
+
+    pub fn heaps(&self) -> &[usize] {
+        &self.heaps
+    }
+
+    pub fn nim_sum(&self) -> usize {
+        self.heaps.iter().fold(0, |acc, h| acc ^ h)
+    }
+
+    // Game-theoretic value for the player to move: lost exactly when the nim-sum is 0
+    pub fn value(&self) -> f32 {
+        if self.nim_sum() != 0 {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_winning_move(&self, m: &NimMove) -> bool {
+        self.make_move(m).nim_sum() == 0
+    }
+}
+
+impl Game for Nim {
+    type Move = NimMove;
+
+    fn get_state(&self) -> TerminationState<Self::Move> {
+        let moves = self
+            .heaps
+            .iter()
+            .enumerate()
+            .flat_map(|(heap, &size)| (1..=size).map(move |take| NimMove { heap, take }))
+            .collect::<Vec<_>>();
+        if moves.is_empty() {
+            TerminationState::Terminal(0.0)
+        } else {
+            TerminationState::Moves(moves)
+        }
+    }
+
+    fn make_move(&self, m: &Self::Move) -> Self {
+        let mut heaps = self.heaps.clone();
+        heaps[m.heap] -= m.take;
+        Self { heaps }
+    }
+
+    fn is_legal(&self, m: &Self::Move) -> bool {
+        m.take > 0 && self.heaps.get(m.heap).is_some_and(|&h| m.take <= h)
+    }
+}
+
+// Nim is solved by the nim-sum alone
+#[derive(Clone, Copy, Default)]
+pub struct NimSolver;
+
+impl Solver<Nim> for NimSolver {
+    fn value(&mut self, state: &Nim) -> f32 {
+        state.value()
+    }
+}
@@ -0,0 +1,755 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alpha_zero::{
+        config_hash, AmpConfig, AmpMode, DeviceSetting, EarlyStoppingConfig, GatingConfig,
+        LossConfig, LrDecay, LrSchedule, MatchConfig, SampleWeighting, Seed, TimeControl,
+        TrainConfig,
+    },
+    sweep::Hyperparameters,
+};
+
+// What an experiment is set up with, read from a TOML, YAML or JSON file by its extension, the
+// command line overriding it. Sections and fields a file leaves out keep their default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Name in the game registry
+    pub game: String,
+    // Of every net, `auto` by default
+    pub device: DeviceSetting,
+    pub network: NetworkConfig,
+    pub search: SearchConfig,
+    pub executor: ExecutorConfig,
+    pub trainer: TrainerConfig,
+    pub schedule: ScheduleConfig,
+    pub evaluation: EvaluationConfig,
+}
+
+// Size of the residual tower, for games whose net has one. Left out, the game's default.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    pub blocks: Option<usize>,
+    pub channels: Option<i64>,
+    // Of the value head
+    pub value_hidden: Option<i64>,
+}
+
+// Of the search, in self-play as well as in matches and analyses
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    pub simulations: usize,
+    pub c_puct: f32,
+}
+
+// Of the executor batching self-play's evaluations
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecutorConfig {
+    // Games played at the same time
+    pub parallelism: usize,
+    pub batch_size: usize,
+    // Longest a partial batch waits for more evaluations
+    pub max_wait_ms: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrainerConfig {
+    // Of the first generation after the warmup, decayed from there
+    pub lr: f64,
+    // Generations over which the rate grows linearly up to `lr`
+    pub lr_warmup: usize,
+    pub lr_decay: LrDecay,
+    pub batch_size: usize,
+    // Of the squared L2 norm of the weights added to the loss
+    pub weight_decay: f64,
+    // Most global norm of the gradients, not clipped if left out
+    pub grad_clip_norm: Option<f64>,
+    pub loss: LossConfig,
+    // Renormalizes the predicted policies over the legal moves before the loss
+    pub mask_illegal_moves: bool,
+    pub sample_weighting: SampleWeighting,
+    // Prioritized replay exponents, of the priorities and of the importance weights
+    pub priority_alpha: f32,
+    pub priority_beta: f32,
+    // Of the exponential moving average of the weights, 0 disabling it
+    pub ema_decay: f64,
+    // Share of the self-play games held out to measure overfitting on
+    pub validation_fraction: f64,
+    // Mixed precision, on CUDA devices only, training in fp32 on others
+    pub amp: AmpMode,
+    // Positions the replay buffer holds
+    pub replay_buffer: usize,
+    // Batches prepared ahead of the optimizer
+    pub prefetch: usize,
+    // Passes over the augmented positions a generation adds
+    pub epochs_per_generation: usize,
+    // Optimizer steps per generation instead of the passes
+    pub steps_per_generation: Option<usize>,
+    // Checkpoints kept besides the one with the best Elo
+    pub keep_checkpoints: usize,
+    // Root of every random choice of the run, random if left out
+    pub seed: Option<u64>,
+    // Ends training once the metric stops improving, before `schedule.generations` if
+    // there are some. Training runs until then if left out.
+    pub early_stopping: Option<EarlyStoppingConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScheduleConfig {
+    // Self-play games the learner waits for before training a generation
+    pub games_per_generation: usize,
+    // Training runs forever if left out
+    pub generations: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EvaluationConfig {
+    // Of a match, or of every pair of an arena
+    pub games: usize,
+    // Most games a new generation plays against self-play's weights to replace them
+    pub gating_games: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            game: "gomoku".to_owned(),
+            device: DeviceSetting::default(),
+            network: NetworkConfig::default(),
+            search: SearchConfig::default(),
+            executor: ExecutorConfig::default(),
+            trainer: TrainerConfig::default(),
+            schedule: ScheduleConfig::default(),
+            evaluation: EvaluationConfig::default(),
+        }
+    }
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        let hyperparameters = Hyperparameters::default();
+        Self {
+            simulations: hyperparameters.simulations,
+            c_puct: hyperparameters.c_puct,
+        }
+    }
+}
+
+impl Default for ExecutorConfig {
+    fn default() -> Self {
+        Self {
+            parallelism: 192,
+            batch_size: 128,
+            max_wait_ms: 100,
+        }
+    }
+}
+
+impl Default for TrainerConfig {
+    // The trainer's own defaults, but for an EMA and a validation set
+    fn default() -> Self {
+        let hyperparameters = Hyperparameters::default();
+        let train = TrainConfig::default();
+        Self {
+            lr: hyperparameters.lr,
+            lr_warmup: 0,
+            lr_decay: LrDecay::Constant,
+            batch_size: hyperparameters.batch_size,
+            weight_decay: train.weight_decay,
+            grad_clip_norm: train.grad_clip_norm,
+            loss: train.loss,
+            mask_illegal_moves: train.mask_illegal_moves,
+            sample_weighting: train.sample_weighting,
+            priority_alpha: train.priority_alpha,
+            priority_beta: train.priority_beta,
+            ema_decay: 0.999,
+            validation_fraction: 0.05,
+            amp: AmpMode::Off,
+            replay_buffer: 250_000,
+            prefetch: train.prefetch,
+            epochs_per_generation: train.epochs_per_generation,
+            steps_per_generation: None,
+            keep_checkpoints: train.keep_checkpoints,
+            seed: None,
+            early_stopping: None,
+        }
+    }
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            games_per_generation: 600,
+            generations: None,
+        }
+    }
+}
+
+impl Default for EvaluationConfig {
+    fn default() -> Self {
+        Self {
+            games: MatchConfig::default().games,
+            gating_games: GatingConfig::default().games.games,
+        }
+    }
+}
+
+impl NetworkConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Blocks, channels and hidden units of the value head, the net's own where left out
+    pub fn tower(&self, blocks: usize, channels: i64, value_hidden: i64) -> (usize, i64, i64) {
+        (
+            self.blocks.unwrap_or(blocks),
+            self.channels.unwrap_or(channels),
+            self.value_hidden.unwrap_or(value_hidden),
+        )
+    }
+}
+
+impl ExecutorConfig {
+    pub fn max_wait(&self) -> Duration {
+        Duration::from_millis(self.max_wait_ms)
+    }
+}
+
+impl TrainerConfig {
+    pub fn lr_schedule(&self) -> LrSchedule {
+        LrSchedule {
+            base_lr: self.lr,
+            warmup: self.lr_warmup,
+            decay: self.lr_decay,
+        }
+    }
+
+    pub fn ema_decay(&self) -> Option<f64> {
+        Some(self.ema_decay).filter(|&decay| decay > 0.0)
+    }
+
+    // With the default loss scaling. bf16 is turned down by `Config::validate`.
+    pub fn amp(&self) -> Option<AmpConfig> {
+        match self.amp {
+            AmpMode::Fp16 => Some(AmpConfig::default()),
+            AmpMode::Off | AmpMode::Bf16 => None,
+        }
+    }
+
+    pub fn seed(&self) -> Option<Seed> {
+        self.seed.map(Seed)
+    }
+}
+
+impl Config {
+    // Validated, see `validate`
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let config: Self = match extension {
+            "toml" => toml::from_str(&text).map_err(anyhow::Error::from),
+            "yaml" | "yml" => serde_yaml::from_str(&text).map_err(anyhow::Error::from),
+            "json" => serde_json::from_str(&text).map_err(anyhow::Error::from),
+            _ => anyhow::bail!(
+                "Unknown format of {}, expected .toml, .yaml or .json",
+                path.display()
+            ),
+        }
+        .with_context(|| format!("Invalid config {}", path.display()))?;
+        config
+            .validate()
+            .with_context(|| format!("Invalid config {}", path.display()))?;
+        Ok(config)
+    }
+
+    // As TOML, whatever the extension, to snapshot the config of a run
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, toml::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    // Every setting out of its range at once, a line each
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut problems = vec![];
+        let mut check = |valid: bool, problem: String| {
+            if !valid {
+                problems.push(problem);
+            }
+        };
+        let NetworkConfig {
+            blocks,
+            channels,
+            value_hidden,
+        } = self.network;
+        if let Some(blocks) = blocks {
+            check(
+                blocks >= 1,
+                format!("network.blocks must be at least 1, got {blocks}"),
+            );
+        }
+        for (name, size) in [("channels", channels), ("value_hidden", value_hidden)] {
+            if let Some(size) = size {
+                check(
+                    size >= 1,
+                    format!("network.{name} must be at least 1, got {size}"),
+                );
+            }
+        }
+        let SearchConfig {
+            simulations,
+            c_puct,
+        } = self.search;
+        check(
+            simulations >= 1,
+            format!("search.simulations must be at least 1, got {simulations}"),
+        );
+        check(
+            c_puct > 0.0 && c_puct.is_finite(),
+            format!("search.c_puct must be positive, got {c_puct}"),
+        );
+        let ExecutorConfig {
+            parallelism,
+            batch_size,
+            ..
+        } = self.executor;
+        check(
+            (1..=parallelism).contains(&batch_size),
+            format!(
+                "executor.batch_size must be between 1 and executor.parallelism ({parallelism}), \
+                 got {batch_size}"
+            ),
+        );
+        let trainer = &self.trainer;
+        check(
+            trainer.lr > 0.0 && trainer.lr.is_finite(),
+            format!("trainer.lr must be positive, got {}", trainer.lr),
+        );
+        match trainer.lr_decay {
+            LrDecay::Constant => {}
+            LrDecay::Step { every, factor } => {
+                check(
+                    every >= 1,
+                    format!("trainer.lr_decay.every must be at least 1, got {every}"),
+                );
+                check(
+                    factor > 0.0 && factor <= 1.0,
+                    format!("trainer.lr_decay.factor must be in (0, 1], got {factor}"),
+                );
+            }
+            LrDecay::Cosine {
+                generations,
+                min_lr,
+            } => {
+                check(
+                    generations >= 1,
+                    format!("trainer.lr_decay.generations must be at least 1, got {generations}"),
+                );
+                check(
+                    (0.0..=trainer.lr).contains(&min_lr),
+                    format!(
+                        "trainer.lr_decay.min_lr must be between 0 and trainer.lr ({}), got \
+                         {min_lr}",
+                        trainer.lr
+                    ),
+                );
+            }
+        }
+        check(
+            trainer.batch_size >= 1,
+            format!(
+                "trainer.batch_size must be at least 1, got {}",
+                trainer.batch_size
+            ),
+        );
+        check(
+            (0.0..1.0).contains(&trainer.ema_decay),
+            format!(
+                "trainer.ema_decay must be in [0, 1), 0 disabling it, got {}",
+                trainer.ema_decay
+            ),
+        );
+        check(
+            (0.0..1.0).contains(&trainer.validation_fraction),
+            format!(
+                "trainer.validation_fraction must be in [0, 1), got {}",
+                trainer.validation_fraction
+            ),
+        );
+        check(
+            trainer.weight_decay >= 0.0 && trainer.weight_decay.is_finite(),
+            format!(
+                "trainer.weight_decay must be at least 0, got {}",
+                trainer.weight_decay
+            ),
+        );
+        if let Some(norm) = trainer.grad_clip_norm {
+            check(
+                norm > 0.0 && norm.is_finite(),
+                format!("trainer.grad_clip_norm must be positive or left out, got {norm}"),
+            );
+        }
+        let LossConfig {
+            value_weight,
+            policy_weight,
+            entropy_bonus,
+            ownership_weight,
+            soft_policy_weight,
+        } = trainer.loss;
+        for (name, weight) in [
+            ("value_weight", value_weight),
+            ("policy_weight", policy_weight),
+            ("entropy_bonus", entropy_bonus),
+            ("ownership_weight", ownership_weight),
+            ("soft_policy_weight", soft_policy_weight),
+        ] {
+            check(
+                weight >= 0.0 && weight.is_finite(),
+                format!("trainer.loss.{name} must be at least 0, got {weight}"),
+            );
+        }
+        let SampleWeighting {
+            recency_decay,
+            draw_weight,
+        } = trainer.sample_weighting;
+        check(
+            recency_decay > 0.0 && recency_decay <= 1.0,
+            format!(
+                "trainer.sample_weighting.recency_decay must be in (0, 1], 1 disabling it, got \
+                 {recency_decay}"
+            ),
+        );
+        check(
+            draw_weight >= 0.0 && draw_weight.is_finite(),
+            format!("trainer.sample_weighting.draw_weight must be at least 0, got {draw_weight}"),
+        );
+        check(
+            trainer.priority_alpha >= 0.0 && trainer.priority_alpha.is_finite(),
+            format!(
+                "trainer.priority_alpha must be at least 0, 0 sampling uniformly, got {}",
+                trainer.priority_alpha
+            ),
+        );
+        check(
+            (0.0..=1.0).contains(&trainer.priority_beta),
+            format!(
+                "trainer.priority_beta must be in [0, 1], got {}",
+                trainer.priority_beta
+            ),
+        );
+        for (name, value) in [
+            ("prefetch", trainer.prefetch),
+            ("epochs_per_generation", trainer.epochs_per_generation),
+            ("keep_checkpoints", trainer.keep_checkpoints),
+        ] {
+            check(
+                value >= 1,
+                format!("trainer.{name} must be at least 1, got {value}"),
+            );
+        }
+        check(
+            trainer.amp != AmpMode::Bf16,
+            "trainer.amp must be off or fp16, bf16 isn't supported by tch's autocast yet"
+                .to_owned(),
+        );
+        check(
+            trainer.replay_buffer >= trainer.batch_size,
+            format!(
+                "trainer.replay_buffer must hold at least a batch ({}), got {}",
+                trainer.batch_size, trainer.replay_buffer
+            ),
+        );
+        if let Some(EarlyStoppingConfig {
+            patience,
+            min_delta,
+            ..
+        }) = trainer.early_stopping
+        {
+            check(
+                patience >= 1,
+                format!("trainer.early_stopping.patience must be at least 1, got {patience}"),
+            );
+            check(
+                min_delta >= 0.0 && min_delta.is_finite(),
+                format!("trainer.early_stopping.min_delta must be at least 0, got {min_delta}"),
+            );
+        }
+        check(
+            self.schedule.games_per_generation >= 1,
+            "schedule.games_per_generation must be at least 1, got 0".to_owned(),
+        );
+        check(
+            self.schedule.generations != Some(0),
+            "schedule.generations must be at least 1 or left out to train forever, got 0"
+                .to_owned(),
+        );
+        check(
+            self.trainer.steps_per_generation != Some(0),
+            "trainer.steps_per_generation must be at least 1 or left out, got 0".to_owned(),
+        );
+        check(
+            self.evaluation.games >= 1,
+            "evaluation.games must be at least 1, got 0".to_owned(),
+        );
+        check(
+            self.evaluation.gating_games >= 1,
+            "evaluation.gating_games must be at least 1, got 0".to_owned(),
+        );
+        match problems.is_empty() {
+            true => Ok(()),
+            false => anyhow::bail!("{}", problems.join("\n")),
+        }
+    }
+
+    // Recorded in the checkpoints, to tell which experiment they belong to
+    pub fn hash(&self) -> u64 {
+        config_hash(self)
+    }
+
+    // With the ones a sweep varies replaced
+    pub fn with_hyperparameters(&self, hyperparameters: Hyperparameters) -> Self {
+        let mut config = self.clone();
+        config.trainer.lr = hyperparameters.lr;
+        config.trainer.batch_size = hyperparameters.batch_size;
+        config.search.simulations = hyperparameters.simulations;
+        config.search.c_puct = hyperparameters.c_puct;
+        config
+    }
+
+    pub fn hyperparameters(&self) -> Hyperparameters {
+        Hyperparameters {
+            lr: self.trainer.lr,
+            c_puct: self.search.c_puct,
+            simulations: self.search.simulations,
+            batch_size: self.trainer.batch_size,
+        }
+    }
+
+    // Shrunk to go through every stage of training once in well under a minute on a CPU: a
+    // handful of games of a few simulations, a single training step and one gating game
+    pub fn smoke(&self) -> Self {
+        let mut config = self.clone();
+        config.device = DeviceSetting::Cpu;
+        config.search.simulations = 4;
+        config.executor = ExecutorConfig {
+            parallelism: 4,
+            batch_size: 4,
+            max_wait_ms: 10,
+        };
+        config.trainer.batch_size = 64;
+        config.trainer.replay_buffer = config.trainer.replay_buffer.max(64);
+        config.trainer.steps_per_generation = Some(1);
+        config.schedule = ScheduleConfig {
+            games_per_generation: 4,
+            generations: Some(1),
+        };
+        config.evaluation.gating_games = 1;
+        config
+    }
+
+    // Of the learner, writing its checkpoints to `checkpoint_dir`
+    pub fn train_config(&self, checkpoint_dir: PathBuf) -> TrainConfig {
+        let trainer = &self.trainer;
+        TrainConfig {
+            lr_schedule: trainer.lr_schedule(),
+            batch_size: trainer.batch_size,
+            epochs_per_generation: trainer.epochs_per_generation,
+            steps_per_generation: trainer.steps_per_generation,
+            loss: trainer.loss,
+            mask_illegal_moves: trainer.mask_illegal_moves,
+            weight_decay: trainer.weight_decay,
+            grad_clip_norm: trainer.grad_clip_norm,
+            ema_decay: trainer.ema_decay(),
+            amp: trainer.amp(),
+            priority_alpha: trainer.priority_alpha,
+            priority_beta: trainer.priority_beta,
+            sample_weighting: trainer.sample_weighting,
+            prefetch: trainer.prefetch,
+            validation_fraction: trainer.validation_fraction,
+            seed: trainer.seed(),
+            checkpoint_dir,
+            keep_checkpoints: trainer.keep_checkpoints,
+            early_stopping: trainer.early_stopping,
+            gating: Some(self.gating()),
+            experiment_hash: Some(self.hash()),
+        }
+    }
+
+    // Searching like self-play
+    pub fn gating(&self) -> GatingConfig {
+        let default = GatingConfig::default();
+        GatingConfig {
+            games: MatchConfig {
+                games: self.evaluation.gating_games,
+                sprt: default.games.sprt,
+                ..self.match_config(None)
+            },
+            ..default
+        }
+    }
+
+    // Nets search as long as the clock lets them under a time control
+    pub fn match_config(&self, time_control: Option<TimeControl>) -> MatchConfig {
+        MatchConfig {
+            games: self.evaluation.games,
+            simulations: match time_control {
+                Some(_) => usize::MAX,
+                None => self.search.simulations,
+            },
+            c_puct: self.search.c_puct,
+            time_control,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        alpha_zero::{EarlyStoppingConfig, LrDecay, LrSchedule, StopMetric, TimeControl},
+        sweep::Hyperparameters,
+    };
+
+    use super::Config;
+
+    #[test]
+    fn partial_files_keep_defaults() {
+        let path = |extension| {
+            std::env::temp_dir().join(format!("config-{}.{extension}", std::process::id()))
+        };
+        let toml = path("toml");
+        std::fs::write(
+            &toml,
+            "game = \"tictactoe\"\n[search]\nsimulations = 100\n[network]\nblocks = 2\n",
+        )
+        .unwrap();
+        let config = Config::load(&toml).unwrap();
+        assert_eq!(config.game, "tictactoe");
+        assert_eq!(config.network.blocks, Some(2));
+        assert_eq!(
+            config.hyperparameters(),
+            Hyperparameters {
+                simulations: 100,
+                ..Default::default()
+            }
+        );
+        assert_eq!(config.schedule.games_per_generation, 600);
+        assert_eq!(config.match_config(None).simulations, 100);
+        let timed = config.match_config(Some(TimeControl::PerMove(Duration::from_secs(1))));
+        assert_eq!(timed.simulations, usize::MAX);
+
+        // Snapshots read back the same, whatever the format they were read from
+        let yaml = path("yaml");
+        std::fs::write(
+            &yaml,
+            "game: tictactoe\nsearch:\n  simulations: 100\nnetwork:\n  blocks: 2\n",
+        )
+        .unwrap();
+        assert_eq!(Config::load(&yaml).unwrap(), config);
+        config.save(&toml).unwrap();
+        assert_eq!(Config::load(&toml).unwrap(), config);
+        assert_eq!(config.hash(), Config::load(&toml).unwrap().hash());
+        assert_ne!(config.hash(), Config::default().hash());
+
+        // Schedules as tables of their kind
+        std::fs::write(
+            &toml,
+            "[trainer]\nlr = 0.01\nlr_warmup = 2\namp = \"fp16\"\n\
+             [trainer.lr_decay]\nkind = \"cosine\"\ngenerations = 10\nmin_lr = 0.001\n\
+             [trainer.early_stopping]\nmetric = \"loss\"\npatience = 3\n\
+             [trainer.loss]\nentropy_bonus = 0.01\n",
+        )
+        .unwrap();
+        let scheduled = Config::load(&toml).unwrap();
+        assert_eq!(
+            scheduled.trainer.lr_schedule(),
+            LrSchedule {
+                base_lr: 0.01,
+                warmup: 2,
+                decay: LrDecay::Cosine {
+                    generations: 10,
+                    min_lr: 0.001,
+                },
+            }
+        );
+        assert!(scheduled.trainer.amp().is_some());
+        assert_eq!(
+            scheduled.trainer.early_stopping,
+            Some(EarlyStoppingConfig {
+                metric: StopMetric::Loss,
+                patience: 3,
+                min_delta: 0.0,
+            })
+        );
+        // Everything the trainer is set up with comes from the config
+        let train = scheduled.train_config("checkpoints".into());
+        assert_eq!(train.lr_schedule, scheduled.trainer.lr_schedule());
+        assert_eq!(train.loss.entropy_bonus, 0.01);
+        assert_eq!(train.loss.value_weight, 1.0);
+        assert_eq!(train.validation_fraction, 0.05);
+        assert_eq!(train.experiment_hash, Some(scheduled.hash()));
+        scheduled.save(&toml).unwrap();
+        assert_eq!(Config::load(&toml).unwrap(), scheduled);
+
+        let smoke = config.smoke();
+        smoke.validate().unwrap();
+        assert_eq!(smoke.game, "tictactoe");
+        assert_eq!(smoke.schedule.generations, Some(1));
+        assert_eq!(smoke.gating().games.games, 1);
+        assert_eq!(config.gating().games.simulations, 100);
+
+        std::fs::write(&toml, "[search]\nsimulation = 100\n").unwrap();
+        let error = format!("{:#}", Config::load(&toml).unwrap_err());
+        assert!(error.contains("unknown field `simulation`"), "{error}");
+        std::fs::write(
+            &yaml,
+            "search:\n  simulations: 0\ntrainer:\n  ema_decay: 1.0\n  \
+             lr_decay:\n    kind: step\n    every: 0\n    factor: 0.5\n  amp: bf16\n  \
+             early_stopping:\n    metric: elo\n    patience: 0\n  priority_beta: 2.0\n  \
+             loss:\n    policy_weight: -1.0\n",
+        )
+        .unwrap();
+        let error = format!("{:#}", Config::load(&yaml).unwrap_err());
+        assert!(
+            error.contains("search.simulations must be at least 1, got 0"),
+            "{error}"
+        );
+        assert!(
+            error.contains("trainer.ema_decay must be in [0, 1)"),
+            "{error}"
+        );
+        assert!(
+            error.contains("trainer.lr_decay.every must be at least 1, got 0"),
+            "{error}"
+        );
+        assert!(error.contains("trainer.amp must be off or fp16"), "{error}");
+        assert!(
+            error.contains("trainer.early_stopping.patience must be at least 1, got 0"),
+            "{error}"
+        );
+        assert!(
+            error.contains("trainer.priority_beta must be in [0, 1], got 2"),
+            "{error}"
+        );
+        assert!(
+            error.contains("trainer.loss.policy_weight must be at least 0, got -1"),
+            "{error}"
+        );
+        std::fs::remove_file(&toml).unwrap();
+        std::fs::remove_file(&yaml).unwrap();
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tracing::warn;
+
+use crate::{
+    http::{Request, Response},
+    metrics::MetricsSink,
+};
+
+// Rendered games shown, the most recent first
+const RECENT_GAMES: usize = 6;
+// Of the charts, in pixels
+const CHART_WIDTH: usize = 320;
+const CHART_HEIGHT: usize = 140;
+// Seconds between reloads of the page
+const REFRESH: usize = 15;
+
+// Self-play of the generation being played
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Progress {
+    pub generation: usize,
+    pub games: usize,
+    pub games_per_generation: usize,
+}
+
+#[derive(Default, Serialize)]
+struct DashboardState {
+    // Every scalar reported so far, by tag, as `(step, value)`
+    series: BTreeMap<String, Vec<(usize, f64)>>,
+    progress: Option<Progress>,
+}
+
+// A web page following a training run: a chart of every scalar of the metrics, like the
+// losses, the Elo and the executor's batch fill, self-play's progress and the most recent
+// games rendered into the run's games directory. `/data.json` has the same as JSON.
+pub struct Dashboard {
+    state: Arc<Mutex<DashboardState>>,
+    addr: SocketAddr,
+}
+
+impl Dashboard {
+    pub async fn bind(addr: impl ToSocketAddrs, games: PathBuf) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(DashboardState::default()));
+        let shared = state.clone();
+        tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(err) => {
+                        warn!("Failed to accept a dashboard connection: {err}");
+                        continue;
+                    }
+                };
+                let (state, games) = (shared.clone(), games.clone());
+                tokio::spawn(async move {
+                    if let Err(err) = serve(stream, &state, &games).await {
+                        warn!("Failed to serve the dashboard: {err:#}");
+                    }
+                });
+            }
+        });
+        Ok(Self { state, addr })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // For `Metrics::with`, to chart what the run reports
+    pub fn sink(&self) -> DashboardSink {
+        DashboardSink(self.state.clone())
+    }
+
+    pub fn set_progress(&self, progress: Progress) {
+        self.state.lock().unwrap().progress = Some(progress);
+    }
+}
+
+pub struct DashboardSink(Arc<Mutex<DashboardState>>);
+
+impl MetricsSink for DashboardSink {
+    fn scalar(&mut self, tag: &str, step: usize, value: f64) -> std::io::Result<()> {
+        let mut state = self.0.lock().unwrap();
+        state
+            .series
+            .entry(tag.to_owned())
+            .or_default()
+            .push((step, value));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// A single request per connection
+async fn serve(
+    mut stream: TcpStream,
+    state: &Mutex<DashboardState>,
+    games: &Path,
+) -> anyhow::Result<()> {
+    let request = Request::read(&mut stream).await?;
+    let response = match request.method.as_str() {
+        "GET" => respond(state, games, &request.path),
+        _ => Response::text("405 Method Not Allowed", "Only GET is supported"),
+    };
+    response.write(&mut stream).await
+}
+
+fn respond(state: &Mutex<DashboardState>, games: &Path, path: &str) -> Response {
+    match path {
+        "/" => Response::ok(
+            "text/html; charset=utf-8",
+            page(&state.lock().unwrap(), &recent_games(games)),
+        ),
+        "/data.json" => match serde_json::to_vec(&*state.lock().unwrap()) {
+            Ok(json) => Response::ok("application/json", json),
+            Err(_) => not_found(),
+        },
+        _ => {
+            // Only the files that are listed, so that nothing outside the directory is served
+            let Some(name) = path.strip_prefix("/games/") else {
+                return not_found();
+            };
+            if !recent_games(games).iter().any(|game| game == name) {
+                return not_found();
+            }
+            match std::fs::read(games.join(name)) {
+                Ok(image) => Response::ok(content_type(name), image),
+                Err(_) => not_found(),
+            }
+        }
+    }
+}
+
+fn not_found() -> Response {
+    Response::text("404 Not Found", "Not found")
+}
+
+fn content_type(name: &str) -> &'static str {
+    match Path::new(name).extension().and_then(|e| e.to_str()) {
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+}
+
+// Names of the directory's images, the latest first. The training loop names them after the
+// generation, so the names sort by age.
+fn recent_games(games: &Path) -> Vec<String> {
+    let mut names = std::fs::read_dir(games)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| content_type(name).starts_with("image/"))
+        .collect::<Vec<_>>();
+    names.sort_unstable_by(|a, b| b.cmp(a));
+    names.truncate(RECENT_GAMES);
+    names
+}
+
+fn page(state: &DashboardState, games: &[String]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><meta http-equiv=\"refresh\" \
+         content=\"{REFRESH}\"><title>Training</title></head>\n<body style=\"font-family: \
+         sans-serif\">\n<h1>Training</h1>\n"
+    );
+    if let Some(progress) = state.progress {
+        let _ = writeln!(
+            html,
+            "<p>Generation {}: {} of {} games played</p>",
+            progress.generation, progress.games, progress.games_per_generation
+        );
+    }
+    for (tag, points) in &state.series {
+        html += &chart(tag, points);
+    }
+    if !games.is_empty() {
+        html += "<h2>Recent games</h2>\n";
+        for name in games {
+            let _ = writeln!(
+                html,
+                "<figure><img src=\"/games/{name}\" alt=\"{name}\"><figcaption>{name}\
+                 </figcaption></figure>"
+            );
+        }
+    }
+    html += "</body></html>\n";
+    html
+}
+
+// A line chart of the series, scaled to its range
+fn chart(tag: &str, points: &[(usize, f64)]) -> String {
+    let (low, high) = points
+        .iter()
+        .map(|&(_, value)| value)
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), value| {
+            (low.min(value), high.max(value))
+        });
+    let (first, last) = (
+        points.first().map_or(0, |p| p.0),
+        points.last().map_or(0, |p| p.0),
+    );
+    let x = |step: usize| (step - first) as f64 * CHART_WIDTH as f64 / (last - first).max(1) as f64;
+    let y = |value: f64| match high > low {
+        true => (high - value) * CHART_HEIGHT as f64 / (high - low),
+        false => CHART_HEIGHT as f64 / 2.0,
+    };
+    let line = points
+        .iter()
+        .map(|&(step, value)| format!("{:.1},{:.1}", x(step), y(value)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "<figure style=\"display: inline-block\"><figcaption>{tag}: {:.4}</figcaption>\
+         <svg width=\"{CHART_WIDTH}\" height=\"{CHART_HEIGHT}\" style=\"border: 1px solid gray\">\
+         <polyline points=\"{line}\" fill=\"none\" stroke=\"steelblue\" stroke-width=\"2\"/>\
+         </svg><br>{low:.4} to {high:.4}, steps {first} to {last}</figure>\n",
+        points.last().map_or(f64::NAN, |p| p.1)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use crate::metrics::MetricsSink;
+
+    use super::{chart, Dashboard, Progress};
+
+    async fn get(dashboard: &Dashboard, path: &str) -> String {
+        let mut stream = TcpStream::connect(dashboard.local_addr()).await.unwrap();
+        stream
+            .write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn serves_metrics_and_games() {
+        let games = std::env::temp_dir().join(format!("dashboard-{}", std::process::id()));
+        std::fs::create_dir_all(&games).unwrap();
+        std::fs::write(games.join("00.00.png"), b"old").unwrap();
+        std::fs::write(games.join("01.00.png"), b"new").unwrap();
+        std::fs::write(games.join("notes.txt"), b"secret").unwrap();
+
+        let dashboard = Dashboard::bind("127.0.0.1:0", games.clone()).await.unwrap();
+        let mut sink = dashboard.sink();
+        sink.scalar("loss/value", 0, 0.5).unwrap();
+        sink.scalar("loss/value", 1, 0.25).unwrap();
+        dashboard.set_progress(Progress {
+            generation: 2,
+            games: 10,
+            games_per_generation: 600,
+        });
+
+        let page = get(&dashboard, "/").await;
+        assert!(page.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(page.contains("Generation 2: 10 of 600 games played"));
+        assert!(page.contains("loss/value: 0.2500"));
+        assert!(page.find("/games/01.00.png").unwrap() < page.find("/games/00.00.png").unwrap());
+        assert!(!page.contains("notes.txt"));
+
+        let data = get(&dashboard, "/data.json").await;
+        assert!(data.contains(r#""series":{"loss/value":[[0,0.5],[1,0.25]]}"#));
+        assert!(get(&dashboard, "/games/01.00.png")
+            .await
+            .ends_with("\r\n\r\nnew"));
+        for hidden in ["/games/notes.txt", "/games/../dashboard.rs", "/missing"] {
+            assert!(
+                get(&dashboard, hidden).await.starts_with("HTTP/1.1 404"),
+                "{hidden}"
+            );
+        }
+        std::fs::remove_dir_all(&games).unwrap();
+    }
+
+    #[test]
+    fn charts_scale_to_their_range() {
+        let svg = chart("elo/rating", &[(2, 0.0), (3, 50.0), (4, 100.0)]);
+        assert!(svg.contains("points=\"0.0,140.0 160.0,70.0 320.0,0.0\""));
+        assert!(svg.contains("0.0000 to 100.0000, steps 2 to 4"));
+        // A constant series is drawn across the middle
+        assert!(chart("x", &[(0, 1.0), (1, 1.0)]).contains("points=\"0.0,70.0 320.0,70.0\""));
+    }
+}
@@ -0,0 +1,501 @@
+use std::{
+    cell::RefCell,
+    future::Future,
+    net::SocketAddr,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::Context;
+use futures::{stream::FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tracing::{info, warn};
+
+use crate::{
+    alpha_zero::{Baseline, MatchConfig, MatchResult, MatchStats, Significance},
+    http::{Request, Response},
+};
+
+// A match to play, as posted to `/matches`. Contenders are a baseline as for `eval`, or a file
+// of weights named relative to the server's models directory. Unset settings are the server's.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct MatchRequest {
+    pub game: String,
+    pub contender: String,
+    pub opponent: String,
+    #[serde(default)]
+    pub games: Option<usize>,
+    #[serde(default)]
+    pub simulations: Option<usize>,
+    #[serde(default)]
+    pub c_puct: Option<f32>,
+    #[serde(default)]
+    pub parallelism: Option<usize>,
+}
+
+// Most a posted match may ask for, the matches being played one at a time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatchLimits {
+    pub games: usize,
+    // Of the nets and of the baselines that search
+    pub simulations: usize,
+    pub parallelism: usize,
+}
+
+impl Default for MatchLimits {
+    fn default() -> Self {
+        Self {
+            games: 1000,
+            simulations: 3200,
+            parallelism: 256,
+        }
+    }
+}
+
+impl MatchLimits {
+    pub fn check(&self, config: &MatchConfig) -> anyhow::Result<()> {
+        for (name, value, most) in [
+            ("games", config.games, self.games),
+            ("simulations", config.simulations, self.simulations),
+            ("parallelism", config.parallelism, self.parallelism),
+        ] {
+            anyhow::ensure!(
+                (1..=most).contains(&value),
+                "{name} must be between 1 and {most}, got {value}"
+            );
+        }
+        anyhow::ensure!(
+            config.c_puct > 0.0 && config.c_puct.is_finite(),
+            "c_puct must be positive, got {}",
+            config.c_puct
+        );
+        Ok(())
+    }
+}
+
+impl MatchRequest {
+    fn config(&self, defaults: &MatchConfig) -> MatchConfig {
+        MatchConfig {
+            games: self.games.unwrap_or(defaults.games),
+            simulations: self.simulations.unwrap_or(defaults.simulations),
+            c_puct: self.c_puct.unwrap_or(defaults.c_puct),
+            parallelism: self.parallelism.unwrap_or(defaults.parallelism),
+            ..*defaults
+        }
+    }
+}
+
+// The result of a finished match, from the contender's point of view
+#[derive(Clone, Debug, Serialize)]
+pub struct MatchReport {
+    pub total: MatchResult,
+    pub significance: Significance,
+    pub stats: MatchStats,
+    // As `eval` writes it to the match's summary.md
+    pub summary: String,
+}
+
+impl MatchReport {
+    pub fn new(stats: MatchStats, summary: String) -> Self {
+        let total = stats.total();
+        Self {
+            total,
+            significance: Significance::of(&total),
+            stats,
+            summary,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum JobState {
+    Queued,
+    Running,
+    Done { report: MatchReport },
+    Failed { error: String },
+}
+
+#[derive(Debug, Serialize)]
+struct Job {
+    id: usize,
+    request: MatchRequest,
+    // With the files of the contenders as paths on the server
+    #[serde(skip)]
+    resolved: MatchRequest,
+    // The settings the match is played with
+    config: MatchConfig,
+    #[serde(flatten)]
+    state: JobState,
+}
+
+// Plays the matches posted to `/matches` one after the other, sharing the machine's GPU, and
+// keeps every job's report for `/matches/<id>`. `play` runs a job's match on the server's
+// task, like `play_head_to_head` on the request's game, with its contenders' files as paths
+// under `models`.
+pub struct EvalService<F> {
+    games: Vec<String>,
+    models: PathBuf,
+    defaults: MatchConfig,
+    limits: MatchLimits,
+    play: F,
+    jobs: RefCell<Vec<Job>>,
+}
+
+impl<F, Fut> EvalService<F>
+where
+    F: Fn(MatchRequest, MatchConfig) -> Fut,
+    Fut: Future<Output = anyhow::Result<MatchReport>>,
+{
+    // Requests for games that aren't among `games` are turned down
+    pub fn new(games: Vec<String>, models: PathBuf, defaults: MatchConfig, play: F) -> Self {
+        Self {
+            games,
+            models,
+            defaults,
+            limits: MatchLimits::default(),
+            play,
+            jobs: RefCell::new(vec![]),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: MatchLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    // Serves the connections until the listener fails. They are served on the current task,
+    // taking turns with the match being played.
+    pub async fn serve(&self, listener: TcpListener) -> anyhow::Result<()> {
+        let mut connections = FuturesUnordered::new();
+        // At most one
+        let mut matches = FuturesUnordered::new();
+        loop {
+            if matches.is_empty() {
+                if let Some((id, request, config)) = self.start_next() {
+                    matches.push(async move { (id, (self.play)(request, config).await) });
+                }
+            }
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, peer) = match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            warn!("Failed to accept a connection: {e}");
+                            continue;
+                        }
+                    };
+                    connections.push(async move { (peer, self.connection(stream).await) });
+                }
+                Some((peer, result)) = connections.next(), if !connections.is_empty() => {
+                    if let Err(e) = result {
+                        warn!(%peer, "Connection failed: {e:#}");
+                    }
+                }
+                Some((id, result)) = matches.next(), if !matches.is_empty() => {
+                    self.finish(id, result);
+                }
+            }
+        }
+    }
+
+    pub async fn bind_and_serve(&self, addr: impl ToSocketAddrs) -> anyhow::Result<()> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr: SocketAddr = listener.local_addr()?;
+        info!(%addr, "Accepting matches at http://{addr}/matches");
+        self.serve(listener).await
+    }
+
+    fn start_next(&self) -> Option<(usize, MatchRequest, MatchConfig)> {
+        let mut jobs = self.jobs.borrow_mut();
+        let job = jobs
+            .iter_mut()
+            .find(|job| matches!(job.state, JobState::Queued))?;
+        job.state = JobState::Running;
+        info!(
+            id = job.id,
+            game = job.request.game,
+            "Playing {} against {}",
+            job.request.contender,
+            job.request.opponent
+        );
+        Some((job.id, job.resolved.clone(), job.config))
+    }
+
+    fn finish(&self, id: usize, result: anyhow::Result<MatchReport>) {
+        let state = match result {
+            Ok(report) => {
+                info!(id, score = report.total.score(), "Finished the match");
+                JobState::Done { report }
+            }
+            Err(e) => {
+                warn!(id, "The match failed: {e:#}");
+                JobState::Failed {
+                    error: format!("{e:#}"),
+                }
+            }
+        };
+        self.jobs.borrow_mut()[id].state = state;
+    }
+
+    // A single request per connection
+    async fn connection(&self, mut stream: TcpStream) -> anyhow::Result<()> {
+        let request = Request::read(&mut stream).await?;
+        let response = match request.path.is_empty() {
+            true => Response::json_error("400 Bad Request", "Expected a request line"),
+            false => self.respond(&request.method, &request.path, &request.body),
+        };
+        response.write(&mut stream).await
+    }
+
+    fn respond(&self, method: &str, path: &str, body: &[u8]) -> Response {
+        match (method, path) {
+            ("POST", "/matches") => self.submit(body),
+            ("GET", "/matches") => Response::json("200 OK", &*self.jobs.borrow()),
+            ("GET", _) => {
+                let job = path
+                    .strip_prefix("/matches/")
+                    .and_then(|id| id.parse::<usize>().ok());
+                let jobs = self.jobs.borrow();
+                match job.and_then(|id| jobs.get(id)) {
+                    Some(job) => Response::json("200 OK", job),
+                    None => Response::json_error("404 Not Found", "No such match"),
+                }
+            }
+            _ => Response::json_error("405 Method Not Allowed", "Expected GET or POST /matches"),
+        }
+    }
+
+    fn submit(&self, body: &[u8]) -> Response {
+        let request = match serde_json::from_slice::<MatchRequest>(body) {
+            Ok(request) => request,
+            Err(e) => {
+                return Response::json_error("400 Bad Request", &format!("Invalid match: {e}"))
+            }
+        };
+        if !self.games.contains(&request.game) {
+            let message = format!(
+                "Unknown game {}, expected one of: {}",
+                request.game,
+                self.games.join(", ")
+            );
+            return Response::json_error("400 Bad Request", &message);
+        }
+        let config = request.config(&self.defaults);
+        let resolved = self.limits.check(&config).and_then(|()| {
+            Ok(MatchRequest {
+                contender: self.contender(&request.contender)?,
+                opponent: self.contender(&request.opponent)?,
+                ..request.clone()
+            })
+        });
+        let resolved = match resolved {
+            Ok(resolved) => resolved,
+            Err(e) => return Response::json_error("400 Bad Request", &format!("{e:#}")),
+        };
+        let mut jobs = self.jobs.borrow_mut();
+        let job = Job {
+            id: jobs.len(),
+            config,
+            request,
+            resolved,
+            state: JobState::Queued,
+        };
+        let response = Response::json("202 Accepted", &job);
+        jobs.push(job);
+        response
+    }
+
+    // A baseline as it is, or the path of a file of weights under the models directory. Other
+    // files of the server are never read.
+    fn contender(&self, name: &str) -> anyhow::Result<String> {
+        if let Ok(baseline) = name.parse::<Baseline>() {
+            if let Baseline::UniformMcts(simulations) | Baseline::RolloutMcts(simulations) =
+                baseline
+            {
+                anyhow::ensure!(
+                    simulations <= self.limits.simulations,
+                    "{name} searches more than {} simulations",
+                    self.limits.simulations
+                );
+            }
+            return Ok(name.to_owned());
+        }
+        let relative = Path::new(name);
+        anyhow::ensure!(
+            relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_))),
+            "{name} is neither a baseline nor a file named relative to the models directory"
+        );
+        // Symbolic links can't lead out of the directory either
+        let models = self.models.canonicalize().with_context(|| {
+            format!(
+                "Failed to open the models directory {}",
+                self.models.display()
+            )
+        })?;
+        let path = models.join(relative);
+        let path = path
+            .canonicalize()
+            .ok()
+            .filter(|path| path.starts_with(&models) && path.is_file())
+            .with_context(|| format!("No model {name}"))?;
+        Ok(path.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::Value;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use crate::alpha_zero::{MatchConfig, MatchStats};
+
+    use super::{EvalService, MatchLimits, MatchReport, MatchRequest};
+
+    // The status and the JSON body of the response
+    async fn request(addr: std::net::SocketAddr, method: &str, path: &str, body: &str) -> Value {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{method} {path} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap();
+        serde_json::json!({ "status": status, "body": serde_json::from_str::<Value>(body).unwrap() })
+    }
+
+    #[tokio::test]
+    async fn matches_one_after_the_other() {
+        let dir = std::env::temp_dir().join(format!("eval-service-{}", std::process::id()));
+        let models = dir.join("models");
+        std::fs::create_dir_all(models.join("run")).unwrap();
+        std::fs::write(models.join("run/07.safetensors"), b"").unwrap();
+        std::fs::write(dir.join("secret.safetensors"), b"").unwrap();
+
+        // The contender wins every game, unless its opponent is greedy
+        let play = |request: MatchRequest, config: MatchConfig| async move {
+            anyhow::ensure!(request.opponent != "greedy", "The match failed");
+            assert!(Path::new(&request.contender).is_absolute());
+            let mut stats = MatchStats::default();
+            for i in 0..config.games {
+                stats.record(1.0, i % 2 == 0, None);
+            }
+            Ok(MatchReport::new(stats, String::new()))
+        };
+        let defaults = MatchConfig {
+            games: 4,
+            ..Default::default()
+        };
+        let limits = MatchLimits {
+            games: 10,
+            ..Default::default()
+        };
+        let service = EvalService::new(vec!["tictactoe".to_owned()], models, defaults, play)
+            .with_limits(limits);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = async {
+            let post = |body: &'static str| request(addr, "POST", "/matches", body);
+            let reply = post(r#"{"game": "chess"}"#).await;
+            assert_eq!(reply["status"], "400");
+            let reply = post(r#"{"game": "chess", "contender": "a", "opponent": "b"}"#).await;
+            assert_eq!(reply["status"], "400");
+            assert!(reply["body"]["error"]
+                .as_str()
+                .unwrap()
+                .contains("tictactoe"));
+
+            // Settings out of the limits, and files out of the models directory
+            for (body, error) in [
+                (
+                    r#"{"game": "tictactoe", "contender": "random", "opponent": "random",
+                        "parallelism": 0}"#,
+                    "parallelism must be between 1 and 256, got 0",
+                ),
+                (
+                    r#"{"game": "tictactoe", "contender": "random", "opponent": "random",
+                        "games": 11}"#,
+                    "games must be between 1 and 10, got 11",
+                ),
+                (
+                    r#"{"game": "tictactoe", "contender": "uniform-mcts:100000",
+                        "opponent": "random"}"#,
+                    "searches more than 3200 simulations",
+                ),
+                (
+                    r#"{"game": "tictactoe", "contender": "../secret.safetensors",
+                        "opponent": "random"}"#,
+                    "neither a baseline nor a file",
+                ),
+                (
+                    r#"{"game": "tictactoe", "contender": "run/08.safetensors",
+                        "opponent": "random"}"#,
+                    "No model run/08.safetensors",
+                ),
+            ] {
+                let reply = post(body).await;
+                assert_eq!(reply["status"], "400");
+                let message = reply["body"]["error"].as_str().unwrap();
+                assert!(message.contains(error), "{message}");
+            }
+
+            let reply = post(
+                r#"{"game": "tictactoe", "contender": "run/07.safetensors", "opponent": "random",
+                    "games": 6}"#,
+            )
+            .await;
+            assert_eq!(reply["status"], "202");
+            assert_eq!(reply["body"]["id"], 0);
+            assert_eq!(reply["body"]["config"]["games"], 6);
+            // As posted
+            assert_eq!(reply["body"]["request"]["contender"], "run/07.safetensors");
+            let reply = post(
+                r#"{"game": "tictactoe", "contender": "run/07.safetensors",
+                    "opponent": "greedy"}"#,
+            )
+            .await;
+            assert_eq!(reply["body"]["id"], 1);
+            assert_eq!(reply["body"]["config"]["games"], 4);
+
+            // Until both are played
+            let jobs = loop {
+                let reply = request(addr, "GET", "/matches", "").await;
+                let jobs = reply["body"].as_array().unwrap().clone();
+                if jobs
+                    .iter()
+                    .all(|job| job["status"] != "queued" && job["status"] != "running")
+                {
+                    break jobs;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            };
+            assert_eq!(jobs[0]["status"], "done");
+            assert_eq!(jobs[0]["report"]["total"]["wins"], 6);
+            assert_eq!(jobs[0]["report"]["stats"]["as_first"]["wins"], 3);
+            assert_eq!(jobs[1]["status"], "failed");
+            assert_eq!(jobs[1]["error"], "The match failed");
+
+            let reply = request(addr, "GET", "/matches/0", "").await;
+            assert_eq!(reply["body"]["report"]["significance"]["score"], 1.0);
+            assert_eq!(
+                request(addr, "GET", "/matches/2", "").await["status"],
+                "404"
+            );
+        };
+        tokio::select! {
+            result = service.serve(listener) => panic!("The server stopped: {result:?}"),
+            () = client => {}
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+use tch::Tensor;
+
+use crate::{
+    alpha_zero::{ActionEncoding, AlphaZeroAdapter, AlphaZeroNet, SymmetryTransform},
+    tictactoe::CellState,
+};
+
+use super::{GoColor, GoMove, GoState, GO_HISTORY};
+
+pub const GO_INPUT_PLANES: i64 = 2 * (GO_HISTORY as i64 + 1) + 1;
+
+// AlphaGo Zero's input: 8 planes of own stones (current position first), 8 of the
+// opponent's, and a constant plane that is 1 when black is to move. The policy is flat,
+// `N * N` points followed by the pass.
+pub struct GoAlphaZeroAdapter<const N: usize>;
+
+impl<const N: usize> ActionEncoding<GoState> for GoAlphaZeroAdapter<N> {
+    fn action_space_size() -> usize {
+        N * N + 1
+    }
+
+    fn move_to_index(m: &GoMove) -> usize {
+        match *m {
+            GoMove::Place(p) => p,
+            GoMove::Pass => N * N,
+        }
+    }
+
+    fn index_to_move(index: usize) -> GoMove {
+        match index == N * N {
+            true => GoMove::Pass,
+            false => GoMove::Place(index),
+        }
+    }
+}
+
+impl<const N: usize> GoAlphaZeroAdapter<N> {
+    // Applies a board transform to the points of a flat policy, the pass stays in place
+    fn on_points(transform: impl Fn(&Tensor) -> Tensor) -> impl Fn(&Tensor) -> Tensor {
+        move |policy| {
+            let mut shape = policy.size();
+            let points = policy.narrow(-1, 0, (N * N) as i64);
+            *shape.last_mut().unwrap() = N as i64;
+            shape.push(N as i64);
+            let points = transform(&points.view(shape.as_slice())).flatten(-2, -1);
+            Tensor::concat(&[points, policy.narrow(-1, (N * N) as i64, 1)], -1)
+        }
+    }
+}
+
+impl<const N: usize, Net: AlphaZeroNet> AlphaZeroAdapter<GoState, Net> for GoAlphaZeroAdapter<N> {
+    fn symmetries() -> Vec<SymmetryTransform> {
+        SymmetryTransform::dihedral_group()
+            .into_iter()
+            .map(|s| {
+                let (forward, inverse) = (s.clone(), s.clone());
+                SymmetryTransform::new(
+                    move |state| s.transform_state(state),
+                    Self::on_points(move |p| forward.transform_policy(p)),
+                    Self::on_points(move |p| inverse.inverse_policy(p)),
+                )
+            })
+            .collect()
+    }
+
+    fn convert_game_to_nn_input(state: &GoState) -> Tensor {
+        assert_eq!(state.config().size, N);
+        let (own, other) = match state.to_move() {
+            GoColor::Black => (CellState::X, CellState::O),
+            GoColor::White => (CellState::O, CellState::X),
+        };
+
+        let mut fld = vec![0f32; GO_INPUT_PLANES as usize * N * N];
+        for t in 0..=GO_HISTORY {
+            let Some(position) = state.past_position(t) else {
+                break;
+            };
+            for (p, &cell) in position.iter().enumerate() {
+                if cell == own {
+                    fld[t * N * N + p] = 1.;
+                } else if cell == other {
+                    fld[(GO_HISTORY + 1 + t) * N * N + p] = 1.;
+                }
+            }
+        }
+        if state.to_move() == GoColor::Black {
+            fld[2 * (GO_HISTORY + 1) * N * N..].fill(1.);
+        }
+        Tensor::from_slice(&fld).view([GO_INPUT_PLANES, N as i64, N as i64])
+    }
+
+    fn get_estimated_policy(policy: &Tensor, moves: &[GoMove]) -> Vec<f32> {
+        Self::decode_policy(policy, moves)
+    }
+
+    fn convert_policy_to_nn(policy: &[f32], moves: &[GoMove]) -> Tensor {
+        Self::encode_policy(policy, moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{ActionEncoding, Game},
+        go::{GoConfig, GoMove, GoState},
+    };
+
+    use super::GoAlphaZeroAdapter;
+
+    #[test]
+    fn action_encoding_round_trip() {
+        let state = GoState::new(GoConfig::default()).make_move(&GoMove::Place(40));
+        let moves = state.get_state().get_moves().unwrap();
+        assert_eq!(moves.len(), 9 * 9);
+        for m in &moves {
+            let index = GoAlphaZeroAdapter::<9>::move_to_index(m);
+            assert!(index < GoAlphaZeroAdapter::<9>::action_space_size());
+            assert_eq!(GoAlphaZeroAdapter::<9>::index_to_move(index), *m);
+        }
+    }
+}
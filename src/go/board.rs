@@ -0,0 +1,411 @@
+use crate::{
+    alpha_zero::{Game, MoveParameters, SgfGame, TerminationState},
+    tictactoe::CellState,
+};
+
+// Number of previous positions kept for the network input, besides the current one
+pub const GO_HISTORY: usize = 7;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GoColor {
+    Black,
+    White,
+}
+
+impl GoColor {
+    fn cell(self) -> CellState {
+        match self {
+            GoColor::Black => CellState::X,
+            GoColor::White => CellState::O,
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            GoColor::Black => GoColor::White,
+            GoColor::White => GoColor::Black,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GoConfig {
+    pub size: usize,
+    pub komi: f32,
+    // The game is scored as is after this many moves, passes included
+    pub max_moves: usize,
+}
+
+impl Default for GoConfig {
+    fn default() -> Self {
+        Self {
+            size: 9,
+            komi: 7.5,
+            max_moves: 9 * 9 * 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GoMove {
+    // Index of the point, `row * size + column`
+    Place(usize),
+    Pass,
+}
+
+impl MoveParameters for GoMove {
+    fn is_player_switch(&self) -> bool {
+        true
+    }
+}
+
+// Go with simple ko, no suicide and area scoring. The board is stored by color (black is
+// `X`) since komi makes the colors asymmetric. Two consecutive passes end the game.
+#[derive(Clone)]
+pub struct GoState {
+    config: GoConfig,
+    board: Vec<CellState>,
+    to_move: GoColor,
+    ko: Option<usize>,
+    passes: usize,
+    moves_played: usize,
+    // Previous positions, most recent first
+    history: Vec<Vec<CellState>>,
+}
+
+impl Default for GoState {
+    fn default() -> Self {
+        Self::new(GoConfig::default())
+    }
+}
+
+impl GoState {
+    pub fn new(config: GoConfig) -> Self {
+        Self {
+            config,
+            board: vec![CellState::Empty; config.size * config.size],
+            to_move: GoColor::Black,
+            ko: None,
+            passes: 0,
+            moves_played: 0,
+            history: vec![],
+        }
+    }
+
+    pub fn config(&self) -> &GoConfig {
+        &self.config
+    }
+
+    pub fn to_move(&self) -> GoColor {
+        self.to_move
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> CellState {
+        self.board[row * self.config.size + column]
+    }
+
+    // Position `moves_ago` moves back, empty before the start of the game
+    pub fn past_position(&self, moves_ago: usize) -> Option<&[CellState]> {
+        if moves_ago == 0 {
+            Some(&self.board)
+        } else {
+            self.history.get(moves_ago - 1).map(Vec::as_slice)
+        }
+    }
+
+    fn neighbours(&self, p: usize) -> impl Iterator<Item = usize> {
+        let n = self.config.size;
+        let (i, j) = (p / n, p % n);
+        [
+            (i > 0).then(|| p - n),
+            (i + 1 < n).then(|| p + n),
+            (j > 0).then(|| p - 1),
+            (j + 1 < n).then(|| p + 1),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    // Stones of the group at `p` and whether it has any liberty
+    fn group(&self, board: &[CellState], p: usize) -> (Vec<usize>, bool) {
+        let color = board[p];
+        let mut visited = vec![false; board.len()];
+        let mut stack = vec![p];
+        let mut stones = vec![];
+        let mut has_liberty = false;
+        visited[p] = true;
+        while let Some(cur) = stack.pop() {
+            stones.push(cur);
+            for nb in self.neighbours(cur) {
+                if board[nb] == CellState::Empty {
+                    has_liberty = true;
+                } else if board[nb] == color && !visited[nb] {
+                    visited[nb] = true;
+                    stack.push(nb);
+                }
+            }
+        }
+        (stones, has_liberty)
+    }
+
+    // Resulting board and the ko point, or `None` for suicide
+    fn place(&self, p: usize) -> Option<(Vec<CellState>, Option<usize>)> {
+        let own = self.to_move.cell();
+        let other = self.to_move.other().cell();
+
+        let mut board = self.board.clone();
+        board[p] = own;
+        let mut captured = vec![];
+        for nb in self.neighbours(p) {
+            if board[nb] == other {
+                let (stones, has_liberty) = self.group(&board, nb);
+                if !has_liberty {
+                    for s in stones {
+                        board[s] = CellState::Empty;
+                        captured.push(s);
+                    }
+                }
+            }
+        }
+
+        let (group, has_liberty) = self.group(&board, p);
+        if !has_liberty {
+            return None;
+        }
+        // A lone stone that captured a lone stone may not be taken back immediately
+        let ko = match captured.as_slice() {
+            &[single] if group.len() == 1 => {
+                let liberties = self
+                    .neighbours(p)
+                    .filter(|&nb| board[nb] == CellState::Empty)
+                    .count();
+                (liberties == 1).then_some(single)
+            }
+            _ => None,
+        };
+        Some((board, ko))
+    }
+
+    fn is_legal_point(&self, p: usize) -> bool {
+        p < self.board.len()
+            && self.board[p] == CellState::Empty
+            && self.ko != Some(p)
+            && self.place(p).is_some()
+    }
+
+    // Area score: stones plus empty regions bordered by a single color
+    pub fn score(&self) -> (usize, usize) {
+        let (mut black, mut white) = (0, 0);
+        let mut visited = vec![false; self.board.len()];
+        for p in 0..self.board.len() {
+            match self.board[p] {
+                CellState::X => black += 1,
+                CellState::O => white += 1,
+                CellState::Empty if !visited[p] => {
+                    let (mut size, mut borders_black, mut borders_white) = (0, false, false);
+                    let mut stack = vec![p];
+                    visited[p] = true;
+                    while let Some(cur) = stack.pop() {
+                        size += 1;
+                        for nb in self.neighbours(cur) {
+                            match self.board[nb] {
+                                CellState::X => borders_black = true,
+                                CellState::O => borders_white = true,
+                                CellState::Empty if !visited[nb] => {
+                                    visited[nb] = true;
+                                    stack.push(nb);
+                                }
+                                CellState::Empty => {}
+                            }
+                        }
+                    }
+                    match (borders_black, borders_white) {
+                        (true, false) => black += size,
+                        (false, true) => white += size,
+                        _ => {}
+                    }
+                }
+                CellState::Empty => {}
+            }
+        }
+        (black, white)
+    }
+}
+
+impl Game for GoState {
+    type Move = GoMove;
+
+    fn get_state(&self) -> TerminationState<Self::Move> {
+        if self.passes >= 2 || self.moves_played >= self.config.max_moves {
+            let (black, white) = self.score();
+            let margin = black as f32 - white as f32 - self.config.komi;
+            let black_score = if margin > 0. {
+                1.0
+            } else if margin < 0. {
+                0.0
+            } else {
+                0.5
+            };
+            return TerminationState::Terminal(match self.to_move {
+                GoColor::Black => black_score,
+                GoColor::White => 1.0 - black_score,
+            });
+        }
+
+        let mut moves = (0..self.board.len())
+            .filter(|&p| self.is_legal_point(p))
+            .map(GoMove::Place)
+            .collect::<Vec<_>>();
+        moves.push(GoMove::Pass);
+        TerminationState::Moves(moves)
+    }
+
+    fn make_move(&self, m: &Self::Move) -> Self {
+        let (board, ko, passes) = match *m {
+            GoMove::Place(p) => {
+                assert!(self.is_legal_point(p), "Illegal move {m:?}");
+                let (board, ko) = self.place(p).unwrap();
+                (board, ko, 0)
+            }
+            GoMove::Pass => (self.board.clone(), None, self.passes + 1),
+        };
+
+        let mut history = Vec::with_capacity(GO_HISTORY);
+        history.push(self.board.clone());
+        history.extend(self.history.iter().take(GO_HISTORY - 1).cloned());
+
+        Self {
+            config: self.config,
+            board,
+            to_move: self.to_move.other(),
+            ko,
+            passes,
+            moves_played: self.moves_played + 1,
+            history,
+        }
+    }
+
+    fn is_legal(&self, m: &Self::Move) -> bool {
+        self.passes < 2
+            && self.moves_played < self.config.max_moves
+            && match *m {
+                GoMove::Place(p) => self.is_legal_point(p),
+                GoMove::Pass => true,
+            }
+    }
+}
+
+impl SgfGame for GoState {
+    const GAME_TYPE: u32 = 1;
+
+    fn board_size(&self) -> usize {
+        self.config.size
+    }
+
+    fn move_point(&self, m: &Self::Move) -> Option<(usize, usize)> {
+        let n = self.config.size;
+        match *m {
+            GoMove::Place(p) => Some((p / n, p % n)),
+            GoMove::Pass => None,
+        }
+    }
+
+    fn point_move(&self, point: Option<(usize, usize)>) -> anyhow::Result<Self::Move> {
+        Ok(match point {
+            Some((row, column)) => GoMove::Place(row * self.config.size + column),
+            None => GoMove::Pass,
+        })
+    }
+
+    fn root_properties(&self) -> Vec<(&'static str, String)> {
+        vec![("KM", self.config.komi.to_string())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{Game, TerminationState},
+        tictactoe::CellState,
+    };
+
+    use super::{GoConfig, GoMove, GoState};
+
+    fn play(state: GoState, moves: &[(usize, usize)]) -> GoState {
+        let n = state.config().size;
+        moves.iter().fold(state, |state, &(i, j)| {
+            let m = GoMove::Place(i * n + j);
+            assert!(state.is_legal(&m), "({i}, {j})");
+            state.make_move(&m)
+        })
+    }
+
+    #[test]
+    fn go_capture_suicide_and_ko() {
+        // Black surrounds the white stone at (1, 1) and captures it
+        let state = play(
+            GoState::default(),
+            &[(0, 1), (1, 1), (1, 0), (8, 8), (1, 2), (8, 7)],
+        );
+        let state = play(state, &[(2, 1)]);
+        assert_eq!(state.get(1, 1), CellState::Empty);
+
+        // White can't play into the eye
+        assert!(!state.is_legal(&GoMove::Place(10)));
+
+        // Ko: black takes at (1, 2), white can't retake at (1, 1) right away
+        let state = play(
+            GoState::default(),
+            &[
+                (0, 1),
+                (0, 2),
+                (1, 0),
+                (1, 3),
+                (2, 1),
+                (2, 2),
+                (8, 8),
+                (1, 1),
+                (1, 2),
+            ],
+        );
+        assert_eq!(state.get(1, 1), CellState::Empty);
+        assert!(!state.is_legal(&GoMove::Place(10)));
+        let state = play(state, &[(8, 0), (8, 1)]);
+        assert!(state.is_legal(&GoMove::Place(10)));
+    }
+
+    #[test]
+    fn go_area_scoring() {
+        let config = GoConfig {
+            size: 4,
+            komi: 0.5,
+            max_moves: 100,
+        };
+        // Black walls off the left half, white the right one
+        let walls = [
+            (0, 1),
+            (0, 2),
+            (1, 1),
+            (1, 2),
+            (2, 1),
+            (2, 2),
+            (3, 1),
+            (3, 2),
+        ];
+        let state = play(GoState::new(config), &walls);
+        assert_eq!(state.score(), (8, 8));
+        let state = state.make_move(&GoMove::Pass);
+        assert!(state.get_state().get_terminal().is_none());
+        let state = state.make_move(&GoMove::Pass);
+        // Black to move loses by komi
+        assert_eq!(state.get_state(), TerminationState::Terminal(0.0));
+
+        let config = GoConfig {
+            komi: 0.0,
+            ..config
+        };
+        let state = play(GoState::new(config), &walls);
+        let state = state.make_move(&GoMove::Pass).make_move(&GoMove::Pass);
+        assert_eq!(state.get_state(), TerminationState::Terminal(0.5));
+    }
+}
@@ -0,0 +1,9 @@
+#[cfg(feature = "torch")]
+mod alpha_zero_adapter;
+mod board;
+mod renju;
+
+#[cfg(feature = "torch")]
+pub use alpha_zero_adapter::*;
+pub use board::*;
+pub use renju::*;
@@ -0,0 +1,224 @@
+use std::sync::Arc;
+
+use tch::Tensor;
+
+use crate::{
+    alpha_zero::{ActionEncoding, AlphaZeroAdapter, AlphaZeroNet, SymmetryTransform},
+    tictactoe::{CellState, TicTacToeMove},
+};
+
+use super::{GomokuMove, GomokuState, Stone};
+
+pub const GOMOKU_INPUT_PLANES: i64 = 4;
+
+// Input planes: black stones, white stones, a constant plane telling whether black places
+// the next stone and one telling whether the opening is still on. The policy is flat, `N * N`
+// cells followed by choosing black, choosing white and extending.
+pub struct GomokuAlphaZeroAdapter;
+
+impl<const N: usize> ActionEncoding<GomokuState<N>> for GomokuAlphaZeroAdapter {
+    fn action_space_size() -> usize {
+        N * N + 3
+    }
+
+    fn move_to_index(m: &GomokuMove) -> usize {
+        match *m {
+            GomokuMove::Place {
+                at: TicTacToeMove(i, j),
+                ..
+            } => i * N + j,
+            GomokuMove::Choose(Stone::Black) => N * N,
+            GomokuMove::Choose(Stone::White) => N * N + 1,
+            GomokuMove::Extend => N * N + 2,
+        }
+    }
+
+    // Whether a stone switches the player depends on the opening, look the cell up in the
+    // legal moves to get the exact move
+    fn index_to_move(index: usize) -> GomokuMove {
+        match index.checked_sub(N * N) {
+            None => GomokuMove::Place {
+                at: TicTacToeMove(index / N, index % N),
+                player_switch: true,
+            },
+            Some(0) => GomokuMove::Choose(Stone::Black),
+            Some(1) => GomokuMove::Choose(Stone::White),
+            Some(_) => GomokuMove::Extend,
+        }
+    }
+}
+
+impl GomokuAlphaZeroAdapter {
+    // Applies `transform` to the cells of flat policies as N×N planes, the opening choices
+    // keep their slots
+    fn transform_cells<const N: usize>(
+        policy: &Tensor,
+        transform: impl Fn(&Tensor) -> Tensor,
+    ) -> Tensor {
+        let cells = (N * N) as i64;
+        let mut shape = policy.size();
+        shape.pop();
+        let planes = [shape.as_slice(), &[N as i64, N as i64]].concat();
+        let flat = [shape.as_slice(), &[cells]].concat();
+        let transformed = transform(&policy.narrow(-1, 0, cells).reshape(planes.as_slice()));
+        Tensor::concat(
+            &[
+                transformed.reshape(flat.as_slice()),
+                policy.narrow(-1, cells, 3),
+            ],
+            -1,
+        )
+    }
+}
+
+impl<const N: usize, Net: AlphaZeroNet> AlphaZeroAdapter<GomokuState<N>, Net>
+    for GomokuAlphaZeroAdapter
+{
+    // The rules don't depend on the direction, so all reflections and rotations of the board
+    // apply, to the cells of the policy only
+    fn symmetries() -> Vec<SymmetryTransform> {
+        SymmetryTransform::dihedral_group()
+            .into_iter()
+            .map(|symmetry| {
+                let symmetry = Arc::new(symmetry);
+                let (policy, inverse) = (symmetry.clone(), symmetry.clone());
+                SymmetryTransform::new(
+                    move |state| symmetry.transform_state(state),
+                    move |p| Self::transform_cells::<N>(p, |cells| policy.transform_policy(cells)),
+                    move |p| Self::transform_cells::<N>(p, |cells| inverse.inverse_policy(cells)),
+                )
+            })
+            .collect()
+    }
+
+    fn input_planes() -> Vec<String> {
+        ["black", "white", "black to move", "opening"]
+            .map(str::to_owned)
+            .to_vec()
+    }
+
+    fn convert_game_to_nn_input(state: &GomokuState<N>) -> Tensor {
+        let mut fld = vec![0f32; GOMOKU_INPUT_PLANES as usize * N * N];
+        let board = state.board();
+        for i in 0..N {
+            for j in 0..N {
+                match board[(i, j)] {
+                    CellState::X => fld[i * N + j] = 1.,
+                    CellState::O => fld[N * N + i * N + j] = 1.,
+                    CellState::Empty => {}
+                }
+            }
+        }
+        if state.to_move() == Stone::Black {
+            fld[2 * N * N..3 * N * N].fill(1.);
+        }
+        if state.is_opening() {
+            fld[3 * N * N..].fill(1.);
+        }
+        Tensor::from_slice(&fld).view([GOMOKU_INPUT_PLANES, N as i64, N as i64])
+    }
+
+    // `GomokuMove` doesn't tell the board size, so the encoding is named with it
+    fn get_estimated_policy(policy: &Tensor, moves: &[GomokuMove]) -> Vec<f32> {
+        <Self as ActionEncoding<GomokuState<N>>>::decode_policy(policy, moves)
+    }
+
+    fn convert_policy_to_nn(policy: &[f32], moves: &[GomokuMove]) -> Tensor {
+        <Self as ActionEncoding<GomokuState<N>>>::encode_policy(policy, moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tch::Tensor;
+
+    use crate::{
+        alpha_zero::{ActionEncoding, AlphaZeroAdapter, AlphaZeroNet, Game, NetOutput},
+        gomoku::{GomokuRules, OpeningRule, RuleSet},
+        tictactoe::TicTacToeMove,
+    };
+
+    use super::{GomokuAlphaZeroAdapter, GomokuMove, GomokuState};
+
+    struct NoNet;
+
+    impl AlphaZeroNet for NoNet {
+        fn forward_t(&self, _: &Tensor, _: bool) -> NetOutput {
+            unreachable!()
+        }
+    }
+
+    type Adapter = GomokuAlphaZeroAdapter;
+
+    fn swap2() -> GomokuState<5> {
+        GomokuState::new(GomokuRules {
+            rule_set: RuleSet::Renju,
+            opening: OpeningRule::Swap2,
+        })
+    }
+
+    #[test]
+    fn action_encoding_round_trip() {
+        let mut state = swap2();
+        for at in [
+            TicTacToeMove(0, 0),
+            TicTacToeMove(0, 1),
+            TicTacToeMove(2, 2),
+        ] {
+            let m = state
+                .get_state()
+                .get_moves()
+                .unwrap()
+                .into_iter()
+                .find(|m| matches!(m, GomokuMove::Place { at: a, .. } if *a == at))
+                .unwrap();
+            state = state.make_move(&m);
+        }
+        let moves = state.get_state().get_moves().unwrap();
+        assert_eq!(moves.len(), 3);
+        let indices = moves
+            .iter()
+            .map(<Adapter as ActionEncoding<GomokuState<5>>>::move_to_index)
+            .collect::<Vec<_>>();
+        assert_eq!(indices, [25, 26, 27]);
+        for (m, index) in moves.iter().zip(indices) {
+            assert_eq!(
+                <Adapter as ActionEncoding<GomokuState<5>>>::index_to_move(index),
+                *m
+            );
+        }
+        assert_eq!(
+            <Adapter as ActionEncoding<GomokuState<5>>>::index_to_move(7),
+            GomokuMove::Place {
+                at: TicTacToeMove(1, 2),
+                player_switch: true
+            }
+        );
+    }
+
+    #[test]
+    fn augmentation_keeps_opening_choices() {
+        let state = swap2();
+        let policy = Tensor::arange(28, (tch::Kind::Float, tch::Device::Cpu));
+        let input =
+            <Adapter as AlphaZeroAdapter<GomokuState<5>, NoNet>>::convert_game_to_nn_input(&state);
+        assert_eq!(input.size(), [4, 5, 5]);
+        // Black places the first stone of the opening
+        assert_eq!(input.double_value(&[2, 0, 0]), 1.);
+        assert_eq!(input.double_value(&[3, 4, 4]), 1.);
+
+        let augmented = <Adapter as AlphaZeroAdapter<GomokuState<5>, NoNet>>::reflect_and_augment(
+            &input, &policy,
+        );
+        assert_eq!(augmented.len(), 8);
+        for (_, transformed) in &augmented {
+            assert_eq!(transformed.size(), [28]);
+            for i in 25..28 {
+                assert_eq!(transformed.double_value(&[i]), i as f64);
+            }
+            // Only the corners map to corners
+            let corners = [0, 4, 20, 24].map(|i| transformed.double_value(&[i]) as i64);
+            assert!(corners.iter().all(|c| [0, 4, 20, 24].contains(c)));
+        }
+    }
+}
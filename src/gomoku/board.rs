@@ -0,0 +1,347 @@
+use crate::{
+    alpha_zero::{Game, MoveParameters, TerminationState},
+    tictactoe::{CellState, GomokuBoard, TicTacToeMove},
+};
+
+use super::is_forbidden;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stone {
+    Black,
+    White,
+}
+
+impl Stone {
+    fn cell(self) -> CellState {
+        match self {
+            Stone::Black => CellState::X,
+            Stone::White => CellState::O,
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            Stone::Black => Stone::White,
+            Stone::White => Stone::Black,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RuleSet {
+    // Five or more in a row wins for both colors
+    #[default]
+    Freestyle,
+    // Black wins with exactly five only and may not make overlines, double fours or double threes
+    Renju,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OpeningRule {
+    #[default]
+    Standard,
+    // The first player places two black stones and a white one, the second picks a color
+    Swap,
+    // Like `Swap`, but the second player may instead place two more stones and leave the
+    // choice to the first player
+    Swap2,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GomokuRules {
+    pub rule_set: RuleSet,
+    pub opening: OpeningRule,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Phase {
+    // One player places `remaining` stones of alternating colors, then the other one chooses
+    Tentative { remaining: usize, extended: bool },
+    Choose { can_extend: bool },
+    Play,
+}
+
+// Players don't own a color until the opening is over, so moves switch players according to
+// the protocol rather than on every stone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GomokuMove {
+    Place {
+        at: TicTacToeMove,
+        player_switch: bool,
+    },
+    // Play the given color from now on
+    Choose(Stone),
+    // Swap2: place two more stones and let the opponent choose
+    Extend,
+}
+
+impl MoveParameters for GomokuMove {
+    fn is_player_switch(&self) -> bool {
+        match self {
+            GomokuMove::Place { player_switch, .. } => *player_switch,
+            // White moves next, so choosing black hands the turn to the opponent
+            GomokuMove::Choose(stone) => *stone == Stone::Black,
+            GomokuMove::Extend => false,
+        }
+    }
+}
+
+// Gomoku on an N×N board under configurable rules. Unlike `GomokuBoard` on its own the board
+// is stored by color (black is `X`), since Renju treats the colors differently.
+#[derive(Clone, Hash, PartialEq, Eq)]
+pub struct GomokuState<const N: usize> {
+    board: GomokuBoard<N, 5>,
+    rules: GomokuRules,
+    phase: Phase,
+    to_move: Stone,
+    lost: bool,
+}
+
+impl<const N: usize> GomokuState<N> {
+    pub fn new(rules: GomokuRules) -> Self {
+        let phase = match rules.opening {
+            OpeningRule::Standard => Phase::Play,
+            OpeningRule::Swap | OpeningRule::Swap2 => Phase::Tentative {
+                remaining: 3,
+                extended: false,
+            },
+        };
+        Self {
+            board: GomokuBoard::new(),
+            rules,
+            phase,
+            to_move: Stone::Black,
+            lost: false,
+        }
+    }
+
+    pub fn board(&self) -> &GomokuBoard<N, 5> {
+        &self.board
+    }
+
+    pub fn rules(&self) -> GomokuRules {
+        self.rules
+    }
+
+    // Color of the next stone to be placed
+    pub fn to_move(&self) -> Stone {
+        self.to_move
+    }
+
+    pub fn is_opening(&self) -> bool {
+        self.phase != Phase::Play
+    }
+
+    // Whether a color is to be chosen rather than a stone placed
+    pub fn is_choosing(&self) -> bool {
+        matches!(self.phase, Phase::Choose { .. })
+    }
+
+    fn is_allowed(&self, at: (usize, usize)) -> bool {
+        self.board[at] == CellState::Empty
+            && !(self.rules.rule_set == RuleSet::Renju
+                && self.phase == Phase::Play
+                && self.to_move == Stone::Black
+                && is_forbidden(&self.board, at))
+    }
+
+    fn makes_five(&self, (x, y): (usize, usize)) -> bool {
+        const DIRECTIONS: [(i32, i32); 4] = [(-1, 1), (0, 1), (1, 1), (1, 0)];
+
+        let stone = self.to_move.cell();
+        let same = |k: i32, (dx, dy): (i32, i32)| {
+            let (cx, cy) = (x as i32 + dx * k, y as i32 + dy * k);
+            (0..N as i32).contains(&cx)
+                && (0..N as i32).contains(&cy)
+                && self.board[(cx as usize, cy as usize)] == stone
+        };
+        let exact = self.rules.rule_set == RuleSet::Renju && self.to_move == Stone::Black;
+
+        DIRECTIONS.into_iter().any(|dir| {
+            let forward = (1..).take_while(|&k| same(k, dir)).count();
+            let backward = (1..).take_while(|&k| same(-k, dir)).count();
+            let len = forward + backward + 1;
+            if exact {
+                len == 5
+            } else {
+                len >= 5
+            }
+        })
+    }
+}
+
+impl<const N: usize> Game for GomokuState<N> {
+    type Move = GomokuMove;
+
+    fn get_state(&self) -> TerminationState<Self::Move> {
+        if self.lost {
+            return TerminationState::Terminal(0.0);
+        }
+        let player_switch = match self.phase {
+            Phase::Choose { can_extend } => {
+                let mut moves = vec![
+                    GomokuMove::Choose(Stone::Black),
+                    GomokuMove::Choose(Stone::White),
+                ];
+                if can_extend {
+                    moves.push(GomokuMove::Extend);
+                }
+                return TerminationState::Moves(moves);
+            }
+            Phase::Tentative { remaining, .. } => remaining == 1,
+            Phase::Play => true,
+        };
+
+        let moves = (0..N)
+            .flat_map(|i| (0..N).map(move |j| (i, j)))
+            .filter(|&crd| self.is_allowed(crd))
+            .map(|(i, j)| GomokuMove::Place {
+                at: TicTacToeMove(i, j),
+                player_switch,
+            })
+            .collect::<Vec<_>>();
+
+        if moves.is_empty() {
+            TerminationState::Terminal(0.5)
+        } else {
+            TerminationState::Moves(moves)
+        }
+    }
+
+    fn make_move(&self, m: &Self::Move) -> Self {
+        let mut res = self.clone();
+        match *m {
+            GomokuMove::Place {
+                at: TicTacToeMove(i, j),
+                ..
+            } => {
+                res.lost = self.makes_five((i, j));
+                res.board.set_inplace((i, j), self.to_move.cell());
+                res.to_move = self.to_move.other();
+                res.phase = match self.phase {
+                    Phase::Tentative {
+                        remaining: 1,
+                        extended,
+                    } => Phase::Choose {
+                        can_extend: self.rules.opening == OpeningRule::Swap2 && !extended,
+                    },
+                    Phase::Tentative {
+                        remaining,
+                        extended,
+                    } => Phase::Tentative {
+                        remaining: remaining - 1,
+                        extended,
+                    },
+                    phase => phase,
+                };
+            }
+            GomokuMove::Choose(_) => res.phase = Phase::Play,
+            GomokuMove::Extend => {
+                res.phase = Phase::Tentative {
+                    remaining: 2,
+                    extended: true,
+                }
+            }
+        }
+        res
+    }
+
+    fn is_legal(&self, m: &Self::Move) -> bool {
+        match self.get_state() {
+            TerminationState::Moves(moves) => moves.contains(m),
+            TerminationState::Terminal(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{Game, MoveParameters, TerminationState},
+        tictactoe::TicTacToeMove,
+    };
+
+    use super::{GomokuMove, GomokuRules, GomokuState, OpeningRule, RuleSet, Stone};
+
+    fn place(state: &GomokuState<19>, i: usize, j: usize) -> GomokuState<19> {
+        let m = state
+            .get_state()
+            .get_moves()
+            .unwrap()
+            .into_iter()
+            .find(|m| matches!(m, GomokuMove::Place { at, .. } if *at == TicTacToeMove(i, j)))
+            .unwrap();
+        state.make_move(&m)
+    }
+
+    #[test]
+    fn swap2_protocol() {
+        let state = GomokuState::<19>::new(GomokuRules {
+            rule_set: RuleSet::Freestyle,
+            opening: OpeningRule::Swap2,
+        });
+
+        // The first player places three stones in a row, only the last one passes the turn
+        let moves = state.get_state().get_moves().unwrap();
+        assert!(!moves[0].is_player_switch());
+        let state = place(&place(&state, 9, 9), 9, 10);
+        let moves = state.get_state().get_moves().unwrap();
+        assert!(moves[0].is_player_switch());
+        let state = place(&state, 10, 9);
+
+        assert_eq!(
+            state.get_state().get_moves().unwrap(),
+            [
+                GomokuMove::Choose(Stone::Black),
+                GomokuMove::Choose(Stone::White),
+                GomokuMove::Extend
+            ]
+        );
+
+        // After two more stones the first player chooses and can't extend again
+        let state = state.make_move(&GomokuMove::Extend);
+        let state = place(&place(&state, 8, 8), 11, 11);
+        assert_eq!(state.get_state().get_moves().unwrap().len(), 2);
+        let state = state.make_move(&GomokuMove::Choose(Stone::White));
+        assert!(!state.is_opening());
+        assert_eq!(state.to_move(), Stone::White);
+    }
+
+    #[test]
+    fn renju_rules() {
+        let rules = GomokuRules {
+            rule_set: RuleSet::Renju,
+            opening: OpeningRule::Standard,
+        };
+        // Black builds a double three at (9, 9), white plays far away
+        let mut state = GomokuState::<19>::new(rules);
+        for (black, white) in [
+            ((9, 7), (0, 0)),
+            ((9, 8), (0, 2)),
+            ((7, 9), (0, 4)),
+            ((8, 9), (0, 6)),
+        ] {
+            state = place(&place(&state, black.0, black.1), white.0, white.1);
+        }
+        let forbidden = GomokuMove::Place {
+            at: TicTacToeMove(9, 9),
+            player_switch: true,
+        };
+        assert!(!state.is_legal(&forbidden));
+        assert!(GomokuState::<19>::new(GomokuRules::default()).is_legal(&forbidden));
+
+        // White wins with an overline
+        let mut state = GomokuState::<19>::new(rules);
+        for (black, white) in [
+            ((18, 0), (5, 0)),
+            ((18, 2), (5, 1)),
+            ((18, 4), (5, 2)),
+            ((18, 6), (5, 4)),
+            ((18, 8), (5, 5)),
+            ((18, 10), (5, 3)),
+        ] {
+            state = place(&place(&state, black.0, black.1), white.0, white.1);
+        }
+        assert_eq!(state.get_state(), TerminationState::Terminal(0.0));
+    }
+}
@@ -0,0 +1,137 @@
+use crate::tictactoe::{CellState, GomokuBoard};
+
+const DIRECTIONS: [(i32, i32); 4] = [(-1, 1), (0, 1), (1, 1), (1, 0)];
+
+// Cells from -5 to +5 around a point along one direction, black is `X`. `None` is off the board.
+type Line = [Option<CellState>; 11];
+const CENTER: usize = 5;
+
+fn line<const N: usize>(
+    board: &GomokuBoard<N, 5>,
+    (x, y): (usize, usize),
+    (dx, dy): (i32, i32),
+) -> Line {
+    let mut res = [None; 11];
+    for (k, cell) in res.iter_mut().enumerate() {
+        let (cx, cy) = (
+            x as i32 + dx * (k as i32 - 5),
+            y as i32 + dy * (k as i32 - 5),
+        );
+        if (0..N as i32).contains(&cx) && (0..N as i32).contains(&cy) {
+            *cell = Some(board[(cx as usize, cy as usize)]);
+        }
+    }
+    res
+}
+
+// Length of the black run through `i`
+fn run(line: &Line, i: usize) -> usize {
+    if line[i] != Some(CellState::X) {
+        return 0;
+    }
+    let left = (0..i)
+        .rev()
+        .take_while(|&k| line[k] == Some(CellState::X))
+        .count();
+    let right = (i + 1..line.len())
+        .take_while(|&k| line[k] == Some(CellState::X))
+        .count();
+    left + 1 + right
+}
+
+// Empty points completing an exactly-five through the center
+fn five_points(line: &Line) -> Vec<usize> {
+    (CENTER - 4..=CENTER + 4)
+        .filter(|&k| line[k] == Some(CellState::Empty))
+        .filter(|&k| {
+            let mut line = *line;
+            line[k] = Some(CellState::X);
+            run(&line, CENTER) == 5
+        })
+        .collect()
+}
+
+// Number of fours through the center. A straight four (`.XXXX.`) has two completion
+// points but is a single four, while `X.XXX.X` holds two fours on one line.
+fn fours(line: &Line) -> usize {
+    match five_points(line).as_slice() {
+        [a, b] if b - a == 5 => 1,
+        points => points.len(),
+    }
+}
+
+fn is_straight_four(line: &Line) -> bool {
+    matches!(five_points(line).as_slice(), [a, b] if b - a == 5)
+}
+
+// A three that can become a straight four in one move. Unlike full Renju rules this doesn't
+// check recursively whether that move would itself be forbidden.
+fn is_open_three(line: &Line) -> bool {
+    fours(line) == 0
+        && (CENTER - 4..=CENTER + 4)
+            .filter(|&k| line[k] == Some(CellState::Empty))
+            .any(|k| {
+                let mut line = *line;
+                line[k] = Some(CellState::X);
+                is_straight_four(&line)
+            })
+}
+
+// Whether black (`X`) may not play at the empty cell `at` under Renju rules: overlines,
+// double fours and double threes are forbidden, unless the move makes an exact five.
+pub fn is_forbidden<const N: usize>(board: &GomokuBoard<N, 5>, at: (usize, usize)) -> bool {
+    debug_assert_eq!(board[at], CellState::Empty);
+    let lines = DIRECTIONS.map(|dir| {
+        let mut line = line(board, at, dir);
+        line[CENTER] = Some(CellState::X);
+        line
+    });
+
+    if lines.iter().any(|line| run(line, CENTER) == 5) {
+        return false;
+    }
+    lines.iter().any(|line| run(line, CENTER) > 5)
+        || lines.iter().map(fours).sum::<usize>() >= 2
+        || lines.iter().filter(|line| is_open_three(line)).count() >= 2
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tictactoe::{BoardState, CellState};
+
+    use super::is_forbidden;
+
+    fn black(cells: &[(usize, usize)]) -> BoardState {
+        cells
+            .iter()
+            .fold(BoardState::new(), |board, &c| board.set(c, CellState::X))
+    }
+
+    #[test]
+    fn renju_forbidden_points() {
+        // Double three: open twos crossing at (9, 9)
+        let board = black(&[(9, 7), (9, 8), (7, 9), (8, 9)]);
+        assert!(is_forbidden(&board, (9, 9)));
+        // Blocking one of them with white leaves a single three
+        assert!(!is_forbidden(
+            &board.clone().set((9, 6), CellState::O),
+            (9, 9)
+        ));
+
+        // Double four, including two fours on the same line
+        let board = black(&[(9, 6), (9, 7), (9, 8), (6, 9), (7, 9), (8, 9)])
+            .set((9, 5), CellState::O)
+            .set((5, 9), CellState::O);
+        assert!(is_forbidden(&board, (9, 9)));
+        let board = black(&[(9, 5), (9, 7), (9, 8), (9, 11)]);
+        assert!(is_forbidden(&board, (9, 9)));
+
+        // Overline, unless the move also makes an exact five in another direction
+        let board = black(&[(9, 4), (9, 5), (9, 7), (9, 8), (9, 9)]);
+        assert!(is_forbidden(&board, (9, 6)));
+        let board = [(5, 6), (6, 6), (7, 6), (8, 6)]
+            .into_iter()
+            .fold(board, |board, c| board.set(c, CellState::X));
+        assert!(!is_forbidden(&board, (9, 6)));
+    }
+}
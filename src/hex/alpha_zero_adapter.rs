@@ -0,0 +1,157 @@
+use tch::Tensor;
+
+use crate::{
+    alpha_zero::{ActionEncoding, AlphaZeroAdapter, AlphaZeroNet, SymmetryTransform},
+    tictactoe::CellState,
+};
+
+use super::{HexBoard, HexMove};
+
+pub const HEX_INPUT_PLANES: i64 = 3;
+
+// Input planes: own stones, opponent stones, and a constant plane telling whether a swap is
+// possible. The policy is flat, `N * N` cells followed by the swap move.
+pub struct HexAlphaZeroAdapter;
+
+impl<const N: usize> ActionEncoding<HexBoard<N>> for HexAlphaZeroAdapter {
+    fn action_space_size() -> usize {
+        N * N + 1
+    }
+
+    fn move_to_index(m: &HexMove) -> usize {
+        match *m {
+            HexMove::Place(i) => i,
+            HexMove::Swap => N * N,
+        }
+    }
+
+    fn index_to_move(index: usize) -> HexMove {
+        match index == N * N {
+            true => HexMove::Swap,
+            false => HexMove::Place(index),
+        }
+    }
+}
+
+impl HexAlphaZeroAdapter {
+    // Rotating the rhombus by 180° keeps both players' edges in place. On the flat policy
+    // that's reversing the cells, the swap entry stays where it is.
+    fn rotate_policy(policy: &Tensor) -> Tensor {
+        let cells = policy.size().last().unwrap() - 1;
+        Tensor::concat(
+            &[
+                policy.narrow(-1, 0, cells).flip([-1]),
+                policy.narrow(-1, cells, 1),
+            ],
+            -1,
+        )
+    }
+}
+
+impl<const N: usize, Net: AlphaZeroNet> AlphaZeroAdapter<HexBoard<N>, Net> for HexAlphaZeroAdapter {
+    // The board isn't square-symmetric: the other reflections and rotations exchange the
+    // players' edges. Swapping colors is built into the canonical view of `HexBoard`, so
+    // the only symmetry left for the player to move is the half turn.
+    fn symmetries() -> Vec<SymmetryTransform> {
+        vec![
+            SymmetryTransform::identity(),
+            SymmetryTransform::new(
+                |state| state.flip([-2, -1]),
+                Self::rotate_policy,
+                Self::rotate_policy,
+            ),
+        ]
+    }
+
+    fn convert_game_to_nn_input(state: &HexBoard<N>) -> Tensor {
+        let mut fld = vec![0f32; HEX_INPUT_PLANES as usize * N * N];
+        for i in 0..N {
+            for j in 0..N {
+                match state.get(i, j) {
+                    CellState::X => fld[i * N + j] = 1.,
+                    CellState::O => fld[N * N + i * N + j] = 1.,
+                    CellState::Empty => {}
+                }
+            }
+        }
+        if state.can_swap() {
+            fld[2 * N * N..].fill(1.);
+        }
+        Tensor::from_slice(&fld).view([HEX_INPUT_PLANES, N as i64, N as i64])
+    }
+
+    // `HexMove` doesn't tell the board size, so the encoding is named with it
+    fn get_estimated_policy(policy: &Tensor, moves: &[HexMove]) -> Vec<f32> {
+        <Self as ActionEncoding<HexBoard<N>>>::decode_policy(policy, moves)
+    }
+
+    fn convert_policy_to_nn(policy: &[f32], moves: &[HexMove]) -> Tensor {
+        <Self as ActionEncoding<HexBoard<N>>>::encode_policy(policy, moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tch::Tensor;
+
+    use crate::alpha_zero::{ActionEncoding, AlphaZeroAdapter, AlphaZeroNet, Game, NetOutput};
+
+    use super::{HexAlphaZeroAdapter, HexBoard, HexMove};
+
+    struct NoNet;
+
+    impl AlphaZeroNet for NoNet {
+        fn forward_t(&self, _: &Tensor, _: bool) -> NetOutput {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn half_turn_augmentation() {
+        let board = HexBoard::<3>::new(true).make_move(&HexMove::Place(1));
+        let moves = board.get_state().get_moves().unwrap();
+        let policy = (0..moves.len()).map(|i| i as f32).collect::<Vec<_>>();
+
+        let state =
+            <HexAlphaZeroAdapter as AlphaZeroAdapter<HexBoard<3>, NoNet>>::convert_game_to_nn_input(
+                &board,
+            );
+        let policy =
+            <HexAlphaZeroAdapter as AlphaZeroAdapter<HexBoard<3>, NoNet>>::convert_policy_to_nn(
+                &policy, &moves,
+            );
+        let augmented =
+            <HexAlphaZeroAdapter as AlphaZeroAdapter<HexBoard<3>, NoNet>>::reflect_and_augment(
+                &state, &policy,
+            );
+        assert_eq!(augmented.len(), 2);
+
+        // Opponent stone moves from (1, 0) to (1, 2), the swap plane is untouched
+        let (state, rotated) = &augmented[1];
+        assert_eq!(state.double_value(&[1, 1, 2]), 1.);
+        assert_eq!(state.double_value(&[1, 1, 0]), 0.);
+        assert_eq!(state.double_value(&[2, 0, 0]), 1.);
+
+        // Cell 0 gets the probability of cell 8 and vice versa, swap keeps its slot
+        assert_eq!(rotated.double_value(&[0]), policy.double_value(&[8]));
+        assert_eq!(rotated.double_value(&[8]), policy.double_value(&[0]));
+        assert_eq!(rotated.double_value(&[9]), policy.double_value(&[9]));
+    }
+
+    #[test]
+    fn action_encoding_round_trip() {
+        let board = HexBoard::<3>::new(true).make_move(&HexMove::Place(1));
+        let moves = board.get_state().get_moves().unwrap();
+        assert!(moves.contains(&HexMove::Swap));
+        for m in &moves {
+            let index = <HexAlphaZeroAdapter as ActionEncoding<HexBoard<3>>>::move_to_index(m);
+            assert!(
+                index < <HexAlphaZeroAdapter as ActionEncoding<HexBoard<3>>>::action_space_size()
+            );
+            assert_eq!(
+                <HexAlphaZeroAdapter as ActionEncoding<HexBoard<3>>>::index_to_move(index),
+                *m
+            );
+        }
+    }
+}
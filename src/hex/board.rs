@@ -0,0 +1,263 @@
+use std::collections::VecDeque;
+
+use crate::{
+    alpha_zero::{Game, MoveParameters, SgfGame, TerminationState},
+    tictactoe::CellState,
+};
+
+// Offsets of the six neighbours of a cell on the rhombus. The set is invariant under both
+// transposition and 180° rotation, which is what makes the canonical view below work.
+const NEIGHBOURS: [(isize, isize); 6] = [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0)];
+
+// N×N Hex from the point of view of the player to move ("X"), who always connects the top
+// and bottom rows. After every move the board is transposed and the colors swapped, so the
+// opponent becomes "X" connecting top and bottom in turn.
+#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+pub struct HexBoard<const N: usize> {
+    cells: [[CellState; N]; N],
+    swap_rule: bool,
+    moves_made: usize,
+    lost: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HexMove {
+    // Index of the cell, `row * N + column`
+    Place(usize),
+    // Second player takes over the first stone instead of placing one
+    Swap,
+}
+
+impl MoveParameters for HexMove {
+    fn is_player_switch(&self) -> bool {
+        true
+    }
+}
+
+impl<const N: usize> HexBoard<N> {
+    pub fn new(swap_rule: bool) -> Self {
+        Self {
+            cells: [[CellState::Empty; N]; N],
+            swap_rule,
+            moves_made: 0,
+            lost: false,
+        }
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> CellState {
+        self.cells[row][column]
+    }
+
+    pub fn can_swap(&self) -> bool {
+        self.swap_rule && self.moves_made == 1
+    }
+
+    fn connects_top_bottom(&self) -> bool {
+        let mut visited = [[false; N]; N];
+        let mut queue = VecDeque::new();
+        for (j, &cell) in self.cells[0].iter().enumerate() {
+            if cell == CellState::X {
+                visited[0][j] = true;
+                queue.push_back((0, j));
+            }
+        }
+        while let Some((i, j)) = queue.pop_front() {
+            if i == N - 1 {
+                return true;
+            }
+            for (di, dj) in NEIGHBOURS {
+                let (Some(ni), Some(nj)) = (i.checked_add_signed(di), j.checked_add_signed(dj))
+                else {
+                    continue;
+                };
+                if ni < N && nj < N && !visited[ni][nj] && self.cells[ni][nj] == CellState::X {
+                    visited[ni][nj] = true;
+                    queue.push_back((ni, nj));
+                }
+            }
+        }
+        false
+    }
+
+    // Whether the canonical view is the transpose of the board the first player sees, as it
+    // is after an odd number of stones. A swap takes over a stone without placing one.
+    fn is_transposed(&self) -> bool {
+        let stones = self.cells.iter().flatten();
+        stones.filter(|&&cell| cell != CellState::Empty).count() % 2 == 1
+    }
+
+    fn swap_sides(&self) -> Self {
+        let mut cells = [[CellState::Empty; N]; N];
+        for (i, row) in cells.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
+                *cell = match self.cells[j][i] {
+                    CellState::X => CellState::O,
+                    CellState::O => CellState::X,
+                    CellState::Empty => CellState::Empty,
+                };
+            }
+        }
+        Self { cells, ..*self }
+    }
+}
+
+impl<const N: usize> Game for HexBoard<N> {
+    type Move = HexMove;
+
+    fn get_state(&self) -> TerminationState<Self::Move> {
+        // Hex can't end in a draw: the board only fills up once somebody is connected
+        if self.lost {
+            return TerminationState::Terminal(0.0);
+        }
+        let mut moves = (0..N * N)
+            .filter(|&i| self.cells[i / N][i % N] == CellState::Empty)
+            .map(HexMove::Place)
+            .collect::<Vec<_>>();
+        if self.can_swap() {
+            moves.push(HexMove::Swap);
+        }
+        TerminationState::Moves(moves)
+    }
+
+    fn make_move(&self, m: &Self::Move) -> Self {
+        assert!(self.is_legal(m), "Illegal move {m:?}");
+        match *m {
+            HexMove::Place(i) => {
+                let mut res = *self;
+                res.cells[i / N][i % N] = CellState::X;
+                let won = res.connects_top_bottom();
+                Self {
+                    moves_made: self.moves_made + 1,
+                    lost: won,
+                    ..res.swap_sides()
+                }
+            }
+            // Taking over the opponent's stone means mirroring it into our own color, which
+            // in the canonical view is the very position the opponent is looking at now
+            HexMove::Swap => Self {
+                moves_made: self.moves_made + 1,
+                ..*self
+            },
+        }
+    }
+
+    fn is_legal(&self, m: &Self::Move) -> bool {
+        !self.lost
+            && match *m {
+                HexMove::Place(i) => i < N * N && self.cells[i / N][i % N] == CellState::Empty,
+                HexMove::Swap => self.can_swap(),
+            }
+    }
+}
+
+// Points are on the board the first player sees. Hex has no pass, so the swap takes its place.
+impl<const N: usize> SgfGame for HexBoard<N> {
+    const GAME_TYPE: u32 = 11;
+
+    fn board_size(&self) -> usize {
+        N
+    }
+
+    fn move_point(&self, m: &HexMove) -> Option<(usize, usize)> {
+        match *m {
+            HexMove::Place(i) if self.is_transposed() => Some((i % N, i / N)),
+            HexMove::Place(i) => Some((i / N, i % N)),
+            HexMove::Swap => None,
+        }
+    }
+
+    fn point_move(&self, point: Option<(usize, usize)>) -> anyhow::Result<HexMove> {
+        Ok(match point {
+            Some((row, column)) if self.is_transposed() => HexMove::Place(column * N + row),
+            Some((row, column)) => HexMove::Place(row * N + column),
+            None => HexMove::Swap,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{seq::SliceRandom, thread_rng};
+
+    use crate::{
+        alpha_zero::{Game, SgfGame, TerminationState},
+        tictactoe::CellState,
+    };
+
+    use super::{HexBoard, HexMove};
+
+    // Plays moves given in absolute coordinates: the first player connects top and bottom,
+    // the second one sees the board transposed.
+    fn play<const N: usize>(moves: &[(usize, usize)]) -> HexBoard<N> {
+        moves
+            .iter()
+            .enumerate()
+            .fold(HexBoard::new(false), |board, (k, &(r, c))| {
+                let index = if k % 2 == 0 { r * N + c } else { c * N + r };
+                board.make_move(&HexMove::Place(index))
+            })
+    }
+
+    #[test]
+    fn hex_wins() {
+        let moves = [(0, 1), (0, 0), (1, 1), (1, 0), (2, 1)];
+        assert!(play::<3>(&moves[..4]).get_state().get_terminal().is_none());
+        assert_eq!(
+            play::<3>(&moves).get_state(),
+            TerminationState::Terminal(0.0)
+        );
+
+        // Second player connecting left and right along a bent line
+        let moves = [(0, 0), (1, 0), (0, 1), (1, 1), (2, 2), (0, 2)];
+        assert_eq!(
+            play::<3>(&moves).get_state(),
+            TerminationState::Terminal(0.0)
+        );
+    }
+
+    #[test]
+    fn hex_has_no_draws() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let mut board = HexBoard::<5>::new(false);
+            while let Some(moves) = board.get_state().get_moves() {
+                board = board.make_move(moves.choose(&mut rng).unwrap());
+            }
+            assert_eq!(board.get_state(), TerminationState::Terminal(0.0));
+        }
+    }
+
+    #[test]
+    fn hex_swap_rule() {
+        let board = HexBoard::<3>::new(true);
+        assert!(!board.is_legal(&HexMove::Swap));
+
+        let board = board.make_move(&HexMove::Place(2));
+        let moves = board.get_state().get_moves().unwrap();
+        assert_eq!(moves.last(), Some(&HexMove::Swap));
+
+        // The first player now faces their own stone mirrored into the opponent's color
+        let board = board.make_move(&HexMove::Swap);
+        assert_eq!(board.get(2, 0), CellState::O);
+        assert!(!board.can_swap());
+        assert_eq!(board.get_state().get_moves().unwrap().len(), 8);
+    }
+
+    #[test]
+    fn hex_points_on_the_first_players_board() {
+        let board = play::<3>(&[(0, 1), (2, 0)]);
+        for point in [(0, 1), (2, 0)] {
+            let m = board.point_move(Some(point)).unwrap();
+            assert!(!board.is_legal(&m), "{point:?}");
+        }
+        let m = board.point_move(Some((1, 2))).unwrap();
+        assert_eq!(board.move_point(&m), Some((1, 2)));
+
+        // The second player sees the board transposed, also after swapping
+        let board = HexBoard::<3>::new(true).make_move(&HexMove::Place(1));
+        let m = board.point_move(Some((0, 2))).unwrap();
+        assert_eq!(m, HexMove::Place(2 * 3));
+        let board = board.make_move(&board.point_move(None).unwrap());
+        assert_eq!(board.point_move(Some((0, 2))).unwrap(), m);
+    }
+}
@@ -0,0 +1,172 @@
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// Longest request read, head and body, a browser's GET being much shorter
+const MAX_REQUEST: usize = 16 * 1024;
+
+// The one request of a connection, which the server closes once it has answered
+pub struct Request {
+    // Empty if the request line lacks them
+    pub method: String,
+    pub path: String,
+    headers: Vec<(String, String)>,
+    // Of the length the head gives, empty without one
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub async fn read(stream: &mut (impl AsyncRead + Unpin)) -> anyhow::Result<Self> {
+        let mut request = vec![];
+        let mut buf = [0; 1024];
+        let end = loop {
+            if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end;
+            }
+            let read = stream.read(&mut buf).await?;
+            anyhow::ensure!(read > 0, "The request ended early");
+            request.extend_from_slice(&buf[..read]);
+            anyhow::ensure!(request.len() <= MAX_REQUEST, "The request is too long");
+        };
+        let head = String::from_utf8_lossy(&request[..end]);
+        let mut lines = head.lines();
+        let mut words = lines.next().unwrap_or("").split_whitespace();
+        let (method, path) = (words.next(), words.next());
+        let headers = lines
+            .filter_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                Some((name.trim().to_owned(), value.trim().to_owned()))
+            })
+            .collect::<Vec<_>>();
+        let mut parsed = Self {
+            method: method.unwrap_or("").to_owned(),
+            path: path.unwrap_or("").to_owned(),
+            headers,
+            body: vec![],
+        };
+
+        let length = parsed
+            .header("content-length")
+            .map(str::parse::<usize>)
+            .transpose()?
+            .unwrap_or(0);
+        let start = end + 4;
+        anyhow::ensure!(start + length <= MAX_REQUEST, "The request is too long");
+        while request.len() < start + length {
+            let read = stream.read(&mut buf).await?;
+            anyhow::ensure!(read > 0, "The request ended early");
+            request.extend_from_slice(&buf[..read]);
+        }
+        request.truncate(start + length);
+        parsed.body = request.split_off(start);
+        Ok(parsed)
+    }
+
+    // The first header of the name, which is case-insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+// Closes the connection once written
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: "200 OK",
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    pub fn text(status: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain",
+            body: message.into(),
+        }
+    }
+
+    pub fn json(status: &'static str, value: &impl Serialize) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self {
+                status,
+                content_type: "application/json",
+                body,
+            },
+            Err(e) => Self::json_error("500 Internal Server Error", &e.to_string()),
+        }
+    }
+
+    // Like `{"error": "No such match"}`
+    pub fn json_error(status: &'static str, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+
+    pub async fn write(&self, stream: &mut (impl AsyncWrite + Unpin)) -> anyhow::Result<()> {
+        let head = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        );
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(&self.body).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::{Request, Response};
+
+    #[tokio::test]
+    async fn request_with_a_body() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client
+            .write_all(b"POST /matches HTTP/1.1\r\ncontent-LENGTH: 7\r\n\r\n{\"a\":1}")
+            .await
+            .unwrap();
+        let request = Request::read(&mut server).await.unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/matches")
+        );
+        assert_eq!(request.header("Content-Length"), Some("7"));
+        assert_eq!(request.body, b"{\"a\":1}");
+
+        // Refused from its head
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client
+            .write_all(b"POST / HTTP/1.1\r\nContent-Length: 100000\r\n\r\n")
+            .await
+            .unwrap();
+        assert!(Request::read(&mut server).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn response_closes_the_connection() {
+        let (mut client, mut server) = tokio::io::duplex(256);
+        Response::json_error("404 Not Found", "No such match")
+            .write(&mut server)
+            .await
+            .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert_eq!(
+            response,
+            "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: 25\r\n\
+             Connection: close\r\n\r\n{\"error\":\"No such match\"}"
+        );
+    }
+}
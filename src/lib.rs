@@ -2,4 +2,42 @@
 #![feature(slice_flatten)]
 
 pub mod alpha_zero;
+pub mod checkers;
+pub mod chess;
+#[cfg(feature = "torch")]
+pub mod cli;
+pub mod combinatorial;
+#[cfg(feature = "torch")]
+pub mod config;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod dashboard;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod eval_service;
+pub mod go;
+pub mod gomoku;
+pub mod hex;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod http;
+pub mod logging;
+pub mod metrics;
+#[cfg(feature = "open-spiel")]
+pub mod open_spiel;
+pub mod othello;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod play_server;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "torch")]
+pub mod registry;
+pub mod run;
+pub mod selfplay;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod shutdown;
+#[cfg(feature = "torch")]
+pub mod sweep;
 pub mod tictactoe;
+pub mod tictactoe3;
+#[cfg(feature = "torch")]
+pub mod viewer;
+#[cfg(feature = "wasm")]
+pub mod wasm;
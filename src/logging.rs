@@ -0,0 +1,29 @@
+use clap::ValueEnum;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    // Lines for the terminal
+    #[default]
+    Text,
+    // An object per line, for collecting the logs of long unattended runs
+    Json,
+}
+
+// Logs to stderr, leaving stdout to the results of the commands. The verbosity per module
+// comes from RUST_LOG, like `warn,pytorch::alpha_zero=debug`, and is info without it.
+pub fn init(format: LogFormat) -> anyhow::Result<()> {
+    let filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .from_env()
+        .map_err(|err| anyhow::anyhow!("Invalid RUST_LOG: {err}"))?;
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    match format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().flatten_event(true).init(),
+    }
+    Ok(())
+}
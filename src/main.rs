@@ -1,196 +1,2113 @@
-use std::{path::PathBuf, time::Duration};
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
 
+use anyhow::Context;
+use clap::Parser;
+use futures::{future::LocalBoxFuture, StreamExt};
+#[cfg(feature = "arrow")]
+use pytorch::selfplay::SampleFormat;
 use pytorch::{
-    alpha_zero::{generate_self_played_game, AlphaZeroAdapter, AlphaZeroNet, ExecutorScope, Game},
-    tictactoe::{generate_game_image, BoardState, TicTacToeAlphaZeroAdapter, TicTacToeNet},
-};
-use rand::{
-    seq::{IteratorRandom, SliceRandom},
-    thread_rng,
+    alpha_zero::{
+        analyze_game, bradley_terry, describe_model, diverging_lines, elo_difference,
+        example_input, export_torchscript, format_reports, generate_self_played_game,
+        import_weights, match_summary, parse_move_list, perfect_play_eval, play_head_to_head,
+        play_in_terminal, play_match, plot_value_trajectories, render_heatmaps, render_line_diff,
+        save_animation, search_heatmaps, set_ownership_targets, AlphaZeroAdapter, AlphaZeroNet,
+        Arena, Baseline, CheckpointManager, Contender, ContenderAgent, DataReport, ExecutorScope,
+        Game, GameLog, GtpEngine, MatchConfig, MatchStats, MctsAgent, NameMapping,
+        NetworkEvaluator, OpeningBook, PerfectPlay, PortableGame, RatingEntry, RatingHistory,
+        ReplayBuffer, Seed, SelfPlayProfile, Significance, TrainStats, Trainer, UciEngine,
+    },
+    cli::{Cli, Command, ExportFormat, Side},
+    config::{Config, ExecutorConfig, NetworkConfig},
+    dashboard::{Dashboard, Progress},
+    eval_service::{EvalService, MatchLimits, MatchReport, MatchRequest},
+    logging,
+    metrics::{
+        plot_series, read_metrics, ConsoleSink, CsvSink, Metrics, MetricsSink, TensorBoardSink,
+    },
+    play_server::{PlayServer, GOMOKU_PAGE},
+    registry::{GameRegistry, GameSpec, GameVisitor},
+    run::RunContext,
+    selfplay::{
+        deserialize_weights, DataStore, GameServer, LearnerConnection, PlayedGame, ReplayJournal,
+    },
+    shutdown::Shutdown,
+    sweep::{comparison_table, Hyperparameters, TrialResult},
 };
-use tap::{tap, Tap};
-use tch::{
-    nn::{self, OptimizerConfig},
-    Device, Kind, Tensor,
-};
-use unzip3::Unzip3;
+use rand::{seq::IteratorRandom, thread_rng, Rng};
+use tch::{nn, Device, Kind};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
 
-fn get_checkpoint_file(epoch: usize) -> PathBuf {
-    PathBuf::from(format!("checkpoints/{epoch:02}.safetensors"))
+enum Mode {
+    // Optionally accepting remote workers on the given address
+    Learn {
+        listen: Option<String>,
+        // Serving a dashboard of the run on the given address
+        dashboard: Option<String>,
+        run: RunContext,
+        config: Config,
+    },
+    // Plays games for the learner at the given address
+    Work {
+        learner: String,
+        config: Config,
+    },
+    // Where the time of self-play goes
+    Profile {
+        weights: Option<PathBuf>,
+        config: Config,
+    },
+    // Trains a shortened run per trial and compares them
+    Sweep {
+        trials: Vec<Hyperparameters>,
+        generations: usize,
+        // Trials trained at the same time
+        jobs: usize,
+        run: RunContext,
+        config: Config,
+    },
+    // Two nets or baselines against each other
+    Match {
+        contender: Contender,
+        opponent: Contender,
+        config: MatchConfig,
+        run: RunContext,
+    },
+    // Round-robin between the checkpoints in a directory and the baselines
+    Arena {
+        checkpoints: PathBuf,
+        baselines: Vec<Baseline>,
+        config: MatchConfig,
+    },
+    // Renders the games of a file written by a match or an arena
+    Replay {
+        games: PathBuf,
+    },
+    // A game against a human in the terminal
+    Play {
+        engine: Contender,
+        human_first: bool,
+        config: MatchConfig,
+    },
+    // Writes the games of a file written by a match or an arena as SGF
+    ExportSgf {
+        games: PathBuf,
+    },
+    // Writes the self-play samples of a run as Parquet or Arrow
+    ExportSamples {
+        run: RunContext,
+        format: ExportFormat,
+    },
+    // Writes a net traced at a batch size as TorchScript, with a description of it
+    ExportModel {
+        game: String,
+        weights: PathBuf,
+        batch_size: usize,
+    },
+    // Writes the game's net with weights trained elsewhere, renamed by the mapping
+    Import {
+        file: PathBuf,
+        mapping: NameMapping,
+        output: PathBuf,
+    },
+    // Charts the metrics of a finished or running run
+    Report {
+        run: RunContext,
+    },
+    // A GTP engine on stdin and stdout
+    Gtp {
+        weights: PathBuf,
+        config: MatchConfig,
+    },
+    // A UCI engine on stdin and stdout
+    Uci {
+        weights: PathBuf,
+        config: MatchConfig,
+    },
+    // Games against browsers over WebSockets, with the page served to them if there is one
+    PlayServer {
+        weights: PathBuf,
+        listen: String,
+        page: Option<&'static str>,
+        config: MatchConfig,
+    },
+    // Reports what a net's search thinks of every move of the games of a file
+    Analyze {
+        games: PathBuf,
+        weights: PathBuf,
+        config: MatchConfig,
+    },
+    // Renders the prior, the visits and the Q of a net's search of the position after a list
+    // of moves
+    Heatmaps {
+        moves: PathBuf,
+        weights: PathBuf,
+        config: MatchConfig,
+    },
+    // Renders where the search of two nets, like consecutive generations, stops agreeing on
+    // the best move after an opening, and both lines from there
+    Diff {
+        moves: PathBuf,
+        weights: PathBuf,
+        versus: PathBuf,
+        config: MatchConfig,
+    },
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let mut vs = nn::VarStore::new(Device::Mps);
-    println!("Going to use device {:?}", vs.device());
+impl Mode {
+    // On the device the nets run on
+    fn run<TGame, TNet, TAdapter>(
+        self,
+        spec: GameSpec<TGame, TNet, TAdapter>,
+        device: Device,
+    ) -> LocalBoxFuture<'static, anyhow::Result<()>>
+    where
+        TGame: Game + Clone + Send + Sync + 'static,
+        TGame::Move: Clone + PartialEq + Send + Sync,
+        TNet: AlphaZeroNet + Send + 'static,
+        TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+    {
+        match self {
+            Mode::Learn {
+                listen,
+                dashboard,
+                run,
+                config,
+            } => Box::pin(async move {
+                let shutdown = Shutdown::install()?;
+                train(spec, device, listen, dashboard, run, config, shutdown).await?;
+                Ok(())
+            }),
+            Mode::Work { learner, config } => Box::pin(work(spec, device, learner, config)),
+            Mode::Profile { weights, config } => Box::pin(profile(spec, device, weights, config)),
+            Mode::Sweep {
+                trials,
+                generations,
+                jobs,
+                run,
+                config,
+            } => Box::pin(sweep(spec, device, trials, generations, jobs, run, config)),
+            Mode::Match {
+                contender,
+                opponent,
+                config,
+                run,
+            } => Box::pin(head_to_head(spec, device, contender, opponent, config, run)),
+            Mode::Arena {
+                checkpoints,
+                baselines,
+                config,
+            } => Box::pin(arena(spec, device, checkpoints, baselines, config)),
+            Mode::Replay { games } => Box::pin(async move { replay(spec, games) }),
+            Mode::ExportSgf { games } => Box::pin(async move { export_sgf(spec, games) }),
+            Mode::ExportSamples { run, format } => {
+                Box::pin(async move { export_samples(run, format) })
+            }
+            Mode::ExportModel {
+                game,
+                weights,
+                batch_size,
+            } => Box::pin(async move { export_model(spec, game, weights, batch_size) }),
+            Mode::Import {
+                file,
+                mapping,
+                output,
+            } => Box::pin(async move { import(spec, file, mapping, output) }),
+            Mode::Report { run } => Box::pin(async move { report(run) }),
+            Mode::Play {
+                engine,
+                human_first,
+                config,
+            } => Box::pin(play(spec, device, engine, human_first, config)),
+            Mode::Gtp { weights, config } => Box::pin(gtp(spec, device, weights, config)),
+            Mode::Uci { weights, config } => Box::pin(uci(spec, device, weights, config)),
+            Mode::PlayServer {
+                weights,
+                listen,
+                page,
+                config,
+            } => Box::pin(play_server(spec, device, weights, listen, page, config)),
+            Mode::Analyze {
+                games,
+                weights,
+                config,
+            } => Box::pin(analyze(spec, device, games, weights, config)),
+            Mode::Heatmaps {
+                moves,
+                weights,
+                config,
+            } => Box::pin(heatmaps(spec, device, moves, weights, config)),
+            Mode::Diff {
+                moves,
+                weights,
+                versus,
+                config,
+            } => Box::pin(diff(spec, device, moves, weights, versus, config)),
+        }
+    }
+}
+
+// A mode with the game's net sized by the config, on the chosen device
+struct Job {
+    mode: Mode,
+    network: NetworkConfig,
+    device: Device,
+}
 
-    let mut net = TicTacToeNet::new(&vs.root());
-    let mut opt = nn::Adam::default().build(&vs, 1e-4)?;
+impl GameVisitor for Job {
+    type Output = LocalBoxFuture<'static, anyhow::Result<()>>;
 
-    let mut start_epoch = 0;
-    if get_checkpoint_file(0)
-        .parent()
-        .map(|p| p.exists())
-        .unwrap_or(false)
+    fn visit<TGame, TNet, TAdapter>(self, spec: GameSpec<TGame, TNet, TAdapter>) -> Self::Output
+    where
+        TGame: Game + Clone + Send + Sync + 'static,
+        TGame::Move: Clone + PartialEq + Send + Sync,
+        TNet: AlphaZeroNet + Send + 'static,
+        TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
     {
-        println!("Checkpoint folder found");
-        let mut cp = 0;
-        while get_checkpoint_file(cp).exists() {
-            cp += 1;
+        match spec.with_network(&self.network) {
+            Ok(spec) => self.mode.run(spec, self.device),
+            Err(e) => Box::pin(async move { Err(e) }),
         }
-        if cp > 0 {
-            cp -= 1;
-            println!("Restoring from checkpoint {cp}");
-            vs.load(get_checkpoint_file(cp))?;
-            start_epoch = cp + 1;
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    logging::init(cli.log_format)?;
+    let smoke = matches!(cli.command, Command::Train { smoke: true, .. });
+    let config = match &cli.command {
+        Command::Train { smoke: true, .. } => cli.overrides.config()?.smoke(),
+        Command::Resume { run, .. } => cli.overrides.resumed(run)?,
+        _ => cli.overrides.config()?,
+    };
+    let started = Instant::now();
+    let registry = GameRegistry::with_builtin_games();
+    let (game, network) = (config.game.clone(), config.network.clone());
+    #[cfg(feature = "open-spiel")]
+    let registry = registry.with_open_spiel(&game)?;
+    let device = config.device.resolve()?;
+    info!(?device, "Going to use the device");
+    if !registry.contains(&game) {
+        anyhow::bail!(
+            "Unknown game {game}, expected one of: {}",
+            registry.names().collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    let mode = match cli.command {
+        Command::Train {
+            run,
+            listen,
+            dashboard,
+            smoke,
+        } => {
+            let run = match (run, smoke) {
+                (Some(dir), _) => RunContext::open(dir)?,
+                (None, true) => RunContext::create("runs", &format!("{game}-smoke"))?,
+                (None, false) => RunContext::create("runs", &game)?,
+            };
+            info!(dir = %run.dir().display(), "Writing the run");
+            Mode::Learn {
+                listen,
+                dashboard,
+                run,
+                config,
+            }
+        }
+        Command::Resume {
+            run,
+            listen,
+            dashboard,
+        } => {
+            info!(dir = %run.display(), "Resuming the run");
+            Mode::Learn {
+                listen,
+                dashboard,
+                run: RunContext::open(run)?,
+                config,
+            }
+        }
+        Command::Selfplay { learner } => Mode::Work { learner, config },
+        Command::Profile { weights } => Mode::Profile { weights, config },
+        Command::Sweep {
+            space,
+            trials,
+            generations,
+            jobs,
+        } => {
+            // A grid unless a number of random trials is given
+            let trials = match trials {
+                Some(trials) => space.random(trials, &mut Seed::random().rng()),
+                None => space.grid()?,
+            };
+            let run = RunContext::create("runs", &format!("{game}-sweep"))?;
+            run.save_config(&space)?;
+            info!(
+                trials = trials.len(),
+                dir = %run.dir().display(),
+                "Writing the sweep"
+            );
+            Mode::Sweep {
+                trials,
+                generations,
+                jobs: jobs.max(1),
+                run,
+                config,
+            }
+        }
+        Command::Eval {
+            contender,
+            against,
+            arena,
+            baselines,
+            time_control,
+        } => {
+            let config = config.match_config(time_control);
+            match (arena, contender.zip(against)) {
+                (Some(checkpoints), _) => Mode::Arena {
+                    checkpoints,
+                    baselines,
+                    config,
+                },
+                (None, Some((contender, opponent))) => {
+                    let run = RunContext::create("runs", &format!("{game}-match"))?;
+                    info!(dir = %run.dir().display(), "Writing the match");
+                    Mode::Match {
+                        contender,
+                        opponent,
+                        config,
+                        run,
+                    }
+                }
+                (None, None) => anyhow::bail!("Expected --arena or a contender and --against"),
+            }
         }
+        Command::Play {
+            engine,
+            human,
+            time_control,
+        } => Mode::Play {
+            engine,
+            human_first: human == Side::First,
+            config: config.match_config(time_control),
+        },
+        Command::Gtp {
+            weights,
+            time_control,
+        } => Mode::Gtp {
+            weights,
+            config: config.match_config(time_control),
+        },
+        Command::Uci { weights } => Mode::Uci {
+            weights,
+            config: config.match_config(None),
+        },
+        Command::PlayServer { weights, listen } => Mode::PlayServer {
+            weights,
+            listen,
+            page: game.starts_with("gomoku").then_some(GOMOKU_PAGE),
+            config: config.match_config(None),
+        },
+        // Plays matches of any game, each visiting the registry for its own
+        Command::EvalServer {
+            models,
+            listen,
+            max_games,
+            max_simulations,
+        } => {
+            let default = MatchLimits::default();
+            let limits = MatchLimits {
+                games: max_games.unwrap_or(default.games),
+                simulations: max_simulations.unwrap_or(default.simulations),
+                ..default
+            };
+            let defaults = config.match_config(None);
+            limits
+                .check(&defaults)
+                .context("The config's match settings are over the server's limits")?;
+            anyhow::ensure!(
+                models.is_dir(),
+                "No models directory at {}",
+                models.display()
+            );
+            let server = EvalServer {
+                models,
+                listen,
+                limits,
+                defaults,
+            };
+            return eval_server(&game, network, device, server).await;
+        }
+        Command::Analyze {
+            file,
+            weights,
+            heatmaps,
+            versus,
+        } => {
+            let config = config.match_config(None);
+            match (heatmaps, versus) {
+                (true, _) => Mode::Heatmaps {
+                    moves: file,
+                    weights,
+                    config,
+                },
+                (false, Some(versus)) => Mode::Diff {
+                    moves: file,
+                    weights,
+                    versus,
+                    config,
+                },
+                (false, None) => Mode::Analyze {
+                    games: file,
+                    weights,
+                    config,
+                },
+            }
+        }
+        Command::Export {
+            format,
+            path,
+            trace_batch,
+        } => match format {
+            ExportFormat::Sgf => Mode::ExportSgf { games: path },
+            ExportFormat::Images => Mode::Replay { games: path },
+            ExportFormat::Charts => {
+                // Opening would create it
+                anyhow::ensure!(path.is_dir(), "No run directory at {}", path.display());
+                Mode::Report {
+                    run: RunContext::open(path)?,
+                }
+            }
+            ExportFormat::Parquet | ExportFormat::Arrow => {
+                anyhow::ensure!(path.is_dir(), "No run directory at {}", path.display());
+                Mode::ExportSamples {
+                    run: RunContext::open(path)?,
+                    format,
+                }
+            }
+            ExportFormat::Torchscript => {
+                anyhow::ensure!(trace_batch > 0, "Tracing needs a batch of at least 1");
+                Mode::ExportModel {
+                    game: game.clone(),
+                    weights: path,
+                    batch_size: trace_batch,
+                }
+            }
+        },
+        Command::Import {
+            file,
+            mapping,
+            output,
+        } => Mode::Import {
+            file,
+            mapping: match mapping {
+                Some(path) => NameMapping::load(&path)?,
+                None => NameMapping::default(),
+            },
+            output,
+        },
+    };
+    registry
+        .visit(
+            &game,
+            Job {
+                mode,
+                network,
+                device,
+            },
+        )
+        .unwrap()
+        .await?;
+    if smoke {
+        info!(
+            "Smoke test passed in {:.1}s",
+            started.elapsed().as_secs_f64()
+        );
     }
+    Ok(())
+}
 
-    // let executor = NetworkBatchedExecutor::new(net);
-    //
-    // let mut worker_handles = FuturesUnordered::new();
+// Of the value, for analyses to mark a move as a blunder
+const BLUNDER_SWING: f32 = 0.2;
+// Positions of each net's line rendered by a diff, the diverging one included
+const DIFF_PLIES: usize = 6;
 
-    for epoch in start_epoch.. {
-        let mut executor = ExecutorScope::new(
-            net,
-            192,
-            128,
-            Duration::from_millis(100),
-            (Kind::Float, vs.device()),
+// Trains for the experiment's generations if it has some, forever otherwise. Returns the stats
+// of the last generation trained.
+async fn train<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    listen: Option<String>,
+    dashboard: Option<String>,
+    run: RunContext,
+    experiment: Config,
+    shutdown: Shutdown,
+) -> anyhow::Result<Option<TrainStats>>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let vs = nn::VarStore::new(device);
+
+    // The experiment as the run started, resuming with other settings keeping it
+    let settings = run.experiment();
+    match settings.exists() {
+        true if Config::load(&settings).ok().as_ref() != Some(&experiment) => warn!(
+            "Resuming with other settings than the run's {}",
+            settings.display()
+        ),
+        true => {}
+        false => experiment.save(&settings)?,
+    }
+    let hyperparameters = experiment.hyperparameters();
+    let games_per_generation = experiment.schedule.games_per_generation;
+    let config = experiment.train_config(run.checkpoints());
+    run.save_config(&config)?;
+    let mut trainer = Trainer::<TNet, TAdapter, TGame>::new(vs, config)?;
+    let net = (spec.build_net)(&trainer.root());
+    // Only creates the EMA variables, the actors get snapshots of them
+    if let Some(root) = trainer.ema_root() {
+        (spec.build_net)(&root);
+    }
+    let start_epoch = trainer.restore()?;
+    info!(seed = trainer.seed().0, "Seeded the run");
+    // Weights self-play uses, only replaced by generations that pass the gating. A resumed
+    // run starts from the latest generation.
+    let mut best = nn::VarStore::new(trainer.device());
+    (spec.build_net)(&best.root());
+    best.copy(trainer.self_play_weights())?;
+    let mut ratings = RatingHistory::load(run.ratings())?;
+    let mut best_elo = trainer
+        .checkpoints()
+        .latest()?
+        .and_then(|metadata| metadata.elo)
+        .unwrap_or(0.0);
+    let mut perfect_play = spec.perfect_play.map(|build| build());
+    let suite = spec.suite.map(|build| build());
+    // Self-play's results by opening since the run (re)started, from the first player's point
+    // of view
+    let mut selfplay_stats = MatchStats::default();
+
+    // Positions from the last few generations, sampled by priority
+    let replay_buffer = Arc::new(RwLock::new(ReplayBuffer::new(
+        experiment.trainer.replay_buffer,
+    )));
+    // A resumed run refills it with the games it had, crashed or stopped by a signal
+    let mut journal = ReplayJournal::open(run.replay_journal())?;
+    let games = journal.load(
+        experiment.trainer.replay_buffer,
+        &spec.start,
+        spec.openings.as_ref(),
+    )?;
+    if !games.is_empty() {
+        let mut replay_buffer = replay_buffer.write().unwrap();
+        for mut game in games {
+            set_ownership_targets::<TGame, TNet, TAdapter>(&mut game.samples);
+            replay_buffer.extend(game.samples);
+        }
+        info!(
+            positions = replay_buffer.len(),
+            "Restored the replay buffer"
         );
+    }
+    let mut data_store = DataStore::open(run.selfplay(), 100)?;
 
-        let total_games = 600;
-        // let total_games = 1;
-        for _ in 0..total_games {
-            executor.spawn(|handle| async {
-                generate_self_played_game::<BoardState, TicTacToeNet, TicTacToeAlphaZeroAdapter, _>(
-                    BoardState::new(),
-                    // 128,
-                    // 512,
-                    // 2048,
-                    32,
-                    1.0 / 32.0,
-                    |_| 1.0,
-                    handle,
-                )
-                .await
-            });
+    // Actors keep playing while the learner trains, picking up the weights it publishes
+    // after every generation
+    let (net_tx, net_rx) = mpsc::channel(1);
+    let (games_tx, mut games_rx) = mpsc::unbounded_channel();
+    let server = match listen {
+        Some(addr) => {
+            let server = GameServer::bind(
+                addr,
+                spec.start.clone(),
+                spec.openings.clone(),
+                &best,
+                games_tx.clone(),
+            )
+            .await?;
+            info!(addr = %server.local_addr(), "Accepting workers");
+            Some(server)
         }
+        None => None,
+    };
+    let executor = actor_executor(
+        snapshot(&*spec.build_net, &best)?,
+        trainer.device(),
+        &experiment.executor,
+    );
+    let batch_stats = executor.batch_stats();
+    let mut actors = tokio::spawn(self_play::<TGame, TNet, TAdapter>(
+        executor,
+        spec.start.clone(),
+        spec.openings.clone(),
+        trainer.seed().derive("self-play").derive(start_epoch),
+        experiment.clone(),
+        net_rx,
+        games_tx,
+        shutdown.clone(),
+    ));
 
-        let mut batch_size = 128;
+    let mut metrics = Metrics::new()
+        .with(ConsoleSink)
+        .with(CsvSink::create(run.metrics().join("metrics.csv"))?)
+        .with(TensorBoardSink::create(run.metrics())?);
+    let dashboard = match dashboard {
+        Some(addr) => {
+            let dashboard = Dashboard::bind(addr, run.games()).await?;
+            info!("Serving the dashboard on http://{}", dashboard.local_addr());
+            metrics = metrics.with(dashboard.sink());
+            Some(dashboard)
+        }
+        None => None,
+    };
 
-        let (lim_tx, mut lim_rx) = tokio::sync::mpsc::channel(1);
-        tokio::spawn({
-            async move {
-                for _ in 0..24 {
-                    tokio::time::sleep(Duration::from_secs(6)).await;
-                    if lim_tx.send(()).await.is_err() {
-                        break;
+    let mut last_stats = None;
+    for epoch in start_epoch..experiment.schedule.generations.unwrap_or(usize::MAX) {
+        let mut history = vec![];
+        // Whole games are held out, their positions are too alike to split them
+        let mut split = trainer.seed().derive("validation").derive(epoch).rng();
+        let mut validation = vec![];
+        let mut generation_stats = MatchStats::default();
+        // Once the actors finished their games in progress on a shutdown
+        let mut stopped = false;
+        while history.len() < games_per_generation {
+            let mut game = match stopped {
+                true => match games_rx.try_recv() {
+                    Ok(game) => game,
+                    Err(_) => break,
+                },
+                false => tokio::select! {
+                    biased;
+                    Some(game) = games_rx.recv() => game,
+                    // Actors only stop on an error or a shutdown
+                    result = &mut actors => {
+                        result??;
+                        anyhow::ensure!(shutdown.requested(), "Self-play stopped");
+                        stopped = true;
+                        continue;
                     }
-                }
+                },
+            };
+            for sample in &mut game.samples {
+                sample.generation = epoch;
             }
-        });
+            set_ownership_targets::<TGame, TNet, TAdapter>(&mut game.samples);
+            // The first sample's player moves first unless the opening handed over the move
+            let switched = match (&spec.openings, game.opening) {
+                (Some(book), Some(opening)) => book.switches_player(opening),
+                _ => false,
+            };
+            let value = game.samples.first().map_or(0.5, |sample| sample.value);
+            generation_stats.record(
+                if switched { 1.0 - value } else { value },
+                true,
+                game.opening,
+            );
+            data_store.write_game::<TGame, TNet, TAdapter>(&game.samples)?;
+            if split.gen_bool(trainer.config().validation_fraction) {
+                validation.extend(game.samples.iter().cloned());
+            } else {
+                journal.write(&game)?;
+                replay_buffer
+                    .write()
+                    .unwrap()
+                    .extend(game.samples.iter().cloned());
+            }
+            history.push(game.samples);
+            if let Some(dashboard) = &dashboard {
+                dashboard.set_progress(Progress {
+                    generation: epoch,
+                    games: history.len(),
+                    games_per_generation,
+                });
+            }
+        }
+        data_store.flush()?;
+        journal.flush()?;
+        // The weights are the last checkpoint's and the games are in the journal
+        if stopped {
+            info!(
+                generation = epoch,
+                "Stopped, continue with `resume {}`",
+                run.dir().display()
+            );
+            return Ok(last_stats);
+        }
 
-        let mut history = vec![];
+        let total_score: f32 = history.iter().map(|game| game[0].value).sum();
+        let played_positions: usize = history.iter().map(Vec::len).sum();
+        let new_positions = played_positions - validation.len();
+        let games = history.len() as f64;
+        metrics.scalar("selfplay/average_score", epoch, total_score as f64 / games)?;
+        let first_player = generation_stats.first_player();
+        metrics.scalar("selfplay/first_player_score", epoch, first_player.score())?;
+        metrics.scalar(
+            "selfplay/draw_rate",
+            epoch,
+            first_player.draws as f64 / games,
+        )?;
+        selfplay_stats.merge(&generation_stats);
+        std::fs::write(run.dir().join("openings.md"), selfplay_stats.to_markdown())?;
+        metrics.scalar(
+            "selfplay/game_length",
+            epoch,
+            played_positions as f64 / games,
+        )?;
+        if let Some((fill_rate, batches)) = batch_stats.take_fill_rate() {
+            metrics.scalar("selfplay/batch_fill_rate", epoch, fill_rate)?;
+            metrics.scalar("selfplay/batches", epoch, batches as f64)?;
+        }
+        metrics.scalar(
+            "replay/positions",
+            epoch,
+            replay_buffer.read().unwrap().len() as f64,
+        )?;
+        let data = DataReport::new(
+            replay_buffer.read().unwrap().iter(),
+            spec.hash,
+            spec.notation,
+        );
+        data.report(&mut metrics, epoch)?;
+        let report = run.data_report(epoch);
+        std::fs::write(&report, data.to_markdown())
+            .with_context(|| format!("Failed to write {}", report.display()))?;
+
+        let stats = trainer
+            .train_generation(&net, &replay_buffer, new_positions, &validation, epoch)
+            .await;
+        stats.report(&mut metrics, epoch)?;
+        metrics.flush()?;
+        last_stats = Some(stats);
+
+        trainer.save_checkpoint(epoch)?;
+
+        // Absolute strength of the new generation, for solved games
+        if let Some(PerfectPlay { positions, solver }) = &mut perfect_play {
+            let net = ExecutorScope::<(), _>::new(
+                snapshot(&*spec.build_net, trainer.self_play_weights())?,
+                64,
+                64,
+                Duration::from_millis(10),
+                (Kind::Float, trainer.device()),
+            );
+            let report = perfect_play_eval(
+                positions,
+                64,
+                || {
+                    MctsAgent::new(
+                        NetworkEvaluator::<TGame, TNet, TAdapter>::new(net.handle()),
+                        hyperparameters.simulations,
+                        hyperparameters.c_puct,
+                        0.0,
+                    )
+                },
+                solver.as_mut(),
+            )
+            .await;
+            net.join().await;
+            let positions = report.positions.max(1) as f64;
+            metrics.scalar("perfect_play/blunder_rate", epoch, report.blunder_rate())?;
+            metrics.scalar(
+                "perfect_play/lost_wins",
+                epoch,
+                report.lost_wins as f64 / positions,
+            )?;
+            metrics.flush()?;
+        }
+
+        // Accuracy on the game's test positions, per tag
+        if let Some(suite) = &suite {
+            let net = ExecutorScope::<(), _>::new(
+                snapshot(&*spec.build_net, trainer.self_play_weights())?,
+                64,
+                64,
+                Duration::from_millis(10),
+                (Kind::Float, trainer.device()),
+            );
+            let report = suite
+                .run(64, || {
+                    MctsAgent::new(
+                        NetworkEvaluator::<TGame, TNet, TAdapter>::new(net.handle()),
+                        hyperparameters.simulations,
+                        hyperparameters.c_puct,
+                        0.0,
+                    )
+                })
+                .await;
+            net.join().await;
+            metrics.scalar("suite/solved", epoch, report.total().fraction())?;
+            for (tag, score) in &report.tags {
+                metrics.scalar(&format!("suite/{tag}"), epoch, score.fraction())?;
+            }
+            metrics.flush()?;
+        }
+
+        let promote = match trainer.config().gating {
+            Some(gating) => {
+                let stats = play_match::<TGame, TNet, TAdapter>(
+                    &spec.start,
+                    spec.openings.as_ref(),
+                    &gating.games,
+                    snapshot(&*spec.build_net, trainer.self_play_weights())?,
+                    snapshot(&*spec.build_net, &best)?,
+                    trainer.device(),
+                )
+                .await;
+                let result = stats.total();
+                let significance = Significance::of(&result);
+                metrics.scalar("gating/score", epoch, result.score())?;
+                metrics.scalar("gating/score_lower", epoch, significance.wilson.0)?;
+                metrics.scalar("gating/score_upper", epoch, significance.wilson.1)?;
+                metrics.scalar("gating/los", epoch, significance.likelihood_of_superiority)?;
+                // Scores by color, which the first player's advantage could hide
+                metrics.scalar("gating/score_as_first", epoch, stats.as_first.score())?;
+                metrics.scalar("gating/score_as_second", epoch, stats.as_second.score())?;
+                // Fewer than configured when the SPRT stopped the match
+                metrics.scalar("gating/games", epoch, result.games() as f64)?;
+                metrics.flush()?;
+                info!(
+                    generation = epoch,
+                    wins = result.wins,
+                    draws = result.draws,
+                    losses = result.losses,
+                    "Against self-play's weights, scoring {significance}"
+                );
 
-        let mut total_score = 0.0;
-        let mut total_length = 0;
-        loop {
-            tokio::select! {
-                Some(()) = lim_rx.recv() => {
-                    println!("Increasing parallelism by 16");
-                    executor.increase_parallelism(16).await;
-                    batch_size += 16;
-                    executor.set_batch_size(batch_size).await;
+                // Rated relative to the weights it played against
+                let elo = best_elo + elo_difference(&result);
+                trainer.checkpoints().set_elo(epoch, elo)?;
+                ratings.record(
+                    format!("gen{epoch:02}"),
+                    RatingEntry {
+                        step: epoch,
+                        elo,
+                        games: result.games(),
+                    },
+                );
+                ratings.save(run.ratings())?;
+                metrics.scalar("elo/rating", epoch, elo)?;
+                metrics.flush()?;
+
+                let promote = gating.passes(&result);
+                if promote {
+                    best_elo = elo;
+                    if significance.wilson.0 <= 0.5 {
+                        warn!(
+                            generation = epoch,
+                            "Promoted, though the match can't tell it from self-play's weights"
+                        );
+                    }
                 }
-                task_result = executor.next() => {
-                    let res = match task_result {
-                        Some(v) => v,
-                        None => break,
+                promote
+            }
+            None => true,
+        };
+        if trainer.should_stop(epoch, &stats)? {
+            info!(
+                dir = %trainer.checkpoints().best_dir().display(),
+                "Kept the best checkpoint"
+            );
+            break;
+        }
+        if promote {
+            best.copy(trainer.self_play_weights())?;
+            let published = snapshot(&*spec.build_net, &best)?;
+            // Stopped actors on a shutdown leave their last games to the next generation
+            if net_tx.send(published).await.is_err() && !shutdown.requested() {
+                return actors.await?.map(|()| last_stats);
+            }
+            if let Some(server) = &server {
+                server.publish(&best)?;
+            }
+        }
+
+        let sample_games = history.iter().choose_multiple(&mut thread_rng(), 20);
+        let values = run.value_plot(epoch);
+        plot_value_trajectories(&sample_games)
+            .save(&values)
+            .with_context(|| format!("Failed to write {}", values.display()))?;
+        if let Some(visualizer) = &spec.visualizer {
+            for (i, sample_game) in sample_games.into_iter().enumerate() {
+                let image = run.game_image(epoch, i);
+                visualizer
+                    .render_history(sample_game)
+                    .save(&image)
+                    .with_context(|| format!("Failed to write {}", image.display()))?;
+            }
+            // A single game, its frames taking long to encode
+            if let Some(frames) = history.first().and_then(|game| visualizer.animate(game)) {
+                save_animation(frames, &run.game_animation(epoch, 0))?;
+            }
+        }
+    }
+
+    actors.abort();
+    Ok(last_stats)
+}
+
+// Trials are trained `jobs` at a time, each for `generations` and in a run directory of
+// its own below the sweep's. A failing trial doesn't stop the others. Their losses are each on
+// their own self-play data, so the final weights of the trials then play a round-robin, which
+// ranks them.
+async fn sweep<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    trials: Vec<Hyperparameters>,
+    generations: usize,
+    jobs: usize,
+    run: RunContext,
+    config: Config,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    std::fs::write(
+        run.dir().join("trials.json"),
+        serde_json::to_string_pretty(&trials)?,
+    )?;
+    let mut results = futures::stream::iter(trials.into_iter().enumerate())
+        .map(|(i, hyperparameters)| {
+            let spec = spec.clone();
+            let root = run.dir().join("trials");
+            let mut experiment = config.with_hyperparameters(hyperparameters);
+            experiment.schedule.generations = Some(generations);
+            async move {
+                let trial_run = RunContext::create(root, &format!("trial{i}"))?;
+                let dir = trial_run.dir().to_path_buf();
+                // Trials are short, a signal simply ends the sweep
+                let shutdown = Shutdown::never();
+                let stats =
+                    match train(spec, device, None, None, trial_run, experiment, shutdown).await {
+                        Ok(Some(stats)) => Ok(stats),
+                        Ok(None) => Err("No generation was trained".to_owned()),
+                        Err(err) => Err(format!("{err:#}")),
                     };
-                    total_score += res[0].2;
-                    total_length += res.len();
-                    history.push(res);
-                    println!("Game finished, {} more to go", executor.len());
-                }
+                anyhow::Ok(TrialResult {
+                    hyperparameters,
+                    run: dir,
+                    stats,
+                    elo: None,
+                })
             }
+        })
+        .buffered(jobs)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // Trials by participant
+    let mut arena = Arena::new(spec.build_net.clone(), device);
+    let mut rated = vec![];
+    for (i, result) in results.iter().enumerate() {
+        let checkpoints = CheckpointManager::new(result.run.join("checkpoints"), 1);
+        if result.stats.is_ok() && arena.add_latest(format!("trial{i}"), &checkpoints)? {
+            rated.push(i);
         }
+    }
+    let mut report = String::new();
+    if arena.len() > 1 {
+        let table = arena
+            .round_robin::<TGame, TAdapter>(
+                &spec.start,
+                spec.openings.as_ref(),
+                spec.heuristic,
+                &config.match_config(None),
+            )
+            .await?;
+        for (i, elo) in rated.into_iter().zip(bradley_terry(&table, 2.0)) {
+            results[i].elo = Some(elo);
+        }
+        report = format!("\n{}", table.to_markdown());
+    } else {
+        warn!(
+            trials = arena.len(),
+            "Fewer than two trials to rate, the sweep ranks none"
+        );
+    }
+
+    let table = comparison_table(&results) + &report;
+    println!("{table}");
+    std::fs::write(run.dir().join("sweep.md"), table)?;
+    Ok(())
+}
+
+// Plays a generation of games like self-play does, with the config's search and executor,
+// timing every stage
+async fn profile<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    weights: Option<PathBuf>,
+    config: Config,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let mut vs = nn::VarStore::new(device);
+    let net = (spec.build_net)(&vs.root());
+    if let Some(weights) = &weights {
+        vs.load(weights)
+            .with_context(|| format!("Failed to load {}", weights.display()))?;
+    }
+    let profile = Arc::new(SelfPlayProfile::default());
+    let (simulations, c_puct) = (config.search.simulations, config.search.c_puct);
+    let mut executor = actor_executor(net, device, &config.executor);
+    let batch_stats = executor.batch_stats();
+    let seed = Seed::random();
+    let started = Instant::now();
+    for game in 0..config.schedule.games_per_generation {
+        let (start, openings) = (spec.start.clone(), spec.openings.clone());
+        let profile = profile.clone();
+        executor.spawn(|handle| {
+            generate_self_played_game::<TGame, TNet, TAdapter, _>(
+                start,
+                openings,
+                simulations,
+                c_puct,
+                |_| 1.0,
+                seed.derive(game),
+                handle,
+                Some(profile),
+            )
+        });
+    }
+    while executor.next().await.is_some() {}
+    let wall = started.elapsed();
+    executor.join().await;
+    let (busy, forward) = batch_stats.forward_times();
+    println!("{}", profile.report(wall, busy, forward));
+    Ok(())
+}
+
+// Writes every move of the games to the run's logs and a summary of the result next to them
+async fn head_to_head<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    contender: Contender,
+    opponent: Contender,
+    config: MatchConfig,
+    run: RunContext,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Clone + PartialEq + Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let (stats, logs) = play_head_to_head::<TGame, TNet, TAdapter>(
+        &*spec.build_net,
+        device,
+        &spec.start,
+        spec.openings.as_ref(),
+        spec.heuristic,
+        &config,
+        &contender,
+        &opponent,
+    )
+    .await?;
+
+    // One game per line, `agent` in the moves being the contender
+    let mut moves = String::new();
+    for log in &logs {
+        moves += &serde_json::to_string(log)?;
+        moves.push('\n');
+    }
+    std::fs::write(run.logs().join("moves.jsonl"), moves)?;
+    let (contender_name, opponent_name) = (contender.to_string(), opponent.to_string());
+    let games = logs
+        .iter()
+        .map(|log| (log, contender_name.as_str(), opponent_name.as_str()));
+    if let Some(games) = portable_games(&spec, games) {
+        std::fs::write(run.dir().join("games.txt"), games)?;
+    }
+    let summary = format!(
+        "{}\n{}",
+        match_summary(&contender, &opponent, &stats.total()),
+        stats.to_markdown()
+    );
+    println!("{summary}");
+    std::fs::write(run.dir().join("summary.md"), summary)?;
+    Ok(())
+}
+
+// Plays every checkpoint and baseline against every other one and writes the cross-table next
+// to the checkpoints
+async fn arena<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    dir: PathBuf,
+    baselines: Vec<Baseline>,
+    config: MatchConfig,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Clone + PartialEq + Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let mut arena = Arena::new(spec.build_net.clone(), device);
+    let checkpoints = CheckpointManager::new(&dir, 1);
+    arena.add_checkpoints(&checkpoints)?;
+    let listed = checkpoints.list()?;
+    for baseline in baselines {
+        arena.add_baseline(baseline);
+    }
+    anyhow::ensure!(
+        arena.len() > 1,
+        "Found {} checkpoints in {}, at least two participants are needed",
+        listed.len(),
+        dir.display()
+    );
+    let table = arena
+        .round_robin::<TGame, TAdapter>(
+            &spec.start,
+            spec.openings.as_ref(),
+            spec.heuristic,
+            &config,
+        )
+        .await?;
 
-        println!("Average score is {}", total_score / total_games as f32);
+    // The participants are rated too, and the ratings go into the run's history. Checkpoints
+    // come first, in the order they are listed.
+    let elo = bradley_terry(&table, 2.0);
+    let step = listed.last().map_or(0, |metadata| metadata.generation);
+    let ratings_file = dir.parent().unwrap_or(&dir).join("ratings.json");
+    let mut ratings = RatingHistory::load(&ratings_file)?;
+    let mut report = table.to_markdown();
+    report += "\n| | elo |\n|---|---|\n";
+    for (i, elo) in elo.into_iter().enumerate() {
+        report += &format!("| {} | {elo:.0} |\n", table.names[i]);
+        if let Some(metadata) = listed.get(i) {
+            checkpoints.set_elo(metadata.generation, elo)?;
+        }
+        ratings.record(
+            table.names[i].clone(),
+            RatingEntry {
+                step,
+                elo,
+                games: table.total(i).games(),
+            },
+        );
+    }
+    ratings.save(&ratings_file)?;
+    let games = table
+        .games
+        .iter()
+        .map(|(i, j, log)| (log, table.names[*i].as_str(), table.names[*j].as_str()));
+    if let Some(games) = portable_games(&spec, games) {
+        std::fs::write(dir.join("arena_games.txt"), games)?;
+    }
+    report += "\n";
+    report += &table.colors_to_markdown();
+    println!("{report}");
+    std::fs::write(dir.join("arena.md"), report)?;
+    Ok(())
+}
+
+// The games in the portable text format, for games with a notation. The names are those of
+// each log's agent and opponent.
+fn portable_games<'a, TGame, TNet, TAdapter>(
+    spec: &GameSpec<TGame, TNet, TAdapter>,
+    games: impl Iterator<Item = (&'a GameLog, &'a str, &'a str)>,
+) -> Option<String>
+where
+    TGame: Game + Clone,
+    TGame::Move: Clone,
+{
+    let notation = spec.notation?;
+    let games = games
+        .map(|(log, agent, opponent)| {
+            PortableGame::from_log(&spec.start, spec.openings.as_ref(), log, agent, opponent)
+                .to_text(&spec.start, notation)
+        })
+        .collect::<Vec<_>>();
+    Some(games.join("\n"))
+}
+
+// Writes an image of every game in the file next to it, as `<file>-<game>.png`, and for
+// games that have them an animation as `<file>-<game>.gif` and the final position with the
+// value graph as `<file>-<game>.svg`
+fn replay<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    file: PathBuf,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone,
+    TGame::Move: Clone + PartialEq,
+{
+    let visualizer = spec
+        .visualizer
+        .as_ref()
+        .context("The game has no visualizer")?;
+    let games = spec.read_games(&file)?;
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    for (i, game) in games.iter().enumerate() {
+        let image = file.with_file_name(format!("{stem}-{i}.png"));
+        let samples = game.samples(&spec.start);
+        visualizer.render_history(&samples).save(&image)?;
+        if let Some(frames) = visualizer.animate(&samples) {
+            save_animation(frames, &image.with_extension("gif"))?;
+        }
+        if let Some(svg) = visualizer.render_svg(&samples, samples.len()) {
+            std::fs::write(image.with_extension("svg"), svg)?;
+        }
         println!(
-            "Average length is {}",
-            total_length as f32 / total_games as f32
+            "{} - {}: {}, {} moves, written to {}",
+            game.first,
+            game.second,
+            game.result,
+            game.moves.len(),
+            image.display()
         );
+    }
+    Ok(())
+}
 
-        net = executor.join().await;
+// A PNG per metric of the run, into its plots directory
+fn report(run: RunContext) -> anyhow::Result<()> {
+    let series = read_metrics(run.metrics())
+        .with_context(|| format!("Failed to read the metrics of {}", run.dir().display()))?;
+    let charts = plot_series(&series, &run.plots())?;
+    println!(
+        "{} charts written to {}",
+        charts.len(),
+        run.plots().display()
+    );
+    Ok(())
+}
 
-        let sample_games = history
-            .iter()
-            .choose_multiple(&mut thread_rng(), 20)
-            .into_iter()
-            .map(Vec::clone)
-            .collect::<Vec<_>>();
+// Next to the file, with the `.sgf` extension
+fn export_sgf<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    file: PathBuf,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone,
+    TGame::Move: Clone,
+{
+    let sgf = spec.sgf.context("The game has no SGF format")?;
+    let games = spec.read_games(&file)?;
+    let text = games
+        .iter()
+        .map(|game| (sgf.write)(&spec.start, game))
+        .collect::<String>();
+    let exported = file.with_extension("sgf");
+    std::fs::write(&exported, text)
+        .with_context(|| format!("Failed to write {}", exported.display()))?;
+    println!("{} games written to {}", games.len(), exported.display());
+    Ok(())
+}
+
+// Into the run's directory as `samples.parquet` or `samples.arrow`
+#[cfg(feature = "arrow")]
+fn export_samples(run: RunContext, format: ExportFormat) -> anyhow::Result<()> {
+    let (format, name) = match format {
+        ExportFormat::Parquet => (SampleFormat::Parquet, "samples.parquet"),
+        ExportFormat::Arrow => (SampleFormat::Arrow, "samples.arrow"),
+        _ => unreachable!("{format:?} isn't a format of samples"),
+    };
+    anyhow::ensure!(
+        run.selfplay().is_dir(),
+        "{} has no self-play samples",
+        run.dir().display()
+    );
+    let store = DataStore::open(run.selfplay(), 1)?;
+    let path = run.dir().join(name);
+    let rows = pytorch::selfplay::export_samples(&store, &path, format)?;
+    println!("{rows} positions written to {}", path.display());
+    Ok(())
+}
+
+#[cfg(not(feature = "arrow"))]
+fn export_samples(_run: RunContext, _format: ExportFormat) -> anyhow::Result<()> {
+    anyhow::bail!("Exporting samples needs a build with the arrow feature")
+}
+
+// Writes the net of the weights traced as `<weights>.pt` and its description as
+// `<weights>.json`. On the CPU, for the module to load anywhere.
+fn export_model<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    game: String,
+    weights: PathBuf,
+    batch_size: usize,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone,
+    TNet: AlphaZeroNet,
+    TAdapter: AlphaZeroAdapter<TGame, TNet>,
+{
+    let mut vs = nn::VarStore::new(Device::Cpu);
+    let net = (spec.build_net)(&vs.root());
+    vs.load(&weights)
+        .with_context(|| format!("Failed to load {}", weights.display()))?;
+    let description = describe_model::<TGame, TNet, TAdapter>(
+        &game,
+        &net,
+        &spec.start,
+        batch_size,
+        spec.notation,
+    )?;
+    let module = weights.with_extension("pt");
+    let input = example_input::<TGame, TNet, TAdapter>(&spec.start, batch_size);
+    export_torchscript(&net, &input, &module)?;
+    let json = weights.with_extension("json");
+    std::fs::write(&json, serde_json::to_string_pretty(&description)?)
+        .with_context(|| format!("Failed to write {}", json.display()))?;
+    println!(
+        "Net traced at a batch of {batch_size} written to {}, described in {}",
+        module.display(),
+        json.display()
+    );
+    Ok(())
+}
+
+fn import<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    file: PathBuf,
+    mapping: NameMapping,
+    output: PathBuf,
+) -> anyhow::Result<()>
+where
+    TGame: Game,
+{
+    let vs = nn::VarStore::new(Device::Cpu);
+    let _net = (spec.build_net)(&vs.root());
+    let report = import_weights(&vs, &file, &mapping)?;
+    if !report.unused.is_empty() {
+        warn!(
+            "Weights named after no variable of the net, map them or drop them: {}",
+            report.unused.join(", ")
+        );
+    }
+    vs.save(&output)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    println!(
+        "{} weights imported, {} dropped, written to {}",
+        report.imported,
+        report.dropped.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+// A game between the engine and a human in the terminal. Nets search with the config's
+// settings and always play their best move.
+async fn play<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    engine: Contender,
+    human_first: bool,
+    config: MatchConfig,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Clone + PartialEq + Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let notation = spec
+        .notation
+        .context("The game has no notation to read moves in")?;
+    let (mut agent, executor) = match &engine {
+        Contender::Weights(path) => {
+            let mut vs = nn::VarStore::new(device);
+            let net = (spec.build_net)(&vs.root());
+            vs.load(path)
+                .with_context(|| format!("Failed to load {}", path.display()))?;
+            let executor = ExecutorScope::<(), _>::new(
+                net,
+                config.parallelism,
+                config.parallelism,
+                Duration::from_millis(10),
+                (Kind::Float, vs.device()),
+            );
+            let agent = MctsAgent::new(
+                NetworkEvaluator::<TGame, TNet, TAdapter>::new(executor.handle()),
+                config.simulations,
+                config.c_puct,
+                0.0,
+            )
+            .with_time_control(config.time_control);
+            (ContenderAgent::Net(Box::new(agent)), Some(executor))
+        }
+        Contender::Baseline(baseline) => (
+            ContenderAgent::Baseline(baseline.agent(spec.heuristic, 0.0)),
+            None,
+        ),
+    };
+    println!("Playing against {engine}, type quit to stop");
+    let score = play_in_terminal(
+        &spec.start,
+        notation,
+        spec.text,
+        &mut agent,
+        human_first,
+        std::io::stdin().lock(),
+        std::io::stdout(),
+    )
+    .await?;
+    if let Some(executor) = executor {
+        executor.join().await;
+    }
+    if score.is_none() {
+        println!("Game abandoned");
+    }
+    Ok(())
+}
+
+// Serves GTP on stdin and stdout with the net of the weights, searching with the config's
+// settings. Logs go to stderr, out of the controller's way.
+async fn gtp<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    weights: PathBuf,
+    config: MatchConfig,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Clone + PartialEq + Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let board = spec.gtp.context("The game has no board GTP can address")?;
+    let mut vs = nn::VarStore::new(device);
+    let net = (spec.build_net)(&vs.root());
+    vs.load(&weights)
+        .with_context(|| format!("Failed to load {}", weights.display()))?;
+    let executor = ExecutorScope::<(), _>::new(
+        net,
+        config.parallelism,
+        config.parallelism,
+        Duration::from_millis(10),
+        (Kind::Float, vs.device()),
+    );
+    let handle = executor.handle();
+    let mut engine = GtpEngine::new(
+        spec.start,
+        board,
+        spec.text,
+        config.time_control,
+        |time_control| {
+            MctsAgent::new(
+                NetworkEvaluator::<TGame, TNet, TAdapter>::new(handle.clone()),
+                config.simulations,
+                config.c_puct,
+                0.0,
+            )
+            .with_time_control(time_control)
+        },
+    );
+    info!(weights = %weights.display(), "Serving GTP");
+    engine
+        .serve(std::io::stdin().lock(), std::io::stdout())
+        .await?;
+    executor.join().await;
+    Ok(())
+}
+
+// Plays browsers with the net of the weights, searching with the config's settings
+async fn play_server<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    weights: PathBuf,
+    listen: String,
+    page: Option<&'static str>,
+    config: MatchConfig,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Clone + PartialEq + Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let notation = spec
+        .notation
+        .context("The game has no notation to send moves in")?;
+    let mut vs = nn::VarStore::new(device);
+    let net = (spec.build_net)(&vs.root());
+    vs.load(&weights)
+        .with_context(|| format!("Failed to load {}", weights.display()))?;
+    let executor = ExecutorScope::<(), _>::new(
+        net,
+        config.parallelism,
+        config.parallelism,
+        Duration::from_millis(10),
+        (Kind::Float, vs.device()),
+    );
+    let handle = executor.handle();
+    let mut server = PlayServer::new(
+        spec.start,
+        notation,
+        spec.text,
+        || NetworkEvaluator::<TGame, TNet, TAdapter>::new(handle.clone()),
+        config.simulations,
+        config.c_puct,
+    );
+    if let Some(page) = page {
+        server = server.with_page(page);
+    }
+    let result = server.bind_and_serve(listen).await;
+    executor.join().await;
+    result
+}
+
+// A match posted to the evaluation server, on the net sized by the config
+struct ServiceMatch {
+    request: MatchRequest,
+    config: MatchConfig,
+    network: NetworkConfig,
+    device: Device,
+}
+
+impl GameVisitor for ServiceMatch {
+    type Output = LocalBoxFuture<'static, anyhow::Result<MatchReport>>;
+
+    fn visit<TGame, TNet, TAdapter>(self, spec: GameSpec<TGame, TNet, TAdapter>) -> Self::Output
+    where
+        TGame: Game + Clone + Send + Sync + 'static,
+        TGame::Move: Clone + PartialEq + Send + Sync,
+        TNet: AlphaZeroNet + Send + 'static,
+        TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+    {
+        Box::pin(async move {
+            let spec = spec.with_network(&self.network)?;
+            let contender: Contender = self.request.contender.parse()?;
+            let opponent: Contender = self.request.opponent.parse()?;
+            let (stats, _) = play_head_to_head::<TGame, TNet, TAdapter>(
+                &*spec.build_net,
+                self.device,
+                &spec.start,
+                spec.openings.as_ref(),
+                spec.heuristic,
+                &self.config,
+                &contender,
+                &opponent,
+            )
+            .await?;
+            let summary = format!(
+                "{}\n{}",
+                match_summary(&contender, &opponent, &stats.total()),
+                stats.to_markdown()
+            );
+            Ok(MatchReport::new(stats, summary))
+        })
+    }
+}
+
+// How the evaluation server is set up
+struct EvalServer {
+    models: PathBuf,
+    listen: String,
+    limits: MatchLimits,
+    // Of the matches that don't set their own
+    defaults: MatchConfig,
+}
+
+// Plays the matches posted over HTTP on the device, one at a time, with the weights of the
+// models directory. The config's game only tells which OpenSpiel game to load.
+async fn eval_server(
+    game: &str,
+    network: NetworkConfig,
+    device: Device,
+    server: EvalServer,
+) -> anyhow::Result<()> {
+    let registry = GameRegistry::with_builtin_games();
+    #[cfg(feature = "open-spiel")]
+    let registry = registry.with_open_spiel(game)?;
+    #[cfg(not(feature = "open-spiel"))]
+    let _ = game;
+    let games = registry.names().map(str::to_owned).collect();
+    let play = |request: MatchRequest, config| {
+        let game = request.game.clone();
+        let visitor = ServiceMatch {
+            request,
+            config,
+            network: network.clone(),
+            device,
+        };
+        // Only known games are queued
+        registry.visit(&game, visitor).unwrap()
+    };
+    let service =
+        EvalService::new(games, server.models, server.defaults, play).with_limits(server.limits);
+    service.bind_and_serve(server.listen).await
+}
+
+// Serves UCI on stdin and stdout with the net of the weights, searching the config's
+// simulations unless the controller limits the time or the nodes
+async fn uci<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    weights: PathBuf,
+    config: MatchConfig,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Clone + PartialEq + Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let format = spec
+        .uci
+        .context("The game has no UCI format, it's for chess")?;
+    let mut vs = nn::VarStore::new(device);
+    let net = (spec.build_net)(&vs.root());
+    vs.load(&weights)
+        .with_context(|| format!("Failed to load {}", weights.display()))?;
+    let executor = ExecutorScope::<(), _>::new(
+        net,
+        config.parallelism,
+        config.parallelism,
+        Duration::from_millis(10),
+        (Kind::Float, vs.device()),
+    );
+    let mut engine = UciEngine::new(
+        spec.start,
+        format,
+        NetworkEvaluator::<TGame, TNet, TAdapter>::new(executor.handle()),
+        config.simulations,
+        config.c_puct,
+    );
+    info!(weights = %weights.display(), "Serving UCI");
+    engine
+        .serve(std::io::BufReader::new(std::io::stdin()), std::io::stdout())
+        .await?;
+    executor.join().await;
+    Ok(())
+}
 
-        let history = history
+// Analyzes the games of a file written by a match or an arena, or a single game given as a
+// list of moves, with the config's search
+async fn analyze<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    file: PathBuf,
+    weights: PathBuf,
+    config: MatchConfig,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Clone + PartialEq + Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let notation = spec
+        .notation
+        .context("The game has no notation to read games in")?;
+    let text = std::fs::read_to_string(&file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let games = match text.trim_start().starts_with(['[', '(']) {
+        true => spec
+            .read_games(&file)?
             .into_iter()
-            .flatten()
-            .map(|(state, policy, value)| {
-                (
-                    TicTacToeAlphaZeroAdapter::convert_game_to_nn_input(&state),
-                    TicTacToeAlphaZeroAdapter::convert_policy_to_nn(
-                        &policy,
-                        &state.get_state().get_moves().unwrap(),
-                    ),
-                    value,
+            .map(|game| game.moves.into_iter().map(|m| m.r#move).collect())
+            .collect(),
+        false => vec![parse_move_list(&spec.start, notation, &text)?],
+    };
+
+    let mut vs = nn::VarStore::new(device);
+    let net = (spec.build_net)(&vs.root());
+    vs.load(&weights)
+        .with_context(|| format!("Failed to load {}", weights.display()))?;
+    let executor = ExecutorScope::<(), _>::new(
+        net,
+        1,
+        1,
+        Duration::from_millis(10),
+        (Kind::Float, vs.device()),
+    );
+    for (i, moves) in games.iter().enumerate() {
+        let evaluator = NetworkEvaluator::<TGame, TNet, TAdapter>::new(executor.handle());
+        let reports = analyze_game(
+            &spec.start,
+            moves,
+            evaluator,
+            config.simulations,
+            config.c_puct,
+        )
+        .await?;
+        println!("Game {}:", i + 1);
+        print!(
+            "{}",
+            format_reports(&spec.start, notation, &reports, BLUNDER_SWING)
+        );
+    }
+    executor.join().await;
+    Ok(())
+}
+
+// Writes the heatmaps of the position after the moves of the file next to it, as
+// `<file>.heatmaps.png` and, if the game's visualizer draws SVG, `<file>.heatmaps.svg`
+async fn heatmaps<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    file: PathBuf,
+    weights: PathBuf,
+    config: MatchConfig,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Clone + PartialEq + Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let notation = spec
+        .notation
+        .context("The game has no notation to read moves in")?;
+    let visualizer = spec
+        .visualizer
+        .clone()
+        .context("The game has no visualizer to render positions")?;
+    let text = std::fs::read_to_string(&file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let state = parse_move_list(&spec.start, notation, &text)?
+        .iter()
+        .fold(spec.start.clone(), |state, m| state.make_move(m));
+    anyhow::ensure!(
+        state.get_state().get_moves().is_some(),
+        "The game is over after the moves"
+    );
+
+    let mut vs = nn::VarStore::new(device);
+    let net = (spec.build_net)(&vs.root());
+    vs.load(&weights)
+        .with_context(|| format!("Failed to load {}", weights.display()))?;
+    let executor = ExecutorScope::<(), _>::new(
+        net,
+        1,
+        1,
+        Duration::from_millis(10),
+        (Kind::Float, vs.device()),
+    );
+    let evaluator = NetworkEvaluator::<TGame, TNet, TAdapter>::new(executor.handle());
+    let heatmaps = search_heatmaps(&state, evaluator, config.simulations, config.c_puct).await;
+    executor.join().await;
+
+    println!("Value for the player to move: {:.3}", heatmaps.value);
+    let (image, svg) = render_heatmaps(&*visualizer, &state, &heatmaps);
+    let png = file.with_extension("heatmaps.png");
+    image
+        .save(&png)
+        .with_context(|| format!("Failed to write {}", png.display()))?;
+    println!("Prior, visits and Q written to {}", png.display());
+    if let Some(svg) = svg {
+        let path = file.with_extension("heatmaps.svg");
+        std::fs::write(&path, svg)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("Written as SVG to {}", path.display());
+    }
+    Ok(())
+}
+
+// Writes where the nets diverge after the moves of the file next to it, as `<file>.diff.png`
+async fn diff<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    file: PathBuf,
+    weights: PathBuf,
+    versus: PathBuf,
+    config: MatchConfig,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Clone + PartialEq + Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let notation = spec
+        .notation
+        .context("The game has no notation to read moves in")?;
+    let visualizer = spec
+        .visualizer
+        .clone()
+        .context("The game has no visualizer to render positions")?;
+    let text = std::fs::read_to_string(&file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let state = parse_move_list(&spec.start, notation, &text)?
+        .iter()
+        .fold(spec.start.clone(), |state, m| state.make_move(m));
+    anyhow::ensure!(
+        state.get_state().get_moves().is_some(),
+        "The game is over after the moves"
+    );
+
+    let mut executors = vec![];
+    for weights in [&weights, &versus] {
+        let mut vs = nn::VarStore::new(device);
+        let net = (spec.build_net)(&vs.root());
+        vs.load(weights)
+            .with_context(|| format!("Failed to load {}", weights.display()))?;
+        let executor = ExecutorScope::<(), _>::new(
+            net,
+            1,
+            1,
+            Duration::from_millis(10),
+            (Kind::Float, vs.device()),
+        );
+        executors.push(executor);
+    }
+    let diff = diverging_lines(
+        &state,
+        NetworkEvaluator::<TGame, TNet, TAdapter>::new(executors[0].handle()),
+        NetworkEvaluator::<TGame, TNet, TAdapter>::new(executors[1].handle()),
+        config.simulations,
+        config.c_puct,
+        DIFF_PLIES,
+    )
+    .await;
+    for executor in executors {
+        executor.join().await;
+    }
+
+    let mut agreed = state.clone();
+    let moves = diff
+        .agreed
+        .iter()
+        .map(|m| {
+            let text = (notation.format)(&agreed, m);
+            agreed = agreed.make_move(m);
+            text
+        })
+        .collect::<Vec<_>>();
+    println!("Agreed on {} moves: {}", moves.len(), moves.join(" "));
+    let Some(image) = render_line_diff(&*visualizer, &diff) else {
+        println!("The nets never diverged before the game ended");
+        return Ok(());
+    };
+    for (name, line) in [(&weights, &diff.first), (&versus, &diff.second)] {
+        let moves = line
+            .iter()
+            .map(|position| {
+                let legal = position.state.get_state().get_moves().unwrap_or_default();
+                let visits = legal
+                    .iter()
+                    .position(|m| *m == position.r#move)
+                    .map_or(0.0, |i| position.visits[i]);
+                format!(
+                    "{} {:.0}%",
+                    (notation.format)(&position.state, &position.r#move),
+                    100.0 * visits
                 )
             })
-            .map(|(state, policy, value)| {
-                TicTacToeAlphaZeroAdapter::reflect_and_augment(&state, &policy)
-                    .into_iter()
-                    .map(move |(state, policy)| (state, policy, Tensor::from(value)))
-            })
-            .flatten()
-            .collect::<Vec<_>>()
-            .tap_mut(|h| h.shuffle(&mut thread_rng()));
-
-        let mut total_values_loss = 0.0;
-        let mut total_policies_loss = 0.0;
-        for chunk in history.chunks(1024) {
-            let (states, policies, values): (Vec<_>, Vec<_>, Vec<_>) = chunk
-                .into_iter()
-                .map(|(state, policy, value)| (state.copy(), policy.copy(), value.copy()))
-                .unzip3();
-
-            let states = Tensor::stack(&states, 0)
-                .to_kind(Kind::Float)
-                .to(vs.device());
-            let policies = Tensor::stack(&policies, 0)
-                .to_kind(Kind::Float)
-                .to(vs.device());
-            let values = Tensor::stack(&values, 0)
-                .to_kind(Kind::Float)
-                .to(vs.device());
-
-            let (exp_values, exp_policies) = net.forward_t(&states, true);
-            let val_loss = (exp_values - values)
-                .pow(&Tensor::from(2.).to_kind(Kind::Float).to(vs.device()))
-                .sum(None);
-            let pol_loss = (policies * exp_policies).sum(None);
-            total_values_loss += f32::try_from(&val_loss).unwrap();
-            total_policies_loss += f32::try_from(&pol_loss).unwrap();
-            opt.backward_step(&(val_loss - pol_loss));
-        }
-
-        println!("Total value and policy loss: ({total_values_loss}, {total_policies_loss})");
-
-        vs.save(format!("checkpoints/{epoch:02}.safetensors"))
-            .unwrap();
-        for (i, sample_game) in sample_games.into_iter().enumerate() {
-            generate_game_image(&sample_game)
-                .save(format!("games/{epoch:02}.{i:02}.png"))
-                .unwrap();
+            .collect::<Vec<_>>();
+        println!("{}: {}", name.display(), moves.join(", "));
+    }
+    let png = file.with_extension("diff.png");
+    image
+        .save(&png)
+        .with_context(|| format!("Failed to write {}", png.display()))?;
+    println!(
+        "Both lines and the change of the visits where they diverge written to {}",
+        png.display()
+    );
+    Ok(())
+}
+
+// Copy of the weights for the actors, so that training can go on in the meantime
+fn snapshot<TNet>(
+    build_net: &dyn Fn(&nn::Path) -> TNet,
+    weights: &nn::VarStore,
+) -> anyhow::Result<TNet> {
+    let mut vs = nn::VarStore::new(weights.device());
+    let net = build_net(&vs.root());
+    vs.copy(weights)?;
+    Ok(net)
+}
+
+fn actor_executor<T, TNet>(
+    net: TNet,
+    device: Device,
+    config: &ExecutorConfig,
+) -> ExecutorScope<T, TNet>
+where
+    TNet: AlphaZeroNet + Send + 'static,
+{
+    ExecutorScope::new(
+        net,
+        config.parallelism,
+        config.batch_size,
+        config.max_wait(),
+        (Kind::Float, device),
+    )
+}
+
+// Plays games without a break, swapping in every network received from `nets`. The n-th
+// game started is played with `seed.derive(n)`. On a shutdown, finishes the games in progress
+// and returns.
+#[allow(clippy::too_many_arguments)]
+async fn self_play<TGame, TNet, TAdapter>(
+    mut executor: ExecutorScope<PlayedGame<TGame>, TNet>,
+    start: TGame,
+    openings: Option<OpeningBook<TGame>>,
+    seed: Seed,
+    config: Config,
+    mut nets: mpsc::Receiver<TNet>,
+    games: mpsc::UnboundedSender<PlayedGame<TGame>>,
+    mut shutdown: Shutdown,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let (simulations, c_puct) = (config.search.simulations, config.search.c_puct);
+    let mut games_started = 0usize;
+    let mut spawn_game = |executor: &ExecutorScope<_, TNet>| {
+        let seed = seed.derive(games_started);
+        games_started += 1;
+        // Sampled here to tell the learner which opening the game started from
+        let (start, opening) = match &openings {
+            Some(book) => {
+                let index = book.sample_index(&mut seed.derive("opening").rng());
+                (book.play(index, &start), Some(index))
+            }
+            None => (start.clone(), None),
+        };
+        executor.spawn(|handle| async move {
+            let samples = generate_self_played_game::<TGame, TNet, TAdapter, _>(
+                start,
+                None,
+                simulations,
+                c_puct,
+                |_| 1.0,
+                seed,
+                handle,
+                None,
+            )
+            .await;
+            PlayedGame { opening, samples }
+        });
+    };
+    for _ in 0..config.schedule.games_per_generation {
+        spawn_game(&executor);
+    }
+
+    let mut batch_size = config.executor.batch_size;
+    let mut stopping = false;
+
+    let (lim_tx, mut lim_rx) = mpsc::channel(1);
+    tokio::spawn({
+        async move {
+            for _ in 0..24 {
+                tokio::time::sleep(Duration::from_secs(6)).await;
+                if lim_tx.send(()).await.is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    loop {
+        tokio::select! {
+            Some(()) = lim_rx.recv() => {
+                debug!("Increasing parallelism by 16");
+                executor.increase_parallelism(16).await;
+                batch_size += 16;
+                executor.set_batch_size(batch_size).await;
+            }
+            Some(net) = nets.recv() => {
+                executor.swap_net(net).await;
+            }
+            () = shutdown.wait(), if !stopping => {
+                executor.stop_admitting();
+                stopping = true;
+            }
+            game = executor.next() => {
+                let Some(game) = game else {
+                    // Only runs out of games once stopping
+                    return Ok(());
+                };
+                if !stopping {
+                    spawn_game(&executor);
+                }
+                if games.send(game).is_err() {
+                    // Nobody is waiting for the games anymore
+                    return Ok(());
+                }
+            }
         }
     }
+}
 
-    Ok(())
+// Self-play for a remote learner, with the weights it publishes
+async fn work<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    device: Device,
+    learner: String,
+    config: Config,
+) -> anyhow::Result<()>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    let (mut weights, mut sender) = LearnerConnection::connect(&learner)
+        .await
+        .with_context(|| format!("Failed to connect to the learner at {learner}"))?
+        .split();
+    info!("Connected to the learner at {learner}");
+    let load = |weights: Vec<u8>| -> anyhow::Result<TNet> {
+        let mut vs = nn::VarStore::new(device);
+        let net = (spec.build_net)(&vs.root());
+        deserialize_weights(&mut vs, &weights)?;
+        Ok(net)
+    };
+
+    let first = weights
+        .recv()
+        .await
+        .context("Learner closed the connection")?;
+    let (net_tx, net_rx) = mpsc::channel(1);
+    let (games_tx, mut games_rx) = mpsc::unbounded_channel();
+    let mut actors = tokio::spawn(self_play::<TGame, TNet, TAdapter>(
+        actor_executor(load(first)?, device, &config.executor),
+        spec.start.clone(),
+        spec.openings.clone(),
+        Seed::random(),
+        config,
+        net_rx,
+        games_tx,
+        // Games in progress are lost with the worker anyway
+        Shutdown::never(),
+    ));
+
+    loop {
+        tokio::select! {
+            published = weights.recv() => match published {
+                Some(published) => {
+                    info!("Received new weights");
+                    if net_tx.send(load(published)?).await.is_err() {
+                        return actors.await?;
+                    }
+                }
+                None => {
+                    info!("Learner closed the connection");
+                    actors.abort();
+                    return Ok(());
+                }
+            },
+            Some(game) = games_rx.recv() => sender.send(&game).await?,
+            result = &mut actors => {
+                result??;
+                anyhow::bail!("Self-play stopped");
+            }
+        }
+    }
 }
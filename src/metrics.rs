@@ -0,0 +1,121 @@
+use std::{
+    collections::BTreeMap,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use tracing::info;
+
+mod csv;
+mod plot;
+mod tensorboard;
+
+pub use csv::*;
+pub use plot::*;
+pub use tensorboard::*;
+
+// Scalars read back from a run, by tag, as `(step, value)` sorted by step
+pub type Series = BTreeMap<String, Vec<(usize, f64)>>;
+
+// The metrics a run wrote into `dir`: its CSV if there is one, its TensorBoard event files
+// otherwise. A resumed run repeats the step it was interrupted in, whose last values are kept.
+pub fn read_metrics(dir: impl AsRef<Path>) -> std::io::Result<Series> {
+    let dir = dir.as_ref();
+    let csv = dir.join("metrics.csv");
+    let scalars = match csv.exists() {
+        true => read_csv(&csv)?,
+        false => read_tensorboard(dir)?,
+    };
+    if scalars.is_empty() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("No metrics in {}", dir.display()),
+        ));
+    }
+    let mut series = Series::new();
+    for (tag, step, value) in scalars {
+        series.entry(tag).or_default().push((step, value));
+    }
+    for points in series.values_mut() {
+        // Stable, so that the last of a step's values ends up last
+        points.sort_by_key(|&(step, _)| step);
+        points.reverse();
+        points.dedup_by_key(|&mut (step, _)| step);
+        points.reverse();
+    }
+    Ok(series)
+}
+
+// Destination of the scalars a training run reports, keyed by tag and step (usually the
+// generation)
+pub trait MetricsSink: Send {
+    fn scalar(&mut self, tag: &str, step: usize, value: f64) -> std::io::Result<()>;
+
+    fn flush(&mut self) -> std::io::Result<()>;
+}
+
+// Logs every scalar, for following a run from the terminal
+pub struct ConsoleSink;
+
+impl MetricsSink for ConsoleSink {
+    fn scalar(&mut self, tag: &str, step: usize, value: f64) -> std::io::Result<()> {
+        info!(step, "{tag} = {value}");
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// Forwards everything to all of its sinks
+#[derive(Default)]
+pub struct Metrics {
+    sinks: Vec<Box<dyn MetricsSink>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, sink: impl MetricsSink + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+}
+
+impl MetricsSink for Metrics {
+    fn scalar(&mut self, tag: &str, step: usize, value: f64) -> std::io::Result<()> {
+        self.sinks
+            .iter_mut()
+            .try_for_each(|sink| sink.scalar(tag, step, value))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.sinks.iter_mut().try_for_each(|sink| sink.flush())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_metrics, CsvSink, MetricsSink};
+
+    #[test]
+    fn resumed_steps_keep_their_last_values() {
+        let dir = std::env::temp_dir().join(format!("read-metrics-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut sink = CsvSink::create(dir.join("metrics.csv")).unwrap();
+        for (step, value) in [(0, 1.0), (1, 0.8), (1, 0.7), (2, 0.6)] {
+            sink.scalar("train/value_loss", step, value).unwrap();
+        }
+        sink.scalar("elo/rating", 1, 40.0).unwrap();
+        sink.flush().unwrap();
+
+        let series = read_metrics(&dir).unwrap();
+        assert_eq!(series["train/value_loss"], [(0, 1.0), (1, 0.7), (2, 0.6)]);
+        assert_eq!(series["elo/rating"], [(1, 40.0)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(read_metrics(&dir).is_err());
+    }
+}
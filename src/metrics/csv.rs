@@ -0,0 +1,98 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Error, ErrorKind, Write},
+    path::Path,
+};
+
+use super::MetricsSink;
+
+// One `step,tag,value` row per scalar, appended to if the file already exists
+pub struct CsvSink {
+    file: BufWriter<File>,
+}
+
+impl CsvSink {
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let is_new = !path.exists();
+        let mut file = BufWriter::new(OpenOptions::new().create(true).append(true).open(path)?);
+        if is_new {
+            writeln!(file, "step,tag,value")?;
+        }
+        Ok(Self { file })
+    }
+}
+
+impl MetricsSink for CsvSink {
+    fn scalar(&mut self, tag: &str, step: usize, value: f64) -> std::io::Result<()> {
+        assert!(
+            !tag.contains([',', '"', '\n']),
+            "Tag {tag:?} would need quoting"
+        );
+        writeln!(self.file, "{step},{tag},{value}")
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+// The scalars of a file written by `CsvSink`, as `(tag, step, value)` in file order
+pub fn read_csv(path: impl AsRef<Path>) -> std::io::Result<Vec<(String, usize, f64)>> {
+    let text = fs::read_to_string(path)?;
+    text.lines()
+        .skip(1)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let invalid = || Error::new(ErrorKind::InvalidData, format!("Invalid row {line:?}"));
+            let mut fields = line.splitn(3, ',');
+            let (Some(step), Some(tag), Some(value)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid());
+            };
+            Ok((
+                tag.to_owned(),
+                step.parse().map_err(|_| invalid())?,
+                value.parse().map_err(|_| invalid())?,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::MetricsSink;
+
+    use super::{read_csv, CsvSink};
+
+    #[test]
+    fn appends_rows() {
+        let path = std::env::temp_dir().join(format!("metrics-{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut sink = CsvSink::create(&path).unwrap();
+        sink.scalar("loss/value", 0, 0.25).unwrap();
+        sink.flush().unwrap();
+        drop(sink);
+        let mut sink = CsvSink::create(&path).unwrap();
+        sink.scalar("selfplay/game_length", 1, 42.0).unwrap();
+        sink.flush().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "step,tag,value\n0,loss/value,0.25\n1,selfplay/game_length,42\n"
+        );
+        assert_eq!(
+            read_csv(&path).unwrap(),
+            [
+                ("loss/value".to_owned(), 0, 0.25),
+                ("selfplay/game_length".to_owned(), 1, 42.0)
+            ]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use plotters::prelude::*;
+
+use super::Series;
+
+// Of every chart, in pixels
+const CHART_SIZE: (u32, u32) = (800, 480);
+
+// A line chart per tag into `dir`, named after the tag with its slashes as dashes, like
+// `train-value_loss.png`. Returns the charts' paths, in the order of the tags.
+pub fn plot_series(series: &Series, dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let mut paths = vec![];
+    for (tag, points) in series {
+        if points.is_empty() {
+            continue;
+        }
+        let path = dir.join(format!("{}.png", tag.replace('/', "-")));
+        plot_tag(tag, points, &path)
+            .map_err(|err| anyhow::anyhow!("{err}"))
+            .with_context(|| format!("Failed to plot {tag} into {}", path.display()))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+fn plot_tag(
+    tag: &str,
+    points: &[(usize, f64)],
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (first, last) = (points[0].0 as f64, points[points.len() - 1].0 as f64);
+    let (low, high) = points.iter().fold(
+        (f64::INFINITY, f64::NEG_INFINITY),
+        |(low, high), &(_, value)| (low.min(value), high.max(value)),
+    );
+    // A margin of a tenth of the range, or around the value of a constant series
+    let margin = match high > low {
+        true => (high - low) / 10.0,
+        false => high.abs().max(1.0) / 10.0,
+    };
+
+    let root = BitMapBackend::new(path, CHART_SIZE).into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(tag, ("sans-serif", 24))
+        .margin(16)
+        .x_label_area_size(40)
+        .y_label_area_size(70)
+        .build_cartesian_2d(first..last.max(first + 1.0), low - margin..high + margin)?;
+    chart.configure_mesh().x_desc("step").draw()?;
+    let line = points.iter().map(|&(step, value)| (step as f64, value));
+    chart.draw_series(LineSeries::new(line.clone(), BLUE.stroke_width(2)))?;
+    chart.draw_series(line.map(|point| Circle::new(point, 3, BLUE.filled())))?;
+    root.present()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::Series;
+
+    use super::plot_series;
+
+    #[test]
+    fn chart_per_tag() {
+        let dir = std::env::temp_dir().join(format!("plots-{}", std::process::id()));
+        let series = Series::from([
+            (
+                "elo/rating".to_owned(),
+                vec![(0, 0.0), (1, 35.0), (2, 60.0)],
+            ),
+            ("train/value_loss".to_owned(), vec![(4, 0.5)]),
+        ]);
+        let paths = plot_series(&series, &dir).unwrap();
+        assert_eq!(
+            paths,
+            [dir.join("elo-rating.png"), dir.join("train-value_loss.png")]
+        );
+        let chart = image::open(&paths[0]).unwrap();
+        assert_eq!((chart.width(), chart.height()), (800, 480));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Error, ErrorKind, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::MetricsSink;
+
+// Writes a TensorBoard event file into `dir`, readable with `tensorboard --logdir`.
+// Events are TFRecords holding hand-encoded `Event` protobufs, so no protobuf crate is needed.
+pub struct TensorBoardSink {
+    file: BufWriter<File>,
+}
+
+impl TensorBoardSink {
+    pub fn create(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let path = dir.join(format!(
+            "events.out.tfevents.{}.{}",
+            now.as_secs(),
+            std::process::id()
+        ));
+        let mut sink = Self {
+            file: BufWriter::new(File::create(path)?),
+        };
+        // Event.file_version, expected first in every file
+        let mut event = Message::default();
+        event.double(1, wall_time());
+        event.bytes(3, b"brain.Event:2");
+        sink.write_record(&event.0)?;
+        Ok(sink)
+    }
+
+    fn write_record(&mut self, data: &[u8]) -> std::io::Result<()> {
+        let len = (data.len() as u64).to_le_bytes();
+        self.file.write_all(&len)?;
+        self.file.write_all(&masked_crc32c(&len).to_le_bytes())?;
+        self.file.write_all(data)?;
+        self.file.write_all(&masked_crc32c(data).to_le_bytes())
+    }
+}
+
+impl MetricsSink for TensorBoardSink {
+    fn scalar(&mut self, tag: &str, step: usize, value: f64) -> std::io::Result<()> {
+        let mut summary_value = Message::default();
+        summary_value.bytes(1, tag.as_bytes());
+        summary_value.float(2, value as f32);
+        let mut summary = Message::default();
+        summary.bytes(1, &summary_value.0);
+
+        let mut event = Message::default();
+        event.double(1, wall_time());
+        event.varint(2, step as u64);
+        event.bytes(5, &summary.0);
+        self.write_record(&event.0)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn wall_time() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+}
+
+// Just the protobuf wire types the event messages need
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn key(&mut self, field: u64, wire_type: u64) {
+        self.raw_varint(field << 3 | wire_type);
+    }
+
+    fn raw_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn varint(&mut self, field: u64, value: u64) {
+        self.key(field, 0);
+        self.raw_varint(value);
+    }
+
+    fn double(&mut self, field: u64, value: f64) {
+        self.key(field, 1);
+        self.0.extend(value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, field: u64, value: &[u8]) {
+        self.key(field, 2);
+        self.raw_varint(value.len() as u64);
+        self.0.extend(value);
+    }
+
+    fn float(&mut self, field: u64, value: f32) {
+        self.key(field, 5);
+        self.0.extend(value.to_le_bytes());
+    }
+}
+
+// The scalars of the event files in `dir`, like those written by `TensorBoardSink`, as
+// `(tag, step, value)` with the files in the order of their names. A record cut off by a
+// crash ends its file.
+pub fn read_tensorboard(dir: impl AsRef<Path>) -> std::io::Result<Vec<(String, usize, f64)>> {
+    let mut files = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    files.retain(|path| {
+        path.file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with("events.out.tfevents."))
+    });
+    files.sort();
+
+    let mut scalars = vec![];
+    for path in files {
+        let data = fs::read(&path)?;
+        let mut rest = &data[..];
+        while rest.len() >= 12 {
+            let len = u64::from_le_bytes(rest[..8].try_into().unwrap()) as usize;
+            if rest.len() < 16 + len {
+                break;
+            }
+            let record = &rest[12..12 + len];
+            let checksum = u32::from_le_bytes(rest[12 + len..16 + len].try_into().unwrap());
+            if checksum != masked_crc32c(record) {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Corrupt record in {}", path.display()),
+                ));
+            }
+            read_event(record, &mut scalars);
+            rest = &rest[16 + len..];
+        }
+    }
+    Ok(scalars)
+}
+
+// The scalars of an `Event`'s summary, ignoring the fields that aren't written
+fn read_event(event: &[u8], scalars: &mut Vec<(String, usize, f64)>) {
+    let mut step = 0;
+    let mut values = vec![];
+    for (field, value) in Fields(event) {
+        match (field, value) {
+            (2, Field::Varint(value)) => step = value as usize,
+            (5, Field::Bytes(summary)) => values.extend(Fields(summary).filter_map(
+                |(field, value)| match (field, value) {
+                    (1, Field::Bytes(value)) => Some(value),
+                    _ => None,
+                },
+            )),
+            _ => {}
+        }
+    }
+    for value in values {
+        let (mut tag, mut scalar) = (None, None);
+        for (field, value) in Fields(value) {
+            match (field, value) {
+                (1, Field::Bytes(bytes)) => tag = Some(String::from_utf8_lossy(bytes).into_owned()),
+                (2, Field::Fixed32(bytes)) => scalar = Some(f32::from_le_bytes(bytes) as f64),
+                _ => {}
+            }
+        }
+        if let (Some(tag), Some(scalar)) = (tag, scalar) {
+            scalars.push((tag, step, scalar));
+        }
+    }
+}
+
+enum Field<'a> {
+    Varint(u64),
+    Fixed64,
+    Bytes(&'a [u8]),
+    Fixed32([u8; 4]),
+}
+
+// The fields of a protobuf message, ending early at anything malformed
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    fn raw_varint(&mut self) -> Option<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.0.split_first()?;
+            self.0 = rest;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let taken = self.0.get(..len)?;
+        self.0 = &self.0[len..];
+        Some(taken)
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = (u64, Field<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.raw_varint()?;
+        let field = match key & 7 {
+            0 => Field::Varint(self.raw_varint()?),
+            1 => {
+                self.take(8)?;
+                Field::Fixed64
+            }
+            2 => {
+                let len = self.raw_varint()? as usize;
+                Field::Bytes(self.take(len)?)
+            }
+            5 => Field::Fixed32(self.take(4)?.try_into().unwrap()),
+            _ => return None,
+        };
+        Some((key >> 3, field))
+    }
+}
+
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn masked_crc32c(data: &[u8]) -> u32 {
+    let crc = crc32c(data);
+    crc.rotate_right(15).wrapping_add(0xa282_ead8)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::MetricsSink;
+
+    use super::{crc32c, masked_crc32c, read_tensorboard, TensorBoardSink};
+
+    #[test]
+    fn writes_checksummed_records() {
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+
+        let dir = std::env::temp_dir().join(format!("tensorboard-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut sink = TensorBoardSink::create(&dir).unwrap();
+        sink.scalar("loss/value", 3, 0.5).unwrap();
+        sink.flush().unwrap();
+
+        let file = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap();
+        let data = std::fs::read(file.path()).unwrap();
+        let mut rest = &data[..];
+        let mut records = vec![];
+        while !rest.is_empty() {
+            let len = u64::from_le_bytes(rest[..8].try_into().unwrap()) as usize;
+            assert_eq!(
+                u32::from_le_bytes(rest[8..12].try_into().unwrap()),
+                masked_crc32c(&rest[..8])
+            );
+            let record = &rest[12..12 + len];
+            assert_eq!(
+                u32::from_le_bytes(rest[12 + len..16 + len].try_into().unwrap()),
+                masked_crc32c(record)
+            );
+            records.push(record.to_vec());
+            rest = &rest[16 + len..];
+        }
+        assert_eq!(records.len(), 2);
+        assert!(records[0].ends_with(b"brain.Event:2"));
+        // step = 3, then the summary with the tag and 0.5f32
+        assert_eq!(&records[1][9..11], &[0x10, 3]);
+        assert!(records[1].ends_with(&[b'e', 0x15, 0, 0, 0, 0x3f]));
+        assert_eq!(
+            read_tensorboard(&dir).unwrap(),
+            [("loss/value".to_owned(), 3, 0.5)]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,356 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use pyo3::{prelude::*, types::PyModule};
+use tch::Tensor;
+
+use crate::{
+    alpha_zero::{
+        AlphaZeroAdapter, Game, MoveNotation, MoveParameters, Notation, ResNetAlphaZero,
+        ResNetConfig, TerminationState,
+    },
+    registry::{GameRegistry, GameSpec, GameVisitor},
+};
+
+// Of the registry's names for OpenSpiel games, followed by OpenSpiel's name and parameters
+const PREFIX: &str = "open_spiel:";
+
+// What every state of a game shares
+#[derive(Debug)]
+struct OpenSpielInfo {
+    observation_shape: Vec<usize>,
+    distinct_actions: usize,
+    // Of the returns, which values are scaled from
+    min_utility: f32,
+    max_utility: f32,
+}
+
+// A state of a game of OpenSpiel, played through its Python bindings. States are never
+// changed, `make_move` making a child, so clones share the Python object.
+#[derive(Clone, Debug)]
+pub struct OpenSpielState {
+    state: Py<PyAny>,
+    // The player to move by the moves' perspectives, whose return a terminal state's value is
+    player: usize,
+    info: Arc<OpenSpielInfo>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpenSpielAction {
+    pub action: usize,
+    switch: bool,
+    // Of the game, the size of the policies
+    distinct_actions: usize,
+}
+
+impl MoveParameters for OpenSpielAction {
+    fn is_player_switch(&self) -> bool {
+        self.switch
+    }
+}
+
+// OpenSpiel's errors being bugs, like those of any game
+fn with_python<T>(f: impl FnOnce(Python<'_>) -> PyResult<T>) -> T {
+    Python::with_gil(|py| f(py).unwrap_or_else(|e| panic!("OpenSpiel failed: {e}")))
+}
+
+impl OpenSpielState {
+    // The start of the game OpenSpiel's `load_game` makes of `name`, which has to be a
+    // deterministic, zero-sum game of two players taking turns with perfect information
+    pub fn load(name: &str) -> anyhow::Result<Self> {
+        Python::with_gil(|py| {
+            let pyspiel = PyModule::import_bound(py, "pyspiel")
+                .context("Failed to import pyspiel, install it with `pip install open_spiel`")?;
+            let game = pyspiel.call_method1("load_game", (name,))?;
+            let game_type = game.call_method0("get_type")?;
+            let is = |attribute: &str, class: &str, value: &str| -> PyResult<bool> {
+                let expected = pyspiel
+                    .getattr("GameType")?
+                    .getattr(class)?
+                    .getattr(value)?;
+                game_type.getattr(attribute)?.eq(expected)
+            };
+            anyhow::ensure!(
+                game.call_method0("num_players")?.extract::<usize>()? == 2,
+                "{name} isn't a game of two players"
+            );
+            anyhow::ensure!(
+                is("dynamics", "Dynamics", "SEQUENTIAL")?,
+                "{name} isn't played in turns"
+            );
+            anyhow::ensure!(
+                is("chance_mode", "ChanceMode", "DETERMINISTIC")?,
+                "{name} has chance nodes"
+            );
+            anyhow::ensure!(
+                is("information", "Information", "PERFECT_INFORMATION")?,
+                "{name} has hidden information"
+            );
+            anyhow::ensure!(
+                is("utility", "Utility", "ZERO_SUM")? || is("utility", "Utility", "CONSTANT_SUM")?,
+                "{name} isn't zero-sum"
+            );
+            anyhow::ensure!(
+                game_type
+                    .getattr("provides_observation_tensor")?
+                    .extract::<bool>()?,
+                "{name} has no observation tensor"
+            );
+            let info = OpenSpielInfo {
+                observation_shape: game.call_method0("observation_tensor_shape")?.extract()?,
+                distinct_actions: game.call_method0("num_distinct_actions")?.extract()?,
+                min_utility: game.call_method0("min_utility")?.extract()?,
+                max_utility: game.call_method0("max_utility")?.extract()?,
+            };
+            anyhow::ensure!(
+                info.min_utility < info.max_utility,
+                "{name} has a single outcome"
+            );
+            let state = game.call_method0("new_initial_state")?;
+            Ok(Self {
+                player: state.call_method0("current_player")?.extract()?,
+                state: state.unbind(),
+                info: Arc::new(info),
+            })
+        })
+    }
+
+    fn observation(&self) -> Vec<f32> {
+        with_python(|py| {
+            self.state
+                .bind(py)
+                .call_method0("observation_tensor")?
+                .extract()
+        })
+    }
+
+    // OpenSpiel's drawing of the state
+    pub fn text(&self) -> String {
+        with_python(|py| Ok(self.state.bind(py).str()?.to_string()))
+    }
+}
+
+impl Game for OpenSpielState {
+    type Move = OpenSpielAction;
+
+    // Each action is tried for whether it changes the player to move, which OpenSpiel's games
+    // decide as they like
+    fn get_state(&self) -> TerminationState<Self::Move> {
+        with_python(|py| {
+            let state = self.state.bind(py);
+            if state.call_method0("is_terminal")?.extract()? {
+                let returns = state.call_method0("returns")?.extract::<Vec<f32>>()?;
+                let (min, max) = (self.info.min_utility, self.info.max_utility);
+                return Ok(TerminationState::Terminal(
+                    (returns[self.player] - min) / (max - min),
+                ));
+            }
+            let player = state.call_method0("current_player")?;
+            let actions = state
+                .call_method0("legal_actions")?
+                .extract::<Vec<usize>>()?;
+            let moves = actions
+                .into_iter()
+                .map(|action| {
+                    let child = state.call_method1("child", (action,))?;
+                    let switch = child.call_method0("is_terminal")?.extract::<bool>()?
+                        || !child.call_method0("current_player")?.eq(&player)?;
+                    Ok(OpenSpielAction {
+                        action,
+                        switch,
+                        distinct_actions: self.info.distinct_actions,
+                    })
+                })
+                .collect::<PyResult<Vec<_>>>()?;
+            Ok(TerminationState::Moves(moves))
+        })
+    }
+
+    fn make_move(&self, m: &Self::Move) -> Self {
+        let state = with_python(|py| {
+            let child = self.state.bind(py).call_method1("child", (m.action,))?;
+            Ok(child.unbind())
+        });
+        Self {
+            state,
+            player: match m.switch {
+                true => 1 - self.player,
+                false => self.player,
+            },
+            info: self.info.clone(),
+        }
+    }
+
+    fn is_legal(&self, m: &Self::Move) -> bool {
+        self.get_state()
+            .get_moves()
+            .is_some_and(|moves| moves.contains(m))
+    }
+}
+
+// By OpenSpiel's names of the actions, their spaces made underscores
+impl MoveNotation for OpenSpielState {
+    fn parse_move(&self, text: &str) -> anyhow::Result<OpenSpielAction> {
+        let moves = self.get_state().get_moves().context("The game is over")?;
+        moves
+            .into_iter()
+            .find(|m| self.format_move(m) == text)
+            .with_context(|| format!("No legal action is named {text}"))
+    }
+
+    fn format_move(&self, m: &OpenSpielAction) -> String {
+        let name = with_python(|py| {
+            self.state
+                .bind(py)
+                .call_method1("action_to_string", (m.action,))?
+                .extract::<String>()
+        });
+        name.split_whitespace().collect::<Vec<_>>().join("_")
+    }
+}
+
+// Observations as planes of a square board for the ResNet, the last two dimensions being the
+// rows and the columns. Flat ones are wrapped into rows as long as the side.
+#[derive(Debug, PartialEq)]
+struct BoardLayout {
+    planes: usize,
+    rows: usize,
+    columns: usize,
+    side: usize,
+}
+
+impl BoardLayout {
+    fn of(shape: &[usize]) -> Self {
+        match shape {
+            [] => Self::of(&[1]),
+            &[size] => {
+                let side = (size.max(1) as f64).sqrt().ceil() as usize;
+                Self {
+                    planes: 1,
+                    rows: size.div_ceil(side),
+                    columns: side,
+                    side,
+                }
+            }
+            &[ref planes @ .., rows, columns] => Self {
+                planes: planes.iter().product(),
+                rows,
+                columns,
+                side: rows.max(columns),
+            },
+        }
+    }
+
+    // Padded with zeros past the rows and the columns
+    fn pad(&self, observation: &[f32]) -> Vec<f32> {
+        let mut planes = vec![0.0; self.planes * self.side * self.side];
+        let area = self.rows * self.columns;
+        for (i, &value) in observation.iter().enumerate() {
+            let (plane, cell) = (i / area, i % area);
+            let (row, column) = (cell / self.columns, cell % self.columns);
+            planes[(plane * self.side + row) * self.side + column] = value;
+        }
+        planes
+    }
+}
+
+// Every OpenSpiel game with the same adapter, its observation being the net's input and its
+// distinct actions the policy
+pub struct OpenSpielAdapter;
+
+impl AlphaZeroAdapter<OpenSpielState, ResNetAlphaZero> for OpenSpielAdapter {
+    fn convert_game_to_nn_input(state: &OpenSpielState) -> Tensor {
+        let layout = BoardLayout::of(&state.info.observation_shape);
+        let side = layout.side as i64;
+        Tensor::from_slice(&layout.pad(&state.observation())).view([
+            layout.planes as i64,
+            side,
+            side,
+        ])
+    }
+
+    fn get_estimated_policy(policy: &Tensor, moves: &[OpenSpielAction]) -> Vec<f32> {
+        let policy = <Vec<f32>>::try_from(policy.exp().view([-1])).unwrap();
+        let mut res = moves.iter().map(|m| policy[m.action]).collect::<Vec<_>>();
+        let sum = res.iter().sum::<f32>();
+        if sum > 0. {
+            for x in &mut res {
+                *x /= sum;
+            }
+        }
+        res
+    }
+
+    // Of positions with moves, which tell the size of the policy
+    fn convert_policy_to_nn(policy: &[f32], moves: &[OpenSpielAction]) -> Tensor {
+        let mut res = vec![0f32; moves[0].distinct_actions];
+        for (m, &pol) in moves.iter().zip(policy) {
+            res[m.action] = pol;
+        }
+        Tensor::from_slice(&res)
+    }
+}
+
+// The game with a ResNet fitted to its observations, which is only as good as they are for
+// a convolutional net
+pub fn open_spiel_spec(
+    name: &str,
+) -> anyhow::Result<GameSpec<OpenSpielState, ResNetAlphaZero, OpenSpielAdapter>> {
+    let start = OpenSpielState::load(name)?;
+    let layout = BoardLayout::of(&start.info.observation_shape);
+    let config = ResNetConfig::new(
+        layout.planes as i64,
+        layout.side as i64,
+        vec![start.info.distinct_actions as i64],
+    );
+    Ok(
+        GameSpec::new(start, move |path| ResNetAlphaZero::new(path, &config))
+            .with_notation(Notation::of())
+            .with_text(OpenSpielState::text),
+    )
+}
+
+impl<TVisitor: GameVisitor> GameRegistry<TVisitor> {
+    // With the OpenSpiel game `game` names, like `open_spiel:connect_four` or
+    // `open_spiel:breakthrough(rows=6,columns=6)`. Other names are left as they are.
+    pub fn with_open_spiel(mut self, game: &str) -> anyhow::Result<Self> {
+        let Some(name) = game.strip_prefix(PREFIX) else {
+            return Ok(self);
+        };
+        let spec = open_spiel_spec(name).with_context(|| format!("Failed to load {game}"))?;
+        // Registered names live as long as the program, and there is one such game per run
+        self.register(game.to_owned().leak(), move || spec.clone());
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BoardLayout;
+
+    #[test]
+    fn observations_fill_square_boards() {
+        // Connect four's cells of either player or empty
+        let layout = BoardLayout::of(&[3, 6, 7]);
+        assert_eq!((layout.planes, layout.side), (3, 7));
+        let planes = layout.pad(&[1.0; 3 * 6 * 7]);
+        assert_eq!(planes.len(), 3 * 7 * 7);
+        assert_eq!(planes[..7], [1.0; 7]);
+        assert_eq!(planes[6 * 7..7 * 7], [0.0; 7]);
+        assert_eq!(planes[7 * 7], 1.0);
+
+        let layout = BoardLayout::of(&[10]);
+        assert_eq!(
+            layout,
+            BoardLayout {
+                planes: 1,
+                rows: 3,
+                columns: 4,
+                side: 4
+            }
+        );
+        let planes = layout.pad(&[1.0; 10]);
+        assert_eq!(planes.iter().sum::<f32>(), 10.0);
+        assert_eq!(planes[9..], [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,82 @@
+use tch::Tensor;
+
+use crate::{
+    alpha_zero::{ActionEncoding, AlphaZeroAdapter, SymmetryTransform},
+    tictactoe::CellState,
+};
+
+use super::{OthelloBoard, OthelloMove, OthelloNet};
+
+pub struct OthelloAlphaZeroAdapter;
+
+impl ActionEncoding<OthelloBoard> for OthelloAlphaZeroAdapter {
+    fn action_space_size() -> usize {
+        64
+    }
+
+    // A pass is always the only legal move, so it never needs a slot in the policy
+    fn move_to_index(m: &OthelloMove) -> usize {
+        match m {
+            OthelloMove::Place { square, .. } => *square,
+            OthelloMove::Pass => panic!("Pass has no policy index"),
+        }
+    }
+
+    // Whether the move switches the player depends on the position, look the square up
+    // in the legal moves to get the exact move
+    fn index_to_move(index: usize) -> OthelloMove {
+        OthelloMove::Place {
+            square: index,
+            player_switch: true,
+        }
+    }
+
+    fn policy_shape() -> Vec<i64> {
+        vec![8, 8]
+    }
+}
+
+impl AlphaZeroAdapter<OthelloBoard, OthelloNet> for OthelloAlphaZeroAdapter {
+    // The starting position is only invariant under these 4 transforms: identity,
+    // 180° rotation and the two diagonal reflections
+    fn symmetries() -> Vec<SymmetryTransform> {
+        vec![
+            SymmetryTransform::identity(),
+            SymmetryTransform::dihedral(false, 2),
+            SymmetryTransform::dihedral(true, 1),
+            SymmetryTransform::dihedral(true, 3),
+        ]
+    }
+
+    fn input_planes() -> Vec<String> {
+        vec!["to move".to_owned(), "opponent".to_owned()]
+    }
+
+    fn convert_game_to_nn_input(state: &OthelloBoard) -> Tensor {
+        let mut fld = [0f32; 2 * 64];
+        for i in 0..8 {
+            for j in 0..8 {
+                match state.get(i, j) {
+                    CellState::X => fld[i * 8 + j] = 1.,
+                    CellState::O => fld[64 + i * 8 + j] = 1.,
+                    CellState::Empty => {}
+                }
+            }
+        }
+        Tensor::from_slice(&fld).view([2, 8, 8])
+    }
+
+    fn get_estimated_policy(policy: &Tensor, moves: &[OthelloMove]) -> Vec<f32> {
+        if moves == [OthelloMove::Pass] {
+            return vec![1.0];
+        }
+        Self::decode_policy(policy, moves)
+    }
+
+    fn convert_policy_to_nn(policy: &[f32], moves: &[OthelloMove]) -> Tensor {
+        if moves == [OthelloMove::Pass] {
+            return Self::encode_policy(&[], &[]);
+        }
+        Self::encode_policy(policy, moves)
+    }
+}
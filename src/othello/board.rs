@@ -0,0 +1,257 @@
+use crate::{
+    alpha_zero::{Game, MoveParameters, TerminationState},
+    tictactoe::CellState,
+};
+
+const NOT_FIRST_COLUMN: u64 = 0xfefe_fefe_fefe_fefe;
+const NOT_LAST_COLUMN: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+
+// Bit `row * 8 + column`, shifts move a whole bitboard one step in a direction
+const DIRECTIONS: [fn(u64) -> u64; 8] = [
+    |b| (b << 1) & NOT_FIRST_COLUMN,
+    |b| (b >> 1) & NOT_LAST_COLUMN,
+    |b| b << 8,
+    |b| b >> 8,
+    |b| (b << 9) & NOT_FIRST_COLUMN,
+    |b| (b << 7) & NOT_LAST_COLUMN,
+    |b| (b >> 7) & NOT_FIRST_COLUMN,
+    |b| (b >> 9) & NOT_LAST_COLUMN,
+];
+
+// 8x8 Othello from the point of view of the player to move ("X")
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub struct OthelloBoard {
+    own: u64,
+    other: u64,
+}
+
+// A pass is only generated for positions where the player to move is stuck but the
+// opponent isn't. In regular play it never happens: a move after which the opponent
+// has no reply simply doesn't switch the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OthelloMove {
+    Place { square: usize, player_switch: bool },
+    Pass,
+}
+
+impl MoveParameters for OthelloMove {
+    fn is_player_switch(&self) -> bool {
+        match self {
+            OthelloMove::Place { player_switch, .. } => *player_switch,
+            OthelloMove::Pass => true,
+        }
+    }
+}
+
+impl Default for OthelloBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OthelloBoard {
+    pub fn new() -> Self {
+        Self {
+            own: (1 << 28) | (1 << 35),
+            other: (1 << 27) | (1 << 36),
+        }
+    }
+
+    pub fn from_bitboards(own: u64, other: u64) -> Self {
+        assert_eq!(own & other, 0);
+        Self { own, other }
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> CellState {
+        let bit = 1 << (row * 8 + column);
+        if self.own & bit != 0 {
+            CellState::X
+        } else if self.other & bit != 0 {
+            CellState::O
+        } else {
+            CellState::Empty
+        }
+    }
+
+    pub fn count(&self) -> (u32, u32) {
+        (self.own.count_ones(), self.other.count_ones())
+    }
+
+    fn legal_squares(own: u64, other: u64) -> u64 {
+        let empty = !(own | other);
+        let mut moves = 0;
+        for shift in DIRECTIONS {
+            let mut line = shift(own) & other;
+            for _ in 0..5 {
+                line |= shift(line) & other;
+            }
+            moves |= shift(line) & empty;
+        }
+        moves
+    }
+
+    fn flips(&self, square: usize) -> u64 {
+        let mut flips = 0;
+        for shift in DIRECTIONS {
+            let mut line = 0;
+            let mut cur = shift(1 << square);
+            while cur & self.other != 0 {
+                line |= cur;
+                cur = shift(cur);
+            }
+            if cur & self.own != 0 {
+                flips |= line;
+            }
+        }
+        flips
+    }
+
+    fn place(&self, square: usize) -> (u64, u64) {
+        let flips = self.flips(square);
+        (self.own | flips | (1 << square), self.other & !flips)
+    }
+
+    pub fn is_legal_square(&self, square: usize) -> bool {
+        square < 64 && Self::legal_squares(self.own, self.other) & (1 << square) != 0
+    }
+}
+
+impl Game for OthelloBoard {
+    type Move = OthelloMove;
+
+    fn get_state(&self) -> TerminationState<Self::Move> {
+        let mut squares = Self::legal_squares(self.own, self.other);
+        if squares == 0 {
+            if Self::legal_squares(self.other, self.own) != 0 {
+                return TerminationState::Moves(vec![OthelloMove::Pass]);
+            }
+            let (own, other) = self.count();
+            return TerminationState::Terminal(match own.cmp(&other) {
+                std::cmp::Ordering::Greater => 1.0,
+                std::cmp::Ordering::Equal => 0.5,
+                std::cmp::Ordering::Less => 0.0,
+            });
+        }
+
+        let mut moves = vec![];
+        while squares != 0 {
+            let square = squares.trailing_zeros() as usize;
+            squares &= squares - 1;
+
+            let (own, other) = self.place(square);
+            // The opponent has to pass, unless the game is over anyway
+            let player_switch =
+                Self::legal_squares(other, own) != 0 || Self::legal_squares(own, other) == 0;
+            moves.push(OthelloMove::Place {
+                square,
+                player_switch,
+            });
+        }
+        TerminationState::Moves(moves)
+    }
+
+    fn make_move(&self, m: &Self::Move) -> Self {
+        match *m {
+            OthelloMove::Place {
+                square,
+                player_switch,
+            } => {
+                let (own, other) = self.place(square);
+                if player_switch {
+                    Self {
+                        own: other,
+                        other: own,
+                    }
+                } else {
+                    Self { own, other }
+                }
+            }
+            OthelloMove::Pass => Self {
+                own: self.other,
+                other: self.own,
+            },
+        }
+    }
+
+    fn is_legal(&self, m: &Self::Move) -> bool {
+        match self.get_state() {
+            TerminationState::Moves(moves) => moves.contains(m),
+            TerminationState::Terminal(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{Game, MoveParameters, TerminationState},
+        tictactoe::CellState,
+    };
+
+    use super::{OthelloBoard, OthelloMove};
+
+    fn squares(moves: &[OthelloMove]) -> Vec<usize> {
+        moves
+            .iter()
+            .map(|m| match m {
+                OthelloMove::Place { square, .. } => *square,
+                OthelloMove::Pass => 64,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn opening_moves() {
+        let board = OthelloBoard::new();
+        let moves = board.get_state().get_moves().unwrap();
+        assert_eq!(squares(&moves), [19, 26, 37, 44]);
+        assert!(moves.iter().all(|m| m.is_player_switch()));
+
+        // Playing d3 flips d4 and hands the turn over
+        let board = board.make_move(&moves[0]);
+        assert_eq!(board.count(), (1, 4));
+        assert_eq!(board.get(3, 3), CellState::O);
+    }
+
+    #[test]
+    fn move_without_reply_keeps_the_turn() {
+        // Own discs in the a1 and h1 corners, opponent discs on b1 and h2: the opponent
+        // can never outflank a corner, so after c1 it has to pass.
+        let board = OthelloBoard::from_bitboards((1 << 0) | (1 << 7), (1 << 1) | (1 << 15));
+        let moves = board.get_state().get_moves().unwrap();
+        assert_eq!(squares(&moves), [2, 23]);
+        assert!(!moves[0].is_player_switch());
+
+        let board = board.make_move(&moves[0]);
+        assert_eq!(board.get(0, 1), CellState::X);
+        assert_eq!(board.count(), (4, 1));
+
+        // Taking the last disc ends the game, so the turn is handed over
+        let moves = board.get_state().get_moves().unwrap();
+        assert_eq!(squares(&moves), [23]);
+        assert!(moves[0].is_player_switch());
+        assert_eq!(
+            board.make_move(&moves[0]).get_state(),
+            TerminationState::Terminal(0.0)
+        );
+    }
+
+    #[test]
+    fn pass_and_termination() {
+        assert_eq!(
+            OthelloBoard::from_bitboards(0, 1 << 0).get_state(),
+            TerminationState::Terminal(0.0)
+        );
+
+        // The player to move is stuck, but the opponent can still capture on c1
+        let board = OthelloBoard::from_bitboards(1 << 1, 1 << 0);
+        assert_eq!(
+            board.get_state(),
+            TerminationState::Moves(vec![OthelloMove::Pass])
+        );
+        assert_eq!(
+            board.make_move(&OthelloMove::Pass),
+            OthelloBoard::from_bitboards(1 << 0, 1 << 1)
+        );
+    }
+}
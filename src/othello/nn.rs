@@ -0,0 +1,112 @@
+use tch::{
+    nn::{self, BatchNorm, Conv2D, ConvConfig, Linear, Module, ModuleT},
+    Tensor,
+};
+
+use crate::alpha_zero::{AlphaZeroNet, NetOutput};
+
+const CHANNELS: i64 = 64;
+
+fn conv3x3(path: nn::Path, c_in: i64) -> Conv2D {
+    nn::conv2d(
+        path,
+        c_in,
+        CHANNELS,
+        3,
+        ConvConfig {
+            padding: 1,
+            bias: false,
+            ..Default::default()
+        },
+    )
+}
+
+struct ResidualBlock {
+    conv1: Conv2D,
+    bn1: BatchNorm,
+    conv2: Conv2D,
+    bn2: BatchNorm,
+}
+
+impl ResidualBlock {
+    fn new(path: nn::Path) -> Self {
+        Self {
+            conv1: conv3x3(&path / "conv1", CHANNELS),
+            bn1: nn::batch_norm2d(&path / "bn1", CHANNELS, Default::default()),
+            conv2: conv3x3(&path / "conv2", CHANNELS),
+            bn2: nn::batch_norm2d(&path / "bn2", CHANNELS, Default::default()),
+        }
+    }
+
+    fn forward_t(&self, xs: &Tensor, is_training: bool) -> Tensor {
+        let ys = self.conv1.forward_t(xs, is_training);
+        let ys = self.bn1.forward_t(&ys, is_training).relu();
+        let ys = self.conv2.forward_t(&ys, is_training);
+        let ys = self.bn2.forward_t(&ys, is_training);
+        (ys + xs).relu()
+    }
+}
+
+// Small AlphaZero-style residual tower, every layer keeps the 8x8 resolution
+pub struct OthelloNet {
+    conv_input: Conv2D,
+    bn_input: BatchNorm,
+    blocks: Vec<ResidualBlock>,
+
+    conv_policy: Conv2D,
+    bn_policy: BatchNorm,
+    fc_policy: Linear,
+
+    conv_value: Conv2D,
+    bn_value: BatchNorm,
+    fc_value_1: Linear,
+    fc_value_2: Linear,
+}
+
+impl OthelloNet {
+    pub fn new(path: &nn::Path, blocks: usize) -> Self {
+        Self {
+            conv_input: conv3x3(path / "conv_input", 2),
+            bn_input: nn::batch_norm2d(path / "bn_input", CHANNELS, Default::default()),
+            blocks: (0..blocks)
+                .map(|i| ResidualBlock::new(path / "blocks" / i))
+                .collect(),
+
+            conv_policy: nn::conv2d(path / "conv_policy", CHANNELS, 2, 1, Default::default()),
+            bn_policy: nn::batch_norm2d(path / "bn_policy", 2, Default::default()),
+            fc_policy: nn::linear(path / "fc_policy", 2 * 64, 64, Default::default()),
+
+            conv_value: nn::conv2d(path / "conv_value", CHANNELS, 1, 1, Default::default()),
+            bn_value: nn::batch_norm2d(path / "bn_value", 1, Default::default()),
+            fc_value_1: nn::linear(path / "fc_value_1", 64, 64, Default::default()),
+            fc_value_2: nn::linear(path / "fc_value_2", 64, 1, Default::default()),
+        }
+    }
+}
+
+impl AlphaZeroNet for OthelloNet {
+    fn forward_t(&self, xs: &Tensor, is_training: bool) -> NetOutput {
+        let batch = xs.size()[0];
+
+        let mid = self.conv_input.forward_t(xs, is_training);
+        let mut mid = self.bn_input.forward_t(&mid, is_training).relu();
+        for block in &self.blocks {
+            mid = block.forward_t(&mid, is_training);
+        }
+
+        let policy = self.conv_policy.forward_t(&mid, is_training);
+        let policy = self.bn_policy.forward_t(&policy, is_training).relu();
+        let policy = self
+            .fc_policy
+            .forward(&policy.view([batch, -1]))
+            .log_softmax(1, None)
+            .view([batch, 8, 8]);
+
+        let val = self.conv_value.forward_t(&mid, is_training);
+        let val = self.bn_value.forward_t(&val, is_training).relu();
+        let val = self.fc_value_1.forward(&val.view([batch, -1])).relu();
+        let val = self.fc_value_2.forward(&val).view([batch]).sigmoid();
+
+        NetOutput::new(val, policy)
+    }
+}
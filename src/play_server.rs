@@ -0,0 +1,470 @@
+use std::net::SocketAddr;
+
+use futures::{stream::FuturesUnordered, SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+use tokio_tungstenite::{
+    tungstenite::{handshake::derive_accept_key, protocol::Role, Message},
+    WebSocketStream,
+};
+use tracing::{info, warn};
+
+use crate::{
+    alpha_zero::{
+        argmax, Evaluator, Game, MonteCarloTree, Notation, Perspective, TerminationState,
+    },
+    http::{self, Response},
+};
+
+// Of the engine's moves, the most visited ones reported with each
+const CANDIDATES: usize = 5;
+
+// A browser's messages, as JSON text messages like `{"type": "move", "move": "7,7"}`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    NewGame {
+        #[serde(default = "human_first")]
+        human_first: bool,
+    },
+    // In the game's notation
+    Move {
+        r#move: String,
+    },
+    Resign,
+}
+
+fn human_first() -> bool {
+    true
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Winner {
+    Human,
+    Engine,
+    Draw,
+}
+
+#[derive(Debug, Serialize)]
+struct Candidate {
+    r#move: String,
+    // Share of the search's visits
+    visits: f32,
+    prior: f32,
+    // Mean value for the engine, `None` if the search never tried the move
+    value: Option<f32>,
+}
+
+// The server's messages
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Reply {
+    // After a new game and every move
+    Position {
+        moves: Vec<String>,
+        legal: Vec<String>,
+        human_to_move: bool,
+        text: Option<String>,
+    },
+    EngineMove {
+        r#move: String,
+        // Of the engine before its move, by its search
+        value: f32,
+        candidates: Vec<Candidate>,
+    },
+    GameOver {
+        winner: Winner,
+        resigned: bool,
+    },
+    Error {
+        message: String,
+    },
+}
+
+// A game of a connection, against an engine searching with its evaluator
+struct Session<TGame: Game, TEval> {
+    state: TGame,
+    moves: Vec<String>,
+    human_to_move: bool,
+    over: bool,
+    evaluator: Option<TEval>,
+}
+
+// Plays games against browsers over WebSockets, at `/ws`, a game at a time per connection and
+// a new evaluator for each. `/` serves the page given to `with_page`, if any.
+pub struct PlayServer<TGame: Game, F> {
+    start: TGame,
+    notation: Notation<TGame>,
+    text: Option<fn(&TGame) -> String>,
+    new_evaluator: F,
+    simulations: usize,
+    c_puct: f32,
+    page: Option<&'static str>,
+}
+
+// For gomoku, on boards of any size, with moves as `<row>,<column>`
+pub const GOMOKU_PAGE: &str = include_str!("play_server/gomoku.html");
+
+impl<TGame, TEval, F> PlayServer<TGame, F>
+where
+    TGame: Game + Clone,
+    TGame::Move: PartialEq,
+    TEval: Evaluator<TGame>,
+    F: Fn() -> TEval,
+{
+    pub fn new(
+        start: TGame,
+        notation: Notation<TGame>,
+        text: Option<fn(&TGame) -> String>,
+        new_evaluator: F,
+        simulations: usize,
+        c_puct: f32,
+    ) -> Self {
+        Self {
+            start,
+            notation,
+            text,
+            new_evaluator,
+            simulations,
+            c_puct,
+            page: None,
+        }
+    }
+
+    pub fn with_page(mut self, page: &'static str) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    // Serves the connections until the listener fails. They are all served on the current
+    // task, taking turns at the engine's searches.
+    pub async fn serve(&self, listener: TcpListener) -> anyhow::Result<()> {
+        let mut connections = FuturesUnordered::new();
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, peer) = match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            warn!("Failed to accept a connection: {e}");
+                            continue;
+                        }
+                    };
+                    connections.push(async move { (peer, self.connection(stream).await) });
+                }
+                Some((peer, result)) = connections.next(), if !connections.is_empty() => {
+                    if let Err(e) = result {
+                        warn!(%peer, "Connection failed: {e:#}");
+                    }
+                }
+            }
+        }
+    }
+
+    pub async fn bind_and_serve(&self, addr: impl ToSocketAddrs) -> anyhow::Result<()> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr: SocketAddr = listener.local_addr()?;
+        info!(%addr, "Serving games at http://{addr}");
+        self.serve(listener).await
+    }
+
+    // A WebSocket if the request upgrades to one, or else a single response
+    async fn connection(&self, mut stream: TcpStream) -> anyhow::Result<()> {
+        let request = http::Request::read(&mut stream).await?;
+        let key = request.header("sec-websocket-key");
+        let response = match (
+            request.method.as_str(),
+            request.path.as_str(),
+            key,
+            self.page,
+        ) {
+            ("GET", "/ws", Some(key), _) => {
+                let head = format!(
+                    "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                     Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                    derive_accept_key(key.as_bytes())
+                );
+                stream.write_all(head.as_bytes()).await?;
+                let socket = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+                return self.play(socket).await;
+            }
+            ("GET", "/", _, Some(page)) => Response::ok("text/html; charset=utf-8", page),
+            ("GET", ..) => Response::text("404 Not Found", "Not found"),
+            _ => Response::text("405 Method Not Allowed", "Only GET is supported"),
+        };
+        response.write(&mut stream).await
+    }
+
+    async fn play(&self, mut socket: WebSocketStream<TcpStream>) -> anyhow::Result<()> {
+        let mut session = None;
+        while let Some(message) = socket.next().await {
+            let text = match message? {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                // Pings are answered by the socket
+                _ => continue,
+            };
+            let replies = match serde_json::from_str::<Request>(&text) {
+                Ok(request) => self.answer(&mut session, request).await,
+                Err(e) => vec![Reply::Error {
+                    message: format!("Invalid request {text}: {e}"),
+                }],
+            };
+            for reply in replies {
+                socket
+                    .send(Message::Text(serde_json::to_string(&reply)?))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn answer(
+        &self,
+        session: &mut Option<Session<TGame, TEval>>,
+        request: Request,
+    ) -> Vec<Reply> {
+        let error = |message: &str| {
+            vec![Reply::Error {
+                message: message.to_owned(),
+            }]
+        };
+        match request {
+            Request::NewGame { human_first } => {
+                // The evaluator of the last game is kept, with its executor handle
+                let evaluator = session.take().and_then(|session| session.evaluator);
+                let session = session.insert(Session {
+                    state: self.start.clone(),
+                    moves: vec![],
+                    human_to_move: human_first,
+                    over: false,
+                    evaluator: evaluator.or_else(|| Some((self.new_evaluator)())),
+                });
+                let mut replies = vec![self.position(session)];
+                if !human_first {
+                    replies.extend(self.engine_turn(session).await);
+                }
+                replies
+            }
+            Request::Move { r#move } => {
+                let Some(session) = session.as_mut().filter(|session| !session.over) else {
+                    return error("No game is in progress");
+                };
+                if !session.human_to_move {
+                    return error("It's the engine's turn");
+                }
+                let m = match (self.notation.parse)(&session.state, &r#move) {
+                    Ok(m) if session.state.is_legal(&m) => m,
+                    Ok(_) => return error(&format!("{} isn't legal", r#move)),
+                    Err(e) => return error(&format!("{e:#}")),
+                };
+                self.make_move(session, &m);
+                let mut replies = vec![self.position(session)];
+                match self.outcome(session) {
+                    Some(over) => replies.push(over),
+                    None if !session.human_to_move => {
+                        replies.extend(self.engine_turn(session).await)
+                    }
+                    None => {}
+                }
+                replies
+            }
+            Request::Resign => {
+                let Some(session) = session.as_mut().filter(|session| !session.over) else {
+                    return error("No game is in progress");
+                };
+                session.over = true;
+                vec![Reply::GameOver {
+                    winner: Winner::Engine,
+                    resigned: true,
+                }]
+            }
+        }
+    }
+
+    fn position(&self, session: &Session<TGame, TEval>) -> Reply {
+        let legal = match session.over {
+            true => vec![],
+            false => session.state.get_state().get_moves().unwrap_or_default(),
+        };
+        Reply::Position {
+            moves: session.moves.clone(),
+            legal: legal
+                .iter()
+                .map(|m| (self.notation.format)(&session.state, m))
+                .collect(),
+            human_to_move: session.human_to_move,
+            text: self.text.map(|text| text(&session.state)),
+        }
+    }
+
+    fn make_move(&self, session: &mut Session<TGame, TEval>, m: &TGame::Move) {
+        session
+            .moves
+            .push((self.notation.format)(&session.state, m));
+        session.state = session.state.make_move(m);
+        if Perspective::after_move(m) == Perspective::Opponent {
+            session.human_to_move = !session.human_to_move;
+        }
+    }
+
+    // `GameOver` if the game just ended
+    fn outcome(&self, session: &mut Session<TGame, TEval>) -> Option<Reply> {
+        let TerminationState::Terminal(value) = session.state.get_state() else {
+            return None;
+        };
+        session.over = true;
+        let to_move_won = match value {
+            v if v > 0.5 => Some(true),
+            v if v < 0.5 => Some(false),
+            _ => None,
+        };
+        let winner = match to_move_won.map(|won| won == session.human_to_move) {
+            Some(true) => Winner::Human,
+            Some(false) => Winner::Engine,
+            None => Winner::Draw,
+        };
+        Some(Reply::GameOver {
+            winner,
+            resigned: false,
+        })
+    }
+
+    // The engine's moves until the human is to move or the game is over
+    async fn engine_turn(&self, session: &mut Session<TGame, TEval>) -> Vec<Reply> {
+        let mut replies = vec![];
+        while !session.human_to_move {
+            let TerminationState::Moves(moves) = session.state.get_state() else {
+                break;
+            };
+            let evaluator = session.evaluator.take().unwrap();
+            let mut tree = MonteCarloTree::new(session.state.clone(), evaluator);
+            // The root is only evaluated by the first simulation
+            tree.do_simulations(self.simulations.max(1), self.c_puct)
+                .await;
+            let (visits, priors, q) = (tree.get_policy(), tree.get_priors(), tree.get_q_values());
+            let value = tree.get_root_value();
+            session.evaluator = Some(tree.into_evaluator());
+
+            let mut order = (0..moves.len()).collect::<Vec<_>>();
+            order.sort_by(|&a, &b| visits[b].total_cmp(&visits[a]));
+            let candidates = order
+                .into_iter()
+                .take(CANDIDATES)
+                .map(|i| Candidate {
+                    r#move: (self.notation.format)(&session.state, &moves[i]),
+                    visits: visits[i],
+                    prior: priors[i],
+                    value: q[i],
+                })
+                .collect();
+            let best = &moves[argmax(&visits)];
+            replies.push(Reply::EngineMove {
+                r#move: (self.notation.format)(&session.state, best),
+                value,
+                candidates,
+            });
+            self.make_move(session, best);
+            replies.push(self.position(session));
+            if let Some(over) = self.outcome(session) {
+                replies.push(over);
+            }
+        }
+        replies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use serde_json::Value;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+    use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+    use crate::{
+        alpha_zero::{Notation, UniformEvaluator},
+        tictactoe3::TicTacToe3,
+    };
+
+    use super::PlayServer;
+
+    // The replies to the request, until none come for a while
+    async fn exchange(
+        socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+        request: &str,
+    ) -> Vec<Value> {
+        socket
+            .send(Message::Text(request.to_owned()))
+            .await
+            .unwrap();
+        let mut replies = vec![];
+        let wait = std::time::Duration::from_millis(500);
+        while let Ok(Some(Ok(Message::Text(text)))) =
+            tokio::time::timeout(wait, socket.next()).await
+        {
+            replies.push(serde_json::from_str(&text).unwrap());
+        }
+        replies
+    }
+
+    #[tokio::test]
+    async fn game_with_a_browser() {
+        let server = PlayServer::new(
+            TicTacToe3::new(),
+            Notation::of(),
+            None,
+            || UniformEvaluator,
+            200,
+            1.0,
+        )
+        .with_page("<html></html>");
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let browser = async {
+            let mut page = String::new();
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+            stream.read_to_string(&mut page).await.unwrap();
+            assert!(page.ends_with("\r\n\r\n<html></html>"), "{page}");
+
+            let (mut socket, _) = connect_async(format!("ws://{addr}/ws")).await.unwrap();
+            let replies = exchange(&mut socket, r#"{"type": "move", "move": "0"}"#).await;
+            assert_eq!(replies[0]["type"], "error");
+
+            let replies =
+                exchange(&mut socket, r#"{"type": "new_game", "human_first": false}"#).await;
+            let kinds = replies
+                .iter()
+                .map(|r| r["type"].clone())
+                .collect::<Vec<_>>();
+            assert_eq!(kinds, ["position", "engine_move", "position"]);
+            assert_eq!(replies[0]["legal"].as_array().unwrap().len(), 9);
+            assert!(!replies[1]["candidates"].as_array().unwrap().is_empty());
+            assert_eq!(replies[2]["human_to_move"], true);
+
+            let legal = replies[2]["legal"][0].as_str().unwrap().to_owned();
+            let replies = exchange(
+                &mut socket,
+                &format!(r#"{{"type": "move", "move": "{legal}"}}"#),
+            )
+            .await;
+            assert_eq!(replies[0]["moves"].as_array().unwrap().len(), 2);
+            assert_eq!(replies[1]["type"], "engine_move");
+
+            let replies = exchange(&mut socket, r#"{"type": "resign"}"#).await;
+            assert_eq!(replies[0]["winner"], "engine");
+            assert_eq!(replies[0]["resigned"], true);
+        };
+        tokio::select! {
+            result = server.serve(listener) => panic!("The server stopped: {result:?}"),
+            () = browser => {}
+        }
+    }
+}
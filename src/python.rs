@@ -0,0 +1,416 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use numpy::{PyArray, PyArrayDyn, PyArrayMethods, PyReadonlyArrayDyn, PyUntypedArrayMethods};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use tch::{nn, Device, Kind, Tensor};
+
+use crate::{
+    alpha_zero::{
+        generate_self_played_game, AlphaZeroAdapter, AlphaZeroNet, Arena, CrossTable,
+        DeviceSetting, ExecutorScope, Game, MatchConfig, Seed,
+    },
+    config::{ExecutorConfig, SearchConfig},
+    registry::{GameRegistry, GameSpec, GameVisitor},
+};
+
+// Python bindings to self-play, the arena and the nets, with arrays as numpy's. Built with
+// maturin, whose settings are in pyproject.toml, into the `alpha_zero` module.
+#[pymodule]
+#[pyo3(name = "alpha_zero")]
+fn module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(games, m)?)?;
+    m.add_function(wrap_pyfunction!(self_play, m)?)?;
+    m.add_function(wrap_pyfunction!(arena, m)?)?;
+    m.add_class::<Network>()?;
+    Ok(())
+}
+
+// As Python's `ValueError`, with the causes
+fn to_py_err(e: anyhow::Error) -> PyErr {
+    PyValueError::new_err(format!("{e:#}"))
+}
+
+fn visit<T, TVisitor>(game: &str, visitor: TVisitor) -> anyhow::Result<T>
+where
+    TVisitor: GameVisitor<Output = anyhow::Result<T>>,
+{
+    let registry = GameRegistry::with_builtin_games();
+    let names = registry.names().collect::<Vec<_>>();
+    registry
+        .visit(game, visitor)
+        .with_context(|| format!("Unknown game {game}, expected one of: {}", names.join(", ")))?
+}
+
+fn resolve_device(device: &str) -> anyhow::Result<Device> {
+    device.parse::<DeviceSetting>()?.resolve()
+}
+
+fn load_net<TNet>(
+    build_net: &dyn Fn(&nn::Path) -> TNet,
+    device: Device,
+    weights: Option<&Path>,
+) -> anyhow::Result<TNet> {
+    let mut vs = nn::VarStore::new(device);
+    let net = build_net(&vs.root());
+    if let Some(weights) = weights {
+        vs.load(weights)
+            .with_context(|| format!("Failed to load {}", weights.display()))?;
+    }
+    Ok(net)
+}
+
+fn to_numpy<'py>(py: Python<'py>, tensor: &Tensor) -> PyResult<Bound<'py, PyArrayDyn<f32>>> {
+    let shape = tensor
+        .size()
+        .iter()
+        .map(|&d| d as usize)
+        .collect::<Vec<_>>();
+    let values = Vec::<f32>::try_from(tensor.flatten(0, -1).to_kind(Kind::Float))
+        .map_err(|e| to_py_err(e.into()))?;
+    PyArray::from_vec_bound(py, values).reshape(shape)
+}
+
+fn from_numpy<T: numpy::Element + tch::kind::Element>(array: &PyReadonlyArrayDyn<'_, T>) -> Tensor {
+    let shape = array.shape().iter().map(|&d| d as i64).collect::<Vec<_>>();
+    let values = array.as_array().iter().copied().collect::<Vec<_>>();
+    Tensor::from_slice(&values).view(shape.as_slice())
+}
+
+// Self-played positions as the net takes them, with their targets, a row per position
+struct Samples {
+    inputs: Tensor,
+    // Visit distributions, in the net's policy shape
+    policies: Tensor,
+    // Final outcomes for the players to move
+    values: Vec<f32>,
+    root_q: Vec<f32>,
+    games: Vec<i64>,
+    move_numbers: Vec<i64>,
+}
+
+struct SelfPlayJob {
+    weights: Option<PathBuf>,
+    games: usize,
+    search: SearchConfig,
+    temperature: f32,
+    seed: Seed,
+    device: Device,
+}
+
+impl GameVisitor for SelfPlayJob {
+    type Output = anyhow::Result<Samples>;
+
+    fn visit<TGame, TNet, TAdapter>(self, spec: GameSpec<TGame, TNet, TAdapter>) -> Self::Output
+    where
+        TGame: Game + Clone + Send + Sync + 'static,
+        TGame::Move: Clone + PartialEq + Send + Sync,
+        TNet: AlphaZeroNet + Send + 'static,
+        TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+    {
+        tokio::runtime::Runtime::new()?.block_on(play_games(spec, self))
+    }
+}
+
+// The games in order, each played with `job.seed.derive(game)`
+async fn play_games<TGame, TNet, TAdapter>(
+    spec: GameSpec<TGame, TNet, TAdapter>,
+    job: SelfPlayJob,
+) -> anyhow::Result<Samples>
+where
+    TGame: Game + Clone + Send + Sync + 'static,
+    TGame::Move: Send + Sync,
+    TNet: AlphaZeroNet + Send + 'static,
+    TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+{
+    anyhow::ensure!(job.games > 0, "No games to play");
+    let net = load_net(&*spec.build_net, job.device, job.weights.as_deref())?;
+    let config = ExecutorConfig::default();
+    let parallelism = job.games.min(config.parallelism);
+    let mut executor = ExecutorScope::new(
+        net,
+        parallelism,
+        config.batch_size.min(parallelism),
+        config.max_wait(),
+        (Kind::Float, job.device),
+    );
+    let (simulations, c_puct) = (job.search.simulations, job.search.c_puct);
+    for game in 0..job.games {
+        let (start, openings) = (spec.start.clone(), spec.openings.clone());
+        let (temperature, seed) = (job.temperature, job.seed.derive(game));
+        executor.spawn(move |handle| async move {
+            let samples = generate_self_played_game::<TGame, TNet, TAdapter, _>(
+                start,
+                openings,
+                simulations,
+                c_puct,
+                |_| temperature,
+                seed,
+                handle,
+                None,
+            )
+            .await;
+            (game, samples)
+        });
+    }
+    let mut games = vec![];
+    while let Some(game) = executor.next().await {
+        games.push(game);
+    }
+    executor.join().await;
+    games.sort_unstable_by_key(|&(game, _)| game);
+
+    let samples = games
+        .into_iter()
+        .flat_map(|(game, samples)| samples.into_iter().map(move |sample| (game, sample)))
+        .collect::<Vec<_>>();
+    let states = samples
+        .iter()
+        .map(|(_, sample)| sample.state.clone())
+        .collect::<Vec<_>>();
+    let moves = states
+        .iter()
+        .map(|state| state.get_state().get_moves().unwrap_or_default())
+        .collect::<Vec<_>>();
+    let policies = samples
+        .iter()
+        .map(|(_, sample)| sample.policy.clone())
+        .collect::<Vec<_>>();
+    Ok(Samples {
+        inputs: TAdapter::convert_games_to_nn_input(&states, (Kind::Float, Device::Cpu)),
+        policies: TAdapter::convert_policies_to_nn(&policies, &moves, (Kind::Float, Device::Cpu)),
+        values: samples.iter().map(|(_, sample)| sample.value).collect(),
+        root_q: samples.iter().map(|(_, sample)| sample.root_q).collect(),
+        games: samples.iter().map(|&(game, _)| game as i64).collect(),
+        move_numbers: samples
+            .iter()
+            .map(|(_, sample)| sample.move_number as i64)
+            .collect(),
+    })
+}
+
+struct ArenaJob {
+    weights: Vec<PathBuf>,
+    config: MatchConfig,
+    device: Device,
+}
+
+impl GameVisitor for ArenaJob {
+    type Output = anyhow::Result<CrossTable>;
+
+    fn visit<TGame, TNet, TAdapter>(self, spec: GameSpec<TGame, TNet, TAdapter>) -> Self::Output
+    where
+        TGame: Game + Clone + Send + Sync + 'static,
+        TGame::Move: Clone + PartialEq + Send + Sync,
+        TNet: AlphaZeroNet + Send + 'static,
+        TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+    {
+        let mut arena = Arena::new(spec.build_net.clone(), self.device);
+        for weights in &self.weights {
+            arena
+                .add(weights.display().to_string(), weights)
+                .with_context(|| format!("Failed to load {}", weights.display()))?;
+        }
+        anyhow::ensure!(arena.len() > 1, "At least two nets are needed");
+        tokio::runtime::Runtime::new()?.block_on(arena.round_robin::<TGame, TAdapter>(
+            &spec.start,
+            spec.openings.as_ref(),
+            spec.heuristic,
+            &self.config,
+        ))
+    }
+}
+
+struct NetworkJob {
+    weights: Option<PathBuf>,
+    device: Device,
+}
+
+impl GameVisitor for NetworkJob {
+    type Output = anyhow::Result<Box<dyn AlphaZeroNet + Send>>;
+
+    fn visit<TGame, TNet, TAdapter>(self, spec: GameSpec<TGame, TNet, TAdapter>) -> Self::Output
+    where
+        TGame: Game + Clone + Send + Sync + 'static,
+        TGame::Move: Clone + PartialEq + Send + Sync,
+        TNet: AlphaZeroNet + Send + 'static,
+        TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+    {
+        let net = load_net(&*spec.build_net, self.device, self.weights.as_deref())?;
+        Ok(Box::new(net))
+    }
+}
+
+// Names of the games
+#[pyfunction]
+fn games() -> Vec<&'static str> {
+    GameRegistry::<NetworkJob>::with_builtin_games()
+        .names()
+        .collect()
+}
+
+// Plays `games` games against itself with the net, the game's default one if there are no
+// weights, and returns their positions in order as a dict of arrays: `inputs` and `policies`
+// in the net's shapes, `values`, `root_q`, `game` and `move_number`
+#[pyfunction]
+#[pyo3(signature = (
+    game,
+    weights=None,
+    games=1,
+    simulations=None,
+    c_puct=None,
+    temperature=1.0,
+    seed=None,
+    device="cpu"
+))]
+#[allow(clippy::too_many_arguments)]
+fn self_play<'py>(
+    py: Python<'py>,
+    game: &str,
+    weights: Option<PathBuf>,
+    games: usize,
+    simulations: Option<usize>,
+    c_puct: Option<f32>,
+    temperature: f32,
+    seed: Option<u64>,
+    device: &str,
+) -> PyResult<Bound<'py, PyDict>> {
+    let defaults = SearchConfig::default();
+    let job = SelfPlayJob {
+        weights,
+        games,
+        search: SearchConfig {
+            simulations: simulations.unwrap_or(defaults.simulations),
+            c_puct: c_puct.unwrap_or(defaults.c_puct),
+        },
+        temperature,
+        seed: seed.map_or_else(Seed::random, Seed),
+        device: resolve_device(device).map_err(to_py_err)?,
+    };
+    let samples = py.allow_threads(|| visit(game, job)).map_err(to_py_err)?;
+    let dict = PyDict::new_bound(py);
+    dict.set_item("inputs", to_numpy(py, &samples.inputs)?)?;
+    dict.set_item("policies", to_numpy(py, &samples.policies)?)?;
+    dict.set_item("values", PyArray::from_vec_bound(py, samples.values))?;
+    dict.set_item("root_q", PyArray::from_vec_bound(py, samples.root_q))?;
+    dict.set_item("game", PyArray::from_vec_bound(py, samples.games))?;
+    dict.set_item(
+        "move_number",
+        PyArray::from_vec_bound(py, samples.move_numbers),
+    )?;
+    Ok(dict)
+}
+
+// Plays every net against every other one and returns the cross-table as a dict: the `names`,
+// the `results` as `(wins, draws, losses)` of each row against each column, and the table as
+// `markdown`
+#[pyfunction]
+#[pyo3(signature = (game, weights, games=100, simulations=None, c_puct=None, device="cpu"))]
+fn arena<'py>(
+    py: Python<'py>,
+    game: &str,
+    weights: Vec<PathBuf>,
+    games: usize,
+    simulations: Option<usize>,
+    c_puct: Option<f32>,
+    device: &str,
+) -> PyResult<Bound<'py, PyDict>> {
+    let defaults = MatchConfig::default();
+    let job = ArenaJob {
+        weights,
+        config: MatchConfig {
+            games,
+            simulations: simulations.unwrap_or(defaults.simulations),
+            c_puct: c_puct.unwrap_or(defaults.c_puct),
+            ..defaults
+        },
+        device: resolve_device(device).map_err(to_py_err)?,
+    };
+    let table = py.allow_threads(|| visit(game, job)).map_err(to_py_err)?;
+    let results = table
+        .results
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|result| (result.wins, result.draws, result.losses))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let dict = PyDict::new_bound(py);
+    dict.set_item("markdown", table.to_markdown())?;
+    dict.set_item("names", table.names)?;
+    dict.set_item("results", results)?;
+    Ok(dict)
+}
+
+// A game's net, evaluating batches of inputs in its shape like the executor does
+#[pyclass]
+struct Network {
+    net: Box<dyn AlphaZeroNet + Send>,
+    device: Device,
+}
+
+#[pymethods]
+impl Network {
+    #[new]
+    #[pyo3(signature = (game, weights=None, device="cpu"))]
+    fn new(game: &str, weights: Option<PathBuf>, device: &str) -> PyResult<Self> {
+        let device = resolve_device(device).map_err(to_py_err)?;
+        let net = visit(game, NetworkJob { weights, device }).map_err(to_py_err)?;
+        Ok(Self { net, device })
+    }
+
+    // The values and the policies, as probabilities. With a boolean `legal` of the policies'
+    // shape, the policies are only over the actions it sets.
+    #[pyo3(signature = (inputs, legal=None))]
+    fn evaluate<'py>(
+        &self,
+        py: Python<'py>,
+        inputs: PyReadonlyArrayDyn<'py, f32>,
+        legal: Option<PyReadonlyArrayDyn<'py, bool>>,
+    ) -> PyResult<(Bound<'py, PyArrayDyn<f32>>, Bound<'py, PyArrayDyn<f32>>)> {
+        let inputs = from_numpy(&inputs).to(self.device);
+        let output = tch::no_grad(|| match &legal {
+            Some(legal) => {
+                let legal = from_numpy(legal).to(self.device);
+                self.net.forward_masked_t(&inputs, &legal, false)
+            }
+            None => self.net.forward_t(&inputs, false),
+        });
+        Ok((
+            to_numpy(py, &output.value.to(Device::Cpu))?,
+            to_numpy(py, &output.policy.exp().to(Device::Cpu))?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tch::Device;
+
+    use crate::{alpha_zero::Seed, config::SearchConfig};
+
+    use super::{visit, SelfPlayJob};
+
+    #[test]
+    fn self_play_rows_line_up() {
+        let job = SelfPlayJob {
+            weights: None,
+            games: 2,
+            search: SearchConfig {
+                simulations: 8,
+                c_puct: 1.0,
+            },
+            temperature: 1.0,
+            seed: Seed(7),
+            device: Device::Cpu,
+        };
+        let samples = visit("tictactoe", job).unwrap();
+        let rows = samples.values.len();
+        assert_eq!(samples.inputs.size(), [rows as i64, 2, 3, 3]);
+        assert_eq!(samples.policies.size(), [rows as i64, 3, 3]);
+        assert_eq!(samples.games.first(), Some(&0));
+        assert_eq!(samples.games.last(), Some(&1));
+        assert_eq!(samples.move_numbers[0], 0);
+    }
+}
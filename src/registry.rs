@@ -0,0 +1,476 @@
+use std::{collections::BTreeMap, marker::PhantomData, path::Path, sync::Arc};
+
+use anyhow::Context;
+use tch::nn;
+
+use crate::{
+    alpha_zero::{
+        hash_position, reachable_positions, AlphaZeroAdapter, AlphaZeroNet, BuildNet, Game,
+        GameVisualizer, GtpBoard, HeuristicEval, Notation, OpeningBook, PerfectPlay, PortableGame,
+        PositionSuite, ResNetAlphaZero, ResNetConfig, SgfFormat, UciFormat,
+    },
+    chess::{ChessAlphaZeroAdapter, ChessGame, ChessNet, ChessNetConfig},
+    config::NetworkConfig,
+    go::{GoAlphaZeroAdapter, GoConfig, GoState, GO_INPUT_PLANES},
+    gomoku::{
+        GomokuAlphaZeroAdapter, GomokuRules, GomokuState, OpeningRule, RuleSet, GOMOKU_INPUT_PLANES,
+    },
+    hex::{HexAlphaZeroAdapter, HexBoard, HEX_INPUT_PLANES},
+    othello::{OthelloAlphaZeroAdapter, OthelloBoard, OthelloNet},
+    tictactoe::{
+        gomoku_opening_book, gomoku_tactics, CellState, GomokuBoard, GomokuVisualizer,
+        TicTacToeAlphaZeroAdapter,
+    },
+    tictactoe3::{TicTacToe3, TicTacToe3AlphaZeroAdapter, TicTacToe3Net, TicTacToe3Solver},
+};
+
+// Everything needed to train on a game: the start state, the network with its default
+// config and optional extras. The adapter is only carried in the type.
+pub struct GameSpec<TGame: Game, TNet, TAdapter> {
+    pub start: TGame,
+    pub openings: Option<OpeningBook<TGame>>,
+    pub build_net: BuildNet<TNet>,
+    // For nets whose tower a `NetworkConfig` can resize
+    pub sized_net: Option<fn(&nn::Path, &NetworkConfig) -> TNet>,
+    pub visualizer: Option<Arc<dyn GameVisualizer<TGame> + Send + Sync>>,
+    // Value of the player to move, for baselines that don't search
+    pub heuristic: Option<fn(&TGame) -> f32>,
+    // For solved games, built once per run
+    pub perfect_play: Option<fn() -> PerfectPlay<TGame>>,
+    // Test positions with known best moves
+    pub suite: Option<fn() -> PositionSuite<TGame>>,
+    // For writing and reading games as text
+    pub notation: Option<Notation<TGame>>,
+    // The board for the terminal, the first player's pieces being told apart by their symbol
+    pub text: Option<fn(&TGame) -> String>,
+    // For games on a square board, to exchange games with other programs
+    pub sgf: Option<SgfFormat<TGame>>,
+    // For games on a square board, to play in GUIs speaking GTP
+    pub gtp: Option<GtpBoard<TGame>>,
+    // For chess, to play in GUIs and tournaments speaking UCI
+    pub uci: Option<UciFormat<TGame>>,
+    // Of positions, for spotting duplicates in the training data
+    pub hash: Option<fn(&TGame) -> u64>,
+    adapter: PhantomData<fn() -> TAdapter>,
+}
+
+// Not derived, which would require the net and the adapter to be `Clone` too
+impl<TGame: Game + Clone, TNet, TAdapter> Clone for GameSpec<TGame, TNet, TAdapter> {
+    fn clone(&self) -> Self {
+        Self {
+            start: self.start.clone(),
+            openings: self.openings.clone(),
+            build_net: self.build_net.clone(),
+            sized_net: self.sized_net,
+            visualizer: self.visualizer.clone(),
+            heuristic: self.heuristic,
+            perfect_play: self.perfect_play,
+            suite: self.suite,
+            notation: self.notation,
+            text: self.text,
+            sgf: self.sgf,
+            gtp: self.gtp,
+            uci: self.uci,
+            hash: self.hash,
+            adapter: PhantomData,
+        }
+    }
+}
+
+impl<TGame: Game, TNet, TAdapter> GameSpec<TGame, TNet, TAdapter> {
+    pub fn new(
+        start: TGame,
+        build_net: impl Fn(&nn::Path) -> TNet + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            start,
+            openings: None,
+            build_net: Arc::new(build_net),
+            sized_net: None,
+            visualizer: None,
+            heuristic: None,
+            perfect_play: None,
+            suite: None,
+            notation: None,
+            text: None,
+            sgf: None,
+            gtp: None,
+            uci: None,
+            hash: None,
+            adapter: PhantomData,
+        }
+    }
+
+    // With the game's default tower unless the experiment resizes it, see `with_network`
+    pub fn sized(start: TGame, build_net: fn(&nn::Path, &NetworkConfig) -> TNet) -> Self
+    where
+        TNet: 'static,
+    {
+        let mut spec = Self::new(start, move |path| {
+            build_net(path, &NetworkConfig::default())
+        });
+        spec.sized_net = Some(build_net);
+        spec
+    }
+
+    // Nets resized by the config, which can only leave the tower as it is for games without a
+    // sized net
+    pub fn with_network(mut self, network: &NetworkConfig) -> anyhow::Result<Self>
+    where
+        TNet: 'static,
+    {
+        if network.is_default() {
+            return Ok(self);
+        }
+        let build_net = self
+            .sized_net
+            .context("The game's net has no tower to resize, leave the network settings out")?;
+        let network = network.clone();
+        self.build_net = Arc::new(move |path| build_net(path, &network));
+        Ok(self)
+    }
+
+    pub fn with_openings(mut self, openings: OpeningBook<TGame>) -> Self {
+        self.openings = Some(openings);
+        self
+    }
+
+    pub fn with_visualizer(
+        mut self,
+        visualizer: impl GameVisualizer<TGame> + Send + Sync + 'static,
+    ) -> Self {
+        self.visualizer = Some(Arc::new(visualizer));
+        self
+    }
+
+    pub fn with_heuristic(mut self, heuristic: fn(&TGame) -> f32) -> Self {
+        self.heuristic = Some(heuristic);
+        self
+    }
+
+    pub fn with_perfect_play(mut self, perfect_play: fn() -> PerfectPlay<TGame>) -> Self {
+        self.perfect_play = Some(perfect_play);
+        self
+    }
+
+    pub fn with_suite(mut self, suite: fn() -> PositionSuite<TGame>) -> Self {
+        self.suite = Some(suite);
+        self
+    }
+
+    pub fn with_notation(mut self, notation: Notation<TGame>) -> Self {
+        self.notation = Some(notation);
+        self
+    }
+
+    pub fn with_text(mut self, text: fn(&TGame) -> String) -> Self {
+        self.text = Some(text);
+        self
+    }
+
+    pub fn with_sgf(mut self, sgf: SgfFormat<TGame>) -> Self {
+        self.sgf = Some(sgf);
+        self
+    }
+
+    pub fn with_gtp(mut self, gtp: GtpBoard<TGame>) -> Self {
+        self.gtp = Some(gtp);
+        self
+    }
+
+    pub fn with_uci(mut self, uci: UciFormat<TGame>) -> Self {
+        self.uci = Some(uci);
+        self
+    }
+
+    pub fn with_hash(mut self, hash: fn(&TGame) -> u64) -> Self {
+        self.hash = Some(hash);
+        self
+    }
+
+    // The games of a file written by a match or an arena, or of an SGF collection for files
+    // ending in `.sgf`
+    pub fn read_games(&self, file: &Path) -> anyhow::Result<Vec<PortableGame<TGame::Move>>>
+    where
+        TGame: Clone,
+        TGame::Move: Clone,
+    {
+        let text = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let games = match file.extension().is_some_and(|e| e == "sgf") {
+            true => {
+                let sgf = self.sgf.context("The game has no SGF format")?;
+                (sgf.read)(&self.start, &text)
+            }
+            false => {
+                let notation = self
+                    .notation
+                    .context("The game has no notation to read games in")?;
+                PortableGame::parse_all(&self.start, notation, &text)
+            }
+        };
+        games.with_context(|| format!("Failed to parse {}", file.display()))
+    }
+}
+
+// Code that is generic over the game, called with the concrete types of the game picked
+// at runtime
+pub trait GameVisitor {
+    type Output;
+
+    fn visit<TGame, TNet, TAdapter>(self, spec: GameSpec<TGame, TNet, TAdapter>) -> Self::Output
+    where
+        TGame: Game + Clone + Send + Sync + 'static,
+        TGame::Move: Clone + PartialEq + Send + Sync,
+        TNet: AlphaZeroNet + Send + 'static,
+        TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static;
+}
+
+type Entry<TVisitor> = Box<dyn Fn(TVisitor) -> <TVisitor as GameVisitor>::Output>;
+
+// Games selectable by name. Factories are only called for the game that is visited.
+pub struct GameRegistry<TVisitor: GameVisitor> {
+    games: BTreeMap<&'static str, Entry<TVisitor>>,
+}
+
+impl<TVisitor: GameVisitor> Default for GameRegistry<TVisitor> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<TVisitor: GameVisitor> GameRegistry<TVisitor> {
+    pub fn new() -> Self {
+        Self {
+            games: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_builtin_games() -> Self {
+        let mut registry = Self::new();
+        registry.register("go9", || {
+            GameSpec::<_, _, GoAlphaZeroAdapter<9>>::sized(
+                GoState::new(GoConfig::default()),
+                |path, network| {
+                    let config = ResNetConfig::new(GO_INPUT_PLANES, 9, vec![9 * 9 + 1]);
+                    resnet(path, network, config)
+                },
+            )
+            .with_sgf(SgfFormat::of())
+            .with_gtp(GtpBoard::of())
+        });
+        registry.register("gomoku", gomoku::<19>);
+        registry.register("gomoku15", gomoku::<15>);
+        registry.register("hex", || {
+            GameSpec::<_, _, HexAlphaZeroAdapter>::sized(
+                HexBoard::<11>::new(true),
+                |path, network| {
+                    let config = ResNetConfig::new(HEX_INPUT_PLANES, 11, vec![11 * 11 + 1]);
+                    resnet(path, network, config)
+                },
+            )
+            .with_sgf(SgfFormat::of())
+            .with_gtp(GtpBoard::of())
+            .with_hash(hash_position)
+        });
+        registry.register("renju", || {
+            let rules = GomokuRules {
+                rule_set: RuleSet::Renju,
+                opening: OpeningRule::Swap2,
+            };
+            GameSpec::<_, _, GomokuAlphaZeroAdapter>::sized(
+                GomokuState::<15>::new(rules),
+                |path, network| {
+                    let config = ResNetConfig::new(GOMOKU_INPUT_PLANES, 15, vec![15 * 15 + 3])
+                        .with_squeeze_excitation(4)
+                        .with_global_pooling();
+                    resnet(path, network, config)
+                },
+            )
+            .with_hash(hash_position)
+        });
+        registry.register("tictactoe", || {
+            GameSpec::<_, _, TicTacToe3AlphaZeroAdapter>::new(TicTacToe3::new(), TicTacToe3Net::new)
+                .with_perfect_play(|| {
+                    // Every position of the game
+                    let positions = reachable_positions(&TicTacToe3::new(), usize::MAX);
+                    PerfectPlay::new(positions, TicTacToe3Solver::new())
+                })
+                .with_notation(Notation::of())
+                .with_text(|board| {
+                    // The player to move is always X, who moved first if the counts are even
+                    let count = |cell| (0..9).filter(|&i| board[i] == cell).count();
+                    let first_to_move = count(CellState::X) == count(CellState::O);
+                    (0..3)
+                        .map(|row| {
+                            (0..3)
+                                .map(|column| match (board[row * 3 + column], first_to_move) {
+                                    (CellState::Empty, _) => '.',
+                                    (CellState::X, true) | (CellState::O, false) => 'X',
+                                    _ => 'O',
+                                })
+                                .collect::<String>()
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .with_hash(hash_position)
+        });
+        registry.register("othello", || {
+            GameSpec::<_, _, OthelloAlphaZeroAdapter>::new(OthelloBoard::new(), |path| {
+                OthelloNet::new(path, 6)
+            })
+            .with_hash(hash_position)
+        });
+        registry.register("chess", || {
+            GameSpec::<_, _, ChessAlphaZeroAdapter>::sized(ChessGame::default(), |path, network| {
+                let default = ChessNetConfig::default();
+                let (blocks, channels, value_hidden) =
+                    network.tower(default.blocks, default.channels, default.value_hidden);
+                let config = ChessNetConfig {
+                    blocks,
+                    channels,
+                    value_hidden,
+                };
+                ChessNet::new(path, config)
+            })
+            .with_notation(Notation::of())
+            .with_uci(UciFormat::of())
+            .with_hash(ChessGame::position_hash)
+        });
+        registry
+    }
+
+    pub fn register<TGame, TNet, TAdapter>(
+        &mut self,
+        name: &'static str,
+        factory: impl Fn() -> GameSpec<TGame, TNet, TAdapter> + 'static,
+    ) where
+        TGame: Game + Clone + Send + Sync + 'static,
+        TGame::Move: Clone + PartialEq + Send + Sync,
+        TNet: AlphaZeroNet + Send + 'static,
+        TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+    {
+        let previous = self
+            .games
+            .insert(name, Box::new(move |visitor| visitor.visit(factory())));
+        assert!(previous.is_none(), "Game {name} is registered twice");
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.games.keys().copied()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.games.contains_key(name)
+    }
+
+    // `None` if there is no such game
+    pub fn visit(&self, name: &str, visitor: TVisitor) -> Option<TVisitor::Output> {
+        self.games.get(name).map(|factory| factory(visitor))
+    }
+}
+
+// The config's tower, unless the experiment resizes it
+fn resnet(path: &nn::Path, network: &NetworkConfig, config: ResNetConfig) -> ResNetAlphaZero {
+    let (blocks, channels, value_hidden) =
+        network.tower(config.blocks, config.channels, config.value_hidden);
+    let config = config
+        .with_tower(blocks, channels)
+        .with_value_hidden(value_hidden);
+    ResNetAlphaZero::new(path, &config)
+}
+
+fn gomoku<const N: usize>(
+) -> GameSpec<GomokuBoard<N, 5>, ResNetAlphaZero, TicTacToeAlphaZeroAdapter> {
+    GameSpec::sized(GomokuBoard::new(), |path, network| {
+        let planes = TicTacToeAlphaZeroAdapter::<0, false>::INPUT_PLANES as i64;
+        // Threats span more of the board than the tower's receptive field
+        let config = ResNetConfig::new(planes, N as i64, vec![N as i64, N as i64])
+            .with_squeeze_excitation(4)
+            .with_global_pooling()
+            .with_auxiliary_heads(true, true);
+        resnet(path, network, config)
+    })
+    .with_openings(gomoku_opening_book(4))
+    .with_visualizer(GomokuVisualizer)
+    .with_heuristic(<GomokuBoard<N, 5> as HeuristicEval>::eval)
+    .with_suite(gomoku_tactics::<N>)
+    .with_notation(Notation::of())
+    .with_text(|board| match board.is_first_player_to_move() {
+        true => board.to_string(),
+        false => board.clone().flip_players().to_string(),
+    })
+    .with_sgf(SgfFormat::of())
+    .with_gtp(GtpBoard::of())
+    .with_hash(hash_position)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{AlphaZeroAdapter, AlphaZeroNet, Game},
+        config::NetworkConfig,
+        tictactoe3::{TicTacToe3, TicTacToe3AlphaZeroAdapter, TicTacToe3Net},
+    };
+
+    use super::{gomoku, GameRegistry, GameSpec, GameVisitor};
+
+    struct CountStartMoves;
+
+    impl GameVisitor for CountStartMoves {
+        type Output = usize;
+
+        fn visit<TGame, TNet, TAdapter>(self, spec: GameSpec<TGame, TNet, TAdapter>) -> usize
+        where
+            TGame: Game + Clone + Send + Sync + 'static,
+            TGame::Move: Clone + PartialEq + Send + Sync,
+            TNet: AlphaZeroNet + Send + 'static,
+            TAdapter: AlphaZeroAdapter<TGame, TNet> + Send + 'static,
+        {
+            spec.start.get_state().get_moves().unwrap().len()
+        }
+    }
+
+    #[test]
+    fn builtin_games() {
+        let registry = GameRegistry::with_builtin_games();
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            [
+                "chess",
+                "go9",
+                "gomoku",
+                "gomoku15",
+                "hex",
+                "othello",
+                "renju",
+                "tictactoe"
+            ]
+        );
+
+        let moves = |name| registry.visit(name, CountStartMoves);
+        assert_eq!(moves("gomoku"), Some(19 * 19));
+        assert_eq!(moves("gomoku15"), Some(15 * 15));
+        assert_eq!(moves("go9"), Some(9 * 9 + 1));
+        assert_eq!(moves("hex"), Some(11 * 11));
+        // The opening's stones, before any color is chosen
+        assert_eq!(moves("renju"), Some(15 * 15));
+        assert_eq!(moves("tictactoe"), Some(9));
+        assert_eq!(moves("othello"), Some(4));
+        assert_eq!(moves("chess"), Some(20));
+        assert_eq!(moves("connect4"), None);
+
+        let network = NetworkConfig {
+            blocks: Some(2),
+            ..Default::default()
+        };
+        assert!(gomoku::<19>().with_network(&network).is_ok());
+        let spec = GameSpec::<_, _, TicTacToe3AlphaZeroAdapter>::new(
+            TicTacToe3::new(),
+            TicTacToe3Net::new,
+        );
+        assert!(spec.clone().with_network(&NetworkConfig::default()).is_ok());
+        assert!(spec.with_network(&network).is_err());
+    }
+}
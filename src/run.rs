@@ -0,0 +1,202 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use serde::Serialize;
+
+// Directory of a single training run, e.g. `runs/20240501-120000-gomoku`. Everything a run
+// writes goes below it, so experiments don't overwrite each other's files.
+pub struct RunContext {
+    dir: PathBuf,
+}
+
+impl RunContext {
+    // Creates a new run directory under `root`, named after the current UTC time and `name`
+    pub fn create(root: impl AsRef<Path>, name: &str) -> anyhow::Result<Self> {
+        let root = root.as_ref();
+        fs::create_dir_all(root).with_context(|| format!("Failed to create {}", root.display()))?;
+        let base = format!("{}-{name}", timestamp(SystemTime::now()));
+        // Runs started within the same second get a suffix
+        for attempt in 1.. {
+            let dir = match attempt {
+                1 => root.join(&base),
+                _ => root.join(format!("{base}-{attempt}")),
+            };
+            match fs::create_dir(&dir) {
+                Ok(()) => return Self::open(dir),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => {
+                    return Err(err).with_context(|| format!("Failed to create {}", dir.display()))
+                }
+            }
+        }
+        unreachable!()
+    }
+
+    // Continues writing into an existing run directory
+    pub fn open(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let context = Self {
+            dir: dir.as_ref().to_path_buf(),
+        };
+        for sub in [
+            context.checkpoints(),
+            context.data_reports(),
+            context.games(),
+            context.logs(),
+            context.metrics(),
+            context.selfplay(),
+        ] {
+            fs::create_dir_all(&sub)
+                .with_context(|| format!("Failed to create {}", sub.display()))?;
+        }
+        Ok(context)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn checkpoints(&self) -> PathBuf {
+        self.dir.join("checkpoints")
+    }
+
+    // Charts of the metrics, written on request only
+    pub fn plots(&self) -> PathBuf {
+        self.dir.join("plots")
+    }
+
+    // Statistics of the replay buffer, see `data_report`
+    pub fn data_reports(&self) -> PathBuf {
+        self.dir.join("data")
+    }
+
+    pub fn data_report(&self, generation: usize) -> PathBuf {
+        self.data_reports().join(format!("{generation:02}.md"))
+    }
+
+    // Rendered sample games
+    pub fn games(&self) -> PathBuf {
+        self.dir.join("games")
+    }
+
+    pub fn logs(&self) -> PathBuf {
+        self.dir.join("logs")
+    }
+
+    pub fn metrics(&self) -> PathBuf {
+        self.dir.join("metrics")
+    }
+
+    // Games of the replay buffer, see `ReplayJournal`
+    pub fn replay_journal(&self) -> PathBuf {
+        self.dir.join("replay")
+    }
+
+    // Snapshot of the experiment's `Config`, as TOML
+    pub fn experiment(&self) -> PathBuf {
+        self.dir.join("experiment.toml")
+    }
+
+    // History of the Elo ratings of the checkpoints
+    pub fn ratings(&self) -> PathBuf {
+        self.dir.join("ratings.json")
+    }
+
+    // Shards of the data store
+    pub fn selfplay(&self) -> PathBuf {
+        self.dir.join("selfplay")
+    }
+
+    pub fn game_image(&self, generation: usize, index: usize) -> PathBuf {
+        self.games().join(format!("{generation:02}.{index:02}.png"))
+    }
+
+    pub fn game_animation(&self, generation: usize, index: usize) -> PathBuf {
+        self.games().join(format!("{generation:02}.{index:02}.gif"))
+    }
+
+    // Of the generation's sample games, see `plot_value_trajectories`
+    pub fn value_plot(&self, generation: usize) -> PathBuf {
+        self.games().join(format!("{generation:02}.values.png"))
+    }
+
+    // Snapshot of the settings the run was started with. A resumed run keeps the snapshots
+    // of earlier starts, numbered.
+    pub fn save_config(&self, config: &impl Serialize) -> anyhow::Result<PathBuf> {
+        let json = serde_json::to_string_pretty(config)?;
+        let mut path = self.dir.join("config.json");
+        for attempt in 2.. {
+            if !path.exists() {
+                break;
+            }
+            path = self.dir.join(format!("config.{attempt}.json"));
+        }
+        fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+// UTC, as `YYYYmmdd-HHMMSS`
+fn timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+    // Days to civil date, from Howard Hinnant's `civil_from_days`
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{timestamp, RunContext};
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(timestamp(UNIX_EPOCH), "19700101-000000");
+        assert_eq!(
+            timestamp(UNIX_EPOCH + Duration::from_secs(951_827_696)),
+            "20000229-123456"
+        );
+    }
+
+    #[test]
+    fn runs_get_separate_directories() {
+        let root = std::env::temp_dir().join(format!("runs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let first = RunContext::create(&root, "gomoku").unwrap();
+        let second = RunContext::create(&root, "gomoku").unwrap();
+        assert_ne!(first.dir(), second.dir());
+        assert!(first.checkpoints().is_dir() && second.games().is_dir());
+
+        let reopened = RunContext::open(first.dir()).unwrap();
+        assert_eq!(
+            reopened.save_config(&[1, 2]).unwrap(),
+            first.dir().join("config.json")
+        );
+        assert_eq!(
+            reopened.save_config(&[3]).unwrap(),
+            first.dir().join("config.2.json")
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
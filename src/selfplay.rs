@@ -0,0 +1,17 @@
+#[cfg(feature = "torch")]
+mod data_store;
+#[cfg(feature = "torch")]
+mod remote;
+#[cfg(feature = "torch")]
+mod replay_journal;
+#[cfg(feature = "arrow")]
+mod sample_export;
+
+#[cfg(feature = "torch")]
+pub use data_store::*;
+#[cfg(feature = "torch")]
+pub use remote::*;
+#[cfg(feature = "torch")]
+pub use replay_journal::*;
+#[cfg(feature = "arrow")]
+pub use sample_export::*;
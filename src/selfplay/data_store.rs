@@ -0,0 +1,245 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use tch::{Device, Kind, Tensor};
+
+use crate::alpha_zero::{AlphaZeroAdapter, AlphaZeroNet, Game, SelfPlaySample};
+
+// Positions of one or more games, already encoded for the network
+pub struct StoredPositions {
+    pub states: Tensor,
+    pub policies: Tensor,
+    // Final outcome for the player to move
+    pub values: Tensor,
+    // Search's value estimate of the position
+    pub root_q: Tensor,
+    pub move_numbers: Tensor,
+    // Number of positions of every game, in order
+    pub game_lengths: Tensor,
+}
+
+impl StoredPositions {
+    pub fn len(&self) -> usize {
+        self.values.size()[0] as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn games(&self) -> usize {
+        self.game_lengths.size()[0] as usize
+    }
+
+    fn encode<TGame, TNet, TAdapter>(game: &[SelfPlaySample<TGame>]) -> Self
+    where
+        TGame: Game,
+        TNet: AlphaZeroNet,
+        TAdapter: AlphaZeroAdapter<TGame, TNet>,
+    {
+        let cpu = (Kind::Float, Device::Cpu);
+        let moves = game
+            .iter()
+            .map(|sample| sample.state.get_state().get_moves().unwrap())
+            .collect::<Vec<_>>();
+        let states = game
+            .iter()
+            .map(|sample| TAdapter::convert_game_to_nn_input(&sample.state))
+            .collect::<Vec<_>>();
+        let policies = game
+            .iter()
+            .map(|sample| sample.policy.clone())
+            .collect::<Vec<_>>();
+        let column = |f: fn(&SelfPlaySample<TGame>) -> f32| {
+            Tensor::from_slice(&game.iter().map(f).collect::<Vec<_>>())
+        };
+
+        Self {
+            states: Tensor::stack(&states, 0).to_kind(Kind::Float),
+            policies: TAdapter::convert_policies_to_nn(&policies, &moves, cpu),
+            values: column(|sample| sample.value),
+            root_q: column(|sample| sample.root_q),
+            move_numbers: Tensor::from_slice(
+                &game
+                    .iter()
+                    .map(|sample| sample.move_number as i64)
+                    .collect::<Vec<_>>(),
+            ),
+            game_lengths: Tensor::from_slice(&[game.len() as i64]),
+        }
+    }
+
+    fn concat(parts: &[StoredPositions]) -> Self {
+        let field = |f: fn(&StoredPositions) -> &Tensor| {
+            Tensor::concat(&parts.iter().map(f).collect::<Vec<_>>(), 0)
+        };
+        Self {
+            states: field(|p| &p.states),
+            policies: field(|p| &p.policies),
+            values: field(|p| &p.values),
+            root_q: field(|p| &p.root_q),
+            move_numbers: field(|p| &p.move_numbers),
+            game_lengths: field(|p| &p.game_lengths),
+        }
+    }
+
+    fn fields(&self) -> [(&'static str, &Tensor); 6] {
+        [
+            ("states", &self.states),
+            ("policies", &self.policies),
+            ("values", &self.values),
+            ("root_q", &self.root_q),
+            ("move_numbers", &self.move_numbers),
+            ("game_lengths", &self.game_lengths),
+        ]
+    }
+}
+
+// Self-play games on disk, in safetensors shards of `games_per_shard` games. Lets
+// generation and training run as separate processes and survive restarts.
+pub struct DataStore {
+    dir: PathBuf,
+    games_per_shard: usize,
+    pending: Vec<StoredPositions>,
+    next_shard: usize,
+}
+
+impl DataStore {
+    // Continues after the shards already in `dir`
+    pub fn open(dir: impl AsRef<Path>, games_per_shard: usize) -> anyhow::Result<Self> {
+        assert!(games_per_shard > 0);
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create data store at {}", dir.display()))?;
+        let mut store = Self {
+            dir,
+            games_per_shard,
+            pending: vec![],
+            next_shard: 0,
+        };
+        while store.shard_path(store.next_shard).exists() {
+            store.next_shard += 1;
+        }
+        Ok(store)
+    }
+
+    fn shard_path(&self, shard: usize) -> PathBuf {
+        self.dir.join(format!("shard-{shard:06}.safetensors"))
+    }
+
+    // Shards written so far, not counting the pending games
+    pub fn shards(&self) -> usize {
+        self.next_shard
+    }
+
+    pub fn write_game<TGame, TNet, TAdapter>(
+        &mut self,
+        game: &[SelfPlaySample<TGame>],
+    ) -> anyhow::Result<()>
+    where
+        TGame: Game,
+        TNet: AlphaZeroNet,
+        TAdapter: AlphaZeroAdapter<TGame, TNet>,
+    {
+        if game.is_empty() {
+            return Ok(());
+        }
+        self.pending
+            .push(StoredPositions::encode::<TGame, TNet, TAdapter>(game));
+        if self.pending.len() >= self.games_per_shard {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    // Writes the pending games as a shard of their own, even if it isn't full
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let shard = StoredPositions::concat(&std::mem::take(&mut self.pending));
+        let path = self.shard_path(self.next_shard);
+        Tensor::write_safetensors(&shard.fields(), &path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        self.next_shard += 1;
+        Ok(())
+    }
+
+    pub fn load_shard(&self, shard: usize) -> anyhow::Result<StoredPositions> {
+        let path = self.shard_path(shard);
+        let mut tensors = Tensor::read_safetensors(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut take = |name: &str| {
+            let i = tensors
+                .iter()
+                .position(|(n, _)| n == name)
+                .with_context(|| format!("{} has no {name}", path.display()))?;
+            anyhow::Ok(tensors.swap_remove(i).1)
+        };
+        Ok(StoredPositions {
+            states: take("states")?,
+            policies: take("policies")?,
+            values: take("values")?,
+            root_q: take("root_q")?,
+            move_numbers: take("move_numbers")?,
+            game_lengths: take("game_lengths")?,
+        })
+    }
+
+    // The last `shards` shards concatenated, `None` if nothing was written yet
+    pub fn load_recent(&self, shards: usize) -> anyhow::Result<Option<StoredPositions>> {
+        let parts = (self.next_shard.saturating_sub(shards)..self.next_shard)
+            .map(|shard| self.load_shard(shard))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok((!parts.is_empty()).then(|| StoredPositions::concat(&parts)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{uniform_game, SelfPlaySample},
+        tictactoe3::{TicTacToe3, TicTacToe3AlphaZeroAdapter, TicTacToe3Move, TicTacToe3Net},
+    };
+
+    use super::DataStore;
+
+    fn game(moves: &[usize]) -> Vec<SelfPlaySample<TicTacToe3>> {
+        let moves = moves.iter().map(|&m| TicTacToe3Move(m)).collect::<Vec<_>>();
+        uniform_game(TicTacToe3::new(), &moves)
+    }
+
+    #[test]
+    fn shards_survive_reopening() {
+        let dir = std::env::temp_dir().join(format!("data-store-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut store = DataStore::open(&dir, 2).unwrap();
+        for moves in [&[4, 0, 8][..], &[0, 1], &[2, 4, 6, 8]] {
+            store
+                .write_game::<_, TicTacToe3Net, TicTacToe3AlphaZeroAdapter>(&game(moves))
+                .unwrap();
+        }
+        assert_eq!(store.shards(), 1);
+        store.flush().unwrap();
+
+        let store = DataStore::open(&dir, 2).unwrap();
+        assert_eq!(store.shards(), 2);
+        assert_eq!(store.load_shard(0).unwrap().games(), 2);
+
+        let recent = store.load_recent(5).unwrap().unwrap();
+        assert_eq!(recent.games(), 3);
+        assert_eq!(recent.len(), 9);
+        assert_eq!(recent.policies.size()[0], 9);
+        assert_eq!(
+            Vec::<i64>::try_from(&recent.game_lengths).unwrap(),
+            [3, 2, 4]
+        );
+        assert_eq!(store.load_recent(1).unwrap().unwrap().len(), 4);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
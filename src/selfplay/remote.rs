@@ -0,0 +1,422 @@
+use std::{io::Cursor, net::SocketAddr, sync::Arc};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tch::nn;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpListener, TcpStream, ToSocketAddrs,
+    },
+    sync::{mpsc, watch},
+};
+use tracing::{info, warn};
+
+use crate::alpha_zero::{Game, OpeningBook, Perspective, SelfPlaySample};
+
+// Self-play game together with the opening it started from
+pub struct PlayedGame<TGame> {
+    pub opening: Option<usize>,
+    pub samples: Vec<SelfPlaySample<TGame>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MoveRecord {
+    pub played: usize,
+    pub policy: Vec<f32>,
+    pub value: f32,
+    pub root_q: f32,
+    pub simulations: usize,
+}
+
+// Of a policy's sum, for the rounding of the worker's normalization
+const POLICY_SUM_TOLERANCE: f32 = 1e-3;
+
+impl MoveRecord {
+    // Targets the learner would train on, so that a broken worker can't poison the weights
+    fn check(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.policy.iter().all(|p| p.is_finite() && *p >= 0.0),
+            "The policy has negative or non-finite probabilities"
+        );
+        let sum = self.policy.iter().sum::<f32>();
+        anyhow::ensure!(
+            (sum - 1.0).abs() <= POLICY_SUM_TOLERANCE,
+            "The policy sums to {sum}"
+        );
+        for (name, value) in [("value", self.value), ("root_q", self.root_q)] {
+            anyhow::ensure!(
+                (0.0..=1.0).contains(&value),
+                "The {name} {value} isn't in [0, 1]"
+            );
+        }
+        Ok(())
+    }
+}
+
+// Wire format of a game. States aren't sent, the learner replays the moves from the
+// start of the game instead.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub opening: Option<usize>,
+    pub moves: Vec<MoveRecord>,
+}
+
+impl GameRecord {
+    pub fn new<TGame>(game: &PlayedGame<TGame>) -> Self {
+        Self {
+            opening: game.opening,
+            moves: game
+                .samples
+                .iter()
+                .map(|sample| MoveRecord {
+                    played: sample.played,
+                    policy: sample.policy.clone(),
+                    value: sample.value,
+                    root_q: sample.root_q,
+                    simulations: sample.simulations,
+                })
+                .collect(),
+        }
+    }
+
+    // Records come from other machines, so every move is checked
+    pub fn replay<TGame: Game + Clone>(
+        &self,
+        start: &TGame,
+        openings: Option<&OpeningBook<TGame>>,
+    ) -> anyhow::Result<PlayedGame<TGame>> {
+        let mut state = match (self.opening, openings) {
+            (None, _) => start.clone(),
+            (Some(index), Some(book)) if index < book.len() => book.play(index, start),
+            (Some(index), _) => anyhow::bail!("Unknown opening {index}"),
+        };
+        let mut player = Perspective::Same;
+        let mut samples = Vec::with_capacity(self.moves.len());
+        for (move_number, record) in self.moves.iter().enumerate() {
+            let moves = state
+                .get_state()
+                .get_moves()
+                .with_context(|| format!("Game is over before move {move_number}"))?;
+            anyhow::ensure!(
+                record.policy.len() == moves.len(),
+                "Policy of move {move_number} doesn't match the {} legal moves",
+                moves.len()
+            );
+            record
+                .check()
+                .with_context(|| format!("Invalid targets of move {move_number}"))?;
+            let m = moves
+                .get(record.played)
+                .with_context(|| format!("Move {move_number} is illegal"))?;
+            let next = state.make_move(m);
+            let perspective = Perspective::after_move(m);
+            samples.push(SelfPlaySample {
+                state,
+                policy: record.policy.clone(),
+                value: record.value,
+                ownership: None,
+                move_number,
+                player,
+                root_q: record.root_q,
+                simulations: record.simulations,
+                played: record.played,
+                generation: 0,
+            });
+            state = next;
+            player = player.then(perspective);
+        }
+        anyhow::ensure!(
+            state.get_state().get_terminal().is_some(),
+            "Game isn't finished"
+        );
+        Ok(PlayedGame {
+            opening: self.opening,
+            samples,
+        })
+    }
+}
+
+// Frames are a tag byte followed by the length of the payload
+const WEIGHTS_FRAME: u8 = 0;
+const GAME_FRAME: u8 = 1;
+// Well above the weights of any network here
+const MAX_WEIGHTS_LEN: u64 = 1 << 32;
+// Games come from workers, which aren't trusted with the learner's memory. The longest
+// game of gomoku on the 19×19 board is under 2 MB of JSON.
+const MAX_GAME_LEN: u64 = 8 << 20;
+
+async fn write_frame(
+    writer: &mut (impl AsyncWrite + Unpin),
+    tag: u8,
+    payload: &[u8],
+) -> std::io::Result<()> {
+    writer.write_u8(tag).await?;
+    writer.write_u64(payload.len() as u64).await?;
+    writer.write_all(payload).await?;
+    writer.flush().await
+}
+
+// `None` once the other side closed the connection. Longer payloads than `max_len` are
+// refused before anything is allocated for them.
+async fn read_frame(
+    reader: &mut (impl AsyncRead + Unpin),
+    max_len: u64,
+) -> anyhow::Result<Option<(u8, Vec<u8>)>> {
+    let tag = match reader.read_u8().await {
+        Ok(tag) => tag,
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let len = reader.read_u64().await?;
+    anyhow::ensure!(len <= max_len, "Frame of {len} bytes is too long");
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload).await?;
+    Ok(Some((tag, payload)))
+}
+
+pub fn serialize_weights(vs: &nn::VarStore) -> anyhow::Result<Vec<u8>> {
+    let mut weights = vec![];
+    vs.save_to_stream(&mut weights)?;
+    Ok(weights)
+}
+
+pub fn deserialize_weights(vs: &mut nn::VarStore, weights: &[u8]) -> anyhow::Result<()> {
+    Ok(vs.load_from_stream(Cursor::new(weights))?)
+}
+
+// Learner side: accepts workers, sends them the latest published weights and feeds the
+// games they play into `games`, next to the ones of the local actors
+pub struct GameServer {
+    weights: watch::Sender<Arc<Vec<u8>>>,
+    addr: SocketAddr,
+}
+
+impl GameServer {
+    pub async fn bind<TGame>(
+        addr: impl ToSocketAddrs,
+        start: TGame,
+        openings: Option<OpeningBook<TGame>>,
+        weights: &nn::VarStore,
+        games: mpsc::UnboundedSender<PlayedGame<TGame>>,
+    ) -> anyhow::Result<Self>
+    where
+        TGame: Game + Clone + Send + Sync + 'static,
+        TGame::Move: Send + Sync,
+    {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let (weights_tx, weights_rx) = watch::channel(Arc::new(serialize_weights(weights)?));
+        tokio::spawn(async move {
+            loop {
+                let (stream, worker) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        warn!("Failed to accept a worker: {err}");
+                        continue;
+                    }
+                };
+                info!(%worker, "Worker connected");
+                let (reader, writer) = stream.into_split();
+                let sender = tokio::spawn(send_weights(writer, weights_rx.clone()));
+                let (start, openings, games) = (start.clone(), openings.clone(), games.clone());
+                tokio::spawn(async move {
+                    match receive_games(reader, start, openings, games).await {
+                        Ok(()) => info!(%worker, "Worker disconnected"),
+                        Err(err) => warn!(%worker, "Dropping the worker: {err:#}"),
+                    }
+                    sender.abort();
+                });
+            }
+        });
+        Ok(Self {
+            weights: weights_tx,
+            addr,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn publish(&self, weights: &nn::VarStore) -> anyhow::Result<()> {
+        self.weights
+            .send_replace(Arc::new(serialize_weights(weights)?));
+        Ok(())
+    }
+}
+
+async fn send_weights(mut writer: OwnedWriteHalf, mut weights: watch::Receiver<Arc<Vec<u8>>>) {
+    loop {
+        let latest = weights.borrow_and_update().clone();
+        if write_frame(&mut writer, WEIGHTS_FRAME, &latest)
+            .await
+            .is_err()
+            || weights.changed().await.is_err()
+        {
+            return;
+        }
+    }
+}
+
+async fn receive_games<TGame: Game + Clone>(
+    mut reader: OwnedReadHalf,
+    start: TGame,
+    openings: Option<OpeningBook<TGame>>,
+    games: mpsc::UnboundedSender<PlayedGame<TGame>>,
+) -> anyhow::Result<()> {
+    while let Some((tag, payload)) = read_frame(&mut reader, MAX_GAME_LEN).await? {
+        anyhow::ensure!(tag == GAME_FRAME, "Unexpected frame {tag}");
+        let record: GameRecord = serde_json::from_slice(&payload)?;
+        if games
+            .send(record.replay(&start, openings.as_ref())?)
+            .is_err()
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Worker side of the connection
+pub struct LearnerConnection {
+    reader: OwnedReadHalf,
+    writer: OwnedWriteHalf,
+}
+
+impl LearnerConnection {
+    pub async fn connect(addr: impl ToSocketAddrs) -> anyhow::Result<Self> {
+        let (reader, writer) = TcpStream::connect(addr).await?.into_split();
+        Ok(Self { reader, writer })
+    }
+
+    // Serialized weights, as they get published. Closes with the connection.
+    pub fn split(self) -> (mpsc::Receiver<Vec<u8>>, GameSender) {
+        let (tx, rx) = mpsc::channel(1);
+        let mut reader = self.reader;
+        tokio::spawn(async move {
+            loop {
+                match read_frame(&mut reader, MAX_WEIGHTS_LEN).await {
+                    Ok(Some((WEIGHTS_FRAME, weights))) => {
+                        if tx.send(weights).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(Some((tag, _))) => {
+                        warn!("Unexpected frame {tag} from the learner");
+                        break;
+                    }
+                    Ok(None) => break,
+                    Err(err) => {
+                        warn!("Lost the learner: {err:#}");
+                        break;
+                    }
+                }
+            }
+        });
+        (rx, GameSender(self.writer))
+    }
+}
+
+pub struct GameSender(OwnedWriteHalf);
+
+impl GameSender {
+    pub async fn send<TGame>(&mut self, game: &PlayedGame<TGame>) -> anyhow::Result<()> {
+        let payload = serde_json::to_vec(&GameRecord::new(game))?;
+        Ok(write_frame(&mut self.0, GAME_FRAME, &payload).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{uniform_game, Game, OpeningBook, SelfPlaySample},
+        tictactoe3::{TicTacToe3, TicTacToe3Move},
+    };
+
+    use super::{read_frame, write_frame, GameRecord, MoveRecord, PlayedGame};
+
+    #[test]
+    fn records_replay_to_the_same_game() {
+        let book = OpeningBook::new(vec![
+            (vec![TicTacToe3Move(4)], 1.0),
+            (vec![TicTacToe3Move(0)], 1.0),
+        ]);
+        let moves = [4, 8, 1, 7, 6, 2, 5, 3].map(TicTacToe3Move);
+        let start = book.play(1, &TicTacToe3::new());
+        let end = moves.iter().fold(start, |state, m| state.make_move(m));
+        assert_eq!(end.get_state().get_terminal(), Some(0.5));
+        // Searches of every move their own, unlike the fixture's
+        let samples = uniform_game(start, &moves)
+            .into_iter()
+            .map(|sample| SelfPlaySample {
+                root_q: 0.25,
+                simulations: 32 + sample.move_number,
+                ..sample
+            })
+            .collect::<Vec<_>>();
+
+        let record = GameRecord::new(&PlayedGame {
+            opening: Some(1),
+            samples: samples.clone(),
+        });
+        let json = serde_json::to_vec(&record).unwrap();
+        let replayed = serde_json::from_slice::<GameRecord>(&json)
+            .unwrap()
+            .replay(&TicTacToe3::new(), Some(&book))
+            .unwrap();
+        assert_eq!(replayed.opening, Some(1));
+        for (a, b) in replayed.samples.iter().zip(&samples) {
+            assert_eq!(a.state, b.state);
+            assert_eq!(a.policy, b.policy);
+            assert_eq!(a.value, b.value);
+            assert_eq!(a.root_q, b.root_q);
+            assert_eq!(a.simulations, b.simulations);
+            assert_eq!(a.move_number, b.move_number);
+            assert_eq!(a.player, b.player);
+            assert_eq!(a.played, b.played);
+        }
+        assert_eq!(replayed.samples.len(), samples.len());
+
+        let mut truncated = record.clone();
+        truncated.moves.pop();
+        assert!(truncated.replay(&TicTacToe3::new(), Some(&book)).is_err());
+        assert!(record.replay(&TicTacToe3::new(), None).is_err());
+
+        // Targets that would poison the weights
+        for corrupt in [
+            |m: &mut MoveRecord| m.policy[0] = f32::NAN,
+            |m: &mut MoveRecord| m.policy[0] = -m.policy[0],
+            |m: &mut MoveRecord| m.policy[0] += 0.5,
+            |m: &mut MoveRecord| m.value = 1.5,
+            |m: &mut MoveRecord| m.root_q = f32::INFINITY,
+        ] {
+            let mut corrupted = record.clone();
+            corrupt(&mut corrupted.moves[3]);
+            let Err(error) = corrupted.replay(&TicTacToe3::new(), Some(&book)) else {
+                panic!("Replayed a corrupted record");
+            };
+            assert!(format!("{error:#}").contains("move 3"), "{error:#}");
+        }
+    }
+
+    #[tokio::test]
+    async fn frames_round_trip() {
+        let (mut a, mut b) = tokio::io::duplex(64);
+        let payload = (0..=255).collect::<Vec<u8>>();
+        let writer = tokio::spawn(async move {
+            write_frame(&mut a, 1, &payload).await.unwrap();
+            write_frame(&mut a, 0, &[]).await.unwrap();
+            write_frame(&mut a, 1, &[0; 17]).await.unwrap();
+        });
+        assert_eq!(
+            read_frame(&mut b, 256).await.unwrap(),
+            Some((1, (0..=255).collect()))
+        );
+        assert_eq!(read_frame(&mut b, 256).await.unwrap(), Some((0, vec![])));
+        // Refused from its header
+        assert!(read_frame(&mut b, 16).await.is_err());
+        writer.await.unwrap();
+    }
+}
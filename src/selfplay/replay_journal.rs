@@ -0,0 +1,216 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use super::{GameRecord, PlayedGame};
+use crate::alpha_zero::{Game, OpeningBook};
+
+// Games of the replay buffer on disk, as JSON lines of their records in a shard per
+// generation, for a run to refill its buffer when it resumes after a crash or a signal. Unlike
+// the data store's encoded positions, records can be replayed into samples.
+pub struct ReplayJournal {
+    dir: PathBuf,
+    // Generation of the shard being appended to
+    writer: Option<(usize, BufWriter<File>)>,
+}
+
+impl ReplayJournal {
+    pub fn open(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        Ok(Self { dir, writer: None })
+    }
+
+    fn shard_path(&self, generation: usize) -> PathBuf {
+        self.dir.join(format!("{generation:04}.jsonl"))
+    }
+
+    // With a shard, in order
+    fn generations(&self) -> anyhow::Result<Vec<usize>> {
+        let entries = fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to list {}", self.dir.display()))?;
+        let mut generations = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "jsonl")
+            {
+                if let Some(generation) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str()?.parse().ok())
+                {
+                    generations.push(generation);
+                }
+            }
+        }
+        generations.sort_unstable();
+        Ok(generations)
+    }
+
+    // Of a game added to the buffer, to the shard of its samples' generation
+    pub fn write<TGame>(&mut self, game: &PlayedGame<TGame>) -> anyhow::Result<()> {
+        let Some(generation) = game.samples.first().map(|sample| sample.generation) else {
+            return Ok(());
+        };
+        if self.writer.as_ref().map(|(current, _)| *current) != Some(generation) {
+            self.flush()?;
+            let path = self.shard_path(generation);
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            self.writer = Some((generation, BufWriter::new(file)));
+        }
+        let (_, writer) = self.writer.as_mut().unwrap();
+        serde_json::to_writer(&mut *writer, &GameRecord::new(game))?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        let Some((generation, writer)) = &mut self.writer else {
+            return Ok(());
+        };
+        writer.flush().with_context(|| {
+            format!(
+                "Failed to write shard {generation} of {}",
+                self.dir.display()
+            )
+        })
+    }
+
+    // The games of the latest shards holding at least `positions` positions, oldest first.
+    // Removes the shards before them, which a buffer of that size doesn't hold anymore.
+    pub fn load<TGame: Game + Clone>(
+        &self,
+        positions: usize,
+        start: &TGame,
+        openings: Option<&OpeningBook<TGame>>,
+    ) -> anyhow::Result<Vec<PlayedGame<TGame>>> {
+        let generations = self.generations()?;
+        let mut shards = vec![];
+        let mut loaded = 0;
+        let mut first_kept = generations.len();
+        for (i, &generation) in generations.iter().enumerate().rev() {
+            if loaded >= positions {
+                break;
+            }
+            let games = self.load_shard(generation, start, openings)?;
+            loaded += games.iter().map(|game| game.samples.len()).sum::<usize>();
+            shards.push(games);
+            first_kept = i;
+        }
+        for &generation in &generations[..first_kept] {
+            let path = self.shard_path(generation);
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        Ok(shards.into_iter().rev().flatten().collect())
+    }
+
+    fn load_shard<TGame: Game + Clone>(
+        &self,
+        generation: usize,
+        start: &TGame,
+        openings: Option<&OpeningBook<TGame>>,
+    ) -> anyhow::Result<Vec<PlayedGame<TGame>>> {
+        let path = self.shard_path(generation);
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        // A crash can cut the last line short
+        let complete = &text[..text.rfind('\n').map_or(0, |end| end + 1)];
+        complete
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                let replay = || -> anyhow::Result<PlayedGame<TGame>> {
+                    let record = serde_json::from_str::<GameRecord>(line)?;
+                    let mut played = record.replay(start, openings)?;
+                    for sample in &mut played.samples {
+                        sample.generation = generation;
+                    }
+                    Ok(played)
+                };
+                replay().with_context(|| format!("Invalid game {} of {}", i + 1, path.display()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crate::{
+        alpha_zero::{uniform_game, OpeningBook, SelfPlaySample},
+        selfplay::PlayedGame,
+        tictactoe3::{TicTacToe3, TicTacToe3Move},
+    };
+
+    use super::ReplayJournal;
+
+    fn game(
+        start: TicTacToe3,
+        opening: Option<usize>,
+        cells: &[usize],
+        generation: usize,
+    ) -> PlayedGame<TicTacToe3> {
+        let moves = cells.iter().map(|&m| TicTacToe3Move(m)).collect::<Vec<_>>();
+        let samples = uniform_game(start, &moves)
+            .into_iter()
+            .map(|sample| SelfPlaySample {
+                generation,
+                ..sample
+            })
+            .collect();
+        PlayedGame { opening, samples }
+    }
+
+    #[test]
+    fn latest_games_survive_a_crash() {
+        let book = OpeningBook::new(vec![(vec![TicTacToe3Move(0)], 1.0)]);
+        let start = TicTacToe3::new();
+        let games = [
+            game(start, None, &[0, 3, 1, 4, 2], 0),
+            game(book.play(0, &start), Some(0), &[4, 8, 1, 7, 6, 2, 5, 3], 1),
+            game(start, None, &[0, 1, 3, 2, 6], 2),
+        ];
+        let dir = std::env::temp_dir().join(format!("journal-{}", std::process::id()));
+        let mut journal = ReplayJournal::open(&dir).unwrap();
+        for game in &games {
+            journal.write(game).unwrap();
+        }
+        journal.flush().unwrap();
+        // Cut short while writing a game
+        let mut last = std::fs::OpenOptions::new()
+            .append(true)
+            .open(dir.join("0002.jsonl"))
+            .unwrap();
+        write!(last, "{{\"opening\":null,\"mo").unwrap();
+
+        // The last two generations fill a buffer of 10 positions, the first one goes
+        let loaded = ReplayJournal::open(&dir)
+            .unwrap()
+            .load(10, &start, Some(&book))
+            .unwrap();
+        assert!(!dir.join("0000.jsonl").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded.len(), 2);
+        for (played, game) in loaded.iter().zip(&games[1..]) {
+            assert_eq!(played.opening, game.opening);
+            assert_eq!(played.samples.len(), game.samples.len());
+            for (a, b) in played.samples.iter().zip(&game.samples) {
+                assert_eq!(
+                    (a.state, a.played, a.generation),
+                    (b.state, b.played, b.generation)
+                );
+            }
+        }
+    }
+}
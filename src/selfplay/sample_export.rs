@@ -0,0 +1,253 @@
+use std::{collections::HashMap, fs::File, path::Path, sync::Arc};
+
+use anyhow::Context;
+use arrow::{
+    array::{ArrayRef, FixedSizeListArray, Float32Array, Int64Array, UInt64Array},
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    ipc::writer::FileWriter,
+    record_batch::RecordBatch,
+};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use tch::{Kind, Tensor};
+
+use super::{DataStore, StoredPositions};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleFormat {
+    Parquet,
+    // Arrow's IPC file, also known as Feather
+    Arrow,
+}
+
+enum SampleWriter {
+    Parquet(ArrowWriter<File>),
+    Arrow(FileWriter<File>),
+}
+
+impl SampleWriter {
+    fn create(path: &Path, schema: &SchemaRef, format: SampleFormat) -> anyhow::Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(match format {
+            SampleFormat::Parquet => {
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                Self::Parquet(ArrowWriter::try_new(
+                    file,
+                    schema.clone(),
+                    Some(properties),
+                )?)
+            }
+            SampleFormat::Arrow => Self::Arrow(FileWriter::try_new(file, schema)?),
+        })
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> anyhow::Result<()> {
+        match self {
+            Self::Parquet(writer) => writer.write(batch)?,
+            Self::Arrow(writer) => writer.write(batch)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        match self {
+            Self::Parquet(writer) => {
+                writer.close()?;
+            }
+            Self::Arrow(mut writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
+
+// Past the positions' dimension
+fn item_shape(tensor: &Tensor) -> Vec<i64> {
+    tensor.size()[1..].to_vec()
+}
+
+fn floats(tensor: &Tensor) -> Vec<f32> {
+    Vec::<f32>::try_from(tensor.flatten(0, -1).to_kind(Kind::Float)).unwrap()
+}
+
+// Lists of `size` floats, a row per position
+fn flat_rows(tensor: &Tensor, size: i64) -> anyhow::Result<ArrayRef> {
+    let item = Arc::new(Field::new("item", DataType::Float32, false));
+    let values = Arc::new(Float32Array::from(floats(tensor)));
+    Ok(Arc::new(FixedSizeListArray::try_new(
+        item,
+        size as i32,
+        values,
+        None,
+    )?))
+}
+
+fn schema(states: &[i64], policies: &[i64]) -> SchemaRef {
+    let list = |shape: &[i64]| {
+        let item = Arc::new(Field::new("item", DataType::Float32, false));
+        DataType::FixedSizeList(item, shape.iter().product::<i64>() as i32)
+    };
+    let metadata = HashMap::from([
+        ("state_shape".to_owned(), format!("{states:?}")),
+        ("policy_shape".to_owned(), format!("{policies:?}")),
+    ]);
+    Arc::new(
+        Schema::new(vec![
+            Field::new("shard", DataType::UInt64, false),
+            Field::new("game", DataType::UInt64, false),
+            Field::new("move_number", DataType::Int64, false),
+            Field::new("value", DataType::Float32, false),
+            Field::new("root_q", DataType::Float32, false),
+            Field::new("state", list(states), false),
+            Field::new("policy", list(policies), false),
+        ])
+        .with_metadata(metadata),
+    )
+}
+
+// The positions of a shard, its games numbered from `first_game`
+fn shard_batch(
+    schema: &SchemaRef,
+    shard: usize,
+    first_game: usize,
+    positions: &StoredPositions,
+) -> anyhow::Result<RecordBatch> {
+    let lengths = Vec::<i64>::try_from(&positions.game_lengths)?;
+    let games = lengths
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &length)| std::iter::repeat((first_game + i) as u64).take(length as usize))
+        .collect::<Vec<_>>();
+    let size = |tensor: &Tensor| item_shape(tensor).iter().product::<i64>();
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from(vec![shard as u64; positions.len()])),
+        Arc::new(UInt64Array::from(games)),
+        Arc::new(Int64Array::from(Vec::<i64>::try_from(
+            &positions.move_numbers,
+        )?)),
+        Arc::new(Float32Array::from(floats(&positions.values))),
+        Arc::new(Float32Array::from(floats(&positions.root_q))),
+        flat_rows(&positions.states, size(&positions.states))?,
+        flat_rows(&positions.policies, size(&positions.policies))?,
+    ];
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+// Writes the positions of all of the store's shards to `path`, a row per position with the
+// net's input planes and the policy target flattened, their shapes being in the schema's
+// metadata. Values are the games' outcomes for the player to move. A shard at a time, as a
+// batch of its own. The number of positions written.
+pub fn export_samples(
+    store: &DataStore,
+    path: &Path,
+    format: SampleFormat,
+) -> anyhow::Result<usize> {
+    anyhow::ensure!(store.shards() > 0, "The data store has no shards");
+    let mut writer = None;
+    let (mut games, mut rows) = (0, 0);
+    for shard in 0..store.shards() {
+        let positions = store.load_shard(shard)?;
+        let (writer, schema) = match &mut writer {
+            Some(writer) => writer,
+            None => {
+                let schema = schema(
+                    &item_shape(&positions.states),
+                    &item_shape(&positions.policies),
+                );
+                let created = SampleWriter::create(path, &schema, format)?;
+                writer.insert((created, schema))
+            }
+        };
+        writer.write(&shard_batch(schema, shard, games, &positions)?)?;
+        games += positions.games();
+        rows += positions.len();
+    }
+    let (writer, _) = writer.unwrap();
+    writer
+        .finish()
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use arrow::{
+        array::AsArray,
+        datatypes::{Float32Type, Int64Type, UInt64Type},
+    };
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use crate::{
+        alpha_zero::{uniform_game, SelfPlaySample},
+        selfplay::DataStore,
+        tictactoe3::{TicTacToe3, TicTacToe3AlphaZeroAdapter, TicTacToe3Move, TicTacToe3Net},
+    };
+
+    use super::{export_samples, SampleFormat};
+
+    // Every position won by the player to move, whoever that is
+    fn game(moves: &[usize]) -> Vec<SelfPlaySample<TicTacToe3>> {
+        let moves = moves.iter().map(|&m| TicTacToe3Move(m)).collect::<Vec<_>>();
+        uniform_game(TicTacToe3::new(), &moves)
+            .into_iter()
+            .map(|sample| SelfPlaySample {
+                value: 1.0,
+                ..sample
+            })
+            .collect()
+    }
+
+    #[test]
+    fn positions_as_rows() {
+        let dir = std::env::temp_dir().join(format!("sample-export-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut store = DataStore::open(&dir, 1).unwrap();
+        for moves in [&[4, 0][..], &[0]] {
+            store
+                .write_game::<_, TicTacToe3Net, TicTacToe3AlphaZeroAdapter>(&game(moves))
+                .unwrap();
+        }
+
+        let path = dir.join("samples.parquet");
+        assert_eq!(
+            export_samples(&store, &path, SampleFormat::Parquet).unwrap(),
+            3
+        );
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.schema().metadata()["state_shape"], "[2, 3, 3]");
+        let batches = reader
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let batch = arrow::compute::concat_batches(&batches[0].schema(), &batches).unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        let column = |name: &str| batch.column_by_name(name).unwrap().clone();
+        assert_eq!(
+            column("shard").as_primitive::<UInt64Type>().values(),
+            &[0, 0, 1]
+        );
+        assert_eq!(
+            column("game").as_primitive::<UInt64Type>().values(),
+            &[0, 0, 1]
+        );
+        let moves = column("move_number");
+        assert_eq!(moves.as_primitive::<Int64Type>().values(), &[0, 1, 0]);
+        let values = column("value");
+        assert_eq!(values.as_primitive::<Float32Type>().values(), &[1.0; 3]);
+        // The first move's stone
+        let states = column("state");
+        let planes = states.as_fixed_size_list().value(1);
+        let planes = planes.as_primitive::<Float32Type>().values();
+        assert_eq!((planes.len(), planes.iter().sum::<f32>()), (18, 1.0));
+        let policies = column("policy");
+        let policy = policies.as_fixed_size_list().value(0);
+        let policy = policy.as_primitive::<Float32Type>().values();
+        assert!((policy.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
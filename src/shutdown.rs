@@ -0,0 +1,53 @@
+use tokio::sync::watch;
+use tracing::warn;
+
+// Requested by the first SIGINT or SIGTERM, for training to stop where it can resume from
+// instead of dying mid-generation. A second signal exits right away.
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    // Takes over the signals from the default handlers, which exit on the spot
+    pub fn install() -> anyhow::Result<Self> {
+        let (tx, rx) = watch::channel(false);
+        #[cfg(unix)]
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::spawn(async move {
+            loop {
+                #[cfg(unix)]
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                #[cfg(not(unix))]
+                let _ = tokio::signal::ctrl_c().await;
+                if tx.send_replace(true) {
+                    warn!("Exiting without saving");
+                    std::process::exit(130);
+                }
+                warn!(
+                    "Stopping after the games in progress and saving the run, signal again to \
+                     exit right away"
+                );
+            }
+        });
+        Ok(Self(rx))
+    }
+
+    // Never requested
+    pub fn never() -> Self {
+        Self(watch::channel(false).1)
+    }
+
+    pub fn requested(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    pub async fn wait(&mut self) {
+        // Without a sender it can't be requested anymore
+        if self.0.wait_for(|&requested| requested).await.is_err() {
+            std::future::pending().await
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::{fmt::Write, path::PathBuf, str::FromStr};
+
+use anyhow::Context;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::alpha_zero::TrainStats;
+
+// What a sweep varies. The defaults are the ones of a regular training run.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Hyperparameters {
+    pub lr: f64,
+    pub c_puct: f32,
+    // MCTS simulations per self-play move
+    pub simulations: usize,
+    pub batch_size: usize,
+}
+
+impl Default for Hyperparameters {
+    fn default() -> Self {
+        Self {
+            lr: 1e-4,
+            c_puct: 1.0 / 32.0,
+            simulations: 32,
+            batch_size: 1024,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Param {
+    Lr,
+    CPuct,
+    Simulations,
+    BatchSize,
+}
+
+impl Param {
+    // Integer parameters are rounded
+    fn set(self, hyperparameters: &mut Hyperparameters, value: f64) {
+        match self {
+            Param::Lr => hyperparameters.lr = value,
+            Param::CPuct => hyperparameters.c_puct = value as f32,
+            Param::Simulations => hyperparameters.simulations = value.round().max(1.0) as usize,
+            Param::BatchSize => hyperparameters.batch_size = value.round().max(1.0) as usize,
+        }
+    }
+}
+
+impl FromStr for Param {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(match s {
+            "lr" => Param::Lr,
+            "c_puct" => Param::CPuct,
+            "simulations" => Param::Simulations,
+            "batch_size" => Param::BatchSize,
+            "dirichlet_alpha" => anyhow::bail!("Self-play doesn't add Dirichlet noise"),
+            _ => anyhow::bail!(
+                "Unknown hyperparameter {s}, expected lr, c_puct, simulations or batch_size"
+            ),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum Axis {
+    Values(Vec<f64>),
+    // Sampled log-uniformly, so only by random sweeps
+    LogUniform(f64, f64),
+}
+
+// Parsed from e.g. `lr=1e-4..1e-2 simulations=32,64`, `lo..hi` being a log-uniform range.
+// Hyperparameters that aren't mentioned keep their default.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SweepSpace {
+    axes: Vec<(Param, Axis)>,
+}
+
+impl SweepSpace {
+    // Every combination of the listed values
+    pub fn grid(&self) -> anyhow::Result<Vec<Hyperparameters>> {
+        let mut trials = vec![Hyperparameters::default()];
+        for (param, axis) in &self.axes {
+            let Axis::Values(values) = axis else {
+                anyhow::bail!("{param:?} is a range, which only a random sweep can sample");
+            };
+            trials = trials
+                .into_iter()
+                .flat_map(|trial| {
+                    values.iter().map(move |&value| {
+                        let mut trial = trial;
+                        param.set(&mut trial, value);
+                        trial
+                    })
+                })
+                .collect();
+        }
+        Ok(trials)
+    }
+
+    pub fn random(&self, trials: usize, rng: &mut impl Rng) -> Vec<Hyperparameters> {
+        (0..trials)
+            .map(|_| {
+                let mut trial = Hyperparameters::default();
+                for (param, axis) in &self.axes {
+                    let value = match axis {
+                        Axis::Values(values) => values[rng.gen_range(0..values.len())],
+                        Axis::LogUniform(lo, hi) => rng.gen_range(lo.ln()..=hi.ln()).exp(),
+                    };
+                    param.set(&mut trial, value);
+                }
+                trial
+            })
+            .collect()
+    }
+}
+
+impl FromStr for SweepSpace {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut axes = vec![];
+        for assignment in s.split([' ', ';']).filter(|part| !part.is_empty()) {
+            let (name, values) = assignment
+                .split_once('=')
+                .with_context(|| format!("Expected <name>=<values>, got {assignment}"))?;
+            let param = name.parse::<Param>()?;
+            let number = |s: &str| {
+                s.parse::<f64>()
+                    .with_context(|| format!("Invalid value {s} of {name}"))
+            };
+            let axis = match values.split_once("..") {
+                Some((lo, hi)) => {
+                    let (lo, hi) = (number(lo)?, number(hi)?);
+                    anyhow::ensure!(0.0 < lo && lo <= hi, "Invalid range {values} of {name}");
+                    Axis::LogUniform(lo, hi)
+                }
+                None => Axis::Values(values.split(',').map(number).collect::<Result<_, _>>()?),
+            };
+            anyhow::ensure!(
+                axes.iter().all(|(other, _)| *other != param),
+                "{name} is given twice"
+            );
+            axes.push((param, axis));
+        }
+        Ok(Self { axes })
+    }
+}
+
+pub struct TrialResult {
+    pub hyperparameters: Hyperparameters,
+    pub run: PathBuf,
+    // Of the last generation trained, `Err` if the trial failed
+    pub stats: Result<TrainStats, String>,
+    // Of the final weights in a round-robin between the trials, `None` if they didn't play
+    pub elo: Option<f64>,
+}
+
+impl TrialResult {
+    // Validation loss if there was a validation split, training loss otherwise. Each trial
+    // computes it on its own self-play data, so it doesn't compare trials.
+    pub fn loss(&self) -> Option<f64> {
+        let stats = self.stats.as_ref().ok()?;
+        Some(match stats.validation {
+            Some(validation) => validation.value_loss + validation.policy_loss,
+            None => stats.value_loss + stats.policy_loss,
+        })
+    }
+}
+
+// Markdown table of the trials, the highest rated first and the unrated last
+pub fn comparison_table(results: &[TrialResult]) -> String {
+    let mut order = (0..results.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        let elo = |i: usize| results[i].elo.unwrap_or(f64::NEG_INFINITY);
+        elo(b).total_cmp(&elo(a))
+    });
+
+    let mut table = String::from(
+        "| trial | lr | c_puct | simulations | batch size | elo | loss | value loss | policy kl | run |\n\
+         |---|---|---|---|---|---|---|---|---|---|\n",
+    );
+    for i in order {
+        let TrialResult {
+            hyperparameters: h,
+            run,
+            stats,
+            elo,
+        } = &results[i];
+        let _ = write!(
+            table,
+            "| {i} | {:e} | {} | {} | {} | ",
+            h.lr, h.c_puct, h.simulations, h.batch_size
+        );
+        let _ = match elo {
+            Some(elo) => write!(table, "{elo:.0} | "),
+            None => write!(table, " | "),
+        };
+        let _ = match (stats, results[i].loss()) {
+            (Ok(stats), Some(loss)) => {
+                let (value_loss, policy_kl) = match stats.validation {
+                    Some(validation) => (validation.value_loss, validation.policy_kl),
+                    None => (stats.value_loss, stats.policy_kl),
+                };
+                write!(table, "{loss:.4} | {value_loss:.4} | {policy_kl:.4}")
+            }
+            (Err(err), _) => write!(table, "failed: {err} | | "),
+            _ => write!(table, " | | "),
+        };
+        let _ = writeln!(table, " | {} |", run.display());
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::alpha_zero::TrainStats;
+
+    use super::{comparison_table, Hyperparameters, SweepSpace, TrialResult};
+
+    #[test]
+    fn grid_and_random_trials() {
+        let space = "lr=1e-3,1e-4 simulations=32,64;batch_size=256"
+            .parse::<SweepSpace>()
+            .unwrap();
+        let grid = space.grid().unwrap();
+        assert_eq!(grid.len(), 4);
+        assert_eq!(
+            grid[1],
+            Hyperparameters {
+                lr: 1e-3,
+                simulations: 64,
+                batch_size: 256,
+                ..Default::default()
+            }
+        );
+
+        let space = "c_puct=0.5..4 simulations=16,32"
+            .parse::<SweepSpace>()
+            .unwrap();
+        assert!(space.grid().is_err());
+        let trials = space.random(20, &mut StdRng::seed_from_u64(1));
+        assert!(trials
+            .iter()
+            .all(|t| (0.5..=4.0).contains(&t.c_puct) && [16, 32].contains(&t.simulations)));
+
+        assert!("dirichlet_alpha=0.3".parse::<SweepSpace>().is_err());
+        assert!("lr=1e-3 lr=1e-4".parse::<SweepSpace>().is_err());
+    }
+
+    #[test]
+    fn table_puts_the_highest_rated_first() {
+        let result = |lr, value_loss, elo| TrialResult {
+            hyperparameters: Hyperparameters {
+                lr,
+                ..Default::default()
+            },
+            run: PathBuf::from(format!("runs/{lr}")),
+            stats: Ok(TrainStats {
+                value_loss,
+                ..Default::default()
+            }),
+            elo: Some(elo),
+        };
+        let failed = TrialResult {
+            stats: Err("diverged".to_owned()),
+            elo: None,
+            ..result(1e-1, 0.0, 0.0)
+        };
+        // The loss doesn't rank the trials, it only says how well each fits its own data
+        let table = comparison_table(&[result(1e-3, 0.5, 80.0), failed, result(1e-4, 0.25, 0.0)]);
+        let rows = table.lines().skip(2).collect::<Vec<_>>();
+        assert!(rows[0].starts_with("| 0 | 1e-3 |") && rows[0].contains("| 80 | 0.5000 |"));
+        assert!(rows[1].starts_with("| 2 | 1e-4 |"));
+        assert!(rows[2].contains("|  | failed: diverged"));
+    }
+}
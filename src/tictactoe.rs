@@ -1,9 +1,17 @@
 mod alpha_zero_adapter;
 mod board;
+#[cfg(feature = "torch")]
 mod nn;
+mod notation;
+mod openings;
+mod tactics;
 mod visualize;
 
 pub use alpha_zero_adapter::*;
 pub use board::*;
+#[cfg(feature = "torch")]
 pub use nn::*;
+pub use notation::*;
+pub use openings::*;
+pub use tactics::*;
 pub use visualize::*;
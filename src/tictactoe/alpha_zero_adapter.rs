@@ -1,91 +1,286 @@
-use tch::Tensor;
+#[cfg(feature = "torch")]
+use tch::{Device, Kind, Tensor};
 
-use crate::alpha_zero::{AlphaZeroAdapter, Game};
+use crate::alpha_zero::{ActionEncoding, InputEncoding};
+#[cfg(feature = "torch")]
+use crate::alpha_zero::{AlphaZeroAdapter, AlphaZeroNet, SymmetryTransform};
 
-use super::{BoardState, CellState, TicTacToeMove, TicTacToeNet};
+use super::{GomokuBoard, TicTacToeMove, GOMOKU_HISTORY};
 
-pub struct TicTacToeAlphaZeroAdapter;
+// Encodes the stones of the player to move and of the other player as two planes. With
+// `HISTORY`, that many planes follow with a single cell set for each of the last moves, most
+// recent first, and with `SIDE_TO_MOVE` a plane of ones if the player to move made the game's
+// first move, so that the net can tell the tempo apart. Nets have to be built with
+// `INPUT_PLANES` planes, see `ResNetConfig` and `TicTacToeNet::with_input_planes`.
+pub struct TicTacToeAlphaZeroAdapter<const HISTORY: usize = 0, const SIDE_TO_MOVE: bool = false>;
 
-impl AlphaZeroAdapter<BoardState, TicTacToeNet> for TicTacToeAlphaZeroAdapter {
-    fn convert_game_to_nn_input(state: &BoardState) -> tch::Tensor {
-        // let start = Instant::now();
-        let mut fld = [[[0; 19]; 19]; 2];
-        for i in 0..19 {
-            for j in 0..19 {
-                let l = match state[(i, j)] {
-                    CellState::X => 0,
-                    CellState::O => 1,
-                    CellState::Empty => continue,
-                };
-                fld[l][i][j] = 1;
+impl<const HISTORY: usize, const SIDE_TO_MOVE: bool>
+    TicTacToeAlphaZeroAdapter<HISTORY, SIDE_TO_MOVE>
+{
+    pub const INPUT_PLANES: usize = 2 + HISTORY + SIDE_TO_MOVE as usize;
+
+    // Sets the cells of the `INPUT_PLANES` N×N planes of `planes` to `one` where they apply
+    fn set_planes<const N: usize, const K: usize, T: Copy>(
+        state: &GomokuBoard<N, K>,
+        planes: &mut [T],
+        one: T,
+    ) {
+        const { assert!(HISTORY <= GOMOKU_HISTORY, "Boards remember fewer moves") };
+        for (l, rows) in state.canonical().iter().enumerate() {
+            for (i, &row) in rows.iter().enumerate() {
+                let mut bits = row;
+                while bits != 0 {
+                    planes[(l * N + i) * N + bits.trailing_zeros() as usize] = one;
+                    bits &= bits - 1;
+                }
             }
         }
-        let res = Tensor::from_slice(fld.flatten().flatten()).view([2, 19, 19]);
-        // println!("Converted input to tensor in {:?}", Instant::now() - start);
-        res
+        for (l, TicTacToeMove(i, j)) in state.recent_moves().take(HISTORY).enumerate() {
+            planes[((2 + l) * N + i) * N + j] = one;
+        }
+        if SIDE_TO_MOVE && state.is_first_player_to_move() {
+            let l = 2 + HISTORY;
+            planes[l * N * N..(l + 1) * N * N].fill(one);
+        }
     }
+}
 
-    fn get_estimated_policy(policy: &Tensor, moves: &[<BoardState as Game>::Move]) -> Vec<f32> {
-        // let start = Instant::now();
-        let policy = policy.exp();
-        let mut res = Vec::with_capacity(moves.len());
-        let policy = <Vec<f32>>::try_from(policy.view([-1])).unwrap();
-        for &TicTacToeMove(i, j) in moves {
-            res.push(policy[i * 19 + j]);
-        }
+impl<const N: usize, const K: usize, const HISTORY: usize, const SIDE_TO_MOVE: bool>
+    ActionEncoding<GomokuBoard<N, K>> for TicTacToeAlphaZeroAdapter<HISTORY, SIDE_TO_MOVE>
+{
+    fn action_space_size() -> usize {
+        N * N
+    }
 
-        let sum = res.iter().sum::<f32>();
-        if sum > 0. {
-            for x in &mut res {
-                *x /= sum;
-            }
-        }
+    fn move_to_index(&TicTacToeMove(i, j): &TicTacToeMove) -> usize {
+        i * N + j
+    }
 
-        // println!("Calculated policy in {:?}", Instant::now() - start);
+    fn index_to_move(index: usize) -> TicTacToeMove {
+        TicTacToeMove(index / N, index % N)
+    }
 
+    fn policy_shape() -> Vec<i64> {
+        vec![N as i64, N as i64]
+    }
+}
+
+// The planes as `convert_game_to_nn_input` has them, for nets run without tch
+impl<const N: usize, const K: usize, const HISTORY: usize, const SIDE_TO_MOVE: bool>
+    InputEncoding<GomokuBoard<N, K>> for TicTacToeAlphaZeroAdapter<HISTORY, SIDE_TO_MOVE>
+{
+    fn input_shape() -> Vec<usize> {
+        vec![Self::INPUT_PLANES, N, N]
+    }
+
+    fn encode_input(state: &GomokuBoard<N, K>) -> Vec<f32> {
+        let mut planes = vec![0.0; Self::INPUT_PLANES * N * N];
+        Self::set_planes(state, &mut planes, 1.0);
+        planes
+    }
+}
+
+// The net has to be built for the same board size and output N×N policies
+#[cfg(feature = "torch")]
+impl<
+        const N: usize,
+        const K: usize,
+        const HISTORY: usize,
+        const SIDE_TO_MOVE: bool,
+        TNet: AlphaZeroNet,
+    > AlphaZeroAdapter<GomokuBoard<N, K>, TNet>
+    for TicTacToeAlphaZeroAdapter<HISTORY, SIDE_TO_MOVE>
+{
+    fn input_planes() -> Vec<String> {
+        let mut planes = vec!["to move".to_owned(), "opponent".to_owned()];
+        planes.extend((1..=HISTORY).map(|ago| format!("move {ago} ago")));
+        if SIDE_TO_MOVE {
+            planes.push("to move played first".to_owned());
+        }
+        planes
+    }
+
+    fn convert_game_to_nn_input(state: &GomokuBoard<N, K>) -> tch::Tensor {
+        // let start = Instant::now();
+        let planes = Self::INPUT_PLANES;
+        let mut fld = vec![0; planes * N * N];
+        Self::set_planes(state, &mut fld, 1);
+        let res = Tensor::from_slice(&fld).view([planes as i64, N as i64, N as i64]);
+        // println!("Converted input to tensor in {:?}", Instant::now() - start);
         res
     }
 
-    fn convert_policy_to_nn(policy: &[f32], moves: &[<BoardState as Game>::Move]) -> tch::Tensor {
-        let mut res = [[0f32; 19]; 19];
-        for (&TicTacToeMove(i, j), &pol) in moves.iter().zip(policy) {
-            res[i][j] = pol;
+    fn convert_games_to_nn_input(
+        states: &[GomokuBoard<N, K>],
+        (kind, device): (Kind, Device),
+    ) -> Tensor {
+        let planes = Self::INPUT_PLANES;
+        let mut fld = vec![0u8; states.len() * planes * N * N];
+        for (state, state_planes) in states.iter().zip(fld.chunks_mut(planes * N * N)) {
+            Self::set_planes(state, state_planes, 1);
         }
-        Tensor::from_slice(res.flatten()).view([19, 19])
+        Tensor::from_slice(&fld)
+            .view([states.len() as i64, planes as i64, N as i64, N as i64])
+            .to_kind(kind)
+            .to(device)
+    }
+
+    fn get_estimated_policy(policy: &Tensor, moves: &[TicTacToeMove]) -> Vec<f32> {
+        <Self as ActionEncoding<GomokuBoard<N, K>>>::decode_policy(policy, moves)
+    }
+
+    fn convert_policy_to_nn(policy: &[f32], moves: &[TicTacToeMove]) -> tch::Tensor {
+        <Self as ActionEncoding<GomokuBoard<N, K>>>::encode_policy(policy, moves)
     }
 
-    fn reflect_and_augment(state: &Tensor, policy: &Tensor) -> Vec<(Tensor, Tensor)> {
-        let mut rotations = Vec::with_capacity(8);
-        for reflect in [false, true] {
-            for rots in 0..4 {
-                let transform = move |inp: &Tensor, dim: i64| -> Tensor {
-                    if reflect { inp.flip([dim]) } else { inp.copy() }.rot90(rots, [dim, dim + 1])
-                };
-                rotations.push((transform(state, 1), transform(policy, 0)));
+    fn symmetries() -> Vec<SymmetryTransform> {
+        SymmetryTransform::dihedral_group()
+    }
+
+    // Stones never move, so this is where the players' stones end up
+    fn ownership_target(
+        state: &GomokuBoard<N, K>,
+        final_state: &GomokuBoard<N, K>,
+    ) -> Option<Vec<f32>> {
+        let [mover, other] = final_state.canonical();
+        let (own, opponent) =
+            match state.is_first_player_to_move() == final_state.is_first_player_to_move() {
+                true => (mover, other),
+                false => (other, mover),
+            };
+        let mut target = vec![0.0; N * N];
+        for (rows, sign) in [(own, 1.0), (opponent, -1.0)] {
+            for (i, &row) in rows.iter().enumerate() {
+                let mut bits = row;
+                while bits != 0 {
+                    target[i * N + bits.trailing_zeros() as usize] = sign;
+                    bits &= bits - 1;
+                }
             }
         }
-        rotations
+        Some(target)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use tch::IndexOp;
+    #[cfg(feature = "torch")]
+    use tch::{IndexOp, Kind, Tensor};
 
+    #[cfg(feature = "torch")]
     use crate::{
         alpha_zero::AlphaZeroAdapter,
-        tictactoe::{BoardState, CellState},
+        tictactoe::{BoardState, CellState, TicTacToeNet},
+    };
+    use crate::{
+        alpha_zero::Game,
+        tictactoe::{GomokuBoard, TicTacToeMove},
     };
 
     use super::TicTacToeAlphaZeroAdapter;
 
+    #[test]
+    fn history_and_side_to_move_planes() {
+        type Adapter = TicTacToeAlphaZeroAdapter<2, true>;
+        let planes = |state: &GomokuBoard<9, 5>| {
+            let mut planes = vec![0; Adapter::INPUT_PLANES * 81];
+            Adapter::set_planes(state, &mut planes, 1);
+            // The cells set on each plane
+            planes
+                .chunks(81)
+                .map(|plane| {
+                    (0..81)
+                        .filter(|&cell| plane[cell] == 1)
+                        .map(|cell| (cell / 9, cell % 9))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut state = GomokuBoard::<9, 5>::new();
+        for (i, j) in [(4, 4), (0, 0), (8, 8)] {
+            state = state.make_move(&TicTacToeMove(i, j));
+        }
+        assert_eq!(
+            planes(&state),
+            [
+                vec![(0, 0)],
+                vec![(4, 4), (8, 8)],
+                vec![(8, 8)],
+                vec![(0, 0)],
+                vec![]
+            ]
+        );
+
+        // The first player is to move again
+        let state = state.make_move(&TicTacToeMove(1, 1));
+        let all = planes(&state);
+        assert_eq!(all[2], [(1, 1)]);
+        assert_eq!(all[3], [(8, 8)]);
+        assert_eq!(all[4].len(), 81);
+        assert_eq!(<TicTacToeAlphaZeroAdapter>::INPUT_PLANES, 2);
+    }
+
+    #[cfg(feature = "torch")]
+    #[test]
+    fn augmentation_moves_policies_with_states() {
+        type Adapter = TicTacToeAlphaZeroAdapter<1, true>;
+        let state = BoardState::new()
+            .make_move(&TicTacToeMove(3, 5))
+            .make_move(&TicTacToeMove(0, 1));
+        // All of the policy on the cell of the first player's stone, which is on plane 0
+        let moves = BoardState::new().get_state().get_moves().unwrap();
+        let policy = moves
+            .iter()
+            .map(|&m| (m == TicTacToeMove(3, 5)) as u8 as f32)
+            .collect::<Vec<_>>();
+        let input =
+            <Adapter as AlphaZeroAdapter<BoardState, TicTacToeNet>>::convert_game_to_nn_input(
+                &state,
+            )
+            .to_kind(Kind::Float);
+        let policy = <Adapter as AlphaZeroAdapter<BoardState, TicTacToeNet>>::convert_policy_to_nn(
+            &policy, &moves,
+        );
+
+        let symmetries = <Adapter as AlphaZeroAdapter<BoardState, TicTacToeNet>>::symmetries();
+        let augmented =
+            <Adapter as AlphaZeroAdapter<BoardState, TicTacToeNet>>::reflect_and_augment(
+                &input, &policy,
+            );
+        assert_eq!(augmented.len(), 8);
+        let values = |tensor: &Tensor| Vec::<f32>::try_from(tensor.flatten(0, -1)).unwrap();
+        let mut cells = vec![];
+        for (symmetry, (state, policy_of_state)) in symmetries.iter().zip(&augmented) {
+            assert_eq!(values(&state.i(0)), values(policy_of_state));
+            assert_eq!(
+                values(&symmetry.inverse_policy(policy_of_state)),
+                values(&policy)
+            );
+            // The last move's plane turns with the stones, the side to move's is all ones
+            assert_eq!(
+                values(&state.i(2)),
+                values(&symmetry.transform_policy(&input.i(2)))
+            );
+            assert_eq!(f64::try_from(state.i(3).sum(Kind::Float)).unwrap(), 361.0);
+            cells.push(i64::try_from(policy_of_state.flatten(0, -1).argmax(0, false)).unwrap());
+        }
+        cells.sort();
+        cells.dedup();
+        assert_eq!(cells.len(), 8);
+    }
+
+    #[cfg(feature = "torch")]
     #[test]
     fn convert_board_to_tensor() {
         let mut game = BoardState::new();
         game.set_inplace((10, 0), CellState::O);
         game.set_inplace((1, 3), CellState::X);
 
-        let tensor = TicTacToeAlphaZeroAdapter::convert_game_to_nn_input(&game);
+        let tensor =
+            <TicTacToeAlphaZeroAdapter as AlphaZeroAdapter<_, TicTacToeNet>>::convert_game_to_nn_input(
+                &game,
+            );
         assert_eq!(tensor.size(), [2, 19, 19]);
 
         let ones = [(1, 10, 0), (0, 1, 3)];
@@ -99,4 +294,29 @@ mod tests {
             }
         }
     }
+    #[cfg(feature = "torch")]
+    #[test]
+    fn ownership_from_the_mover_at_every_position() {
+        let ownership = |before: &str, after: &str| {
+            <TicTacToeAlphaZeroAdapter as AlphaZeroAdapter<_, TicTacToeNet>>::ownership_target(
+                &GomokuBoard::<7, 5>::from_moves(before).unwrap(),
+                &GomokuBoard::<7, 5>::from_moves(after).unwrap(),
+            )
+            .unwrap()
+        };
+        let first_row = |target: Vec<f32>| target[..4].to_vec();
+        assert_eq!(first_row(ownership("", "a1b1c1")), [1.0, -1.0, 1.0, 0.0]);
+        assert_eq!(first_row(ownership("a1", "a1b1c1")), [-1.0, 1.0, -1.0, 0.0]);
+        assert_eq!(
+            first_row(ownership("a1b1", "a1b1c1")),
+            [1.0, -1.0, 1.0, 0.0]
+        );
+        assert_eq!(
+            ownership("a1b1", "a1b1c1")
+                .iter()
+                .filter(|&&x| x != 0.0)
+                .count(),
+            3
+        );
+    }
 }
@@ -1,60 +1,148 @@
-use std::ops::{Index, Range};
+use std::{
+    hash::{Hash, Hasher},
+    ops::Index,
+};
 
 use serde::Serialize;
 
-use crate::alpha_zero::{Game, MoveParameters, TerminationState};
+use anyhow::Context;
+
+use crate::alpha_zero::{
+    Game, HeuristicEval, MoveNotation, MoveParameters, ReversibleGame, SgfGame, TerminationState,
+};
+
+// Moves a board remembers, for the net's history planes
+pub const GOMOKU_HISTORY: usize = 4;
+
+// Marks the slots of `recent` without a move
+const NO_MOVE: u16 = u16::MAX;
+
+// Gomoku on an N×N board, K in a row wins. Each player's stones are a bitboard of a u64 per
+// row, bit `y` of word `x` standing for the cell `(x, y)`, so that lines are found by shifting
+// and ANDing whole rows. Cells are read and set as `X` for the player to move and `O` for the
+// other one, though moves only flip which of the bitboards that is.
+#[derive(Clone)]
+pub struct GomokuBoard<const N: usize, const K: usize> {
+    // The first player's stones, then the second player's
+    stones: [[u64; N]; 2],
+    // Index in `stones` of the player to move
+    to_move: usize,
+    // Known after `make_move`, which only checks the lines through the move. Boards whose cells
+    // were set directly are scanned.
+    winner: Option<CellState>,
+    // The last moves made, as `row * N + column`, most recent first. Not part of the position:
+    // boards compare and hash equal whatever moves led to them.
+    recent: [u16; GOMOKU_HISTORY],
+}
 
-const N: usize = 19;
+// By the cells as the player to move sees them, whether the winner is known or not
+impl<const N: usize, const K: usize> PartialEq for GomokuBoard<N, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical() == other.canonical()
+    }
+}
 
-const BYTES: usize = (N * N - 1) / (std::mem::size_of::<u8>() * 4) + 1;
+impl<const N: usize, const K: usize> Eq for GomokuBoard<N, K> {}
 
-#[derive(Clone, Hash, PartialEq, Eq)]
-pub struct BoardState {
-    state: [u8; BYTES],
+impl<const N: usize, const K: usize> Hash for GomokuBoard<N, K> {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.canonical().hash(hasher);
+    }
 }
 
-impl Serialize for BoardState {
+// The classic 19x19 five-in-a-row board
+pub type BoardState = GomokuBoard<19, 5>;
+
+// As a u64 per row with 2 bits per cell, 1 for `X` and 2 for `O`
+impl<const N: usize, const K: usize> Serialize for GomokuBoard<N, K> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.collect_seq(&self.state)
+        let [own, other] = self.canonical();
+        let packed = (0..N).map(|x| {
+            (0..N).fold(0u64, |row, y| {
+                let x_bit = (own[x] >> y) & 1;
+                let o_bit = (other[x] >> y) & 1;
+                row | (x_bit | (o_bit << 1)) << (2 * y)
+            })
+        });
+        serializer.collect_seq(packed)
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum CellState {
     Empty,
     X,
     O,
 }
 
-impl Default for BoardState {
+impl CellState {
+    // The other player's stone, `Empty` staying empty
+    pub fn other(self) -> Self {
+        match self {
+            CellState::Empty => CellState::Empty,
+            CellState::X => CellState::O,
+            CellState::O => CellState::X,
+        }
+    }
+}
+
+impl<const N: usize, const K: usize> Default for GomokuBoard<N, K> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl BoardState {
+impl<const N: usize, const K: usize> GomokuBoard<N, K> {
+    pub const SIZE: usize = N;
+    pub const WIN_LENGTH: usize = K;
+    // The bits of a row's cells
+    const ROW: u64 = (1 << N) - 1;
+
     pub fn new() -> Self {
-        Self { state: [0; BYTES] }
+        const { assert!(N <= 32 && 0 < K && K <= N) };
+        Self {
+            stones: [[0; N]; 2],
+            to_move: 0,
+            winner: Some(CellState::Empty),
+            recent: [NO_MOVE; GOMOKU_HISTORY],
+        }
+    }
+
+    // Up to `GOMOKU_HISTORY` of the moves that led to the board, most recent first, only
+    // counting the moves made with `make_move`
+    pub fn recent_moves(&self) -> impl Iterator<Item = TicTacToeMove> + '_ {
+        self.recent
+            .iter()
+            .take_while(|&&cell| cell != NO_MOVE)
+            .map(|&cell| TicTacToeMove(cell as usize / N, cell as usize % N))
+    }
+
+    // Whether the player to move made the game's first move, or would have on a board whose
+    // cells were set directly
+    pub fn is_first_player_to_move(&self) -> bool {
+        self.to_move == 0
     }
 
     pub fn set_inplace(&mut self, (x, y): (usize, usize), state: CellState) {
         assert!(x < N && y < N);
-        let idx = x * N + y;
-        let chunk = idx / 4;
-        let offset = idx % 4;
-
-        let chunk = &mut self.state[chunk];
-        *chunk &= !(3 << (2 * offset));
+        self.winner = None;
+        let bit = 1 << y;
+        self.stones[0][x] &= !bit;
+        self.stones[1][x] &= !bit;
+        match state {
+            CellState::Empty => {}
+            CellState::X => self.stones[self.to_move][x] |= bit,
+            CellState::O => self.stones[1 - self.to_move][x] |= bit,
+        }
+    }
 
-        let v = match state {
-            CellState::Empty => 0,
-            CellState::X => 1,
-            CellState::O => 2,
-        };
-        *chunk |= v << (2 * offset);
+    // The bitboards of the player to move (`X`) and of the other player (`O`), the way the
+    // net sees the board
+    pub fn canonical(&self) -> [[u64; N]; 2] {
+        [self.stones[self.to_move], self.stones[1 - self.to_move]]
     }
 
     pub fn set(mut self, coord: (usize, usize), state: CellState) -> Self {
@@ -63,16 +151,8 @@ impl BoardState {
     }
 
     pub fn flip_players_inplace(&mut self) {
-        for i in 0..N {
-            for j in 0..N {
-                let other = match self[(i, j)] {
-                    CellState::Empty => CellState::Empty,
-                    CellState::X => CellState::O,
-                    CellState::O => CellState::X,
-                };
-                self.set_inplace((i, j), other);
-            }
-        }
+        self.to_move = 1 - self.to_move;
+        self.winner = self.winner.map(CellState::other);
     }
 
     pub fn flip_players(mut self) -> Self {
@@ -81,35 +161,88 @@ impl BoardState {
     }
 
     pub fn is_win(&self) -> CellState {
-        const RANGES: [Range<usize>; 3] = [4..N, 0..N, 0..(N - 4)];
+        self.winner.unwrap_or_else(|| self.scan_win())
+    }
+
+    // Checks every line of the board
+    pub fn scan_win(&self) -> CellState {
+        if Self::has_line(&self.stones[self.to_move]) {
+            CellState::X
+        } else if Self::has_line(&self.stones[1 - self.to_move]) {
+            CellState::O
+        } else {
+            CellState::Empty
+        }
+    }
+
+    // Whether the stones have K in a row. A bit stays set in a row shifted by `k` and ANDed
+    // with the others if the cells `k` further along the line are all taken. Shifting left
+    // only moves bits past the board's edge, where the unshifted row clears them.
+    fn has_line(rows: &[u64; N]) -> bool {
+        let horizontal = rows
+            .iter()
+            .any(|&row| (1..K).fold(row, |line, k| line & (row >> k)) != 0);
+        horizontal
+            || rows.windows(K).any(|window| {
+                let (mut vertical, mut diagonal, mut anti_diagonal) = (!0, !0, !0);
+                for (k, &row) in window.iter().enumerate() {
+                    vertical &= row;
+                    diagonal &= row >> k;
+                    anti_diagonal &= row << k;
+                }
+                vertical | diagonal | anti_diagonal != 0
+            })
+    }
+
+    // Whether an X at the empty `(x, y)` would complete K in a row
+    fn completes_line(&self, (x, y): (usize, usize)) -> bool {
         const DIRECTIONS: [(i32, i32); 4] = [(-1, 1), (0, 1), (1, 1), (1, 0)];
 
-        for (dx, dy) in DIRECTIONS {
-            for x in RANGES[(dx + 1) as usize].clone() {
-                'cell: for y in RANGES[(dy + 1) as usize].clone() {
-                    let goal = match self[(x, y)] {
-                        CellState::Empty => continue,
-                        v => v,
-                    };
-
-                    for k in 1..5 {
-                        if self[((x as i32 + dx * k) as usize, (y as i32 + dy * k) as usize)]
-                            != goal
-                        {
-                            continue 'cell;
-                        }
-                    }
+        let (x, y) = (x as i32, y as i32);
+        let run = |dx: i32, dy: i32| {
+            (1..K as i32)
+                .take_while(|&k| self.cell(x + dx * k, y + dy * k) == Some(CellState::X))
+                .count()
+        };
+        DIRECTIONS
+            .into_iter()
+            .any(|(dx, dy)| run(dx, dy) + run(-dx, -dy) + 1 >= K)
+    }
+
+    fn cell(&self, x: i32, y: i32) -> Option<CellState> {
+        if (0..N as i32).contains(&x) && (0..N as i32).contains(&y) {
+            Some(self[(x as usize, y as usize)])
+        } else {
+            None
+        }
+    }
+
+    pub fn longest_line(&self, player: CellState) -> usize {
+        const DIRECTIONS: [(i32, i32); 4] = [(-1, 1), (0, 1), (1, 1), (1, 0)];
 
-                    return goal;
+        let mut longest = 0;
+        for x in 0..N as i32 {
+            for y in 0..N as i32 {
+                if self.cell(x, y) != Some(player) {
+                    continue;
+                }
+                for (dx, dy) in DIRECTIONS {
+                    if self.cell(x - dx, y - dy) == Some(player) {
+                        continue;
+                    }
+                    let mut len = 1;
+                    while self.cell(x + dx * len, y + dy * len) == Some(player) {
+                        len += 1;
+                    }
+                    longest = longest.max(len as usize);
                 }
             }
         }
-
-        CellState::Empty
+        longest
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TicTacToeMove(pub usize, pub usize);
 
 impl MoveParameters for TicTacToeMove {
@@ -118,7 +251,7 @@ impl MoveParameters for TicTacToeMove {
     }
 }
 
-impl Game for BoardState {
+impl<const N: usize, const K: usize> Game for GomokuBoard<N, K> {
     type Move = TicTacToeMove;
 
     fn get_state(&self) -> TerminationState<Self::Move> {
@@ -128,12 +261,17 @@ impl Game for BoardState {
             CellState::Empty => {}
         }
 
-        let moves = (0..N)
-            .map(|i| (0..N).map(move |j| (i, j)))
-            .flatten()
-            .filter(|&crd| self[crd] == CellState::Empty)
-            .map(|(i, j)| TicTacToeMove(i, j))
-            .collect::<Vec<_>>();
+        // By row, then column
+        let empty = |i: usize| !(self.stones[0][i] | self.stones[1][i]) & Self::ROW;
+        let count = (0..N).map(|i| empty(i).count_ones() as usize).sum();
+        let mut moves = Vec::with_capacity(count);
+        for i in 0..N {
+            let mut free = empty(i);
+            while free != 0 {
+                moves.push(TicTacToeMove(i, free.trailing_zeros() as usize));
+                free &= free - 1;
+            }
+        }
 
         if moves.is_empty() {
             TerminationState::Terminal(0.5)
@@ -145,37 +283,184 @@ impl Game for BoardState {
     fn make_move(&self, m: &Self::Move) -> Self {
         let mut new_state = self.clone();
         let &TicTacToeMove(i, j) = m;
-        new_state.set_inplace((i, j), CellState::X);
-        new_state.flip_players()
+        let winner = match self.is_win() {
+            CellState::Empty if self.completes_line((i, j)) => CellState::X,
+            winner => winner,
+        };
+        new_state.stones[self.to_move][i] |= 1 << j;
+        new_state.to_move = 1 - self.to_move;
+        new_state.winner = Some(winner.other());
+        new_state.recent.copy_within(..GOMOKU_HISTORY - 1, 1);
+        new_state.recent[0] = (i * N + j) as u16;
+        new_state
+    }
+
+    fn is_legal(&self, &TicTacToeMove(i, j): &Self::Move) -> bool {
+        i < N && j < N && self[(i, j)] == CellState::Empty && self.is_win() == CellState::Empty
     }
 }
 
-impl Index<(usize, usize)> for BoardState {
+impl<const N: usize, const K: usize> HeuristicEval for GomokuBoard<N, K> {
+    fn eval(&self) -> f32 {
+        let own = self.longest_line(CellState::X) as f32;
+        let other = self.longest_line(CellState::O) as f32;
+        (0.5 + 0.1 * (own - other)).clamp(0.05, 0.95)
+    }
+
+    // Prefers cells next to existing stones
+    fn policy_prior(&self, moves: &[Self::Move]) -> Vec<f32> {
+        let weights = moves
+            .iter()
+            .map(|&TicTacToeMove(i, j)| {
+                let mut neighbours = 0;
+                for dx in -1..=1 {
+                    for dy in -1..=1 {
+                        let cell = self.cell(i as i32 + dx, j as i32 + dy);
+                        if matches!(cell, Some(CellState::X | CellState::O)) {
+                            neighbours += 1;
+                        }
+                    }
+                }
+                1.0 + 4.0 * neighbours as f32
+            })
+            .collect::<Vec<_>>();
+        let sum = weights.iter().sum::<f32>();
+        weights.into_iter().map(|w| w / sum).collect()
+    }
+}
+
+// `<row>,<column>`, both from 0
+impl<const N: usize, const K: usize> MoveNotation for GomokuBoard<N, K> {
+    fn parse_move(&self, text: &str) -> anyhow::Result<Self::Move> {
+        let (row, column) = text
+            .split_once(',')
+            .with_context(|| format!("Expected <row>,<column>, got {text}"))?;
+        let coordinate = |s: &str| match s.parse::<usize>() {
+            Ok(x) if x < N => Ok(x),
+            _ => anyhow::bail!("Invalid coordinate {s} of {text}"),
+        };
+        Ok(TicTacToeMove(coordinate(row)?, coordinate(column)?))
+    }
+
+    fn format_move(&self, &TicTacToeMove(row, column): &Self::Move) -> String {
+        format!("{row},{column}")
+    }
+}
+
+impl<const N: usize, const K: usize> SgfGame for GomokuBoard<N, K> {
+    // Gomoku and Renju, the rules being up to the game's other properties
+    const GAME_TYPE: u32 = 4;
+
+    fn board_size(&self) -> usize {
+        N
+    }
+
+    fn move_point(&self, &TicTacToeMove(row, column): &Self::Move) -> Option<(usize, usize)> {
+        Some((row, column))
+    }
+
+    fn point_move(&self, point: Option<(usize, usize)>) -> anyhow::Result<Self::Move> {
+        let (row, column) = point.context("Gomoku has no passes")?;
+        Ok(TicTacToeMove(row, column))
+    }
+}
+
+impl<const N: usize, const K: usize> ReversibleGame for GomokuBoard<N, K> {
+    fn undo_move(&self, &TicTacToeMove(i, j): &Self::Move) -> Self {
+        // The player who made the move is "O" now
+        assert_eq!(self[(i, j)], CellState::O);
+        let mut state = self.clone().set((i, j), CellState::Empty).flip_players();
+        // The move before the oldest one remembered is lost
+        state.recent.copy_within(1.., 0);
+        state.recent[GOMOKU_HISTORY - 1] = NO_MOVE;
+        state
+    }
+}
+
+impl<const N: usize, const K: usize> Index<(usize, usize)> for GomokuBoard<N, K> {
     type Output = CellState;
 
     fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
         assert!(x < N && y < N);
-        let idx = x * N + y;
-        let chunk = idx / 4;
-        let offset = idx % 4;
-        let val = (self.state[chunk] >> (2 * offset)) & 3;
-        match val {
-            0 => &CellState::Empty,
-            1 => &CellState::X,
-            2 => &CellState::O,
-            _ => unreachable!(),
+        if (self.stones[self.to_move][x] >> y) & 1 == 1 {
+            &CellState::X
+        } else if (self.stones[1 - self.to_move][x] >> y) & 1 == 1 {
+            &CellState::O
+        } else {
+            &CellState::Empty
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use proptest::{collection::vec, prelude::*, sample::Index};
+
     use crate::{
-        alpha_zero::{Game, TerminationState},
-        tictactoe::CellState,
+        alpha_zero::{Game, IllegalMove, MoveHistory, ReversibleGame, TerminationState},
+        tictactoe::{CellState, TicTacToeMove},
     };
 
-    use super::BoardState;
+    use super::{BoardState, GomokuBoard};
+
+    // Cell by cell, from every cell in every direction
+    fn naive_line<const N: usize, const K: usize>(
+        board: &GomokuBoard<N, K>,
+        player: CellState,
+    ) -> bool {
+        const DIRECTIONS: [(i32, i32); 4] = [(-1, 1), (0, 1), (1, 1), (1, 0)];
+        (0..N as i32)
+            .flat_map(|x| (0..N as i32).map(move |y| (x, y)))
+            .any(|(x, y)| {
+                DIRECTIONS.into_iter().any(|(dx, dy)| {
+                    (0..K as i32).all(|k| board.cell(x + dx * k, y + dy * k) == Some(player))
+                })
+            })
+    }
+
+    // Plays the chosen moves while the game lasts, checking the winner after each one
+    fn incremental_matches_scan<const N: usize, const K: usize>(
+        choices: &[Index],
+    ) -> Result<(), TestCaseError> {
+        let mut board = GomokuBoard::<N, K>::new();
+        for choice in choices {
+            let Some(moves) = board.get_state().get_moves() else {
+                break;
+            };
+            board = board.make_move(choice.get(&moves));
+            prop_assert_eq!(board.is_win(), board.scan_win());
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn bitboard_lines(cells in vec(0..4u8, 81)) {
+            let mut board = GomokuBoard::<9, 4>::new();
+            for (i, &cell) in cells.iter().enumerate() {
+                let state = match cell {
+                    1 => CellState::X,
+                    2 => CellState::O,
+                    _ => CellState::Empty,
+                };
+                board.set_inplace((i / 9, i % 9), state);
+            }
+            for (stones, player) in board.canonical().iter().zip([CellState::X, CellState::O]) {
+                prop_assert_eq!(
+                    GomokuBoard::<9, 4>::has_line(stones),
+                    naive_line(&board, player)
+                );
+            }
+        }
+
+        #[test]
+        fn incremental_win_detection(choices in vec(any::<Index>(), 1..200)) {
+            incremental_matches_scan::<9, 4>(&choices)?;
+            incremental_matches_scan::<19, 5>(&choices)?;
+        }
+    }
 
     #[test]
     fn tic_tac_toe_win() {
@@ -244,6 +529,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn win_through_the_last_move() {
+        // X fills the gap of 0,0 0,1 _ 0,3 0,4 while O plays on row 5
+        let moves = [
+            (0, 0),
+            (5, 0),
+            (0, 1),
+            (5, 2),
+            (0, 3),
+            (5, 4),
+            (0, 4),
+            (5, 6),
+            (0, 2),
+        ];
+        let mut board = BoardState::new();
+        for (ply, &(i, j)) in moves.iter().enumerate() {
+            assert_eq!(board.is_win(), CellState::Empty, "{ply}");
+            board = board.make_move(&TicTacToeMove(i, j));
+        }
+        // The winner has just moved
+        assert_eq!(board.is_win(), CellState::O);
+        assert_eq!(board.scan_win(), CellState::O);
+        assert_eq!(board.get_state(), TerminationState::Terminal(0.));
+        // Equal to the same cells set directly, whose winner isn't known
+        let mut set = BoardState::new();
+        for (ply, &(i, j)) in moves.iter().enumerate() {
+            let stone = match ply % 2 {
+                0 => CellState::O,
+                _ => CellState::X,
+            };
+            set.set_inplace((i, j), stone);
+        }
+        assert!(set == board);
+        // The stone just played is the other player's for the one to move now
+        let [own, other] = BoardState::new()
+            .make_move(&TicTacToeMove(2, 3))
+            .canonical();
+        assert_eq!((own[2], other[2]), (0, 1 << 3));
+    }
+
+    #[test]
+    fn configurable_size_and_win_length() {
+        let mut board = GomokuBoard::<9, 4>::new();
+        for i in 5..8 {
+            board.set_inplace((8, i), CellState::X);
+        }
+        assert_eq!(board.is_win(), CellState::Empty);
+        board.set_inplace((8, 8), CellState::X);
+        assert_eq!(board.is_win(), CellState::X);
+
+        assert_eq!(
+            GomokuBoard::<15, 5>::new()
+                .get_state()
+                .get_moves()
+                .unwrap()
+                .len(),
+            225
+        );
+        assert!(!GomokuBoard::<9, 4>::new().is_legal(&TicTacToeMove(9, 0)));
+    }
+
     #[test]
     fn tic_tac_toe_draw() {
         let mut board = BoardState::new();
@@ -260,4 +606,57 @@ mod tests {
 
         assert_eq!(board.get_state(), TerminationState::Terminal(0.5));
     }
+
+    #[test]
+    fn tic_tac_toe_illegal_moves() {
+        let board = BoardState::new().set((3, 3), CellState::O);
+
+        assert!(board.is_legal(&TicTacToeMove(0, 0)));
+        assert!(!board.is_legal(&TicTacToeMove(3, 3)));
+        assert!(!board.is_legal(&TicTacToeMove(19, 0)));
+        assert!(!board.is_legal(&TicTacToeMove(0, 19)));
+
+        assert!(board.try_make_move(&TicTacToeMove(3, 3)) == Err(IllegalMove));
+        assert!(
+            board.try_make_move(&TicTacToeMove(1, 2)) == Ok(board.make_move(&TicTacToeMove(1, 2)))
+        );
+
+        let mut won = BoardState::new();
+        for i in 0..5 {
+            won.set_inplace((i, 0), CellState::X);
+        }
+        assert!(!won.is_legal(&TicTacToeMove(10, 10)));
+    }
+
+    #[test]
+    fn tic_tac_toe_undo() {
+        let start = BoardState::new().set((5, 5), CellState::X);
+        let after = start.make_move(&TicTacToeMove(2, 7));
+        assert!(after.undo_move(&TicTacToeMove(2, 7)) == start);
+
+        let mut history = MoveHistory::new(start.clone());
+        history.play(TicTacToeMove(0, 0)).unwrap();
+        history.play(TicTacToeMove(0, 1)).unwrap();
+        assert!(history.play(TicTacToeMove(0, 1)).is_err());
+        let two_moves = history.state().clone();
+
+        assert_eq!(
+            two_moves.recent_moves().collect::<Vec<_>>(),
+            [TicTacToeMove(0, 1), TicTacToeMove(0, 0)]
+        );
+        assert_eq!(history.undo(), Some(&TicTacToeMove(0, 1)));
+        assert_eq!(
+            history.state().recent_moves().collect::<Vec<_>>(),
+            [TicTacToeMove(0, 0)]
+        );
+        assert_eq!(history.undo(), Some(&TicTacToeMove(0, 0)));
+        assert_eq!(history.undo(), None);
+        assert!(history.state() == &start);
+
+        assert_eq!(history.redo(), Some(&TicTacToeMove(0, 0)));
+        assert_eq!(history.redo(), Some(&TicTacToeMove(0, 1)));
+        assert_eq!(history.redo(), None);
+        assert!(history.state() == &two_moves);
+        assert_eq!(history.moves(), [TicTacToeMove(0, 0), TicTacToeMove(0, 1)]);
+    }
 }
@@ -5,7 +5,7 @@ use tch::{
     Tensor,
 };
 
-use crate::alpha_zero::AlphaZeroNet;
+use crate::alpha_zero::{AlphaZeroNet, NetOutput};
 
 pub struct TicTacToeNet {
     conv1: Conv2D,
@@ -33,12 +33,22 @@ pub struct TicTacToeNet {
     fc_value_2: Linear,
     // bn_fc_value_3: BatchNorm,
     fc_value_3: Linear,
+
+    size: i64,
 }
 
 impl TicTacToeNet {
-    pub fn new(path: &nn::Path) -> Self {
+    // `size` is the side of the board, the spatial dimensions below are for 19
+    pub fn new(path: &nn::Path, size: i64) -> Self {
+        Self::with_input_planes(path, size, 2)
+    }
+
+    // For adapters with more planes than the stones, see `TicTacToeAlphaZeroAdapter`
+    pub fn with_input_planes(path: &nn::Path, size: i64, planes: i64) -> Self {
+        assert!(size >= 7, "Board of size {size} is too small for the net");
+        let mid = 40 * Self::pooled(size) * Self::pooled(size);
         Self {
-            conv1: nn::conv2d(path / "conv1", 2, 10, 4, Default::default()), // 2x19x19 -> 10x16x16
+            conv1: nn::conv2d(path / "conv1", planes, 10, 4, Default::default()), // 2x19x19 -> 10x16x16
             bn_conv2: nn::batch_norm2d(path / "bn_conv2", 10, Default::default()),
             conv2: nn::conv2d(
                 path / "conv2",
@@ -62,20 +72,10 @@ impl TicTacToeNet {
                 },
             ), // 20x4x4 -> 40x4x4
             bn_fc_mid_1: nn::batch_norm2d(path / "bn_fc_mid_1", 40, Default::default()),
-            fc_mid_1: nn::linear(
-                path / "fc_mid_1",
-                40 * 4 * 4,
-                40 * 4 * 4,
-                Default::default(),
-            ),
+            fc_mid_1: nn::linear(path / "fc_mid_1", mid, mid, Default::default()),
 
             bn_fc_mid_2: nn::batch_norm1d(path / "bn_fc_mid_2", 1, Default::default()),
-            fc_mid_2: nn::linear(
-                path / "fc_mid_2",
-                40 * 4 * 4,
-                40 * 4 * 4,
-                Default::default(),
-            ),
+            fc_mid_2: nn::linear(path / "fc_mid_2", mid, mid, Default::default()),
             bn_upconv3: nn::batch_norm2d(path / "bn_upconv3", 40, Default::default()),
             upconv3: nn::conv_transpose2d(
                 path / "upconv3",
@@ -114,46 +114,57 @@ impl TicTacToeNet {
                 },
             ), // 7x19x19 -> 1x19x19
 
-            fc_value_1: nn::linear(path / "fc_value_1", 4 * 4 * 40, 50, Default::default()),
+            fc_value_1: nn::linear(path / "fc_value_1", mid, 50, Default::default()),
             bn_fc_value_2: nn::batch_norm1d(path / "bn_fc_value_2", 1, Default::default()),
             fc_value_2: nn::linear(path / "fc_value_2", 50, 10, Default::default()),
             // bn_fc_value_3: nn::batch_norm1d(path / "bn_fc_value_3", 1, Default::default()),
             fc_value_3: nn::linear(path / "fc_value_3", 10, 1, Default::default()),
+
+            size,
         }
     }
+
+    // Side after the first convolution and both poolings
+    fn pooled(size: i64) -> i64 {
+        (size - 3) / 2 / 2
+    }
 }
 
 impl AlphaZeroNet for TicTacToeNet {
-    fn forward_t(&self, xs: &Tensor, is_training: bool) -> (Tensor, Tensor) {
+    fn forward_t(&self, xs: &Tensor, is_training: bool) -> NetOutput {
+        let s1 = self.size - 3;
+        let s2 = s1 / 2;
+        let s3 = s2 / 2;
+
         let layer1 = self.conv1.forward_t(xs, is_training);
-        assert_eq!(layer1.size()[1..], [10, 16, 16]);
+        assert_eq!(layer1.size()[1..], [10, s1, s1]);
         let layer1 = layer1.relu();
         let layer1 = self.bn_conv2.forward_t(&layer1, is_training);
-        // 10x16x16
-        assert_eq!(layer1.size()[1..], [10, 16, 16]);
+        // 10xS1xS1
+        assert_eq!(layer1.size()[1..], [10, s1, s1]);
 
         let layer2 = self.conv2.forward_t(&layer1, is_training);
-        assert_eq!(layer2.size()[1..], [20, 16, 16]);
+        assert_eq!(layer2.size()[1..], [20, s1, s1]);
         let (layer2, indices2) = layer2.max_pool2d_with_indices(2, 2, 0, 1, false);
-        assert_eq!(layer2.size()[1..], [20, 8, 8]);
+        assert_eq!(layer2.size()[1..], [20, s2, s2]);
         let layer2 = layer2.relu();
         let layer2 = self.bn_conv3.forward_t(&layer2, is_training);
-        // 20x8x8
-        assert_eq!(layer2.size()[1..], [20, 8, 8]);
+        // 20xS2xS2
+        assert_eq!(layer2.size()[1..], [20, s2, s2]);
 
         let layer3 = self.conv3.forward_t(&layer2, is_training);
-        assert_eq!(layer3.size()[1..], [40, 8, 8]);
+        assert_eq!(layer3.size()[1..], [40, s2, s2]);
         let (layer3, indices3) = layer3.max_pool2d_with_indices(2, 2, 0, 1, false);
-        assert_eq!(layer3.size()[1..], [40, 4, 4]);
+        assert_eq!(layer3.size()[1..], [40, s3, s3]);
         let layer3 = layer3.relu();
         let layer3 = self.bn_fc_mid_1.forward_t(&layer3, is_training);
-        // 40x4x4
-        assert_eq!(layer3.size()[1..], [40, 4, 4]);
+        // 40xS3xS3
+        assert_eq!(layer3.size()[1..], [40, s3, s3]);
 
         let mid = self
             .fc_mid_1
             .forward_t(&layer3.view([layer3.size()[0], -1]), is_training);
-        assert_eq!(mid.size()[1..], [4 * 4 * 40]);
+        assert_eq!(mid.size()[1..], [s3 * s3 * 40]);
         let mid = mid.relu();
         let mid = self
             .bn_fc_mid_2
@@ -175,33 +186,33 @@ impl AlphaZeroNet for TicTacToeNet {
         // Policy
         let policy = self.fc_mid_2.forward_t(&mid, is_training);
         let policy = policy.relu();
-        let policy = policy.view([policy.size()[0], 40, 4, 4]);
+        let policy = policy.view([policy.size()[0], 40, s3, s3]);
         let policy = self.bn_upconv3.forward_t(&policy, is_training);
         let policy = Tensor::concat(&[policy, layer3], 1);
-        let policy = policy.max_unpool2d(&Tensor::concat(&[&indices3, &indices3], 1), &[8, 8]);
-        let policy = self.upconv3.forward_t(&policy, is_training); // 20x8x8
+        let policy = policy.max_unpool2d(&Tensor::concat(&[&indices3, &indices3], 1), &[s2, s2]);
+        let policy = self.upconv3.forward_t(&policy, is_training); // 20xS2xS2
         let policy = policy.relu();
 
         let policy = self.bn_upconv2.forward_t(&policy, is_training);
         let policy = Tensor::concat(&[policy, layer2], 1);
-        let policy = policy.max_unpool2d(&Tensor::concat(&[&indices2, &indices2], 1), &[16, 16]);
-        let policy = self.upconv2.forward_t(&policy, is_training); // 10x16x16
+        let policy = policy.max_unpool2d(&Tensor::concat(&[&indices2, &indices2], 1), &[s1, s1]);
+        let policy = self.upconv2.forward_t(&policy, is_training); // 10xS1xS1
         let policy = policy.relu();
 
         let policy = self.bn_upconv1.forward_t(&policy, is_training);
         let policy = Tensor::concat(&[policy, layer1], 1);
-        let policy = self.upconv1.forward_t(&policy, is_training); // 5x19x19
+        let policy = self.upconv1.forward_t(&policy, is_training); // 5xNxN
         let policy = policy.relu();
 
         let policy = self.bn_conv_final.forward_t(&policy, is_training);
         let policy = Tensor::concat(&[&policy, xs], 1);
         let policy = self.conv_final.forward_t(&policy, is_training);
-        assert_eq!(policy.size()[1..], [1, 19, 19]);
+        assert_eq!(policy.size()[1..], [1, self.size, self.size]);
         let policy = policy
             .view([policy.size()[0], -1])
             .log_softmax(1, None)
-            .view([policy.size()[0], 19, 19]);
+            .view([policy.size()[0], self.size, self.size]);
 
-        (val, policy)
+        NetOutput::new(val, policy)
     }
 }
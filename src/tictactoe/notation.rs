@@ -0,0 +1,174 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::Context;
+
+use crate::alpha_zero::Game;
+
+use super::{CellState, GomokuBoard, TicTacToeMove};
+
+// A row per line, `X` for the player to move, `O` for the other one and `.` for empty cells
+impl<const N: usize, const K: usize> fmt::Display for GomokuBoard<N, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in 0..N {
+            let row = (0..N)
+                .map(|j| match self[(i, j)] {
+                    CellState::X => 'X',
+                    CellState::O => 'O',
+                    CellState::Empty => '.',
+                })
+                .collect::<String>();
+            writeln!(f, "{row}")?;
+        }
+        Ok(())
+    }
+}
+
+// The grid, so that failed assertions show the position
+impl<const N: usize, const K: usize> fmt::Debug for GomokuBoard<N, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\n{self}")
+    }
+}
+
+// Like `Display` writes them. Blank lines and whitespace within lines are skipped, so that
+// indented positions can be pasted into tests.
+impl<const N: usize, const K: usize> FromStr for GomokuBoard<N, K> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut board = Self::new();
+        let rows = s
+            .lines()
+            .map(|line| line.split_whitespace().collect::<String>())
+            .filter(|row| !row.is_empty())
+            .collect::<Vec<_>>();
+        anyhow::ensure!(rows.len() == N, "Expected {N} rows, got {}", rows.len());
+        for (i, row) in rows.iter().enumerate() {
+            anyhow::ensure!(
+                row.chars().count() == N,
+                "Expected {N} cells in row {}, got {row}",
+                i + 1
+            );
+            for (j, c) in row.chars().enumerate() {
+                let state = match c {
+                    'X' | 'x' => CellState::X,
+                    'O' | 'o' => CellState::O,
+                    '.' => CellState::Empty,
+                    c => anyhow::bail!("Invalid cell {c} in row {}", i + 1),
+                };
+                board.set_inplace((i, j), state);
+            }
+        }
+        Ok(board)
+    }
+}
+
+impl<const N: usize, const K: usize> GomokuBoard<N, K> {
+    // The position after `moves` from the empty board, in the notation of `format_moves`
+    pub fn from_moves(moves: &str) -> anyhow::Result<Self> {
+        let mut board = Self::new();
+        for m in parse_moves::<N>(moves)? {
+            anyhow::ensure!(
+                board.get_state().get_moves().is_some() && board.is_legal(&m),
+                "Illegal move {}",
+                format_moves::<N>(&[m])
+            );
+            board = board.make_move(&m);
+        }
+        Ok(board)
+    }
+}
+
+// Moves as a column letter from `a` and a row number from 1, without separators, like
+// `h8i9g7` for the moves at (7, 7), (8, 8) and (6, 6)
+pub fn format_moves<const N: usize>(moves: &[TicTacToeMove]) -> String {
+    const { assert!(N <= 26, "Columns are written as single letters") };
+    moves
+        .iter()
+        .map(|&TicTacToeMove(row, column)| format!("{}{}", (b'a' + column as u8) as char, row + 1))
+        .collect()
+}
+
+// Like `format_moves` writes them, whitespace between moves being allowed
+pub fn parse_moves<const N: usize>(text: &str) -> anyhow::Result<Vec<TicTacToeMove>> {
+    const { assert!(N <= 26, "Columns are written as single letters") };
+    let mut moves = vec![];
+    let mut chars = text.chars().filter(|c| !c.is_whitespace()).peekable();
+    while let Some(letter) = chars.next() {
+        let column = match letter {
+            'a'..='z' => letter as usize - 'a' as usize,
+            _ => anyhow::bail!("Expected a column letter, got {letter}"),
+        };
+        let mut digits = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            digits.push(digit);
+        }
+        let row = digits
+            .parse::<usize>()
+            .ok()
+            .filter(|row| (1..=N).contains(row))
+            .with_context(|| format!("Invalid row {digits} after column {letter}"))?;
+        anyhow::ensure!(column < N, "Column {letter} is off the board");
+        moves.push(TicTacToeMove(row - 1, column));
+    }
+    Ok(moves)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{Game, TerminationState},
+        tictactoe::{BoardState, CellState, GomokuBoard, TicTacToeMove},
+    };
+
+    use super::{format_moves, parse_moves};
+
+    #[test]
+    fn grid() {
+        let board = "
+            X . . . .
+            . O . . .
+            . . X . .
+            . . . . .
+            . . . . O
+        "
+        .parse::<GomokuBoard<5, 4>>()
+        .unwrap();
+        assert_eq!(board[(1, 1)], CellState::O);
+        assert_eq!(board[(2, 2)], CellState::X);
+        assert_eq!(board.to_string(), "X....\n.O...\n..X..\n.....\n....O\n");
+        assert_eq!(
+            board.to_string().parse::<GomokuBoard<5, 4>>().unwrap(),
+            board
+        );
+        assert_eq!(format!("{board:?}"), format!("\n{board}"));
+
+        assert!("X....\n".parse::<GomokuBoard<5, 4>>().is_err());
+        assert!("X...\n".repeat(5).parse::<GomokuBoard<5, 4>>().is_err());
+        assert!("X...Y\n".repeat(5).parse::<GomokuBoard<5, 4>>().is_err());
+    }
+
+    #[test]
+    fn move_lists() {
+        let moves = [
+            TicTacToeMove(7, 7),
+            TicTacToeMove(8, 8),
+            TicTacToeMove(14, 0),
+        ];
+        assert_eq!(format_moves::<15>(&moves), "h8i9a15");
+        assert_eq!(parse_moves::<15>("h8i9a15").unwrap(), moves);
+        assert_eq!(parse_moves::<15>(" h8 i9\na15 ").unwrap(), moves);
+        assert!(parse_moves::<15>("h0").is_err());
+        assert!(parse_moves::<15>("h16").is_err());
+        assert!(parse_moves::<15>("p1").is_err());
+        assert!(parse_moves::<15>("8h").is_err());
+        assert!(parse_moves::<15>("h").is_err());
+
+        // X wins on the diagonal, O having played along the last row
+        let board = BoardState::from_moves("a1a19b2b19c3c19d4d19e5").unwrap();
+        assert_eq!(board.get_state(), TerminationState::Terminal(0.0));
+        assert_eq!(board[(4, 4)], CellState::O);
+        assert!(BoardState::from_moves("a1a1").is_err());
+        assert!(BoardState::from_moves("a1a19b2b19c3c19d4d19e5f6").is_err());
+    }
+}
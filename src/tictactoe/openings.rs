@@ -0,0 +1,19 @@
+use crate::alpha_zero::OpeningBook;
+
+use super::{GomokuBoard, TicTacToeMove};
+
+// Single-stone openings within `radius` of the center, so self-play doesn't
+// always start from the same position
+pub fn gomoku_opening_book<const N: usize, const K: usize>(
+    radius: usize,
+) -> OpeningBook<GomokuBoard<N, K>> {
+    let center = N / 2;
+    assert!(radius <= center, "Radius {radius} doesn't fit the board");
+    let cells = center - radius..=center + radius;
+    OpeningBook::new(
+        cells
+            .clone()
+            .flat_map(|i| cells.clone().map(move |j| (vec![TicTacToeMove(i, j)], 1.0)))
+            .collect(),
+    )
+}
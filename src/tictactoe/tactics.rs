@@ -0,0 +1,10 @@
+use crate::alpha_zero::PositionSuite;
+
+use super::GomokuBoard;
+
+// Five-in-a-row tactics, all within the first 15 rows and columns so that every board size
+// from 15 up can use them
+pub fn gomoku_tactics<const N: usize>() -> PositionSuite<GomokuBoard<N, 5>> {
+    PositionSuite::parse(&GomokuBoard::new(), include_str!("gomoku_tactics.txt"))
+        .expect("The built-in tactics are valid")
+}
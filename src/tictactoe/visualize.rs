@@ -1,64 +1,53 @@
-use image::{math::Rect, ImageBuffer, Pixel, Rgb};
+use std::fmt::Write;
 
-use crate::tictactoe::CellState;
+use image::{math::Rect, Delay, Frame, ImageBuffer, Pixel, Rgb, RgbImage, Rgba, RgbaImage};
 
-use super::BoardState;
+use crate::{
+    alpha_zero::{Game, GameVisualizer, Perspective, SelfPlaySample},
+    tictactoe::CellState,
+};
 
-pub fn generate_game_image(
-    history: &[(BoardState, Vec<f32>, f32)],
-) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    let square = 10;
-    let fld = 19 * square;
-    let line = 5;
-    let mut img = image::RgbImage::new(
-        fld * history.len() as u32 + line * (history.len() as u32 - 1),
-        fld,
-    );
+use super::{GomokuBoard, TicTacToeMove};
 
-    let width = img.width();
-    let height = img.height();
-    fn draw_rect(img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, r: Rect, pixel: Rgb<f32>) {
-        for i in r.x..r.x + r.width {
-            for j in r.y..r.y + r.height {
-                img.put_pixel(i, j, Rgb(pixel.0.map(|v| v as u8)));
-            }
-        }
-    }
+// Boards as 10px cells, `X` in red, `O` in blue and the policy as green on empty cells, and
+// `generate_game_animation` and `generate_game_svg` for whole games
+#[derive(Clone, Copy, Default)]
+pub struct GomokuVisualizer;
 
-    draw_rect(
-        &mut img,
-        Rect {
-            x: 0,
-            y: 0,
-            width,
-            height,
-        },
-        Rgb([255., 255., 255.]),
-    );
+impl<const N: usize, const K: usize> GameVisualizer<GomokuBoard<N, K>> for GomokuVisualizer {
+    fn render_position(&self, state: &GomokuBoard<N, K>, policy: Option<&[f32]>) -> RgbImage {
+        let square = 10;
+        let fld = N as u32 * square;
+        let mut img = RgbImage::new(fld, fld);
 
-    for (i, (state, pol, _)) in history.iter().enumerate() {
-        let x = i as u32 * (fld + line);
+        fn draw_rect(img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, r: Rect, pixel: Rgb<f32>) {
+            for i in r.x..r.x + r.width {
+                for j in r.y..r.y + r.height {
+                    img.put_pixel(i, j, Rgb(pixel.0.map(|v| v as u8)));
+                }
+            }
+        }
 
-        let mut pol = pol.iter().copied();
+        let mut pol = policy.unwrap_or_default().iter().copied();
         let x_clr = Rgb([255., 0., 0.]);
         let o_clr = Rgb([0., 0., 255.]);
         let policy = Rgb([0., 255., 0.]);
 
-        for i in 0..19 {
-            for j in 0..19 {
+        for i in 0..N as u32 {
+            for j in 0..N as u32 {
                 let clr = match state[(i as usize, j as usize)] {
                     CellState::X => x_clr,
                     CellState::O => o_clr,
                     CellState::Empty => {
-                        let p = pol.next().unwrap();
+                        let p = pol.next().unwrap_or(0.0);
                         policy.map(|x| p * x)
                     }
                 };
                 draw_rect(
                     &mut img,
                     Rect {
-                        x: x + i * square,
-                        y: 0 + j * square,
+                        x: i * square,
+                        y: j * square,
                         width: square,
                         height: square,
                     },
@@ -66,7 +55,417 @@ pub fn generate_game_image(
                 );
             }
         }
+
+        img
+    }
+
+    fn animate(&self, history: &[SelfPlaySample<GomokuBoard<N, K>>]) -> Option<Vec<Frame>> {
+        Some(generate_game_animation(history))
+    }
+
+    fn render_svg(
+        &self,
+        history: &[SelfPlaySample<GomokuBoard<N, K>>],
+        position: usize,
+    ) -> Option<String> {
+        Some(generate_game_svg(history, position))
+    }
+
+    fn render_maps_svg(
+        &self,
+        state: &GomokuBoard<N, K>,
+        maps: &[(&str, Vec<f32>)],
+    ) -> Option<String> {
+        Some(generate_maps_svg(state, maps))
+    }
+}
+
+// Cells of the animation, larger than the strip's so that single stones can be followed
+const FRAME_SQUARE: u32 = 20;
+// Frames the policy fades in over before every move
+const FADE_FRAMES: u32 = 4;
+const FADE_DELAY_MS: u32 = 80;
+const MOVE_DELAY_MS: u32 = 600;
+
+// One position of an animation, the first player's stones in red whoever is to move. `policy`
+// is over the position's moves with the intensity it has faded in to, and shown relative to
+// its largest probability so that it stays visible on large boards.
+fn draw_frame<const N: usize, const K: usize>(
+    state: &GomokuBoard<N, K>,
+    policy: Option<(&[f32], f32)>,
+    last: Option<TicTacToeMove>,
+    delay_ms: u32,
+) -> Frame {
+    let side = N as u32 * FRAME_SQUARE + 1;
+    let mut img = RgbaImage::from_pixel(side, side, Rgba([128, 128, 128, 255]));
+    let mut fill = |TicTacToeMove(row, column): TicTacToeMove, inset: u32, color: Rgba<u8>| {
+        let (x, y) = (column as u32 * FRAME_SQUARE, row as u32 * FRAME_SQUARE);
+        for i in x + 1 + inset..x + FRAME_SQUARE - inset {
+            for j in y + 1 + inset..y + FRAME_SQUARE - inset {
+                img.put_pixel(i, j, color);
+            }
+        }
+    };
+
+    let (first, second) = (Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255]));
+    let (x_clr, o_clr) = match state.is_first_player_to_move() {
+        true => (first, second),
+        false => (second, first),
+    };
+    if let Some(m) = last {
+        fill(m, 0, Rgba([255, 255, 0, 255]));
+    }
+    let mut heat = vec![0.0; N * N];
+    if let (Some((policy, intensity)), Some(moves)) = (policy, state.get_state().get_moves()) {
+        let max = policy.iter().copied().fold(f32::MIN_POSITIVE, f32::max);
+        for (TicTacToeMove(row, column), p) in moves.into_iter().zip(policy) {
+            heat[row * N + column] = intensity * p / max;
+        }
+    }
+    for row in 0..N {
+        for column in 0..N {
+            let m = TicTacToeMove(row, column);
+            // The last move's stone is inset to leave its highlight around it
+            let inset = match last == Some(m) {
+                true => 2,
+                false => 0,
+            };
+            match state[(row, column)] {
+                CellState::X => fill(m, inset, x_clr),
+                CellState::O => fill(m, inset, o_clr),
+                CellState::Empty => {
+                    let green = (255.0 * heat[row * N + column]) as u8;
+                    fill(m, 0, Rgba([255 - green, 255, 255 - green, 255]))
+                }
+            }
+        }
+    }
+    Frame::from_parts(img, 0, 0, Delay::from_numer_denom_ms(delay_ms, 1))
+}
+
+// The game move by move, the search's policy fading in before every move is made, for
+// `save_animation`. Ends with the position after the last move.
+pub fn generate_game_animation<const N: usize, const K: usize>(
+    history: &[SelfPlaySample<GomokuBoard<N, K>>],
+) -> Vec<Frame> {
+    let mut frames = vec![];
+    let mut last = None;
+    for sample in history {
+        for step in 1..=FADE_FRAMES {
+            let intensity = step as f32 / FADE_FRAMES as f32;
+            frames.push(draw_frame(
+                &sample.state,
+                Some((&sample.policy, intensity)),
+                last,
+                FADE_DELAY_MS,
+            ));
+        }
+        let moves = sample
+            .state
+            .get_state()
+            .get_moves()
+            .expect("A sample of a finished game");
+        last = Some(moves[sample.played]);
+    }
+    if let (Some(sample), Some(m)) = (history.last(), last) {
+        frames.push(draw_frame(
+            &sample.state.make_move(&m),
+            None,
+            last,
+            MOVE_DELAY_MS,
+        ));
+    }
+    frames
+}
+
+// Between lines of the SVG board, its margin holding the coordinates
+const SVG_CELL: usize = 30;
+const SVG_MARGIN: usize = 30;
+const SVG_GRAPH_HEIGHT: usize = 120;
+
+fn svg_point(x: usize) -> usize {
+    SVG_MARGIN + x * SVG_CELL
+}
+
+// The board with its lines and coordinates, at the origin
+fn write_svg_grid<const N: usize>(svg: &mut String) {
+    let board = 2 * SVG_MARGIN + (N - 1) * SVG_CELL;
+    let _ = writeln!(
+        svg,
+        r##"<rect width="{board}" height="{board}" fill="#dcb35c"/>"##
+    );
+    for i in 0..N {
+        let (from, to) = (svg_point(0), svg_point(N - 1));
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{from}" y1="{y}" x2="{to}" y2="{y}" stroke="black"/><line x1="{x}" y1="{from}" x2="{x}" y2="{to}" stroke="black"/>"#,
+            x = svg_point(i),
+            y = svg_point(i)
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle">{}</text><text x="{}" y="{}" text-anchor="end">{}</text>"#,
+            svg_point(i),
+            SVG_MARGIN / 2,
+            (b'a' + i as u8) as char,
+            SVG_MARGIN / 2 + 5,
+            svg_point(i) + 4,
+            i + 1
+        );
+    }
+}
+
+// The position once per map, side by side under the maps' names, each map being over the
+// position's moves and drawn as the opacity of green dots. The first player's stones are
+// black.
+pub fn generate_maps_svg<const N: usize, const K: usize>(
+    state: &GomokuBoard<N, K>,
+    maps: &[(&str, Vec<f32>)],
+) -> String {
+    let board = 2 * SVG_MARGIN + (N - 1) * SVG_CELL;
+    let radius = SVG_CELL / 2 - 1;
+    let (to_move, other) = match state.is_first_player_to_move() {
+        true => ("black", "white"),
+        false => ("white", "black"),
+    };
+    let moves = state.get_state().get_moves().unwrap_or_default();
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="12">"#,
+        maps.len() * (board + SVG_MARGIN),
+        board + SVG_MARGIN
+    );
+    for (i, (name, map)) in maps.iter().enumerate() {
+        let left = i * (board + SVG_MARGIN);
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle" font-size="16">{name}</text>"#,
+            left + board / 2,
+            SVG_MARGIN / 2 + 5
+        );
+        let _ = writeln!(svg, r#"<g transform="translate({left},{SVG_MARGIN})">"#);
+        write_svg_grid::<N>(&mut svg);
+        for row in 0..N {
+            for column in 0..N {
+                let fill = match state[(row, column)] {
+                    CellState::X => to_move,
+                    CellState::O => other,
+                    CellState::Empty => continue,
+                };
+                let _ = writeln!(
+                    svg,
+                    r#"<circle class="stone" cx="{}" cy="{}" r="{radius}" fill="{fill}" stroke="black"/>"#,
+                    svg_point(column),
+                    svg_point(row)
+                );
+            }
+        }
+        for (&TicTacToeMove(row, column), value) in moves.iter().zip(map) {
+            let _ = writeln!(
+                svg,
+                r#"<circle class="heat" cx="{}" cy="{}" r="{}" fill="green" fill-opacity="{:.3}"/>"#,
+                svg_point(column),
+                svg_point(row),
+                radius - 2,
+                value.clamp(0.0, 1.0)
+            );
+        }
+        svg += "</g>\n";
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+// The position before move `position` of the game, or after the last one for
+// `history.len()`, as an SVG: stones numbered by their move on a grid with the coordinates of
+// `format_moves`, the position's policy as the opacity of green dots, and under the board the
+// first player's value by the search before every move, with a marker at the position.
+pub fn generate_game_svg<const N: usize, const K: usize>(
+    history: &[SelfPlaySample<GomokuBoard<N, K>>],
+    position: usize,
+) -> String {
+    assert!(position <= history.len(), "No position {position}");
+    let point = svg_point;
+    let board = 2 * SVG_MARGIN + (N - 1) * SVG_CELL;
+    let (graph_top, legend) = (board + 10, board + SVG_GRAPH_HEIGHT + 30);
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{board}" height="{}" font-family="sans-serif" font-size="12">"#,
+        legend + 20
+    );
+    write_svg_grid::<N>(&mut svg);
+
+    let radius = SVG_CELL / 2 - 1;
+    let mut player = Perspective::Same;
+    for (number, sample) in history[..position].iter().enumerate() {
+        let moves = sample
+            .state
+            .get_state()
+            .get_moves()
+            .expect("A sample of a finished game");
+        let TicTacToeMove(row, column) = moves[sample.played];
+        let (fill, text) = match player {
+            Perspective::Same => ("black", "white"),
+            Perspective::Opponent => ("white", "black"),
+        };
+        let (x, y) = (point(column), point(row));
+        let _ = writeln!(
+            svg,
+            r#"<circle class="stone" cx="{x}" cy="{y}" r="{radius}" fill="{fill}" stroke="black"/><text x="{x}" y="{}" text-anchor="middle" fill="{text}">{}</text>"#,
+            y + 4,
+            number + 1
+        );
+        player = player.then(Perspective::after_move(&moves[sample.played]));
+    }
+    if let Some(sample) = history.get(position) {
+        let moves = sample.state.get_state().get_moves().unwrap_or_default();
+        let max = sample
+            .policy
+            .iter()
+            .copied()
+            .fold(f32::MIN_POSITIVE, f32::max);
+        for (TicTacToeMove(row, column), p) in moves.into_iter().zip(&sample.policy) {
+            let _ = writeln!(
+                svg,
+                r#"<circle class="policy" cx="{}" cy="{}" r="{}" fill="green" fill-opacity="{:.3}"/>"#,
+                point(column),
+                point(row),
+                radius / 2,
+                p / max
+            );
+        }
     }
 
-    img
+    // The value graph, from 1 at the top to 0 at the bottom
+    let (left, right) = (point(0), point(N - 1));
+    let y = |value: f32| graph_top + ((1.0 - value) * SVG_GRAPH_HEIGHT as f32) as usize;
+    let x = |i: usize| left + i * (right - left) / history.len().saturating_sub(1).max(1);
+    let _ = writeln!(
+        svg,
+        r#"<rect x="{left}" y="{graph_top}" width="{}" height="{SVG_GRAPH_HEIGHT}" fill="none" stroke="gray"/><line x1="{left}" y1="{mid}" x2="{right}" y2="{mid}" stroke="gray" stroke-dasharray="4"/>"#,
+        right - left,
+        mid = y(0.5)
+    );
+    for (label, value) in [("1", 1.0), ("0.5", 0.5), ("0", 0.0)] {
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="end">{label}</text>"#,
+            left - 4,
+            y(value) + 4
+        );
+    }
+    let points = history
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| format!("{},{}", x(i), y(sample.player.convert(sample.root_q))))
+        .collect::<Vec<_>>();
+    let _ = writeln!(
+        svg,
+        r#"<polyline points="{}" fill="none" stroke="black" stroke-width="2"/>"#,
+        points.join(" ")
+    );
+    if position < history.len() {
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{0}" y1="{graph_top}" x2="{0}" y2="{1}" stroke="red"/>"#,
+            x(position),
+            y(0.0)
+        );
+    }
+    let _ = writeln!(
+        svg,
+        r#"<text x="{left}" y="{legend}">Value for the first player (black) by move, policy in green</text>"#
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::{uniform_game, Game, SelfPlaySample},
+        tictactoe::{GomokuBoard, TicTacToeMove},
+    };
+
+    use super::{
+        generate_game_animation, generate_game_svg, generate_maps_svg, FADE_FRAMES, FRAME_SQUARE,
+    };
+
+    // Uniform policies, the search giving the first player 0.75 before every move
+    fn game(moves: &[TicTacToeMove]) -> Vec<SelfPlaySample<GomokuBoard<5, 4>>> {
+        uniform_game(GomokuBoard::new(), moves)
+            .into_iter()
+            .map(|sample| SelfPlaySample {
+                root_q: sample.player.convert(0.75),
+                ..sample
+            })
+            .collect()
+    }
+
+    #[test]
+    fn animation_frames() {
+        let history = game(&[TicTacToeMove(2, 2), TicTacToeMove(0, 0)]);
+        let frames = generate_game_animation(&history);
+        assert_eq!(frames.len(), 2 * FADE_FRAMES as usize + 1);
+
+        // The first player's stone stays red after the second player's move, which is the
+        // last one and highlighted
+        let last = frames.last().unwrap().buffer();
+        let center = |row: u32, column: u32| {
+            last.get_pixel(
+                column * FRAME_SQUARE + FRAME_SQUARE / 2,
+                row * FRAME_SQUARE + FRAME_SQUARE / 2,
+            )
+            .0
+        };
+        assert_eq!(center(2, 2), [255, 0, 0, 255]);
+        assert_eq!(center(0, 0), [0, 0, 255, 255]);
+        assert_eq!(last.get_pixel(2, 2).0, [255, 255, 0, 255]);
+        // The policy is fully faded in on the frame before the move
+        let faded = frames[2 * FADE_FRAMES as usize - 1].buffer();
+        assert_eq!(
+            faded.get_pixel(FRAME_SQUARE / 2, FRAME_SQUARE / 2).0,
+            [0, 255, 0, 255]
+        );
+    }
+
+    #[test]
+    fn svg_positions() {
+        let history = game(&[
+            TicTacToeMove(2, 2),
+            TicTacToeMove(0, 0),
+            TicTacToeMove(1, 3),
+        ]);
+        let svg = generate_game_svg(&history, 2);
+        assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches(r#"class="stone""#).count(), 2);
+        // The first player's stone in the center, the second's in the corner
+        assert!(svg.contains(r#"cx="90" cy="90" r="14" fill="black""#));
+        assert!(svg.contains(r#"cx="30" cy="30" r="14" fill="white""#));
+        assert!(svg.contains(">2</text>"));
+        assert_eq!(svg.matches(r#"class="policy""#).count(), 23);
+        assert!(svg.contains(r#"fill-opacity="1.000""#));
+        // The first player's 0.75 a quarter down the graph
+        assert!(svg.contains(r#"<polyline points="30,220 90,220 150,220""#));
+
+        let last = generate_game_svg(&history, 3);
+        assert_eq!(last.matches(r#"class="stone""#).count(), 3);
+        assert_eq!(last.matches(r#"class="policy""#).count(), 0);
+    }
+
+    #[test]
+    fn maps_side_by_side() {
+        // The second player is to move after the first took the center
+        let state = GomokuBoard::<5, 4>::new().make_move(&TicTacToeMove(2, 2));
+        let maps = [("Prior", vec![1.0; 24]), ("Q", vec![0.25; 24])];
+        let svg = generate_maps_svg(&state, &maps);
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="420" "#));
+        assert!(svg.contains(">Prior</text>") && svg.contains(">Q</text>"));
+        assert!(svg.contains(r#"<g transform="translate(210,30)">"#));
+        assert_eq!(svg.matches(r#"cy="90" r="14" fill="black""#).count(), 2);
+        assert_eq!(svg.matches(r#"class="heat""#).count(), 48);
+        assert_eq!(svg.matches(r#"fill-opacity="0.250""#).count(), 24);
+    }
 }
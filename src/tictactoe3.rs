@@ -0,0 +1,132 @@
+#[cfg(feature = "torch")]
+mod alpha_zero_adapter;
+mod board;
+mod minimax;
+#[cfg(feature = "torch")]
+mod nn;
+
+#[cfg(feature = "torch")]
+pub use alpha_zero_adapter::*;
+pub use board::*;
+pub use minimax::*;
+#[cfg(feature = "torch")]
+pub use nn::*;
+
+#[cfg(all(test, feature = "torch"))]
+mod tests {
+    use std::time::Duration;
+
+    use tch::{
+        nn::{self, OptimizerConfig},
+        Device, Kind, Tensor,
+    };
+
+    use crate::alpha_zero::{
+        generate_self_played_game, Agent, AlphaZeroAdapter, AlphaZeroNet, ExecutorScope, Game,
+        MctsAgent, NetOutput, NetworkEvaluator, Perspective, Seed, TerminationState,
+    };
+
+    use super::{TicTacToe3, TicTacToe3AlphaZeroAdapter, TicTacToe3Net, TicTacToe3Solver};
+
+    type Adapter = TicTacToe3AlphaZeroAdapter;
+
+    // Walks every line of play where the oracle picks any of its optimal moves,
+    // returning the number of games the agent lost. `side` is the agent's relative to the
+    // player to move.
+    async fn count_losses<A: Agent<TicTacToe3>>(
+        agent: &mut A,
+        solver: &mut TicTacToe3Solver,
+        state: TicTacToe3,
+        side: Perspective,
+    ) -> usize {
+        let moves = match state.get_state() {
+            TerminationState::Terminal(v) => return (side.convert(v) < 0.5) as usize,
+            TerminationState::Moves(moves) => moves,
+        };
+
+        let replies = match side {
+            Perspective::Same => vec![moves[agent.select_move(&state).await]],
+            Perspective::Opponent => solver.optimal_moves(&state),
+        };
+        let mut losses = 0;
+        for m in replies {
+            let side = side.then(Perspective::after_move(&m));
+            losses += Box::pin(count_losses(agent, solver, state.make_move(&m), side)).await;
+        }
+        losses
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "trains a network for a few minutes"]
+    async fn trained_net_never_loses_to_perfect_play() {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let mut net = TicTacToe3Net::new(&vs.root());
+        let mut opt = nn::Adam::default().build(&vs, 1e-3).unwrap();
+        let options = (Kind::Float, Device::Cpu);
+
+        for _generation in 0..30 {
+            let mut executor = ExecutorScope::new(net, 64, 64, Duration::from_millis(1), options);
+            for _ in 0..128 {
+                executor.spawn(|handle| {
+                    generate_self_played_game::<TicTacToe3, TicTacToe3Net, Adapter, _>(
+                        TicTacToe3::new(),
+                        None,
+                        64,
+                        1.5,
+                        |turn| if turn < 3 { 1.0 } else { 0.3 },
+                        Seed::random(),
+                        handle,
+                        None,
+                    )
+                });
+            }
+            let mut samples = vec![];
+            while let Some(game) = executor.next().await {
+                samples.extend(game);
+            }
+            net = executor.join().await;
+
+            let states = samples.iter().map(|s| s.state).collect::<Vec<_>>();
+            let moves = states
+                .iter()
+                .map(|s| s.get_state().get_moves().unwrap())
+                .collect::<Vec<_>>();
+            let policies = samples.iter().map(|s| s.policy.clone()).collect::<Vec<_>>();
+            let values = samples.iter().map(|s| s.value).collect::<Vec<_>>();
+
+            let states = Adapter::convert_games_to_nn_input(&states, options);
+            let policies = Adapter::convert_policies_to_nn(&policies, &moves, options);
+            let values = Tensor::from_slice(&values);
+
+            for _ in 0..20 {
+                let NetOutput {
+                    value: exp_values,
+                    policy: exp_policies,
+                    ..
+                } = net.forward_t(&states, true);
+                let batch = values.size()[0] as f64;
+                let val_loss = (exp_values - &values).square().mean(None);
+                let pol_loss = -(&policies * exp_policies).sum(None) / batch;
+                opt.backward_step(&(val_loss + pol_loss));
+            }
+        }
+
+        let mut executor = ExecutorScope::new(net, 1, 1, Duration::from_millis(1), options);
+        executor.spawn(|handle| async move {
+            let mut agent = MctsAgent::new(
+                NetworkEvaluator::<_, _, Adapter>::new(handle),
+                128,
+                1.5,
+                0.0,
+            );
+            let mut solver = TicTacToe3Solver::new();
+            let start = TicTacToe3::new();
+            count_losses(&mut agent, &mut solver, start, Perspective::Same).await
+                + count_losses(&mut agent, &mut solver, start, Perspective::Opponent).await
+        });
+        let losses = executor.next().await.unwrap();
+        executor.join().await;
+
+        assert_eq!(losses, 0);
+    }
+}
@@ -0,0 +1,58 @@
+use tch::Tensor;
+
+use crate::{
+    alpha_zero::{ActionEncoding, AlphaZeroAdapter, SymmetryTransform},
+    tictactoe::CellState,
+};
+
+use super::{TicTacToe3, TicTacToe3Move, TicTacToe3Net};
+
+pub struct TicTacToe3AlphaZeroAdapter;
+
+impl ActionEncoding<TicTacToe3> for TicTacToe3AlphaZeroAdapter {
+    fn action_space_size() -> usize {
+        9
+    }
+
+    fn move_to_index(&TicTacToe3Move(i): &TicTacToe3Move) -> usize {
+        i
+    }
+
+    fn index_to_move(index: usize) -> TicTacToe3Move {
+        TicTacToe3Move(index)
+    }
+
+    fn policy_shape() -> Vec<i64> {
+        vec![3, 3]
+    }
+}
+
+impl AlphaZeroAdapter<TicTacToe3, TicTacToe3Net> for TicTacToe3AlphaZeroAdapter {
+    fn symmetries() -> Vec<SymmetryTransform> {
+        SymmetryTransform::dihedral_group()
+    }
+
+    fn input_planes() -> Vec<String> {
+        vec!["to move".to_owned(), "opponent".to_owned()]
+    }
+
+    fn convert_game_to_nn_input(state: &TicTacToe3) -> Tensor {
+        let mut fld = [0f32; 2 * 9];
+        for i in 0..9 {
+            match state[i] {
+                CellState::X => fld[i] = 1.,
+                CellState::O => fld[9 + i] = 1.,
+                CellState::Empty => {}
+            }
+        }
+        Tensor::from_slice(&fld).view([2, 3, 3])
+    }
+
+    fn get_estimated_policy(policy: &Tensor, moves: &[TicTacToe3Move]) -> Vec<f32> {
+        Self::decode_policy(policy, moves)
+    }
+
+    fn convert_policy_to_nn(policy: &[f32], moves: &[TicTacToe3Move]) -> Tensor {
+        Self::encode_policy(policy, moves)
+    }
+}
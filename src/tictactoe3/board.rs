@@ -0,0 +1,132 @@
+use std::ops::Index;
+
+use crate::{
+    alpha_zero::{Game, MoveNotation, MoveParameters, ReversibleGame, TerminationState},
+    tictactoe::CellState,
+};
+
+// Classic 3x3 tic-tac-toe. Like the gomoku board, the player to move is always X.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub struct TicTacToe3 {
+    cells: [CellState; 9],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TicTacToe3Move(pub usize);
+
+impl MoveParameters for TicTacToe3Move {
+    fn is_player_switch(&self) -> bool {
+        true
+    }
+}
+
+impl Default for TicTacToe3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TicTacToe3 {
+    const LINES: [[usize; 3]; 8] = [
+        [0, 1, 2],
+        [3, 4, 5],
+        [6, 7, 8],
+        [0, 3, 6],
+        [1, 4, 7],
+        [2, 5, 8],
+        [0, 4, 8],
+        [2, 4, 6],
+    ];
+
+    pub fn new() -> Self {
+        Self {
+            cells: [CellState::Empty; 9],
+        }
+    }
+
+    pub fn set(mut self, idx: usize, state: CellState) -> Self {
+        self.cells[idx] = state;
+        self
+    }
+
+    pub fn flip_players(mut self) -> Self {
+        for cell in &mut self.cells {
+            *cell = match *cell {
+                CellState::Empty => CellState::Empty,
+                CellState::X => CellState::O,
+                CellState::O => CellState::X,
+            };
+        }
+        self
+    }
+
+    pub fn is_win(&self) -> CellState {
+        for [a, b, c] in Self::LINES {
+            let goal = self.cells[a];
+            if goal != CellState::Empty && self.cells[b] == goal && self.cells[c] == goal {
+                return goal;
+            }
+        }
+        CellState::Empty
+    }
+}
+
+impl Game for TicTacToe3 {
+    type Move = TicTacToe3Move;
+
+    fn get_state(&self) -> TerminationState<Self::Move> {
+        match self.is_win() {
+            CellState::X => return TerminationState::Terminal(1.),
+            CellState::O => return TerminationState::Terminal(0.),
+            CellState::Empty => {}
+        }
+
+        let moves = (0..9)
+            .filter(|&i| self.cells[i] == CellState::Empty)
+            .map(TicTacToe3Move)
+            .collect::<Vec<_>>();
+
+        if moves.is_empty() {
+            TerminationState::Terminal(0.5)
+        } else {
+            TerminationState::Moves(moves)
+        }
+    }
+
+    fn make_move(&self, &TicTacToe3Move(i): &Self::Move) -> Self {
+        self.set(i, CellState::X).flip_players()
+    }
+
+    fn is_legal(&self, &TicTacToe3Move(i): &Self::Move) -> bool {
+        i < 9 && self.cells[i] == CellState::Empty && self.is_win() == CellState::Empty
+    }
+}
+
+// Cells are numbered row by row from 0 to 8
+impl MoveNotation for TicTacToe3 {
+    fn parse_move(&self, text: &str) -> anyhow::Result<Self::Move> {
+        match text.parse::<usize>() {
+            Ok(cell) if cell < 9 => Ok(TicTacToe3Move(cell)),
+            _ => anyhow::bail!("Expected a cell from 0 to 8, got {text}"),
+        }
+    }
+
+    fn format_move(&self, &TicTacToe3Move(cell): &Self::Move) -> String {
+        cell.to_string()
+    }
+}
+
+impl ReversibleGame for TicTacToe3 {
+    fn undo_move(&self, &TicTacToe3Move(i): &Self::Move) -> Self {
+        assert_eq!(self.cells[i], CellState::O);
+        self.set(i, CellState::Empty).flip_players()
+    }
+}
+
+impl Index<usize> for TicTacToe3 {
+    type Output = CellState;
+
+    fn index(&self, idx: usize) -> &Self::Output {
+        &self.cells[idx]
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use crate::alpha_zero::{Game, Perspective, Solver, TerminationState};
+
+use super::{TicTacToe3, TicTacToe3Move};
+
+// Exhaustive solver, used as a perfect-play oracle in tests and evaluation
+#[derive(Default)]
+pub struct TicTacToe3Solver {
+    cache: HashMap<TicTacToe3, f32>,
+}
+
+impl TicTacToe3Solver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Game-theoretic value for the player to move
+    pub fn value(&mut self, state: &TicTacToe3) -> f32 {
+        if let Some(&v) = self.cache.get(state) {
+            return v;
+        }
+        let value = match state.get_state() {
+            TerminationState::Terminal(v) => v,
+            TerminationState::Moves(moves) => moves
+                .iter()
+                .map(|m| Perspective::after_move(m).convert(self.value(&state.make_move(m))))
+                .fold(0.0, f32::max),
+        };
+        self.cache.insert(*state, value);
+        value
+    }
+
+    // All moves achieving the game-theoretic value
+    pub fn optimal_moves(&mut self, state: &TicTacToe3) -> Vec<TicTacToe3Move> {
+        let best = self.value(state);
+        let moves = state.get_state().get_moves().unwrap_or_default();
+        moves
+            .into_iter()
+            .filter(|m| Perspective::after_move(m).convert(self.value(&state.make_move(m))) == best)
+            .collect()
+    }
+}
+
+impl Solver<TicTacToe3> for TicTacToe3Solver {
+    fn value(&mut self, state: &TicTacToe3) -> f32 {
+        TicTacToe3Solver::value(self, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alpha_zero::Game,
+        tictactoe::CellState,
+        tictactoe3::{TicTacToe3, TicTacToe3Move},
+    };
+
+    use super::TicTacToe3Solver;
+
+    #[test]
+    fn solved_values() {
+        let mut solver = TicTacToe3Solver::new();
+        assert_eq!(solver.value(&TicTacToe3::new()), 0.5);
+
+        // X to move with two in a row
+        let board = TicTacToe3::new()
+            .set(0, CellState::X)
+            .set(1, CellState::X)
+            .set(3, CellState::O)
+            .set(4, CellState::O);
+        assert_eq!(solver.value(&board), 1.0);
+        assert_eq!(solver.optimal_moves(&board), [TicTacToe3Move(2)]);
+
+        // Corner opening must not be answered on an edge
+        let board = TicTacToe3::new().make_move(&TicTacToe3Move(0));
+        assert_eq!(solver.value(&board.make_move(&TicTacToe3Move(1))), 1.0);
+        assert_eq!(solver.optimal_moves(&board), [TicTacToe3Move(4)]);
+    }
+}
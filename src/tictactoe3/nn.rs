@@ -0,0 +1,42 @@
+use tch::{
+    nn::{self, Linear, Module},
+    Tensor,
+};
+
+use crate::alpha_zero::{AlphaZeroNet, NetOutput};
+
+// Tiny MLP, 3x3 tic-tac-toe doesn't need anything convolutional
+pub struct TicTacToe3Net {
+    fc1: Linear,
+    fc2: Linear,
+    fc_value: Linear,
+    fc_policy: Linear,
+}
+
+impl TicTacToe3Net {
+    pub fn new(path: &nn::Path) -> Self {
+        Self {
+            fc1: nn::linear(path / "fc1", 2 * 9, 64, Default::default()),
+            fc2: nn::linear(path / "fc2", 64, 64, Default::default()),
+            fc_value: nn::linear(path / "fc_value", 64, 1, Default::default()),
+            fc_policy: nn::linear(path / "fc_policy", 64, 9, Default::default()),
+        }
+    }
+}
+
+impl AlphaZeroNet for TicTacToe3Net {
+    fn forward_t(&self, xs: &Tensor, _is_training: bool) -> NetOutput {
+        let batch = xs.size()[0];
+        let mid = self.fc1.forward(&xs.view([batch, -1])).relu();
+        let mid = self.fc2.forward(&mid).relu();
+
+        let val = self.fc_value.forward(&mid).view([batch]).sigmoid();
+        let policy = self
+            .fc_policy
+            .forward(&mid)
+            .log_softmax(1, None)
+            .view([batch, 3, 3]);
+
+        NetOutput::new(val, policy)
+    }
+}
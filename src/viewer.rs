@@ -0,0 +1,397 @@
+use std::io;
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    symbols::Marker,
+    text::Line,
+    widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph},
+    DefaultTerminal, Frame,
+};
+use tch::Kind;
+
+use crate::{
+    alpha_zero::{Game, Notation, Perspective, PortableGame},
+    selfplay::StoredPositions,
+};
+
+// Most likely moves listed beside the board
+const POLICY_ENTRIES: usize = 12;
+// Of the policy's bars, in characters
+const BAR_WIDTH: usize = 16;
+// Rows of the value chart under the board
+const CHART_HEIGHT: u16 = 10;
+
+// A position as the viewer shows it, whatever the game
+#[derive(Clone, Debug, PartialEq)]
+pub struct ViewerPosition {
+    // As text, like `GameSpec::text`
+    pub board: String,
+    // The move made from the position, `None` for the last one
+    pub played: Option<String>,
+    // Labeled probabilities of the moves, the most likely first. Empty if none was stored.
+    pub policy: Vec<(String, f32)>,
+    // Of the player to move: the search's, if it was stored, and the game's outcome
+    pub value: Option<f32>,
+    pub outcome: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ViewerGame {
+    pub title: String,
+    pub positions: Vec<ViewerPosition>,
+}
+
+// The games of a match or an arena, with the position after the last move. Boards are drawn
+// with `text` if the game has it, as the moves so far otherwise. Only the played move is
+// known of the policies.
+pub fn portable_games<TGame: Game + Clone>(
+    start: &TGame,
+    notation: Notation<TGame>,
+    text: Option<fn(&TGame) -> String>,
+    games: &[PortableGame<TGame::Move>],
+) -> Vec<ViewerGame> {
+    games
+        .iter()
+        .map(|game| {
+            let mut state = start.clone();
+            let mut player = Perspective::Same;
+            let mut moves = vec![];
+            let mut positions = vec![];
+            let board = |state: &TGame, moves: &[String]| match text {
+                Some(text) => text(state),
+                None => moves.join(" "),
+            };
+            for portable in &game.moves {
+                let played = (notation.format)(&state, &portable.r#move);
+                positions.push(ViewerPosition {
+                    board: board(&state, &moves),
+                    played: Some(played.clone()),
+                    policy: vec![(played.clone(), 1.0)],
+                    value: portable.value,
+                    outcome: player.convert(game.result),
+                });
+                moves.push(played);
+                player = player.then(Perspective::after_move(&portable.r#move));
+                state = state.make_move(&portable.r#move);
+            }
+            positions.push(ViewerPosition {
+                board: board(&state, &moves),
+                played: None,
+                policy: vec![],
+                value: None,
+                outcome: player.convert(game.result),
+            });
+            ViewerGame {
+                title: format!("{} - {}", game.first, game.second),
+                positions,
+            }
+        })
+        .collect()
+}
+
+// The games of a self-play shard. Only their encoding for the network is stored, so boards
+// are its planes, `#` marking the set inputs, and moves are the indices of the policy.
+pub fn shard_games(shard: &StoredPositions) -> anyhow::Result<Vec<ViewerGame>> {
+    let values =
+        |tensor: &tch::Tensor| Vec::<f32>::try_from(tensor.flatten(0, -1).to_kind(Kind::Float));
+    let (outcomes, root_q) = (values(&shard.values)?, values(&shard.root_q)?);
+    let lengths = Vec::<i64>::try_from(&shard.game_lengths)?;
+    let size = shard.states.size();
+    // Every plane is drawn as a grid, inputs that aren't planes as a single row
+    let (rows, columns) = match size.len() {
+        4 => (size[2] as usize, size[3] as usize),
+        _ => (1, size[1..].iter().product::<i64>() as usize),
+    };
+
+    let mut games = vec![];
+    let mut first = 0;
+    for (game, length) in lengths.into_iter().enumerate() {
+        let mut positions = vec![];
+        for i in first..first + length as usize {
+            let inputs = values(&shard.states.get(i as i64))?;
+            let planes = inputs.chunks(rows * columns).collect::<Vec<_>>();
+            let board = (0..rows)
+                .map(|row| {
+                    planes
+                        .iter()
+                        .map(|plane| {
+                            plane[row * columns..(row + 1) * columns]
+                                .iter()
+                                .map(|&x| if x >= 0.5 { '#' } else { '.' })
+                                .collect::<String>()
+                        })
+                        .collect::<Vec<_>>()
+                        .join("  ")
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let mut policy = values(&shard.policies.get(i as i64))?
+                .into_iter()
+                .enumerate()
+                .filter(|&(_, p)| p > 0.0)
+                .map(|(action, p)| (format!("#{action}"), p))
+                .collect::<Vec<_>>();
+            policy.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+            positions.push(ViewerPosition {
+                board,
+                played: None,
+                policy,
+                value: Some(root_q[i]),
+                outcome: outcomes[i],
+            });
+        }
+        first += length as usize;
+        games.push(ViewerGame {
+            title: format!("Self-play game {}", game + 1),
+            positions,
+        });
+    }
+    Ok(games)
+}
+
+// Steps through games in the terminal, with the policy beside the board and the values of
+// the game under it, each of which can be hidden
+pub struct Viewer {
+    games: Vec<ViewerGame>,
+    game: usize,
+    position: usize,
+    show_policy: bool,
+    show_value: bool,
+}
+
+impl Viewer {
+    pub fn new(games: Vec<ViewerGame>) -> Self {
+        Self {
+            games,
+            game: 0,
+            position: 0,
+            show_policy: true,
+            show_value: true,
+        }
+    }
+
+    // Until the viewer is closed with `q` or escape
+    pub fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // Returns whether the viewer stays open
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        let last = self
+            .games
+            .get(self.game)
+            .map_or(0, |game| game.positions.len().saturating_sub(1));
+        match key {
+            KeyCode::Right | KeyCode::Char('l' | ' ') => {
+                self.position = (self.position + 1).min(last)
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.position = self.position.saturating_sub(1),
+            KeyCode::Home | KeyCode::Char('g') => self.position = 0,
+            KeyCode::End | KeyCode::Char('G') => self.position = last,
+            KeyCode::Down | KeyCode::PageDown | KeyCode::Char('j') => {
+                self.game = (self.game + 1).min(self.games.len().saturating_sub(1));
+                self.position = 0;
+            }
+            KeyCode::Up | KeyCode::PageUp | KeyCode::Char('k') => {
+                self.game = self.game.saturating_sub(1);
+                self.position = 0;
+            }
+            KeyCode::Char('p') => self.show_policy = !self.show_policy,
+            KeyCode::Char('v') => self.show_value = !self.show_value,
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            _ => {}
+        }
+        true
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let [header, body, help] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        frame.render_widget(
+            Line::from("←/→ move, Home/End first and last, ↑/↓ game, p policy, v value, q quit")
+                .style(Style::new().fg(Color::DarkGray)),
+            help,
+        );
+        let Some(game) = self.games.get(self.game) else {
+            frame.render_widget(Line::from("No games"), header);
+            return;
+        };
+        let position = &game.positions[self.position];
+        let played = match &position.played {
+            Some(played) => format!(", {played} played"),
+            None => String::new(),
+        };
+        frame.render_widget(
+            Line::from(format!(
+                "Game {} of {}: {}, position {} of {}{played}",
+                self.game + 1,
+                self.games.len(),
+                game.title,
+                self.position,
+                game.positions.len() - 1
+            )),
+            header,
+        );
+
+        let [main, chart] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(if self.show_value { CHART_HEIGHT } else { 0 }),
+        ])
+        .areas(body);
+        let [board, policy] = Layout::horizontal([
+            Constraint::Min(0),
+            Constraint::Length(if self.show_policy {
+                BAR_WIDTH as u16 + 20
+            } else {
+                0
+            }),
+        ])
+        .areas(main);
+        let mut text = position.board.lines().map(Line::from).collect::<Vec<_>>();
+        if self.show_value {
+            let value = position
+                .value
+                .map_or("none".to_owned(), |value| format!("{value:.3}"));
+            text.push(Line::default());
+            text.push(Line::from(format!(
+                "Search's value {value}, outcome {:.1}",
+                position.outcome
+            )));
+        }
+        frame.render_widget(
+            Paragraph::new(text).block(Block::bordered().title("Board")),
+            board,
+        );
+        if self.show_policy {
+            self.draw_policy(frame, position, policy);
+        }
+        if self.show_value {
+            self.draw_values(frame, game, chart);
+        }
+    }
+
+    fn draw_policy(&self, frame: &mut Frame, position: &ViewerPosition, area: Rect) {
+        let max = position
+            .policy
+            .first()
+            .map_or(f32::MIN_POSITIVE, |&(_, p)| p.max(f32::MIN_POSITIVE));
+        let lines = match position.policy.is_empty() {
+            true => vec![Line::from("None stored")],
+            false => position
+                .policy
+                .iter()
+                .take(POLICY_ENTRIES)
+                .map(|(label, p)| {
+                    let bar = "█".repeat((p / max * BAR_WIDTH as f32).round() as usize);
+                    Line::from(format!("{label:>6} {bar:<BAR_WIDTH$} {:>5.1}%", 100.0 * p))
+                })
+                .collect(),
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Policy")),
+            area,
+        );
+    }
+
+    // The search's values of the player to move through the game, the current position
+    // marked
+    fn draw_values(&self, frame: &mut Frame, game: &ViewerGame, area: Rect) {
+        let values = game
+            .positions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, position)| Some((i as f64, position.value? as f64)))
+            .collect::<Vec<_>>();
+        let marker = [(self.position as f64, 0.0), (self.position as f64, 1.0)];
+        let last = (game.positions.len() - 1).max(1) as f64;
+        let chart = Chart::new(vec![
+            Dataset::default()
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::new().fg(Color::Cyan))
+                .data(&values),
+            Dataset::default()
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::new().fg(Color::Red))
+                .data(&marker),
+        ])
+        .block(Block::bordered().title("Value for the player to move"))
+        .x_axis(Axis::default().bounds([0.0, last]))
+        .y_axis(Axis::default().bounds([0.0, 1.0]).labels(["0", "0.5", "1"]));
+        frame.render_widget(chart, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{backend::TestBackend, crossterm::event::KeyCode, Terminal};
+
+    use crate::{
+        alpha_zero::{Notation, PortableGame},
+        tictactoe3::TicTacToe3,
+    };
+
+    use super::{portable_games, Viewer};
+
+    fn screen(viewer: &Viewer) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+        terminal.draw(|frame| viewer.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn steps_through_games() {
+        let start = TicTacToe3::new();
+        let text = "[First \"gen01\"]\n[Second \"random\"]\n[Result \"1-0\"]\n\n\
+                    0 {0.700} 3 1 {0.900} 4 2 1-0\n\n[First \"random\"]\n[Second \"gen01\"]\n\
+                    [Result \"1/2-1/2\"]\n\n4 0 1/2-1/2\n";
+        let games = PortableGame::parse_all(&start, Notation::of(), text).unwrap();
+        let games = portable_games(&start, Notation::of(), None, &games);
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].positions.len(), 6);
+        // The second player lost, and moved with the outcome at 0
+        assert_eq!(games[0].positions[1].outcome, 0.0);
+        assert_eq!(games[0].positions[2].value, Some(0.9));
+        assert_eq!(games[0].positions[5].board, "0 3 1 4 2");
+
+        let mut viewer = Viewer::new(games);
+        let first = screen(&viewer);
+        assert!(first.contains("Game 1 of 2: gen01 - random, position 0 of 5, 0 played"));
+        assert!(first.contains("Search's value 0.700, outcome 1.0"));
+        assert!(first.contains("Policy") && first.contains("100.0%"));
+
+        for key in [KeyCode::Right, KeyCode::Right, KeyCode::End, KeyCode::Right] {
+            assert!(viewer.handle_key(key));
+        }
+        let last = screen(&viewer);
+        assert!(last.contains("position 5 of 5") && last.contains("None stored"));
+
+        viewer.handle_key(KeyCode::Down);
+        viewer.handle_key(KeyCode::Char('p'));
+        viewer.handle_key(KeyCode::Char('v'));
+        let second = screen(&viewer);
+        assert!(second.contains("Game 2 of 2: random - gen01, position 0 of 2, 4 played"));
+        assert!(!second.contains("Policy") && !second.contains("Search's value"));
+        assert!(!viewer.handle_key(KeyCode::Char('q')));
+    }
+}
@@ -0,0 +1,155 @@
+use std::{cell::RefCell, rc::Rc};
+
+use js_sys::{Array, Float32Array, Function, Promise};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+
+use crate::{
+    alpha_zero::{
+        argmax, BackendEvaluator, Game, InferenceBackend, InputEncoding, MonteCarloTree,
+        MoveNotation, TerminationState,
+    },
+    tictactoe::{BoardState, TicTacToeAlphaZeroAdapter},
+};
+
+// The registry's `gomoku`, whose nets take the adapter's planes without history
+type Adapter = TicTacToeAlphaZeroAdapter;
+
+// A net run by JavaScript: `infer` takes the planes as a Float32Array and returns
+// `[value, logPolicy]` or a promise of it, like a session of ONNX Runtime Web on the net of
+// `export torchscript` converted by `torch.onnx.export`. Its failures are the page's bugs, and
+// panic like a failing net would.
+pub struct JsBackend(Function);
+
+impl InferenceBackend for JsBackend {
+    async fn infer(&mut self, input: Vec<f32>) -> (f32, Vec<f32>) {
+        let input = Float32Array::from(input.as_slice());
+        let output = self
+            .0
+            .call1(&JsValue::NULL, &input)
+            .expect("infer threw an exception");
+        let output = JsFuture::from(Promise::resolve(&output))
+            .await
+            .expect("infer's promise was rejected");
+        let output = Array::from(&output);
+        let value = output.get(0).as_f64().expect("infer gave no value") as f32;
+        (value, Float32Array::new(&output.get(1)).to_vec())
+    }
+}
+
+struct Engine {
+    state: BoardState,
+    // Taken by the search while it runs
+    evaluator: Option<BackendEvaluator<BoardState, JsBackend, Adapter>>,
+    simulations: usize,
+    c_puct: f32,
+}
+
+impl Engine {
+    fn check_idle(&self) -> Result<(), JsError> {
+        match self.evaluator {
+            Some(_) => Ok(()),
+            None => Err(JsError::new("The engine is thinking")),
+        }
+    }
+}
+
+// Gomoku on the 19×19 board, played in the browser against a net evaluated by `infer`, see
+// `JsBackend`. Moves are `<row>,<column>` as everywhere else.
+#[wasm_bindgen]
+pub struct GomokuEngine(Rc<RefCell<Engine>>);
+
+#[wasm_bindgen]
+impl GomokuEngine {
+    #[wasm_bindgen(constructor)]
+    pub fn new(infer: Function, simulations: usize, c_puct: f32) -> Self {
+        Self(Rc::new(RefCell::new(Engine {
+            state: BoardState::new(),
+            evaluator: Some(BackendEvaluator::new(JsBackend(infer))),
+            simulations,
+            c_puct,
+        })))
+    }
+
+    // Of the net `infer` takes, for checking the page's model against it
+    #[wasm_bindgen(js_name = inputShape)]
+    pub fn input_shape() -> Vec<u32> {
+        let shape = <Adapter as InputEncoding<BoardState>>::input_shape();
+        shape.into_iter().map(|size| size as u32).collect()
+    }
+
+    #[wasm_bindgen(js_name = newGame)]
+    pub fn new_game(&self) -> Result<(), JsError> {
+        let mut engine = self.0.borrow_mut();
+        engine.check_idle()?;
+        engine.state = BoardState::new();
+        Ok(())
+    }
+
+    // Empty once the game is over
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> Array {
+        let engine = self.0.borrow();
+        let moves = engine.state.get_state().get_moves().unwrap_or_default();
+        moves
+            .iter()
+            .map(|m| JsValue::from_str(&engine.state.format_move(m)))
+            .collect()
+    }
+
+    pub fn play(&self, text: &str) -> Result<(), JsError> {
+        let mut engine = self.0.borrow_mut();
+        engine.check_idle()?;
+        let m = engine
+            .state
+            .parse_move(text)
+            .map_err(|e| JsError::new(&format!("{e:#}")))?;
+        if !engine.state.is_legal(&m) {
+            return Err(JsError::new(&format!("{text} isn't legal")));
+        }
+        engine.state = engine.state.make_move(&m);
+        Ok(())
+    }
+
+    // Searches the position and plays the best move, which the promise resolves to
+    pub fn think(&self) -> Promise {
+        let engine = self.0.clone();
+        future_to_promise(async move {
+            let (state, evaluator, simulations, c_puct) = {
+                let mut engine = engine.borrow_mut();
+                if engine.state.get_state().get_moves().is_none() {
+                    return Err(JsValue::from_str("The game is over"));
+                }
+                let evaluator = engine
+                    .evaluator
+                    .take()
+                    .ok_or_else(|| JsValue::from_str("The engine is thinking"))?;
+                let state = engine.state.clone();
+                (state, evaluator, engine.simulations, engine.c_puct)
+            };
+            let moves = state.get_state().get_moves().unwrap();
+            let mut tree = MonteCarloTree::new(state.clone(), evaluator);
+            tree.do_simulations(simulations, c_puct).await;
+            let best = &moves[argmax(&tree.get_policy())];
+            let mut engine = engine.borrow_mut();
+            engine.evaluator = Some(tree.into_evaluator());
+            engine.state = state.make_move(best);
+            Ok(JsValue::from_str(&state.format_move(best)))
+        })
+    }
+
+    // "first", "second" or "draw" once the game is over
+    pub fn result(&self) -> Option<String> {
+        let state = &self.0.borrow().state;
+        let TerminationState::Terminal(value) = state.get_state() else {
+            return None;
+        };
+        // Of the player to move
+        let result = match value {
+            0.5 => "draw",
+            _ if (value > 0.5) == state.is_first_player_to_move() => "first",
+            _ => "second",
+        };
+        Some(result.to_owned())
+    }
+}